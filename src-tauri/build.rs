@@ -1,3 +1,7 @@
 fn main() {
-    tauri_build::build()
+    // The standalone `antigravity-server` binary is built with `--no-default-features`
+    // and must not require the Tauri toolchain (icons, tauri.conf.json, WebKit).
+    if std::env::var_os("CARGO_FEATURE_DESKTOP").is_some() {
+        tauri_build::build()
+    }
 }
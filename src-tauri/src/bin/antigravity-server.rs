@@ -0,0 +1,7 @@
+//! Standalone headless server: proxy + Web UI + management CLI, without Tauri.
+//!
+//!   cargo build --release --bin antigravity-server --no-default-features
+
+fn main() {
+    antigravity_tools_lib::cli::main()
+}
@@ -0,0 +1,419 @@
+//! `antigravity-server` 命令行入口
+//!
+//! 不依赖 Tauri，可在服务器 / 容器中直接运行反代服务并管理账号、用户令牌与配置。
+//! 数据目录与桌面版相同 (`ABV_DATA_DIR` 或 `~/.antigravity_tools`)。
+
+use clap::{Args, Parser, Subcommand};
+use serde_json::Value;
+use std::path::PathBuf;
+
+use crate::modules::backup::{BackupBundle, RestoreOptions};
+use crate::modules::integration::SystemManager;
+use crate::modules::{self, account_service::AccountService, logger, user_token_db};
+
+#[derive(Debug, Parser)]
+#[command(name = "antigravity-server", version, about = "Antigravity Tools headless proxy server")]
+struct Cli {
+    /// 数据目录 (默认 ~/.antigravity_tools)
+    #[arg(long, global = true, env = "ABV_DATA_DIR")]
+    data_dir: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// 启动反代服务 (含 Web 管理界面)，直到收到 Ctrl-C
+    Serve {
+        /// TOML/JSON 配置覆盖文件 (优先级低于 ABV_* 环境变量)
+        #[arg(long, env = "ABV_CONFIG_FILE")]
+        config: Option<PathBuf>,
+    },
+    /// 账号管理
+    #[command(subcommand)]
+    Accounts(AccountsCommand),
+    /// 用户令牌管理
+    #[command(subcommand)]
+    Tokens(TokensCommand),
+    /// 读取或修改 gui_config.json
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// 查看 Token 用量统计
+    Stats {
+        /// 统计最近 N 小时
+        #[arg(long, default_value_t = 24)]
+        hours: i64,
+        /// 以 JSON 输出
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum AccountsCommand {
+    /// 列出所有账号
+    List {
+        #[arg(long)]
+        json: bool,
+    },
+    /// 通过 refresh_token 添加账号
+    Add {
+        #[arg(required = true)]
+        refresh_tokens: Vec<String>,
+    },
+    /// 删除账号 (ID 或邮箱)
+    Remove {
+        #[arg(required = true)]
+        accounts: Vec<String>,
+    },
+    /// 从文件导入账号：加密备份包，或 refresh_token 的 JSON 列表
+    Import(ImportArgs),
+}
+
+#[derive(Debug, Args)]
+struct ImportArgs {
+    file: PathBuf,
+    /// 备份包口令
+    #[arg(long, env = "ABV_BACKUP_PASSPHRASE", hide_env_values = true)]
+    passphrase: Option<String>,
+    /// 同时恢复备份包中的用户令牌
+    #[arg(long)]
+    with_user_tokens: bool,
+    /// 同时合并备份包中的应用配置 (保留本机端口与 API Key)
+    #[arg(long)]
+    with_config: bool,
+}
+
+#[derive(Debug, Subcommand)]
+enum TokensCommand {
+    /// 列出用户令牌
+    List {
+        #[arg(long)]
+        json: bool,
+    },
+    /// 创建用户令牌
+    Create {
+        username: String,
+        /// day / week / month / never
+        #[arg(long, default_value = "never")]
+        expires: String,
+        #[arg(long)]
+        description: Option<String>,
+        /// 最大绑定 IP 数 (0 = 不限)
+        #[arg(long, default_value_t = 0)]
+        max_ips: i32,
+    },
+    /// 吊销 (删除) 用户令牌 (ID 或令牌值)
+    Revoke { token: String },
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigCommand {
+    /// 读取配置项，路径以点分隔 (如 proxy.port)；省略时输出完整配置
+    Get { path: Option<String> },
+    /// 修改配置项；值按 JSON 解析，解析失败时视为字符串
+    Set { path: String, value: String },
+    /// 校验分层后的最终配置 (gui_config.json + 配置文件 + ABV_* 环境变量)
+    Validate {
+        #[arg(long, env = "ABV_CONFIG_FILE")]
+        config: Option<PathBuf>,
+    },
+}
+
+/// `antigravity-server` 二进制入口
+pub fn main() {
+    let cli = Cli::parse();
+    if let Some(dir) = &cli.data_dir {
+        std::env::set_var("ABV_DATA_DIR", dir);
+    }
+
+    if let Err(e) = run(cli.command) {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(command: Command) -> Result<(), String> {
+    match command {
+        Command::Serve { config } => {
+            // Increase file descriptor limit (macOS only)
+            #[cfg(target_os = "macos")]
+            crate::increase_nofile_limit();
+
+            logger::init_logger();
+            crate::headless::init_storage();
+            crate::headless::run_blocking(config.as_deref());
+            Ok(())
+        }
+        Command::Accounts(cmd) => {
+            init_account_storage()?;
+            run_accounts(cmd)
+        }
+        Command::Tokens(cmd) => {
+            user_token_db::init_db()?;
+            run_tokens(cmd)
+        }
+        Command::Config(cmd) => run_config(cmd),
+        Command::Stats { hours, json } => {
+            modules::token_stats::init_db()?;
+            let summary = modules::token_stats::get_summary_stats(hours)?;
+            let by_account = modules::token_stats::get_account_stats(hours)?;
+            if json {
+                print_json(&serde_json::json!({ "summary": summary, "accounts": by_account }))
+            } else {
+                println!("Last {}h: {} requests, {} input / {} output tokens ({} total), {} account(s)",
+                    hours,
+                    summary.total_requests,
+                    summary.total_input_tokens,
+                    summary.total_output_tokens,
+                    summary.total_tokens,
+                    summary.unique_accounts
+                );
+                for stat in by_account {
+                    println!("  {:<40} {:>8} req {:>12} tokens", stat.account_email, stat.request_count, stat.total_tokens);
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// 账号文件可能已加密，需要先通过环境变量解锁
+fn init_account_storage() -> Result<(), String> {
+    modules::account_crypto::init_from_env()?;
+    if modules::account_crypto::is_enabled() && !modules::account_crypto::is_unlocked() {
+        return Err("account files are encrypted; set ABV_ACCOUNT_PASSPHRASE or ABV_ACCOUNT_KEYFILE".to_string());
+    }
+    Ok(())
+}
+
+fn block_on<F: std::future::Future>(future: F) -> Result<F::Output, String> {
+    let rt = tokio::runtime::Runtime::new().map_err(|e| format!("failed_to_create_runtime: {}", e))?;
+    Ok(rt.block_on(future))
+}
+
+fn run_accounts(cmd: AccountsCommand) -> Result<(), String> {
+    let service = AccountService::new(SystemManager::Headless);
+    match cmd {
+        AccountsCommand::List { json } => {
+            let accounts = service.list_accounts()?;
+            if json {
+                return print_json(&accounts);
+            }
+            let current = service.get_current_id()?.unwrap_or_default();
+            for acc in accounts {
+                let status = if acc.disabled {
+                    "disabled"
+                } else if acc.proxy_disabled {
+                    "proxy-disabled"
+                } else {
+                    "active"
+                };
+                let marker = if acc.id == current { "*" } else { " " };
+                println!("{} {}  {:<40} {}", marker, acc.id, acc.email, status);
+            }
+            Ok(())
+        }
+        AccountsCommand::Add { refresh_tokens } => add_refresh_tokens(&service, &refresh_tokens),
+        AccountsCommand::Remove { accounts } => {
+            for key in accounts {
+                let id = resolve_account_id(&key)?;
+                service.delete_account(&id)?;
+                println!("removed {}", key);
+            }
+            Ok(())
+        }
+        AccountsCommand::Import(args) => import_accounts(&service, args),
+    }
+}
+
+fn resolve_account_id(key: &str) -> Result<String, String> {
+    if key.contains('@') {
+        return modules::account::find_account_id_by_email(key)
+            .ok_or_else(|| format!("account not found: {}", key));
+    }
+    modules::account::load_account(key).map(|acc| acc.id)
+}
+
+fn add_refresh_tokens(service: &AccountService, refresh_tokens: &[String]) -> Result<(), String> {
+    let mut failed = 0;
+    for token in refresh_tokens {
+        match block_on(service.add_account(token.trim()))? {
+            Ok(acc) => println!("added {} ({})", acc.email, acc.id),
+            Err(e) => {
+                failed += 1;
+                eprintln!("failed to add account: {}", e);
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(format!("{} of {} account(s) failed", failed, refresh_tokens.len()));
+    }
+    Ok(())
+}
+
+fn import_accounts(service: &AccountService, args: ImportArgs) -> Result<(), String> {
+    let content = std::fs::read_to_string(&args.file)
+        .map_err(|e| format!("failed to read {}: {}", args.file.display(), e))?;
+    let value: Value = serde_json::from_str(&content).map_err(|e| format!("invalid JSON: {}", e))?;
+
+    // 加密备份包
+    if value.get("manifest").is_some() && value.get("payload").is_some() {
+        let bundle: BackupBundle = serde_json::from_value(value)
+            .map_err(|e| format!("invalid backup bundle: {}", e))?;
+        let passphrase = args
+            .passphrase
+            .ok_or("backup bundle requires --passphrase or ABV_BACKUP_PASSPHRASE")?;
+        if args.with_user_tokens {
+            user_token_db::init_db()?;
+        }
+        let options = RestoreOptions {
+            restore_user_tokens: args.with_user_tokens,
+            restore_config: args.with_config,
+        };
+        let report = modules::backup::restore_backup(&bundle, &passphrase, &options)?;
+        print_json(&report)?;
+        return if report.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("{} item(s) failed to restore", report.errors.len()))
+        };
+    }
+
+    // ["1//...", ...] 或 [{"email": "...", "refresh_token": "..."}, ...] (与导出格式一致)
+    let items = value.as_array().ok_or("expected a backup bundle or a JSON array")?;
+    let tokens: Vec<String> = items
+        .iter()
+        .filter_map(|item| match item {
+            Value::String(s) => Some(s.clone()),
+            Value::Object(obj) => obj.get("refresh_token").and_then(|v| v.as_str()).map(str::to_string),
+            _ => None,
+        })
+        .filter(|t| !t.trim().is_empty())
+        .collect();
+    if tokens.is_empty() {
+        return Err("no refresh tokens found in file".to_string());
+    }
+    add_refresh_tokens(service, &tokens)
+}
+
+fn run_tokens(cmd: TokensCommand) -> Result<(), String> {
+    match cmd {
+        TokensCommand::List { json } => {
+            let tokens = user_token_db::list_tokens()?;
+            if json {
+                return print_json(&tokens);
+            }
+            for t in tokens {
+                println!(
+                    "{}  {:<20} {:<8} {:>8} req  {}",
+                    t.id,
+                    t.username,
+                    if t.enabled { "enabled" } else { "disabled" },
+                    t.total_requests,
+                    t.expires_type
+                );
+            }
+            Ok(())
+        }
+        TokensCommand::Create { username, expires, description, max_ips } => {
+            if !matches!(expires.as_str(), "day" | "week" | "month" | "never") {
+                return Err(format!("invalid --expires: {} (day/week/month/never)", expires));
+            }
+            let token = user_token_db::create_token(username, expires, description, max_ips, None, None, None)?;
+            println!("{}", token.token);
+            eprintln!("created token {} for {}", token.id, token.username);
+            Ok(())
+        }
+        TokensCommand::Revoke { token } => {
+            let found = match user_token_db::get_token_by_id(&token)? {
+                Some(t) => Some(t),
+                None => user_token_db::get_token_by_value(&token)?,
+            };
+            let found = found.ok_or_else(|| format!("user token not found: {}", token))?;
+            user_token_db::delete_token(&found.id)?;
+            println!("revoked {} ({})", found.id, found.username);
+            Ok(())
+        }
+    }
+}
+
+fn run_config(cmd: ConfigCommand) -> Result<(), String> {
+    let config = modules::config::load_app_config()?;
+    let mut root = serde_json::to_value(&config).map_err(|e| e.to_string())?;
+    match cmd {
+        ConfigCommand::Get { path } => {
+            let value = match path.as_deref() {
+                Some(p) => json_path_mut(&mut root, p)?.clone(),
+                None => root,
+            };
+            match value {
+                Value::String(s) => println!("{}", s),
+                other => print_json(&other)?,
+            }
+            Ok(())
+        }
+        ConfigCommand::Set { path, value } => {
+            let parsed = serde_json::from_str(&value).unwrap_or(Value::String(value));
+            *json_path_mut(&mut root, &path)? = parsed;
+            let updated: crate::models::AppConfig = serde_json::from_value(root)
+                .map_err(|e| format!("invalid value for {}: {}", path, e))?;
+            let errors = modules::config_loader::validate_app_config(&updated);
+            if !errors.is_empty() {
+                return Err(errors.join("; "));
+            }
+            modules::config::save_app_config(&updated)?;
+            println!("{} updated (restart the server to apply)", path);
+            Ok(())
+        }
+        ConfigCommand::Validate { config } => {
+            let layered = modules::config_loader::load_layered_config(config.as_deref())?;
+            println!("configuration is valid");
+            for path in layered.overridden {
+                println!("  overridden: {}", path);
+            }
+            Ok(())
+        }
+    }
+}
+
+/// 按点分隔路径定位已存在的配置项 (不允许新建未知字段，避免拼写错误被静默忽略)
+fn json_path_mut<'a>(root: &'a mut Value, path: &str) -> Result<&'a mut Value, String> {
+    let mut current = root;
+    for segment in path.split('.') {
+        current = match current {
+            Value::Object(map) => map.get_mut(segment),
+            Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get_mut(i)),
+            _ => None,
+        }
+        .ok_or_else(|| format!("unknown config key: {}", path))?;
+    }
+    Ok(current)
+}
+
+fn print_json<T: serde::Serialize>(value: &T) -> Result<(), String> {
+    let out = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    println!("{}", out);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli_definition_is_valid() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_json_path_mut_rejects_unknown_keys() {
+        let mut root = serde_json::json!({ "proxy": { "port": 8045, "upstream": [1, 2] } });
+        *json_path_mut(&mut root, "proxy.port").unwrap() = serde_json::json!(9000);
+        assert_eq!(root["proxy"]["port"], 9000);
+        assert_eq!(*json_path_mut(&mut root, "proxy.upstream.1").unwrap(), 2);
+        assert!(json_path_mut(&mut root, "proxy.prot").is_err());
+    }
+}
@@ -1,7 +1,7 @@
 // Autostart 命令
 use tauri_plugin_autostart::ManagerExt;
 
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn toggle_auto_launch(
     app: tauri::AppHandle,
     enable: bool,
@@ -32,7 +32,7 @@ pub async fn toggle_auto_launch(
     Ok(())
 }
 
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn is_auto_launch_enabled(app: tauri::AppHandle) -> Result<bool, String> {
     let manager = app.autolaunch();
     manager.is_enabled().map_err(|e| e.to_string())
@@ -1,3 +1,4 @@
+#[cfg(feature = "desktop")]
 use tauri::State;
 use crate::modules::cloudflared::{CloudflaredConfig, CloudflaredManager, CloudflaredStatus};
 use std::sync::Arc;
@@ -28,7 +29,8 @@ impl CloudflaredState {
 }
 
 /// 检查cloudflared是否已安装
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn cloudflared_check(
     state: State<'_, CloudflaredState>,
 ) -> Result<CloudflaredStatus, String> {
@@ -50,7 +52,8 @@ pub async fn cloudflared_check(
 }
 
 /// 安装cloudflared
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn cloudflared_install(
     state: State<'_, CloudflaredState>,
 ) -> Result<CloudflaredStatus, String> {
@@ -65,7 +68,8 @@ pub async fn cloudflared_install(
 }
 
 /// 启动cloudflared隧道
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn cloudflared_start(
     state: State<'_, CloudflaredState>,
     config: CloudflaredConfig,
@@ -81,7 +85,8 @@ pub async fn cloudflared_start(
 }
 
 /// 停止cloudflared隧道
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn cloudflared_stop(
     state: State<'_, CloudflaredState>,
 ) -> Result<CloudflaredStatus, String> {
@@ -96,7 +101,8 @@ pub async fn cloudflared_stop(
 }
 
 /// 获取cloudflared状态
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn cloudflared_get_status(
     state: State<'_, CloudflaredState>,
 ) -> Result<CloudflaredStatus, String> {
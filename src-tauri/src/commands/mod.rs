@@ -1,11 +1,14 @@
 use crate::models::{Account, AppConfig, QuotaData};
 use crate::modules;
+#[cfg(feature = "desktop")]
 use tauri::{Emitter, Manager};
+#[cfg(feature = "desktop")]
 use tauri_plugin_opener::OpenerExt;
 
 // 导出 proxy 命令
 pub mod proxy;
 // 导出 autostart 命令
+#[cfg(feature = "desktop")]
 pub mod autostart;
 // 导出 cloudflared 命令
 pub mod cloudflared;
@@ -17,13 +20,14 @@ pub mod proxy_pool;
 pub mod user_token;
 
 /// 列出所有账号
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn list_accounts() -> Result<Vec<Account>, String> {
     modules::list_accounts()
 }
 
 /// 添加账号
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn add_account(
     app: tauri::AppHandle,
     _email: String,
@@ -49,7 +53,8 @@ pub async fn add_account(
 
 /// 删除账号
 /// 删除账号
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn delete_account(
     app: tauri::AppHandle,
     proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
@@ -67,7 +72,8 @@ pub async fn delete_account(
 }
 
 /// 批量删除账号
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn delete_accounts(
     app: tauri::AppHandle,
     proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
@@ -93,7 +99,8 @@ pub async fn delete_accounts(
 
 /// 重新排序账号列表
 /// 根据传入的账号ID数组顺序更新账号排列
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn reorder_accounts(
     proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
     account_ids: Vec<String>,
@@ -113,7 +120,8 @@ pub async fn reorder_accounts(
 }
 
 /// 切换账号
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn switch_account(
     app: tauri::AppHandle,
     proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
@@ -135,7 +143,7 @@ pub async fn switch_account(
 }
 
 /// 获取当前账号
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_current_account() -> Result<Option<Account>, String> {
     // println!("🚀 Backend Command: get_current_account called"); // Commented out to reduce noise for frequent calls, relies on frontend log for frequency
     // Actually user WANTS to see it.
@@ -155,12 +163,98 @@ pub async fn get_current_account() -> Result<Option<Account>, String> {
 /// 导出账号（包含 refresh_token）
 use crate::models::AccountExportResponse;
 
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn export_accounts(account_ids: Vec<String>) -> Result<AccountExportResponse, String> {
     modules::account::export_accounts_by_ids(&account_ids)
 }
 
+/// 创建加密备份包 (账号完整记录 + 用户令牌 + 配置)
+#[cfg_attr(feature = "desktop", tauri::command)]
+pub async fn create_backup_bundle(
+    passphrase: String,
+    options: Option<modules::backup::BackupOptions>,
+) -> Result<modules::backup::BackupBundle, String> {
+    tokio::task::spawn_blocking(move || {
+        modules::backup::create_backup(&passphrase, &options.unwrap_or_default())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// 恢复加密备份包 (与本地数据合并)
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
+pub async fn restore_backup_bundle(
+    proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
+    bundle: modules::backup::BackupBundle,
+    passphrase: String,
+    options: Option<modules::backup::RestoreOptions>,
+) -> Result<modules::backup::RestoreReport, String> {
+    let report = tokio::task::spawn_blocking(move || {
+        modules::backup::restore_backup(&bundle, &passphrase, &options.unwrap_or_default())
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let _ = crate::commands::proxy::reload_proxy_accounts(proxy_state).await;
+    Ok(report)
+}
+
+/// 获取账号文件静态加密状态
+#[cfg_attr(feature = "desktop", tauri::command)]
+pub async fn get_account_encryption_status(
+) -> Result<modules::account_crypto::EncryptionStatus, String> {
+    modules::account_crypto::status()
+}
+
+/// 启用账号文件静态加密 (会迁移现有明文账号文件)
+#[cfg_attr(feature = "desktop", tauri::command)]
+pub async fn enable_account_encryption(passphrase: String) -> Result<usize, String> {
+    tokio::task::spawn_blocking(move || modules::account_crypto::enable(&passphrase))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// 使用口令解锁加密的账号文件
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
+pub async fn unlock_account_encryption(
+    proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
+    passphrase: String,
+) -> Result<usize, String> {
+    let migrated =
+        tokio::task::spawn_blocking(move || modules::account_crypto::unlock(&passphrase))
+            .await
+            .map_err(|e| e.to_string())??;
+
+    // 解锁后重新加载账号池 (锁定期间加载的账号会被跳过)
+    let _ = crate::commands::proxy::reload_proxy_accounts(proxy_state).await;
+    Ok(migrated)
+}
+
+/// 更换账号加密口令 (re-key)
+#[cfg_attr(feature = "desktop", tauri::command)]
+pub async fn rekey_account_encryption(
+    current_passphrase: String,
+    new_passphrase: String,
+) -> Result<usize, String> {
+    tokio::task::spawn_blocking(move || {
+        modules::account_crypto::rekey(&current_passphrase, &new_passphrase)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// 关闭账号文件静态加密
+#[cfg_attr(feature = "desktop", tauri::command)]
+pub async fn disable_account_encryption(passphrase: String) -> Result<usize, String> {
+    tokio::task::spawn_blocking(move || modules::account_crypto::disable(&passphrase))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
 /// 内部辅助功能：在添加或导入账号后自动刷新一次额度
+#[cfg(feature = "desktop")]
 async fn internal_refresh_account_quota(
     app: &tauri::AppHandle,
     account: &mut Account,
@@ -184,7 +278,8 @@ async fn internal_refresh_account_quota(
 }
 
 /// 查询账号配额
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn fetch_account_quota(
     app: tauri::AppHandle,
     proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
@@ -217,7 +312,7 @@ pub use modules::account::RefreshStats;
 /// 刷新所有账号配额 (内部实现)
 pub async fn refresh_all_quotas_internal(
     proxy_state: &crate::commands::proxy::ProxyServiceState,
-    app_handle: Option<tauri::AppHandle>,
+    app_handle: Option<modules::integration::AppHandle>,
 ) -> Result<RefreshStats, String> {
     let stats = modules::account::refresh_all_quotas_logic().await?;
 
@@ -229,15 +324,15 @@ pub async fn refresh_all_quotas_internal(
 
     // 发送全局刷新事件给 UI (如果需要)
     if let Some(handle) = app_handle {
-        use tauri::Emitter;
-        let _ = handle.emit("accounts://refreshed", ());
+        modules::integration::emit_event(&handle, "accounts://refreshed", ());
     }
 
     Ok(stats)
 }
 
 /// 刷新所有账号配额 (Tauri Command)
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn refresh_all_quotas(
     proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
     app_handle: tauri::AppHandle,
@@ -245,7 +340,7 @@ pub async fn refresh_all_quotas(
     refresh_all_quotas_internal(&proxy_state, Some(app_handle)).await
 }
 /// 获取设备指纹（当前 storage.json + 账号绑定）
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_device_profiles(
     account_id: String,
 ) -> Result<modules::account::DeviceProfiles, String> {
@@ -253,7 +348,7 @@ pub async fn get_device_profiles(
 }
 
 /// 绑定设备指纹（capture: 采集当前；generate: 生成新指纹），并写入 storage.json
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn bind_device_profile(
     account_id: String,
     mode: String,
@@ -262,13 +357,13 @@ pub async fn bind_device_profile(
 }
 
 /// 预览生成一个指纹（不落盘）
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn preview_generate_profile() -> Result<crate::models::DeviceProfile, String> {
     Ok(crate::modules::device::generate_profile())
 }
 
 /// 使用给定指纹直接绑定
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn bind_device_profile_with_profile(
     account_id: String,
     profile: crate::models::DeviceProfile,
@@ -277,7 +372,7 @@ pub async fn bind_device_profile_with_profile(
 }
 
 /// 将账号已绑定的指纹应用到 storage.json
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn apply_device_profile(
     account_id: String,
 ) -> Result<crate::models::DeviceProfile, String> {
@@ -285,13 +380,13 @@ pub async fn apply_device_profile(
 }
 
 /// 恢复最早的 storage.json 备份（近似“原始”状态）
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn restore_original_device() -> Result<String, String> {
     modules::restore_original_device()
 }
 
 /// 列出指纹版本
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn list_device_versions(
     account_id: String,
 ) -> Result<modules::account::DeviceProfiles, String> {
@@ -299,7 +394,7 @@ pub async fn list_device_versions(
 }
 
 /// 按版本恢复指纹
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn restore_device_version(
     account_id: String,
     version_id: String,
@@ -308,13 +403,14 @@ pub async fn restore_device_version(
 }
 
 /// 删除历史指纹（baseline 不可删）
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn delete_device_version(account_id: String, version_id: String) -> Result<(), String> {
     modules::delete_device_version(&account_id, &version_id)
 }
 
 /// 打开设备存储目录
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn open_device_folder(app: tauri::AppHandle) -> Result<(), String> {
     let dir = modules::device::get_storage_dir()?;
     let dir_str = dir
@@ -327,13 +423,14 @@ pub async fn open_device_folder(app: tauri::AppHandle) -> Result<(), String> {
 }
 
 /// 加载配置
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn load_config() -> Result<AppConfig, String> {
     modules::load_app_config()
 }
 
 /// 保存配置
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn save_config(
     app: tauri::AppHandle,
     proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
@@ -358,6 +455,8 @@ pub async fn save_config(
         instance.axum_server.update_security(&config.proxy).await;
         // 更新 z.ai 配置
         instance.axum_server.update_zai(&config.proxy).await;
+        // [NEW] 更新第三方提供商配置
+        instance.axum_server.update_providers(&config.proxy).await;
         // 更新实验性配置
         instance
             .axum_server
@@ -376,6 +475,22 @@ pub async fn save_config(
         crate::proxy::update_global_system_prompt_config(config.proxy.global_system_prompt.clone());
         // [NEW] 更新全局图像思维模式配置
         crate::proxy::update_image_thinking_mode(config.proxy.image_thinking_mode.clone());
+        // [NEW] 更新模型降级链
+        crate::proxy::update_fallback_chains(config.proxy.fallback_chains.clone());
+        // 更新请求调度配置
+        crate::proxy::update_dispatch_config(config.proxy.dispatch.clone());
+        // 更新路由规则
+        crate::proxy::update_routing_config(config.proxy.routing.clone());
+        // 更新声明式客户端适配器
+        crate::proxy::update_client_adapters(config.proxy.client_adapters.clone());
+        // 更新声明式工具适配器
+        crate::proxy::update_tool_adapters(config.proxy.tool_adapters.clone());
+        // 更新签名缓存持久化配置
+        crate::proxy::update_signature_cache_config(config.proxy.signature_cache.clone());
+        // 更新响应缓存配置
+        crate::proxy::update_response_cache_config(config.proxy.response_cache.clone());
+        crate::proxy::update_context_window_config(config.proxy.context_window.clone());
+        crate::proxy::update_script_hooks(config.proxy.script_hooks.clone());
         // 更新代理池配置
         instance
             .axum_server
@@ -394,7 +509,8 @@ pub async fn save_config(
 
 // --- OAuth 命令 ---
 
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn start_oauth_login(app_handle: tauri::AppHandle, oauth_client_key: Option<String>) -> Result<Account, String> {
     modules::logger::log_info("开始 OAuth 授权流程...");
     let service = modules::account_service::AccountService::new(
@@ -416,7 +532,8 @@ pub async fn start_oauth_login(app_handle: tauri::AppHandle, oauth_client_key: O
 }
 
 /// 完成 OAuth 授权（不自动打开浏览器）
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn complete_oauth_login(app_handle: tauri::AppHandle) -> Result<Account, String> {
     modules::logger::log_info("完成 OAuth 授权流程 (manual)...");
     let service = modules::account_service::AccountService::new(
@@ -438,7 +555,8 @@ pub async fn complete_oauth_login(app_handle: tauri::AppHandle) -> Result<Accoun
 }
 
 /// 预生成 OAuth 授权链接 (不打开浏览器)
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn prepare_oauth_url(app_handle: tauri::AppHandle, oauth_client_key: Option<String>) -> Result<String, String> {
     let service = modules::account_service::AccountService::new(
         crate::modules::integration::SystemManager::Desktop(app_handle.clone()),
@@ -446,37 +564,38 @@ pub async fn prepare_oauth_url(app_handle: tauri::AppHandle, oauth_client_key: O
     service.prepare_oauth_url(oauth_client_key).await
 }
 
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn cancel_oauth_login() -> Result<(), String> {
     modules::oauth_server::cancel_oauth_flow();
     Ok(())
 }
 
 /// 手动提交 OAuth Code (用于 Docker/远程环境无法自动回调时)
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn submit_oauth_code(code: String, state: Option<String>) -> Result<(), String> {
     modules::logger::log_info("收到手动提交 OAuth Code 请求");
     modules::oauth_server::submit_oauth_code(code, state).await
 }
 
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn list_oauth_clients() -> Result<Vec<crate::modules::oauth::OAuthClientDescriptor>, String> {
     crate::modules::oauth::list_oauth_clients()
 }
 
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_active_oauth_client() -> Result<String, String> {
     crate::modules::oauth::get_active_oauth_client_key()
 }
 
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn set_active_oauth_client(client_key: String) -> Result<(), String> {
     crate::modules::oauth::set_active_oauth_client_key(&client_key)
 }
 
 // --- 导入命令 ---
 
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn import_v1_accounts(
     app: tauri::AppHandle,
     proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
@@ -494,7 +613,8 @@ pub async fn import_v1_accounts(
     Ok(accounts)
 }
 
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn import_from_db(
     app: tauri::AppHandle,
     proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
@@ -518,7 +638,8 @@ pub async fn import_from_db(
     Ok(account)
 }
 
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 #[allow(dead_code)]
 pub async fn import_custom_db(
     app: tauri::AppHandle,
@@ -544,7 +665,8 @@ pub async fn import_custom_db(
     Ok(account)
 }
 
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn sync_account_from_db(
     app: tauri::AppHandle,
     proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
@@ -610,34 +732,34 @@ fn validate_path(path: &str) -> Result<(), String> {
 }
 
 /// 保存文本文件 (绕过前端 Scope 限制)
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn save_text_file(path: String, content: String) -> Result<(), String> {
     validate_path(&path)?;
     std::fs::write(&path, content).map_err(|e| format!("写入文件失败: {}", e))
 }
 
 /// 读取文本文件 (绕过前端 Scope 限制)
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn read_text_file(path: String) -> Result<String, String> {
     validate_path(&path)?;
     std::fs::read_to_string(&path).map_err(|e| format!("读取文件失败: {}", e))
 }
 
 /// 清理日志缓存
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn clear_log_cache() -> Result<(), String> {
     modules::logger::clear_logs()
 }
 
 /// 清理 Antigravity 应用缓存
 /// 用于解决登录失败、版本验证错误等问题
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn clear_antigravity_cache() -> Result<modules::cache::ClearResult, String> {
     modules::cache::clear_antigravity_cache(None)
 }
 
 /// 获取 Antigravity 缓存路径列表（用于预览）
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_antigravity_cache_paths() -> Result<Vec<String>, String> {
     Ok(modules::cache::get_existing_cache_paths()
         .into_iter()
@@ -646,7 +768,7 @@ pub async fn get_antigravity_cache_paths() -> Result<Vec<String>, String> {
 }
 
 /// 打开数据目录
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn open_data_folder() -> Result<(), String> {
     let path = modules::account::get_data_dir()?;
 
@@ -680,20 +802,22 @@ pub async fn open_data_folder() -> Result<(), String> {
 }
 
 /// 获取数据目录绝对路径
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_data_dir_path() -> Result<String, String> {
     let path = modules::account::get_data_dir()?;
     Ok(path.to_string_lossy().to_string())
 }
 
 /// 显示主窗口
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn show_main_window(window: tauri::Window) -> Result<(), String> {
     window.show().map_err(|e| e.to_string())
 }
 
 /// 设置窗口主题（用于同步 Windows 标题栏按钮颜色）
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn set_window_theme(window: tauri::Window, theme: String) -> Result<(), String> {
     use tauri::Theme;
 
@@ -707,7 +831,7 @@ pub async fn set_window_theme(window: tauri::Window, theme: String) -> Result<()
 }
 
 /// 获取 Antigravity 可执行文件路径
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_antigravity_path(bypass_config: Option<bool>) -> Result<String, String> {
     // 1. 优先从配置查询 (除非明确要求绕过)
     if bypass_config != Some(true) {
@@ -728,7 +852,7 @@ pub async fn get_antigravity_path(bypass_config: Option<bool>) -> Result<String,
 }
 
 /// 获取 Antigravity 启动参数
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_antigravity_args() -> Result<Vec<String>, String> {
     match crate::modules::process::get_args_from_running_process() {
         Some(args) => Ok(args),
@@ -740,13 +864,13 @@ pub async fn get_antigravity_args() -> Result<Vec<String>, String> {
 pub use crate::modules::update_checker::UpdateInfo;
 
 /// 检测 GitHub releases 更新
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn check_for_updates() -> Result<UpdateInfo, String> {
     modules::logger::log_info("收到前端触发的更新检查请求");
     crate::modules::update_checker::check_for_updates().await
 }
 
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn should_check_updates() -> Result<bool, String> {
     let settings = crate::modules::update_checker::load_update_settings()?;
     Ok(crate::modules::update_checker::should_check_for_updates(
@@ -754,20 +878,20 @@ pub async fn should_check_updates() -> Result<bool, String> {
     ))
 }
 
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn update_last_check_time() -> Result<(), String> {
     crate::modules::update_checker::update_last_check_time()
 }
 
 
 /// 检测是否通过 Homebrew Cask 安装
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn check_homebrew_installation() -> Result<bool, String> {
     Ok(crate::modules::update_checker::is_homebrew_installed())
 }
 
 /// 通过 Homebrew Cask 升级应用
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn brew_upgrade_cask() -> Result<String, String> {
     modules::logger::log_info("收到前端触发的 Homebrew 升级请求");
     crate::modules::update_checker::brew_upgrade_cask().await
@@ -775,14 +899,14 @@ pub async fn brew_upgrade_cask() -> Result<String, String> {
 
 
 /// 获取更新设置
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_update_settings() -> Result<crate::modules::update_checker::UpdateSettings, String>
 {
     crate::modules::update_checker::load_update_settings()
 }
 
 /// 保存更新设置
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn save_update_settings(
     settings: crate::modules::update_checker::UpdateSettings,
 ) -> Result<(), String> {
@@ -790,7 +914,8 @@ pub async fn save_update_settings(
 }
 
 /// 切换账号的反代禁用状态
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn toggle_proxy_status(
     app: tauri::AppHandle,
     proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
@@ -880,19 +1005,19 @@ pub async fn toggle_proxy_status(
 }
 
 /// 预热所有可用账号
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn warm_up_all_accounts() -> Result<String, String> {
     modules::quota::warm_up_all_accounts().await
 }
 
 /// 预热指定账号
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn warm_up_account(account_id: String) -> Result<String, String> {
     modules::quota::warm_up_account(&account_id).await
 }
 
 /// 更新账号自定义标签
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn update_account_label(account_id: String, label: String) -> Result<(), String> {
     // 验证标签长度（按字符数计算，支持中文）
     if label.chars().count() > 15 {
@@ -951,13 +1076,13 @@ pub async fn update_account_label(account_id: String, label: String) -> Result<(
 // ============================================================================
 
 /// 获取 HTTP API 设置
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_http_api_settings() -> Result<crate::modules::http_api::HttpApiSettings, String> {
     crate::modules::http_api::load_settings()
 }
 
 /// 保存 HTTP API 设置
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn save_http_api_settings(
     settings: crate::modules::http_api::HttpApiSettings,
 ) -> Result<(), String> {
@@ -970,60 +1095,60 @@ pub async fn save_http_api_settings(
 
 pub use crate::modules::token_stats::{AccountTokenStats, TokenStatsAggregated, TokenStatsSummary};
 
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_token_stats_hourly(hours: i64) -> Result<Vec<TokenStatsAggregated>, String> {
     crate::modules::token_stats::get_hourly_stats(hours)
 }
 
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_token_stats_daily(days: i64) -> Result<Vec<TokenStatsAggregated>, String> {
     crate::modules::token_stats::get_daily_stats(days)
 }
 
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_token_stats_weekly(weeks: i64) -> Result<Vec<TokenStatsAggregated>, String> {
     crate::modules::token_stats::get_weekly_stats(weeks)
 }
 
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_token_stats_by_account(hours: i64) -> Result<Vec<AccountTokenStats>, String> {
     crate::modules::token_stats::get_account_stats(hours)
 }
 
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_token_stats_summary(hours: i64) -> Result<TokenStatsSummary, String> {
     crate::modules::token_stats::get_summary_stats(hours)
 }
 
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_token_stats_by_model(
     hours: i64,
 ) -> Result<Vec<crate::modules::token_stats::ModelTokenStats>, String> {
     crate::modules::token_stats::get_model_stats(hours)
 }
 
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_token_stats_model_trend_hourly(
     hours: i64,
 ) -> Result<Vec<crate::modules::token_stats::ModelTrendPoint>, String> {
     crate::modules::token_stats::get_model_trend_hourly(hours)
 }
 
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_token_stats_model_trend_daily(
     days: i64,
 ) -> Result<Vec<crate::modules::token_stats::ModelTrendPoint>, String> {
     crate::modules::token_stats::get_model_trend_daily(days)
 }
 
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_token_stats_account_trend_hourly(
     hours: i64,
 ) -> Result<Vec<crate::modules::token_stats::AccountTrendPoint>, String> {
     crate::modules::token_stats::get_account_trend_hourly(hours)
 }
 
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_token_stats_account_trend_daily(
     days: i64,
 ) -> Result<Vec<crate::modules::token_stats::AccountTrendPoint>, String> {
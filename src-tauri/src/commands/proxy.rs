@@ -3,6 +3,7 @@ use crate::proxy::{ProxyConfig, ProxyPoolConfig, TokenManager};
 use serde::{Deserialize, Serialize};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+#[cfg(feature = "desktop")]
 use tauri::State;
 use tokio::sync::RwLock;
 use tokio::time::Duration;
@@ -52,7 +53,8 @@ impl ProxyServiceState {
 }
 
 /// 启动反代服务 (Tauri 命令)
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn start_proxy_service(
     config: ProxyConfig,
     state: State<'_, ProxyServiceState>,
@@ -250,7 +252,8 @@ pub async fn ensure_admin_server(
         config.user_agent_override.clone(),
         crate::proxy::ProxySecurityConfig::from_proxy_config(&config),
         config.zai.clone(),
-        monitor,
+        config.providers.clone(),
+        monitor.clone(),
         config.experimental.clone(),
         config.debug_logging.clone(),
         integration.clone(),
@@ -263,6 +266,9 @@ pub async fn ensure_admin_server(
         Err(e) => return Err(format!("启动管理服务器失败: {}", e)),
     };
 
+    // [NEW] 监听配置文件变化并热更新运行中的服务
+    crate::proxy::config_reload::spawn_config_watcher(axum_server.clone(), monitor);
+
     *admin_lock = Some(AdminServerInstance {
         axum_server,
         server_handle,
@@ -274,12 +280,30 @@ pub async fn ensure_admin_server(
     crate::proxy::update_global_system_prompt_config(config.global_system_prompt.clone());
     // [NEW] 初始化全局图像思维模式配置
     crate::proxy::update_image_thinking_mode(config.image_thinking_mode.clone());
+    // [NEW] 初始化模型降级链
+    crate::proxy::update_fallback_chains(config.fallback_chains.clone());
+    // 初始化请求调度配置
+    crate::proxy::update_dispatch_config(config.dispatch.clone());
+    // 初始化路由规则
+    crate::proxy::update_routing_config(config.routing.clone());
+    // 初始化声明式客户端适配器
+    crate::proxy::update_client_adapters(config.client_adapters.clone());
+    // 初始化声明式工具适配器
+    crate::proxy::update_tool_adapters(config.tool_adapters.clone());
+    // 初始化签名缓存持久化 (首次启动时从磁盘恢复)
+    crate::proxy::update_signature_cache_config(config.signature_cache.clone());
+    // 初始化响应缓存配置
+    crate::proxy::update_response_cache_config(config.response_cache.clone());
+    crate::proxy::update_context_window_config(config.context_window.clone());
+    crate::proxy::update_script_hooks(config.script_hooks.clone());
+    crate::proxy::signature_persistence::start();
 
     Ok(())
 }
 
 /// 停止反代服务
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn stop_proxy_service(state: State<'_, ProxyServiceState>) -> Result<(), String> {
     let mut instance_lock = state.instance.write().await;
 
@@ -298,7 +322,8 @@ pub async fn stop_proxy_service(state: State<'_, ProxyServiceState>) -> Result<(
 }
 
 /// 获取反代服务状态
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_proxy_status(state: State<'_, ProxyServiceState>) -> Result<ProxyStatus, String> {
     // 优先检查启动标志，避免被写锁阻塞
     if state.starting.load(Ordering::SeqCst) {
@@ -341,7 +366,8 @@ pub async fn get_proxy_status(state: State<'_, ProxyServiceState>) -> Result<Pro
 }
 
 /// 获取反代服务统计
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_proxy_stats(state: State<'_, ProxyServiceState>) -> Result<ProxyStats, String> {
     let monitor_lock = state.monitor.read().await;
     if let Some(monitor) = monitor_lock.as_ref() {
@@ -352,7 +378,8 @@ pub async fn get_proxy_stats(state: State<'_, ProxyServiceState>) -> Result<Prox
 }
 
 /// 获取反代请求日志
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_proxy_logs(
     state: State<'_, ProxyServiceState>,
     limit: Option<usize>,
@@ -366,7 +393,8 @@ pub async fn get_proxy_logs(
 }
 
 /// 设置监控开启状态
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn set_proxy_monitor_enabled(
     state: State<'_, ProxyServiceState>,
     enabled: bool,
@@ -379,7 +407,8 @@ pub async fn set_proxy_monitor_enabled(
 }
 
 /// 清除反代请求日志
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn clear_proxy_logs(state: State<'_, ProxyServiceState>) -> Result<(), String> {
     let monitor_lock = state.monitor.read().await;
     if let Some(monitor) = monitor_lock.as_ref() {
@@ -389,7 +418,7 @@ pub async fn clear_proxy_logs(state: State<'_, ProxyServiceState>) -> Result<(),
 }
 
 /// 获取反代请求日志 (分页)
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_proxy_logs_paginated(
     limit: Option<usize>,
     offset: Option<usize>,
@@ -398,19 +427,19 @@ pub async fn get_proxy_logs_paginated(
 }
 
 /// 获取单条日志的完整详情
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_proxy_log_detail(log_id: String) -> Result<ProxyRequestLog, String> {
     crate::modules::proxy_db::get_log_detail(&log_id)
 }
 
 /// 获取日志总数
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_proxy_logs_count() -> Result<u64, String> {
     crate::modules::proxy_db::get_logs_count()
 }
 
 /// 导出所有日志到指定文件
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn export_proxy_logs(file_path: String) -> Result<usize, String> {
     let logs = crate::modules::proxy_db::get_all_logs_for_export()?;
     let count = logs.len();
@@ -424,7 +453,7 @@ pub async fn export_proxy_logs(file_path: String) -> Result<usize, String> {
 }
 
 /// 导出指定的日志JSON到文件
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn export_proxy_logs_json(file_path: String, json_data: String) -> Result<usize, String> {
     // Parse to count items
     let logs: Vec<serde_json::Value> =
@@ -441,7 +470,7 @@ pub async fn export_proxy_logs_json(file_path: String, json_data: String) -> Res
 }
 
 /// 获取带搜索条件的日志数量
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_proxy_logs_count_filtered(
     filter: String,
     errors_only: bool,
@@ -450,7 +479,7 @@ pub async fn get_proxy_logs_count_filtered(
 }
 
 /// 获取带搜索条件的分页日志
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_proxy_logs_filtered(
     filter: String,
     errors_only: bool,
@@ -461,13 +490,14 @@ pub async fn get_proxy_logs_filtered(
 }
 
 /// 生成 API Key
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub fn generate_api_key() -> String {
     format!("sk-{}", uuid::Uuid::new_v4().simple())
 }
 
 /// 重新加载账号（当主应用添加/删除账号时调用）
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn reload_proxy_accounts(state: State<'_, ProxyServiceState>) -> Result<usize, String> {
     let instance_lock = state.instance.read().await;
 
@@ -490,7 +520,8 @@ pub async fn reload_proxy_accounts(state: State<'_, ProxyServiceState>) -> Resul
 }
 
 /// 更新模型映射表 (热更新)
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn update_model_mapping(
     config: ProxyConfig,
     state: State<'_, ProxyServiceState>,
@@ -571,7 +602,7 @@ fn extract_model_ids(value: &serde_json::Value) -> Vec<String> {
 }
 
 /// Fetch available models from the configured z.ai Anthropic-compatible API (`/v1/models`).
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn fetch_zai_models(
     zai: crate::proxy::ZaiConfig,
     upstream_proxy: crate::proxy::config::UpstreamProxyConfig,
@@ -632,7 +663,8 @@ pub async fn fetch_zai_models(
 }
 
 /// 获取当前调度配置
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_proxy_scheduling_config(
     state: State<'_, ProxyServiceState>,
 ) -> Result<crate::proxy::sticky_config::StickySessionConfig, String> {
@@ -645,7 +677,8 @@ pub async fn get_proxy_scheduling_config(
 }
 
 /// 更新调度配置
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn update_proxy_scheduling_config(
     state: State<'_, ProxyServiceState>,
     config: crate::proxy::sticky_config::StickySessionConfig,
@@ -660,7 +693,8 @@ pub async fn update_proxy_scheduling_config(
 }
 
 /// 清除所有会话粘性绑定
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn clear_proxy_session_bindings(
     state: State<'_, ProxyServiceState>,
 ) -> Result<(), String> {
@@ -677,7 +711,8 @@ pub async fn clear_proxy_session_bindings(
 
 /// 设置优先使用的账号（固定账号模式）
 /// 传入 account_id 启用固定模式，传入 null/空字符串恢复轮询模式
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn set_preferred_account(
     state: State<'_, ProxyServiceState>,
     account_id: Option<String>,
@@ -716,7 +751,8 @@ pub async fn set_preferred_account(
 }
 
 /// 获取当前优先使用的账号ID
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_preferred_account(
     state: State<'_, ProxyServiceState>,
 ) -> Result<Option<String>, String> {
@@ -729,7 +765,8 @@ pub async fn get_preferred_account(
 }
 
 /// 清除指定账号的限流记录
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn clear_proxy_rate_limit(
     state: State<'_, ProxyServiceState>,
     account_id: String,
@@ -743,7 +780,8 @@ pub async fn clear_proxy_rate_limit(
 }
 
 /// 清除所有限流记录
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn clear_all_proxy_rate_limits(
     state: State<'_, ProxyServiceState>,
 ) -> Result<(), String> {
@@ -757,7 +795,8 @@ pub async fn clear_all_proxy_rate_limits(
 }
 
 /// 触发所有代理的健康检查，并返回更新后的配置
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn check_proxy_health(
     state: State<'_, ProxyServiceState>,
 ) -> Result<ProxyPoolConfig, String> {
@@ -777,7 +816,8 @@ pub async fn check_proxy_health(
 }
 
 /// 获取当前内存中的代理池状态
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_proxy_pool_config(
     state: State<'_, ProxyServiceState>,
 ) -> Result<ProxyPoolConfig, String> {
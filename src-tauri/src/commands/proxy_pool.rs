@@ -1,9 +1,11 @@
+#[cfg(feature = "desktop")]
 use tauri::State;
 use crate::commands::proxy::ProxyServiceState;
 use std::collections::HashMap;
 
 /// Bind an account to a specific proxy
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn bind_account_proxy(
     state: State<'_, ProxyServiceState>,
     account_id: String,
@@ -18,7 +20,8 @@ pub async fn bind_account_proxy(
 }
 
 /// Unbind an account from its proxy
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn unbind_account_proxy(
     state: State<'_, ProxyServiceState>,
     account_id: String,
@@ -33,7 +36,8 @@ pub async fn unbind_account_proxy(
 }
 
 /// Get the proxy binding for a specific account
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_account_proxy_binding(
     state: State<'_, ProxyServiceState>,
     account_id: String,
@@ -47,7 +51,8 @@ pub async fn get_account_proxy_binding(
 }
 
 /// Get all account proxy bindings
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_all_account_bindings(
     state: State<'_, ProxyServiceState>,
 ) -> Result<HashMap<String, String>, String> {
@@ -1,3 +1,4 @@
+#[cfg(feature = "desktop")]
 use tauri::State;
 use serde::{Deserialize, Serialize};
 use crate::modules::security_db;
@@ -45,7 +46,7 @@ pub struct IpStatsResponse {
 // ==================== IP 访问日志命令 ====================
 
 /// 获取 IP 访问日志列表
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_ip_access_logs(
     query: IpAccessLogQuery,
 ) -> Result<IpAccessLogResponse, String> {
@@ -65,7 +66,7 @@ pub async fn get_ip_access_logs(
 }
 
 /// 获取 IP 统计信息
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_ip_stats() -> Result<IpStatsResponse, String> {
     let stats = security_db::get_ip_stats()?;
     let top_ips = security_db::get_top_ips(10, 24)?; // Top 10 IPs in last 24 hours
@@ -79,7 +80,7 @@ pub async fn get_ip_stats() -> Result<IpStatsResponse, String> {
 }
 
 /// 清空 IP 访问日志
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn clear_ip_access_logs() -> Result<(), String> {
     security_db::clear_ip_access_logs()
 }
@@ -87,13 +88,13 @@ pub async fn clear_ip_access_logs() -> Result<(), String> {
 // ==================== IP 黑名单命令 ====================
 
 /// 获取 IP 黑名单列表
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_ip_blacklist() -> Result<Vec<security_db::IpBlacklistEntry>, String> {
     security_db::get_blacklist()
 }
 
 /// 添加 IP 到黑名单
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn add_ip_to_blacklist(
     request: AddBlacklistRequest,
 ) -> Result<(), String> {
@@ -112,7 +113,7 @@ pub async fn add_ip_to_blacklist(
 }
 
 /// 从黑名单移除 IP
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn remove_ip_from_blacklist(ip_pattern: String) -> Result<(), String> {
     // 先获取黑名单列表，找到对应的id
     let entries = security_db::get_blacklist()?;
@@ -126,7 +127,7 @@ pub async fn remove_ip_from_blacklist(ip_pattern: String) -> Result<(), String>
 }
 
 /// 清空黑名单
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn clear_ip_blacklist() -> Result<(), String> {
     // 获取所有黑名单条目并逐个删除
     let entries = security_db::get_blacklist()?;
@@ -137,7 +138,7 @@ pub async fn clear_ip_blacklist() -> Result<(), String> {
 }
 
 /// 检查 IP 是否在黑名单中
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn check_ip_in_blacklist(ip: String) -> Result<bool, String> {
     security_db::is_ip_in_blacklist(&ip)
 }
@@ -145,13 +146,13 @@ pub async fn check_ip_in_blacklist(ip: String) -> Result<bool, String> {
 // ==================== IP 白名单命令 ====================
 
 /// 获取 IP 白名单列表
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_ip_whitelist() -> Result<Vec<security_db::IpWhitelistEntry>, String> {
     security_db::get_whitelist()
 }
 
 /// 添加 IP 到白名单
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn add_ip_to_whitelist(
     request: AddWhitelistRequest,
 ) -> Result<(), String> {
@@ -168,7 +169,7 @@ pub async fn add_ip_to_whitelist(
 }
 
 /// 从白名单移除 IP
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn remove_ip_from_whitelist(ip_pattern: String) -> Result<(), String> {
     // 先获取白名单列表，找到对应的id
     let entries = security_db::get_whitelist()?;
@@ -182,7 +183,7 @@ pub async fn remove_ip_from_whitelist(ip_pattern: String) -> Result<(), String>
 }
 
 /// 清空白名单
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn clear_ip_whitelist() -> Result<(), String> {
     // 获取所有白名单条目并逐个删除
     let entries = security_db::get_whitelist()?;
@@ -193,7 +194,7 @@ pub async fn clear_ip_whitelist() -> Result<(), String> {
 }
 
 /// 检查 IP 是否在白名单中
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn check_ip_in_whitelist(ip: String) -> Result<bool, String> {
     security_db::is_ip_in_whitelist(&ip)
 }
@@ -201,7 +202,8 @@ pub async fn check_ip_in_whitelist(ip: String) -> Result<bool, String> {
 // ==================== 安全配置命令 ====================
 
 /// 获取安全监控配置
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_security_config(
     app_state: State<'_, crate::commands::proxy::ProxyServiceState>,
 ) -> Result<crate::proxy::config::SecurityMonitorConfig, String> {
@@ -218,7 +220,8 @@ pub async fn get_security_config(
 }
 
 /// 更新安全监控配置
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn update_security_config(
     config: crate::proxy::config::SecurityMonitorConfig,
     app_state: State<'_, crate::commands::proxy::ProxyServiceState>,
@@ -250,7 +253,7 @@ pub async fn update_security_config(
 // ==================== 统计分析命令 ====================
 
 /// 获取 IP Token 消耗统计
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn get_ip_token_stats(
     limit: Option<usize>,
     hours: Option<i64>
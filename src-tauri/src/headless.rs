@@ -0,0 +1,113 @@
+//! Headless 运行模式 (无 Tauri 窗口)
+//!
+//! 桌面版的 `--headless` 参数与独立的 `antigravity-server` 二进制共用此处的启动逻辑。
+
+use std::path::Path;
+use std::sync::Arc;
+use tracing::{error, info};
+
+use crate::{commands, modules};
+
+/// 初始化本地存储 (统计/安全/用户令牌数据库与账号加密)
+pub(crate) fn init_storage() {
+    // Initialize token stats database
+    if let Err(e) = modules::token_stats::init_db() {
+        error!("Failed to initialize token stats database: {}", e);
+    }
+
+    // Initialize security database
+    if let Err(e) = modules::security_db::init_db() {
+        error!("Failed to initialize security database: {}", e);
+    }
+
+    // Initialize user token database
+    if let Err(e) = modules::user_token_db::init_db() {
+        error!("Failed to initialize user token database: {}", e);
+    }
+
+    // Unlock (or enable) account at-rest encryption from ABV_ACCOUNT_PASSPHRASE / ABV_ACCOUNT_KEYFILE
+    if let Err(e) = modules::account_crypto::init_from_env() {
+        error!("Failed to initialize account encryption: {}", e);
+    }
+}
+
+/// 启动反代服务并阻塞直到收到 Ctrl-C
+///
+/// 配置按 gui_config.json → `config_file` → `ABV_*` 环境变量分层加载，校验失败时直接返回错误。
+pub(crate) async fn serve(config_file: Option<&Path>) -> Result<(), String> {
+    let proxy_state = commands::proxy::ProxyServiceState::new();
+    let cf_state = Arc::new(commands::cloudflared::CloudflaredState::new());
+
+    // Load config
+    modules::config_loader::set_layered_source(config_file);
+    let layered = modules::config_loader::load_layered_config(config_file)
+        .map_err(|e| format!("Failed to load config for headless mode: {}", e))?;
+    let mut config = layered.config;
+    modules::config_loader::apply_headless_overrides(&mut config);
+
+    info!("--------------------------------------------------");
+    info!("🚀 Headless mode proxy service starting...");
+    info!("📍 Port: {}", config.proxy.port);
+    info!("🔑 Current API Key: {}", config.proxy.api_key);
+    if let Some(ref pwd) = config.proxy.admin_password {
+        info!("🔐 Web UI Password: {}", pwd);
+    } else {
+        info!("🔐 Web UI Password: (Same as API Key)");
+    }
+    info!("💡 Tips: You can use these keys to login to Web UI and access AI APIs.");
+    info!("💡 Search docker logs or grep gui_config.json to find them.");
+    info!("--------------------------------------------------");
+
+    // [FIX #1460] Persist environment overrides to ensure they are visible in Web UI/load_config
+    // 只在 gui_config.json 本身上应用并保存上述兼容覆盖；`--config` 文件层与 ABV_* 环境变量层
+    // 仅作用于本次运行，不写回磁盘 (避免密钥落盘，移除环境变量后也能恢复原值)
+    match modules::config::load_app_config() {
+        Ok(mut persisted) if modules::config_loader::apply_headless_overrides(&mut persisted) => {
+            if let Err(e) = modules::config::save_app_config(&persisted) {
+                error!("Failed to persist environment overrides: {}", e);
+            } else {
+                info!("Environment overrides persisted to gui_config.json");
+            }
+        }
+        Ok(_) => {}
+        Err(e) => error!("Failed to persist environment overrides: {}", e),
+    }
+
+    // Start proxy service
+    commands::proxy::internal_start_proxy_service(
+        config.proxy,
+        &proxy_state,
+        crate::modules::integration::SystemManager::Headless,
+        cf_state.clone(),
+    )
+    .await
+    .map_err(|e| format!("Failed to start proxy service in headless mode: {}", e))?;
+
+    info!("Headless proxy service is running.");
+
+    // [DISABLED] Start smart scheduler (Automatic warmup disabled as per user request)
+    // modules::scheduler::start_scheduler(None, proxy_state.clone());
+    info!("Smart scheduler (Automatic Warmup) is DISABLED.");
+
+    // Wait for Ctrl-C
+    tokio::signal::ctrl_c().await.ok();
+    info!("Headless mode shutting down");
+    crate::proxy::signature_persistence::flush_on_shutdown();
+
+    if let Some(instance) = proxy_state.instance.read().await.as_ref() {
+        instance
+            .token_manager
+            .graceful_shutdown(std::time::Duration::from_secs(2))
+            .await;
+    }
+    Ok(())
+}
+
+/// 同步入口：创建 Tokio 运行时并运行 [`serve`]，失败时以非零状态码退出
+pub(crate) fn run_blocking(config_file: Option<&Path>) {
+    let rt = tokio::runtime::Runtime::new().expect("Failed to create Tokio runtime");
+    if let Err(e) = rt.block_on(serve(config_file)) {
+        error!("{}", e);
+        std::process::exit(1);
+    }
+}
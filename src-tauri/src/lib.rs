@@ -5,24 +5,32 @@ mod utils;
 mod proxy;  // Proxy service module
 pub mod error;
 pub mod constants;
+mod headless;
+pub mod cli;
 
+#[cfg(feature = "desktop")]
 use tauri::Manager;
+#[cfg(feature = "desktop")]
 use modules::logger;
+#[cfg(feature = "desktop")]
 use tracing::{info, warn, error};
+#[cfg(feature = "desktop")]
 use std::sync::Arc;
 
+#[cfg(feature = "desktop")]
 #[derive(Clone, Copy)]
 struct AppRuntimeFlags {
     tray_enabled: bool,
 }
 
+#[cfg(feature = "desktop")]
 fn env_flag_enabled(name: &str) -> bool {
     std::env::var(name)
         .map(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes" | "on"))
         .unwrap_or(false)
 }
 
-#[cfg(target_os = "linux")]
+#[cfg(all(feature = "desktop", target_os = "linux"))]
 fn is_wayland_session() -> bool {
     std::env::var("WAYLAND_DISPLAY")
         .map(|v| !v.trim().is_empty())
@@ -32,6 +40,7 @@ fn is_wayland_session() -> bool {
             .unwrap_or(false)
 }
 
+#[cfg(feature = "desktop")]
 fn should_enable_tray() -> bool {
     if env_flag_enabled("ANTIGRAVITY_DISABLE_TRAY") {
         info!("Tray disabled by ANTIGRAVITY_DISABLE_TRAY");
@@ -51,7 +60,7 @@ fn should_enable_tray() -> bool {
     true
 }
 
-#[cfg(target_os = "linux")]
+#[cfg(all(feature = "desktop", target_os = "linux"))]
 fn configure_linux_gdk_backend() {
     if std::env::var("GDK_BACKEND").is_ok() {
         return;
@@ -76,6 +85,8 @@ fn configure_linux_gdk_backend() {
 /// Increase file descriptor limit for macOS to prevent "Too many open files" errors
 #[cfg(target_os = "macos")]
 fn increase_nofile_limit() {
+    use tracing::{info, warn};
+
     unsafe {
         let mut rl = libc::rlimit {
             rlim_cur: 0,
@@ -100,11 +111,13 @@ fn increase_nofile_limit() {
 }
 
 // Test command
-#[tauri::command]
+#[cfg(feature = "desktop")]
+#[cfg_attr(feature = "desktop", tauri::command)]
 fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
+#[cfg(feature = "desktop")]
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     // Check for headless mode
@@ -121,170 +134,19 @@ pub fn run() {
     #[cfg(target_os = "linux")]
     configure_linux_gdk_backend();
 
-    // Initialize token stats database
-    if let Err(e) = modules::token_stats::init_db() {
-        error!("Failed to initialize token stats database: {}", e);
-    }
-
-    // Initialize security database
-    if let Err(e) = modules::security_db::init_db() {
-        error!("Failed to initialize security database: {}", e);
-    }
-    
-    // Initialize user token database
-    if let Err(e) = modules::user_token_db::init_db() {
-        error!("Failed to initialize user token database: {}", e);
-    }
+    // Initialize databases and account encryption
+    headless::init_storage();
 
     if is_headless {
         info!("Starting in HEADLESS mode...");
-
-        let rt = tokio::runtime::Runtime::new().expect("Failed to create Tokio runtime");
-        rt.block_on(async {
-            // Initialize states manually
-            // [FIX] Initialize log bridge for headless mode
-            // Pass a dummy app handle or None since we don't have a Tauri app handle in headless mode
-            // Actually log_bridge relies on AppHandle to emit events.
-            // In headless mode, we don't emit events, but we still need the buffer.
-            // We need to modify log_bridge to handle missing AppHandle gracefully, which it already does (Option).
-            // But init_log_bridge requires AppHandle.
-            // We'll skip passing AppHandle for now and just leverage the global buffer capability.
-            // Since init_log_bridge takes AppHandle, we might need a separate init for headless or just not call init and rely on lazy init of buffer?
-            // Checking log_bridge code again...
-            // "static LOG_BUFFER: OnceLock<...> = OnceLock::new();" -> lazy init.
-            // So we just need to ensure the tracing layer is added.
-            // And `logger::init_logger()` adds the layer?
-            // Let's check `modules::logger`.
-
-            let proxy_state = commands::proxy::ProxyServiceState::new();
-            let cf_state = Arc::new(commands::cloudflared::CloudflaredState::new());
-
-            // Load config
-            match modules::config::load_app_config() {
-                Ok(mut config) => {
-                    let mut modified = false;
-                    // Headless/docker 默认允许 LAN 访问（绑定 0.0.0.0）
-                    // 若设置 ABV_BIND_LOCAL_ONLY，则仅绑定 127.0.0.1
-                    let bind_local_only = std::env::var("ABV_BIND_LOCAL_ONLY")
-                        .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes" | "on"))
-                        .unwrap_or(false);
-                    if bind_local_only {
-                        config.proxy.allow_lan_access = false;
-                        modified = true;
-                    } else {
-                        config.proxy.allow_lan_access = true;
-                    }
-
-                    // [FIX] Force auth mode to AllExceptHealth in headless mode if it's Off or Auto
-                    // This ensures Web UI login validation works properly
-                    if matches!(config.proxy.auth_mode, crate::proxy::ProxyAuthMode::Off | crate::proxy::ProxyAuthMode::Auto) {
-                        info!("Headless mode: Forcing auth_mode to AllExceptHealth for Web UI security");
-                        config.proxy.auth_mode = crate::proxy::ProxyAuthMode::AllExceptHealth;
-                        modified = true;
-                    }
-
-                    // [NEW] 支持通过环境变量注入 API Key
-                    // 优先级：ABV_API_KEY > API_KEY > 配置文件
-                    let env_key = std::env::var("ABV_API_KEY")
-                        .or_else(|_| std::env::var("API_KEY"))
-                        .ok();
-
-                    if let Some(key) = env_key {
-                        if !key.trim().is_empty() {
-                            info!("Using API Key from environment variable");
-                            config.proxy.api_key = key;
-                            modified = true;
-                        }
-                    }
-
-                    // [NEW] 支持通过环境变量注入 Web UI 密码
-                    // 优先级：ABV_WEB_PASSWORD > WEB_PASSWORD > 配置文件
-                    let env_web_password = std::env::var("ABV_WEB_PASSWORD")
-                        .or_else(|_| std::env::var("WEB_PASSWORD"))
-                        .ok();
-
-                    if let Some(pwd) = env_web_password {
-                        if !pwd.trim().is_empty() {
-                            info!("Using Web UI Password from environment variable");
-                            config.proxy.admin_password = Some(pwd);
-                            modified = true;
-                        }
-                    }
-
-                    // [NEW] 支持通过环境变量注入鉴权模式
-                    // 优先级：ABV_AUTH_MODE > AUTH_MODE > 配置文件
-                    let env_auth_mode = std::env::var("ABV_AUTH_MODE")
-                        .or_else(|_| std::env::var("AUTH_MODE"))
-                        .ok();
-
-                    if let Some(mode_str) = env_auth_mode {
-                        let mode = match mode_str.to_lowercase().as_str() {
-                            "off" => Some(crate::proxy::ProxyAuthMode::Off),
-                            "strict" => Some(crate::proxy::ProxyAuthMode::Strict),
-                            "all_except_health" => Some(crate::proxy::ProxyAuthMode::AllExceptHealth),
-                            "auto" => Some(crate::proxy::ProxyAuthMode::Auto),
-                            _ => {
-                                warn!("Invalid AUTH_MODE: {}, ignoring", mode_str);
-                                None
-                            }
-                        };
-                        if let Some(m) = mode {
-                            info!("Using Auth Mode from environment variable: {:?}", m);
-                            config.proxy.auth_mode = m;
-                            modified = true;
-                        }
-                    }
-
-                    info!("--------------------------------------------------");
-                    info!("🚀 Headless mode proxy service starting...");
-                    info!("📍 Port: {}", config.proxy.port);
-                    info!("🔑 Current API Key: {}", config.proxy.api_key);
-                    if let Some(ref pwd) = config.proxy.admin_password {
-                        info!("🔐 Web UI Password: {}", pwd);
-                    } else {
-                        info!("🔐 Web UI Password: (Same as API Key)");
-                    }
-                    info!("💡 Tips: You can use these keys to login to Web UI and access AI APIs.");
-                    info!("💡 Search docker logs or grep gui_config.json to find them.");
-                    info!("--------------------------------------------------");
-
-                    // [FIX #1460] Persist environment overrides to ensure they are visible in Web UI/load_config
-                    if modified {
-                        if let Err(e) = modules::config::save_app_config(&config) {
-                            error!("Failed to persist environment overrides: {}", e);
-                        } else {
-                            info!("Environment overrides persisted to gui_config.json");
-                        }
-                    }
-
-                    // Start proxy service
-                    if let Err(e) = commands::proxy::internal_start_proxy_service(
-                        config.proxy,
-                        &proxy_state,
-                        crate::modules::integration::SystemManager::Headless,
-                        cf_state.clone(),
-                    ).await {
-                        error!("Failed to start proxy service in headless mode: {}", e);
-                        std::process::exit(1);
-                    }
-
-                    info!("Headless proxy service is running.");
-
-                    // [DISABLED] Start smart scheduler (Automatic warmup disabled as per user request)
-                    // modules::scheduler::start_scheduler(None, proxy_state.clone());
-                    info!("Smart scheduler (Automatic Warmup) is DISABLED.");
-                    info!("Smart scheduler started in headless mode.");
-                }
-                Err(e) => {
-                    error!("Failed to load config for headless mode: {}", e);
-                    std::process::exit(1);
-                }
-            }
-
-            // Wait for Ctrl-C
-            tokio::signal::ctrl_c().await.ok();
-            info!("Headless mode shutting down");
-        });
+        // --config <FILE> (或 ABV_CONFIG_FILE) 指定的 TOML/JSON 配置覆盖层
+        let config_file = args
+            .iter()
+            .position(|arg| arg == "--config")
+            .and_then(|i| args.get(i + 1))
+            .map(std::path::PathBuf::from)
+            .or_else(|| std::env::var_os("ABV_CONFIG_FILE").map(std::path::PathBuf::from));
+        headless::run_blocking(config_file.as_deref());
         return;
     }
 
@@ -430,6 +292,13 @@ pub fn run() {
             commands::reorder_accounts,
             commands::switch_account,
             commands::export_accounts,
+            commands::create_backup_bundle,
+            commands::restore_backup_bundle,
+            commands::get_account_encryption_status,
+            commands::enable_account_encryption,
+            commands::unlock_account_encryption,
+            commands::rekey_account_encryption,
+            commands::disable_account_encryption,
             // Device fingerprint
             commands::get_device_profiles,
             commands::bind_device_profile,
@@ -593,6 +462,7 @@ pub fn run() {
                 // Handle app exit - cleanup background tasks
                 tauri::RunEvent::Exit => {
                     tracing::info!("Application exiting, cleaning up background tasks...");
+                    crate::proxy::signature_persistence::flush_on_shutdown();
                     if let Some(state) = app_handle.try_state::<crate::commands::proxy::ProxyServiceState>() {
                         tauri::async_runtime::block_on(async {
                             // Use timeout-based read() instead of try_read() to handle lock contention
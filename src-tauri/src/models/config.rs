@@ -98,6 +98,13 @@ impl QuotaProtectionConfig {
             monitored_models: default_monitored_models(),
         }
     }
+
+    pub fn validate(&self, errors: &mut Vec<String>) {
+        if self.enabled && !(1..=99).contains(&self.threshold_percentage) {
+            errors
+                .push("quota_protection.threshold_percentage must be between 1 and 99".to_string());
+        }
+    }
 }
 
 impl Default for QuotaProtectionConfig {
@@ -160,6 +167,12 @@ impl CircuitBreakerConfig {
             backoff_steps: default_backoff_steps(),
         }
     }
+
+    pub fn validate(&self, errors: &mut Vec<String>) {
+        if self.enabled && self.backoff_steps.is_empty() {
+            errors.push("circuit_breaker.backoff_steps must not be empty when enabled".to_string());
+        }
+    }
 }
 
 impl Default for CircuitBreakerConfig {
@@ -319,6 +319,41 @@ mod tests {
         println!("Backup creation on parse failure: successfully created backup");
     }
 
+    #[test]
+    fn test_merge_account_records_fills_gaps_without_overwriting() {
+        let token = TokenData::new("atk".into(), "rtk".into(), 3600, None, None, None, true);
+        let mut local = Account::new("local-id".into(), "user@example.com".into(), token.clone());
+        local.custom_label = Some("local label".into());
+        local.protected_models.insert("gemini-3-pro-high".into());
+
+        let mut backup = Account::new("backup-id".into(), "user@example.com".into(), token);
+        backup.custom_label = Some("backup label".into());
+        backup.proxy_id = Some("proxy-1".into());
+        backup.protected_models.insert("claude-opus-4-6-thinking".into());
+        backup.created_at = local.created_at - 100;
+
+        merge_account_records(&mut local, backup);
+
+        assert_eq!(local.id, "local-id");
+        assert_eq!(local.custom_label.as_deref(), Some("local label"));
+        assert_eq!(local.proxy_id.as_deref(), Some("proxy-1"));
+        assert_eq!(local.protected_models.len(), 2);
+        assert!(local.created_at < chrono::Utc::now().timestamp() - 50);
+    }
+
+    #[test]
+    fn test_merge_account_records_replaces_disabled_token() {
+        let old = TokenData::new("atk".into(), "revoked".into(), 3600, None, None, None, true);
+        let fresh = TokenData::new("atk2".into(), "fresh".into(), 3600, None, None, None, true);
+        let mut local = Account::new("id".into(), "user@example.com".into(), old);
+        local.disabled = true;
+        local.disabled_reason = Some("invalid_grant".into());
+
+        merge_account_records(&mut local, Account::new("id".into(), "user@example.com".into(), fresh));
+
+        assert!(!local.disabled);
+        assert_eq!(local.token.refresh_token, "fresh");
+    }
 }
 
 /// Global account write lock to prevent corruption during concurrent operations
@@ -515,7 +550,11 @@ fn rebuild_index_from_accounts_in_dir(data_dir: &PathBuf) -> Result<AccountIndex
 fn load_account_at_path(account_path: &PathBuf) -> Result<Account, String> {
     let content = fs::read_to_string(account_path)
         .map_err(|e| format!("failed_to_read_account_data: {}", e))?;
-    serde_json::from_str(&content).map_err(|e| format!("failed_to_parse_account_data: {}", e))
+    let mut value: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| format!("failed_to_parse_account_data: {}", e))?;
+    // Transparently decrypt token fields (plaintext legacy files pass through unchanged)
+    modules::account_crypto::open_account_value(&mut value)?;
+    serde_json::from_value(value).map_err(|e| format!("failed_to_parse_account_data: {}", e))
 }
 
 /// Load account index with recovery support
@@ -599,7 +638,7 @@ pub fn save_account_index(index: &AccountIndex) -> Result<(), String> {
 
 /// Platform-specific atomic file replacement
 #[cfg(target_os = "windows")]
-fn atomic_replace_file(src: &PathBuf, dst: &PathBuf) -> Result<(), String> {
+pub(crate) fn atomic_replace_file(src: &PathBuf, dst: &PathBuf) -> Result<(), String> {
     use std::os::windows::ffi::OsStrExt;
 
     type Bool = i32;
@@ -640,7 +679,7 @@ fn atomic_replace_file(src: &PathBuf, dst: &PathBuf) -> Result<(), String> {
 
 /// Non-Windows: use standard rename
 #[cfg(not(target_os = "windows"))]
-fn atomic_replace_file(src: &PathBuf, dst: &PathBuf) -> Result<(), String> {
+pub(crate) fn atomic_replace_file(src: &PathBuf, dst: &PathBuf) -> Result<(), String> {
     fs::rename(src, dst).map_err(|e| format!("rename failed: {}", e))
 }
 
@@ -659,7 +698,11 @@ pub fn save_account(account: &Account) -> Result<(), String> {
     let temp_filename = format!("{}.tmp.{}", account.id, Uuid::new_v4());
     let temp_path = accounts_dir.join(&temp_filename);
 
-    let content = serde_json::to_string_pretty(account)
+    let mut value = serde_json::to_value(account)
+        .map_err(|e| format!("failed_to_serialize_account_data: {}", e))?;
+    // Encrypt token fields when at-rest encryption is enabled
+    modules::account_crypto::seal_account_value(&mut value)?;
+    let content = serde_json::to_string_pretty(&value)
         .map_err(|e| format!("failed_to_serialize_account_data: {}", e))?;
 
     if let Err(e) = std::fs::write(&temp_path, content) {
@@ -822,6 +865,107 @@ pub fn upsert_account(
     add_account(email, name, token)
 }
 
+/// Restore a full account record from a backup bundle, merging into an existing account
+/// with the same email instead of overwriting it. Returns `true` when a new account was created.
+pub fn restore_account_record(incoming: Account) -> Result<bool, String> {
+    let _lock = ACCOUNT_INDEX_LOCK
+        .lock()
+        .map_err(|e| format!("failed_to_acquire_lock: {}", e))?;
+    let mut index = load_account_index()?;
+
+    let existing_id = index
+        .accounts
+        .iter()
+        .find(|s| s.email == incoming.email)
+        .map(|s| s.id.clone());
+
+    let (account, created) = match existing_id.and_then(|id| load_account(&id).ok()) {
+        Some(mut existing) => {
+            merge_account_records(&mut existing, incoming);
+            (existing, false)
+        }
+        None => {
+            let mut account = incoming;
+            // Keep the original ID unless it is already taken by a different account
+            if index.accounts.iter().any(|s| s.id == account.id) {
+                account.id = Uuid::new_v4().to_string();
+            }
+            (account, true)
+        }
+    };
+
+    save_account(&account)?;
+
+    let summary = AccountSummary {
+        id: account.id.clone(),
+        email: account.email.clone(),
+        name: account.name.clone(),
+        disabled: account.disabled,
+        proxy_disabled: account.proxy_disabled,
+        protected_models: account.protected_models.clone(),
+        created_at: account.created_at,
+        last_used: account.last_used,
+    };
+    match index.accounts.iter_mut().find(|s| s.id == account.id) {
+        Some(existing) => *existing = summary,
+        None => index.accounts.push(summary),
+    }
+    if index.current_account_id.is_none() {
+        index.current_account_id = Some(account.id.clone());
+    }
+    save_account_index(&index)?;
+
+    crate::proxy::server::trigger_account_reload(&account.id);
+    Ok(created)
+}
+
+/// Merge a backed-up account into the local record.
+///
+/// Local state wins for anything the user may have changed since the backup; the backup only
+/// fills gaps, except that a disabled local token is replaced by a different backed-up one.
+fn merge_account_records(existing: &mut Account, incoming: Account) {
+    if existing.disabled && incoming.token.refresh_token != existing.token.refresh_token {
+        existing.token = incoming.token;
+        existing.disabled = false;
+        existing.disabled_reason = None;
+        existing.disabled_at = None;
+    }
+
+    if existing.name.is_none() {
+        existing.name = incoming.name;
+    }
+    if existing.custom_label.is_none() {
+        existing.custom_label = incoming.custom_label;
+    }
+    if existing.device_profile.is_none() {
+        existing.device_profile = incoming.device_profile;
+    }
+    for version in incoming.device_history {
+        if !existing.device_history.iter().any(|v| v.id == version.id) {
+            existing.device_history.push(DeviceProfileVersion {
+                is_current: false,
+                ..version
+            });
+        }
+    }
+    existing.protected_models.extend(incoming.protected_models);
+    if existing.proxy_id.is_none() {
+        existing.proxy_id = incoming.proxy_id;
+        existing.proxy_bound_at = incoming.proxy_bound_at;
+    }
+
+    let incoming_quota_newer = match (&existing.quota, &incoming.quota) {
+        (None, Some(_)) => true,
+        (Some(local), Some(backup)) => backup.last_updated > local.last_updated,
+        _ => false,
+    };
+    if incoming_quota_newer {
+        existing.quota = incoming.quota;
+    }
+
+    existing.created_at = existing.created_at.min(incoming.created_at);
+}
+
 /// Delete account
 pub fn delete_account(account_id: &str) -> Result<(), String> {
     let _lock = ACCOUNT_INDEX_LOCK
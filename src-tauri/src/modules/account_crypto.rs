@@ -0,0 +1,643 @@
+//! 账号文件静态加密 (At-rest encryption)
+//!
+//! 仅对 `token.access_token` / `token.refresh_token` 字段做字段级加密，
+//! 其余字段保持明文，便于 TokenManager 等模块继续以 JSON 方式局部读写账号文件。
+//!
+//! 密钥来源 (优先级从高到低):
+//! 1. 环境变量 `ABV_ACCOUNT_PASSPHRASE`
+//! 2. 环境变量 `ABV_ACCOUNT_KEYFILE` 指向的文件内容 (适用于 Docker secrets)
+//! 3. 运行时通过 GUI / 管理 API 解锁
+
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use base64::{engine::general_purpose, Engine as _};
+
+use crate::modules::account::{atomic_replace_file, get_accounts_dir, get_data_dir};
+use crate::utils::crypto;
+
+const META_FILE: &str = "account_encryption.json";
+const META_VERSION: u32 = 1;
+const KDF_ITERATIONS: u32 = 600_000;
+const CHECK_PLAINTEXT: &str = "antigravity-account-vault";
+const SECRET_FIELDS: [&str; 2] = ["access_token", "refresh_token"];
+
+pub const ENV_PASSPHRASE: &str = "ABV_ACCOUNT_PASSPHRASE";
+pub const ENV_KEYFILE: &str = "ABV_ACCOUNT_KEYFILE";
+
+/// 加密元数据 (account_encryption.json)，不包含密钥本身
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct VaultMeta {
+    version: u32,
+    kdf: String,
+    iterations: u32,
+    salt: String,
+    /// 使用派生密钥加密的固定串，用于校验口令是否正确
+    check: String,
+    updated_at: i64,
+    /// 进行中的 rekey：先于账号文件落盘，中途崩溃后可用新旧任一口令解锁并续做
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pending_rekey: Option<PendingRekey>,
+}
+
+/// 旧口令的 KDF 参数，以及互相包裹的新旧密钥
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PendingRekey {
+    iterations: u32,
+    salt: String,
+    check: String,
+    /// 以新密钥加密的旧密钥
+    previous_key: String,
+    /// 以旧密钥加密的新密钥
+    next_key: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeySource {
+    Env,
+    Keyfile,
+    Manual,
+}
+
+#[derive(Default)]
+struct VaultState {
+    key: Option<[u8; 32]>,
+    /// 重新加密 (rekey) 后保留旧密钥，用于读取并发写入的旧密文
+    previous_key: Option<[u8; 32]>,
+    source: Option<KeySource>,
+}
+
+static VAULT: Lazy<RwLock<VaultState>> = Lazy::new(|| RwLock::new(VaultState::default()));
+
+/// 加密状态 (供 UI / 管理 API 展示)
+#[derive(Debug, Clone, Serialize)]
+pub struct EncryptionStatus {
+    pub enabled: bool,
+    pub unlocked: bool,
+    pub key_source: Option<KeySource>,
+    pub encrypted_accounts: usize,
+    pub plaintext_accounts: usize,
+}
+
+fn meta_path() -> Result<PathBuf, String> {
+    Ok(get_data_dir()?.join(META_FILE))
+}
+
+fn load_meta() -> Result<Option<VaultMeta>, String> {
+    let path = meta_path()?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("failed_to_read_encryption_meta: {}", e))?;
+    serde_json::from_str(&content)
+        .map(Some)
+        .map_err(|e| format!("failed_to_parse_encryption_meta: {}", e))
+}
+
+fn save_meta(meta: &VaultMeta) -> Result<(), String> {
+    let path = meta_path()?;
+    let temp_path = path.with_extension(format!("json.tmp.{}", uuid::Uuid::new_v4()));
+    let content = serde_json::to_string_pretty(meta)
+        .map_err(|e| format!("failed_to_serialize_encryption_meta: {}", e))?;
+    fs::write(&temp_path, content).map_err(|e| {
+        let _ = fs::remove_file(&temp_path);
+        format!("failed_to_write_encryption_meta: {}", e)
+    })?;
+    atomic_replace_file(&temp_path, &path)
+}
+
+fn new_meta(passphrase: &str) -> Result<(VaultMeta, [u8; 32]), String> {
+    new_meta_with(passphrase, KDF_ITERATIONS)
+}
+
+fn new_meta_with(passphrase: &str, iterations: u32) -> Result<(VaultMeta, [u8; 32]), String> {
+    let salt = crypto::random_salt();
+    let key = crypto::derive_key(passphrase, &salt, iterations);
+    let meta = VaultMeta {
+        version: META_VERSION,
+        kdf: "pbkdf2-sha256".to_string(),
+        iterations,
+        salt: general_purpose::STANDARD.encode(salt),
+        check: crypto::encrypt_with_key(&key, CHECK_PLAINTEXT)?,
+        updated_at: chrono::Utc::now().timestamp(),
+        pending_rekey: None,
+    };
+    Ok((meta, key))
+}
+
+/// 根据元数据派生密钥并校验口令
+fn derive_and_verify(meta: &VaultMeta, passphrase: &str) -> Result<[u8; 32], String> {
+    verify_passphrase(passphrase, &meta.salt, meta.iterations, &meta.check)
+}
+
+fn verify_passphrase(
+    passphrase: &str,
+    salt: &str,
+    iterations: u32,
+    check: &str,
+) -> Result<[u8; 32], String> {
+    let salt = general_purpose::STANDARD
+        .decode(salt)
+        .map_err(|e| format!("invalid_encryption_salt: {}", e))?;
+    let key = crypto::derive_key(passphrase, &salt, iterations);
+    match crypto::decrypt_with_key(&key, check) {
+        Ok(check) if check == CHECK_PLAINTEXT => Ok(key),
+        _ => Err("invalid_account_passphrase".to_string()),
+    }
+}
+
+fn wrap_key(wrapping: &[u8; 32], key: &[u8; 32]) -> Result<String, String> {
+    crypto::encrypt_with_key(wrapping, &general_purpose::STANDARD.encode(key))
+}
+
+fn unwrap_key(wrapping: &[u8; 32], wrapped: &str) -> Result<[u8; 32], String> {
+    let encoded = crypto::decrypt_with_key(wrapping, wrapped)?;
+    general_purpose::STANDARD
+        .decode(encoded)
+        .ok()
+        .and_then(|raw| raw.try_into().ok())
+        .ok_or_else(|| "invalid_pending_rekey_key".to_string())
+}
+
+/// 派生当前密钥；若存在未完成的 rekey，新旧口令均可解锁，并同时返回旧密钥
+fn unlock_keys(meta: &VaultMeta, passphrase: &str) -> Result<([u8; 32], Option<[u8; 32]>), String> {
+    let Some(pending) = &meta.pending_rekey else {
+        return Ok((derive_and_verify(meta, passphrase)?, None));
+    };
+    match derive_and_verify(meta, passphrase) {
+        Ok(key) => Ok((key, Some(unwrap_key(&key, &pending.previous_key)?))),
+        Err(e) => {
+            let old_key = verify_passphrase(
+                passphrase,
+                &pending.salt,
+                pending.iterations,
+                &pending.check,
+            )
+            .map_err(|_| e)?;
+            Ok((unwrap_key(&old_key, &pending.next_key)?, Some(old_key)))
+        }
+    }
+}
+
+/// 在元数据中记录待完成的 rekey (新口令参数 + 旧口令参数 + 互相包裹的密钥)
+fn begin_rekey(
+    meta: &VaultMeta,
+    old_key: &[u8; 32],
+    new_passphrase: &str,
+    iterations: u32,
+) -> Result<(VaultMeta, [u8; 32]), String> {
+    let (mut next, new_key) = new_meta_with(new_passphrase, iterations)?;
+    next.pending_rekey = Some(PendingRekey {
+        iterations: meta.iterations,
+        salt: meta.salt.clone(),
+        check: meta.check.clone(),
+        previous_key: wrap_key(&new_key, old_key)?,
+        next_key: wrap_key(old_key, &new_key)?,
+    });
+    Ok((next, new_key))
+}
+
+fn passphrase_from_env() -> Option<(String, KeySource)> {
+    if let Ok(pass) = std::env::var(ENV_PASSPHRASE) {
+        if !pass.trim().is_empty() {
+            return Some((pass, KeySource::Env));
+        }
+    }
+    if let Ok(path) = std::env::var(ENV_KEYFILE) {
+        if !path.trim().is_empty() {
+            match fs::read_to_string(path.trim()) {
+                Ok(content) if !content.trim().is_empty() => {
+                    return Some((content.trim().to_string(), KeySource::Keyfile));
+                }
+                Ok(_) => tracing::warn!("[AccountCrypto] Keyfile {} is empty, ignoring", path),
+                Err(e) => tracing::error!("[AccountCrypto] Failed to read keyfile {}: {}", path, e),
+            }
+        }
+    }
+    None
+}
+
+/// 是否已启用加密 (以元数据文件是否存在为准)
+pub fn is_enabled() -> bool {
+    meta_path().map(|p| p.exists()).unwrap_or(false)
+}
+
+pub fn is_unlocked() -> bool {
+    VAULT.read().key.is_some()
+}
+
+/// 启动时从环境变量 / keyfile 初始化
+///
+/// - 已启用加密：使用提供的口令解锁
+/// - 未启用加密但提供了口令：自动启用并迁移现有明文账号文件
+pub fn init_from_env() -> Result<(), String> {
+    let meta = load_meta()?;
+    let Some((passphrase, source)) = passphrase_from_env() else {
+        if meta.is_some() {
+            tracing::warn!(
+                "[AccountCrypto] Account files are encrypted but no passphrase was provided; \
+                 set {} / {} or unlock from the UI",
+                ENV_PASSPHRASE,
+                ENV_KEYFILE
+            );
+        }
+        return Ok(());
+    };
+
+    match meta {
+        Some(meta) => {
+            let (key, previous_key) = unlock_keys(&meta, &passphrase)?;
+            set_key(key, previous_key, source);
+            tracing::info!("[AccountCrypto] Account store unlocked via {:?}", source);
+            resume_rekey(meta, &key, previous_key.as_ref())?;
+            migrate_accounts()?;
+        }
+        None => {
+            enable_with_source(&passphrase, source)?;
+        }
+    }
+    Ok(())
+}
+
+fn set_key(key: [u8; 32], previous_key: Option<[u8; 32]>, source: KeySource) {
+    let mut vault = VAULT.write();
+    vault.key = Some(key);
+    vault.previous_key = previous_key;
+    vault.source = Some(source);
+}
+
+/// 启用加密并迁移所有账号文件
+pub fn enable(passphrase: &str) -> Result<usize, String> {
+    enable_with_source(passphrase, KeySource::Manual)
+}
+
+fn enable_with_source(passphrase: &str, source: KeySource) -> Result<usize, String> {
+    if passphrase.trim().is_empty() {
+        return Err("account_passphrase_empty".to_string());
+    }
+    if is_enabled() {
+        return Err("account_encryption_already_enabled".to_string());
+    }
+
+    let (meta, key) = new_meta(passphrase)?;
+    save_meta(&meta)?;
+    set_key(key, None, source);
+
+    let migrated = migrate_accounts()?;
+    tracing::info!(
+        "[AccountCrypto] Account encryption enabled, {} account file(s) migrated",
+        migrated
+    );
+    Ok(migrated)
+}
+
+/// 使用口令解锁 (GUI / 管理 API)
+pub fn unlock(passphrase: &str) -> Result<usize, String> {
+    let meta = load_meta()?.ok_or("account_encryption_not_enabled")?;
+    let (key, previous_key) = unlock_keys(&meta, passphrase)?;
+    set_key(key, previous_key, KeySource::Manual);
+    resume_rekey(meta, &key, previous_key.as_ref())?;
+    // 迁移解锁前导入的明文文件
+    migrate_accounts()
+}
+
+/// 上次 rekey 中途中断时，用新密钥重写剩余账号文件并清除标记
+fn resume_rekey(
+    meta: VaultMeta,
+    key: &[u8; 32],
+    previous_key: Option<&[u8; 32]>,
+) -> Result<(), String> {
+    if meta.pending_rekey.is_none() {
+        return Ok(());
+    }
+    let count = finish_rekey(meta, key, previous_key)?;
+    tracing::warn!(
+        "[AccountCrypto] Resumed interrupted re-encryption, {} account file(s) rewritten",
+        count
+    );
+    Ok(())
+}
+
+/// 用新密钥逐个重写账号文件 (新旧密钥均可读取)，全部完成后清除 rekey 标记
+fn finish_rekey(
+    mut meta: VaultMeta,
+    key: &[u8; 32],
+    previous_key: Option<&[u8; 32]>,
+) -> Result<usize, String> {
+    let mut count = 0;
+    for path in list_account_files()? {
+        let mut value = read_account_value(&path)?;
+        open_value_with(&mut value, Some(key), previous_key)?;
+        seal_value_with(&mut value, key)?;
+        let temp_path = stage_account_value(&path, &value)?;
+        if let Err(e) = atomic_replace_file(&temp_path, &path) {
+            let _ = fs::remove_file(&temp_path);
+            return Err(format!("failed_to_replace_account_file: {}", e));
+        }
+        count += 1;
+    }
+    meta.pending_rekey = None;
+    meta.updated_at = chrono::Utc::now().timestamp();
+    save_meta(&meta)?;
+    Ok(count)
+}
+
+/// 更换口令：使用新密钥重新加密所有账号文件
+pub fn rekey(current_passphrase: &str, new_passphrase: &str) -> Result<usize, String> {
+    if new_passphrase.trim().is_empty() {
+        return Err("account_passphrase_empty".to_string());
+    }
+    let mut meta = load_meta()?.ok_or("account_encryption_not_enabled")?;
+    // 先完成上一次被中断的 rekey，保证所有文件都由当前密钥加密
+    if meta.pending_rekey.is_some() {
+        let (key, previous_key) = unlock_keys(&meta, current_passphrase)?;
+        finish_rekey(meta, &key, previous_key.as_ref())?;
+        meta = load_meta()?.ok_or("account_encryption_not_enabled")?;
+    }
+    let old_key = derive_and_verify(&meta, current_passphrase)?;
+
+    // 先写入带 rekey 标记的新元数据再改写账号文件：任何时刻崩溃，新旧口令都能解锁并续做
+    let (new_meta, new_key) = begin_rekey(&meta, &old_key, new_passphrase, KDF_ITERATIONS)?;
+    save_meta(&new_meta)?;
+
+    let source = {
+        let mut vault = VAULT.write();
+        vault.previous_key = Some(old_key);
+        vault.key = Some(new_key);
+        vault.source.unwrap_or(KeySource::Manual)
+    };
+
+    let count = finish_rekey(new_meta, &new_key, Some(&old_key))?;
+
+    tracing::info!(
+        "[AccountCrypto] Re-encrypted {} account file(s) with new passphrase (source: {:?})",
+        count,
+        source
+    );
+    Ok(count)
+}
+
+/// 关闭加密：解密所有账号文件并删除元数据
+pub fn disable(passphrase: &str) -> Result<usize, String> {
+    let meta = load_meta()?.ok_or("account_encryption_not_enabled")?;
+    let (key, previous_key) = unlock_keys(&meta, passphrase)?;
+
+    let mut count = 0;
+    for path in list_account_files()? {
+        let mut value = read_account_value(&path)?;
+        open_value_with(&mut value, Some(&key), previous_key.as_ref())?;
+        let temp_path = stage_account_value(&path, &value)?;
+        atomic_replace_file(&temp_path, &path)?;
+        count += 1;
+    }
+
+    fs::remove_file(meta_path()?).map_err(|e| format!("failed_to_remove_encryption_meta: {}", e))?;
+    *VAULT.write() = VaultState::default();
+
+    tracing::info!("[AccountCrypto] Account encryption disabled, {} file(s) decrypted", count);
+    Ok(count)
+}
+
+pub fn status() -> Result<EncryptionStatus, String> {
+    let mut encrypted_accounts = 0;
+    let mut plaintext_accounts = 0;
+    for path in list_account_files()? {
+        match read_account_value(&path) {
+            Ok(value) if has_plaintext_secret(&value) => plaintext_accounts += 1,
+            Ok(_) => encrypted_accounts += 1,
+            Err(_) => {}
+        }
+    }
+
+    let vault = VAULT.read();
+    Ok(EncryptionStatus {
+        enabled: is_enabled(),
+        unlocked: vault.key.is_some(),
+        key_source: vault.source,
+        encrypted_accounts,
+        plaintext_accounts,
+    })
+}
+
+/// 加密单个字段值 (未启用加密时原样返回)
+pub fn seal_field(value: &str) -> Result<String, String> {
+    if value.is_empty() || crypto::is_keyed_ciphertext(value) {
+        return Ok(value.to_string());
+    }
+    let key = VAULT.read().key;
+    match key {
+        Some(key) => crypto::encrypt_with_key(&key, value),
+        None if is_enabled() => Err("account_store_locked".to_string()),
+        None => Ok(value.to_string()),
+    }
+}
+
+/// 解密单个字段值 (明文旧数据原样返回)
+pub fn open_field(value: &str) -> Result<String, String> {
+    if !crypto::is_keyed_ciphertext(value) {
+        return Ok(value.to_string());
+    }
+    let (key, previous_key) = {
+        let vault = VAULT.read();
+        (vault.key, vault.previous_key)
+    };
+    open_with(value, key.as_ref(), previous_key.as_ref())
+}
+
+fn open_with(
+    value: &str,
+    key: Option<&[u8; 32]>,
+    previous_key: Option<&[u8; 32]>,
+) -> Result<String, String> {
+    let key = key.ok_or("account_store_locked")?;
+    crypto::decrypt_with_key(key, value).or_else(|e| match previous_key {
+        Some(prev) => crypto::decrypt_with_key(prev, value),
+        None => Err(e),
+    })
+}
+
+/// 加密账号 JSON 中的敏感字段 (用于写盘)
+pub fn seal_account_value(account: &mut serde_json::Value) -> Result<(), String> {
+    for field in SECRET_FIELDS {
+        if let Some(slot) = account.get_mut("token").and_then(|t| t.get_mut(field)) {
+            if let Some(plain) = slot.as_str() {
+                *slot = serde_json::Value::String(seal_field(plain)?);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 解密账号 JSON 中的敏感字段 (用于读盘)
+pub fn open_account_value(account: &mut serde_json::Value) -> Result<(), String> {
+    let (key, previous_key) = {
+        let vault = VAULT.read();
+        (vault.key, vault.previous_key)
+    };
+    open_value_with(account, key.as_ref(), previous_key.as_ref())
+}
+
+fn seal_value_with(account: &mut serde_json::Value, key: &[u8; 32]) -> Result<(), String> {
+    for field in SECRET_FIELDS {
+        if let Some(slot) = account.get_mut("token").and_then(|t| t.get_mut(field)) {
+            if let Some(plain) = slot.as_str() {
+                if !plain.is_empty() && !crypto::is_keyed_ciphertext(plain) {
+                    *slot = serde_json::Value::String(crypto::encrypt_with_key(key, plain)?);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn open_value_with(
+    account: &mut serde_json::Value,
+    key: Option<&[u8; 32]>,
+    previous_key: Option<&[u8; 32]>,
+) -> Result<(), String> {
+    for field in SECRET_FIELDS {
+        if let Some(slot) = account.get_mut("token").and_then(|t| t.get_mut(field)) {
+            if let Some(sealed) = slot.as_str().filter(|s| crypto::is_keyed_ciphertext(s)) {
+                *slot = serde_json::Value::String(open_with(sealed, key, previous_key)?);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn has_plaintext_secret(account: &serde_json::Value) -> bool {
+    SECRET_FIELDS.iter().any(|field| {
+        account
+            .get("token")
+            .and_then(|t| t.get(*field))
+            .and_then(|v| v.as_str())
+            .map(|s| !s.is_empty() && !crypto::is_keyed_ciphertext(s))
+            .unwrap_or(false)
+    })
+}
+
+/// 将仍为明文的账号文件加密写回，返回迁移数量
+pub fn migrate_accounts() -> Result<usize, String> {
+    let key = match VAULT.read().key {
+        Some(key) => key,
+        None => return Ok(0),
+    };
+
+    let mut migrated = 0;
+    for path in list_account_files()? {
+        let mut value = match read_account_value(&path) {
+            Ok(v) => v,
+            Err(e) => {
+                tracing::warn!("[AccountCrypto] Skipping {:?} during migration: {}", path, e);
+                continue;
+            }
+        };
+        if !has_plaintext_secret(&value) {
+            continue;
+        }
+        seal_value_with(&mut value, &key)?;
+        let temp_path = stage_account_value(&path, &value)?;
+        atomic_replace_file(&temp_path, &path)?;
+        migrated += 1;
+    }
+    Ok(migrated)
+}
+
+fn list_account_files() -> Result<Vec<PathBuf>, String> {
+    let accounts_dir = get_accounts_dir()?;
+    let entries =
+        fs::read_dir(&accounts_dir).map_err(|e| format!("failed_to_read_accounts_dir: {}", e))?;
+    Ok(entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().and_then(|s| s.to_str()) == Some("json"))
+        .collect())
+}
+
+fn read_account_value(path: &PathBuf) -> Result<serde_json::Value, String> {
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("failed_to_read_account_data: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("failed_to_parse_account_data: {}", e))
+}
+
+/// 写入同目录临时文件，返回临时文件路径 (由调用方负责替换)
+fn stage_account_value(path: &PathBuf, value: &serde_json::Value) -> Result<PathBuf, String> {
+    let temp_path = path.with_extension(format!("json.tmp.{}", uuid::Uuid::new_v4()));
+    let content = serde_json::to_string_pretty(value)
+        .map_err(|e| format!("failed_to_serialize_account_data: {}", e))?;
+    if let Err(e) = fs::write(&temp_path, content) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(format!("failed_to_write_temp_account_file: {}", e));
+    }
+    Ok(temp_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open_account_value_roundtrip() {
+        let key = crypto::derive_key("passphrase", &crypto::random_salt(), 1_000);
+        let mut account = serde_json::json!({
+            "id": "acc-1",
+            "email": "user@example.com",
+            "token": {
+                "access_token": "ya29.access",
+                "refresh_token": "1//refresh",
+                "expires_in": 3600
+            }
+        });
+
+        seal_value_with(&mut account, &key).unwrap();
+        assert!(!has_plaintext_secret(&account));
+        assert_eq!(account["email"], "user@example.com");
+        assert_eq!(account["token"]["expires_in"], 3600);
+
+        // 旧密钥仍可作为回退解密 rekey 期间的并发写入
+        let new_key = crypto::derive_key("passphrase2", &crypto::random_salt(), 1_000);
+        open_value_with(&mut account, Some(&new_key), Some(&key)).unwrap();
+        assert_eq!(account["token"]["access_token"], "ya29.access");
+        assert_eq!(account["token"]["refresh_token"], "1//refresh");
+    }
+
+    #[test]
+    fn test_open_without_key_fails_for_sealed_values() {
+        let key = crypto::derive_key("passphrase", &crypto::random_salt(), 1_000);
+        let sealed = crypto::encrypt_with_key(&key, "1//refresh").unwrap();
+
+        assert!(open_with(&sealed, None, None).is_err());
+        // 明文旧数据不需要密钥
+        let mut legacy = serde_json::json!({ "token": { "refresh_token": "1//plain" } });
+        open_value_with(&mut legacy, None, None).unwrap();
+        assert_eq!(legacy["token"]["refresh_token"], "1//plain");
+    }
+
+    #[test]
+    fn test_pending_rekey_unlocks_with_either_passphrase() {
+        let (meta, old_key) = new_meta_with("old-pass", 1_000).unwrap();
+        let (pending, new_key) = begin_rekey(&meta, &old_key, "new-pass", 1_000).unwrap();
+
+        // 元数据可序列化往返，标记随之保留
+        let pending: VaultMeta =
+            serde_json::from_str(&serde_json::to_string(&pending).unwrap()).unwrap();
+        assert!(pending.pending_rekey.is_some());
+
+        for passphrase in ["new-pass", "old-pass"] {
+            let (key, previous_key) = unlock_keys(&pending, passphrase).unwrap();
+            assert_eq!(key, new_key);
+            assert_eq!(previous_key, Some(old_key));
+        }
+        assert!(unlock_keys(&pending, "wrong-pass").is_err());
+
+        // 无标记的元数据只接受当前口令
+        let (key, previous_key) = unlock_keys(&meta, "old-pass").unwrap();
+        assert_eq!((key, previous_key), (old_key, None));
+        assert!(unlock_keys(&meta, "new-pass").is_err());
+    }
+}
@@ -0,0 +1,381 @@
+//! 加密备份包 (Backup bundle)
+//!
+//! 备份包包含完整的账号记录 (标签、设备指纹、受保护模型、代理绑定)、用户令牌与应用配置，
+//! 使用口令派生密钥整体加密。明文 manifest 用于在解密前校验格式与版本，
+//! 同时在密文内保留一份副本以防被篡改。恢复时按邮箱/令牌合并，而不是直接覆盖。
+
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Account, AppConfig};
+use crate::modules::user_token_db::UserToken;
+use crate::modules::config_loader::merge_json;
+use crate::modules::{account, config, user_token_db};
+use crate::utils::crypto;
+
+pub const BACKUP_FORMAT: &str = "antigravity-backup";
+/// 当前备份格式版本；恢复时拒绝更高版本的备份包
+pub const BACKUP_VERSION: u32 = 1;
+const KDF_ITERATIONS: u32 = 600_000;
+/// 备份包中 KDF 迭代次数的上限，防止构造的备份包长时间占用 CPU
+const MAX_KDF_ITERATIONS: u32 = 10 * KDF_ITERATIONS;
+
+/// 备份清单 (明文)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub format: String,
+    pub version: u32,
+    pub app_version: String,
+    pub created_at: i64,
+    pub account_count: usize,
+    pub user_token_count: usize,
+    pub includes_config: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupKdf {
+    pub algorithm: String,
+    pub iterations: u32,
+    pub salt: String,
+}
+
+/// 备份包 (写入 .json 文件)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupBundle {
+    pub manifest: BackupManifest,
+    pub kdf: BackupKdf,
+    /// 加密后的 `BackupPayload`
+    pub payload: String,
+}
+
+/// 备份内容 (加密前)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BackupPayload {
+    manifest: BackupManifest,
+    accounts: Vec<Account>,
+    #[serde(default)]
+    current_account_id: Option<String>,
+    #[serde(default)]
+    user_tokens: Vec<UserToken>,
+    /// 以 JSON 形式保存，便于跨版本恢复时与当前配置合并
+    #[serde(default)]
+    config: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupOptions {
+    /// 为空时导出全部账号
+    #[serde(default)]
+    pub account_ids: Option<Vec<String>>,
+    #[serde(default = "default_true")]
+    pub include_user_tokens: bool,
+    #[serde(default = "default_true")]
+    pub include_config: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreOptions {
+    #[serde(default = "default_true")]
+    pub restore_user_tokens: bool,
+    /// 配置默认不恢复；恢复时也始终保留本机的监听端口与 API Key
+    #[serde(default)]
+    pub restore_config: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for BackupOptions {
+    fn default() -> Self {
+        Self {
+            account_ids: None,
+            include_user_tokens: true,
+            include_config: true,
+        }
+    }
+}
+
+impl Default for RestoreOptions {
+    fn default() -> Self {
+        Self {
+            restore_user_tokens: true,
+            restore_config: false,
+        }
+    }
+}
+
+/// 恢复结果统计
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RestoreReport {
+    pub backup_version: u32,
+    pub backup_app_version: String,
+    pub accounts_created: usize,
+    pub accounts_merged: usize,
+    pub user_tokens_created: usize,
+    pub user_tokens_skipped: usize,
+    pub config_restored: bool,
+    pub errors: Vec<String>,
+}
+
+/// 创建加密备份包
+pub fn create_backup(passphrase: &str, options: &BackupOptions) -> Result<BackupBundle, String> {
+    if passphrase.trim().is_empty() {
+        return Err("backup_passphrase_empty".to_string());
+    }
+
+    let accounts: Vec<Account> = account::list_accounts()?
+        .into_iter()
+        .filter(|acc| {
+            options
+                .account_ids
+                .as_ref()
+                .map(|ids| ids.is_empty() || ids.contains(&acc.id))
+                .unwrap_or(true)
+        })
+        .collect();
+
+    let user_tokens = if options.include_user_tokens {
+        user_token_db::list_tokens()?
+    } else {
+        Vec::new()
+    };
+
+    let config = if options.include_config {
+        let mut value = serde_json::to_value(config::load_app_config()?)
+            .map_err(|e| format!("failed_to_serialize_config: {}", e))?;
+        make_secrets_portable(&mut value);
+        Some(value)
+    } else {
+        None
+    };
+
+    let manifest = BackupManifest {
+        format: BACKUP_FORMAT.to_string(),
+        version: BACKUP_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: chrono::Utc::now().timestamp(),
+        account_count: accounts.len(),
+        user_token_count: user_tokens.len(),
+        includes_config: config.is_some(),
+    };
+
+    let payload = BackupPayload {
+        manifest: manifest.clone(),
+        accounts,
+        current_account_id: account::get_current_account_id().ok().flatten(),
+        user_tokens,
+        config,
+    };
+    let plaintext = serde_json::to_string(&payload)
+        .map_err(|e| format!("failed_to_serialize_backup: {}", e))?;
+
+    let salt = crypto::random_salt();
+    let key = crypto::derive_key(passphrase, &salt, KDF_ITERATIONS);
+
+    Ok(BackupBundle {
+        manifest,
+        kdf: BackupKdf {
+            algorithm: "pbkdf2-sha256".to_string(),
+            iterations: KDF_ITERATIONS,
+            salt: general_purpose::STANDARD.encode(salt),
+        },
+        payload: crypto::encrypt_with_key(&key, &plaintext)?,
+    })
+}
+
+/// 校验备份清单 (解密前)
+pub fn validate_manifest(manifest: &BackupManifest) -> Result<(), String> {
+    if manifest.format != BACKUP_FORMAT {
+        return Err(format!("backup_format_unknown: {}", manifest.format));
+    }
+    if manifest.version == 0 || manifest.version > BACKUP_VERSION {
+        return Err(format!(
+            "backup_version_unsupported: bundle v{} (created by {}), supported up to v{}",
+            manifest.version, manifest.app_version, BACKUP_VERSION
+        ));
+    }
+    Ok(())
+}
+
+fn decrypt_payload(bundle: &BackupBundle, passphrase: &str) -> Result<BackupPayload, String> {
+    validate_manifest(&bundle.manifest)?;
+    if bundle.kdf.algorithm != "pbkdf2-sha256" {
+        return Err(format!("backup_kdf_unsupported: {}", bundle.kdf.algorithm));
+    }
+    if bundle.kdf.iterations == 0 || bundle.kdf.iterations > MAX_KDF_ITERATIONS {
+        return Err(format!(
+            "backup_kdf_iterations_invalid: {} (max {})",
+            bundle.kdf.iterations, MAX_KDF_ITERATIONS
+        ));
+    }
+
+    let salt = general_purpose::STANDARD
+        .decode(&bundle.kdf.salt)
+        .map_err(|e| format!("backup_salt_invalid: {}", e))?;
+    let key = crypto::derive_key(passphrase, &salt, bundle.kdf.iterations);
+    let plaintext = crypto::decrypt_with_key(&key, &bundle.payload)
+        .map_err(|_| "invalid_backup_passphrase".to_string())?;
+
+    let payload: BackupPayload = serde_json::from_str(&plaintext)
+        .map_err(|e| format!("backup_payload_invalid: {}", e))?;
+    if payload.manifest != bundle.manifest {
+        return Err("backup_manifest_mismatch".to_string());
+    }
+    Ok(payload)
+}
+
+/// 解密并合并恢复备份包
+pub fn restore_backup(
+    bundle: &BackupBundle,
+    passphrase: &str,
+    options: &RestoreOptions,
+) -> Result<RestoreReport, String> {
+    let payload = decrypt_payload(bundle, passphrase)?;
+    let mut report = RestoreReport {
+        backup_version: payload.manifest.version,
+        backup_app_version: payload.manifest.app_version.clone(),
+        ..Default::default()
+    };
+
+    for incoming in payload.accounts {
+        let email = incoming.email.clone();
+        match account::restore_account_record(incoming) {
+            Ok(true) => report.accounts_created += 1,
+            Ok(false) => report.accounts_merged += 1,
+            Err(e) => report.errors.push(format!("account {}: {}", email, e)),
+        }
+    }
+
+    if let Some(current_id) = payload.current_account_id {
+        // 仅在本机尚无当前账号时沿用备份中的选择
+        if account::get_current_account_id().ok().flatten().is_none()
+            && account::load_account(&current_id).is_ok()
+        {
+            let _ = account::set_current_account_id(&current_id);
+        }
+    }
+
+    if options.restore_user_tokens {
+        let existing = user_token_db::list_tokens()?;
+        for token in payload.user_tokens {
+            if existing.iter().any(|t| t.token == token.token || t.id == token.id) {
+                report.user_tokens_skipped += 1;
+                continue;
+            }
+            match user_token_db::insert_token(&token) {
+                Ok(()) => report.user_tokens_created += 1,
+                Err(e) => report.errors.push(format!("user token {}: {}", token.username, e)),
+            }
+        }
+    }
+
+    if options.restore_config {
+        if let Some(backup_config) = payload.config {
+            let merged = merge_backup_config(config::load_app_config()?, backup_config)?;
+            config::save_app_config(&merged)?;
+            report.config_restored = true;
+        }
+    }
+
+    crate::modules::log_bridge::emit_accounts_refreshed();
+    tracing::info!(
+        "[Backup] Restored bundle v{} from {}: {} created, {} merged, {} token(s) created",
+        report.backup_version,
+        report.backup_app_version,
+        report.accounts_created,
+        report.accounts_merged,
+        report.user_tokens_created
+    );
+    Ok(report)
+}
+
+/// 将备份中的配置合并到本机配置，保留本机的监听端口与 API Key
+fn merge_backup_config(
+    local: AppConfig,
+    backup_config: serde_json::Value,
+) -> Result<AppConfig, String> {
+    let mut current =
+        serde_json::to_value(&local).map_err(|e| format!("failed_to_serialize_config: {}", e))?;
+    merge_json(&mut current, backup_config);
+    let mut merged: AppConfig =
+        serde_json::from_value(current).map_err(|e| format!("backup_config_invalid: {}", e))?;
+    merged.proxy.port = local.proxy.port;
+    merged.proxy.api_key = local.proxy.api_key;
+    Ok(merged)
+}
+
+/// 设备密钥加密的字段 (`ag_enc_`) 无法在其他机器解密，导出前还原为明文 (整体仍由备份口令加密)
+fn make_secrets_portable(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::String(s) if s.starts_with("ag_enc_") => {
+            if let Ok(plain) = crypto::decrypt_string(s) {
+                *s = plain;
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(make_secrets_portable),
+        serde_json::Value::Object(map) => map.values_mut().for_each(make_secrets_portable),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(version: u32) -> BackupManifest {
+        BackupManifest {
+            format: BACKUP_FORMAT.to_string(),
+            version,
+            app_version: "9.9.9".to_string(),
+            created_at: 0,
+            account_count: 0,
+            user_token_count: 0,
+            includes_config: false,
+        }
+    }
+
+    #[test]
+    fn test_validate_manifest_rejects_newer_versions() {
+        assert!(validate_manifest(&manifest(BACKUP_VERSION)).is_ok());
+        assert!(validate_manifest(&manifest(BACKUP_VERSION + 1)).is_err());
+
+        let mut unknown = manifest(BACKUP_VERSION);
+        unknown.format = "something-else".to_string();
+        assert!(validate_manifest(&unknown).is_err());
+    }
+
+    #[test]
+    fn test_rejects_excessive_kdf_iterations() {
+        let bundle = BackupBundle {
+            manifest: manifest(BACKUP_VERSION),
+            kdf: BackupKdf {
+                algorithm: "pbkdf2-sha256".to_string(),
+                iterations: u32::MAX,
+                salt: general_purpose::STANDARD.encode([0u8; 16]),
+            },
+            payload: String::new(),
+        };
+        let err = decrypt_payload(&bundle, "passphrase").unwrap_err();
+        assert!(err.starts_with("backup_kdf_iterations_invalid"), "{}", err);
+    }
+
+    #[test]
+    fn test_restore_config_keeps_local_port_and_api_key() {
+        let mut local = AppConfig::new();
+        local.proxy.port = 8045;
+        local.proxy.api_key = "sk-local".to_string();
+        let backup = serde_json::json!({
+            "language": "en",
+            "proxy": { "port": 9000, "api_key": "sk-backup", "request_timeout": 42 }
+        });
+
+        let merged = merge_backup_config(local, backup).unwrap();
+        assert_eq!(merged.proxy.port, 8045);
+        assert_eq!(merged.proxy.api_key, "sk-local");
+        assert_eq!(merged.proxy.request_timeout, 42);
+        assert_eq!(merged.language, "en");
+    }
+}
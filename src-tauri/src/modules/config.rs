@@ -5,7 +5,7 @@ use crate::models::AppConfig;
 use super::account::get_data_dir;
 use tracing::warn;
 
-const CONFIG_FILE: &str = "gui_config.json";
+pub(crate) const CONFIG_FILE: &str = "gui_config.json";
 
 /// Load application configuration
 pub fn load_app_config() -> Result<AppConfig, String> {
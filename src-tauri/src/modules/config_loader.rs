@@ -0,0 +1,523 @@
+//! 分层配置加载 (Headless / antigravity-server)
+//!
+//! 优先级从低到高：
+//! 1. 默认值 + 数据目录中的 gui_config.json
+//! 2. `--config` 指定的 TOML / JSON 文件 (只需包含要覆盖的字段)
+//! 3. `ABV_` 前缀环境变量，双下划线表示嵌套字段，如 `ABV_PROXY__PORT=9000`、
+//!    `ABV_PROXY__SCHEDULING__MODE=CacheFirst`、`ABV_CIRCUIT_BREAKER__BACKOFF_STEPS=30,120`
+//!
+//! 合并后统一校验，未知字段、类型错误与取值错误会在启动时一次性报告。
+//! 不属于配置字段的 `ABV_*` 变量 (如 `ABV_API_KEY`、`ABV_DATA_DIR`) 会被忽略。
+
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::models::AppConfig;
+
+const ENV_PREFIX: &str = "ABV_";
+const ENV_SEPARATOR: &str = "__";
+
+/// 分层加载模式：未设置时 (桌面端) 只读取 gui_config.json；
+/// Headless 启动时记录 `--config` 路径 (可为空)，热重载据此按相同分层重新加载
+static LAYERED_SOURCE: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+/// 分层加载结果
+#[derive(Debug, Clone)]
+pub struct LayeredConfig {
+    pub config: AppConfig,
+    /// 被文件或环境变量覆盖的字段 (点分隔路径)
+    pub overridden: Vec<String>,
+}
+
+/// 覆盖来源记录，用于定位未知字段
+struct Override {
+    source: String,
+    path: Vec<String>,
+}
+
+/// 加载 gui_config.json，并依次叠加配置文件与环境变量
+pub fn load_layered_config(config_file: Option<&Path>) -> Result<LayeredConfig, String> {
+    let (base, file_layer, env) = layer_sources(config_file)?;
+    apply_layers(&base, file_layer, &env)
+}
+
+/// 读取分层加载的三个来源：gui_config.json、`--config` 文件与 (排序后的) 环境变量
+fn layer_sources(
+    config_file: Option<&Path>,
+) -> Result<(AppConfig, Option<(String, Value)>, Vec<(String, String)>), String> {
+    let base = super::config::load_app_config()?;
+    let file_layer = match config_file {
+        Some(path) => Some((path.display().to_string(), read_config_file(path)?)),
+        None => None,
+    };
+    let mut env: Vec<(String, String)> = std::env::vars_os()
+        .filter_map(|(k, v)| Some((k.into_string().ok()?, v.into_string().ok()?)))
+        .collect();
+    env.sort();
+    Ok((base, file_layer, env))
+}
+
+/// 分层加载后再应用 Headless 兼容覆盖，得到与启动时一致的运行配置
+fn headless_config(
+    base: &AppConfig,
+    file_layer: Option<(String, Value)>,
+    env: &[(String, String)],
+) -> Result<AppConfig, String> {
+    let mut config = apply_layers(base, file_layer, env)?.config;
+    apply_headless_overrides_from(&mut config, |name| {
+        env.iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.clone())
+    });
+    Ok(config)
+}
+
+/// 应用 Headless 模式下的环境变量覆盖 (LAN 访问、鉴权模式、API Key、Web UI 密码)，
+/// 返回配置是否被修改
+pub fn apply_headless_overrides(config: &mut AppConfig) -> bool {
+    apply_headless_overrides_from(config, |name| std::env::var(name).ok())
+}
+
+fn apply_headless_overrides_from(
+    config: &mut AppConfig,
+    var: impl Fn(&str) -> Option<String>,
+) -> bool {
+    use crate::proxy::ProxyAuthMode;
+
+    let mut modified = false;
+    // Headless/docker 默认允许 LAN 访问（绑定 0.0.0.0）
+    // 若设置 ABV_BIND_LOCAL_ONLY，则仅绑定 127.0.0.1
+    let bind_local_only = var("ABV_BIND_LOCAL_ONLY")
+        .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes" | "on"))
+        .unwrap_or(false);
+    if bind_local_only {
+        config.proxy.allow_lan_access = false;
+        modified = true;
+    } else {
+        config.proxy.allow_lan_access = true;
+    }
+
+    // [FIX] Force auth mode to AllExceptHealth in headless mode if it's Off or Auto
+    // This ensures Web UI login validation works properly
+    if matches!(
+        config.proxy.auth_mode,
+        ProxyAuthMode::Off | ProxyAuthMode::Auto
+    ) {
+        tracing::info!("Headless mode: Forcing auth_mode to AllExceptHealth for Web UI security");
+        config.proxy.auth_mode = ProxyAuthMode::AllExceptHealth;
+        modified = true;
+    }
+
+    // [NEW] 支持通过环境变量注入 API Key
+    // 优先级：ABV_API_KEY > API_KEY > 配置文件
+    if let Some(key) = var("ABV_API_KEY").or_else(|| var("API_KEY")) {
+        if !key.trim().is_empty() {
+            tracing::info!("Using API Key from environment variable");
+            config.proxy.api_key = key;
+            modified = true;
+        }
+    }
+
+    // [NEW] 支持通过环境变量注入 Web UI 密码
+    // 优先级：ABV_WEB_PASSWORD > WEB_PASSWORD > 配置文件
+    if let Some(pwd) = var("ABV_WEB_PASSWORD").or_else(|| var("WEB_PASSWORD")) {
+        if !pwd.trim().is_empty() {
+            tracing::info!("Using Web UI Password from environment variable");
+            config.proxy.admin_password = Some(pwd);
+            modified = true;
+        }
+    }
+
+    // [NEW] 支持通过环境变量注入鉴权模式
+    // 优先级：ABV_AUTH_MODE > AUTH_MODE > 配置文件
+    if let Some(mode_str) = var("ABV_AUTH_MODE").or_else(|| var("AUTH_MODE")) {
+        let mode = match mode_str.to_lowercase().as_str() {
+            "off" => Some(ProxyAuthMode::Off),
+            "strict" => Some(ProxyAuthMode::Strict),
+            "all_except_health" => Some(ProxyAuthMode::AllExceptHealth),
+            "auto" => Some(ProxyAuthMode::Auto),
+            _ => {
+                tracing::warn!("Invalid AUTH_MODE: {}, ignoring", mode_str);
+                None
+            }
+        };
+        if let Some(m) = mode {
+            tracing::info!("Using Auth Mode from environment variable: {:?}", m);
+            config.proxy.auth_mode = m;
+            modified = true;
+        }
+    }
+
+    modified
+}
+
+/// 记录本进程使用的分层配置来源，仅首次调用生效
+pub fn set_layered_source(config_file: Option<&Path>) {
+    let _ = LAYERED_SOURCE.set(config_file.map(Path::to_path_buf));
+}
+
+/// 本进程的 `--config` 文件路径 (未启用分层加载或未指定时为 None)
+pub fn layered_config_file() -> Option<&'static Path> {
+    LAYERED_SOURCE.get().and_then(|p| p.as_deref())
+}
+
+/// 按启动时相同的来源重新加载并校验配置
+///
+/// Headless 模式下叠加 `--config` 文件与 `ABV_*` 环境变量并重新应用 Headless 兼容覆盖
+/// (如默认允许 LAN 访问)，避免热重载丢失这些覆盖；桌面端只读取 gui_config.json。
+pub fn reload_config() -> Result<AppConfig, String> {
+    match LAYERED_SOURCE.get() {
+        Some(config_file) => {
+            let (base, file_layer, env) = layer_sources(config_file.as_deref())?;
+            headless_config(&base, file_layer, &env)
+        }
+        None => {
+            let config = super::config::load_app_config()?;
+            let errors = validate_app_config(&config);
+            if !errors.is_empty() {
+                return Err(errors.join("; "));
+            }
+            Ok(config)
+        }
+    }
+}
+
+/// 读取 TOML (按扩展名) 或 JSON 配置文件
+pub fn read_config_file(path: &Path) -> Result<Value, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("failed_to_read_config_file {}: {}", path.display(), e))?;
+    let is_toml = path
+        .extension()
+        .map(|ext| ext.eq_ignore_ascii_case("toml"))
+        .unwrap_or(false);
+    parse_config_str(&content, is_toml).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn parse_config_str(content: &str, is_toml: bool) -> Result<Value, String> {
+    let value = if is_toml {
+        let parsed: toml::Value =
+            toml::from_str(content).map_err(|e| format!("invalid TOML: {}", e))?;
+        serde_json::to_value(parsed).map_err(|e| e.to_string())?
+    } else {
+        serde_json::from_str(content).map_err(|e| format!("invalid JSON: {}", e))?
+    };
+    if !value.is_object() {
+        return Err("config file must contain a table/object at the top level".to_string());
+    }
+    Ok(value)
+}
+
+fn apply_layers(
+    base: &AppConfig,
+    file_layer: Option<(String, Value)>,
+    env: &[(String, String)],
+) -> Result<LayeredConfig, String> {
+    let mut merged = serde_json::to_value(base)
+        .map_err(|e| format!("failed_to_serialize_config: {}", e))?;
+    let mut overrides: Vec<Override> = Vec::new();
+    let mut errors: Vec<String> = Vec::new();
+
+    // Layer 2: config file
+    if let Some((source, value)) = file_layer {
+        collect_leaf_paths(&value, &mut Vec::new(), &mut |path| {
+            overrides.push(Override { source: source.clone(), path })
+        });
+        merge_json(&mut merged, value);
+    }
+
+    // Layer 3: ABV_* environment variables
+    for (key, raw) in env {
+        let Some(rest) = key.strip_prefix(ENV_PREFIX) else {
+            continue;
+        };
+        let path: Vec<String> = rest
+            .split(ENV_SEPARATOR)
+            .map(|s| s.to_ascii_lowercase())
+            .collect();
+        if merged.get(&path[0]).is_none() {
+            if path.len() > 1 {
+                errors.push(format!("{}: unknown config key `{}`", key, path.join(".")));
+            }
+            continue;
+        }
+        if path.iter().any(|s| s.is_empty()) {
+            errors.push(format!("{}: malformed key", key));
+            continue;
+        }
+
+        let slot = match path_slot(&mut merged, &path) {
+            Ok(slot) => slot,
+            Err(e) => {
+                errors.push(format!("{}: {}", key, e));
+                continue;
+            }
+        };
+        match coerce_env_value(raw, slot) {
+            Ok(value) => {
+                let mut prefix = path.clone();
+                collect_leaf_paths(&value, &mut prefix, &mut |p| {
+                    overrides.push(Override { source: key.clone(), path: p })
+                });
+                *slot = value;
+            }
+            Err(e) => errors.push(format!("{} (`{}`): {}", key, path.join("."), e)),
+        }
+    }
+
+    let config: AppConfig = match serde_json::from_value(merged) {
+        Ok(config) => config,
+        Err(e) => {
+            errors.push(format!("invalid config: {}", e));
+            return Err(format_errors(&errors));
+        }
+    };
+
+    // serde 会静默丢弃未知字段：回写一次，凡是覆盖了但不存在于结果中的路径都视为拼写错误
+    let roundtrip = serde_json::to_value(&config)
+        .map_err(|e| format!("failed_to_serialize_config: {}", e))?;
+    for o in &overrides {
+        if get_path(&roundtrip, &o.path).is_none() {
+            errors.push(format!("{}: unknown config key `{}`", o.source, o.path.join(".")));
+        }
+    }
+
+    errors.extend(validate_app_config(&config));
+    if !errors.is_empty() {
+        return Err(format_errors(&errors));
+    }
+
+    let mut overridden: Vec<String> = overrides.iter().map(|o| o.path.join(".")).collect();
+    overridden.sort();
+    overridden.dedup();
+    for path in &overridden {
+        tracing::info!("[Config] Override applied: {}", path);
+    }
+    Ok(LayeredConfig { config, overridden })
+}
+
+fn format_errors(errors: &[String]) -> String {
+    format!("invalid configuration:\n  - {}", errors.join("\n  - "))
+}
+
+/// 语义校验 (取值范围与依赖字段)
+pub fn validate_app_config(config: &AppConfig) -> Vec<String> {
+    let mut errors = Vec::new();
+    if config.refresh_interval <= 0 {
+        errors.push("refresh_interval must be greater than 0".to_string());
+    }
+    if config.sync_interval <= 0 {
+        errors.push("sync_interval must be greater than 0".to_string());
+    }
+    config.quota_protection.validate(&mut errors);
+    config.circuit_breaker.validate(&mut errors);
+    // 各配置段的校验位于 proxy::config 中对应类型的 validate()
+    config.proxy.validate(&mut errors);
+    errors
+}
+
+/// 按路径定位目标位置；缺失的字段以 Null (叶子) 或空对象 (中间层) 占位
+fn path_slot<'a>(root: &'a mut Value, path: &[String]) -> Result<&'a mut Value, String> {
+    let mut current = root;
+    for (i, segment) in path.iter().enumerate() {
+        let is_last = i + 1 == path.len();
+        current = match current {
+            Value::Object(map) => map.entry(segment.clone()).or_insert_with(|| {
+                if is_last {
+                    Value::Null
+                } else {
+                    Value::Object(Map::new())
+                }
+            }),
+            Value::Array(items) => segment
+                .parse::<usize>()
+                .ok()
+                .and_then(|idx| items.get_mut(idx))
+                .ok_or_else(|| format!("index `{}` out of range", segment))?,
+            _ => return Err(format!("`{}` is not a table", path[..i].join("."))),
+        };
+    }
+    Ok(current)
+}
+
+/// 按目标字段的现有类型解析环境变量字符串
+fn coerce_env_value(raw: &str, existing: &Value) -> Result<Value, String> {
+    let trimmed = raw.trim();
+    match existing {
+        Value::Bool(_) => parse_bool(trimmed).map(Value::Bool),
+        Value::Number(_) => parse_number(trimmed),
+        Value::String(_) => Ok(Value::String(raw.to_string())),
+        Value::Object(_) => match serde_json::from_str::<Value>(trimmed) {
+            Ok(v @ Value::Object(_)) => Ok(v),
+            _ => Err("expected a JSON object".to_string()),
+        },
+        Value::Array(items) => {
+            if trimmed.starts_with('[') {
+                return serde_json::from_str(trimmed).map_err(|e| format!("invalid JSON array: {}", e));
+            }
+            // 逗号分隔列表，元素类型参照现有第一个元素
+            let numeric = matches!(items.first(), Some(Value::Number(_)));
+            trimmed
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(|s| if numeric { parse_number(s) } else { Ok(Value::String(s.to_string())) })
+                .collect::<Result<Vec<_>, _>>()
+                .map(Value::Array)
+        }
+        // 未设置的可选字段：结构化值按 JSON 解析，其余视为字符串
+        Value::Null => match serde_json::from_str::<Value>(trimmed) {
+            Ok(v @ (Value::Object(_) | Value::Array(_) | Value::Bool(_) | Value::Null)) => Ok(v),
+            _ => Ok(Value::String(raw.to_string())),
+        },
+    }
+}
+
+fn parse_bool(s: &str) -> Result<bool, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" => Ok(false),
+        _ => Err(format!("expected a boolean, got `{}`", s)),
+    }
+}
+
+fn parse_number(s: &str) -> Result<Value, String> {
+    serde_json::from_str::<Value>(s)
+        .ok()
+        .filter(Value::is_number)
+        .ok_or_else(|| format!("expected a number, got `{}`", s))
+}
+
+/// 收集叶子路径 (数组与空对象视为叶子)
+fn collect_leaf_paths(value: &Value, prefix: &mut Vec<String>, out: &mut dyn FnMut(Vec<String>)) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (k, v) in map {
+                prefix.push(k.clone());
+                collect_leaf_paths(v, prefix, out);
+                prefix.pop();
+            }
+        }
+        // 显式置空的可选字段在回写时可能被省略，无需检查
+        Value::Null => {}
+        _ => out(prefix.clone()),
+    }
+}
+
+fn get_path<'a>(root: &'a Value, path: &[String]) -> Option<&'a Value> {
+    path.iter().try_fold(root, |current, segment| match current {
+        Value::Object(map) => map.get(segment),
+        Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+        _ => None,
+    })
+}
+
+/// 递归合并 JSON：对象逐键合并，其余类型以新值为准
+pub(crate) fn merge_json(base: &mut Value, incoming: Value) {
+    match (base, incoming) {
+        (Value::Object(base_map), Value::Object(incoming_map)) => {
+            for (k, v) in incoming_map {
+                match base_map.get_mut(&k) {
+                    Some(existing) => merge_json(existing, v),
+                    None => {
+                        base_map.insert(k, v);
+                    }
+                }
+            }
+        }
+        (base, incoming) => *base = incoming,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_env_overrides_file_and_coerces_types() {
+        let file = parse_config_str(
+            "language = \"en\"\n[proxy]\nport = 9000\n[proxy.scheduling]\nmode = \"CacheFirst\"\n",
+            true,
+        )
+        .unwrap();
+        let result = apply_layers(
+            &AppConfig::new(),
+            Some(("test.toml".to_string(), file)),
+            &env(&[
+                ("ABV_PROXY__PORT", "9100"),
+                ("ABV_CIRCUIT_BREAKER__ENABLED", "off"),
+                ("ABV_CIRCUIT_BREAKER__BACKOFF_STEPS", "30, 120"),
+                ("ABV_PROXY__ADMIN_PASSWORD", "12345"),
+                // 非配置字段的旧变量应被忽略
+                ("ABV_API_KEY", "sk-legacy"),
+                ("PATH", "/usr/bin"),
+            ]),
+        )
+        .unwrap();
+
+        let config = result.config;
+        assert_eq!(config.language, "en");
+        assert_eq!(config.proxy.port, 9100);
+        assert_eq!(
+            config.proxy.scheduling.mode,
+            crate::proxy::sticky_config::SchedulingMode::CacheFirst
+        );
+        assert!(!config.circuit_breaker.enabled);
+        assert_eq!(config.circuit_breaker.backoff_steps, vec![30, 120]);
+        assert_eq!(config.proxy.admin_password.as_deref(), Some("12345"));
+        assert!(result.overridden.contains(&"proxy.port".to_string()));
+    }
+
+    #[test]
+    fn test_reports_all_errors_at_once() {
+        let file = parse_config_str(r#"{ "proxy": { "prot": 1 } }"#, false).unwrap();
+        let err = apply_layers(
+            &AppConfig::new(),
+            Some(("test.json".to_string(), file)),
+            &env(&[
+                ("ABV_PROXY__ENABLE_LOGGING", "maybe"),
+                ("ABV_QUOTA_PROTECTION__ENABLED", "true"),
+                ("ABV_QUOTA_PROTECTION__THRESHOLD_PERCENTAGE", "0"),
+            ]),
+        )
+        .unwrap_err();
+
+        assert!(err.contains("test.json: unknown config key `proxy.prot`"), "{}", err);
+        assert!(err.contains("ABV_PROXY__ENABLE_LOGGING"), "{}", err);
+        assert!(err.contains("threshold_percentage"), "{}", err);
+    }
+
+    #[test]
+    fn test_reload_keeps_headless_lan_access() {
+        // gui_config.json 中未持久化 LAN 访问，热重载仍需保持启动时的策略
+        let mut base = AppConfig::new();
+        base.proxy.allow_lan_access = false;
+
+        let reloaded = headless_config(&base, None, &env(&[])).unwrap();
+        assert!(reloaded.proxy.allow_lan_access);
+
+        let local_only =
+            headless_config(&base, None, &env(&[("ABV_BIND_LOCAL_ONLY", "1")])).unwrap();
+        assert!(!local_only.proxy.allow_lan_access);
+    }
+
+    #[test]
+    fn test_merge_json_keeps_local_keys() {
+        let mut base = serde_json::json!({
+            "language": "zh",
+            "proxy": { "port": 8045, "custom_mapping": { "a": "x" } }
+        });
+        merge_json(
+            &mut base,
+            serde_json::json!({ "proxy": { "custom_mapping": { "b": "y" } } }),
+        );
+        assert_eq!(base["language"], "zh");
+        assert_eq!(base["proxy"]["port"], 8045);
+        assert_eq!(base["proxy"]["custom_mapping"]["a"], "x");
+        assert_eq!(base["proxy"]["custom_mapping"]["b"], "y");
+    }
+}
@@ -480,6 +480,7 @@ pub async fn start_server(port: u16, integration: crate::modules::integration::S
 }
 
 /// Start HTTP API server in background (non-blocking)
+#[cfg(feature = "desktop")]
 pub fn spawn_server(port: u16, integration: crate::modules::integration::SystemManager) {
     // Use tauri::async_runtime::spawn to ensure running within Tauri's runtime
     tauri::async_runtime::spawn(async move {
@@ -2,6 +2,27 @@ use crate::modules::{process, db, device};
 use crate::models::Account;
 use std::fs;
 
+/// 桌面构建下即 Tauri 的 AppHandle；无界面构建 (antigravity-server) 下为无法构造的占位类型，
+/// 使 `Option<AppHandle>` 形式的参数在两种构建中保持一致 (始终为 None)
+#[cfg(feature = "desktop")]
+pub type AppHandle = tauri::AppHandle;
+
+#[cfg(not(feature = "desktop"))]
+#[derive(Clone)]
+pub enum AppHandle {}
+
+/// 向前端发送事件 (无界面构建下为空操作)
+#[cfg(feature = "desktop")]
+pub fn emit_event<S: serde::Serialize + Clone>(handle: &AppHandle, event: &str, payload: S) {
+    use tauri::Emitter;
+    let _ = handle.emit(event, payload);
+}
+
+#[cfg(not(feature = "desktop"))]
+pub fn emit_event<S: serde::Serialize + Clone>(handle: &AppHandle, _event: &str, _payload: S) {
+    match *handle {}
+}
+
 pub trait SystemIntegration: Send + Sync {
     /// 当切换账号时执行的系统层操作（如杀进程、写入文件、注入数据库）
     async fn on_account_switch(&self, account: &crate::models::Account) -> Result<(), String>;
@@ -15,7 +36,8 @@ pub trait SystemIntegration: Send + Sync {
 
 /// 桌面版实现：包含完整的进程控制和 UI 同步
 pub struct DesktopIntegration {
-    pub app_handle: tauri::AppHandle,
+    #[cfg_attr(not(feature = "desktop"), allow(dead_code))]
+    pub app_handle: AppHandle,
 }
 
 impl SystemIntegration for DesktopIntegration {
@@ -56,13 +78,14 @@ impl SystemIntegration for DesktopIntegration {
         process::start_antigravity()?;
         
         // 6. 更新托盘
-        let _ = crate::modules::tray::update_tray_menus(&self.app_handle);
+        self.update_tray();
         
         Ok(())
     }
 
     fn update_tray(&self) {
-        let _ = crate::modules::tray::update_tray_menus(&self.app_handle);
+        #[cfg(feature = "desktop")]
+        crate::modules::tray::update_tray_menus(&self.app_handle);
     }
 
     fn show_notification(&self, title: &str, body: &str) {
@@ -93,7 +116,7 @@ impl SystemIntegration for HeadlessIntegration {
 /// 系统集成管理器：替代 Arc<dyn SystemIntegration> 以解决 async trait 的 dyn 兼容性问题
 #[derive(Clone)]
 pub enum SystemManager {
-    Desktop(tauri::AppHandle),
+    Desktop(AppHandle),
     Headless,
 }
 
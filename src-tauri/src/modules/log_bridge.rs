@@ -6,7 +6,7 @@ use serde::Serialize;
 use std::collections::VecDeque;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, OnceLock};
-use tauri::Emitter;
+use crate::modules::integration::{emit_event, AppHandle};
 use tracing::field::{Field, Visit};
 use tracing::{Event, Level, Subscriber};
 use tracing_subscriber::layer::Context;
@@ -22,7 +22,7 @@ static LOG_BRIDGE_ENABLED: AtomicBool = AtomicBool::new(false);
 static LOG_ID_COUNTER: AtomicU64 = AtomicU64::new(0);
 
 /// Global app handle for emitting events (set once during setup)
-static APP_HANDLE: OnceLock<tauri::AppHandle> = OnceLock::new();
+static APP_HANDLE: OnceLock<AppHandle> = OnceLock::new();
 
 /// Global log buffer for storing logs before UI connects
 static LOG_BUFFER: OnceLock<Arc<RwLock<VecDeque<LogEntry>>>> = OnceLock::new();
@@ -44,7 +44,8 @@ pub struct LogEntry {
 }
 
 /// Initialize the log bridge with app handle (call from setup)
-pub fn init_log_bridge(app_handle: tauri::AppHandle) {
+#[cfg(feature = "desktop")]
+pub fn init_log_bridge(app_handle: AppHandle) {
     let _ = APP_HANDLE.set(app_handle);
     tracing::debug!("[LogBridge] Initialized with app handle");
 }
@@ -57,7 +58,7 @@ pub fn enable_log_bridge() {
     if let Some(handle) = APP_HANDLE.get() {
         let buffer = get_log_buffer().read();
         for entry in buffer.iter() {
-            let _ = handle.emit("log-event", entry.clone());
+            emit_event(handle, "log-event", entry.clone());
         }
     }
 
@@ -89,7 +90,7 @@ pub fn clear_log_buffer() {
 /// This is used by background tasks (e.g. warmup 403 handling) that cannot access AppHandle directly.
 pub fn emit_accounts_refreshed() {
     if let Some(handle) = APP_HANDLE.get() {
-        let _ = handle.emit("accounts://refreshed", ());
+        emit_event(handle, "accounts://refreshed", ());
         tracing::debug!("[LogBridge] Emitted accounts://refreshed event to frontend");
     }
 }
@@ -212,7 +213,7 @@ where
 
         // Emit to frontend
         if let Some(handle) = APP_HANDLE.get() {
-            let _ = handle.emit("log-event", entry);
+            emit_event(handle, "log-event", entry);
         }
     }
 }
@@ -221,27 +222,27 @@ where
 // Tauri Commands
 // ============================================================================
 
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub fn enable_debug_console() {
     enable_log_bridge();
 }
 
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub fn disable_debug_console() {
     disable_log_bridge();
 }
 
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub fn is_debug_console_enabled() -> bool {
     is_log_bridge_enabled()
 }
 
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub fn get_debug_console_logs() -> Vec<LogEntry> {
     get_buffered_logs()
 }
 
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub fn clear_debug_console_logs() {
     clear_log_buffer();
 }
@@ -1,12 +1,15 @@
 pub mod account;
+pub mod account_crypto;
 pub mod quota;
 pub mod config;
+pub mod config_loader;
 pub mod logger;
 pub mod db;
 pub mod process;
 pub mod oauth;
 pub mod oauth_server;
 pub mod migration;
+#[cfg(feature = "desktop")]
 pub mod tray;
 pub mod i18n;
 pub mod proxy_db;
@@ -17,6 +20,7 @@ pub mod token_stats;
 pub mod cloudflared;
 pub mod integration;
 pub mod account_service;
+pub mod backup;
 #[allow(dead_code)]
 pub mod http_api;
 pub mod cache;
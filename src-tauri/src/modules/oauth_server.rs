@@ -3,7 +3,8 @@ use tokio::net::TcpListener;
 use tokio::sync::mpsc;
 use tokio::sync::watch;
 use std::sync::{Mutex, OnceLock};
-use tauri::Url;
+use crate::modules::integration::{emit_event, AppHandle};
+use url::Url;
 use crate::modules::oauth;
 
 struct OAuthFlowState {
@@ -44,7 +45,7 @@ fn oauth_fail_html() -> &'static str {
     </html>"
 }
 
-async fn ensure_oauth_flow_prepared(app_handle: Option<tauri::AppHandle>, requested_client_key: Option<String>) -> Result<String, String> {
+async fn ensure_oauth_flow_prepared(app_handle: Option<AppHandle>, requested_client_key: Option<String>) -> Result<String, String> {
     if let Ok(mut state) = get_oauth_flow_state().lock() {
         if let Some(s) = state.as_mut() {
             if let Some(requested_key) = requested_client_key.as_ref() {
@@ -225,8 +226,7 @@ async fn ensure_oauth_flow_prepared(app_handle: Option<tauri::AppHandle>, reques
                 let _ = stream.flush().await;
 
                 if let Some(h) = app_handle {
-                    use tauri::Emitter;
-                    let _ = h.emit("oauth-callback-received", ());
+                    emit_event(&h, "oauth-callback-received", ());
                 }
                 let _ = tx.send(result).await;
             }
@@ -307,8 +307,7 @@ async fn ensure_oauth_flow_prepared(app_handle: Option<tauri::AppHandle>, reques
                 let _ = stream.flush().await;
 
                 if let Some(h) = app_handle {
-                    use tauri::Emitter;
-                    let _ = h.emit("oauth-callback-received", ());
+                    emit_event(&h, "oauth-callback-received", ());
                 }
                 let _ = tx.send(result).await;
             }
@@ -330,15 +329,14 @@ async fn ensure_oauth_flow_prepared(app_handle: Option<tauri::AppHandle>, reques
 
     // Send event to frontend (for display/copying link)
     if let Some(h) = app_handle {
-        use tauri::Emitter;
-        let _ = h.emit("oauth-url-generated", &auth_url);
+        emit_event(&h, "oauth-url-generated", &auth_url);
     }
 
     Ok(auth_url)
 }
 
 /// Pre-generate OAuth URL (does not open browser, does not block waiting for callback)
-pub async fn prepare_oauth_url(app_handle: Option<tauri::AppHandle>, oauth_client_key: Option<String>) -> Result<String, String> {
+pub async fn prepare_oauth_url(app_handle: Option<AppHandle>, oauth_client_key: Option<String>) -> Result<String, String> {
     ensure_oauth_flow_prepared(app_handle, oauth_client_key).await
 }
 
@@ -353,10 +351,11 @@ pub fn cancel_oauth_flow() {
 }
 
 /// Start OAuth flow and wait for callback, then exchange token
-pub async fn start_oauth_flow(app_handle: Option<tauri::AppHandle>, oauth_client_key: Option<String>) -> Result<oauth::TokenResponse, String> {
+pub async fn start_oauth_flow(app_handle: Option<AppHandle>, oauth_client_key: Option<String>) -> Result<oauth::TokenResponse, String> {
     // Ensure URL + listener are ready (this way if the user authorizes first, it won't get stuck)
     let auth_url = ensure_oauth_flow_prepared(app_handle.clone(), oauth_client_key).await?;
 
+    #[cfg(feature = "desktop")]
     if let Some(h) = app_handle {
         // Open default browser
         use tauri_plugin_opener::OpenerExt;
@@ -399,7 +398,7 @@ pub async fn start_oauth_flow(app_handle: Option<tauri::AppHandle>, oauth_client
 /// Завершить OAuth flow без открытия браузера.
 /// Предполагается, что пользователь открыл ссылку вручную (или ранее была открыта),
 /// а мы только ждём callback и обмениваем code на token.
-pub async fn complete_oauth_flow(app_handle: Option<tauri::AppHandle>) -> Result<oauth::TokenResponse, String> {
+pub async fn complete_oauth_flow(app_handle: Option<AppHandle>) -> Result<oauth::TokenResponse, String> {
     // Ensure URL + listeners exist
     let _ = ensure_oauth_flow_prepared(app_handle, None).await?;
 
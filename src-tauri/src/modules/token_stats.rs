@@ -30,6 +30,9 @@ pub struct TokenStatsSummary {
     pub total_tokens: u64,
     pub total_requests: u64,
     pub unique_accounts: u64,
+    /// 思考 Token (单独统计，不计入 total_tokens)
+    #[serde(default)]
+    pub total_reasoning_tokens: u64,
 }
 
 /// Per-model token statistics
@@ -40,6 +43,8 @@ pub struct ModelTokenStats {
     pub total_output_tokens: u64,
     pub total_tokens: u64,
     pub request_count: u64,
+    #[serde(default)]
+    pub total_reasoning_tokens: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -122,6 +127,16 @@ pub fn init_db() -> Result<(), String> {
     )
     .map_err(|e| e.to_string())?;
 
+    // 思考 Token 列 (旧库升级，已存在时忽略错误)
+    let _ = conn.execute(
+        "ALTER TABLE token_usage ADD COLUMN reasoning_tokens INTEGER NOT NULL DEFAULT 0",
+        [],
+    );
+    let _ = conn.execute(
+        "ALTER TABLE token_stats_hourly ADD COLUMN total_reasoning_tokens INTEGER NOT NULL DEFAULT 0",
+        [],
+    );
+
     Ok(())
 }
 
@@ -131,6 +146,7 @@ pub fn record_usage(
     model: &str,
     input_tokens: u32,
     output_tokens: u32,
+    reasoning_tokens: u32,
 ) -> Result<(), String> {
     let conn = connect_db()?;
     let timestamp = chrono::Local::now().timestamp();
@@ -138,21 +154,22 @@ pub fn record_usage(
 
     // Insert into raw usage table
     conn.execute(
-        "INSERT INTO token_usage (timestamp, account_email, model, input_tokens, output_tokens, total_tokens)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-        params![timestamp, account_email, model, input_tokens, output_tokens, total_tokens],
+        "INSERT INTO token_usage (timestamp, account_email, model, input_tokens, output_tokens, total_tokens, reasoning_tokens)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![timestamp, account_email, model, input_tokens, output_tokens, total_tokens, reasoning_tokens],
     ).map_err(|e| e.to_string())?;
 
     let hour_bucket = chrono::Local::now().format("%Y-%m-%d %H:00").to_string();
     conn.execute(
-        "INSERT INTO token_stats_hourly (hour_bucket, account_email, total_input_tokens, total_output_tokens, total_tokens, request_count)
-         VALUES (?1, ?2, ?3, ?4, ?5, 1)
+        "INSERT INTO token_stats_hourly (hour_bucket, account_email, total_input_tokens, total_output_tokens, total_tokens, request_count, total_reasoning_tokens)
+         VALUES (?1, ?2, ?3, ?4, ?5, 1, ?6)
          ON CONFLICT(hour_bucket, account_email) DO UPDATE SET
             total_input_tokens = total_input_tokens + ?3,
             total_output_tokens = total_output_tokens + ?4,
             total_tokens = total_tokens + ?5,
-            request_count = request_count + 1",
-        params![hour_bucket, account_email, input_tokens, output_tokens, total_tokens],
+            request_count = request_count + 1,
+            total_reasoning_tokens = total_reasoning_tokens + ?6",
+        params![hour_bucket, account_email, input_tokens, output_tokens, total_tokens, reasoning_tokens],
     ).map_err(|e| e.to_string())?;
 
     Ok(())
@@ -320,16 +337,25 @@ pub fn get_summary_stats(hours: i64) -> Result<TokenStatsSummary, String> {
     let cutoff = chrono::Local::now() - chrono::Duration::hours(hours);
     let cutoff_bucket = cutoff.format("%Y-%m-%d %H:00").to_string();
 
-    let (total_input, total_output, total, requests): (u64, u64, u64, u64) = conn
+    let (total_input, total_output, total, requests, reasoning): (u64, u64, u64, u64, u64) = conn
         .query_row(
             "SELECT COALESCE(SUM(total_input_tokens), 0),
                 COALESCE(SUM(total_output_tokens), 0),
                 COALESCE(SUM(total_tokens), 0),
-                COALESCE(SUM(request_count), 0)
+                COALESCE(SUM(request_count), 0),
+                COALESCE(SUM(total_reasoning_tokens), 0)
          FROM token_stats_hourly 
          WHERE hour_bucket >= ?1",
             [&cutoff_bucket],
-            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                ))
+            },
         )
         .map_err(|e| e.to_string())?;
 
@@ -347,6 +373,7 @@ pub fn get_summary_stats(hours: i64) -> Result<TokenStatsSummary, String> {
         total_tokens: total,
         total_requests: requests,
         unique_accounts,
+        total_reasoning_tokens: reasoning,
     })
 }
 
@@ -360,7 +387,8 @@ pub fn get_model_stats(hours: i64) -> Result<Vec<ModelTokenStats>, String> {
                 SUM(input_tokens) as input,
                 SUM(output_tokens) as output,
                 SUM(total_tokens) as total,
-                COUNT(*) as count
+                COUNT(*) as count,
+                COALESCE(SUM(reasoning_tokens), 0) as reasoning
          FROM token_usage
          WHERE timestamp >= ?1
          GROUP BY model
@@ -376,6 +404,7 @@ pub fn get_model_stats(hours: i64) -> Result<Vec<ModelTokenStats>, String> {
                 total_output_tokens: row.get(2)?,
                 total_tokens: row.get(3)?,
                 request_count: row.get(4)?,
+                total_reasoning_tokens: row.get(5)?,
             })
         })
         .map_err(|e| e.to_string())?;
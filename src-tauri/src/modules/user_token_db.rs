@@ -163,7 +163,6 @@ pub fn create_token(
     curfew_end: Option<String>,
     custom_expires_at: Option<i64>  // 自定义过期时间戳 (秒)
 ) -> Result<UserToken, String> {
-    let conn = connect_db()?;
     let id = Uuid::new_v4().to_string();
     let token = format!("sk-{}", Uuid::new_v4().to_string().replace("-", ""));
     let now = Utc::now().timestamp();
@@ -194,6 +193,14 @@ pub fn create_token(
         total_tokens_used: 0,
     };
 
+    insert_token(&user_token)?;
+
+    Ok(user_token)
+}
+
+/// 插入完整令牌记录 (保留原 ID 与令牌值，用于备份恢复)
+pub fn insert_token(user_token: &UserToken) -> Result<(), String> {
+    let conn = connect_db()?;
     conn.execute(
         "INSERT INTO user_tokens (
             id, token, username, description, enabled, expires_type, expires_at, max_ips,
@@ -218,7 +225,7 @@ pub fn create_token(
         ],
     ).map_err(|e| format!("Failed to insert user token: {}", e))?;
 
-    Ok(user_token)
+    Ok(())
 }
 
 /// 列出所有令牌
@@ -0,0 +1,382 @@
+// 长音频分片
+//
+// 超过内联上限的音频按静音点 (16-bit PCM WAV) 或固定时长窗口 (MP3 帧边界) 切分。
+// 分片只记录原始数据中的字节区间，编码时才按需生成 Base64，避免在内存中保留多份文件副本。
+
+use base64::{engine::general_purpose, write::EncoderStringWriter};
+use std::io::Write;
+use std::ops::Range;
+
+use super::format::wav_header;
+
+/// 单个分片最大原始字节数 (Base64 后约 18.7MB，留出内联请求余量)
+pub const MAX_CHUNK_BYTES: usize = 14 * 1024 * 1024;
+/// 目标分片时长 (秒)
+pub const TARGET_CHUNK_SECS: f64 = 300.0;
+/// 在目标切点之前多长范围内寻找静音 (秒)
+const SILENCE_SEARCH_SECS: f64 = 30.0;
+/// 静音检测窗口 (秒)
+const SILENCE_WINDOW_SECS: f64 = 0.1;
+
+/// 音频分片 (原始数据中的一段字节区间)
+#[derive(Debug, Clone)]
+pub struct AudioChunk {
+    pub index: usize,
+    /// 在原音频中的起始时间 (秒)
+    pub start_secs: f64,
+    pub duration_secs: f64,
+    /// 分片前需要补充的文件头 (WAV 分片)
+    header: Vec<u8>,
+    range: Range<usize>,
+}
+
+impl AudioChunk {
+    /// 不切分的完整文件
+    pub fn whole(len: usize) -> Self {
+        Self {
+            index: 0,
+            start_secs: 0.0,
+            duration_secs: 0.0,
+            header: Vec::new(),
+            range: 0..len,
+        }
+    }
+
+    /// 编码为 Base64；文件头与数据区间直接写入编码器，不额外拷贝
+    pub fn encode_base64(&self, data: &[u8]) -> String {
+        let mut encoder = EncoderStringWriter::new(&general_purpose::STANDARD);
+        // 写入 String 不会失败
+        let _ = encoder.write_all(&self.header);
+        let _ = encoder.write_all(&data[self.range.clone()]);
+        encoder.into_inner()
+    }
+}
+
+/// 按 MIME 类型规划分片
+pub fn plan_chunks(
+    data: &[u8],
+    mime_type: &str,
+    target_secs: f64,
+    max_chunk_bytes: usize,
+) -> Result<Vec<AudioChunk>, String> {
+    match mime_type {
+        "audio/wav" => plan_wav(data, target_secs, max_chunk_bytes),
+        "audio/mp3" => plan_mp3(data, target_secs, max_chunk_bytes),
+        other => Err(format!(
+            "{} 格式暂不支持分片转录，请转换为 WAV 或 MP3 后重试",
+            other
+        )),
+    }
+}
+
+// ===== WAV =====
+
+struct WavInfo {
+    channels: u16,
+    sample_rate: u32,
+    bits_per_sample: u16,
+    block_align: usize,
+    data: Range<usize>,
+}
+
+fn parse_wav(data: &[u8]) -> Result<WavInfo, String> {
+    if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+        return Err("无效的 WAV 文件".to_string());
+    }
+
+    let mut pos = 12;
+    let mut fmt: Option<(u16, u32, u16, u16)> = None;
+    while pos + 8 <= data.len() {
+        let id = &data[pos..pos + 4];
+        let size = u32::from_le_bytes([data[pos + 4], data[pos + 5], data[pos + 6], data[pos + 7]])
+            as usize;
+        let body = pos + 8;
+        match id {
+            b"fmt " if size >= 16 && body + 16 <= data.len() => {
+                let f = &data[body..body + 16];
+                let format = u16::from_le_bytes([f[0], f[1]]);
+                if format != 1 && format != 0xFFFE {
+                    return Err(format!("不支持的 WAV 编码格式: {}", format));
+                }
+                fmt = Some((
+                    u16::from_le_bytes([f[2], f[3]]),
+                    u32::from_le_bytes([f[4], f[5], f[6], f[7]]),
+                    u16::from_le_bytes([f[12], f[13]]),
+                    u16::from_le_bytes([f[14], f[15]]),
+                ));
+            }
+            b"data" => {
+                let (channels, sample_rate, block_align, bits_per_sample) =
+                    fmt.ok_or("WAV 缺少 fmt 块")?;
+                if block_align == 0 || sample_rate == 0 {
+                    return Err("WAV fmt 块无效".to_string());
+                }
+                // 流式写出的 WAV 可能将 data 长度记为 0 或超出实际长度
+                let end = if size == 0 {
+                    data.len()
+                } else {
+                    (body + size).min(data.len())
+                };
+                return Ok(WavInfo {
+                    channels,
+                    sample_rate,
+                    bits_per_sample,
+                    block_align: block_align as usize,
+                    data: body..end,
+                });
+            }
+            _ => {}
+        }
+        pos = body + size + (size & 1);
+    }
+    Err("WAV 缺少 data 块".to_string())
+}
+
+/// 在区间内寻找能量最低的窗口，返回其中点 (按采样帧对齐)；仅支持 16-bit PCM
+fn quietest_point(data: &[u8], range: Range<usize>, wav: &WavInfo) -> Option<usize> {
+    if wav.bits_per_sample != 16 {
+        return None;
+    }
+    let bytes_per_sec = wav.sample_rate as usize * wav.block_align;
+    let window = ((SILENCE_WINDOW_SECS * bytes_per_sec as f64) as usize / wav.block_align).max(1)
+        * wav.block_align;
+
+    let mut best: Option<(u64, usize)> = None;
+    let mut pos = range.start;
+    while pos + window <= range.end {
+        let energy: u64 = data[pos..pos + window]
+            .chunks_exact(2)
+            .map(|s| i16::from_le_bytes([s[0], s[1]]).unsigned_abs() as u64)
+            .sum();
+        match best {
+            // 能量相同时取靠后的窗口，尽量保持分片接近目标时长
+            Some((lowest, _)) if lowest < energy => {}
+            _ => best = Some((energy, pos)),
+        }
+        pos += window;
+    }
+    best.map(|(_, pos)| pos + window / 2 / wav.block_align * wav.block_align)
+}
+
+fn plan_wav(
+    data: &[u8],
+    target_secs: f64,
+    max_chunk_bytes: usize,
+) -> Result<Vec<AudioChunk>, String> {
+    let wav = parse_wav(data)?;
+    let block = wav.block_align;
+    let bytes_per_sec = (wav.sample_rate as usize * block) as f64;
+    let target_bytes =
+        ((target_secs * bytes_per_sec) as usize).min(max_chunk_bytes.saturating_sub(44)) / block
+            * block;
+    if target_bytes == 0 {
+        return Err("分片大小过小".to_string());
+    }
+    let search_bytes = (SILENCE_SEARCH_SECS * bytes_per_sec) as usize / block * block;
+
+    let mut chunks = Vec::new();
+    let mut start = wav.data.start;
+    while start < wav.data.end {
+        let mut end = (start + target_bytes).min(wav.data.end);
+        if end < wav.data.end {
+            let search_start = end.saturating_sub(search_bytes).max(start + block);
+            if let Some(cut) = quietest_point(data, search_start..end, &wav) {
+                end = cut;
+            }
+        }
+        let len = end - start;
+        chunks.push(AudioChunk {
+            index: chunks.len(),
+            start_secs: (start - wav.data.start) as f64 / bytes_per_sec,
+            duration_secs: len as f64 / bytes_per_sec,
+            header: wav_header(
+                len as u32,
+                wav.sample_rate,
+                wav.channels,
+                wav.bits_per_sample,
+            ),
+            range: start..end,
+        });
+        start = end;
+    }
+    Ok(chunks)
+}
+
+// ===== MP3 =====
+
+struct Mp3Frame {
+    len: usize,
+    secs: f64,
+}
+
+/// 跳过开头的 ID3v2 标签
+fn id3v2_len(data: &[u8]) -> usize {
+    if data.len() < 10 || !data.starts_with(b"ID3") {
+        return 0;
+    }
+    let size = data[6..10]
+        .iter()
+        .fold(0usize, |acc, b| (acc << 7) | (*b & 0x7F) as usize);
+    let footer = if data[5] & 0x10 != 0 { 10 } else { 0 };
+    (10 + size + footer).min(data.len())
+}
+
+/// 解析 MPEG Layer III 帧头
+fn mp3_frame(header: &[u8]) -> Option<Mp3Frame> {
+    const BITRATES_V1: [u32; 15] = [
+        0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+    ];
+    const BITRATES_V2: [u32; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+    const SAMPLE_RATES: [u32; 3] = [44100, 48000, 32000];
+
+    if header.len() < 4 || header[0] != 0xFF || header[1] & 0xE0 != 0xE0 {
+        return None;
+    }
+    let version = (header[1] >> 3) & 0x03; // 0: MPEG2.5, 1: 保留, 2: MPEG2, 3: MPEG1
+    let layer = (header[1] >> 1) & 0x03; // 1: Layer III
+    let bitrate_idx = (header[2] >> 4) as usize;
+    let sample_rate_idx = ((header[2] >> 2) & 0x03) as usize;
+    let padding = ((header[2] >> 1) & 0x01) as usize;
+    if version == 1 || layer != 1 || bitrate_idx == 0 || bitrate_idx == 15 || sample_rate_idx == 3 {
+        return None;
+    }
+
+    let (bitrate, sample_rate, samples, coefficient) = if version == 3 {
+        (
+            BITRATES_V1[bitrate_idx],
+            SAMPLE_RATES[sample_rate_idx],
+            1152,
+            144,
+        )
+    } else {
+        let divisor = if version == 2 { 2 } else { 4 };
+        (
+            BITRATES_V2[bitrate_idx],
+            SAMPLE_RATES[sample_rate_idx] / divisor,
+            576,
+            72,
+        )
+    };
+    Some(Mp3Frame {
+        len: (coefficient * bitrate * 1000 / sample_rate) as usize + padding,
+        secs: samples as f64 / sample_rate as f64,
+    })
+}
+
+fn plan_mp3(
+    data: &[u8],
+    target_secs: f64,
+    max_chunk_bytes: usize,
+) -> Result<Vec<AudioChunk>, String> {
+    let mut chunks = Vec::new();
+    let mut pos = id3v2_len(data);
+    let mut chunk_start = pos;
+    let mut chunk_start_secs = 0.0;
+    let mut elapsed = 0.0;
+    let mut frames = 0usize;
+
+    while pos + 4 <= data.len() {
+        let Some(frame) = mp3_frame(&data[pos..pos + 4]) else {
+            // 非帧数据 (损坏字节 / 尾部标签)，逐字节重新同步
+            pos += 1;
+            continue;
+        };
+        let over_time = elapsed - chunk_start_secs + frame.secs > target_secs;
+        let over_size = pos + frame.len - chunk_start > max_chunk_bytes;
+        if pos > chunk_start && (over_time || over_size) {
+            chunks.push(AudioChunk {
+                index: chunks.len(),
+                start_secs: chunk_start_secs,
+                duration_secs: elapsed - chunk_start_secs,
+                header: Vec::new(),
+                range: chunk_start..pos,
+            });
+            chunk_start = pos;
+            chunk_start_secs = elapsed;
+        }
+        elapsed += frame.secs;
+        frames += 1;
+        pos += frame.len;
+    }
+
+    if frames == 0 {
+        return Err("无法解析 MP3 数据帧".to_string());
+    }
+    if chunk_start < data.len() {
+        chunks.push(AudioChunk {
+            index: chunks.len(),
+            start_secs: chunk_start_secs,
+            duration_secs: elapsed - chunk_start_secs,
+            header: Vec::new(),
+            range: chunk_start..data.len(),
+        });
+    }
+    Ok(chunks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine as _;
+
+    fn wav_with_silence(sample_rate: u32, loud_secs: f64, quiet_at: f64) -> Vec<u8> {
+        let total = (sample_rate as f64 * loud_secs) as usize;
+        let quiet_start = (sample_rate as f64 * quiet_at) as usize;
+        let quiet_end = quiet_start + sample_rate as usize / 2;
+        let mut pcm = Vec::with_capacity(total * 2);
+        for i in 0..total {
+            let sample: i16 = if (quiet_start..quiet_end).contains(&i) {
+                0
+            } else if i % 2 == 0 {
+                8000
+            } else {
+                -8000
+            };
+            pcm.extend_from_slice(&sample.to_le_bytes());
+        }
+        let mut wav = wav_header(pcm.len() as u32, sample_rate, 1, 16);
+        wav.extend_from_slice(&pcm);
+        wav
+    }
+
+    #[test]
+    fn test_wav_splits_on_silence() {
+        // 100 秒音频，静音位于 35 秒处，目标分片 40 秒 (静音搜索范围 10-40 秒)
+        let wav = wav_with_silence(1000, 100.0, 35.0);
+        let chunks = plan_chunks(&wav, "audio/wav", 40.0, MAX_CHUNK_BYTES).unwrap();
+
+        assert!(chunks.len() >= 3);
+        assert!((35.0..35.5).contains(&chunks[1].start_secs));
+        assert!(chunks[1].duration_secs > 39.0);
+        let total: f64 = chunks.iter().map(|c| c.duration_secs).sum();
+        assert!((total - 100.0).abs() < 1e-6);
+
+        // 每个分片都是独立可解析的 WAV
+        let decoded = general_purpose::STANDARD
+            .decode(chunks[1].encode_base64(&wav))
+            .unwrap();
+        assert_eq!(decoded.len(), 44 + chunks[1].range.len());
+        assert_eq!(parse_wav(&decoded).unwrap().data.len(), decoded.len() - 44);
+    }
+
+    #[test]
+    fn test_mp3_fixed_windows() {
+        // MPEG1 Layer III, 128kbps, 44.1kHz, 无填充: 417 字节 / 帧, 约 26ms
+        let frame_header = [0xFF, 0xFB, 0x90, 0x00];
+        let mut mp3 = b"ID3\x03\x00\x00\x00\x00\x00\x02ab".to_vec();
+        for _ in 0..100 {
+            mp3.extend_from_slice(&frame_header);
+            mp3.extend_from_slice(&[0u8; 413]);
+        }
+
+        let chunks = plan_chunks(&mp3, "audio/mp3", 1.0, MAX_CHUNK_BYTES).unwrap();
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0].range.start, 12);
+        assert!(chunks
+            .iter()
+            .all(|c| mp3[c.range.start..].starts_with(&frame_header)));
+        assert!((chunks[1].start_secs - 38.0 * 1152.0 / 44100.0).abs() < 1e-9);
+
+        assert!(plan_chunks(&mp3, "audio/ogg", 1.0, MAX_CHUNK_BYTES).is_err());
+    }
+}
@@ -0,0 +1,347 @@
+// 音频响应格式转换 (OpenAI Audio API 兼容)
+//
+// 转录 / 翻译：上游按 JSON Schema 返回带时间戳的分段，再转换为 json / text / srt / vtt / verbose_json。
+// 语音合成：上游返回 PCM (audio/L16)，封装为 WAV 或直接透传。
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// 转录 / 翻译的输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranscriptFormat {
+    Json,
+    Text,
+    Srt,
+    Vtt,
+    VerboseJson,
+}
+
+impl TranscriptFormat {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_lowercase().as_str() {
+            "" | "json" => Ok(Self::Json),
+            "text" => Ok(Self::Text),
+            "srt" => Ok(Self::Srt),
+            "vtt" => Ok(Self::Vtt),
+            "verbose_json" => Ok(Self::VerboseJson),
+            other => Err(format!(
+                "不支持的 response_format: {} (可选 json, text, srt, verbose_json, vtt)",
+                other
+            )),
+        }
+    }
+
+    /// 是否需要上游返回分段时间戳
+    pub fn needs_segments(&self) -> bool {
+        matches!(self, Self::Srt | Self::Vtt | Self::VerboseJson)
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Json | Self::VerboseJson => "application/json",
+            Self::Text => "text/plain; charset=utf-8",
+            Self::Srt => "application/x-subrip; charset=utf-8",
+            Self::Vtt => "text/vtt; charset=utf-8",
+        }
+    }
+}
+
+/// 带时间戳的片段 (秒)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TimedText {
+    #[serde(default)]
+    pub start: f64,
+    #[serde(default)]
+    pub end: f64,
+    #[serde(default, alias = "word")]
+    pub text: String,
+}
+
+/// 上游返回的结构化转录结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Transcript {
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub segments: Vec<TimedText>,
+    #[serde(default)]
+    pub words: Vec<TimedText>,
+}
+
+impl Transcript {
+    /// 解析上游输出；非 JSON 时整体作为单个片段
+    pub fn parse(raw: &str) -> Self {
+        let trimmed = raw.trim();
+        let body = trimmed
+            .strip_prefix("```json")
+            .or_else(|| trimmed.strip_prefix("```"))
+            .and_then(|s| s.strip_suffix("```"))
+            .unwrap_or(trimmed)
+            .trim();
+
+        match serde_json::from_str::<Transcript>(body) {
+            Ok(mut transcript) => {
+                transcript.segments.retain(|s| !s.text.trim().is_empty());
+                transcript
+            }
+            Err(_) => Transcript {
+                language: None,
+                segments: vec![TimedText {
+                    start: 0.0,
+                    end: 0.0,
+                    text: trimmed.to_string(),
+                }],
+                words: Vec::new(),
+            },
+        }
+    }
+
+    /// 追加后续分片的结果，时间戳加上分片起点偏移
+    pub fn append(&mut self, other: Transcript, offset_secs: f64) {
+        let shift = |mut t: TimedText| {
+            t.start += offset_secs;
+            t.end += offset_secs;
+            t
+        };
+        if self.language.is_none() {
+            self.language = other.language;
+        }
+        self.segments.extend(other.segments.into_iter().map(shift));
+        self.words.extend(other.words.into_iter().map(shift));
+    }
+
+    pub fn text(&self) -> String {
+        self.segments
+            .iter()
+            .map(|s| s.text.trim())
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.segments
+            .iter()
+            .chain(self.words.iter())
+            .map(|s| s.end)
+            .fold(0.0, f64::max)
+    }
+}
+
+/// 要求上游按分段输出的 responseSchema
+pub fn transcript_schema(include_words: bool) -> Value {
+    let timed = json!({
+        "type": "OBJECT",
+        "properties": {
+            "start": {"type": "NUMBER", "description": "Start time in seconds"},
+            "end": {"type": "NUMBER", "description": "End time in seconds"},
+            "text": {"type": "STRING"}
+        },
+        "required": ["start", "end", "text"]
+    });
+    let mut properties = json!({
+        "language": {"type": "STRING", "description": "ISO-639-1 code of the spoken language"},
+        "segments": {"type": "ARRAY", "items": timed}
+    });
+    if include_words {
+        properties["words"] = json!({"type": "ARRAY", "items": timed});
+    }
+    json!({
+        "type": "OBJECT",
+        "properties": properties,
+        "required": ["segments"]
+    })
+}
+
+fn format_timestamp(seconds: f64, decimal_sep: char) -> String {
+    let total_ms = (seconds.max(0.0) * 1000.0).round() as u64;
+    let (h, rem) = (total_ms / 3_600_000, total_ms % 3_600_000);
+    let (m, rem) = (rem / 60_000, rem % 60_000);
+    let (s, ms) = (rem / 1000, rem % 1000);
+    format!("{:02}:{:02}:{:02}{}{:03}", h, m, s, decimal_sep, ms)
+}
+
+pub fn to_srt(segments: &[TimedText]) -> String {
+    segments
+        .iter()
+        .enumerate()
+        .map(|(i, seg)| {
+            format!(
+                "{}\n{} --> {}\n{}\n",
+                i + 1,
+                format_timestamp(seg.start, ','),
+                format_timestamp(seg.end, ','),
+                seg.text.trim()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn to_vtt(segments: &[TimedText]) -> String {
+    let mut out = String::from("WEBVTT\n\n");
+    for seg in segments {
+        out.push_str(&format!(
+            "{} --> {}\n{}\n\n",
+            format_timestamp(seg.start, '.'),
+            format_timestamp(seg.end, '.'),
+            seg.text.trim()
+        ));
+    }
+    out
+}
+
+/// OpenAI verbose_json 响应体
+pub fn to_verbose_json(
+    task: &str,
+    transcript: &Transcript,
+    language: Option<&str>,
+    include_words: bool,
+) -> Value {
+    let segments: Vec<Value> = transcript
+        .segments
+        .iter()
+        .enumerate()
+        .map(|(i, seg)| {
+            json!({
+                "id": i,
+                "seek": 0,
+                "start": seg.start,
+                "end": seg.end,
+                "text": seg.text.trim(),
+                "tokens": [],
+                "temperature": 0.0,
+                "avg_logprob": 0.0,
+                "compression_ratio": 0.0,
+                "no_speech_prob": 0.0
+            })
+        })
+        .collect();
+
+    let mut body = json!({
+        "task": task,
+        "language": language.or(transcript.language.as_deref()).unwrap_or("unknown"),
+        "duration": transcript.duration(),
+        "text": transcript.text(),
+        "segments": segments
+    });
+    if include_words {
+        body["words"] = json!(transcript
+            .words
+            .iter()
+            .map(|w| json!({"word": w.text.trim(), "start": w.start, "end": w.end}))
+            .collect::<Vec<_>>());
+    }
+    body
+}
+
+// ===== 语音合成 =====
+
+/// OpenAI 音色 -> Gemini 预置音色；已是 Gemini 音色名时原样使用
+pub fn map_voice(voice: &str) -> String {
+    let mapped = match voice.to_lowercase().as_str() {
+        "alloy" => "Kore",
+        "ash" => "Orus",
+        "ballad" => "Algieba",
+        "coral" => "Leda",
+        "echo" => "Charon",
+        "fable" => "Puck",
+        "nova" => "Aoede",
+        "onyx" => "Fenrir",
+        "sage" => "Sulafat",
+        "shimmer" => "Zephyr",
+        "verse" => "Achird",
+        _ => return voice.to_string(),
+    };
+    mapped.to_string()
+}
+
+/// 从 `audio/L16;codec=pcm;rate=24000` 中解析采样率
+pub fn pcm_sample_rate(mime_type: &str) -> u32 {
+    mime_type
+        .split(';')
+        .filter_map(|p| p.trim().strip_prefix("rate="))
+        .find_map(|r| r.parse().ok())
+        .unwrap_or(24_000)
+}
+
+/// 生成 PCM WAV 文件头 (44 字节)
+pub fn wav_header(data_len: u32, sample_rate: u32, channels: u16, bits_per_sample: u16) -> Vec<u8> {
+    let byte_rate = sample_rate * channels as u32 * bits_per_sample as u32 / 8;
+    let block_align = channels * bits_per_sample / 8;
+
+    let mut header = Vec::with_capacity(44);
+    header.extend_from_slice(b"RIFF");
+    header.extend_from_slice(&(36 + data_len).to_le_bytes());
+    header.extend_from_slice(b"WAVEfmt ");
+    header.extend_from_slice(&16u32.to_le_bytes());
+    header.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    header.extend_from_slice(&channels.to_le_bytes());
+    header.extend_from_slice(&sample_rate.to_le_bytes());
+    header.extend_from_slice(&byte_rate.to_le_bytes());
+    header.extend_from_slice(&block_align.to_le_bytes());
+    header.extend_from_slice(&bits_per_sample.to_le_bytes());
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&data_len.to_le_bytes());
+    header
+}
+
+/// 将 16-bit 单声道 PCM 封装为 WAV
+pub fn pcm_to_wav(pcm: &[u8], sample_rate: u32) -> Vec<u8> {
+    let mut wav = wav_header(pcm.len() as u32, sample_rate, 1, 16);
+    wav.extend_from_slice(pcm);
+    wav
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subtitle_formats() {
+        let transcript = Transcript::parse(
+            "```json\n{\"language\":\"en\",\"segments\":[{\"start\":0,\"end\":1.5,\"text\":\"Hello\"},{\"start\":61.25,\"end\":3725.004,\"text\":\" world \"}]}\n```",
+        );
+        assert_eq!(transcript.language.as_deref(), Some("en"));
+        assert_eq!(transcript.text(), "Hello world");
+        assert_eq!(transcript.duration(), 3725.004);
+
+        let srt = to_srt(&transcript.segments);
+        assert!(srt.starts_with("1\n00:00:00,000 --> 00:00:01,500\nHello\n"));
+        assert!(srt.contains("2\n00:01:01,250 --> 01:02:05,004\nworld\n"));
+
+        let vtt = to_vtt(&transcript.segments);
+        assert!(vtt.starts_with("WEBVTT\n\n00:00:00.000 --> 00:00:01.500\nHello"));
+
+        let mut merged = Transcript::default();
+        merged.append(
+            Transcript::parse("{\"segments\":[{\"start\":1,\"end\":2,\"text\":\"a\"}]}"),
+            0.0,
+        );
+        merged.append(transcript.clone(), 300.0);
+        assert_eq!(merged.segments[1].start, 300.0);
+        assert_eq!(merged.language.as_deref(), Some("en"));
+        assert_eq!(merged.text(), "a Hello world");
+
+        let verbose = to_verbose_json("transcribe", &transcript, None, false);
+        assert_eq!(verbose["language"], "en");
+        assert_eq!(verbose["segments"][1]["id"], 1);
+        assert!(verbose.get("words").is_none());
+    }
+
+    #[test]
+    fn test_plain_text_fallback_and_wav() {
+        let transcript = Transcript::parse("just text");
+        assert_eq!(transcript.text(), "just text");
+        assert!(TranscriptFormat::parse("mp3").is_err());
+        assert_eq!(TranscriptFormat::parse("").unwrap(), TranscriptFormat::Json);
+
+        assert_eq!(pcm_sample_rate("audio/L16;codec=pcm;rate=16000"), 16_000);
+        let wav = pcm_to_wav(&[0u8; 10], 24_000);
+        assert_eq!(wav.len(), 54);
+        assert_eq!(&wav[0..4], b"RIFF");
+        assert_eq!(u32::from_le_bytes(wav[40..44].try_into().unwrap()), 10);
+        assert_eq!(map_voice("alloy"), "Kore");
+        assert_eq!(map_voice("Puck"), "Puck");
+    }
+}
@@ -1,4 +1,6 @@
-use base64::{engine::general_purpose, Engine as _};
+pub mod chunker; // 长音频分片
+pub mod format; // 转录 / 字幕 / 语音输出格式
+
 use std::path::Path;
 
 pub struct AudioProcessor;
@@ -22,12 +24,7 @@ impl AudioProcessor {
         }
     }
 
-    /// 将音频数据编码为 Base64
-    pub fn encode_to_base64(audio_data: &[u8]) -> String {
-        general_purpose::STANDARD.encode(audio_data)
-    }
-
-    /// 判断文件是否超过大小限制
+    /// 判断文件是否超过内联请求大小限制 (超过时需分片转录)
     pub fn exceeds_size_limit(size_bytes: usize) -> bool {
         const MAX_SIZE: usize = 15 * 1024 * 1024; // 15MB
         size_bytes > MAX_SIZE
@@ -58,11 +55,4 @@ mod tests {
         assert!(AudioProcessor::exceeds_size_limit(15 * 1024 * 1024 + 1)); // 刚好超过
         assert!(!AudioProcessor::exceeds_size_limit(15 * 1024 * 1024)); // 刚好等于限制
     }
-
-    #[test]
-    fn test_base64_encoding() {
-        let data = b"test audio data";
-        let encoded = AudioProcessor::encode_to_base64(data);
-        assert!(!encoded.is_empty());
-    }
 }
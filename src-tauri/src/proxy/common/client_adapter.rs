@@ -1,7 +1,10 @@
 use axum::http::HeaderMap;
 use once_cell::sync::Lazy;
-use std::sync::Arc; // [NEW] Import Arc
-use super::client_adapters::OpencodeAdapter;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::{Arc, RwLock}; // [NEW] Import Arc
+use super::client_adapters::{DeclarativeAdapter, OpencodeAdapter};
+use crate::proxy::config::{ClientAdapterConfig, ResponseQuirks};
 
 /// 客户端适配器 trait
 /// 
@@ -13,6 +16,9 @@ use super::client_adapters::OpencodeAdapter;
 /// 2. **向后兼容**：未匹配到适配器的请求完全按照现有流程处理
 /// 3. **单文件修改**：客户端特定逻辑封装在各自的适配器文件中
 pub trait ClientAdapter: Send + Sync {
+    /// 适配器名称 (用于日志及路由规则中的 `client` 条件)
+    fn name(&self) -> &str;
+
     /// 判断该适配器是否匹配给定的请求
     /// 
     /// # Arguments
@@ -51,6 +57,21 @@ pub trait ClientAdapter: Send + Sync {
         // 默认不注入
     }
     
+    /// 是否需要在转发前改写请求体
+    fn rewrites_request(&self) -> bool {
+        false
+    }
+
+    /// 改写请求体 (如移除 thinking、调整工具定义)
+    fn adapt_request(&self, _body: &mut Value) {
+        // 默认不改写
+    }
+
+    /// 响应调整 (追加响应头、移除特定字段)
+    fn response_quirks(&self) -> Option<&ResponseQuirks> {
+        None
+    }
+
     /// 声明支持的协议
     /// 
     /// 用于多协议客户端（如 opencode）
@@ -61,7 +82,8 @@ pub trait ClientAdapter: Send + Sync {
 }
 
 /// 签名缓存策略
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum SignatureBufferStrategy {
     /// 默认策略（当前实现）
     Default,
@@ -93,6 +115,66 @@ pub static CLIENT_ADAPTERS: Lazy<Vec<Arc<dyn ClientAdapter>>> = Lazy::new(|| {
     ]
 });
 
+/// 配置文件中声明的适配器 (含已禁用的，便于管理接口展示)
+static CONFIGURED_ADAPTERS: Lazy<RwLock<Vec<Arc<DeclarativeAdapter>>>> =
+    Lazy::new(|| RwLock::new(Vec::new()));
+
+/// 更新声明式适配器 (配置热更新时调用)
+pub fn update_client_adapters(configs: Vec<ClientAdapterConfig>) {
+    let adapters: Vec<_> = configs
+        .into_iter()
+        .map(|config| Arc::new(DeclarativeAdapter::new(config)))
+        .collect();
+    tracing::info!(
+        "[ClientAdapter] Declarative adapters updated: {} configured",
+        adapters.len()
+    );
+    if let Ok(mut current) = CONFIGURED_ADAPTERS.write() {
+        *current = adapters;
+    }
+}
+
+/// 查找匹配请求的适配器；声明式适配器优先于内置适配器
+pub fn find_client_adapter(headers: &HeaderMap) -> Option<Arc<dyn ClientAdapter>> {
+    let configured = CONFIGURED_ADAPTERS.read().ok().and_then(|adapters| {
+        adapters
+            .iter()
+            .find(|a| a.enabled() && a.matches(headers))
+            .map(|a| a.clone() as Arc<dyn ClientAdapter>)
+    });
+    configured.or_else(|| CLIENT_ADAPTERS.iter().find(|a| a.matches(headers)).cloned())
+}
+
+/// 列出全部适配器 (管理接口)
+pub fn list_client_adapters() -> Vec<Value> {
+    let mut list: Vec<Value> = CONFIGURED_ADAPTERS
+        .read()
+        .map(|adapters| {
+            adapters
+                .iter()
+                .map(|a| {
+                    json!({
+                        "name": a.name(),
+                        "source": "config",
+                        "enabled": a.enabled(),
+                        "config": a.config()
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    list.extend(CLIENT_ADAPTERS.iter().map(|a| {
+        json!({
+            "name": a.name(),
+            "source": "builtin",
+            "enabled": true,
+            "let_it_crash": a.let_it_crash(),
+            "signature_buffer": a.signature_buffer_strategy()
+        })
+    }));
+    list
+}
+
 /// 辅助函数：从 HeaderMap 中提取 User-Agent
 pub fn get_user_agent(headers: &HeaderMap) -> Option<String> {
     headers
@@ -109,6 +191,10 @@ mod tests {
     struct TestAdapter;
     
     impl ClientAdapter for TestAdapter {
+        fn name(&self) -> &str {
+            "test"
+        }
+
         fn matches(&self, headers: &HeaderMap) -> bool {
             get_user_agent(headers)
                 .map(|ua| ua.contains("test-client"))
@@ -0,0 +1,329 @@
+use super::super::client_adapter::{get_user_agent, ClientAdapter, SignatureBufferStrategy};
+use super::super::model_mapping::wildcard_match;
+use crate::proxy::config::{ClientAdapterConfig, ResponseQuirks, ToolSchemaTweaks};
+use axum::http::{HeaderMap, HeaderValue};
+use serde_json::Value;
+
+/// 由配置文件声明的客户端适配器
+///
+/// 通过 `proxy.client_adapters` 定义匹配条件与各项开关，支持：
+/// 1. User-Agent / 请求头匹配
+/// 2. 签名缓存策略与快速失败
+/// 3. 移除 thinking 配置及历史 thinking 块
+/// 4. 工具定义调整 (移除工具、删除 Schema 字段、截断描述)
+/// 5. 响应调整 (追加响应头、移除 `reasoning_content`)
+pub struct DeclarativeAdapter {
+    config: ClientAdapterConfig,
+}
+
+impl DeclarativeAdapter {
+    pub fn new(config: ClientAdapterConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &ClientAdapterConfig {
+        &self.config
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+}
+
+/// 不区分大小写的匹配：含 `*` 时按通配符，否则按子串
+fn ua_matches(pattern: &str, value: &str) -> bool {
+    let (pattern, value) = (pattern.to_lowercase(), value.to_lowercase());
+    if pattern.contains('*') {
+        wildcard_match(&pattern, &value)
+    } else {
+        value.contains(&pattern)
+    }
+}
+
+impl ClientAdapter for DeclarativeAdapter {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn matches(&self, headers: &HeaderMap) -> bool {
+        let matcher = &self.config.matcher;
+        // 没有任何条件的适配器不匹配任何请求，避免误伤全部流量
+        if matcher.is_empty() {
+            return false;
+        }
+        if let Some(pattern) = &matcher.user_agent {
+            if !get_user_agent(headers).is_some_and(|ua| ua_matches(pattern, &ua)) {
+                return false;
+            }
+        }
+        matcher.headers.iter().all(|(name, pattern)| {
+            headers
+                .get(name.to_lowercase().as_str())
+                .and_then(|v| v.to_str().ok())
+                .is_some_and(|value| {
+                    if pattern.contains('*') {
+                        wildcard_match(pattern, value)
+                    } else {
+                        pattern == value
+                    }
+                })
+        })
+    }
+
+    fn let_it_crash(&self) -> bool {
+        self.config.let_it_crash
+    }
+
+    fn signature_buffer_strategy(&self) -> SignatureBufferStrategy {
+        self.config
+            .signature_buffer
+            .unwrap_or(SignatureBufferStrategy::Default)
+    }
+
+    fn inject_beta_headers(&self, headers: &mut HeaderMap) {
+        if self.config.beta_headers.is_empty() {
+            return;
+        }
+        // 与客户端已携带的取值合并
+        let mut values: Vec<String> = headers
+            .get("anthropic-beta")
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.split(',').map(|s| s.trim().to_string()).collect())
+            .unwrap_or_default();
+        for beta in &self.config.beta_headers {
+            if !values.contains(beta) {
+                values.push(beta.clone());
+            }
+        }
+        if let Ok(value) = HeaderValue::from_str(&values.join(",")) {
+            headers.insert("anthropic-beta", value);
+        }
+    }
+
+    fn rewrites_request(&self) -> bool {
+        let tweaks = &self.config.tool_schema;
+        self.config.strip_thinking
+            || !tweaks.drop_tools.is_empty()
+            || !tweaks.remove_keys.is_empty()
+            || tweaks.max_description_chars.is_some()
+    }
+
+    fn adapt_request(&self, body: &mut Value) {
+        if self.config.strip_thinking {
+            strip_thinking(body);
+        }
+        tweak_tools(&self.config.tool_schema, body);
+    }
+
+    fn response_quirks(&self) -> Option<&ResponseQuirks> {
+        Some(&self.config.response)
+    }
+}
+
+fn is_thinking_block(block: &Value) -> bool {
+    matches!(
+        block.get("type").and_then(|t| t.as_str()),
+        Some("thinking" | "redacted_thinking")
+    ) || block.get("thought").and_then(|t| t.as_bool()) == Some(true)
+}
+
+/// 移除三种协议中的 thinking 配置与历史 thinking 内容
+fn strip_thinking(body: &mut Value) {
+    if let Some(obj) = body.as_object_mut() {
+        obj.remove("thinking"); // Anthropic
+        obj.remove("reasoning_effort"); // OpenAI Chat
+        obj.remove("reasoning"); // OpenAI Responses
+    }
+    if let Some(config) = body
+        .get_mut("generationConfig")
+        .and_then(|g| g.as_object_mut())
+    {
+        config.remove("thinkingConfig"); // Gemini
+    }
+
+    if let Some(messages) = body.get_mut("messages").and_then(|m| m.as_array_mut()) {
+        for message in messages {
+            if let Some(obj) = message.as_object_mut() {
+                obj.remove("reasoning_content");
+            }
+            if let Some(content) = message.get_mut("content").and_then(|c| c.as_array_mut()) {
+                content.retain(|block| !is_thinking_block(block));
+            }
+        }
+    }
+    if let Some(contents) = body.get_mut("contents").and_then(|c| c.as_array_mut()) {
+        for content in contents {
+            if let Some(parts) = content.get_mut("parts").and_then(|p| p.as_array_mut()) {
+                parts.retain(|part| !is_thinking_block(part));
+            }
+        }
+    }
+}
+
+/// 工具名称: OpenAI Chat 为 `function.name`，其余为顶层 `name`
+fn tool_name(tool: &Value) -> Option<&str> {
+    tool.get("function")
+        .and_then(|f| f.get("name"))
+        .or_else(|| tool.get("name"))
+        .and_then(|n| n.as_str())
+}
+
+fn is_dropped(tweaks: &ToolSchemaTweaks, tool: &Value) -> bool {
+    tool_name(tool).is_some_and(|name| {
+        tweaks
+            .drop_tools
+            .iter()
+            .any(|pattern| wildcard_match(pattern, name))
+    })
+}
+
+/// 递归删除 Schema 字段；`properties` 下的键是参数名，不参与删除
+fn remove_schema_keys(schema: &mut Value, keys: &[String], in_properties: bool) {
+    match schema {
+        Value::Object(map) => {
+            if !in_properties {
+                map.retain(|k, _| !keys.contains(k));
+            }
+            for (key, value) in map.iter_mut() {
+                let child_in_properties = !in_properties && key == "properties";
+                remove_schema_keys(value, keys, child_in_properties);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                remove_schema_keys(item, keys, false);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn tweak_declaration(tweaks: &ToolSchemaTweaks, declaration: &mut Value) {
+    if let Some(max) = tweaks.max_description_chars {
+        if let Some(desc) = declaration.get_mut("description") {
+            if let Some(text) = desc.as_str() {
+                if text.chars().count() > max {
+                    *desc = Value::String(text.chars().take(max).collect());
+                }
+            }
+        }
+    }
+    if !tweaks.remove_keys.is_empty() {
+        for key in ["parameters", "input_schema", "parametersJsonSchema"] {
+            if let Some(schema) = declaration.get_mut(key) {
+                remove_schema_keys(schema, &tweaks.remove_keys, false);
+            }
+        }
+    }
+}
+
+fn tweak_tools(tweaks: &ToolSchemaTweaks, body: &mut Value) {
+    let Some(tools) = body.get_mut("tools").and_then(|t| t.as_array_mut()) else {
+        return;
+    };
+
+    // Gemini: tools[].functionDeclarations[]
+    for tool in tools.iter_mut() {
+        if let Some(declarations) = tool
+            .get_mut("functionDeclarations")
+            .and_then(|d| d.as_array_mut())
+        {
+            declarations.retain(|d| !is_dropped(tweaks, d));
+            for declaration in declarations {
+                tweak_declaration(tweaks, declaration);
+            }
+        }
+    }
+
+    tools.retain(|tool| !is_dropped(tweaks, tool));
+    for tool in tools.iter_mut() {
+        match tool.get_mut("function") {
+            Some(function) => tweak_declaration(tweaks, function),
+            None => tweak_declaration(tweaks, tool),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn build(config: Value) -> DeclarativeAdapter {
+        DeclarativeAdapter::new(serde_json::from_value(config).unwrap())
+    }
+
+    #[test]
+    fn test_declarative_matching_and_beta_headers() {
+        let adapter = build(json!({
+            "name": "cline",
+            "match": {"user_agent": "Cline/*", "headers": {"X-Client": "vscode*"}},
+            "signature_buffer": "fifo",
+            "beta_headers": ["context-1m-2025-08-07"]
+        }));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("user-agent", HeaderValue::from_static("cline/3.1"));
+        assert!(!adapter.matches(&headers));
+        headers.insert("x-client", HeaderValue::from_static("vscode-1.90"));
+        assert!(adapter.matches(&headers));
+        assert_eq!(
+            adapter.signature_buffer_strategy(),
+            SignatureBufferStrategy::Fifo
+        );
+
+        let mut upstream = HeaderMap::new();
+        upstream.insert("anthropic-beta", HeaderValue::from_static("tools-2024"));
+        adapter.inject_beta_headers(&mut upstream);
+        assert_eq!(
+            upstream.get("anthropic-beta").unwrap(),
+            "tools-2024,context-1m-2025-08-07"
+        );
+
+        // 没有匹配条件的适配器不匹配任何请求
+        assert!(!build(json!({"name": "empty"})).matches(&headers));
+    }
+
+    #[test]
+    fn test_request_rewrites() {
+        let adapter = build(json!({
+            "name": "agent",
+            "match": {"user_agent": "agent"},
+            "strip_thinking": true,
+            "tool_schema": {
+                "drop_tools": ["mcp__*"],
+                "remove_keys": ["$schema", "examples"],
+                "max_description_chars": 5
+            }
+        }));
+        assert!(adapter.rewrites_request());
+
+        let mut body = json!({
+            "thinking": {"type": "enabled", "budget_tokens": 1024},
+            "messages": [{"role": "assistant", "content": [
+                {"type": "thinking", "thinking": "..."},
+                {"type": "text", "text": "hi"}
+            ]}],
+            "tools": [
+                {"name": "mcp__browser", "input_schema": {}},
+                {"name": "bash", "description": "Run a shell command", "input_schema": {
+                    "$schema": "http://json-schema.org/draft-07/schema#",
+                    "properties": {"examples": {"type": "string", "examples": ["ls"]}}
+                }}
+            ]
+        });
+        adapter.adapt_request(&mut body);
+
+        assert!(body.get("thinking").is_none());
+        assert_eq!(body["messages"][0]["content"].as_array().unwrap().len(), 1);
+        let tools = body["tools"].as_array().unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0]["description"], "Run a");
+        assert!(tools[0]["input_schema"].get("$schema").is_none());
+        // 名为 examples 的参数保留，其内部的 examples 字段被移除
+        assert_eq!(
+            tools[0]["input_schema"]["properties"]["examples"],
+            json!({"type": "string"})
+        );
+    }
+}
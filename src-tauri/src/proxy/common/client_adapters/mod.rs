@@ -1,6 +1,8 @@
 // Client Adapters 模块
 // 存放各种客户端的适配器实现
 
+pub mod declarative;
 pub mod opencode;
 
+pub use declarative::DeclarativeAdapter;
 pub use opencode::OpencodeAdapter;
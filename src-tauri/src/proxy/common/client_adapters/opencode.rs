@@ -16,6 +16,10 @@ use axum::http::{HeaderMap, HeaderValue};
 pub struct OpencodeAdapter;
 
 impl ClientAdapter for OpencodeAdapter {
+    fn name(&self) -> &str {
+        "opencode"
+    }
+
     fn matches(&self, headers: &HeaderMap) -> bool {
         get_user_agent(headers)
             .map(|ua| ua.to_lowercase().contains("opencode"))
@@ -1,7 +1,10 @@
+use serde::Serialize;
 use serde_json::{json, Value};
 use once_cell::sync::Lazy;
+use std::sync::{Arc, RwLock};
 use super::tool_adapter::ToolAdapter;
-use super::tool_adapters::PencilAdapter;
+use super::tool_adapters::{DeclarativeToolAdapter, PencilAdapter};
+use crate::proxy::config::ToolAdapterConfig;
 
 /// 不被 Gemini 支持但包含重要语义信息的约束字段
 /// 这些字段将在删除前被转化为 description 提示
@@ -19,18 +22,185 @@ const CONSTRAINT_FIELDS: &[(&str, &str)] = &[
     ("format", "format"),
 ];
 
+/// 通用清洗中的有损改动类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchemaIssueKind {
+    /// $ref 被展开为定义内容 (超过递归深度的引用被丢弃)
+    RefFlattened,
+    /// 无法解析的 $ref，降级为 string
+    UnresolvedRef,
+    /// allOf 被合并，冲突字段以先出现的为准
+    AllOfMerged,
+    /// anyOf/oneOf 只保留得分最高的分支
+    UnionCollapsed,
+    /// type 数组只保留第一个非 null 类型
+    TypeNarrowed,
+    /// null 类型被移除，仅在描述中提示
+    NullableDropped,
+    /// 约束字段被移入 description
+    ConstraintMoved,
+    /// 不支持的字段被删除
+    FieldRemoved,
+    /// required 中的属性被移除 (可空或不在 properties 中)
+    RequiredDropped,
+    /// 非字符串的 enum 值被转为字符串
+    EnumStringified,
+    /// 对象节点上的 items 被并入 properties
+    ItemsMovedToProperties,
+    /// 没有标准关键字的节点被视为简写对象
+    ShorthandObject,
+}
+
+impl SchemaIssueKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::RefFlattened => "ref_flattened",
+            Self::UnresolvedRef => "unresolved_ref",
+            Self::AllOfMerged => "all_of_merged",
+            Self::UnionCollapsed => "union_collapsed",
+            Self::TypeNarrowed => "type_narrowed",
+            Self::NullableDropped => "nullable_dropped",
+            Self::ConstraintMoved => "constraint_moved",
+            Self::FieldRemoved => "field_removed",
+            Self::RequiredDropped => "required_dropped",
+            Self::EnumStringified => "enum_stringified",
+            Self::ItemsMovedToProperties => "items_moved_to_properties",
+            Self::ShorthandObject => "shorthand_object",
+        }
+    }
+}
+
+/// 清洗报告中的单条记录
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaIssue {
+    /// 原始 Schema 中的 JSON Pointer (根节点为空字符串)
+    pub path: String,
+    pub kind: SchemaIssueKind,
+    pub detail: String,
+}
+
+impl std::fmt::Display for SchemaIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let path = if self.path.is_empty() { "/" } else { &self.path };
+        write!(f, "{} {}: {}", self.kind.as_str(), path, self.detail)
+    }
+}
+
+/// Schema lint 结果 (管理接口)
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaLintResult {
+    pub tool: String,
+    /// 命中的工具适配器 (如有)
+    pub adapter: Option<String>,
+    pub cleaned: Value,
+    pub issues: Vec<SchemaIssue>,
+}
+
+fn push_issue(
+    issues: &mut Vec<SchemaIssue>,
+    path: &str,
+    kind: SchemaIssueKind,
+    detail: impl Into<String>,
+) {
+    issues.push(SchemaIssue {
+        path: path.to_string(),
+        kind,
+        detail: detail.into(),
+    });
+}
+
+fn child_path(path: &str, key: &str) -> String {
+    format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"))
+}
+
+/// 截断过长的值，避免报告体积失控
+fn preview(value: &Value) -> String {
+    let text = value.to_string();
+    if text.chars().count() > 80 {
+        format!("{}...", text.chars().take(80).collect::<String>())
+    } else {
+        text
+    }
+}
+
 /// 全局工具适配器注册表
 /// 
 /// 所有注册的适配器都会在 Schema 清洗时被检查和应用
-static TOOL_ADAPTERS: Lazy<Vec<Box<dyn ToolAdapter>>> = Lazy::new(|| {
+static TOOL_ADAPTERS: Lazy<Vec<Arc<dyn ToolAdapter>>> = Lazy::new(|| {
     vec![
-        Box::new(PencilAdapter),
+        Arc::new(PencilAdapter),
         // 未来可以轻松添加更多适配器:
         // Box::new(FilesystemAdapter),
         // Box::new(DatabaseAdapter),
     ]
 });
 
+/// 配置文件声明的工具适配器 (热更新)
+static CONFIGURED_TOOL_ADAPTERS: Lazy<RwLock<Vec<Arc<DeclarativeToolAdapter>>>> =
+    Lazy::new(|| RwLock::new(Vec::new()));
+
+/// 更新声明式工具适配器 (配置热更新时调用)
+pub fn update_tool_adapters(configs: Vec<ToolAdapterConfig>) {
+    let adapters: Vec<_> = configs
+        .into_iter()
+        .map(|config| Arc::new(DeclarativeToolAdapter::new(config)))
+        .collect();
+    tracing::info!(
+        "[ToolAdapter] Declarative adapters updated: {} configured",
+        adapters.len()
+    );
+    if let Ok(mut current) = CONFIGURED_TOOL_ADAPTERS.write() {
+        *current = adapters;
+    }
+}
+
+/// 查找匹配工具名称的适配器；声明式适配器优先于内置适配器
+pub fn find_tool_adapter(tool_name: &str) -> Option<Arc<dyn ToolAdapter>> {
+    let configured = CONFIGURED_TOOL_ADAPTERS.read().ok().and_then(|adapters| {
+        adapters
+            .iter()
+            .find(|a| a.enabled() && a.matches(tool_name))
+            .map(|a| a.clone() as Arc<dyn ToolAdapter>)
+    });
+    configured.or_else(|| TOOL_ADAPTERS.iter().find(|a| a.matches(tool_name)).cloned())
+}
+
+/// 列出全部工具适配器 (管理接口)
+pub fn list_tool_adapters() -> Vec<Value> {
+    let mut list: Vec<Value> = CONFIGURED_TOOL_ADAPTERS
+        .read()
+        .map(|adapters| {
+            adapters
+                .iter()
+                .map(|a| {
+                    json!({
+                        "name": a.name(),
+                        "source": "config",
+                        "enabled": a.enabled(),
+                        "config": a.config()
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    list.extend(TOOL_ADAPTERS.iter().map(|a| {
+        json!({
+            "name": a.name(),
+            "source": "builtin",
+            "enabled": true
+        })
+    }));
+    list
+}
+
+/// 按匹配的适配器改写上游返回的工具调用参数
+pub fn rewrite_tool_call_args(tool_name: &str, args: &mut Value) {
+    if let Some(adapter) = find_tool_adapter(tool_name) {
+        adapter.rewrite_args(args);
+    }
+}
+
 const MAX_RECURSION_DEPTH: usize = 10;
 
 /// 递归清理 JSON Schema 以符合 Gemini 接口要求
@@ -42,6 +212,13 @@ const MAX_RECURSION_DEPTH: usize = 10;
 /// 5. 将 type 字段的值转换为小写 (Gemini v1internal 要求)
 /// 6. 移除数字校验字段: multipleOf, exclusiveMinimum, exclusiveMaximum 等
 pub fn clean_json_schema(value: &mut Value) {
+    clean_json_schema_with_report(value);
+}
+
+/// 与 [`clean_json_schema`] 相同，同时返回全部有损改动的报告
+pub fn clean_json_schema_with_report(value: &mut Value) -> Vec<SchemaIssue> {
+    let mut issues = Vec::new();
+
     // 0. 预处理：展开 $ref (Schema Flattening)
     // [FIX #952] 递归收集所有层级的 $defs/definitions，而非仅从根层级提取
     let mut all_defs = serde_json::Map::new();
@@ -56,11 +233,12 @@ pub fn clean_json_schema(value: &mut Value) {
     // [FIX #952] 始终运行 flatten_refs，即使 defs 为空
     // 这样可以捕获并处理无法解析的 $ref (降级为 string 类型)
     if let Value::Object(map) = value {
-        flatten_refs(map, &all_defs, 0);
+        flatten_refs(map, &all_defs, 0, "", &mut issues);
     }
 
     // 递归清理
-    clean_json_schema_recursive(value, true, 0);
+    clean_json_schema_recursive(value, true, 0, "", &mut issues);
+    issues
 }
 
 /// 带工具适配器支持的 Schema 清洗
@@ -76,23 +254,73 @@ pub fn clean_json_schema(value: &mut Value) {
 /// 2. 执行适配器的预处理 (工具特定优化)
 /// 3. 执行通用清洗逻辑
 /// 4. 执行适配器的后处理 (最终调整)
+///
+/// 通用清洗改动了 Schema 时会连同有损改动报告记录到 [`super::schema_log`]，便于排查需要适配的工具
 pub fn clean_json_schema_for_tool(value: &mut Value, tool_name: &str) {
+    sanitize_for_tool(value, tool_name, true);
+}
+
+/// Schema lint：执行与工具声明相同的清洗流程并返回报告，不写入清洗记录
+pub fn lint_schema_for_tool(schema: &Value, tool_name: &str) -> SchemaLintResult {
+    let mut cleaned = schema.clone();
+    let (adapter, issues) = sanitize_for_tool(&mut cleaned, tool_name, false);
+    SchemaLintResult {
+        tool: tool_name.to_string(),
+        adapter,
+        cleaned,
+        issues,
+    }
+}
+
+fn sanitize_for_tool(
+    value: &mut Value,
+    tool_name: &str,
+    record: bool,
+) -> (Option<String>, Vec<SchemaIssue>) {
     // 1. 查找匹配的适配器
-    let adapter = TOOL_ADAPTERS.iter()
-        .find(|a| a.matches(tool_name));
-    
+    let adapter = find_tool_adapter(tool_name);
+
     // 2. 执行预处理
-    if let Some(adapter) = adapter {
-        let _ = adapter.pre_process(value);
+    if let Some(adapter) = &adapter {
+        if let Err(e) = adapter.pre_process(value) {
+            tracing::warn!(
+                "[ToolAdapter] '{}' pre_process for {}: {}",
+                adapter.name(),
+                tool_name,
+                e
+            );
+        }
     }
-    
+
     // 3. 执行通用清洗
-    clean_json_schema(value);
-    
+    let before = value.clone();
+    let issues = clean_json_schema_with_report(value);
+    if record && before != *value {
+        for issue in &issues {
+            tracing::debug!("[Schema-Lint] {}: {}", tool_name, issue);
+        }
+        super::schema_log::record(
+            tool_name,
+            adapter.as_ref().map(|a| a.name()),
+            &before,
+            value,
+            &issues,
+        );
+    }
+
     // 4. 执行后处理
-    if let Some(adapter) = adapter {
-        let _ = adapter.post_process(value);
+    if let Some(adapter) = &adapter {
+        if let Err(e) = adapter.post_process(value) {
+            tracing::warn!(
+                "[ToolAdapter] '{}' post_process for {}: {}",
+                adapter.name(),
+                tool_name,
+                e
+            );
+        }
     }
+
+    (adapter.map(|a| a.name().to_string()), issues)
 }
 
 /// [NEW #952] 递归收集所有层级的 $defs 和 definitions
@@ -133,9 +361,19 @@ fn flatten_refs(
     map: &mut serde_json::Map<String, Value>,
     defs: &serde_json::Map<String, Value>,
     depth: usize,
+    path: &str,
+    issues: &mut Vec<SchemaIssue>,
 ) {
     if depth > MAX_RECURSION_DEPTH {
         tracing::warn!("[Schema-Flatten] Max recursion depth reached, stopping ref expansion.");
+        if let Some(Value::String(ref_path)) = map.get("$ref") {
+            push_issue(
+                issues,
+                path,
+                SchemaIssueKind::RefFlattened,
+                format!("recursion limit reached, {} left unexpanded", ref_path),
+            );
+        }
         return;
     }
 
@@ -147,6 +385,12 @@ fn flatten_refs(
         if let Some(def_schema) = defs.get(ref_name) {
             // 将定义的内容合并到当前 map
             if let Value::Object(def_map) = def_schema {
+                push_issue(
+                    issues,
+                    path,
+                    SchemaIssueKind::RefFlattened,
+                    format!("inlined {}", ref_path),
+                );
                 for (k, v) in def_map {
                     // 仅当当前 map 没有该 key 时才插入 (避免覆盖)
                     // 但通常 $ref 节点不应该有其他属性
@@ -155,12 +399,18 @@ fn flatten_refs(
 
                 // 递归处理刚刚合并进来的内容中可能包含的 $ref
                 // 注意：由于引入了 depth 限制，循环引用不再会导致栈溢出
-                flatten_refs(map, defs, depth + 1);
+                flatten_refs(map, defs, depth + 1, path, issues);
             }
         } else {
             // [FIX #952] 无法解析的 $ref: 转换为宽松的 string 类型，避免 API 400 错误
             // 这比让请求失败要好，至少工具调用仍可进行
             map.insert("type".to_string(), serde_json::json!("string"));
+            push_issue(
+                issues,
+                path,
+                SchemaIssueKind::UnresolvedRef,
+                format!("{} degraded to string", ref_path),
+            );
             let hint = format!("(Unresolved $ref: {})", ref_path);
             let desc_val = map
                 .entry("description".to_string())
@@ -177,20 +427,27 @@ fn flatten_refs(
     }
 
     // 遍历子节点
-    for (_, v) in map.iter_mut() {
+    for (k, v) in map.iter_mut() {
+        let child = child_path(path, k);
         if let Value::Object(child_map) = v {
-            flatten_refs(child_map, defs, depth + 1);
+            flatten_refs(child_map, defs, depth + 1, &child, issues);
         } else if let Value::Array(arr) = v {
-            for item in arr {
+            for (i, item) in arr.iter_mut().enumerate() {
                 if let Value::Object(item_map) = item {
-                    flatten_refs(item_map, defs, depth + 1);
+                    flatten_refs(item_map, defs, depth + 1, &format!("{}/{}", child, i), issues);
                 }
             }
         }
     }
 }
 
-fn clean_json_schema_recursive(value: &mut Value, is_schema_node: bool, depth: usize) -> bool {
+fn clean_json_schema_recursive(
+    value: &mut Value,
+    is_schema_node: bool,
+    depth: usize,
+    path: &str,
+    issues: &mut Vec<SchemaIssue>,
+) -> bool {
     if depth > MAX_RECURSION_DEPTH {
         debug_assert!(false, "Max recursion depth reached in clean_json_schema_recursive");
         return false;
@@ -200,7 +457,7 @@ fn clean_json_schema_recursive(value: &mut Value, is_schema_node: bool, depth: u
     match value {
         Value::Object(map) => {
             // 0. [NEW] 合并 allOf
-            merge_all_of(map);
+            merge_all_of(map, path, issues);
 
             // 0.5 [NEW] 结构归一化 (Normalization)
             // 针对某些 MCP 工具（如 pencil）误用 items 定义对象属性的情况进行修复。
@@ -209,6 +466,12 @@ fn clean_json_schema_recursive(value: &mut Value, is_schema_node: bool, depth: u
             if map.get("type").and_then(|t| t.as_str()) == Some("object") || map.contains_key("properties") {
                 if let Some(items) = map.remove("items") {
                     tracing::warn!("[Schema-Normalization] Found 'items' in an Object-like node. Moving content to 'properties'.");
+                    push_issue(
+                        issues,
+                        path,
+                        SchemaIssueKind::ItemsMovedToProperties,
+                        format!("items {} merged into properties", preview(&items)),
+                    );
                     let target_props = map.entry("properties".to_string()).or_insert_with(|| json!({}));
                     if let Some(target_map) = target_props.as_object_mut() {
                         if let Some(source_map) = items.as_object() {
@@ -224,9 +487,10 @@ fn clean_json_schema_recursive(value: &mut Value, is_schema_node: bool, depth: u
             // 处理 properties (对象)
             if let Some(Value::Object(props)) = map.get_mut("properties") {
                 let mut nullable_keys = std::collections::HashSet::new();
+                let props_path = child_path(path, "properties");
                 for (k, v) in props {
                     // properties 的每一个值都必须是一个独立的 Schema 节点
-                    if clean_json_schema_recursive(v, true, depth + 1) {
+                    if clean_json_schema_recursive(v, true, depth + 1, &child_path(&props_path, k), issues) {
                         nullable_keys.insert(k.clone());
                     }
                 }
@@ -234,9 +498,19 @@ fn clean_json_schema_recursive(value: &mut Value, is_schema_node: bool, depth: u
                 if !nullable_keys.is_empty() {
                     if let Some(Value::Array(req_arr)) = map.get_mut("required") {
                         req_arr.retain(|r| {
-                            r.as_str()
+                            let keep = r
+                                .as_str()
                                 .map(|s| !nullable_keys.contains(s))
-                                .unwrap_or(true)
+                                .unwrap_or(true);
+                            if !keep {
+                                push_issue(
+                                    issues,
+                                    path,
+                                    SchemaIssueKind::RequiredDropped,
+                                    format!("{} is nullable", r),
+                                );
+                            }
+                            keep
                         });
                         if req_arr.is_empty() {
                             map.remove("required");
@@ -253,7 +527,7 @@ fn clean_json_schema_recursive(value: &mut Value, is_schema_node: bool, depth: u
             // 处理 items (数组)
             if let Some(items) = map.get_mut("items") {
                 // items 的内容必须是一个独立的 Schema 节点
-                clean_json_schema_recursive(items, true, depth + 1);
+                clean_json_schema_recursive(items, true, depth + 1, &child_path(path, "items"), issues);
 
                 // [NEW] 隐式类型注入：如果有 items 但没 type，补全为 array
                 if !map.contains_key("type") {
@@ -266,34 +540,48 @@ fn clean_json_schema_recursive(value: &mut Value, is_schema_node: bool, depth: u
                 for (k, v) in map.iter_mut() {
                     // 排除掉关键字
                     if k != "anyOf" && k != "oneOf" && k != "allOf" && k != "enum" && k != "type" {
-                        clean_json_schema_recursive(v, false, depth + 1);
+                        clean_json_schema_recursive(v, false, depth + 1, &child_path(path, k), issues);
                     }
                 }
             }
 
             // 1.5. [FIX] 递归清理 anyOf/oneOf 数组中的每个分支
             // 必须在合并逻辑之前执行，确保合并的分支已经被清洗
-            if let Some(Value::Array(any_of)) = map.get_mut("anyOf") {
-                for branch in any_of.iter_mut() {
-                    clean_json_schema_recursive(branch, true, depth + 1);
-                }
-            }
-            if let Some(Value::Array(one_of)) = map.get_mut("oneOf") {
-                for branch in one_of.iter_mut() {
-                    clean_json_schema_recursive(branch, true, depth + 1);
+            // 记录清洗前为 null 的分支 (清洗后会变为带 (nullable) 标记的 string)
+            let mut null_branches = Vec::new();
+            for keyword in ["anyOf", "oneOf"] {
+                if let Some(Value::Array(branches)) = map.get_mut(keyword) {
+                    let union_path = child_path(path, keyword);
+                    for (i, branch) in branches.iter_mut().enumerate() {
+                        if clean_json_schema_recursive(
+                            branch,
+                            true,
+                            depth + 1,
+                            &format!("{}/{}", union_path, i),
+                            issues,
+                        ) {
+                            null_branches.push((keyword, i));
+                        }
+                    }
                 }
             }
 
             // 2. [FIX #815] 处理 anyOf/oneOf 联合类型: 合并属性或择优选择分支
             let mut union_to_merge = None;
             if let Some(Value::Array(any_of)) = map.get("anyOf") {
-                union_to_merge = Some(any_of.clone());
+                union_to_merge = Some(("anyOf", any_of.clone()));
             } else if let Some(Value::Array(one_of)) = map.get("oneOf") {
-                union_to_merge = Some(one_of.clone());
+                union_to_merge = Some(("oneOf", one_of.clone()));
             }
 
-            if let Some(union_array) = union_to_merge {
+            if let Some((keyword, union_array)) = union_to_merge {
                 if let Some((best_branch, all_types)) = extract_best_schema_from_union(&union_array) {
+                    let nulls: Vec<usize> = null_branches
+                        .iter()
+                        .filter(|(k, _)| *k == keyword)
+                        .map(|(_, i)| *i)
+                        .collect();
+                    report_union_collapse(keyword, &union_array, &nulls, &best_branch, path, issues);
                     if let Value::Object(branch_obj) = best_branch {
                         // 合并分支属性到当前 map
                         for (k, v) in branch_obj {
@@ -361,6 +649,7 @@ fn clean_json_schema_recursive(value: &mut Value, is_schema_node: bool, depth: u
             if is_schema_node && !has_standard_keyword && !map.is_empty() && !is_not_schema_payload {
                 let mut properties = serde_json::Map::new();
                 let keys: Vec<String> = map.keys().cloned().collect();
+                let keys_moved = keys.clone();
                 for k in keys {
                     if let Some(v) = map.remove(&k) {
                         properties.insert(k, v);
@@ -370,9 +659,16 @@ fn clean_json_schema_recursive(value: &mut Value, is_schema_node: bool, depth: u
                 map.insert("properties".to_string(), Value::Object(properties));
                 
                 // 递归清理刚刚移动进去的属性
+                push_issue(
+                    issues,
+                    path,
+                    SchemaIssueKind::ShorthandObject,
+                    format!("keys {:?} treated as properties", keys_moved),
+                );
                 if let Some(Value::Object(props_map)) = map.get_mut("properties") {
-                    for v in props_map.values_mut() {
-                        clean_json_schema_recursive(v, true, depth + 1); 
+                    let props_path = child_path(path, "properties");
+                    for (k, v) in props_map.iter_mut() {
+                        clean_json_schema_recursive(v, true, depth + 1, &child_path(&props_path, k), issues);
                     }
                 }
             }
@@ -382,7 +678,7 @@ fn clean_json_schema_recursive(value: &mut Value, is_schema_node: bool, depth: u
             if looks_like_schema {
                 // 4. [ROBUST] 约束迁移：在被白名单过滤前，将校验项转为描述 Hint
                 // [NEW] 使用统一的约束回填函数
-                move_constraints_to_description(map);
+                move_constraints_to_description(map, path, issues);
 
                 // 5. [CRITICAL] 白名单过滤：彻底物理移除 Gemini 不支持的内容，防止 400 错误
                 let keys_to_remove: Vec<String> = map
@@ -391,7 +687,20 @@ fn clean_json_schema_recursive(value: &mut Value, is_schema_node: bool, depth: u
                     .cloned()
                     .collect();
                 for k in keys_to_remove {
-                    map.remove(&k);
+                    if let Some(removed) = map.remove(&k) {
+                        // 约束字段与联合类型已单独记录
+                        let reported = k == "anyOf"
+                            || k == "oneOf"
+                            || CONSTRAINT_FIELDS.iter().any(|(field, _)| *field == k);
+                        if !reported {
+                            push_issue(
+                                issues,
+                                &child_path(path, &k),
+                                SchemaIssueKind::FieldRemoved,
+                                format!("removed {}", preview(&removed)),
+                            );
+                        }
+                    }
                 }
 
                 // 6. [SAFETY] 处理空 Object
@@ -413,12 +722,21 @@ fn clean_json_schema_recursive(value: &mut Value, is_schema_node: bool, depth: u
 
                 if let Some(required_val) = map.get_mut("required") {
                     if let Some(req_arr) = required_val.as_array_mut() {
-                        if let Some(keys) = &valid_prop_keys {
-                            req_arr
-                                .retain(|k| k.as_str().map(|s| keys.contains(s)).unwrap_or(false));
-                        } else {
-                            req_arr.clear();
-                        }
+                        req_arr.retain(|k| {
+                            let keep = match &valid_prop_keys {
+                                Some(keys) => k.as_str().map(|s| keys.contains(s)).unwrap_or(false),
+                                None => false,
+                            };
+                            if !keep {
+                                push_issue(
+                                    issues,
+                                    path,
+                                    SchemaIssueKind::RequiredDropped,
+                                    format!("{} is not declared in properties", k),
+                                );
+                            }
+                            keep
+                        });
                     }
                 }
 
@@ -454,16 +772,31 @@ fn clean_json_schema_recursive(value: &mut Value, is_schema_node: bool, depth: u
                             }
                         }
                         Value::Array(arr) => {
-                            for item in arr {
+                            let mut dropped = Vec::new();
+                            for item in arr.iter() {
                                 if let Value::String(s) = item {
                                     let lower = s.to_lowercase();
                                     if lower == "null" {
                                         is_effectively_nullable = true;
                                     } else if selected_type.is_none() {
                                         selected_type = Some(lower);
+                                    } else {
+                                        dropped.push(lower);
                                     }
                                 }
                             }
+                            if !dropped.is_empty() {
+                                push_issue(
+                                    issues,
+                                    path,
+                                    SchemaIssueKind::TypeNarrowed,
+                                    format!(
+                                        "kept {}, dropped {}",
+                                        selected_type.as_deref().unwrap_or(fallback),
+                                        dropped.join(", ")
+                                    ),
+                                );
+                            }
                         }
                         _ => {}
                     }
@@ -473,6 +806,12 @@ fn clean_json_schema_recursive(value: &mut Value, is_schema_node: bool, depth: u
                 }
 
                 if is_effectively_nullable {
+                    push_issue(
+                        issues,
+                        path,
+                        SchemaIssueKind::NullableDropped,
+                        "null type removed, marked (nullable) in description",
+                    );
                     let desc_val = map
                         .entry("description".to_string())
                         .or_insert_with(|| Value::String("".to_string()));
@@ -488,6 +827,19 @@ fn clean_json_schema_recursive(value: &mut Value, is_schema_node: bool, depth: u
 
                 // 9. Enum 值强制转字符串
                 if let Some(Value::Array(arr)) = map.get_mut("enum") {
+                    let converted: Vec<String> = arr
+                        .iter()
+                        .filter(|item| !item.is_string())
+                        .map(|item| item.to_string())
+                        .collect();
+                    if !converted.is_empty() {
+                        push_issue(
+                            issues,
+                            path,
+                            SchemaIssueKind::EnumStringified,
+                            format!("values {} converted to strings", converted.join(", ")),
+                        );
+                    }
                     for item in arr {
                         if !item.is_string() {
                             *item = Value::String(if item.is_null() {
@@ -503,8 +855,14 @@ fn clean_json_schema_recursive(value: &mut Value, is_schema_node: bool, depth: u
         Value::Array(arr) => {
             // [FIX] 递归清理数组中的每个元素
             // 这确保了所有数组类型的值（包括但不限于 anyOf、oneOf、items、enum 等）都会被递归处理
-            for item in arr.iter_mut() {
-                clean_json_schema_recursive(item, is_schema_node, depth + 1);
+            for (i, item) in arr.iter_mut().enumerate() {
+                clean_json_schema_recursive(
+                    item,
+                    is_schema_node,
+                    depth + 1,
+                    &format!("{}/{}", path, i),
+                    issues,
+                );
             }
         }
         _ => {}
@@ -514,18 +872,28 @@ fn clean_json_schema_recursive(value: &mut Value, is_schema_node: bool, depth: u
 }
 
 /// [NEW] 合并 allOf 数组中的所有子 Schema
-fn merge_all_of(map: &mut serde_json::Map<String, Value>) {
+fn merge_all_of(
+    map: &mut serde_json::Map<String, Value>,
+    path: &str,
+    issues: &mut Vec<SchemaIssue>,
+) {
     if let Some(Value::Array(all_of)) = map.remove("allOf") {
         let mut merged_properties = serde_json::Map::new();
         let mut merged_required = std::collections::HashSet::new();
         let mut other_fields = serde_json::Map::new();
+        let mut conflicts = Vec::new();
+        let branch_count = all_of.len();
 
         for sub_schema in all_of {
             if let Value::Object(sub_map) = sub_schema {
                 // 合并属性
                 if let Some(Value::Object(props)) = sub_map.get("properties") {
                     for (k, v) in props {
-                        merged_properties.insert(k.clone(), v.clone());
+                        if let Some(previous) = merged_properties.insert(k.clone(), v.clone()) {
+                            if previous != *v {
+                                conflicts.push(format!("properties/{}", k));
+                            }
+                        }
                     }
                 }
 
@@ -540,12 +908,15 @@ fn merge_all_of(map: &mut serde_json::Map<String, Value>) {
 
                 // 合并其余字段 (第一个出现的胜出)
                 for (k, v) in sub_map {
-                    if k != "properties"
-                        && k != "required"
-                        && k != "allOf"
-                        && !other_fields.contains_key(&k)
-                    {
-                        other_fields.insert(k, v);
+                    if k == "properties" || k == "required" || k == "allOf" {
+                        continue;
+                    }
+                    match other_fields.get(&k) {
+                        Some(existing) if *existing != v => conflicts.push(k),
+                        Some(_) => {}
+                        None => {
+                            other_fields.insert(k, v);
+                        }
                     }
                 }
             }
@@ -553,11 +924,26 @@ fn merge_all_of(map: &mut serde_json::Map<String, Value>) {
 
         // 应用合并后的字段
         for (k, v) in other_fields {
-            if !map.contains_key(&k) {
-                map.insert(k, v);
+            match map.get(&k) {
+                Some(existing) if *existing != v => conflicts.push(k),
+                Some(_) => {}
+                None => {
+                    map.insert(k, v);
+                }
             }
         }
 
+        let detail = if conflicts.is_empty() {
+            format!("merged {} branches", branch_count)
+        } else {
+            format!(
+                "merged {} branches, conflicting {} kept first value",
+                branch_count,
+                conflicts.join(", ")
+            )
+        };
+        push_issue(issues, path, SchemaIssueKind::AllOfMerged, detail);
+
         if !merged_properties.is_empty() {
             let existing_props = map
                 .entry("properties".to_string())
@@ -606,7 +992,11 @@ fn append_hint_to_description(map: &mut serde_json::Map<String, Value>, hint: St
 
 /// [NEW] 将约束字段转化为 description 提示
 /// 在删除约束字段前,将其语义信息保留在描述中,让模型能够理解约束
-fn move_constraints_to_description(map: &mut serde_json::Map<String, Value>) {
+fn move_constraints_to_description(
+    map: &mut serde_json::Map<String, Value>,
+    path: &str,
+    issues: &mut Vec<SchemaIssue>,
+) {
     let mut hints = Vec::new();
     
     for (field, label) in CONSTRAINT_FIELDS {
@@ -617,6 +1007,12 @@ fn move_constraints_to_description(map: &mut serde_json::Map<String, Value>) {
                 } else {
                     val.to_string()
                 };
+                push_issue(
+                    issues,
+                    &child_path(path, field),
+                    SchemaIssueKind::ConstraintMoved,
+                    format!("{} moved to description", val_str),
+                );
                 hints.push(format!("{}: {}", label, val_str));
             }
         }
@@ -628,6 +1024,51 @@ fn move_constraints_to_description(map: &mut serde_json::Map<String, Value>) {
     }
 }
 
+/// 记录 anyOf/oneOf 择优时丢弃的分支
+fn report_union_collapse(
+    keyword: &str,
+    union_array: &[Value],
+    null_branches: &[usize],
+    best_branch: &Value,
+    path: &str,
+    issues: &mut Vec<SchemaIssue>,
+) {
+    let non_null = union_array.len() - null_branches.len();
+    let kept = get_schema_type_name(best_branch).unwrap_or_else(|| "unknown".to_string());
+    if non_null > 1 {
+        let dropped: Vec<String> = union_array
+            .iter()
+            .enumerate()
+            .filter(|(i, b)| *b != best_branch && !null_branches.contains(i))
+            .map(|(_, b)| b)
+            .map(|b| get_schema_type_name(b).unwrap_or_else(|| preview(b)))
+            .fold(Vec::new(), |mut acc, name| {
+                if !acc.contains(&name) {
+                    acc.push(name);
+                }
+                acc
+            });
+        push_issue(
+            issues,
+            &child_path(path, keyword),
+            SchemaIssueKind::UnionCollapsed,
+            format!(
+                "kept {} branch of {}, dropped {}",
+                kept,
+                union_array.len(),
+                dropped.join(", ")
+            ),
+        );
+    } else if !null_branches.is_empty() {
+        push_issue(
+            issues,
+            &child_path(path, keyword),
+            SchemaIssueKind::NullableDropped,
+            format!("null branch dropped, kept {}", kept),
+        );
+    }
+}
+
 /// [NEW] 计算 Schema 分支的复杂度得分 (用于 anyOf/oneOf 择优)
 /// 评分标准: Object (3) > Array (2) > Scalar (1) > Null (0)
 fn score_schema_option(val: &Value) -> i32 {
@@ -1571,4 +2012,82 @@ mod tests {
         // 验证描述中增加了类型提示 (注意: null 分支在清洗后变为了带 (nullable) 标记的 string，因此去重后为 string | object)
         assert!(schema["description"].as_str().unwrap().contains("Accepts: string | object"));
     }
+
+    #[test]
+    fn test_clean_report_lossy_transformations() {
+        let mut schema = json!({
+            "type": "object",
+            "$defs": { "Mode": { "type": "string", "enum": ["fast", 2] } },
+            "additionalProperties": false,
+            "properties": {
+                "mode": { "$ref": "#/$defs/Mode" },
+                "name": { "type": "string", "minLength": 3 },
+                "id": { "type": ["string", "integer"] },
+                "target": {
+                    "anyOf": [
+                        { "type": "string" },
+                        { "type": "object", "properties": { "path": { "type": "string" } } }
+                    ]
+                },
+                "opts": {
+                    "allOf": [
+                        { "properties": { "a": { "type": "string" } } },
+                        { "properties": { "b": { "type": "integer" } } }
+                    ]
+                }
+            },
+            "required": ["name", "missing"]
+        });
+
+        let issues = clean_json_schema_with_report(&mut schema);
+        let has = |kind: SchemaIssueKind, path: &str| {
+            issues.iter().any(|i| i.kind == kind && i.path == path)
+        };
+
+        assert!(has(SchemaIssueKind::RefFlattened, "/properties/mode"));
+        assert!(has(SchemaIssueKind::EnumStringified, "/properties/mode"));
+        assert!(has(SchemaIssueKind::ConstraintMoved, "/properties/name/minLength"));
+        assert!(has(SchemaIssueKind::TypeNarrowed, "/properties/id"));
+        assert!(has(SchemaIssueKind::UnionCollapsed, "/properties/target/anyOf"));
+        assert!(has(SchemaIssueKind::AllOfMerged, "/properties/opts"));
+        assert!(has(SchemaIssueKind::FieldRemoved, "/additionalProperties"));
+        assert!(has(SchemaIssueKind::RequiredDropped, ""));
+        // 约束字段只记录一次 (移入描述)，不再作为删除字段重复记录
+        assert!(!has(SchemaIssueKind::FieldRemoved, "/properties/name/minLength"));
+
+        let collapsed = issues
+            .iter()
+            .find(|i| i.kind == SchemaIssueKind::UnionCollapsed)
+            .unwrap();
+        assert_eq!(collapsed.detail, "kept object branch of 2, dropped string");
+    }
+
+    #[test]
+    fn test_clean_report_empty_for_supported_schema() {
+        let mut schema = json!({
+            "type": "object",
+            "properties": {
+                "query": { "type": "string", "description": "Search query" },
+                "tags": { "type": "array", "items": { "type": "string", "enum": ["a", "b"] } }
+            },
+            "required": ["query"]
+        });
+        assert!(clean_json_schema_with_report(&mut schema).is_empty());
+    }
+
+    #[test]
+    fn test_lint_schema_for_tool_does_not_record() {
+        let tool = "mcp__schema_lint_test__run";
+        let schema = json!({
+            "type": "object",
+            "properties": { "url": { "type": "string", "format": "uri" } }
+        });
+
+        let result = lint_schema_for_tool(&schema, tool);
+        assert_eq!(result.tool, tool);
+        assert_eq!(result.issues.len(), 1);
+        assert_eq!(result.issues[0].path, "/properties/url/format");
+        assert!(result.cleaned["properties"]["url"].get("format").is_none());
+        assert!(super::super::schema_log::list().iter().all(|e| e.tool != tool));
+    }
 }
@@ -3,11 +3,15 @@
 // pub mod error;
 // pub mod rate_limiter;
 pub mod model_mapping;
+pub mod model_info; // 模型元数据 (限额与能力)
 pub mod utils;
 pub mod json_schema;
 pub mod tool_adapter;
 pub mod tool_adapters;
 pub mod schema_cache;
+pub mod schema_log;
+pub mod schema_validate;
 pub mod client_adapter;
 pub mod client_adapters;
 pub mod session; // [ADDED v4.1.24] Tools for deriving stable session identifiers
+pub mod task_detection; // 后台任务检测 (标题/摘要/建议等)
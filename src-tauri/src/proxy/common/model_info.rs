@@ -0,0 +1,219 @@
+// 模型元数据 - 为 /v1/models 与 /v1beta/models 提供真实的限额与能力信息
+//
+// 数据来源优先级: 账号配额数据 (ModelQuota) > model_specs.json > 按模型家族兜底。
+// 自定义映射 (custom_mapping) 中的模型标记为别名，并使用映射目标的元数据。
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use super::model_mapping::{get_all_dynamic_models, peek_model_route};
+use crate::models::quota::ModelQuota;
+use crate::proxy::model_specs;
+use crate::proxy::token_manager::TokenManager;
+
+/// 模型列表中的固定创建时间 (与旧版接口保持一致)
+const MODEL_CREATED_AT: i64 = 1706745600;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelInfo {
+    pub id: String,
+    pub display_name: String,
+    /// 自定义映射别名指向的实际模型
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alias_of: Option<String>,
+    pub input_token_limit: u64,
+    pub output_token_limit: u64,
+    pub supports_images: bool,
+    pub supports_thinking: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking_budget: Option<u64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub supported_mime_types: Vec<String>,
+}
+
+impl ModelInfo {
+    /// 合并配额数据与静态规格生成元数据
+    ///
+    /// `target` 为路由后的实际模型，`quota` 为账号配额中该模型的数据 (如有)
+    pub fn build(id: &str, target: &str, alias: bool, quota: Option<&ModelQuota>) -> Self {
+        let spec = model_specs::get_spec(target);
+        let positive = |v: Option<i32>| v.filter(|n| *n > 0).map(|n| n as u64);
+
+        let supports_thinking = quota
+            .and_then(|q| q.supports_thinking)
+            .or_else(|| spec.as_ref().and_then(|s| s.is_thinking))
+            .unwrap_or_else(|| model_specs::is_thinking_model(target));
+        let thinking_budget = supports_thinking
+            .then(|| {
+                positive(quota.and_then(|q| q.thinking_budget))
+                    .unwrap_or_else(|| model_specs::get_thinking_budget(target, None))
+            })
+            .filter(|b| *b > 0);
+
+        let mut supported_mime_types: Vec<String> = quota
+            .and_then(|q| q.supported_mime_types.as_ref())
+            .map(|types| {
+                types
+                    .iter()
+                    .filter(|(_, supported)| **supported)
+                    .map(|(mime, _)| mime.clone())
+                    .collect()
+            })
+            .unwrap_or_default();
+        supported_mime_types.sort();
+
+        Self {
+            id: id.to_string(),
+            display_name: quota
+                .and_then(|q| q.display_name.clone())
+                .filter(|_| !alias)
+                .unwrap_or_else(|| id.to_string()),
+            alias_of: alias.then(|| target.to_string()),
+            input_token_limit: positive(quota.and_then(|q| q.max_tokens))
+                .unwrap_or_else(|| model_specs::get_max_input_tokens(target)),
+            output_token_limit: positive(quota.and_then(|q| q.max_output_tokens))
+                .unwrap_or_else(|| model_specs::get_max_output_tokens(target, None)),
+            supports_images: quota
+                .and_then(|q| q.supports_images)
+                .unwrap_or_else(|| !target.contains("gpt-oss")),
+            supports_thinking,
+            thinking_budget,
+            supported_mime_types,
+        }
+    }
+
+    /// OpenAI / Anthropic 兼容的 `/v1/models` 条目 (在标准字段之外附加限额与能力)
+    pub fn to_openai_json(&self) -> Value {
+        let mut entry = json!({
+            "id": self.id,
+            "object": "model",
+            "created": MODEL_CREATED_AT,
+            "owned_by": "antigravity",
+            "display_name": self.display_name,
+            "context_window": self.input_token_limit,
+            "max_output_tokens": self.output_token_limit,
+            "capabilities": {
+                "vision": self.supports_images,
+                "thinking": self.supports_thinking,
+            },
+        });
+        if let Some(budget) = self.thinking_budget {
+            entry["capabilities"]["thinking_budget"] = json!(budget);
+        }
+        if let Some(target) = &self.alias_of {
+            entry["alias_of"] = json!(target);
+        }
+        entry
+    }
+
+    /// Gemini `/v1beta/models` 条目
+    pub fn to_gemini_json(&self) -> Value {
+        let mut entry = json!({
+            "name": format!("models/{}", self.id),
+            "baseModelId": self.alias_of.as_deref().unwrap_or(&self.id),
+            "version": "001",
+            "displayName": self.display_name,
+            "description": match &self.alias_of {
+                Some(target) => format!("Alias of {}", target),
+                None => String::new(),
+            },
+            "inputTokenLimit": self.input_token_limit,
+            "outputTokenLimit": self.output_token_limit,
+            "supportedGenerationMethods": ["generateContent", "streamGenerateContent", "countTokens"],
+            "thinking": self.supports_thinking,
+            "temperature": 1.0,
+            "topP": 0.95,
+            "topK": 64
+        });
+        if !self.supported_mime_types.is_empty() {
+            entry["supportedMimeTypes"] = json!(self.supported_mime_types);
+        }
+        entry
+    }
+}
+
+/// 解析单个模型的元数据
+pub fn resolve_model_info(
+    id: &str,
+    custom_mapping: &HashMap<String, String>,
+    token_manager: Option<&TokenManager>,
+) -> ModelInfo {
+    let target = peek_model_route(id, custom_mapping);
+    let alias = custom_mapping.contains_key(id) && target != id;
+    let quota = token_manager.and_then(|tm| {
+        tm.get_model_metadata(&target)
+            .or_else(|| tm.get_model_metadata(id))
+    });
+    ModelInfo::build(id, &target, alias, quota.as_ref())
+}
+
+/// 所有可用模型的元数据 (与 `get_all_dynamic_models` 的模型集合一致)
+pub async fn list_model_info(
+    custom_mapping: &tokio::sync::RwLock<HashMap<String, String>>,
+    token_manager: Option<&TokenManager>,
+) -> Vec<ModelInfo> {
+    let model_ids = get_all_dynamic_models(custom_mapping, token_manager).await;
+    let mapping = custom_mapping.read().await;
+    model_ids
+        .iter()
+        .map(|id| resolve_model_info(id, &mapping, token_manager))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quota(value: Value) -> ModelQuota {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_build_prefers_quota_data() {
+        let q = quota(json!({
+            "name": "gemini-3-flash",
+            "percentage": 100,
+            "reset_time": "",
+            "display_name": "Gemini 3 Flash",
+            "max_tokens": 500000,
+            "max_output_tokens": 8192,
+            "supports_images": false,
+            "supported_mime_types": {"image/png": true, "video/mp4": false, "application/pdf": true}
+        }));
+        let info = ModelInfo::build("gemini-3-flash", "gemini-3-flash", false, Some(&q));
+        assert_eq!(info.display_name, "Gemini 3 Flash");
+        assert_eq!(info.input_token_limit, 500000);
+        assert_eq!(info.output_token_limit, 8192);
+        assert!(!info.supports_images);
+        // 配额中未提供的字段回退到 model_specs
+        assert!(info.supports_thinking);
+        assert_eq!(info.thinking_budget, Some(32768));
+        assert_eq!(
+            info.supported_mime_types,
+            vec!["application/pdf", "image/png"]
+        );
+
+        let gemini = info.to_gemini_json();
+        assert_eq!(gemini["inputTokenLimit"], 500000);
+        assert_eq!(gemini["thinking"], true);
+    }
+
+    #[test]
+    fn test_custom_mapping_alias() {
+        let mapping = HashMap::from([("my-model".to_string(), "claude-sonnet-4-6".to_string())]);
+        let info = resolve_model_info("my-model", &mapping, None);
+        assert_eq!(info.alias_of.as_deref(), Some("claude-sonnet-4-6"));
+        assert_eq!(info.input_token_limit, 200_000);
+        assert_eq!(info.output_token_limit, 64000);
+
+        let openai = info.to_openai_json();
+        assert_eq!(openai["alias_of"], "claude-sonnet-4-6");
+        assert_eq!(openai["context_window"], 200_000);
+        assert_eq!(info.to_gemini_json()["baseModelId"], "claude-sonnet-4-6");
+
+        let plain = resolve_model_info("gemini-3-flash", &mapping, None);
+        assert!(plain.alias_of.is_none());
+        assert!(plain.to_openai_json().get("alias_of").is_none());
+    }
+}
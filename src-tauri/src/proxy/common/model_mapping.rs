@@ -62,6 +62,14 @@ static CLAUDE_TO_GEMINI: Lazy<HashMap<&'static str, &'static str>> = Lazy::new(|
     m.insert("gpt-3.5-turbo-1106", "gemini-2.5-flash");
     m.insert("gpt-3.5-turbo-0613", "gemini-2.5-flash");
 
+    // OpenAI 音频接口映射表 (转录 / 翻译 / 语音合成)
+    m.insert("whisper-1", "gemini-2.5-flash");
+    m.insert("gpt-4o-transcribe", "gemini-2.5-flash");
+    m.insert("gpt-4o-mini-transcribe", "gemini-2.5-flash");
+    m.insert("tts-1", "gemini-2.5-flash-preview-tts");
+    m.insert("tts-1-hd", "gemini-2.5-pro-preview-tts");
+    m.insert("gpt-4o-mini-tts", "gemini-2.5-flash-preview-tts");
+
     // Gemini 协议映射表
     m.insert("gemini-2.5-flash-lite", "gemini-2.5-flash");
     m.insert("gemini-2.5-flash-thinking", "gemini-2.5-flash-thinking");
@@ -204,7 +212,7 @@ pub async fn get_all_dynamic_models(
 /// - `claude-*-sonnet-*` matches `claude-3-5-sonnet-20241022` ✓
 /// - `*-thinking` matches `claude-opus-4-5-thinking` ✓
 /// - `a*b*c` matches `a123b456c` ✓
-fn wildcard_match(pattern: &str, text: &str) -> bool {
+pub(crate) fn wildcard_match(pattern: &str, text: &str) -> bool {
     let parts: Vec<&str> = pattern.split('*').collect();
 
     // No wildcard - exact match
@@ -253,17 +261,37 @@ fn wildcard_match(pattern: &str, text: &str) -> bool {
 pub fn resolve_model_route(
     original_model: &str,
     custom_mapping: &std::collections::HashMap<String, String>,
+) -> String {
+    route_model(original_model, custom_mapping, true)
+}
+
+/// 与 `resolve_model_route` 相同，但不输出路由日志 (用于模型列表等批量查询)
+pub fn peek_model_route(
+    original_model: &str,
+    custom_mapping: &std::collections::HashMap<String, String>,
+) -> String {
+    route_model(original_model, custom_mapping, false)
+}
+
+fn route_model(
+    original_model: &str,
+    custom_mapping: &std::collections::HashMap<String, String>,
+    log: bool,
 ) -> String {
     // 0. API 热更新废弃模型转发 (最高物理优先级，强制纠正)
     // 如果用户非要用已经被移除的模型，并且官方下发了 fallback path，我们在此拦截并纠正
     if let Some(forwarded) = DYNAMIC_MODEL_FORWARDING_RULES.get(original_model) {
-        crate::modules::logger::log_info(&format!("[Router] 官方淘汰重定向: {} -> {}", original_model, forwarded.value()));
+        if log {
+            crate::modules::logger::log_info(&format!("[Router] 官方淘汰重定向: {} -> {}", original_model, forwarded.value()));
+        }
         return forwarded.value().clone();
     }
 
     // 1. 精确匹配 (次高优先级)
     if let Some(target) = custom_mapping.get(original_model) {
-        crate::modules::logger::log_info(&format!("[Router] 精确映射: {} -> {}", original_model, target));
+        if log {
+            crate::modules::logger::log_info(&format!("[Router] 精确映射: {} -> {}", original_model, target));
+        }
         return target.clone();
     }
     
@@ -283,16 +311,18 @@ pub fn resolve_model_route(
     }
 
     if let Some((pattern, target, _)) = best_match {
-        crate::modules::logger::log_info(&format!(
-            "[Router] Wildcard match: {} -> {} (rule: {})",
-            original_model, target, pattern
-        ));
+        if log {
+            crate::modules::logger::log_info(&format!(
+                "[Router] Wildcard match: {} -> {} (rule: {})",
+                original_model, target, pattern
+            ));
+        }
         return target.to_string();
     }
     
     // 3. 系统默认映射
     let result = map_claude_model_to_gemini(original_model);
-    if result != original_model {
+    if log && result != original_model {
         crate::modules::logger::log_info(&format!("[Router] 系统默认映射: {} -> {}", original_model, result));
     }
     result
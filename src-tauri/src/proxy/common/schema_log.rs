@@ -0,0 +1,185 @@
+// Schema 清洗记录 - 记录通用清洗 (`json_schema::clean_json_schema`) 改动过的工具 Schema
+//
+// 同一工具的同一份原始 Schema 只保留一条记录 (累计次数)，便于定位需要编写工具适配器的 MCP 工具。
+// 每条记录附带清洗器输出的有损改动报告 (展开的 $ref、合并的 allOf、被丢弃的联合分支等)。
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::json_schema::SchemaIssue;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+
+const MAX_ENTRIES: usize = 200;
+const MAX_CHANGES_PER_ENTRY: usize = 50;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaChangeEntry {
+    pub tool: String,
+    /// 命中的工具适配器 (如有)
+    pub adapter: Option<String>,
+    pub first_seen: i64,
+    pub last_seen: i64,
+    pub count: u64,
+    /// 改动摘要，如 `removed /properties/path/format`
+    pub changes: Vec<String>,
+    /// 清洗器报告的有损改动
+    pub issues: Vec<SchemaIssue>,
+    pub before: Value,
+    pub after: Value,
+    #[serde(skip)]
+    key: u64,
+}
+
+static SCHEMA_LOG: Lazy<Mutex<VecDeque<SchemaChangeEntry>>> =
+    Lazy::new(|| Mutex::new(VecDeque::new()));
+
+fn entry_key(tool: &str, before: &Value) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    tool.hash(&mut hasher);
+    before.to_string().hash(&mut hasher);
+    hasher.finish()
+}
+
+/// 递归比较两份 Schema，输出 JSON Pointer 形式的改动摘要
+fn diff(before: &Value, after: &Value, path: &str, out: &mut Vec<String>) {
+    if out.len() >= MAX_CHANGES_PER_ENTRY || before == after {
+        return;
+    }
+    match (before, after) {
+        (Value::Object(b), Value::Object(a)) => {
+            for (key, value) in b {
+                let child = format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"));
+                match a.get(key) {
+                    Some(new_value) => diff(value, new_value, &child, out),
+                    None => out.push(format!("removed {}", child)),
+                }
+            }
+            for key in a.keys().filter(|k| !b.contains_key(*k)) {
+                out.push(format!(
+                    "added {}/{}",
+                    path,
+                    key.replace('~', "~0").replace('/', "~1")
+                ));
+            }
+        }
+        (Value::Array(b), Value::Array(a)) if b.len() == a.len() => {
+            for (i, (old, new)) in b.iter().zip(a).enumerate() {
+                diff(old, new, &format!("{}/{}", path, i), out);
+            }
+        }
+        _ => out.push(format!("changed {}: {} -> {}", path, before, after)),
+    }
+    out.truncate(MAX_CHANGES_PER_ENTRY);
+}
+
+/// 记录一次改动；已存在的记录只更新次数与时间
+pub fn record(
+    tool: &str,
+    adapter: Option<&str>,
+    before: &Value,
+    after: &Value,
+    issues: &[SchemaIssue],
+) {
+    let key = entry_key(tool, before);
+    let now = chrono::Utc::now().timestamp();
+    let Ok(mut log) = SCHEMA_LOG.lock() else {
+        return;
+    };
+
+    if let Some(entry) = log.iter_mut().find(|e| e.key == key) {
+        entry.count += 1;
+        entry.last_seen = now;
+        return;
+    }
+
+    let mut changes = Vec::new();
+    diff(before, after, "", &mut changes);
+    tracing::debug!(
+        "[SchemaLog] Sanitizer altered schema of '{}': {} change(s), {} lossy issue(s)",
+        tool,
+        changes.len(),
+        issues.len()
+    );
+    if log.len() >= MAX_ENTRIES {
+        log.pop_front();
+    }
+    log.push_back(SchemaChangeEntry {
+        tool: tool.to_string(),
+        adapter: adapter.map(str::to_string),
+        first_seen: now,
+        last_seen: now,
+        count: 1,
+        changes,
+        issues: issues.to_vec(),
+        before: before.clone(),
+        after: after.clone(),
+        key,
+    });
+}
+
+/// 按最近出现时间倒序返回全部记录
+pub fn list() -> Vec<SchemaChangeEntry> {
+    let mut entries: Vec<_> = SCHEMA_LOG
+        .lock()
+        .map(|log| log.iter().cloned().collect())
+        .unwrap_or_default();
+    entries.sort_by(|a, b| b.last_seen.cmp(&a.last_seen));
+    entries
+}
+
+pub fn clear() {
+    if let Ok(mut log) = SCHEMA_LOG.lock() {
+        log.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_diff_summary() {
+        let before = json!({
+            "$schema": "x",
+            "type": "object",
+            "properties": {"a/b": {"type": "string", "format": "uri"}},
+            "required": ["a/b"]
+        });
+        let after = json!({
+            "type": "OBJECT",
+            "properties": {"a/b": {"type": "STRING", "description": "format: uri"}},
+            "required": ["a/b"]
+        });
+        let mut changes = Vec::new();
+        diff(&before, &after, "", &mut changes);
+        assert_eq!(
+            changes,
+            vec![
+                "removed /$schema",
+                "changed /type: \"object\" -> \"OBJECT\"",
+                "changed /properties/a~1b/type: \"string\" -> \"STRING\"",
+                "removed /properties/a~1b/format",
+                "added /properties/a~1b/description",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_record_deduplicates() {
+        let tool = "mcp__schema_log_test__run";
+        let before = json!({"type": "object", "additionalProperties": false});
+        let after = json!({"type": "object"});
+        record(tool, None, &before, &after, &[]);
+        record(tool, Some("custom"), &before, &after, &[]);
+
+        let entries: Vec<_> = list().into_iter().filter(|e| e.tool == tool).collect();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].count, 2);
+        assert_eq!(entries[0].changes, vec!["removed /additionalProperties"]);
+    }
+}
@@ -0,0 +1,363 @@
+// 工具调用参数校验 - 按客户端声明的原始 (未清洗) Schema 校验模型返回的参数
+//
+// 通用清洗 (`json_schema::clean_json_schema`) 会丢弃 Gemini 不支持的约束，模型因此可能生成
+// 不符合原始 Schema 的参数。这里实现常用 JSON Schema 关键字的子集，用于发现这类偏差：
+// type / enum / const / properties / required / additionalProperties / items / anyOf / oneOf /
+// allOf / $ref (本地引用) 以及字符串、数值、数组的长度与范围约束。未识别的关键字一律忽略。
+
+use regex::Regex;
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::proxy::config::{ToolArgValidationConfig, ToolArgValidationMode};
+
+const MAX_DEPTH: usize = 32;
+const MAX_ERRORS: usize = 20;
+
+/// 校验 `value` 是否符合 `schema`，返回 `路径: 原因` 形式的错误列表 (为空表示通过)
+pub fn validate(value: &Value, schema: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    validate_node(value, schema, schema, "", 0, &mut errors);
+    errors.truncate(MAX_ERRORS);
+    errors
+}
+
+/// 校验一组工具调用 (名称, 参数)；未声明 Schema 的工具跳过
+pub fn validate_tool_calls<'a>(
+    calls: impl IntoIterator<Item = (&'a str, &'a Value)>,
+    schemas: &HashMap<String, Value>,
+) -> Vec<String> {
+    let mut violations = Vec::new();
+    for (name, args) in calls {
+        let Some(schema) = schemas.get(name) else {
+            continue;
+        };
+        violations.extend(
+            validate(args, schema)
+                .into_iter()
+                .map(|e| format!("tool '{}' {}", name, e)),
+        );
+    }
+    violations
+}
+
+/// 校验失败后的处理决定
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ToolArgVerdict {
+    /// 返回当前响应
+    Accept,
+    /// 重新请求上游
+    Retry,
+    /// 返回错误
+    Reject(String),
+}
+
+/// 根据配置与已重试次数决定如何处理校验结果
+pub fn decide(
+    config: &ToolArgValidationConfig,
+    violations: &[String],
+    retries_done: u32,
+    trace_id: &str,
+) -> ToolArgVerdict {
+    if violations.is_empty() || config.mode == ToolArgValidationMode::Off {
+        return ToolArgVerdict::Accept;
+    }
+    tracing::warn!(
+        "[{}] Tool call arguments do not match the original schema: {}",
+        trace_id,
+        violations.join("; ")
+    );
+    match config.mode {
+        ToolArgValidationMode::Off | ToolArgValidationMode::Warn => ToolArgVerdict::Accept,
+        _ if retries_done < config.max_retries => ToolArgVerdict::Retry,
+        ToolArgValidationMode::Retry => ToolArgVerdict::Accept,
+        ToolArgValidationMode::Reject => ToolArgVerdict::Reject(format!(
+            "Tool call arguments do not match the declared schema: {}",
+            violations.join("; ")
+        )),
+    }
+}
+
+fn type_matches(value: &Value, ty: &str) -> bool {
+    match ty {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        "number" => value.is_number(),
+        "integer" => {
+            value.is_i64()
+                || value.is_u64()
+                || value.as_f64().map(|f| f.fract() == 0.0).unwrap_or(false)
+        }
+        _ => true,
+    }
+}
+
+/// 解析本地 `$ref` (`#`、`#/$defs/X`、`#/definitions/X` 或任意 JSON Pointer)
+fn resolve_ref<'a>(root: &'a Value, reference: &str) -> Option<&'a Value> {
+    let pointer = reference.strip_prefix('#')?;
+    if pointer.is_empty() {
+        return Some(root);
+    }
+    root.pointer(pointer)
+}
+
+fn validate_node(
+    value: &Value,
+    schema: &Value,
+    root: &Value,
+    path: &str,
+    depth: usize,
+    errors: &mut Vec<String>,
+) {
+    if depth > MAX_DEPTH || errors.len() >= MAX_ERRORS {
+        return;
+    }
+    let at = if path.is_empty() { "/" } else { path };
+    let schema = match schema {
+        Value::Bool(false) => {
+            errors.push(format!("{}: no value is allowed here", at));
+            return;
+        }
+        Value::Object(map) => map,
+        _ => return,
+    };
+
+    if let Some(Value::String(reference)) = schema.get("$ref") {
+        if let Some(target) = resolve_ref(root, reference) {
+            validate_node(value, target, root, path, depth + 1, errors);
+        }
+    }
+
+    if let Some(ty) = schema.get("type") {
+        let matches = match ty {
+            Value::String(t) => type_matches(value, &t.to_lowercase()),
+            Value::Array(types) => types
+                .iter()
+                .filter_map(|t| t.as_str())
+                .any(|t| type_matches(value, &t.to_lowercase())),
+            _ => true,
+        };
+        if !matches {
+            errors.push(format!("{}: expected type {}, got {}", at, ty, value));
+            return;
+        }
+    }
+
+    if let Some(Value::Array(options)) = schema.get("enum") {
+        if !options.contains(value) {
+            errors.push(format!(
+                "{}: {} is not one of {}",
+                at,
+                value,
+                Value::Array(options.clone())
+            ));
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if expected != value {
+            errors.push(format!(
+                "{}: expected constant {}, got {}",
+                at, expected, value
+            ));
+        }
+    }
+
+    if let Some(Value::Array(all_of)) = schema.get("allOf") {
+        for sub in all_of {
+            validate_node(value, sub, root, path, depth + 1, errors);
+        }
+    }
+    for keyword in ["anyOf", "oneOf"] {
+        if let Some(Value::Array(branches)) = schema.get(keyword) {
+            // oneOf 按 anyOf 宽松处理：模型无法感知分支之间的互斥关系
+            let matched = branches.iter().any(|branch| {
+                let mut branch_errors = Vec::new();
+                validate_node(value, branch, root, path, depth + 1, &mut branch_errors);
+                branch_errors.is_empty()
+            });
+            if !matched {
+                errors.push(format!("{}: does not match any {} branch", at, keyword));
+            }
+        }
+    }
+
+    match value {
+        Value::Object(obj) => {
+            let properties = schema.get("properties").and_then(|p| p.as_object());
+            if let Some(Value::Array(required)) = schema.get("required") {
+                for key in required.iter().filter_map(|k| k.as_str()) {
+                    if !obj.contains_key(key) {
+                        errors.push(format!("{}: missing required property '{}'", at, key));
+                    }
+                }
+            }
+            for (key, child) in obj {
+                let child_path = format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"));
+                match properties.and_then(|p| p.get(key)) {
+                    Some(child_schema) => {
+                        validate_node(child, child_schema, root, &child_path, depth + 1, errors)
+                    }
+                    None => match schema.get("additionalProperties") {
+                        Some(Value::Bool(false)) => {
+                            errors.push(format!("{}: unexpected property '{}'", at, key))
+                        }
+                        Some(extra @ Value::Object(_)) => {
+                            validate_node(child, extra, root, &child_path, depth + 1, errors)
+                        }
+                        _ => {}
+                    },
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(min) = schema.get("minItems").and_then(|v| v.as_u64()) {
+                if (items.len() as u64) < min {
+                    errors.push(format!(
+                        "{}: expected at least {} items, got {}",
+                        at,
+                        min,
+                        items.len()
+                    ));
+                }
+            }
+            if let Some(max) = schema.get("maxItems").and_then(|v| v.as_u64()) {
+                if items.len() as u64 > max {
+                    errors.push(format!(
+                        "{}: expected at most {} items, got {}",
+                        at,
+                        max,
+                        items.len()
+                    ));
+                }
+            }
+            if let Some(item_schema @ Value::Object(_)) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    validate_node(
+                        item,
+                        item_schema,
+                        root,
+                        &format!("{}/{}", path, i),
+                        depth + 1,
+                        errors,
+                    );
+                }
+            }
+        }
+        Value::String(s) => {
+            let len = s.chars().count() as u64;
+            if let Some(min) = schema.get("minLength").and_then(|v| v.as_u64()) {
+                if len < min {
+                    errors.push(format!("{}: shorter than minLength {}", at, min));
+                }
+            }
+            if let Some(max) = schema.get("maxLength").and_then(|v| v.as_u64()) {
+                if len > max {
+                    errors.push(format!("{}: longer than maxLength {}", at, max));
+                }
+            }
+            if let Some(pattern) = schema.get("pattern").and_then(|v| v.as_str()) {
+                // 无法编译的正则 (如使用了 Rust regex 不支持的语法) 直接跳过
+                if let Ok(re) = Regex::new(pattern) {
+                    if !re.is_match(s) {
+                        errors.push(format!("{}: does not match pattern {}", at, pattern));
+                    }
+                }
+            }
+        }
+        Value::Number(n) => {
+            let Some(n) = n.as_f64() else {
+                return;
+            };
+            let bound = |key: &str| schema.get(key).and_then(|v| v.as_f64());
+            if let Some(min) = bound("minimum") {
+                if n < min {
+                    errors.push(format!("{}: {} is less than minimum {}", at, n, min));
+                }
+            }
+            if let Some(max) = bound("maximum") {
+                if n > max {
+                    errors.push(format!("{}: {} is greater than maximum {}", at, n, max));
+                }
+            }
+            if let Some(min) = bound("exclusiveMinimum") {
+                if n <= min {
+                    errors.push(format!("{}: {} must be greater than {}", at, n, min));
+                }
+            }
+            if let Some(max) = bound("exclusiveMaximum") {
+                if n >= max {
+                    errors.push(format!("{}: {} must be less than {}", at, n, max));
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_validate_against_original_schema() {
+        let schema = json!({
+            "type": "object",
+            "$defs": { "Mode": { "type": "string", "enum": ["fast", "safe"] } },
+            "additionalProperties": false,
+            "properties": {
+                "mode": { "$ref": "#/$defs/Mode" },
+                "count": { "type": "integer", "minimum": 1, "maximum": 10 },
+                "name": { "type": "string", "pattern": "^[a-z]+$" },
+                "target": {
+                    "anyOf": [
+                        { "type": "string" },
+                        { "type": "object", "properties": { "path": { "type": "string" } }, "required": ["path"] }
+                    ]
+                }
+            },
+            "required": ["mode"]
+        });
+
+        let valid = json!({"mode": "fast", "count": 3, "name": "abc", "target": {"path": "/tmp"}});
+        assert!(validate(&valid, &schema).is_empty());
+
+        let invalid = json!({"mode": "slow", "count": 0, "name": "ABC", "target": {}, "extra": 1});
+        let errors = validate(&invalid, &schema);
+        assert_eq!(errors.len(), 5, "{:?}", errors);
+        assert!(errors.iter().any(|e| e.starts_with("/mode:")));
+        assert!(errors.iter().any(|e| e.contains("less than minimum 1")));
+        assert!(errors.iter().any(|e| e.contains("does not match pattern")));
+        assert!(errors
+            .iter()
+            .any(|e| e.contains("does not match any anyOf branch")));
+        assert!(errors
+            .iter()
+            .any(|e| e.contains("unexpected property 'extra'")));
+
+        let missing = validate(&json!({}), &schema);
+        assert_eq!(missing, vec!["/: missing required property 'mode'"]);
+    }
+
+    #[test]
+    fn test_decide_by_mode() {
+        let violations = vec!["tool 'run' /: missing required property 'cmd'".to_string()];
+        let mut config = ToolArgValidationConfig::default();
+        assert_eq!(decide(&config, &violations, 0, "t"), ToolArgVerdict::Accept);
+        assert_eq!(decide(&config, &[], 0, "t"), ToolArgVerdict::Accept);
+
+        config.mode = ToolArgValidationMode::Retry;
+        assert_eq!(decide(&config, &violations, 0, "t"), ToolArgVerdict::Retry);
+        assert_eq!(decide(&config, &violations, 1, "t"), ToolArgVerdict::Accept);
+
+        config.mode = ToolArgValidationMode::Reject;
+        assert_eq!(decide(&config, &violations, 0, "t"), ToolArgVerdict::Retry);
+        assert!(matches!(
+            decide(&config, &violations, 1, "t"),
+            ToolArgVerdict::Reject(_)
+        ));
+    }
+}
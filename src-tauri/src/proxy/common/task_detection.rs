@@ -0,0 +1,206 @@
+// 后台任务检测 - 根据最后一条用户消息识别标题生成、摘要等客户端后台请求
+//
+// Claude handler 用于把后台任务重定向到轻量模型，路由规则引擎用作 `task_type` 匹配条件。
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// 后台任务类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackgroundTaskType {
+    TitleGeneration,    // 标题生成
+    SimpleSummary,      // 简单摘要
+    ContextCompression, // 上下文压缩
+    PromptSuggestion,   // 提示建议
+    SystemMessage,      // 系统消息
+    EnvironmentProbe,   // 环境探测
+}
+
+/// 标题生成关键词
+const TITLE_KEYWORDS: &[&str] = &[
+    "write a 5-10 word title",
+    "Please write a 5-10 word title",
+    "Respond with the title",
+    "Generate a title for",
+    "Create a brief title",
+    "title for the conversation",
+    "conversation title",
+    "生成标题",
+    "为对话起个标题",
+];
+
+/// 摘要生成关键词
+const SUMMARY_KEYWORDS: &[&str] = &[
+    "Summarize this coding conversation",
+    "Summarize the conversation",
+    "Concise summary",
+    "in under 50 characters",
+    "compress the context",
+    "Provide a concise summary",
+    "condense the previous messages",
+    "shorten the conversation history",
+    "extract key points from",
+];
+
+/// 建议生成关键词
+const SUGGESTION_KEYWORDS: &[&str] = &[
+    "prompt suggestion generator",
+    "suggest next prompts",
+    "what should I ask next",
+    "generate follow-up questions",
+    "recommend next steps",
+    "possible next actions",
+];
+
+/// 系统消息关键词
+const SYSTEM_KEYWORDS: &[&str] = &[
+    "Warmup",
+    "<system-reminder>",
+    // Removed: "Caveat: The messages below were generated" - this is a normal Claude Desktop system prompt
+    "This is a system message",
+];
+
+/// 环境探测关键词
+const PROBE_KEYWORDS: &[&str] = &[
+    "check current directory",
+    "list available tools",
+    "verify environment",
+    "test connection",
+];
+
+/// 检测后台任务并返回任务类型
+///
+/// `text` 为最后一条有效用户消息 (见 [`is_detection_candidate`])
+pub fn detect_task_type(text: &str) -> Option<BackgroundTaskType> {
+    let preview = text.chars().take(500).collect::<String>();
+
+    // 长度过滤：后台任务通常不超过 800 字符
+    if text.len() > 800 {
+        return None;
+    }
+
+    // 按优先级匹配
+    if matches_keywords(&preview, SYSTEM_KEYWORDS) {
+        return Some(BackgroundTaskType::SystemMessage);
+    }
+
+    if matches_keywords(&preview, TITLE_KEYWORDS) {
+        return Some(BackgroundTaskType::TitleGeneration);
+    }
+
+    if matches_keywords(&preview, SUMMARY_KEYWORDS) {
+        if preview.contains("in under 50 characters") {
+            return Some(BackgroundTaskType::SimpleSummary);
+        }
+        return Some(BackgroundTaskType::ContextCompression);
+    }
+
+    if matches_keywords(&preview, SUGGESTION_KEYWORDS) {
+        return Some(BackgroundTaskType::PromptSuggestion);
+    }
+
+    if matches_keywords(&preview, PROBE_KEYWORDS) {
+        return Some(BackgroundTaskType::EnvironmentProbe);
+    }
+
+    None
+}
+
+/// 辅助函数：关键词匹配
+fn matches_keywords(text: &str, keywords: &[&str]) -> bool {
+    keywords.iter().any(|kw| text.contains(kw))
+}
+
+/// 消息是否参与检测 (跳过空消息、Warmup 与 system-reminder 注入)
+pub fn is_detection_candidate(content: &str) -> bool {
+    !(content.trim().is_empty()
+        || content.starts_with("Warmup")
+        || content.contains("<system-reminder>"))
+}
+
+/// 从任意协议的原始请求体中提取最后一条有效用户消息文本
+///
+/// 支持 Claude / OpenAI 的 `messages` (字符串或内容块数组) 与 Gemini 的 `contents[].parts`。
+pub fn last_user_text(body: &Value) -> Option<String> {
+    let block_text = |block: &Value| -> Option<String> {
+        block
+            .get("text")
+            .and_then(|t| t.as_str())
+            .map(|s| s.to_string())
+    };
+
+    if let Some(messages) = body.get("messages").and_then(|m| m.as_array()) {
+        return messages
+            .iter()
+            .rev()
+            .filter(|m| m.get("role").and_then(|r| r.as_str()) == Some("user"))
+            .find_map(|m| {
+                let content = match m.get("content") {
+                    Some(Value::String(s)) => s.clone(),
+                    Some(Value::Array(blocks)) => blocks
+                        .iter()
+                        .filter_map(block_text)
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                    _ => return None,
+                };
+                is_detection_candidate(&content).then_some(content)
+            });
+    }
+
+    body.get("contents")
+        .and_then(|c| c.as_array())?
+        .iter()
+        .rev()
+        .filter(|c| c.get("role").and_then(|r| r.as_str()).unwrap_or("user") == "user")
+        .find_map(|c| {
+            let content = c
+                .get("parts")
+                .and_then(|p| p.as_array())?
+                .iter()
+                .filter_map(block_text)
+                .collect::<Vec<_>>()
+                .join(" ");
+            is_detection_candidate(&content).then_some(content)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_detect_task_type_from_text() {
+        assert_eq!(
+            detect_task_type("Please write a 5-10 word title for this conversation"),
+            Some(BackgroundTaskType::TitleGeneration)
+        );
+        assert_eq!(
+            detect_task_type("Summarize the conversation in under 50 characters"),
+            Some(BackgroundTaskType::SimpleSummary)
+        );
+        assert_eq!(detect_task_type("Refactor the parser module"), None);
+        let long = format!("Generate a title for {}", "x".repeat(900));
+        assert_eq!(detect_task_type(&long), None);
+    }
+
+    #[test]
+    fn test_last_user_text_across_protocols() {
+        let openai = json!({"messages": [
+            {"role": "user", "content": "first"},
+            {"role": "assistant", "content": "ok"},
+            {"role": "user", "content": [{"type": "text", "text": "second"}]},
+            {"role": "user", "content": "<system-reminder>skip</system-reminder>"}
+        ]});
+        assert_eq!(last_user_text(&openai).as_deref(), Some("second"));
+
+        let gemini = json!({"contents": [
+            {"role": "user", "parts": [{"text": "hello"}, {"text": "world"}]},
+            {"role": "model", "parts": [{"text": "hi"}]}
+        ]});
+        assert_eq!(last_user_text(&gemini).as_deref(), Some("hello world"));
+        assert_eq!(last_user_text(&json!({})), None);
+    }
+}
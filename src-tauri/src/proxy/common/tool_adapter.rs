@@ -5,6 +5,9 @@ use serde_json::Value;
 /// 为不同的 MCP 工具提供定制化的 Schema 处理策略。
 /// 每个工具可以实现自己的适配器来处理特定的需求。
 pub trait ToolAdapter: Send + Sync {
+    /// 适配器名称 (用于日志及管理接口)
+    fn name(&self) -> &str;
+
     /// 判断该适配器是否匹配给定的工具名称
     /// 
     /// # Arguments
@@ -39,6 +42,12 @@ pub trait ToolAdapter: Send + Sync {
     fn post_process(&self, _schema: &mut Value) -> Result<(), String> {
         Ok(())
     }
+
+    /// 改写上游返回的工具调用参数 (转发给客户端前执行)
+    ///
+    /// # Arguments
+    /// * `args` - 工具调用参数对象
+    fn rewrite_args(&self, _args: &mut Value) {}
 }
 
 /// 辅助函数: 向 Schema 的 description 字段追加提示
@@ -66,6 +75,10 @@ mod tests {
     struct TestAdapter;
     
     impl ToolAdapter for TestAdapter {
+        fn name(&self) -> &str {
+            "test"
+        }
+
         fn matches(&self, tool_name: &str) -> bool {
             tool_name.starts_with("test__")
         }
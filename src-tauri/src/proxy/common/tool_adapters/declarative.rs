@@ -0,0 +1,259 @@
+use super::super::model_mapping::wildcard_match;
+use super::super::tool_adapter::{append_hint_to_schema, ToolAdapter};
+use crate::proxy::config::{ArgRewrite, SchemaEdit, ToolAdapterConfig};
+use serde_json::{json, Value};
+
+/// 由配置文件声明的 MCP 工具适配器
+///
+/// 通过 `proxy.tool_adapters` 定义，支持：
+/// 1. 工具名称通配符匹配
+/// 2. 基于 JSON Pointer 的 Schema 修改 (清洗前 / 清洗后)
+/// 3. description 提示注入
+/// 4. 工具调用参数改写 (返回客户端前)
+pub struct DeclarativeToolAdapter {
+    config: ToolAdapterConfig,
+}
+
+impl DeclarativeToolAdapter {
+    pub fn new(config: ToolAdapterConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &ToolAdapterConfig {
+        &self.config
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+}
+
+impl ToolAdapter for DeclarativeToolAdapter {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn matches(&self, tool_name: &str) -> bool {
+        self.config
+            .tools
+            .iter()
+            .any(|pattern| wildcard_match(pattern, tool_name))
+    }
+
+    fn pre_process(&self, schema: &mut Value) -> Result<(), String> {
+        apply_edits(schema, &self.config.schema_edits)
+    }
+
+    fn post_process(&self, schema: &mut Value) -> Result<(), String> {
+        apply_edits(schema, &self.config.post_edits)
+    }
+
+    fn rewrite_args(&self, args: &mut Value) {
+        for rewrite in &self.config.arg_rewrites {
+            match rewrite {
+                ArgRewrite::Rename { from, to } => {
+                    if args.pointer(to).is_none() {
+                        if let Some(value) = remove(args, from) {
+                            let _ = set(args, to, value);
+                        }
+                    }
+                }
+                ArgRewrite::Default { pointer, value } => {
+                    if args.pointer(pointer).is_none() {
+                        let _ = set(args, pointer, value.clone());
+                    }
+                }
+                ArgRewrite::Set { pointer, value } => {
+                    let _ = set(args, pointer, value.clone());
+                }
+                ArgRewrite::Remove { pointer } => {
+                    remove(args, pointer);
+                }
+            }
+        }
+    }
+}
+
+/// 依次应用全部修改；失败的修改不会中断后续修改，错误汇总返回
+fn apply_edits(schema: &mut Value, edits: &[SchemaEdit]) -> Result<(), String> {
+    let mut errors = Vec::new();
+    for edit in edits {
+        let result = match edit {
+            SchemaEdit::Set { pointer, value } => set(schema, pointer, value.clone()),
+            SchemaEdit::Remove { pointer } => remove(schema, pointer)
+                .map(|_| ())
+                .ok_or_else(|| format!("{} not found", pointer)),
+            SchemaEdit::Merge { pointer, value } => merge(schema, pointer, value),
+            SchemaEdit::Hint { pointer, hint } => schema
+                .pointer_mut(pointer)
+                .map(|target| append_hint_to_schema(target, hint))
+                .ok_or_else(|| format!("{} not found", pointer)),
+        };
+        if let Err(e) = result {
+            errors.push(e);
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("; "))
+    }
+}
+
+/// 解析 JSON Pointer 为路径片段 (处理 `~1` / `~0` 转义)
+pub fn parse_pointer(pointer: &str) -> Result<Vec<String>, String> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    let Some(rest) = pointer.strip_prefix('/') else {
+        return Err(format!("JSON Pointer '{}' must start with '/'", pointer));
+    };
+    Ok(rest
+        .split('/')
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect())
+}
+
+/// 沿路径下行，`create` 为 true 时自动创建缺失的中间对象
+fn walk<'a>(root: &'a mut Value, tokens: &[String], create: bool) -> Option<&'a mut Value> {
+    let mut current = root;
+    for token in tokens {
+        current = match current {
+            Value::Object(map) if create => map.entry(token.clone()).or_insert_with(|| json!({})),
+            Value::Object(map) => map.get_mut(token)?,
+            Value::Array(items) => items.get_mut(token.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(current)
+}
+
+fn set(root: &mut Value, pointer: &str, value: Value) -> Result<(), String> {
+    let tokens = parse_pointer(pointer)?;
+    let Some((last, parents)) = tokens.split_last() else {
+        *root = value;
+        return Ok(());
+    };
+    match walk(root, parents, true) {
+        Some(Value::Object(map)) => {
+            map.insert(last.clone(), value);
+            Ok(())
+        }
+        Some(Value::Array(items)) if last == "-" => {
+            items.push(value);
+            Ok(())
+        }
+        Some(Value::Array(items)) => {
+            match last.parse::<usize>().ok().and_then(|i| items.get_mut(i)) {
+                Some(slot) => {
+                    *slot = value;
+                    Ok(())
+                }
+                None => Err(format!("{} is out of bounds", pointer)),
+            }
+        }
+        _ => Err(format!("{} cannot be set", pointer)),
+    }
+}
+
+fn remove(root: &mut Value, pointer: &str) -> Option<Value> {
+    let tokens = parse_pointer(pointer).ok()?;
+    let (last, parents) = tokens.split_last()?;
+    match walk(root, parents, false)? {
+        Value::Object(map) => map.remove(last),
+        Value::Array(items) => {
+            let index = last.parse::<usize>().ok().filter(|i| *i < items.len())?;
+            Some(items.remove(index))
+        }
+        _ => None,
+    }
+}
+
+fn merge(root: &mut Value, pointer: &str, value: &Value) -> Result<(), String> {
+    let tokens = parse_pointer(pointer)?;
+    let target =
+        walk(root, &tokens, true).ok_or_else(|| format!("{} cannot be merged", pointer))?;
+    match (target, value) {
+        (Value::Object(target), Value::Object(patch)) => {
+            for (key, value) in patch {
+                target.insert(key.clone(), value.clone());
+            }
+            Ok(())
+        }
+        (target, value) => {
+            *target = value.clone();
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(config: Value) -> DeclarativeToolAdapter {
+        DeclarativeToolAdapter::new(serde_json::from_value(config).unwrap())
+    }
+
+    #[test]
+    fn test_schema_edits() {
+        let adapter = build(json!({
+            "name": "github",
+            "tools": ["mcp__github__*"],
+            "schema_edits": [
+                {"op": "remove", "pointer": "/properties/filter/oneOf"},
+                {"op": "set", "pointer": "/properties/filter/type", "value": "string"},
+                {"op": "merge", "pointer": "/properties/repo", "value": {"type": "string"}},
+                {"op": "hint", "pointer": "/properties/repo", "hint": "Format: owner/name"},
+                {"op": "remove", "pointer": "/properties/missing"}
+            ],
+            "post_edits": [
+                {"op": "set", "pointer": "/properties/a~1b/enum", "value": ["x"]}
+            ]
+        }));
+        assert!(adapter.matches("mcp__github__search_issues"));
+        assert!(!adapter.matches("mcp__gitlab__search"));
+
+        let mut schema = json!({
+            "type": "object",
+            "properties": {"filter": {"oneOf": [{"type": "string"}, {"type": "number"}]}}
+        });
+        let err = adapter.pre_process(&mut schema).unwrap_err();
+        assert!(err.contains("/properties/missing"));
+        assert_eq!(schema["properties"]["filter"], json!({"type": "string"}));
+        assert_eq!(
+            schema["properties"]["repo"],
+            json!({"type": "string", "description": "Format: owner/name"})
+        );
+
+        adapter.post_process(&mut schema).unwrap();
+        assert_eq!(schema["properties"]["a/b"]["enum"], json!(["x"]));
+        assert!(parse_pointer("properties").is_err());
+    }
+
+    #[test]
+    fn test_arg_rewrites() {
+        let adapter = build(json!({
+            "name": "fs",
+            "tools": ["mcp__fs__read_file"],
+            "arg_rewrites": [
+                {"op": "rename", "from": "/file_path", "to": "/path"},
+                {"op": "rename", "from": "/filename", "to": "/path"},
+                {"op": "default", "pointer": "/options/encoding", "value": "utf-8"},
+                {"op": "set", "pointer": "/tail", "value": 0},
+                {"op": "remove", "pointer": "/description"}
+            ]
+        }));
+        let mut args = json!({"file_path": "a.txt", "filename": "b.txt", "description": "x"});
+        adapter.rewrite_args(&mut args);
+        assert_eq!(
+            args,
+            json!({
+                "path": "a.txt",
+                "filename": "b.txt",
+                "options": {"encoding": "utf-8"},
+                "tail": 0
+            })
+        );
+    }
+}
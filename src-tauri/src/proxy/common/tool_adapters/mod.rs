@@ -1,3 +1,5 @@
+pub mod declarative;
 pub mod pencil;
 
+pub use declarative::DeclarativeToolAdapter;
 pub use pencil::PencilAdapter;
@@ -10,6 +10,10 @@ use super::super::tool_adapter::{ToolAdapter, append_hint_to_schema};
 pub struct PencilAdapter;
 
 impl ToolAdapter for PencilAdapter {
+    fn name(&self) -> &str {
+        "pencil"
+    }
+
     fn matches(&self, tool_name: &str) -> bool {
         tool_name.starts_with("mcp__pencil__")
     }
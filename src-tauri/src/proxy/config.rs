@@ -20,6 +20,42 @@ pub fn normalize_proxy_url(url: &str) -> String {
     }
 }
 
+/// 校验列表项名称非空且不重复，错误路径为 `{prefix}.{index}.name`
+fn validate_names<'a>(
+    prefix: &str,
+    names: impl Iterator<Item = &'a str>,
+    ignore_case: bool,
+    errors: &mut Vec<String>,
+) {
+    let mut seen = std::collections::HashSet::new();
+    for (i, name) in names.enumerate() {
+        let key = if ignore_case {
+            name.to_lowercase()
+        } else {
+            name.to_string()
+        };
+        if name.trim().is_empty() {
+            errors.push(format!("{}.{}.name must not be empty", prefix, i));
+        } else if !seen.insert(key) {
+            errors.push(format!("{}.{}.name '{}' is duplicated", prefix, i, name));
+        }
+    }
+}
+
+/// 校验 protocols 取值 (提示词片段与脚本钩子共用)
+fn validate_protocols(prefix: &str, protocols: &[String], errors: &mut Vec<String>) {
+    for protocol in protocols {
+        if !["openai", "anthropic", "claude", "gemini"]
+            .contains(&protocol.trim().to_lowercase().as_str())
+        {
+            errors.push(format!(
+                "{}.protocols: unknown protocol '{}' (expected openai / anthropic / claude / gemini)",
+                prefix, protocol
+            ));
+        }
+    }
+}
+
 // ============================================================================
 // 全局 Thinking Budget 配置存储
 // 用于在 request transform 函数中访问配置（无需修改函数签名）
@@ -27,7 +63,12 @@ pub fn normalize_proxy_url(url: &str) -> String {
 static GLOBAL_THINKING_BUDGET_CONFIG: OnceLock<RwLock<ThinkingBudgetConfig>> = OnceLock::new();
 
 /// 获取当前 Thinking Budget 配置
+///
+/// 路由规则为当前请求指定了 Thinking Budget 时优先返回该配置
 pub fn get_thinking_budget_config() -> ThinkingBudgetConfig {
+    if let Some(config) = crate::proxy::routing::current_thinking_budget() {
+        return config;
+    }
     GLOBAL_THINKING_BUDGET_CONFIG
         .get()
         .and_then(|lock| lock.read().ok())
@@ -35,6 +76,15 @@ pub fn get_thinking_budget_config() -> ThinkingBudgetConfig {
         .unwrap_or_default()
 }
 
+/// 获取全局配置中的思考策略 (不受路由规则覆盖影响)
+pub fn get_thinking_policies() -> Vec<ThinkingPolicy> {
+    GLOBAL_THINKING_BUDGET_CONFIG
+        .get()
+        .and_then(|lock| lock.read().ok())
+        .map(|cfg| cfg.policies.clone())
+        .unwrap_or_default()
+}
+
 /// 更新全局 Thinking Budget 配置
 pub fn update_thinking_budget_config(config: ThinkingBudgetConfig) {
     if let Some(lock) = GLOBAL_THINKING_BUDGET_CONFIG.get() {
@@ -78,9 +128,10 @@ pub fn update_global_system_prompt_config(config: GlobalSystemPromptConfig) {
         if let Ok(mut cfg) = lock.write() {
             *cfg = config.clone();
             tracing::info!(
-                "[Global-System-Prompt] Config updated: enabled={}, content_len={}",
+                "[Global-System-Prompt] Config updated: enabled={}, content_len={}, prompts={}",
                 config.enabled,
-                config.content.len()
+                config.content.len(),
+                config.prompts.len()
             );
         }
     } else {
@@ -121,6 +172,223 @@ pub fn update_image_thinking_mode(mode: Option<String>) {
     }
 }
 
+// ============================================================================
+// 全局模型降级链存储
+// 由 fallback 中间件在 Google 账号池不可用时查询
+// ============================================================================
+static GLOBAL_FALLBACK_CHAINS: OnceLock<RwLock<HashMap<String, Vec<String>>>> = OnceLock::new();
+
+/// 获取当前模型降级链
+pub fn get_fallback_chains() -> HashMap<String, Vec<String>> {
+    GLOBAL_FALLBACK_CHAINS
+        .get()
+        .and_then(|lock| lock.read().ok())
+        .map(|chains| chains.clone())
+        .unwrap_or_default()
+}
+
+/// 更新全局模型降级链
+pub fn update_fallback_chains(chains: HashMap<String, Vec<String>>) {
+    if let Some(lock) = GLOBAL_FALLBACK_CHAINS.get() {
+        if let Ok(mut cfg) = lock.write() {
+            *cfg = chains;
+            tracing::info!("[Fallback-Chain] Global config updated: {} chains", cfg.len());
+        }
+    } else {
+        tracing::info!("[Fallback-Chain] Global config initialized: {} chains", chains.len());
+        let _ = GLOBAL_FALLBACK_CHAINS.set(RwLock::new(chains));
+    }
+}
+
+// ============================================================================
+// 全局请求调度配置存储
+// 由各协议 handler 的 Dispatcher 在每次请求开始时读取
+// ============================================================================
+static GLOBAL_DISPATCH_CONFIG: OnceLock<RwLock<DispatchConfig>> = OnceLock::new();
+
+/// 获取当前请求调度配置
+pub fn get_dispatch_config() -> DispatchConfig {
+    GLOBAL_DISPATCH_CONFIG
+        .get()
+        .and_then(|lock| lock.read().ok())
+        .map(|cfg| cfg.clone())
+        .unwrap_or_default()
+}
+
+/// 更新全局请求调度配置
+pub fn update_dispatch_config(config: DispatchConfig) {
+    if let Some(lock) = GLOBAL_DISPATCH_CONFIG.get() {
+        if let Ok(mut cfg) = lock.write() {
+            *cfg = config.clone();
+            tracing::info!(
+                "[Dispatch] Global config updated: max_attempts={}, deadline={}s, hedge={}ms",
+                config.max_attempts,
+                config.request_deadline_secs,
+                config.hedge_delay_ms
+            );
+        }
+    } else {
+        let _ = GLOBAL_DISPATCH_CONFIG.set(RwLock::new(config.clone()));
+        tracing::info!(
+            "[Dispatch] Global config initialized: max_attempts={}, deadline={}s, hedge={}ms",
+            config.max_attempts,
+            config.request_deadline_secs,
+            config.hedge_delay_ms
+        );
+    }
+}
+
+// ============================================================================
+// 全局路由规则存储
+// 由 routing 中间件在每个对话请求进入 handler 前评估
+// ============================================================================
+static GLOBAL_ROUTING_CONFIG: OnceLock<RwLock<RoutingConfig>> = OnceLock::new();
+
+/// 获取当前路由规则配置
+pub fn get_routing_config() -> RoutingConfig {
+    GLOBAL_ROUTING_CONFIG
+        .get()
+        .and_then(|lock| lock.read().ok())
+        .map(|cfg| cfg.clone())
+        .unwrap_or_default()
+}
+
+/// 更新全局路由规则配置
+pub fn update_routing_config(config: RoutingConfig) {
+    if let Some(lock) = GLOBAL_ROUTING_CONFIG.get() {
+        if let Ok(mut cfg) = lock.write() {
+            tracing::info!(
+                "[Routing] Global config updated: {} rules, {} account groups",
+                config.rules.len(),
+                config.account_groups.len()
+            );
+            *cfg = config;
+        }
+    } else {
+        tracing::info!(
+            "[Routing] Global config initialized: {} rules, {} account groups",
+            config.rules.len(),
+            config.account_groups.len()
+        );
+        let _ = GLOBAL_ROUTING_CONFIG.set(RwLock::new(config));
+    }
+}
+
+// ============================================================================
+// 全局工具结果压缩配置
+// 由 tool_result_compressor 在转换请求时读取 (随 experimental 配置更新)
+// ============================================================================
+static GLOBAL_TOOL_RESULT_COMPRESSION: OnceLock<RwLock<ToolResultCompressionConfig>> =
+    OnceLock::new();
+
+/// 获取当前工具结果压缩配置
+pub fn get_tool_result_compression_config() -> ToolResultCompressionConfig {
+    GLOBAL_TOOL_RESULT_COMPRESSION
+        .get()
+        .and_then(|lock| lock.read().ok())
+        .map(|cfg| cfg.clone())
+        .unwrap_or_default()
+}
+
+/// 更新工具结果压缩配置
+pub fn update_tool_result_compression_config(config: ToolResultCompressionConfig) {
+    if let Some(lock) = GLOBAL_TOOL_RESULT_COMPRESSION.get() {
+        if let Ok(mut cfg) = lock.write() {
+            tracing::info!(
+                "[ToolCompressor] Config updated: max_chars={}, {} policies",
+                config.max_chars,
+                config.policies.len()
+            );
+            *cfg = config;
+        }
+    } else {
+        tracing::info!(
+            "[ToolCompressor] Config initialized: max_chars={}, {} policies",
+            config.max_chars,
+            config.policies.len()
+        );
+        let _ = GLOBAL_TOOL_RESULT_COMPRESSION.set(RwLock::new(config));
+    }
+}
+
+// ============================================================================
+// 全局思维签名缓存持久化配置
+// 由 signature_persistence 的后台任务在每次落盘前读取
+// ============================================================================
+static GLOBAL_SIGNATURE_CACHE_CONFIG: OnceLock<RwLock<SignatureCacheConfig>> = OnceLock::new();
+
+/// 获取当前签名缓存持久化配置
+pub fn get_signature_cache_config() -> SignatureCacheConfig {
+    GLOBAL_SIGNATURE_CACHE_CONFIG
+        .get()
+        .and_then(|lock| lock.read().ok())
+        .map(|cfg| cfg.clone())
+        .unwrap_or_default()
+}
+
+/// 更新签名缓存持久化配置
+pub fn update_signature_cache_config(config: SignatureCacheConfig) {
+    if let Some(lock) = GLOBAL_SIGNATURE_CACHE_CONFIG.get() {
+        if let Ok(mut cfg) = lock.write() {
+            *cfg = config.clone();
+            tracing::info!(
+                "[SignatureCache] Persistence config updated: persist={}, encrypt={}, flush={}s",
+                config.persist,
+                config.encrypt,
+                config.flush_interval_secs
+            );
+        }
+    } else {
+        let _ = GLOBAL_SIGNATURE_CACHE_CONFIG.set(RwLock::new(config.clone()));
+        tracing::info!(
+            "[SignatureCache] Persistence config initialized: persist={}, encrypt={}, flush={}s",
+            config.persist,
+            config.encrypt,
+            config.flush_interval_secs
+        );
+    }
+}
+
+// ============================================================================
+// 全局响应缓存配置
+// 由 response_cache 中间件在每个对话请求进入 handler 前读取
+// ============================================================================
+static GLOBAL_RESPONSE_CACHE_CONFIG: OnceLock<RwLock<ResponseCacheConfig>> = OnceLock::new();
+
+/// 获取当前响应缓存配置
+pub fn get_response_cache_config() -> ResponseCacheConfig {
+    GLOBAL_RESPONSE_CACHE_CONFIG
+        .get()
+        .and_then(|lock| lock.read().ok())
+        .map(|cfg| cfg.clone())
+        .unwrap_or_default()
+}
+
+/// 更新响应缓存配置
+pub fn update_response_cache_config(config: ResponseCacheConfig) {
+    if let Some(lock) = GLOBAL_RESPONSE_CACHE_CONFIG.get() {
+        if let Ok(mut cfg) = lock.write() {
+            *cfg = config.clone();
+            tracing::info!(
+                "[ResponseCache] Config updated: enabled={}, ttl={}s, max_entries={}, max_size={}MB",
+                config.enabled,
+                config.ttl_secs,
+                config.max_entries,
+                config.max_size_mb
+            );
+        }
+    } else {
+        let _ = GLOBAL_RESPONSE_CACHE_CONFIG.set(RwLock::new(config.clone()));
+        tracing::info!(
+            "[ResponseCache] Config initialized: enabled={}, ttl={}s, max_entries={}, max_size={}MB",
+            config.enabled,
+            config.ttl_secs,
+            config.max_entries,
+            config.max_size_mb
+        );
+    }
+}
+
 /// 全局系统提示词配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GlobalSystemPromptConfig {
@@ -130,6 +398,9 @@ pub struct GlobalSystemPromptConfig {
     /// 系统提示词内容
     #[serde(default)]
     pub content: String,
+    /// 按条件注入的提示词片段 (与上面的全局提示词相互独立，按顺序注入全部命中的片段)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prompts: Vec<SystemPromptRule>,
 }
 
 impl Default for GlobalSystemPromptConfig {
@@ -137,10 +408,68 @@ impl Default for GlobalSystemPromptConfig {
         Self {
             enabled: false,
             content: String::new(),
+            prompts: Vec::new(),
+        }
+    }
+}
+
+impl GlobalSystemPromptConfig {
+    pub fn validate(&self, errors: &mut Vec<String>) {
+        let prefix = "proxy.global_system_prompt.prompts";
+        let names = self.prompts.iter().map(|p| p.name.as_str());
+        validate_names(prefix, names, false, errors);
+        for (i, prompt) in self.prompts.iter().enumerate() {
+            if prompt.content.trim().is_empty() {
+                errors.push(format!("{}.{}.content must not be empty", prefix, i));
+            }
+            validate_protocols(&format!("{}.{}", prefix, i), &prompt.protocols, errors);
         }
     }
 }
 
+/// 提示词片段的注入位置
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SystemPromptPosition {
+    /// 客户端系统提示词之前 (紧跟全局提示词)
+    #[default]
+    Prepend,
+    /// 客户端系统提示词之后
+    Append,
+}
+
+/// 单个提示词片段
+///
+/// 条件之间为 AND 关系，未填写的条件匹配任意值。内容支持模板变量
+/// `{{date}}`、`{{datetime}}`、`{{username}}`、`{{model}}`、`{{client}}`、`{{protocol}}`。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemPromptRule {
+    pub name: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    pub content: String,
+    #[serde(default)]
+    pub position: SystemPromptPosition,
+    /// 适用的协议: `openai` / `anthropic` (或 `claude`) / `gemini`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub protocols: Vec<String>,
+    /// 适用的上游模型 (映射后的模型名，支持 `*` 通配符)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub models: Vec<String>,
+    /// 适用的用户令牌 (用户名，支持 `*` 通配符)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub users: Vec<String>,
+    /// 适用的客户端适配器名称 (如 `opencode`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub clients: Vec<String>,
+    /// 排除的用户令牌 (支持 `*` 通配符)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude_users: Vec<String>,
+    /// 排除的客户端适配器名称
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude_clients: Vec<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ProxyAuthMode {
@@ -199,167 +528,1208 @@ impl Default for ZaiModelDefaults {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ZaiMcpConfig {
+pub struct ZaiMcpConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub web_search_enabled: bool,
+    #[serde(default)]
+    pub web_reader_enabled: bool,
+    #[serde(default)]
+    pub vision_enabled: bool,
+}
+
+impl Default for ZaiMcpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            web_search_enabled: false,
+            web_reader_enabled: false,
+            vision_enabled: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ZaiConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_zai_base_url")]
+    pub base_url: String,
+    #[serde(default)]
+    pub api_key: String,
+    #[serde(default)]
+    pub dispatch_mode: ZaiDispatchMode,
+    /// Optional per-model mapping overrides for Anthropic/Claude model ids.
+    /// Key: incoming `model` string, Value: upstream z.ai model id (e.g. `glm-4.7`).
+    #[serde(default)]
+    pub model_mapping: HashMap<String, String>,
+    #[serde(default)]
+    pub models: ZaiModelDefaults,
+    #[serde(default)]
+    pub mcp: ZaiMcpConfig,
+}
+
+impl Default for ZaiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            base_url: default_zai_base_url(),
+            api_key: String::new(),
+            dispatch_mode: ZaiDispatchMode::Off,
+            model_mapping: HashMap::new(),
+            models: ZaiModelDefaults::default(),
+            mcp: ZaiMcpConfig::default(),
+        }
+    }
+}
+
+impl ZaiConfig {
+    pub fn validate(&self, errors: &mut Vec<String>) {
+        if self.enabled && self.api_key.trim().is_empty() {
+            errors.push("proxy.zai.api_key is required when z.ai is enabled".to_string());
+        }
+    }
+}
+
+/// 第三方上游提供商使用的协议
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderProtocol {
+    /// OpenAI 兼容接口，base_url 需包含版本前缀 (如 `http://vllm:8000/v1`)
+    Openai,
+    /// Anthropic 兼容接口，base_url 不含 `/v1` (如 `https://api.anthropic.com`)
+    Anthropic,
+    /// Gemini API，base_url 不含版本前缀 (如 `https://generativelanguage.googleapis.com`)
+    Gemini,
+}
+
+/// 通用第三方上游提供商 (自建 vLLM、其他厂商等)
+///
+/// 请求只会被透传给与入口协议相同的提供商：`/v1/chat/completions` 等 OpenAI 入口使用 `openai`，
+/// `/v1/messages` 使用 `anthropic`，`/v1beta/models` 使用 `gemini`。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpstreamProviderConfig {
+    /// 唯一名称，也可作为模型前缀显式路由 (如 `vllm:llama-3-70b`)
+    pub name: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    pub base_url: String,
+    pub protocol: ProviderProtocol,
+    #[serde(default)]
+    pub api_key: String,
+    /// Key: 客户端请求的模型 (支持 `*` 通配符)，Value: 上游模型名。
+    /// 非空时仅接管映射表中的模型；为空则接管该协议下的所有模型 (按原名透传)。
+    #[serde(default)]
+    pub model_mapping: HashMap<String, String>,
+    /// 调度方式，语义与 z.ai 相同；`off` 时只能通过 `名称:模型` 前缀显式使用
+    #[serde(default)]
+    pub dispatch_mode: ZaiDispatchMode,
+}
+
+/// 实验性功能配置 (Feature Flags)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExperimentalConfig {
+    /// 启用双层签名缓存 (Signature Cache)
+    #[serde(default = "default_true")]
+    pub enable_signature_cache: bool,
+
+    /// 启用工具循环自动恢复 (Tool Loop Recovery)
+    #[serde(default = "default_true")]
+    pub enable_tool_loop_recovery: bool,
+
+    /// 启用跨模型兼容性检查 (Cross-Model Checks)
+    #[serde(default = "default_true")]
+    pub enable_cross_model_checks: bool,
+
+    /// 启用上下文用量缩放 (Context Usage Scaling)
+    /// 激进模式: 缩放用量并激活自动压缩以突破 200k 限制
+    /// 默认关闭以保持透明度,让客户端能触发原生压缩指令
+    #[serde(default = "default_false")]
+    pub enable_usage_scaling: bool,
+
+    /// 上下文压缩阈值 L1 (Tool Trimming)
+    #[serde(default = "default_threshold_l1")]
+    pub context_compression_threshold_l1: f32,
+
+    /// 上下文压缩阈值 L2 (Thinking Compression)
+    #[serde(default = "default_threshold_l2")]
+    pub context_compression_threshold_l2: f32,
+
+    /// 上下文压缩阈值 L3 (Fork + Summary)
+    #[serde(default = "default_threshold_l3")]
+    pub context_compression_threshold_l3: f32,
+
+    /// 工具结果压缩策略
+    #[serde(default)]
+    pub tool_result_compression: ToolResultCompressionConfig,
+
+    /// 基于分词器的 token 估算 (未配置时使用字符启发式)
+    #[serde(default)]
+    pub tokenizers: TokenizerConfig,
+
+    /// 按客户端原始 (未清洗) Schema 校验模型返回的工具调用参数
+    #[serde(default)]
+    pub tool_arg_validation: ToolArgValidationConfig,
+}
+
+impl Default for ExperimentalConfig {
+    fn default() -> Self {
+        Self {
+            enable_signature_cache: true,
+            enable_tool_loop_recovery: true,
+            enable_cross_model_checks: true,
+            enable_usage_scaling: false, // 默认关闭,回归透明模式
+            context_compression_threshold_l1: 0.4,
+            context_compression_threshold_l2: 0.55,
+            context_compression_threshold_l3: 0.7,
+            tool_result_compression: ToolResultCompressionConfig::default(),
+            tokenizers: TokenizerConfig::default(),
+            tool_arg_validation: ToolArgValidationConfig::default(),
+        }
+    }
+}
+
+impl ExperimentalConfig {
+    pub fn validate(&self, errors: &mut Vec<String>) {
+        self.tool_result_compression.validate(errors);
+        self.tokenizers.validate(errors);
+        self.tool_arg_validation.validate(errors);
+    }
+}
+
+/// 分词器配置
+///
+/// 词表文件从磁盘加载；未显式配置时自动发现 `dir` 下以模型家族命名的文件
+/// (`gemini.*` / `claude.*` / `gpt.*`，扩展名 `.tiktoken` 或 `.json`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TokenizerConfig {
+    /// 词表目录，默认为数据目录下的 `tokenizers`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dir: Option<String>,
+    /// 显式配置的分词器 (按顺序匹配，优先于自动发现)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub entries: Vec<TokenizerEntry>,
+}
+
+impl TokenizerConfig {
+    pub fn validate(&self, errors: &mut Vec<String>) {
+        for (i, entry) in self.entries.iter().enumerate() {
+            let prefix = format!("proxy.experimental.tokenizers.entries.{}", i);
+            if entry.name.trim().is_empty() {
+                errors.push(format!("{}.name must not be empty", prefix));
+            }
+            if entry.models.iter().all(|m| m.trim().is_empty()) {
+                errors.push(format!("{}.models must not be empty", prefix));
+            }
+            if entry.path.trim().is_empty() {
+                errors.push(format!("{}.path must not be empty", prefix));
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenizerEntry {
+    pub name: String,
+    /// 模型名称匹配 (支持 `*` 通配符，匹配映射后的模型)
+    pub models: Vec<String>,
+    /// 词表文件路径，相对路径基于 `dir`
+    pub path: String,
+    #[serde(default)]
+    pub format: TokenizerFormat,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenizerFormat {
+    /// 按扩展名判断: `.json` 为 HuggingFace tokenizer.json，其余为 tiktoken
+    #[default]
+    Auto,
+    /// tiktoken 词表 (每行 `<base64 token> <rank>`)
+    Tiktoken,
+    /// HuggingFace `tokenizer.json` (BPE 模型，支持 ByteLevel 与 SentencePiece Metaspace)
+    HfJson,
+}
+
+/// 工具结果压缩配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolResultCompressionConfig {
+    /// 未命中策略时单个工具结果的最大字符数
+    #[serde(default = "default_tool_result_max_chars")]
+    pub max_chars: usize,
+    /// 按工具名称匹配的压缩策略 (按顺序匹配第一条)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub policies: Vec<ToolResultPolicy>,
+}
+
+impl Default for ToolResultCompressionConfig {
+    fn default() -> Self {
+        Self {
+            max_chars: default_tool_result_max_chars(),
+            policies: Vec::new(),
+        }
+    }
+}
+
+impl ToolResultCompressionConfig {
+    pub fn validate(&self, errors: &mut Vec<String>) {
+        let prefix = "proxy.experimental.tool_result_compression";
+        if self.max_chars == 0 {
+            errors.push(format!("{}.max_chars must be greater than 0", prefix));
+        }
+        let prefix = format!("{}.policies", prefix);
+        let names = self.policies.iter().map(|p| p.name.as_str());
+        validate_names(&prefix, names, true, errors);
+        for (i, policy) in self.policies.iter().enumerate() {
+            let prefix = format!("{}.{}", prefix, i);
+            if policy.tools.iter().all(|t| t.trim().is_empty()) {
+                errors.push(format!("{}.tools must not be empty", prefix));
+            }
+            if policy.max_chars == Some(0) {
+                errors.push(format!("{}.max_chars must be greater than 0", prefix));
+            }
+            if !(0.0..=1.0).contains(&policy.head_ratio) {
+                errors.push(format!("{}.head_ratio must be between 0 and 1", prefix));
+            }
+            if let Some(pattern) = &policy.error_pattern {
+                if let Err(e) = regex::Regex::new(pattern) {
+                    errors.push(format!("{}.error_pattern is invalid: {}", prefix, e));
+                }
+            }
+        }
+    }
+}
+
+/// 单个工具结果压缩策略；超出 `max_chars` 时依次尝试去重、JSON 裁剪、头尾保留截断
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolResultPolicy {
+    /// 策略名称 (用于统计)
+    pub name: String,
+    /// 工具名称匹配 (支持 `*` 通配符)
+    pub tools: Vec<String>,
+    /// 最大字符数，未设置时使用全局 `max_chars`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_chars: Option<usize>,
+    /// 截断时头部所占比例，其余保留尾部
+    #[serde(default = "default_tool_result_head_ratio")]
+    pub head_ratio: f64,
+    /// 结果为 JSON 时裁剪长数组与长字符串，保持 JSON 有效
+    #[serde(default)]
+    pub json_prune: bool,
+    /// 合并连续重复的日志行 (忽略数字差异，如时间戳、序号)
+    #[serde(default)]
+    pub dedup_lines: bool,
+    /// 截断时保留被省略部分中的错误行
+    #[serde(default)]
+    pub keep_error_lines: bool,
+    /// 错误行正则，未设置时匹配 error / fail / panic / exception / traceback 等
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_pattern: Option<String>,
+}
+
+/// 工具调用参数校验失败时的处理方式
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolArgValidationMode {
+    /// 不校验
+    Off,
+    /// 仅记录警告日志
+    #[default]
+    Warn,
+    /// 重新请求上游，重试用尽后仍返回最后一次响应
+    Retry,
+    /// 重新请求上游，重试用尽后返回错误
+    Reject,
+}
+
+/// 工具调用参数校验配置
+///
+/// 仅作用于非流式响应 (含内部转为流式后收集的响应)。客户端请求流式输出时工具调用已实时下发，
+/// 不做校验也不记录警告，`retry` / `reject` 对这类请求同样无效。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolArgValidationConfig {
+    #[serde(default)]
+    pub mode: ToolArgValidationMode,
+    /// `retry` / `reject` 模式下的最大重试次数
+    #[serde(default = "default_tool_arg_max_retries")]
+    pub max_retries: u32,
+}
+
+impl Default for ToolArgValidationConfig {
+    fn default() -> Self {
+        Self {
+            mode: ToolArgValidationMode::default(),
+            max_retries: default_tool_arg_max_retries(),
+        }
+    }
+}
+
+impl ToolArgValidationConfig {
+    pub fn validate(&self, errors: &mut Vec<String>) {
+        if self.max_retries > 5 {
+            errors.push(
+                "proxy.experimental.tool_arg_validation.max_retries must not exceed 5".to_string(),
+            );
+        }
+    }
+}
+
+fn default_tool_arg_max_retries() -> u32 {
+    1
+}
+
+fn default_tool_result_max_chars() -> usize {
+    200_000
+}
+
+fn default_tool_result_head_ratio() -> f64 {
+    0.7
+}
+
+fn default_threshold_l1() -> f32 {
+    0.4
+}
+fn default_threshold_l2() -> f32 {
+    0.55
+}
+fn default_threshold_l3() -> f32 {
+    0.7
+}
+
+/// Thinking Budget 模式
+/// 控制如何处理调用方传入的 thinking_budget 参数
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ThinkingBudgetMode {
+    /// 自动限制：对特定模型（Flash/Thinking）应用 24576 上限
+    Auto,
+    /// 透传：完全使用调用方传入的值，不做任何修改
+    Passthrough,
+    /// 自定义：使用用户设定的固定值覆盖所有请求
+    Custom,
+    /// 自适应：使用 effort 参数控制思考强度 (Claude 4.6+)
+    Adaptive,
+}
+
+impl Default for ThinkingBudgetMode {
+    fn default() -> Self {
+        Self::Auto
+    }
+}
+
+/// Thinking Budget 配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThinkingBudgetConfig {
+    /// 模式选择
+    #[serde(default)]
+    pub mode: ThinkingBudgetMode,
+    /// 自定义固定值（仅在 mode=Custom 时生效）
+    #[serde(default = "default_thinking_budget_custom_value")]
+    pub custom_value: u32,
+    /// 思考强度 (仅在 mode=Adaptive 时生效) : low, medium, high
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effort: Option<String>,
+    /// 按模型家族 / 调用方的思考策略 (按顺序匹配，首个命中的策略生效)
+    ///
+    /// 在上述模式处理之后作用于最终的上游请求；路由规则覆盖的 Thinking Budget 不包含策略。
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub policies: Vec<ThinkingPolicy>,
+}
+
+impl Default for ThinkingBudgetConfig {
+    fn default() -> Self {
+        Self {
+            mode: ThinkingBudgetMode::Auto,
+            custom_value: default_thinking_budget_custom_value(),
+            effort: None,
+            policies: Vec::new(),
+        }
+    }
+}
+
+impl ThinkingBudgetConfig {
+    pub fn validate(&self, errors: &mut Vec<String>) {
+        let prefix = "proxy.thinking_budget.policies";
+        let names = self.policies.iter().map(|p| p.name.as_str());
+        validate_names(prefix, names, false, errors);
+        for (i, policy) in self.policies.iter().enumerate() {
+            if policy.action == ThinkingPolicyAction::Cap && policy.budget.unwrap_or(0) == 0 {
+                errors.push(format!(
+                    "{}.{}.budget must be greater than 0 when action is cap",
+                    prefix, i
+                ));
+            }
+        }
+    }
+}
+
+fn default_thinking_budget_custom_value() -> u32 {
+    24576
+}
+
+/// 思考策略动作
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ThinkingPolicyAction {
+    /// 将思考预算限制在 `budget` 以内
+    Cap,
+    /// 使用模型允许的最大思考预算
+    Max,
+    /// 关闭思考 (无法完全关闭的模型使用最小预算)
+    Off,
+}
+
+/// 单个思考策略
+///
+/// 条件之间为 AND 关系，未填写的条件视为匹配任意值。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThinkingPolicy {
+    pub name: String,
+    /// 适用的上游模型 (映射后的模型名，支持 `*` 通配符，如 `claude-opus-*`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub models: Vec<String>,
+    /// 适用的用户令牌 (用户名，支持 `*` 通配符)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub users: Vec<String>,
+    /// 适用的客户端适配器名称 (如 `opencode`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub clients: Vec<String>,
+    /// 是否仅匹配 (true) 或排除 (false) 后台任务 (标题生成、摘要等)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background: Option<bool>,
+    pub action: ThinkingPolicyAction,
+    /// 预算上限 (仅在 action=cap 时生效)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget: Option<u32>,
+}
+
+/// 单个上游状态码的重试行为覆盖
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusPolicy {
+    /// 是否重试该状态码
+    #[serde(default = "default_true")]
+    pub retry: bool,
+    /// 重试时是否轮换账号 (None 表示沿用内置判断)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotate: Option<bool>,
+    /// 固定重试延迟 (毫秒)，None 表示沿用内置退避策略
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delay_ms: Option<u64>,
+}
+
+/// 思维签名缓存持久化配置
+///
+/// 签名缓存保存在数据目录的 `signature_cache.json`，重启后恢复，
+/// 避免长会话在重启后因签名失效而被迫移除 thinking。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignatureCacheConfig {
+    /// 是否落盘并在启动时恢复
+    #[serde(default = "default_true")]
+    pub persist: bool,
+    /// 使用本机设备密钥加密缓存文件 (AES-256-GCM)
+    #[serde(default)]
+    pub encrypt: bool,
+    /// 落盘间隔 (秒)，仅在缓存有变化时写入
+    #[serde(default = "default_signature_flush_interval")]
+    pub flush_interval_secs: u64,
+}
+
+impl Default for SignatureCacheConfig {
+    fn default() -> Self {
+        Self {
+            persist: true,
+            encrypt: false,
+            flush_interval_secs: default_signature_flush_interval(),
+        }
+    }
+}
+
+// ============================================================================
+// 全局上下文窗口配置
+// 由 context_window 中间件在每个对话请求进入 handler 前读取
+// ============================================================================
+static GLOBAL_CONTEXT_WINDOW_CONFIG: OnceLock<RwLock<ContextWindowConfig>> = OnceLock::new();
+
+/// 获取当前上下文窗口配置
+pub fn get_context_window_config() -> ContextWindowConfig {
+    GLOBAL_CONTEXT_WINDOW_CONFIG
+        .get()
+        .and_then(|lock| lock.read().ok())
+        .map(|cfg| cfg.clone())
+        .unwrap_or_default()
+}
+
+/// 更新上下文窗口配置
+pub fn update_context_window_config(config: ContextWindowConfig) {
+    if let Some(lock) = GLOBAL_CONTEXT_WINDOW_CONFIG.get() {
+        if let Ok(mut cfg) = lock.write() {
+            tracing::info!(
+                "[ContextWindow] Config updated: {} policies, default={:?}",
+                config.policies.len(),
+                config.default_policy
+            );
+            *cfg = config;
+        }
+    } else {
+        tracing::info!(
+            "[ContextWindow] Config initialized: {} policies, default={:?}",
+            config.policies.len(),
+            config.default_policy
+        );
+        let _ = GLOBAL_CONTEXT_WINDOW_CONFIG.set(RwLock::new(config));
+    }
+}
+
+fn default_signature_flush_interval() -> u64 {
+    30
+}
+
+/// 响应缓存配置
+///
+/// 按规范化请求体 (模型、消息、工具、采样参数) 缓存完整响应，命中时按请求的协议
+/// 与流式模式直接回放，不消耗账号配额。适用于 CI 等反复发送相同提示词的场景。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseCacheConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// 缓存有效期 (秒)
+    #[serde(default = "default_response_cache_ttl")]
+    pub ttl_secs: u64,
+    /// 最大条目数，超出后按最近最少使用淘汰
+    #[serde(default = "default_response_cache_max_entries")]
+    pub max_entries: usize,
+    /// 缓存总大小上限 (MB)
+    #[serde(default = "default_response_cache_max_size_mb")]
+    pub max_size_mb: u64,
+    /// 仅缓存 temperature 为 0 的确定性请求
+    #[serde(default = "default_true")]
+    pub deterministic_only: bool,
+    /// 启用缓存的用户令牌 (用户名，支持 `*` 通配符)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub users: Vec<String>,
+    /// 未携带用户令牌的请求 (主 API Key 或免鉴权) 是否使用缓存
+    #[serde(default = "default_true")]
+    pub include_api_key: bool,
+    /// 不同用户令牌之间共享缓存 (默认按令牌隔离)
+    #[serde(default)]
+    pub shared: bool,
+}
+
+impl Default for ResponseCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl_secs: default_response_cache_ttl(),
+            max_entries: default_response_cache_max_entries(),
+            max_size_mb: default_response_cache_max_size_mb(),
+            deterministic_only: true,
+            users: Vec::new(),
+            include_api_key: true,
+            shared: false,
+        }
+    }
+}
+
+impl ResponseCacheConfig {
+    pub fn validate(&self, errors: &mut Vec<String>) {
+        if self.enabled {
+            if self.ttl_secs == 0 {
+                errors.push("proxy.response_cache.ttl_secs must be greater than 0".to_string());
+            }
+            if self.max_entries == 0 {
+                errors.push("proxy.response_cache.max_entries must be greater than 0".to_string());
+            }
+            if self.max_size_mb == 0 {
+                errors.push("proxy.response_cache.max_size_mb must be greater than 0".to_string());
+            }
+        }
+        for (i, user) in self.users.iter().enumerate() {
+            if user.trim().is_empty() {
+                errors.push(format!(
+                    "proxy.response_cache.users.{} must not be empty",
+                    i
+                ));
+            }
+        }
+    }
+}
+
+fn default_response_cache_ttl() -> u64 {
+    3600
+}
+
+fn default_response_cache_max_entries() -> usize {
+    1000
+}
+
+fn default_response_cache_max_size_mb() -> u64 {
+    64
+}
+
+/// 上下文窗口配置
+///
+/// 按用户令牌或客户端适配器选择策略，在转换为上游请求后裁剪 `contents`：
+/// 保留系统提示与开头的固定消息，从最早的轮次开始丢弃直到满足 Token 预算，
+/// 可选将被丢弃的轮次交给摘要模型压缩为一段摘要。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ContextWindowConfig {
+    /// 未匹配任何策略时使用的策略名称 (为空则不处理)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_policy: Option<String>,
+    /// 按顺序匹配，首个命中的策略生效
+    #[serde(default)]
+    pub policies: Vec<ContextPolicy>,
+}
+
+impl ContextWindowConfig {
+    pub fn validate(&self, errors: &mut Vec<String>) {
+        let prefix = "proxy.context_window.policies";
+        let names = self.policies.iter().map(|p| p.name.as_str());
+        validate_names(prefix, names, false, errors);
+        for (i, policy) in self.policies.iter().enumerate() {
+            let prefix = format!("{}.{}", prefix, i);
+            if policy.max_tokens == 0 {
+                errors.push(format!("{}.max_tokens must be greater than 0", prefix));
+            }
+            if policy.summarize {
+                if policy.summary_model.trim().is_empty() {
+                    errors.push(format!("{}.summary_model must not be empty", prefix));
+                }
+                if policy.summary_max_tokens == 0 || policy.summary_max_tokens >= policy.max_tokens
+                {
+                    errors.push(format!(
+                        "{}.summary_max_tokens must be between 1 and max_tokens",
+                        prefix
+                    ));
+                }
+            }
+        }
+        if let Some(name) = &self.default_policy {
+            if !self.policies.iter().any(|p| &p.name == name) {
+                errors.push(format!(
+                    "proxy.context_window.default_policy '{}' does not match any policy",
+                    name
+                ));
+            }
+        }
+    }
+}
+
+/// 单个上下文窗口策略
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextPolicy {
+    pub name: String,
+    /// 适用的用户令牌 (用户名，支持 `*` 通配符)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub users: Vec<String>,
+    /// 适用的客户端适配器名称 (如 `opencode`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub clients: Vec<String>,
+    /// 输入 Token 预算 (含系统提示与工具定义)
+    pub max_tokens: u32,
+    /// 始终保留的开头消息数
+    #[serde(default = "default_context_pinned_messages")]
+    pub pinned_messages: usize,
+    /// 至少保留的最近消息数 (即使超出预算)
+    #[serde(default = "default_context_min_recent_messages")]
+    pub min_recent_messages: usize,
+    /// 将被丢弃的轮次压缩为摘要
     #[serde(default)]
-    pub enabled: bool,
-    #[serde(default)]
-    pub web_search_enabled: bool,
+    pub summarize: bool,
+    /// 生成摘要使用的模型
+    #[serde(default = "default_context_summary_model")]
+    pub summary_model: String,
+    /// 摘要的最大输出 Token (同时从预算中预留)
+    #[serde(default = "default_context_summary_max_tokens")]
+    pub summary_max_tokens: u32,
+}
+
+fn default_context_pinned_messages() -> usize {
+    1
+}
+
+fn default_context_min_recent_messages() -> usize {
+    4
+}
+
+fn default_context_summary_model() -> String {
+    "gemini-2.5-flash".to_string()
+}
+
+fn default_context_summary_max_tokens() -> u32 {
+    1024
+}
+
+/// 脚本钩子配置
+///
+/// 在固定的处理阶段调用用户编写的 Rhai 脚本读取 / 修改 JSON，无需为客户端差异修改映射代码。
+/// 脚本运行在沙箱中 (无文件 / 网络访问)，受执行时间与操作数限制。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptHooksConfig {
+    /// 总开关
     #[serde(default)]
-    pub web_reader_enabled: bool,
+    pub enabled: bool,
+    /// 单个钩子的默认执行时限 (毫秒)
+    #[serde(default = "default_script_timeout_ms")]
+    pub timeout_ms: u64,
+    /// 单个钩子的最大操作数
+    #[serde(default = "default_script_max_operations")]
+    pub max_operations: u64,
+    /// 按顺序执行，同一阶段的多个钩子依次作用于上一个钩子的结果
     #[serde(default)]
-    pub vision_enabled: bool,
+    pub hooks: Vec<ScriptHook>,
 }
 
-impl Default for ZaiMcpConfig {
+impl Default for ScriptHooksConfig {
     fn default() -> Self {
         Self {
             enabled: false,
-            web_search_enabled: false,
-            web_reader_enabled: false,
-            vision_enabled: false,
+            timeout_ms: default_script_timeout_ms(),
+            max_operations: default_script_max_operations(),
+            hooks: Vec::new(),
+        }
+    }
+}
+
+impl ScriptHooksConfig {
+    pub fn validate(&self, errors: &mut Vec<String>) {
+        if self.timeout_ms == 0 {
+            errors.push("proxy.script_hooks.timeout_ms must be greater than 0".to_string());
+        }
+        if self.max_operations == 0 {
+            errors.push("proxy.script_hooks.max_operations must be greater than 0".to_string());
+        }
+        let prefix = "proxy.script_hooks.hooks";
+        let names = self.hooks.iter().map(|h| h.name.as_str());
+        validate_names(prefix, names, false, errors);
+        for (i, hook) in self.hooks.iter().enumerate() {
+            let prefix = format!("{}.{}", prefix, i);
+            if hook.script.is_some() == hook.file.is_some() {
+                errors.push(format!(
+                    "{}: exactly one of script or file must be set",
+                    prefix
+                ));
+            }
+            if hook.timeout_ms == Some(0) {
+                errors.push(format!("{}.timeout_ms must be greater than 0", prefix));
+            }
+            validate_protocols(&prefix, &hook.protocols, errors);
         }
     }
 }
 
+fn default_script_timeout_ms() -> u64 {
+    20
+}
+
+fn default_script_max_operations() -> u64 {
+    500_000
+}
+
+/// 钩子的调用阶段
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScriptHookStage {
+    /// 解析客户端请求之后、进入 handler 之前 (客户端协议格式)
+    Request,
+    /// 转换为 v1internal 请求之后、发送到上游之前
+    UpstreamRequest,
+    /// 返回给客户端的每个 SSE 事件 (`data:` 行的 JSON)
+    SseEvent,
+}
+
+/// 钩子执行失败 (脚本错误 / 超时) 时的处理方式
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScriptHookErrorMode {
+    /// 记录警告并保留钩子执行前的内容
+    #[default]
+    Ignore,
+    /// 拒绝请求 (SSE 阶段为丢弃该事件)
+    Reject,
+}
+
+/// 单个脚本钩子
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ZaiConfig {
-    #[serde(default)]
+pub struct ScriptHook {
+    pub name: String,
+    #[serde(default = "default_true")]
     pub enabled: bool,
-    #[serde(default = "default_zai_base_url")]
-    pub base_url: String,
-    #[serde(default)]
-    pub api_key: String,
+    pub stage: ScriptHookStage,
+    /// 内联脚本
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script: Option<String>,
+    /// 脚本文件 (相对路径基于数据目录)，与 `script` 二选一
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// 适用的协议: `openai` / `anthropic` (或 `claude`) / `gemini`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub protocols: Vec<String>,
+    /// 适用的模型 (支持 `*` 通配符；upstream_request 阶段为映射后的模型)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub models: Vec<String>,
+    /// 适用的用户令牌 (用户名，支持 `*` 通配符)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub users: Vec<String>,
+    /// 适用的客户端适配器名称
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub clients: Vec<String>,
+    /// 覆盖默认执行时限 (毫秒)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
     #[serde(default)]
-    pub dispatch_mode: ZaiDispatchMode,
-    /// Optional per-model mapping overrides for Anthropic/Claude model ids.
-    /// Key: incoming `model` string, Value: upstream z.ai model id (e.g. `glm-4.7`).
+    pub on_error: ScriptHookErrorMode,
+}
+
+/// 请求调度配置 (重试次数/总时限/退避抖动/对冲请求)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DispatchConfig {
+    /// 单个请求的最大尝试次数 (仍受账号池大小限制)
+    #[serde(default = "default_dispatch_max_attempts")]
+    pub max_attempts: usize,
+    /// 单个请求 (含全部重试与退避) 的总时限，0 表示不限制；进行中的上游调用到期即取消 (流式响应仅限制到收到响应头)
     #[serde(default)]
-    pub model_mapping: HashMap<String, String>,
+    pub request_deadline_secs: u64,
+    /// 退避延迟的随机抖动比例 (0.0 - 1.0)
+    #[serde(default = "default_dispatch_jitter_ratio")]
+    pub jitter_ratio: f64,
+    /// 对冲请求延迟 (毫秒)：非流式的音频/图像请求在该时间内未返回时，
+    /// 使用另一个账号并发发起第二个请求并采用先成功的结果；0 表示关闭
     #[serde(default)]
-    pub models: ZaiModelDefaults,
+    pub hedge_delay_ms: u64,
+    /// 按上游状态码覆盖重试行为，如 `{"503": {"retry": false}}`
     #[serde(default)]
-    pub mcp: ZaiMcpConfig,
+    pub status_overrides: HashMap<u16, StatusPolicy>,
 }
 
-impl Default for ZaiConfig {
+impl Default for DispatchConfig {
     fn default() -> Self {
         Self {
-            enabled: false,
-            base_url: default_zai_base_url(),
-            api_key: String::new(),
-            dispatch_mode: ZaiDispatchMode::Off,
-            model_mapping: HashMap::new(),
-            models: ZaiModelDefaults::default(),
-            mcp: ZaiMcpConfig::default(),
+            max_attempts: default_dispatch_max_attempts(),
+            request_deadline_secs: 0,
+            jitter_ratio: default_dispatch_jitter_ratio(),
+            hedge_delay_ms: 0,
+            status_overrides: HashMap::new(),
         }
     }
 }
 
-/// 实验性功能配置 (Feature Flags)
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ExperimentalConfig {
-    /// 启用双层签名缓存 (Signature Cache)
-    #[serde(default = "default_true")]
-    pub enable_signature_cache: bool,
+impl DispatchConfig {
+    pub fn validate(&self, errors: &mut Vec<String>) {
+        if self.max_attempts == 0 {
+            errors.push("proxy.dispatch.max_attempts must be greater than 0".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.jitter_ratio) {
+            errors.push("proxy.dispatch.jitter_ratio must be between 0.0 and 1.0".to_string());
+        }
+        for code in self.status_overrides.keys() {
+            if !(100..=599).contains(code) {
+                errors.push(format!(
+                    "proxy.dispatch.status_overrides.{} is not a valid HTTP status code",
+                    code
+                ));
+            }
+        }
+    }
+}
 
-    /// 启用工具循环自动恢复 (Tool Loop Recovery)
-    #[serde(default = "default_true")]
-    pub enable_tool_loop_recovery: bool,
+fn default_dispatch_max_attempts() -> usize {
+    3
+}
 
-    /// 启用跨模型兼容性检查 (Cross-Model Checks)
-    #[serde(default = "default_true")]
-    pub enable_cross_model_checks: bool,
+fn default_dispatch_jitter_ratio() -> f64 {
+    0.2
+}
 
-    /// 启用上下文用量缩放 (Context Usage Scaling)
-    /// 激进模式: 缩放用量并激活自动压缩以突破 200k 限制
-    /// 默认关闭以保持透明度,让客户端能触发原生压缩指令
-    #[serde(default = "default_false")]
-    pub enable_usage_scaling: bool,
+/// 路由规则匹配条件，所有已设置的条件均满足时命中
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RouteMatch {
+    /// 入口协议 (openai / anthropic / gemini)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<ProviderProtocol>,
+    /// 客户端请求的模型 (支持 `*` 通配符)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// User Token 用户名 (支持 `*` 通配符)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    /// 请求头: Key 为头名称 (不区分大小写)，Value 为取值 (支持 `*` 通配符)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+    /// 命中的客户端适配器名称 (如 `opencode`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client: Option<String>,
+    /// 请求体最小字节数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_bytes: Option<usize>,
+    /// 请求体最大字节数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_bytes: Option<usize>,
+    /// 是否携带工具定义
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub has_tools: Option<bool>,
+    /// 检测到的后台任务类型
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_type: Option<crate::proxy::common::task_detection::BackgroundTaskType>,
+}
 
-    /// 上下文压缩阈值 L1 (Tool Trimming)
-    #[serde(default = "default_threshold_l1")]
-    pub context_compression_threshold_l1: f32,
+/// 路由规则命中后执行的动作
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RouteAction {
+    /// 替换目标模型
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// 交给指定的第三方提供商 (`proxy.providers` 中的名称)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// 仅从指定账号分组 (`proxy.routing.account_groups`) 中选择账号
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account_group: Option<String>,
+    /// 覆盖本次请求的 Thinking Budget 配置
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thinking_budget: Option<ThinkingBudgetConfig>,
+}
 
-    /// 上下文压缩阈值 L2 (Thinking Compression)
-    #[serde(default = "default_threshold_l2")]
-    pub context_compression_threshold_l2: f32,
+/// 单条路由规则
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutingRule {
+    pub name: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default, rename = "match")]
+    pub conditions: RouteMatch,
+    #[serde(default)]
+    pub action: RouteAction,
+    /// 命中后是否停止评估后续规则 (默认 true；为 false 时后续规则可补充尚未设置的动作)
+    #[serde(default = "default_true")]
+    pub stop: bool,
+}
 
-    /// 上下文压缩阈值 L3 (Fork + Summary)
-    #[serde(default = "default_threshold_l3")]
-    pub context_compression_threshold_l3: f32,
+/// 路由规则引擎配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RoutingConfig {
+    /// 按顺序评估的规则
+    #[serde(default)]
+    pub rules: Vec<RoutingRule>,
+    /// 账号分组: Key 为分组名，Value 为账号 ID 或邮箱
+    #[serde(default)]
+    pub account_groups: HashMap<String, Vec<String>>,
 }
 
-impl Default for ExperimentalConfig {
-    fn default() -> Self {
-        Self {
-            enable_signature_cache: true,
-            enable_tool_loop_recovery: true,
-            enable_cross_model_checks: true,
-            enable_usage_scaling: false, // 默认关闭,回归透明模式
-            context_compression_threshold_l1: 0.4,
-            context_compression_threshold_l2: 0.55,
-            context_compression_threshold_l3: 0.7,
+impl RoutingConfig {
+    /// `providers` 用于检查规则引用的上游提供商是否存在
+    pub fn validate(&self, providers: &[UpstreamProviderConfig], errors: &mut Vec<String>) {
+        for (i, rule) in self.rules.iter().enumerate() {
+            if rule.name.trim().is_empty() {
+                errors.push(format!("proxy.routing.rules.{}.name must not be empty", i));
+            }
+            if let Some(group) = &rule.action.account_group {
+                if !self.account_groups.contains_key(group) {
+                    errors.push(format!(
+                        "proxy.routing.rules.{}.action.account_group '{}' is not defined in proxy.routing.account_groups",
+                        i, group
+                    ));
+                }
+            }
+            if let Some(provider) = &rule.action.provider {
+                if !providers
+                    .iter()
+                    .any(|p| p.name.eq_ignore_ascii_case(provider))
+                {
+                    errors.push(format!(
+                        "proxy.routing.rules.{}.action.provider '{}' is not defined in proxy.providers",
+                        i, provider
+                    ));
+                }
+            }
+            if let (Some(min), Some(max)) = (rule.conditions.min_bytes, rule.conditions.max_bytes) {
+                if min > max {
+                    errors.push(format!(
+                        "proxy.routing.rules.{}.match.min_bytes must not exceed max_bytes",
+                        i
+                    ));
+                }
+            }
         }
     }
 }
 
-fn default_threshold_l1() -> f32 {
-    0.4
-}
-fn default_threshold_l2() -> f32 {
-    0.55
+/// 声明式客户端适配器的匹配条件 (所有已设置的条件均满足时命中)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClientMatchConfig {
+    /// User-Agent 匹配: 含 `*` 时按通配符匹配，否则按子串匹配 (均不区分大小写)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    /// 请求头: Key 为头名称 (不区分大小写)，Value 为取值 (支持 `*` 通配符)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
 }
-fn default_threshold_l3() -> f32 {
-    0.7
+
+impl ClientMatchConfig {
+    pub fn is_empty(&self) -> bool {
+        self.user_agent.is_none() && self.headers.is_empty()
+    }
 }
 
-/// Thinking Budget 模式
-/// 控制如何处理调用方传入的 thinking_budget 参数
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-#[serde(rename_all = "snake_case")]
-pub enum ThinkingBudgetMode {
-    /// 自动限制：对特定模型（Flash/Thinking）应用 24576 上限
-    Auto,
-    /// 透传：完全使用调用方传入的值，不做任何修改
-    Passthrough,
-    /// 自定义：使用用户设定的固定值覆盖所有请求
-    Custom,
-    /// 自适应：使用 effort 参数控制思考强度 (Claude 4.6+)
-    Adaptive,
+/// 工具定义调整 (请求转发前应用)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ToolSchemaTweaks {
+    /// 移除名称匹配的工具 (支持 `*` 通配符)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub drop_tools: Vec<String>,
+    /// 从参数 Schema 中递归移除的字段 (如 `$schema`、`examples`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remove_keys: Vec<String>,
+    /// 工具描述最大字符数，超出部分截断
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_description_chars: Option<usize>,
 }
 
-impl Default for ThinkingBudgetMode {
-    fn default() -> Self {
-        Self::Auto
-    }
+/// 响应调整
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResponseQuirks {
+    /// 追加到响应的头部
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+    /// 移除 OpenAI 响应中的 `reasoning_content` 字段 (部分客户端无法解析)
+    #[serde(default)]
+    pub strip_reasoning_content: bool,
 }
 
-/// Thinking Budget 配置
+/// 声明式客户端适配器 (无需修改代码即可为特定客户端定制行为)
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ThinkingBudgetConfig {
-    /// 模式选择
-    #[serde(default)]
-    pub mode: ThinkingBudgetMode,
-    /// 自定义固定值（仅在 mode=Custom 时生效）
-    #[serde(default = "default_thinking_budget_custom_value")]
-    pub custom_value: u32,
-    /// 思考强度 (仅在 mode=Adaptive 时生效) : low, medium, high
+pub struct ClientAdapterConfig {
+    /// 适配器名称 (用于日志及路由规则中的 `client` 条件)
+    pub name: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default, rename = "match")]
+    pub matcher: ClientMatchConfig,
+    /// 签名缓存策略 (default / fifo / lifo)
     #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub effort: Option<String>,
+    pub signature_buffer: Option<crate::proxy::common::client_adapter::SignatureBufferStrategy>,
+    /// 出错时快速失败，不做额外重试
+    #[serde(default)]
+    pub let_it_crash: bool,
+    /// 移除请求中的 thinking 配置与历史 thinking 块
+    #[serde(default)]
+    pub strip_thinking: bool,
+    /// 注入到上游请求的 `anthropic-beta` 取值
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub beta_headers: Vec<String>,
+    #[serde(default)]
+    pub tool_schema: ToolSchemaTweaks,
+    #[serde(default)]
+    pub response: ResponseQuirks,
 }
 
-impl Default for ThinkingBudgetConfig {
-    fn default() -> Self {
-        Self {
-            mode: ThinkingBudgetMode::Auto,
-            custom_value: default_thinking_budget_custom_value(),
-            effort: None,
+impl ClientAdapterConfig {
+    /// 名称唯一性由 [`ProxyConfig::validate`] 检查
+    pub fn validate(&self, prefix: &str, errors: &mut Vec<String>) {
+        if self.matcher.is_empty() {
+            errors.push(format!("{}.match must set user_agent or headers", prefix));
+        }
+        for name in self.response.headers.keys() {
+            if axum::http::HeaderName::from_bytes(name.as_bytes()).is_err() {
+                errors.push(format!(
+                    "{}.response.headers '{}' is not a valid header name",
+                    prefix, name
+                ));
+            }
         }
     }
 }
 
-fn default_thinking_budget_custom_value() -> u32 {
-    24576
+/// 基于 JSON Pointer (RFC 6901) 的 Schema 修改
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum SchemaEdit {
+    /// 设置指定位置的值 (中间对象不存在时自动创建)
+    Set {
+        pointer: String,
+        value: serde_json::Value,
+    },
+    /// 删除指定位置的值
+    Remove { pointer: String },
+    /// 将对象浅合并到指定位置
+    Merge {
+        pointer: String,
+        value: serde_json::Value,
+    },
+    /// 向指定位置 Schema 的 description 追加提示
+    Hint { pointer: String, hint: String },
+}
+
+/// 工具调用参数改写 (上游返回的参数转发给客户端前应用)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum ArgRewrite {
+    /// 移动参数 (如模型使用了错误的参数名)，目标已存在时不覆盖
+    Rename { from: String, to: String },
+    /// 参数缺失时填充默认值
+    Default {
+        pointer: String,
+        value: serde_json::Value,
+    },
+    /// 强制设置参数
+    Set {
+        pointer: String,
+        value: serde_json::Value,
+    },
+    /// 删除参数
+    Remove { pointer: String },
+}
+
+/// 声明式 MCP 工具适配器 (无需修改代码即可修正特定工具的 Schema 与参数)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolAdapterConfig {
+    pub name: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// 工具名称匹配 (支持 `*` 通配符，如 `mcp__github__*`)
+    pub tools: Vec<String>,
+    /// 通用清洗前应用的 Schema 修改
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub schema_edits: Vec<SchemaEdit>,
+    /// 通用清洗后应用的 Schema 修改 (可恢复被清洗移除的内容)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_edits: Vec<SchemaEdit>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub arg_rewrites: Vec<ArgRewrite>,
+}
+
+impl ToolAdapterConfig {
+    /// 名称唯一性由 [`ProxyConfig::validate`] 检查
+    pub fn validate(&self, prefix: &str, errors: &mut Vec<String>) {
+        if self.tools.iter().all(|t| t.trim().is_empty()) {
+            errors.push(format!("{}.tools must not be empty", prefix));
+        }
+        let edit_pointers = self
+            .schema_edits
+            .iter()
+            .chain(self.post_edits.iter())
+            .map(|edit| match edit {
+                SchemaEdit::Set { pointer, .. }
+                | SchemaEdit::Remove { pointer }
+                | SchemaEdit::Merge { pointer, .. }
+                | SchemaEdit::Hint { pointer, .. } => pointer,
+            });
+        let arg_pointers = self.arg_rewrites.iter().flat_map(|rewrite| match rewrite {
+            ArgRewrite::Rename { from, to } => vec![from, to],
+            ArgRewrite::Default { pointer, .. }
+            | ArgRewrite::Set { pointer, .. }
+            | ArgRewrite::Remove { pointer } => vec![pointer],
+        });
+        for pointer in edit_pointers.chain(arg_pointers) {
+            if !pointer.is_empty() && !pointer.starts_with('/') {
+                errors.push(format!(
+                    "{}: JSON Pointer '{}' must start with '/'",
+                    prefix, pointer
+                ));
+            }
+        }
+    }
 }
 
 fn default_true() -> bool {
@@ -510,6 +1880,47 @@ pub struct ProxyConfig {
     #[serde(default)]
     pub zai: ZaiConfig,
 
+    /// 通用第三方上游提供商列表
+    #[serde(default)]
+    pub providers: Vec<UpstreamProviderConfig>,
+
+    /// 模型降级链: Key 为客户端模型 (支持 `*` 通配符)，Value 为按顺序尝试的替代模型。
+    /// 当 Google 账号池对原模型无可用账号时依次检查，`名称:模型` 表示第三方提供商。
+    #[serde(default)]
+    pub fallback_chains: HashMap<String, Vec<String>>,
+
+    /// 请求调度配置 (重试/总时限/对冲请求)
+    #[serde(default)]
+    pub dispatch: DispatchConfig,
+
+    /// 路由规则引擎 (按协议/模型/用户/请求头等条件改写模型、账号分组、Thinking Budget 或提供商)
+    #[serde(default)]
+    pub routing: RoutingConfig,
+
+    /// 声明式客户端适配器 (优先于内置适配器匹配)
+    #[serde(default)]
+    pub client_adapters: Vec<ClientAdapterConfig>,
+
+    /// 声明式 MCP 工具适配器 (优先于内置适配器匹配)
+    #[serde(default)]
+    pub tool_adapters: Vec<ToolAdapterConfig>,
+
+    /// 思维签名缓存持久化
+    #[serde(default)]
+    pub signature_cache: SignatureCacheConfig,
+
+    /// 响应缓存 (确定性请求)
+    #[serde(default)]
+    pub response_cache: ResponseCacheConfig,
+
+    /// 上下文窗口策略 (滑动窗口 / 摘要)
+    #[serde(default)]
+    pub context_window: ContextWindowConfig,
+
+    /// 脚本钩子 (Rhai)
+    #[serde(default)]
+    pub script_hooks: ScriptHooksConfig,
+
     /// 自定义 User-Agent 请求头 (可选覆盖)
     #[serde(default)]
     pub user_agent_override: Option<String>,
@@ -566,6 +1977,16 @@ pub struct UpstreamProxyConfig {
     pub url: String,
 }
 
+impl UpstreamProxyConfig {
+    pub fn validate(&self, errors: &mut Vec<String>) {
+        if self.enabled && self.url.trim().is_empty() {
+            errors.push(
+                "proxy.upstream_proxy.url is required when upstream proxy is enabled".to_string(),
+            );
+        }
+    }
+}
+
 impl Default for ProxyConfig {
     fn default() -> Self {
         Self {
@@ -582,6 +2003,16 @@ impl Default for ProxyConfig {
             debug_logging: DebugLoggingConfig::default(),
             upstream_proxy: UpstreamProxyConfig::default(),
             zai: ZaiConfig::default(),
+            providers: Vec::new(),
+            fallback_chains: HashMap::new(),
+            dispatch: DispatchConfig::default(),
+            routing: RoutingConfig::default(),
+            client_adapters: Vec::new(),
+            tool_adapters: Vec::new(),
+            signature_cache: SignatureCacheConfig::default(),
+            response_cache: ResponseCacheConfig::default(),
+            context_window: ContextWindowConfig::default(),
+            script_hooks: ScriptHooksConfig::default(),
             scheduling: crate::proxy::sticky_config::StickySessionConfig::default(),
             experimental: ExperimentalConfig::default(),
             security_monitor: SecurityMonitorConfig::default(),
@@ -627,6 +2058,63 @@ impl ProxyConfig {
             "127.0.0.1"
         }
     }
+
+    /// 校验代理配置，错误信息以字段路径开头 (由 config_loader::validate_app_config 汇总)
+    pub fn validate(&self, errors: &mut Vec<String>) {
+        if self.port == 0 {
+            errors.push("proxy.port must be between 1 and 65535".to_string());
+        }
+        if self.api_key.trim().is_empty() {
+            errors.push("proxy.api_key must not be empty".to_string());
+        }
+        if self.request_timeout == 0 {
+            errors.push("proxy.request_timeout must be greater than 0".to_string());
+        }
+        self.upstream_proxy.validate(errors);
+        self.zai.validate(errors);
+        let mut provider_names = std::collections::HashSet::new();
+        for (i, provider) in self.providers.iter().enumerate() {
+            let name = provider.name.trim();
+            if name.is_empty() || name.contains(':') {
+                errors.push(format!(
+                    "proxy.providers.{}.name must be non-empty and must not contain ':'",
+                    i
+                ));
+            } else if !provider_names.insert(name.to_lowercase()) {
+                errors.push(format!(
+                    "proxy.providers.{}.name '{}' is duplicated",
+                    i, name
+                ));
+            }
+            if !provider.base_url.starts_with("http://")
+                && !provider.base_url.starts_with("https://")
+            {
+                errors.push(format!(
+                    "proxy.providers.{}.base_url must start with http:// or https://",
+                    i
+                ));
+            }
+        }
+        self.dispatch.validate(errors);
+        self.routing.validate(&self.providers, errors);
+        let names = self.client_adapters.iter().map(|a| a.name.as_str());
+        validate_names("proxy.client_adapters", names, true, errors);
+        for (i, adapter) in self.client_adapters.iter().enumerate() {
+            adapter.validate(&format!("proxy.client_adapters.{}", i), errors);
+        }
+        let names = self.tool_adapters.iter().map(|a| a.name.as_str());
+        validate_names("proxy.tool_adapters", names, true, errors);
+        for (i, adapter) in self.tool_adapters.iter().enumerate() {
+            adapter.validate(&format!("proxy.tool_adapters.{}", i), errors);
+        }
+        self.experimental.validate(errors);
+        self.response_cache.validate(errors);
+        self.context_window.validate(errors);
+        self.global_system_prompt.validate(errors);
+        self.thinking_budget.validate(errors);
+        self.script_hooks.validate(errors);
+        self.proxy_pool.validate(errors);
+    }
 }
 
 /// 代理认证信息
@@ -685,6 +2173,23 @@ impl Default for ProxyPoolConfig {
     }
 }
 
+impl ProxyPoolConfig {
+    pub fn validate(&self, errors: &mut Vec<String>) {
+        if self.enabled && self.health_check_interval == 0 {
+            errors
+                .push("proxy.proxy_pool.health_check_interval must be greater than 0".to_string());
+        }
+        for (i, entry) in self.proxies.iter().enumerate() {
+            if entry.url.trim().is_empty() {
+                errors.push(format!(
+                    "proxy.proxy_pool.proxies.{}.url must not be empty",
+                    i
+                ));
+            }
+        }
+    }
+}
+
 /// 代理选择策略
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
@@ -721,4 +2226,31 @@ mod tests {
         assert_eq!(normalize_proxy_url(""), "");
         assert_eq!(normalize_proxy_url("   "), "");
     }
+
+    #[test]
+    fn test_section_validate_reports_field_paths() {
+        let mut errors = Vec::new();
+        validate_names("x.items", ["a", "A", ""].into_iter(), true, &mut errors);
+        assert_eq!(
+            errors,
+            vec![
+                "x.items.1.name 'A' is duplicated".to_string(),
+                "x.items.2.name must not be empty".to_string(),
+            ]
+        );
+
+        let routing: RoutingConfig = serde_json::from_value(serde_json::json!({
+            "rules": [{ "name": "r", "action": { "provider": "missing", "account_group": "g" } }]
+        }))
+        .unwrap();
+        let mut errors = Vec::new();
+        routing.validate(&[], &mut errors);
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].starts_with("proxy.routing.rules.0.action.account_group 'g'"));
+        assert!(errors[1].starts_with("proxy.routing.rules.0.action.provider 'missing'"));
+
+        let mut errors = Vec::new();
+        ProxyConfig::default().validate(&mut errors);
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
 }
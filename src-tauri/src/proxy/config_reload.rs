@@ -0,0 +1,404 @@
+//! 配置文件热重载
+//!
+//! 定时轮询 gui_config.json (兼容 Docker bind mount 及 Ansible 等外部工具直接改写文件)，
+//! Headless 模式下同时轮询 `--config` 文件，并按启动时相同的分层 (含 `ABV_*` 环境变量与 Headless 兼容覆盖) 重新加载。
+//! 变化后重新加载并校验 `AppConfig`，只把发生变化的配置段通过既有的热更新接口应用到运行中的反代服务。
+//! 无法解析或校验失败的配置会被拒绝，运行中的服务保持原配置不变。
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tracing::{debug, error, info, warn};
+
+use crate::models::AppConfig;
+use crate::modules::config_loader::reload_config;
+use crate::proxy::monitor::ProxyMonitor;
+use crate::proxy::server::AxumServer;
+
+/// 轮询间隔
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// 可热更新的配置段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigSection {
+    CustomMapping,
+    UpstreamProxy,
+    ProxyPool,
+    Security,
+    Zai,
+    Providers,
+    Experimental,
+    DebugLogging,
+    UserAgent,
+    ThinkingBudget,
+    GlobalSystemPrompt,
+    ImageThinkingMode,
+    FallbackChains,
+    Dispatch,
+    Routing,
+    ClientAdapters,
+    ToolAdapters,
+    SignatureCache,
+    ResponseCache,
+    ContextWindow,
+    ScriptHooks,
+    Scheduling,
+    PreferredAccount,
+    RequestLogging,
+    CircuitBreaker,
+}
+
+impl ConfigSection {
+    /// 日志中展示的配置路径
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::CustomMapping => "proxy.custom_mapping",
+            Self::UpstreamProxy => "proxy.upstream_proxy",
+            Self::ProxyPool => "proxy.proxy_pool",
+            Self::Security => "proxy.security",
+            Self::Zai => "proxy.zai",
+            Self::Providers => "proxy.providers",
+            Self::Experimental => "proxy.experimental",
+            Self::DebugLogging => "proxy.debug_logging",
+            Self::UserAgent => "proxy.user_agent_override",
+            Self::ThinkingBudget => "proxy.thinking_budget",
+            Self::GlobalSystemPrompt => "proxy.global_system_prompt",
+            Self::ImageThinkingMode => "proxy.image_thinking_mode",
+            Self::FallbackChains => "proxy.fallback_chains",
+            Self::Dispatch => "proxy.dispatch",
+            Self::Routing => "proxy.routing",
+            Self::ClientAdapters => "proxy.client_adapters",
+            Self::ToolAdapters => "proxy.tool_adapters",
+            Self::SignatureCache => "proxy.signature_cache",
+            Self::ResponseCache => "proxy.response_cache",
+            Self::ContextWindow => "proxy.context_window",
+            Self::ScriptHooks => "proxy.script_hooks",
+            Self::Scheduling => "proxy.scheduling",
+            Self::PreferredAccount => "proxy.preferred_account_id",
+            Self::RequestLogging => "proxy.enable_logging",
+            Self::CircuitBreaker => "circuit_breaker",
+        }
+    }
+}
+
+/// 通过序列化结果比较，避免为每个配置结构体额外实现 PartialEq
+fn changed<T: Serialize>(old: &T, new: &T) -> bool {
+    serde_json::to_value(old).ok() != serde_json::to_value(new).ok()
+}
+
+/// 计算两份配置之间发生变化的可热更新配置段
+pub fn diff_sections(old: &AppConfig, new: &AppConfig) -> Vec<ConfigSection> {
+    let (o, n) = (&old.proxy, &new.proxy);
+    let mut sections = Vec::new();
+
+    if changed(&o.custom_mapping, &n.custom_mapping) {
+        sections.push(ConfigSection::CustomMapping);
+    }
+    if changed(&o.upstream_proxy, &n.upstream_proxy) {
+        sections.push(ConfigSection::UpstreamProxy);
+    }
+    if changed(&o.proxy_pool, &n.proxy_pool) {
+        sections.push(ConfigSection::ProxyPool);
+    }
+    // 与 ProxySecurityConfig::from_proxy_config 读取的字段保持一致
+    if changed(
+        &(
+            &o.auth_mode,
+            &o.api_key,
+            &o.admin_password,
+            o.allow_lan_access,
+            &o.security_monitor,
+        ),
+        &(
+            &n.auth_mode,
+            &n.api_key,
+            &n.admin_password,
+            n.allow_lan_access,
+            &n.security_monitor,
+        ),
+    ) {
+        sections.push(ConfigSection::Security);
+    }
+    if changed(&o.zai, &n.zai) {
+        sections.push(ConfigSection::Zai);
+    }
+    if changed(&o.providers, &n.providers) {
+        sections.push(ConfigSection::Providers);
+    }
+    if changed(&o.experimental, &n.experimental) {
+        sections.push(ConfigSection::Experimental);
+    }
+    if changed(&o.debug_logging, &n.debug_logging) {
+        sections.push(ConfigSection::DebugLogging);
+    }
+    if o.user_agent_override != n.user_agent_override {
+        sections.push(ConfigSection::UserAgent);
+    }
+    if changed(&o.thinking_budget, &n.thinking_budget) {
+        sections.push(ConfigSection::ThinkingBudget);
+    }
+    if changed(&o.global_system_prompt, &n.global_system_prompt) {
+        sections.push(ConfigSection::GlobalSystemPrompt);
+    }
+    if o.image_thinking_mode != n.image_thinking_mode {
+        sections.push(ConfigSection::ImageThinkingMode);
+    }
+    if changed(&o.fallback_chains, &n.fallback_chains) {
+        sections.push(ConfigSection::FallbackChains);
+    }
+    if changed(&o.dispatch, &n.dispatch) {
+        sections.push(ConfigSection::Dispatch);
+    }
+    if changed(&o.routing, &n.routing) {
+        sections.push(ConfigSection::Routing);
+    }
+    if changed(&o.client_adapters, &n.client_adapters) {
+        sections.push(ConfigSection::ClientAdapters);
+    }
+    if changed(&o.tool_adapters, &n.tool_adapters) {
+        sections.push(ConfigSection::ToolAdapters);
+    }
+    if changed(&o.signature_cache, &n.signature_cache) {
+        sections.push(ConfigSection::SignatureCache);
+    }
+    if changed(&o.response_cache, &n.response_cache) {
+        sections.push(ConfigSection::ResponseCache);
+    }
+    if changed(&o.context_window, &n.context_window) {
+        sections.push(ConfigSection::ContextWindow);
+    }
+    if changed(&o.script_hooks, &n.script_hooks) {
+        sections.push(ConfigSection::ScriptHooks);
+    }
+    if changed(&o.scheduling, &n.scheduling) {
+        sections.push(ConfigSection::Scheduling);
+    }
+    if o.preferred_account_id != n.preferred_account_id {
+        sections.push(ConfigSection::PreferredAccount);
+    }
+    if o.enable_logging != n.enable_logging {
+        sections.push(ConfigSection::RequestLogging);
+    }
+    if changed(&old.circuit_breaker, &new.circuit_breaker) {
+        sections.push(ConfigSection::CircuitBreaker);
+    }
+
+    sections
+}
+
+/// 发生变化但必须重启才能生效的字段 (监听地址/端口在启动时绑定)
+pub fn restart_required_changes(old: &AppConfig, new: &AppConfig) -> Vec<&'static str> {
+    let (o, n) = (&old.proxy, &new.proxy);
+    let mut fields = Vec::new();
+    if o.port != n.port {
+        fields.push("proxy.port");
+    }
+    if o.allow_lan_access != n.allow_lan_access {
+        fields.push("proxy.allow_lan_access (bind address)");
+    }
+    if o.request_timeout != n.request_timeout {
+        fields.push("proxy.request_timeout");
+    }
+    fields
+}
+
+/// 通过既有热更新路径应用指定配置段
+pub async fn apply_sections(
+    server: &AxumServer,
+    monitor: &ProxyMonitor,
+    config: &AppConfig,
+    sections: &[ConfigSection],
+) {
+    let proxy = &config.proxy;
+    for section in sections {
+        match section {
+            ConfigSection::CustomMapping => server.update_mapping(proxy).await,
+            ConfigSection::UpstreamProxy => server.update_proxy(proxy.upstream_proxy.clone()).await,
+            ConfigSection::ProxyPool => server.update_proxy_pool(proxy.proxy_pool.clone()).await,
+            ConfigSection::Security => server.update_security(proxy).await,
+            ConfigSection::Zai => server.update_zai(proxy).await,
+            ConfigSection::Providers => server.update_providers(proxy).await,
+            ConfigSection::Experimental => server.update_experimental(proxy).await,
+            ConfigSection::DebugLogging => server.update_debug_logging(proxy).await,
+            ConfigSection::UserAgent => server.update_user_agent(proxy).await,
+            ConfigSection::ThinkingBudget => {
+                crate::proxy::update_thinking_budget_config(proxy.thinking_budget.clone())
+            }
+            ConfigSection::GlobalSystemPrompt => {
+                crate::proxy::update_global_system_prompt_config(proxy.global_system_prompt.clone())
+            }
+            ConfigSection::ImageThinkingMode => {
+                crate::proxy::update_image_thinking_mode(proxy.image_thinking_mode.clone())
+            }
+            ConfigSection::FallbackChains => {
+                crate::proxy::update_fallback_chains(proxy.fallback_chains.clone())
+            }
+            ConfigSection::Dispatch => crate::proxy::update_dispatch_config(proxy.dispatch.clone()),
+            ConfigSection::Routing => crate::proxy::update_routing_config(proxy.routing.clone()),
+            ConfigSection::ClientAdapters => {
+                crate::proxy::update_client_adapters(proxy.client_adapters.clone())
+            }
+            ConfigSection::ToolAdapters => {
+                crate::proxy::update_tool_adapters(proxy.tool_adapters.clone())
+            }
+            ConfigSection::SignatureCache => {
+                crate::proxy::update_signature_cache_config(proxy.signature_cache.clone())
+            }
+            ConfigSection::ResponseCache => {
+                crate::proxy::update_response_cache_config(proxy.response_cache.clone())
+            }
+            ConfigSection::ContextWindow => {
+                crate::proxy::update_context_window_config(proxy.context_window.clone())
+            }
+            ConfigSection::ScriptHooks => {
+                crate::proxy::update_script_hooks(proxy.script_hooks.clone())
+            }
+            ConfigSection::Scheduling => {
+                server
+                    .token_manager
+                    .update_sticky_config(proxy.scheduling.clone())
+                    .await
+            }
+            ConfigSection::PreferredAccount => {
+                server
+                    .token_manager
+                    .set_preferred_account(proxy.preferred_account_id.clone())
+                    .await
+            }
+            ConfigSection::RequestLogging => monitor.set_enabled(proxy.enable_logging),
+            ConfigSection::CircuitBreaker => {
+                server
+                    .token_manager
+                    .update_circuit_breaker_config(config.circuit_breaker.clone())
+                    .await
+            }
+        }
+    }
+}
+
+type FileStamp = Option<(Option<SystemTime>, u64)>;
+
+/// 需要监听的文件：gui_config.json 以及 Headless 模式下的 `--config` 文件
+fn watched_files() -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    if let Ok(dir) = crate::modules::account::get_data_dir() {
+        files.push(dir.join(crate::modules::config::CONFIG_FILE));
+    }
+    if let Some(path) = crate::modules::config_loader::layered_config_file() {
+        files.push(path.to_path_buf());
+    }
+    files
+}
+
+/// 文件修改时间 + 大小，作为是否需要重新加载的廉价判断
+fn file_stamp(path: &Path) -> FileStamp {
+    let meta = std::fs::metadata(path).ok()?;
+    Some((meta.modified().ok(), meta.len()))
+}
+
+/// 启动配置文件监听任务
+///
+/// 以当前磁盘配置为基准快照；之后每次检测到文件变化都与快照比较，
+/// 应用成功后更新快照，校验失败则保留快照以便修正后再次比较。
+pub fn spawn_config_watcher(
+    server: AxumServer,
+    monitor: Arc<ProxyMonitor>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut current = match reload_config() {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("[ConfigReload] 无法读取初始配置，热重载已禁用: {}", e);
+                return;
+            }
+        };
+        let files = watched_files();
+        let mut last_stamps: Vec<FileStamp> = files.iter().map(|f| file_stamp(f)).collect();
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        let names: Vec<String> = files.iter().map(|f| f.display().to_string()).collect();
+        info!("[ConfigReload] Watching {} for changes", names.join(", "));
+        loop {
+            interval.tick().await;
+
+            let stamps: Vec<FileStamp> = files.iter().map(|f| file_stamp(f)).collect();
+            // 文件被删除或暂不可读时保持现状，等待其恢复
+            if stamps.iter().any(Option::is_none) || stamps == last_stamps {
+                continue;
+            }
+            last_stamps = stamps;
+
+            let new_config = match reload_config() {
+                Ok(config) => config,
+                Err(e) => {
+                    error!(
+                        "[ConfigReload] Rejected invalid configuration, keeping running config: {}",
+                        e
+                    );
+                    continue;
+                }
+            };
+
+            let sections = diff_sections(&current, &new_config);
+            let restart_fields = restart_required_changes(&current, &new_config);
+            if sections.is_empty() && restart_fields.is_empty() {
+                debug!("[ConfigReload] Config files touched without effective changes");
+                current = new_config;
+                continue;
+            }
+
+            if !sections.is_empty() {
+                let names: Vec<&str> = sections.iter().map(|s| s.name()).collect();
+                info!(
+                    "[ConfigReload] Applying changed sections: {}",
+                    names.join(", ")
+                );
+                apply_sections(&server, &monitor, &new_config, &sections).await;
+            }
+            if !restart_fields.is_empty() {
+                warn!(
+                    "[ConfigReload] Changes require a restart to take effect: {}",
+                    restart_fields.join(", ")
+                );
+            }
+            current = new_config;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_sections_reports_only_changed() {
+        let old = AppConfig::new();
+        let mut new = old.clone();
+        assert!(diff_sections(&old, &new).is_empty());
+
+        new.proxy.api_key = "sk-rotated".to_string();
+        new.proxy
+            .custom_mapping
+            .insert("gpt-4o".into(), "gemini-2.5-pro".into());
+        new.circuit_breaker.enabled = !old.circuit_breaker.enabled;
+        assert_eq!(
+            diff_sections(&old, &new),
+            vec![
+                ConfigSection::CustomMapping,
+                ConfigSection::Security,
+                ConfigSection::CircuitBreaker
+            ]
+        );
+    }
+
+    #[test]
+    fn test_restart_required_changes() {
+        let old = AppConfig::new();
+        let mut new = old.clone();
+        new.proxy.port = old.proxy.port + 1;
+        assert_eq!(restart_required_changes(&old, &new), vec!["proxy.port"]);
+        assert!(diff_sections(&old, &new).is_empty());
+    }
+}
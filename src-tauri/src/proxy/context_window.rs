@@ -0,0 +1,592 @@
+//! 上下文窗口策略
+//!
+//! OpenAI / Claude / Gemini 三种协议的 handler 在转换为 v1internal 请求后调用 [`apply_current`]，
+//! 对 `request.contents` 执行同一套处理：
+//! 1. 估算系统提示、工具定义与每条消息的 Token (校准后的估算值)
+//! 2. 超出策略预算时，保留开头 `pinned_messages` 条与最近的消息，从最早的轮次开始丢弃
+//! 3. 开启 `summarize` 时调用摘要模型，把被丢弃的轮次压缩为一段文本插入保留窗口之前
+//!
+//! 窗口只会从用户轮次开始 (不以 functionResponse 开头)，保证工具调用与结果成对出现。
+//! 策略由 context_window 中间件按用户令牌 / 客户端适配器选出，并通过 task-local 作用域传递。
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use crate::proxy::common::model_mapping::wildcard_match;
+use crate::proxy::config::{ContextPolicy, ContextWindowConfig};
+use crate::proxy::mappers::context_manager::ContextManager;
+use crate::proxy::upstream::client::UpstreamClient;
+
+/// 摘要缓存上限 (超出后整体清空)
+const MAX_CACHED_SUMMARIES: usize = 256;
+/// 生成摘要时单条工具调用 / 结果保留的字符数
+const MAX_TOOL_CHARS: usize = 1000;
+
+const SUMMARY_PROMPT: &str = "The following is the earlier part of a conversation between a user and an AI assistant. \
+It no longer fits in the context window. Write a concise summary that preserves: the user's goals and constraints, \
+decisions made, important facts, file names, identifiers and code details, tool results that are still relevant, \
+and any open tasks. Write in the language of the conversation. Output only the summary.";
+
+tokio::task_local! {
+    static CURRENT_POLICY: Arc<ContextPolicy>;
+}
+
+/// 在策略作用域内执行 future
+pub async fn with_policy<F: Future>(policy: Arc<ContextPolicy>, fut: F) -> F::Output {
+    CURRENT_POLICY.scope(policy, fut).await
+}
+
+/// 当前请求生效的策略
+pub fn current_policy() -> Option<Arc<ContextPolicy>> {
+    CURRENT_POLICY.try_with(|p| p.clone()).ok()
+}
+
+/// 按用户令牌 / 客户端适配器选择策略，均未命中时使用 `default_policy`
+pub fn select_policy(
+    config: &ContextWindowConfig,
+    user: Option<&str>,
+    client: Option<&str>,
+) -> Option<ContextPolicy> {
+    let matched = config.policies.iter().find(|policy| {
+        let user_match = user.is_some_and(|user| {
+            policy
+                .users
+                .iter()
+                .any(|pattern| wildcard_match(pattern, user))
+        });
+        let client_match = client.is_some_and(|client| {
+            policy
+                .clients
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(client))
+        });
+        user_match || client_match
+    });
+    matched
+        .or_else(|| {
+            let name = config.default_policy.as_deref()?;
+            config.policies.iter().find(|policy| policy.name == name)
+        })
+        .cloned()
+}
+
+/// 裁剪方案：保留 `[0, pinned_end)` 与 `[start, len)`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowPlan {
+    pub pinned_end: usize,
+    pub start: usize,
+    pub original_tokens: u32,
+    pub retained_tokens: u32,
+}
+
+impl WindowPlan {
+    pub fn dropped_messages(&self) -> usize {
+        self.start - self.pinned_end
+    }
+}
+
+fn parts(content: &Value) -> impl Iterator<Item = &Value> {
+    content
+        .get("parts")
+        .and_then(|p| p.as_array())
+        .into_iter()
+        .flatten()
+}
+
+/// 可以作为窗口起点的消息：用户轮次且不是工具结果
+fn is_turn_start(content: &Value) -> bool {
+    content.get("role").and_then(|r| r.as_str()) == Some("user")
+        && !parts(content).any(|part| part.get("functionResponse").is_some())
+}
+
+fn has_function_call(content: &Value) -> bool {
+    parts(content).any(|part| part.get("functionCall").is_some())
+}
+
+/// 根据每条消息的 Token 计算裁剪方案，未超出预算或无法裁剪时返回 None
+///
+/// `fixed_tokens` 为系统提示与工具定义等不可裁剪部分。找不到满足预算的起点时，
+/// 退而选择 `min_recent_messages` 允许的最靠后的起点。
+pub fn plan_window(
+    contents: &[Value],
+    fixed_tokens: u32,
+    costs: &[u32],
+    policy: &ContextPolicy,
+) -> Option<WindowPlan> {
+    let n = contents.len();
+    let original_tokens = fixed_tokens + costs.iter().sum::<u32>();
+    if original_tokens <= policy.max_tokens || n < 2 {
+        return None;
+    }
+
+    // 固定消息以工具调用结尾时，连同其结果一起保留
+    let mut pinned_end = policy.pinned_messages.min(n);
+    while pinned_end > 0 && pinned_end < n && has_function_call(&contents[pinned_end - 1]) {
+        pinned_end += 1;
+    }
+    let pinned_tokens = costs[..pinned_end].iter().sum::<u32>();
+    let latest_start = n.saturating_sub(policy.min_recent_messages).max(pinned_end);
+    let reserve = if policy.summarize {
+        policy.summary_max_tokens
+    } else {
+        0
+    };
+    let budget = policy
+        .max_tokens
+        .saturating_sub(fixed_tokens + pinned_tokens + reserve);
+
+    let mut suffix = vec![0u32; n + 1];
+    for i in (0..n).rev() {
+        suffix[i] = suffix[i + 1] + costs[i];
+    }
+    let candidates: Vec<usize> = (pinned_end + 1..=latest_start.min(n - 1))
+        .filter(|&i| is_turn_start(&contents[i]))
+        .collect();
+    let start = candidates
+        .iter()
+        .copied()
+        .find(|&i| suffix[i] <= budget)
+        .or_else(|| candidates.last().copied())?;
+
+    Some(WindowPlan {
+        pinned_end,
+        start,
+        original_tokens,
+        retained_tokens: fixed_tokens + pinned_tokens + suffix[start],
+    })
+}
+
+/// 按方案重组消息，摘要作为文本插入窗口首条用户消息之前
+pub fn apply_plan(contents: Vec<Value>, plan: &WindowPlan, summary: Option<&str>) -> Vec<Value> {
+    let mut contents = contents;
+    let mut window = contents.split_off(plan.start);
+    contents.truncate(plan.pinned_end);
+
+    if let (Some(summary), Some(first)) = (summary, window.first_mut()) {
+        let text = format!(
+            "<conversation_summary>\n{}\n</conversation_summary>",
+            summary.trim()
+        );
+        if let Some(parts) = first.get_mut("parts").and_then(|p| p.as_array_mut()) {
+            parts.insert(0, json!({ "text": text }));
+        }
+    }
+
+    // 固定消息与窗口在边界处角色相同时合并，保持角色交替
+    let mut window = window.into_iter();
+    if let Some(first) = window.next() {
+        let same_role = contents
+            .last()
+            .is_some_and(|last| last.get("role") == first.get("role"));
+        let target = contents
+            .last_mut()
+            .and_then(|last| last.get_mut("parts"))
+            .and_then(|p| p.as_array_mut());
+        match (
+            same_role,
+            target,
+            first.get("parts").and_then(|p| p.as_array()),
+        ) {
+            (true, Some(target), Some(extra)) => target.extend(extra.iter().cloned()),
+            _ => contents.push(first.clone()),
+        }
+    }
+    contents.extend(window);
+    contents
+}
+
+/// 估算不可裁剪部分与每条消息的 Token
+fn estimate_costs(request: &Value, contents: &[Value], model: &str) -> (u32, Vec<u32>) {
+    let mut fixed = serde_json::Map::new();
+    for key in ["systemInstruction", "tools"] {
+        if let Some(value) = request.get(key) {
+            fixed.insert(key.to_string(), value.clone());
+        }
+    }
+    let fixed = Value::Object(fixed);
+    let fixed_tokens =
+        ContextManager::estimate_gemini_input_tokens(&fixed, model).calibrated(model);
+    let costs = contents
+        .iter()
+        .map(|content| {
+            ContextManager::estimate_gemini_input_tokens(&json!({ "contents": [content] }), model)
+                .calibrated(model)
+        })
+        .collect();
+    (fixed_tokens, costs)
+}
+
+fn truncate_chars(s: &str, max: usize) -> String {
+    match s.char_indices().nth(max) {
+        Some((idx, _)) => format!("{}...", &s[..idx]),
+        None => s.to_string(),
+    }
+}
+
+/// 将被丢弃的消息渲染为纯文本记录 (不携带思维签名，可交给任意模型)
+fn render_transcript(contents: &[Value]) -> String {
+    let mut out = String::new();
+    for content in contents {
+        let speaker = match content.get("role").and_then(|r| r.as_str()) {
+            Some("model") => "Assistant",
+            _ => "User",
+        };
+        for part in parts(content) {
+            if part.get("thought").and_then(|t| t.as_bool()) == Some(true) {
+                continue;
+            }
+            let line = if let Some(text) = part.get("text").and_then(|t| t.as_str()) {
+                text.to_string()
+            } else if let Some(call) = part.get("functionCall") {
+                format!(
+                    "[tool call] {}({})",
+                    call.get("name")
+                        .and_then(|n| n.as_str())
+                        .unwrap_or_default(),
+                    truncate_chars(
+                        &call.get("args").cloned().unwrap_or_default().to_string(),
+                        MAX_TOOL_CHARS
+                    )
+                )
+            } else if let Some(result) = part.get("functionResponse") {
+                format!(
+                    "[tool result] {}: {}",
+                    result
+                        .get("name")
+                        .and_then(|n| n.as_str())
+                        .unwrap_or_default(),
+                    truncate_chars(
+                        &result
+                            .get("response")
+                            .cloned()
+                            .unwrap_or_default()
+                            .to_string(),
+                        MAX_TOOL_CHARS
+                    )
+                )
+            } else if let Some(mime) = part
+                .pointer("/inlineData/mimeType")
+                .and_then(|m| m.as_str())
+            {
+                format!("[attachment: {}]", mime)
+            } else {
+                continue;
+            };
+            if !line.trim().is_empty() {
+                out.push_str(&format!("{}: {}\n\n", speaker, line.trim()));
+            }
+        }
+    }
+    out
+}
+
+/// 摘要请求使用的上游账号
+pub struct SummaryUpstream<'a> {
+    pub upstream: &'a UpstreamClient,
+    pub access_token: &'a str,
+    pub project_id: &'a str,
+    pub account_id: &'a str,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ContextWindowStats {
+    /// 被裁剪的请求数
+    pub trimmed_requests: u64,
+    pub dropped_messages: u64,
+    pub dropped_tokens: u64,
+    pub summaries_generated: u64,
+    pub summary_cache_hits: u64,
+    pub summary_failures: u64,
+    pub cached_summaries: usize,
+}
+
+static STATS: Lazy<Mutex<ContextWindowStats>> =
+    Lazy::new(|| Mutex::new(ContextWindowStats::default()));
+/// 重试或并发请求会丢弃相同的轮次，按记录内容缓存摘要
+static SUMMARIES: Lazy<Mutex<HashMap<String, String>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub fn stats() -> ContextWindowStats {
+    let cached_summaries = SUMMARIES.lock().map(|s| s.len()).unwrap_or(0);
+    STATS
+        .lock()
+        .map(|s| ContextWindowStats {
+            cached_summaries,
+            ..s.clone()
+        })
+        .unwrap_or_default()
+}
+
+fn record(update: impl FnOnce(&mut ContextWindowStats)) {
+    if let Ok(mut stats) = STATS.lock() {
+        update(&mut stats);
+    }
+}
+
+async fn summarize(
+    policy: &ContextPolicy,
+    transcript: &str,
+    upstream: &SummaryUpstream<'_>,
+    trace_id: &str,
+) -> Result<String, String> {
+    let mut hasher = Sha256::new();
+    hasher.update(policy.summary_model.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(transcript.as_bytes());
+    let key = format!("{:x}", hasher.finalize());
+    if let Some(summary) = SUMMARIES.lock().ok().and_then(|s| s.get(&key).cloned()) {
+        record(|s| s.summary_cache_hits += 1);
+        return Ok(summary);
+    }
+
+    let body = json!({
+        "project": upstream.project_id,
+        "requestId": format!("agent/context-summary/{}", trace_id),
+        "request": {
+            "contents": [{
+                "role": "user",
+                "parts": [{ "text": format!("{}\n\n<conversation>\n{}</conversation>", SUMMARY_PROMPT, transcript) }]
+            }],
+            "generationConfig": {
+                "maxOutputTokens": policy.summary_max_tokens,
+                "temperature": 0.2
+            }
+        },
+        "model": policy.summary_model,
+        "userAgent": "antigravity",
+        "requestType": "agent"
+    });
+    let response = upstream
+        .upstream
+        .call_v1_internal(
+            "generateContent",
+            upstream.access_token,
+            body,
+            None,
+            Some(upstream.account_id),
+        )
+        .await?
+        .response;
+    let status = response.status();
+    if !status.is_success() {
+        let text = response.text().await.unwrap_or_default();
+        return Err(format!(
+            "summary model {} returned {}: {}",
+            policy.summary_model,
+            status.as_u16(),
+            truncate_chars(&text, 200)
+        ));
+    }
+    let result: Value = response
+        .json()
+        .await
+        .map_err(|e| format!("解析摘要响应失败: {}", e))?;
+    let summary = result
+        .get("response")
+        .unwrap_or(&result)
+        .pointer("/candidates/0/content/parts")
+        .and_then(|p| p.as_array())
+        .into_iter()
+        .flatten()
+        .filter(|part| part.get("thought").and_then(|t| t.as_bool()) != Some(true))
+        .filter_map(|part| part.get("text").and_then(|t| t.as_str()))
+        .collect::<String>();
+    if summary.trim().is_empty() {
+        return Err("summary model returned empty text".to_string());
+    }
+
+    if let Ok(mut cache) = SUMMARIES.lock() {
+        if cache.len() >= MAX_CACHED_SUMMARIES {
+            cache.clear();
+        }
+        cache.insert(key, summary.clone());
+    }
+    record(|s| s.summaries_generated += 1);
+    Ok(summary)
+}
+
+/// 对 v1internal 请求体应用当前作用域的策略，返回是否裁剪了上下文
+pub async fn apply_current(
+    body: &mut Value,
+    model: &str,
+    upstream: SummaryUpstream<'_>,
+    trace_id: &str,
+) -> bool {
+    match current_policy() {
+        Some(policy) => apply(&policy, body, model, upstream, trace_id).await,
+        None => false,
+    }
+}
+
+pub async fn apply(
+    policy: &ContextPolicy,
+    body: &mut Value,
+    model: &str,
+    upstream: SummaryUpstream<'_>,
+    trace_id: &str,
+) -> bool {
+    let Some(request) = body.get("request") else {
+        return false;
+    };
+    let Some(contents) = request.get("contents").and_then(|c| c.as_array()) else {
+        return false;
+    };
+    let (fixed_tokens, costs) = estimate_costs(request, contents, model);
+    let Some(plan) = plan_window(contents, fixed_tokens, &costs, policy) else {
+        return false;
+    };
+    let transcript = policy
+        .summarize
+        .then(|| render_transcript(&contents[plan.pinned_end..plan.start]));
+
+    let summary = match transcript {
+        Some(transcript) => match summarize(policy, &transcript, &upstream, trace_id).await {
+            Ok(summary) => Some(summary),
+            Err(e) => {
+                tracing::warn!(
+                    "[{}] [ContextWindow] Summarization failed, dropping turns without summary: {}",
+                    trace_id,
+                    e
+                );
+                record(|s| s.summary_failures += 1);
+                None
+            }
+        },
+        None => None,
+    };
+
+    let Some(slot) = body.pointer_mut("/request/contents") else {
+        return false;
+    };
+    let contents = match slot.take() {
+        Value::Array(contents) => contents,
+        _ => return false,
+    };
+    *slot = Value::Array(apply_plan(contents, &plan, summary.as_deref()));
+
+    tracing::info!(
+        "[{}] [ContextWindow] Policy '{}' dropped {} messages: ~{} -> ~{} tokens (budget: {}, summary: {})",
+        trace_id,
+        policy.name,
+        plan.dropped_messages(),
+        plan.original_tokens,
+        plan.retained_tokens,
+        policy.max_tokens,
+        summary.is_some()
+    );
+    record(|s| {
+        s.trimmed_requests += 1;
+        s.dropped_messages += plan.dropped_messages() as u64;
+        s.dropped_tokens += plan.original_tokens.saturating_sub(plan.retained_tokens) as u64;
+    });
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(max_tokens: u32) -> ContextPolicy {
+        ContextPolicy {
+            name: "test".to_string(),
+            users: vec!["ci-*".to_string()],
+            clients: vec!["opencode".to_string()],
+            max_tokens,
+            pinned_messages: 1,
+            min_recent_messages: 1,
+            summarize: false,
+            summary_model: "gemini-2.5-flash".to_string(),
+            summary_max_tokens: 0,
+        }
+    }
+
+    fn text(role: &str, text: &str) -> Value {
+        json!({ "role": role, "parts": [{ "text": text }] })
+    }
+
+    #[test]
+    fn test_select_policy() {
+        let config = ContextWindowConfig {
+            default_policy: Some("fallback".to_string()),
+            policies: vec![
+                policy(1000),
+                ContextPolicy {
+                    name: "fallback".to_string(),
+                    users: Vec::new(),
+                    clients: Vec::new(),
+                    ..policy(2000)
+                },
+            ],
+        };
+        let pick = |user, client| select_policy(&config, user, client).map(|p| p.name);
+
+        assert_eq!(pick(Some("ci-runner"), None).as_deref(), Some("test"));
+        assert_eq!(pick(None, Some("OpenCode")).as_deref(), Some("test"));
+        assert_eq!(pick(Some("alice"), None).as_deref(), Some("fallback"));
+
+        let no_default = ContextWindowConfig {
+            default_policy: None,
+            ..config.clone()
+        };
+        assert!(select_policy(&no_default, Some("alice"), None).is_none());
+    }
+
+    #[test]
+    fn test_plan_window_keeps_pinned_and_tool_pairs() {
+        let contents = vec![
+            text("user", "task"),
+            text("model", "ok"),
+            text("user", "step 1"),
+            json!({ "role": "model", "parts": [{ "functionCall": { "name": "read", "args": {} } }] }),
+            json!({ "role": "user", "parts": [{ "functionResponse": { "name": "read", "response": {} } }] }),
+            text("model", "done"),
+            text("user", "step 2"),
+        ];
+        let costs = vec![10; contents.len()];
+
+        // 未超出预算
+        assert!(plan_window(&contents, 5, &costs, &policy(100)).is_none());
+
+        // 预算只够 4 条：不能从 functionResponse 开始，退到 "step 2"
+        let plan = plan_window(&contents, 0, &costs, &policy(50)).unwrap();
+        assert_eq!(plan.pinned_end, 1);
+        assert_eq!(plan.start, 6);
+        assert_eq!(plan.retained_tokens, 20);
+
+        // 预算充足时保留更早的完整轮次
+        let plan = plan_window(&contents, 0, &costs, &policy(60)).unwrap();
+        assert_eq!(plan.start, 2);
+        assert_eq!(plan.dropped_messages(), 1);
+    }
+
+    #[test]
+    fn test_apply_plan_inserts_summary_and_merges_roles() {
+        let contents = vec![
+            text("user", "task"),
+            text("model", "old answer"),
+            text("user", "latest"),
+        ];
+        let plan = WindowPlan {
+            pinned_end: 1,
+            start: 2,
+            original_tokens: 30,
+            retained_tokens: 20,
+        };
+        let result = apply_plan(contents, &plan, Some("earlier work"));
+
+        // 固定的用户消息与窗口首条用户消息合并
+        assert_eq!(result.len(), 1);
+        let parts = result[0]["parts"].as_array().unwrap();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0]["text"], "task");
+        assert!(parts[1]["text"].as_str().unwrap().contains("earlier work"));
+        assert_eq!(parts[2]["text"], "latest");
+
+        let transcript = render_transcript(&[text("model", "old answer")]);
+        assert_eq!(transcript, "Assistant: old answer\n\n");
+    }
+}
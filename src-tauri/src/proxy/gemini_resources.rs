@@ -0,0 +1,673 @@
+//! Gemini 原生资源模拟: Files API 与 cachedContents
+//!
+//! v1internal 上游不提供这两类资源，这里在本地实现以兼容 Gemini SDK：
+//! - 上传的文件保存在数据目录 `gemini_files/` 下 (内容 + 元数据 JSON)，48 小时后过期；
+//!   请求中通过 `fileData.fileUri` 引用本地文件时替换为 `inlineData`。
+//! - cachedContents 保存在内存中，请求携带 `cachedContent` 时把缓存的 contents /
+//!   systemInstruction / tools / toolConfig 合并进请求。上游仍按完整输入计费。
+//!
+//! 资源归属于创建它的用户令牌 (`UserTokenIdentity::token_id`)，其他调用方不可见；
+//! 未使用用户令牌的请求 (全局 API Key / 未开启鉴权) 共享无归属的资源。
+
+use axum::http::StatusCode;
+use base64::Engine;
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use once_cell::sync::Lazy;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::proxy::mappers::context_manager::ContextManager;
+use crate::proxy::middleware::auth::UserTokenIdentity;
+
+/// 上传文件的保留时长 (与 Gemini Files API 一致)
+pub const FILE_TTL_HOURS: i64 = 48;
+/// 未指定 ttl / expireTime 时 cachedContent 的有效期
+pub const DEFAULT_CACHE_TTL_SECS: i64 = 3600;
+const FILES_DIR: &str = "gemini_files";
+
+pub type ResourceError = (StatusCode, String);
+
+/// 资源归属键：用户令牌 ID
+pub fn owner_of(identity: Option<&UserTokenIdentity>) -> Option<String> {
+    identity.map(|identity| identity.token_id.clone())
+}
+
+pub fn timestamp(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+fn parse_timestamp(s: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(s)
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+/// 生成资源 ID (小写字母与数字)
+pub fn new_resource_id() -> String {
+    rand::thread_rng()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(12)
+        .map(|c| (c as char).to_ascii_lowercase())
+        .collect()
+}
+
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 64
+        && id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+/// 去掉资源名前缀 (`files/abc` -> `abc`)，ID 不合法时返回 None
+pub fn resource_id<'a>(name: &'a str, prefix: &str) -> Option<&'a str> {
+    let id = name
+        .strip_prefix(prefix)
+        .and_then(|rest| rest.strip_prefix('/'))
+        .unwrap_or(name);
+    is_valid_id(id).then_some(id)
+}
+
+// ===== Files API =====
+
+/// 文件元数据 (即 Gemini `File` 资源)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileMeta {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub display_name: String,
+    pub mime_type: String,
+    /// int64 按 Gemini 约定序列化为字符串
+    pub size_bytes: String,
+    pub create_time: String,
+    pub update_time: String,
+    pub expiration_time: String,
+    pub sha256_hash: String,
+    pub uri: String,
+    pub state: String,
+    pub source: String,
+}
+
+impl FileMeta {
+    fn is_expired(&self) -> bool {
+        parse_timestamp(&self.expiration_time).is_some_and(|t| t <= Utc::now())
+    }
+}
+
+/// 磁盘上的元数据 JSON：File 资源 + 归属 (归属不返回给客户端)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredFile {
+    #[serde(flatten)]
+    meta: FileMeta,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    owner: Option<String>,
+}
+
+async fn read_stored_file(path: &std::path::Path) -> Option<StoredFile> {
+    let raw = tokio::fs::read(path).await.ok()?;
+    serde_json::from_slice(&raw).ok()
+}
+
+/// 文件 URI 指向的本地文件 ID
+///
+/// 接受 `files/{id}` 或以 `/v1beta/files/{id}` 结尾的完整 URI (忽略主机名，代理可能经过转发)。
+pub fn file_id_from_uri(uri: &str) -> Option<&str> {
+    let path = uri.split(['?', '#']).next().unwrap_or(uri);
+    let rest = if let Some(rest) = path.strip_prefix("files/") {
+        rest
+    } else {
+        let (_, rest) = path.rsplit_once("/v1beta/files/")?;
+        rest
+    };
+    is_valid_id(rest).then_some(rest)
+}
+
+fn files_dir() -> Result<PathBuf, String> {
+    Ok(crate::modules::account::get_data_dir()?.join(FILES_DIR))
+}
+
+fn meta_path(dir: &std::path::Path, id: &str) -> PathBuf {
+    dir.join(format!("{}.json", id))
+}
+
+fn data_path(dir: &std::path::Path, id: &str) -> PathBuf {
+    dir.join(format!("{}.bin", id))
+}
+
+/// 保存文件，返回元数据 (`base_url` 用于生成客户端可回传的 uri)
+pub async fn save_file(
+    display_name: &str,
+    mime_type: &str,
+    data: &[u8],
+    base_url: &str,
+    source: &str,
+    owner: Option<&str>,
+) -> Result<FileMeta, String> {
+    let dir = files_dir()?;
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| format!("创建文件目录失败: {}", e))?;
+    purge_expired_files().await;
+
+    let id = new_resource_id();
+    let now = Utc::now();
+    let meta = FileMeta {
+        name: format!("files/{}", id),
+        display_name: display_name.to_string(),
+        mime_type: if mime_type.is_empty() {
+            "application/octet-stream".to_string()
+        } else {
+            mime_type.to_string()
+        },
+        size_bytes: data.len().to_string(),
+        create_time: timestamp(now),
+        update_time: timestamp(now),
+        expiration_time: timestamp(now + Duration::hours(FILE_TTL_HOURS)),
+        sha256_hash: base64::engine::general_purpose::STANDARD.encode(Sha256::digest(data)),
+        uri: format!("{}/v1beta/files/{}", base_url.trim_end_matches('/'), id),
+        state: "ACTIVE".to_string(),
+        source: source.to_string(),
+    };
+    tokio::fs::write(data_path(&dir, &id), data)
+        .await
+        .map_err(|e| format!("写入文件失败: {}", e))?;
+    let stored = StoredFile {
+        meta,
+        owner: owner.map(str::to_string),
+    };
+    let json = serde_json::to_vec_pretty(&stored).map_err(|e| e.to_string())?;
+    let meta = stored.meta;
+    tokio::fs::write(meta_path(&dir, &id), json)
+        .await
+        .map_err(|e| format!("写入文件元数据失败: {}", e))?;
+    tracing::info!(
+        "[GeminiFiles] Stored {} ({}, {} bytes)",
+        meta.name,
+        meta.mime_type,
+        data.len()
+    );
+    Ok(meta)
+}
+
+/// 读取调用方可见的未过期文件元数据 (已过期的文件会被删除)
+pub async fn get_file(id: &str, owner: Option<&str>) -> Result<Option<FileMeta>, String> {
+    if !is_valid_id(id) {
+        return Ok(None);
+    }
+    let dir = files_dir()?;
+    let Ok(raw) = tokio::fs::read(meta_path(&dir, id)).await else {
+        return Ok(None);
+    };
+    let stored: StoredFile =
+        serde_json::from_slice(&raw).map_err(|e| format!("解析文件元数据失败: {}", e))?;
+    if stored.meta.is_expired() {
+        remove_file_entry(&dir, id).await;
+        return Ok(None);
+    }
+    if stored.owner.as_deref() != owner {
+        return Ok(None);
+    }
+    Ok(Some(stored.meta))
+}
+
+/// 读取文件内容
+pub async fn read_file(
+    id: &str,
+    owner: Option<&str>,
+) -> Result<Option<(FileMeta, Vec<u8>)>, String> {
+    let Some(meta) = get_file(id, owner).await? else {
+        return Ok(None);
+    };
+    let data = tokio::fs::read(data_path(&files_dir()?, id))
+        .await
+        .map_err(|e| format!("读取文件 {} 失败: {}", meta.name, e))?;
+    Ok(Some((meta, data)))
+}
+
+/// 按创建时间倒序列出调用方可见的未过期文件
+pub async fn list_files(owner: Option<&str>) -> Result<Vec<FileMeta>, String> {
+    purge_expired_files().await;
+    let dir = files_dir()?;
+    let mut entries = match tokio::fs::read_dir(&dir).await {
+        Ok(entries) => entries,
+        Err(_) => return Ok(Vec::new()),
+    };
+    let mut files = Vec::new();
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        if let Some(stored) = read_stored_file(&path).await {
+            if stored.owner.as_deref() == owner {
+                files.push(stored.meta);
+            }
+        }
+    }
+    files.sort_by(|a, b| b.create_time.cmp(&a.create_time));
+    Ok(files)
+}
+
+/// 删除调用方可见的文件，返回是否存在
+pub async fn delete_file(id: &str, owner: Option<&str>) -> Result<bool, String> {
+    if get_file(id, owner).await?.is_none() {
+        return Ok(false);
+    }
+    Ok(remove_file_entry(&files_dir()?, id).await)
+}
+
+async fn remove_file_entry(dir: &std::path::Path, id: &str) -> bool {
+    let existed = tokio::fs::remove_file(meta_path(dir, id)).await.is_ok();
+    let _ = tokio::fs::remove_file(data_path(dir, id)).await;
+    existed
+}
+
+async fn purge_expired_files() {
+    let Ok(dir) = files_dir() else {
+        return;
+    };
+    let Ok(mut entries) = tokio::fs::read_dir(&dir).await else {
+        return;
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let expired = read_stored_file(&path)
+            .await
+            .is_some_and(|stored| stored.meta.is_expired());
+        if expired {
+            if let Some(id) = path.file_stem().and_then(|s| s.to_str()) {
+                remove_file_entry(&dir, id).await;
+                tracing::debug!("[GeminiFiles] Expired file removed: files/{}", id);
+            }
+        }
+    }
+}
+
+// ===== cachedContents =====
+
+#[derive(Debug, Clone)]
+pub struct CachedContent {
+    pub name: String,
+    pub model: String,
+    pub display_name: String,
+    pub create_time: DateTime<Utc>,
+    pub update_time: DateTime<Utc>,
+    pub expire_time: DateTime<Utc>,
+    pub contents: Vec<Value>,
+    pub system_instruction: Option<Value>,
+    pub tools: Option<Value>,
+    pub tool_config: Option<Value>,
+    pub total_tokens: u32,
+    pub owner: Option<String>,
+}
+
+impl CachedContent {
+    /// API 资源表示 (与 Gemini 一致，不返回缓存的内容本身)
+    pub fn to_json(&self) -> Value {
+        let mut value = json!({
+            "name": self.name,
+            "model": self.model,
+            "createTime": timestamp(self.create_time),
+            "updateTime": timestamp(self.update_time),
+            "expireTime": timestamp(self.expire_time),
+            "usageMetadata": { "totalTokenCount": self.total_tokens },
+        });
+        if !self.display_name.is_empty() {
+            value["displayName"] = json!(self.display_name);
+        }
+        value
+    }
+}
+
+static CACHED_CONTENTS: Lazy<Mutex<HashMap<String, CachedContent>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 从 `ttl` (如 `"300s"`) 或 `expireTime` 计算过期时间
+pub fn parse_expiration(body: &Value, now: DateTime<Utc>) -> Result<Option<DateTime<Utc>>, String> {
+    if let Some(ttl) = body.get("ttl").and_then(|t| t.as_str()) {
+        let secs = ttl
+            .strip_suffix('s')
+            .and_then(|s| s.parse::<f64>().ok())
+            .filter(|s| *s > 0.0)
+            .ok_or_else(|| format!("Invalid ttl: {}", ttl))?;
+        return Ok(Some(now + Duration::milliseconds((secs * 1000.0) as i64)));
+    }
+    if let Some(expire) = body.get("expireTime").and_then(|t| t.as_str()) {
+        let time =
+            parse_timestamp(expire).ok_or_else(|| format!("Invalid expireTime: {}", expire))?;
+        if time <= now {
+            return Err("expireTime must be in the future".to_string());
+        }
+        return Ok(Some(time));
+    }
+    Ok(None)
+}
+
+fn bad_request(message: impl Into<String>) -> ResourceError {
+    (StatusCode::BAD_REQUEST, message.into())
+}
+
+fn not_found(name: &str) -> ResourceError {
+    (StatusCode::NOT_FOUND, format!("{} not found", name))
+}
+
+fn with_cache<T>(f: impl FnOnce(&mut HashMap<String, CachedContent>) -> T) -> T {
+    let mut cache = CACHED_CONTENTS.lock().unwrap_or_else(|e| e.into_inner());
+    let now = Utc::now();
+    cache.retain(|_, entry| entry.expire_time > now);
+    f(&mut cache)
+}
+
+pub fn create_cached_content(body: &Value, owner: Option<&str>) -> Result<Value, ResourceError> {
+    let model = body
+        .get("model")
+        .and_then(|m| m.as_str())
+        .filter(|m| !m.trim().is_empty())
+        .ok_or_else(|| bad_request("model is required"))?;
+    let model = format!("models/{}", model.trim_start_matches("models/"));
+    let contents = body
+        .get("contents")
+        .and_then(|c| c.as_array())
+        .cloned()
+        .unwrap_or_default();
+    let system_instruction = body.get("systemInstruction").cloned();
+    if contents.is_empty() && system_instruction.is_none() {
+        return Err(bad_request("contents or systemInstruction is required"));
+    }
+
+    let now = Utc::now();
+    let expire_time = parse_expiration(body, now)
+        .map_err(bad_request)?
+        .unwrap_or(now + Duration::seconds(DEFAULT_CACHE_TTL_SECS));
+    let short_model = model.trim_start_matches("models/");
+    let total_tokens =
+        ContextManager::estimate_gemini_input_tokens(body, short_model).calibrated(short_model);
+    let entry = CachedContent {
+        name: format!("cachedContents/{}", new_resource_id()),
+        model,
+        display_name: body
+            .get("displayName")
+            .and_then(|d| d.as_str())
+            .unwrap_or_default()
+            .to_string(),
+        create_time: now,
+        update_time: now,
+        expire_time,
+        contents,
+        system_instruction,
+        tools: body.get("tools").cloned(),
+        tool_config: body.get("toolConfig").cloned(),
+        total_tokens,
+        owner: owner.map(str::to_string),
+    };
+    let value = entry.to_json();
+    tracing::info!(
+        "[GeminiCache] Created {} for {} (~{} tokens, expires {})",
+        entry.name,
+        entry.model,
+        entry.total_tokens,
+        timestamp(entry.expire_time)
+    );
+    with_cache(|cache| cache.insert(entry.name.clone(), entry));
+    Ok(value)
+}
+
+pub fn get_cached_content(name: &str, owner: Option<&str>) -> Option<CachedContent> {
+    let name = format!("cachedContents/{}", resource_id(name, "cachedContents")?);
+    with_cache(|cache| {
+        cache
+            .get(&name)
+            .filter(|entry| entry.owner.as_deref() == owner)
+            .cloned()
+    })
+}
+
+/// 按创建时间倒序列出调用方可见的缓存
+pub fn list_cached_contents(owner: Option<&str>) -> Vec<Value> {
+    with_cache(|cache| {
+        let mut entries: Vec<_> = cache
+            .values()
+            .filter(|entry| entry.owner.as_deref() == owner)
+            .collect();
+        entries.sort_by(|a, b| b.create_time.cmp(&a.create_time));
+        entries.iter().map(|e| e.to_json()).collect()
+    })
+}
+
+/// 更新有效期 (Gemini 仅允许修改 ttl / expireTime)
+pub fn update_cached_content(
+    name: &str,
+    body: &Value,
+    owner: Option<&str>,
+) -> Result<Value, ResourceError> {
+    let id = resource_id(name, "cachedContents").ok_or_else(|| not_found(name))?;
+    let name = format!("cachedContents/{}", id);
+    let now = Utc::now();
+    let expire_time = parse_expiration(body, now)
+        .map_err(bad_request)?
+        .ok_or_else(|| bad_request("ttl or expireTime is required"))?;
+    with_cache(|cache| {
+        let entry = cache
+            .get_mut(&name)
+            .filter(|entry| entry.owner.as_deref() == owner)
+            .ok_or_else(|| not_found(&name))?;
+        entry.expire_time = expire_time;
+        entry.update_time = now;
+        Ok(entry.to_json())
+    })
+}
+
+pub fn delete_cached_content(name: &str, owner: Option<&str>) -> bool {
+    let Some(id) = resource_id(name, "cachedContents") else {
+        return false;
+    };
+    let name = format!("cachedContents/{}", id);
+    with_cache(|cache| {
+        let owned = cache
+            .get(&name)
+            .is_some_and(|entry| entry.owner.as_deref() == owner);
+        owned && cache.remove(&name).is_some()
+    })
+}
+
+// ===== 请求展开 =====
+
+/// 展开 generateContent 请求中的本地资源引用
+///
+/// 1. `cachedContent`: 把缓存的内容放在请求 contents 之前，并补全 systemInstruction / tools / toolConfig
+/// 2. `fileData.fileUri` 指向本地文件: 替换为 `inlineData`
+///
+/// 只能引用调用方 (`owner`) 自己的缓存与文件。
+pub async fn expand_request(body: &mut Value, owner: Option<&str>) -> Result<(), ResourceError> {
+    if let Some(name) = body
+        .as_object_mut()
+        .and_then(|obj| obj.remove("cachedContent"))
+    {
+        let name = name.as_str().unwrap_or_default().to_string();
+        let cached = get_cached_content(&name, owner).ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                format!("CachedContent not found (or expired): {}", name),
+            )
+        })?;
+        apply_cached_content(body, &cached);
+        tracing::debug!(
+            "[GeminiCache] Expanded {} ({} cached contents)",
+            cached.name,
+            cached.contents.len()
+        );
+    }
+    inline_file_refs(body, owner).await
+}
+
+fn apply_cached_content(body: &mut Value, cached: &CachedContent) {
+    let Some(obj) = body.as_object_mut() else {
+        return;
+    };
+    let mut contents = cached.contents.clone();
+    if let Some(Value::Array(own)) = obj.remove("contents") {
+        contents.extend(own);
+    }
+    obj.insert("contents".to_string(), Value::Array(contents));
+    for (key, value) in [
+        ("systemInstruction", &cached.system_instruction),
+        ("tools", &cached.tools),
+        ("toolConfig", &cached.tool_config),
+    ] {
+        if let (Some(value), false) = (value, obj.contains_key(key)) {
+            obj.insert(key.to_string(), value.clone());
+        }
+    }
+}
+
+async fn inline_file_refs(body: &mut Value, owner: Option<&str>) -> Result<(), ResourceError> {
+    let mut parts: Vec<&mut Value> = Vec::new();
+    if let Some(obj) = body.as_object_mut() {
+        for (key, value) in obj.iter_mut() {
+            match key.as_str() {
+                "contents" => {
+                    for content in value.as_array_mut().into_iter().flatten() {
+                        if let Some(list) = content.get_mut("parts").and_then(|p| p.as_array_mut())
+                        {
+                            parts.extend(list.iter_mut());
+                        }
+                    }
+                }
+                "systemInstruction" => {
+                    if let Some(list) = value.get_mut("parts").and_then(|p| p.as_array_mut()) {
+                        parts.extend(list.iter_mut());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut loaded: HashMap<String, (FileMeta, String)> = HashMap::new();
+    for part in parts {
+        let Some(uri) = part.pointer("/fileData/fileUri").and_then(|u| u.as_str()) else {
+            continue;
+        };
+        let Some(id) = file_id_from_uri(uri).map(|id| id.to_string()) else {
+            continue;
+        };
+        if !loaded.contains_key(&id) {
+            let (meta, data) = read_file(&id, owner)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?
+                .ok_or_else(|| bad_request(format!("File files/{} not found (or expired)", id)))?;
+            let data = base64::engine::general_purpose::STANDARD.encode(data);
+            loaded.insert(id.clone(), (meta, data));
+        }
+        let (meta, data) = &loaded[&id];
+        let mime_type = part
+            .pointer("/fileData/mimeType")
+            .and_then(|m| m.as_str())
+            .filter(|m| !m.is_empty())
+            .unwrap_or(&meta.mime_type)
+            .to_string();
+        if let Some(obj) = part.as_object_mut() {
+            obj.remove("fileData");
+            obj.insert(
+                "inlineData".to_string(),
+                json!({ "mimeType": mime_type, "data": data }),
+            );
+        }
+    }
+    if !loaded.is_empty() {
+        tracing::debug!("[GeminiFiles] Inlined {} local files", loaded.len());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_id_from_uri() {
+        assert_eq!(file_id_from_uri("files/abc123"), Some("abc123"));
+        assert_eq!(
+            file_id_from_uri("http://127.0.0.1:8045/v1beta/files/abc123"),
+            Some("abc123")
+        );
+        assert_eq!(
+            file_id_from_uri("https://proxy.example.com/v1beta/files/abc-1?alt=media"),
+            Some("abc-1")
+        );
+        assert_eq!(file_id_from_uri("https://www.youtube.com/watch?v=x"), None);
+        assert_eq!(file_id_from_uri("files/../secret"), None);
+        assert_eq!(file_id_from_uri("gs://bucket/files/x"), None);
+    }
+
+    #[test]
+    fn test_parse_expiration() {
+        let now = Utc::now();
+        let ttl = parse_expiration(&json!({"ttl": "300s"}), now)
+            .unwrap()
+            .unwrap();
+        assert_eq!((ttl - now).num_seconds(), 300);
+        assert!(parse_expiration(&json!({"ttl": "5m"}), now).is_err());
+        assert!(parse_expiration(&json!({}), now).unwrap().is_none());
+
+        let expire = timestamp(now + Duration::hours(2));
+        let parsed = parse_expiration(&json!({"expireTime": expire}), now)
+            .unwrap()
+            .unwrap();
+        assert_eq!((parsed - now).num_minutes(), 120);
+    }
+
+    #[tokio::test]
+    async fn test_expand_cached_content() {
+        let created = create_cached_content(
+            &json!({
+                "model": "gemini-2.5-flash",
+                "systemInstruction": {"parts": [{"text": "You are a lawyer."}]},
+                "contents": [{"role": "user", "parts": [{"text": "long document"}]}],
+                "ttl": "60s"
+            }),
+            Some("token-a"),
+        )
+        .unwrap();
+        assert_eq!(created["model"], "models/gemini-2.5-flash");
+        let name = created["name"].as_str().unwrap().to_string();
+
+        let mut body = json!({
+            "cachedContent": name,
+            "contents": [{"role": "user", "parts": [{"text": "question"}]}]
+        });
+        // 其他用户令牌与无归属调用方都看不到该缓存
+        for other in [Some("token-b"), None] {
+            let err = expand_request(&mut body.clone(), other).await.unwrap_err();
+            assert_eq!(err.0, StatusCode::NOT_FOUND);
+            assert!(list_cached_contents(other)
+                .iter()
+                .all(|entry| entry["name"] != name));
+            assert!(!delete_cached_content(&name, other));
+        }
+        expand_request(&mut body, Some("token-a")).await.unwrap();
+        assert!(body.get("cachedContent").is_none());
+        assert_eq!(body["contents"].as_array().unwrap().len(), 2);
+        assert_eq!(body["contents"][1]["parts"][0]["text"], "question");
+        assert_eq!(
+            body["systemInstruction"]["parts"][0]["text"],
+            "You are a lawyer."
+        );
+
+        assert!(delete_cached_content(&name, Some("token-a")));
+        let mut body = json!({ "cachedContent": name, "contents": [] });
+        let err = expand_request(&mut body, Some("token-a"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.0, StatusCode::NOT_FOUND);
+    }
+}
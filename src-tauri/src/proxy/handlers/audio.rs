@@ -1,26 +1,85 @@
 use axum::{
+    body::Body,
     extract::{Multipart, State},
-    http::StatusCode,
-    response::IntoResponse,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
+use base64::{engine::general_purpose, Engine as _};
+use bytes::Bytes;
+use futures::{stream, StreamExt, TryStreamExt};
 use serde_json::{json, Value};
 use tracing::{debug, info};
 use uuid::Uuid;
 
+use super::dispatch::Dispatcher;
+use crate::proxy::audio::chunker::{plan_chunks, AudioChunk, MAX_CHUNK_BYTES, TARGET_CHUNK_SECS};
+use crate::proxy::audio::format::{
+    self, map_voice, pcm_sample_rate, pcm_to_wav, Transcript, TranscriptFormat,
+};
 use crate::proxy::{audio::AudioProcessor, server::AppState};
 
+const DEFAULT_TRANSCRIPTION_MODEL: &str = "whisper-1";
+const DEFAULT_SPEECH_MODEL: &str = "tts-1";
+const DEFAULT_VOICE: &str = "alloy";
+const MAX_SPEECH_INPUT_CHARS: usize = 4096;
+/// 长音频分片的最大并行数
+const MAX_PARALLEL_CHUNKS: usize = 4;
+
+/// 转录 (原语言) 或翻译 (英文)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AudioTask {
+    Transcribe,
+    Translate,
+}
+
+impl AudioTask {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Transcribe => "transcribe",
+            Self::Translate => "translate",
+        }
+    }
+}
+
+/// multipart/form-data 中的转录参数
+struct AudioForm {
+    audio: Bytes,
+    filename: String,
+    model: String,
+    prompt: Option<String>,
+    response_format: TranscriptFormat,
+    language: Option<String>,
+    temperature: Option<f64>,
+    word_timestamps: bool,
+}
+
 /// 处理音频转录请求 (OpenAI Whisper API 兼容)
 pub async fn handle_audio_transcription(
     State(state): State<AppState>,
-    mut multipart: Multipart,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let mut audio_data: Option<Vec<u8>> = None;
+    multipart: Multipart,
+) -> Result<Response, (StatusCode, String)> {
+    handle_speech_to_text(state, multipart, AudioTask::Transcribe).await
+}
+
+/// 处理音频翻译请求 (翻译为英文，OpenAI Whisper API 兼容)
+pub async fn handle_audio_translation(
+    State(state): State<AppState>,
+    multipart: Multipart,
+) -> Result<Response, (StatusCode, String)> {
+    handle_speech_to_text(state, multipart, AudioTask::Translate).await
+}
+
+async fn parse_audio_form(mut multipart: Multipart) -> Result<AudioForm, (StatusCode, String)> {
+    let mut audio_data: Option<Bytes> = None;
     let mut filename: Option<String> = None;
-    let mut model = "gemini-2.0-flash-exp".to_string();
-    let mut prompt = "Generate a transcript of the speech.".to_string();
+    let mut model = DEFAULT_TRANSCRIPTION_MODEL.to_string();
+    let mut prompt = None;
+    let mut response_format = TranscriptFormat::Json;
+    let mut language = None;
+    let mut temperature = None;
+    let mut word_timestamps = false;
 
-    // 1. 解析 multipart/form-data
     while let Some(field) = multipart
         .next_field()
         .await
@@ -35,76 +94,518 @@ pub async fn handle_audio_transcription(
                     field
                         .bytes()
                         .await
-                        .map_err(|e| (StatusCode::BAD_REQUEST, format!("读取文件失败: {}", e)))?
-                        .to_vec(),
+                        .map_err(|e| (StatusCode::BAD_REQUEST, format!("读取文件失败: {}", e)))?,
                 );
             }
             "model" => {
                 model = field.text().await.unwrap_or(model);
             }
             "prompt" => {
-                prompt = field.text().await.unwrap_or(prompt);
+                prompt = field.text().await.ok().filter(|p| !p.trim().is_empty());
+            }
+            "response_format" => {
+                let value = field.text().await.unwrap_or_default();
+                response_format =
+                    TranscriptFormat::parse(&value).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+            }
+            "language" => {
+                language = field.text().await.ok().filter(|l| !l.trim().is_empty());
+            }
+            "temperature" => {
+                temperature = field.text().await.ok().and_then(|t| t.trim().parse().ok());
+            }
+            "timestamp_granularities[]" | "timestamp_granularities" => {
+                let value = field.text().await.unwrap_or_default();
+                word_timestamps |= value.split(',').any(|g| g.trim() == "word");
             }
             _ => {}
         }
     }
 
-    let audio_bytes = audio_data.ok_or((StatusCode::BAD_REQUEST, "缺少音频文件".to_string()))?;
+    Ok(AudioForm {
+        audio: audio_data.ok_or((StatusCode::BAD_REQUEST, "缺少音频文件".to_string()))?,
+        filename: filename.ok_or((StatusCode::BAD_REQUEST, "无法获取文件名".to_string()))?,
+        model,
+        prompt,
+        response_format,
+        language,
+        temperature,
+        word_timestamps,
+    })
+}
+
+/// 构建转录 / 翻译指令
+fn speech_to_text_instruction(task: AudioTask, form: &AudioForm) -> String {
+    let mut instruction = match task {
+        AudioTask::Transcribe => "Generate a transcript of the speech.".to_string(),
+        AudioTask::Translate => "Translate the speech into English.".to_string(),
+    };
+    if let Some(language) = &form.language {
+        instruction.push_str(&format!(" The spoken language is '{}'.", language));
+    }
+    if form.response_format.needs_segments() {
+        instruction.push_str(
+            " Split the output into segments of at most a few sentences, each with start and end \
+             timestamps in seconds measured from the beginning of the audio.",
+        );
+        if form.word_timestamps {
+            instruction.push_str(" Also list every word with its start and end timestamps.");
+        }
+    } else {
+        instruction.push_str(" Output only the text, without any commentary.");
+    }
+    if let Some(prompt) = &form.prompt {
+        instruction.push_str(&format!(
+            "\nContext and spelling hints from the user: {}",
+            prompt
+        ));
+    }
+    instruction
+}
 
-    let file_name = filename.ok_or((StatusCode::BAD_REQUEST, "无法获取文件名".to_string()))?;
+async fn handle_speech_to_text(
+    state: AppState,
+    multipart: Multipart,
+    task: AudioTask,
+) -> Result<Response, (StatusCode, String)> {
+    // 1. 解析 multipart/form-data
+    let form = parse_audio_form(multipart).await?;
 
     info!(
-        "收到音频转录请求: 文件={}, 大小={} bytes, 模型={}",
-        file_name,
-        audio_bytes.len(),
-        model
+        "收到音频{}请求: 文件={}, 大小={} bytes, 模型={}, 格式={:?}",
+        if task == AudioTask::Translate {
+            "翻译"
+        } else {
+            "转录"
+        },
+        form.filename,
+        form.audio.len(),
+        form.model,
+        form.response_format
     );
 
     // 2. 检测 MIME 类型
-    let mime_type =
-        AudioProcessor::detect_mime_type(&file_name).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let mime_type = AudioProcessor::detect_mime_type(&form.filename)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    // 3. 超过内联上限时切分为多个分片 (仅记录字节区间，不复制音频数据)
+    let chunks = if AudioProcessor::exceeds_size_limit(form.audio.len()) {
+        let chunks = plan_chunks(&form.audio, &mime_type, TARGET_CHUNK_SECS, MAX_CHUNK_BYTES)
+            .map_err(|e| {
+                let size_mb = form.audio.len() as f64 / (1024.0 * 1024.0);
+                (
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    format!("音频文件过大 ({:.1} MB) 且无法分片: {}", size_mb, e),
+                )
+            })?;
+        info!(
+            "音频超过内联上限，切分为 {} 个分片: {}",
+            chunks.len(),
+            chunks
+                .iter()
+                .map(|c| format!("#{} {:.1}s+{:.1}s", c.index, c.start_secs, c.duration_secs))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        chunks
+    } else {
+        vec![AudioChunk::whole(form.audio.len())]
+    };
+
+    // 4. 构建 Gemini 请求公共部分 (需要时间戳时要求按 JSON Schema 输出分段)
+    let instruction = speech_to_text_instruction(task, &form);
+    let mut generation_config = json!({});
+    if let Some(temperature) = form.temperature {
+        generation_config["temperature"] = json!(temperature);
+    }
+    if form.response_format.needs_segments() {
+        generation_config["responseMimeType"] = json!("application/json");
+        generation_config["responseSchema"] = format::transcript_schema(form.word_timestamps);
+    }
+
+    // 5. 各分片并行发送 (每个分片独立轮换账号)，同时在途的 Base64 数据不超过并行度
+    let mapped_model = map_audio_model(&state, &form.model).await;
+    let parallelism = MAX_PARALLEL_CHUNKS.min(state.token_manager.len().max(1));
+    let results: Vec<(Value, String)> = stream::iter(chunks.iter().map(|chunk| {
+        let instruction = &instruction;
+        let generation_config = &generation_config;
+        let mapped_model = &mapped_model;
+        let mime_type = &mime_type;
+        let audio = &form.audio;
+        let state = &state;
+        async move {
+            debug!("使用 Inline Data 方式处理分片 #{}", chunk.index);
+            let gemini_request = json!({
+                "contents": [{
+                    "role": "user",
+                    "parts": [
+                        {"text": instruction},
+                        {
+                            "inlineData": {
+                                "mimeType": mime_type,
+                                "data": chunk.encode_base64(audio)
+                            }
+                        }
+                    ]
+                }],
+                "generationConfig": generation_config
+            });
+            dispatch_audio(state, mapped_model, &gemini_request, "audio").await
+        }
+    }))
+    .buffered(parallelism)
+    .try_collect()
+    .await?;
 
-    // 3. 验证文件大小
-    if AudioProcessor::exceeds_size_limit(audio_bytes.len()) {
-        let size_mb = audio_bytes.len() as f64 / (1024.0 * 1024.0);
+    // 6. 合并各分片结果 (时间戳按分片起点偏移)
+    let mut raw_parts = Vec::with_capacity(results.len());
+    let mut transcript = Transcript::default();
+    let mut usage = AudioUsage::default();
+    let mut emails: Vec<String> = Vec::new();
+    for ((result, email), chunk) in results.into_iter().zip(&chunks) {
+        let raw = response_text(&result);
+        if form.response_format.needs_segments() {
+            transcript.append(Transcript::parse(&raw), chunk.start_secs);
+        }
+        raw_parts.push(raw.trim().to_string());
+        usage.add(&AudioUsage::from_response(&result));
+        if !emails.contains(&email) {
+            emails.push(email);
+        }
+    }
+    let email = emails.join(", ");
+    let text = raw_parts.join(" ");
+    info!("使用账号: {}", email);
+
+    // 7. 转换为请求的格式
+    let language = match task {
+        AudioTask::Translate => Some("english"),
+        AudioTask::Transcribe => form.language.as_deref(),
+    };
+    let body = match form.response_format {
+        TranscriptFormat::Json => json!({"text": text, "usage": usage.to_json()}).to_string(),
+        TranscriptFormat::Text => text,
+        TranscriptFormat::Srt => format::to_srt(&transcript.segments),
+        TranscriptFormat::Vtt => format::to_vtt(&transcript.segments),
+        TranscriptFormat::VerboseJson => {
+            let mut body =
+                format::to_verbose_json(task.name(), &transcript, language, form.word_timestamps);
+            body["usage"] = usage.to_json();
+            body.to_string()
+        }
+    };
+
+    info!("音频{}完成，返回 {} 字符", task.name(), body.len());
+
+    // 8. 返回响应
+    Ok(audio_response(
+        form.response_format.content_type(),
+        Body::from(body),
+        &email,
+        &mapped_model,
+        &usage,
+    ))
+}
+
+/// 处理语音合成请求 (OpenAI Speech API 兼容)
+pub async fn handle_audio_speech(
+    State(state): State<AppState>,
+    Json(body): Json<Value>,
+) -> Result<Response, (StatusCode, String)> {
+    let input = body
+        .get("input")
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.trim().is_empty())
+        .ok_or((StatusCode::BAD_REQUEST, "缺少 input 文本".to_string()))?;
+    if input.chars().count() > MAX_SPEECH_INPUT_CHARS {
         return Err((
-            StatusCode::PAYLOAD_TOO_LARGE,
+            StatusCode::BAD_REQUEST,
+            format!("input 过长，最多 {} 个字符", MAX_SPEECH_INPUT_CHARS),
+        ));
+    }
+    let model = body
+        .get("model")
+        .and_then(|v| v.as_str())
+        .unwrap_or(DEFAULT_SPEECH_MODEL);
+    let voice = map_voice(
+        body.get("voice")
+            .and_then(|v| v.as_str())
+            .unwrap_or(DEFAULT_VOICE),
+    );
+    let response_format = body
+        .get("response_format")
+        .and_then(|v| v.as_str())
+        .unwrap_or("wav")
+        .to_lowercase();
+    // 上游只输出 PCM，且未内置 mp3 / opus / aac / flac 编码器，仅支持 wav (PCM 封装) 与 pcm
+    if !matches!(response_format.as_str(), "wav" | "pcm") {
+        return Err((
+            StatusCode::BAD_REQUEST,
             format!(
-                "音频文件过大 ({:.1} MB)。最大支持 15 MB (约 16 分钟 MP3)。建议: 1) 压缩音频质量 2) 分段上传",
-                size_mb
+                "不支持的 response_format: {}，仅支持 wav 与 pcm",
+                response_format
             ),
         ));
     }
+    let speed = body.get("speed").and_then(|v| v.as_f64()).unwrap_or(1.0);
+    if !(0.25..=4.0).contains(&speed) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "speed 取值范围为 0.25 - 4.0".to_string(),
+        ));
+    }
 
-    // 4. 使用 Inline Data 方式
-    debug!("使用 Inline Data 方式处理");
-    let base64_audio = AudioProcessor::encode_to_base64(&audio_bytes);
+    // Gemini TTS 没有语速 / 风格参数，通过指令前缀表达
+    let mut style = body
+        .get("instructions")
+        .and_then(|v| v.as_str())
+        .map(|s| s.trim().to_string())
+        .unwrap_or_default();
+    if (speed - 1.0).abs() > f64::EPSILON {
+        if !style.is_empty() {
+            style.push(' ');
+        }
+        style.push_str(&format!("Speak at {}x the normal pace.", speed));
+    }
+    let prompt = if style.is_empty() {
+        input.to_string()
+    } else {
+        format!("{}\n\n{}", style, input)
+    };
+
+    let mapped_model = map_audio_model(&state, model).await;
+    info!(
+        "收到语音合成请求: 模型={} -> {}, 音色={}, 格式={}, 长度={} 字符",
+        model,
+        mapped_model,
+        voice,
+        response_format,
+        input.chars().count()
+    );
 
-    // 5. 构建 Gemini 请求
     let gemini_request = json!({
-        "contents": [{
-            "parts": [
-                {"text": prompt},
-                {
-                    "inlineData": {
-                        "mimeType": mime_type,
-                        "data": base64_audio
-                    }
-                }
-            ]
-        }]
+        "contents": [{"role": "user", "parts": [{"text": prompt}]}],
+        "generationConfig": {
+            "responseModalities": ["AUDIO"],
+            "speechConfig": {
+                "voiceConfig": {"prebuiltVoiceConfig": {"voiceName": voice}}
+            }
+        }
     });
 
-    // 6. 获取 Token 和上游客户端
-    let token_manager = state.token_manager;
-    let (access_token, project_id, email, account_id, _wait_ms) = token_manager
-        .get_token("text", false, None, &model)
-        .await
-        .map_err(|e| (StatusCode::SERVICE_UNAVAILABLE, e))?;
+    let (result, email) = dispatch_audio(&state, &mapped_model, &gemini_request, "speech").await?;
+    let usage = AudioUsage::from_response(&result);
 
-    info!("使用账号: {}", email);
+    let inner = result.get("response").unwrap_or(&result);
+    let inline = inner
+        .pointer("/candidates/0/content/parts")
+        .and_then(|p| p.as_array())
+        .and_then(|parts| parts.iter().find_map(|p| p.get("inlineData")))
+        .ok_or((StatusCode::BAD_GATEWAY, "上游未返回音频数据".to_string()))?;
+    let pcm = inline
+        .get("data")
+        .and_then(|d| d.as_str())
+        .and_then(|d| general_purpose::STANDARD.decode(d).ok())
+        .ok_or((StatusCode::BAD_GATEWAY, "上游音频数据无效".to_string()))?;
+    let sample_rate = inline
+        .get("mimeType")
+        .and_then(|m| m.as_str())
+        .map(pcm_sample_rate)
+        .unwrap_or(24_000);
+
+    // pcm 原样返回，wav 封装 PCM
+    let (content_type, audio) = if response_format == "pcm" {
+        ("audio/pcm", pcm)
+    } else {
+        ("audio/wav", pcm_to_wav(&pcm, sample_rate))
+    };
+
+    info!("语音合成完成: 账号={}, {} bytes", email, audio.len());
+    Ok(audio_response(
+        content_type,
+        Body::from(audio),
+        &email,
+        &mapped_model,
+        &usage,
+    ))
+}
+
+/// 音频模型名映射 (复用自定义映射与内置映射)
+async fn map_audio_model(state: &AppState, model: &str) -> String {
+    crate::proxy::common::model_mapping::resolve_model_route(
+        model,
+        &*state.custom_mapping.read().await,
+    )
+}
+
+/// 上游返回的 token 用量
+#[derive(Debug, Default)]
+struct AudioUsage {
+    input_tokens: u64,
+    output_tokens: u64,
+}
+
+impl AudioUsage {
+    fn from_response(result: &Value) -> Self {
+        let inner = result.get("response").unwrap_or(result);
+        let usage = inner.get("usageMetadata");
+        let count = |key: &str| {
+            usage
+                .and_then(|u| u.get(key))
+                .and_then(|v| v.as_u64())
+                .unwrap_or(0)
+        };
+        Self {
+            input_tokens: count("promptTokenCount"),
+            output_tokens: count("candidatesTokenCount"),
+        }
+    }
+
+    fn add(&mut self, other: &AudioUsage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "type": "tokens",
+            "input_tokens": self.input_tokens,
+            "output_tokens": self.output_tokens,
+            "total_tokens": self.input_tokens + self.output_tokens
+        })
+    }
+}
+
+/// 构建音频接口响应；非 JSON 响应通过头部携带用量供监控统计
+fn audio_response(
+    content_type: &str,
+    body: Body,
+    email: &str,
+    mapped_model: &str,
+    usage: &AudioUsage,
+) -> Response {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, content_type)
+        .header("X-Account-Email", email)
+        .header("X-Mapped-Model", mapped_model)
+        .header("X-Usage-Input-Tokens", usage.input_tokens)
+        .header("X-Usage-Output-Tokens", usage.output_tokens)
+        .body(body)
+        .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())
+}
 
-    // 7. 包装请求为 v1internal 格式
+/// 拼接候选中的全部文本片段（解包 v1internal 响应）
+fn response_text(result: &Value) -> String {
+    let inner_response = result.get("response").unwrap_or(result);
+    inner_response
+        .pointer("/candidates/0/content/parts")
+        .and_then(|p| p.as_array())
+        .map(|parts| {
+            parts
+                .iter()
+                .filter_map(|p| p.get("text").and_then(|t| t.as_str()))
+                .collect::<String>()
+        })
+        .unwrap_or_default()
+}
+
+/// 通过 Dispatcher 发送请求 (失败时轮换账号重试，慢请求可对冲)
+async fn dispatch_audio(
+    state: &AppState,
+    model: &str,
+    gemini_request: &Value,
+    trace_prefix: &str,
+) -> Result<(Value, String), (StatusCode, String)> {
+    let token_manager = state.token_manager.clone();
+    let trace_id = format!("{}_{}", trace_prefix, Uuid::new_v4().simple());
+    let mut dispatcher = Dispatcher::new(token_manager.len(), 1, &trace_id);
+    let max_attempts = dispatcher.max_attempts();
+    let mut last_error = (StatusCode::SERVICE_UNAVAILABLE, "没有可用账号".to_string());
+
+    for attempt in 0..max_attempts {
+        if dispatcher.deadline_exceeded(attempt) {
+            break;
+        }
+        let force_rotate = dispatcher.force_rotate(attempt);
+        let attempt_result = dispatcher.hedged(force_rotate, |force| {
+            audio_attempt(state, force, model, gemini_request)
+        });
+        let result = dispatcher
+            .within_deadline(attempt_result, AudioAttemptError::Network)
+            .await;
+
+        match result {
+            Ok(ok) => return Ok(ok),
+            Err(AudioAttemptError::Token(e)) => {
+                last_error = (StatusCode::SERVICE_UNAVAILABLE, e);
+                if !dispatcher.retry_transient(attempt).await {
+                    break;
+                }
+            }
+            Err(AudioAttemptError::Network(e)) => {
+                debug!(
+                    "[{}] 音频请求失败 ({}/{}): {}",
+                    trace_id,
+                    attempt + 1,
+                    max_attempts,
+                    e
+                );
+                last_error = (StatusCode::BAD_GATEWAY, format!("上游请求失败: {}", e));
+                if !dispatcher.retry_transient(attempt).await {
+                    break;
+                }
+            }
+            Err(AudioAttemptError::Upstream {
+                status,
+                email,
+                text,
+            }) => {
+                if status == 429 || status == 503 || status == 500 {
+                    token_manager
+                        .mark_rate_limited_async(&email, status, None, &text, Some(model))
+                        .await;
+                }
+                let retry = dispatcher.retry(attempt, status, &text, false).await;
+                last_error = (
+                    StatusCode::BAD_GATEWAY,
+                    format!("Gemini API 错误: {}", text),
+                );
+                if !retry {
+                    break;
+                }
+            }
+        }
+    }
+
+    Err(last_error)
+}
+
+/// 单次音频请求尝试的失败原因
+enum AudioAttemptError {
+    Token(String),
+    Upstream {
+        status: u16,
+        email: String,
+        text: String,
+    },
+    Network(String),
+}
+
+/// 获取 token 并发起一次音频请求，成功时返回 (响应 JSON, 账号邮箱)
+async fn audio_attempt(
+    state: &AppState,
+    force_rotate: bool,
+    model: &str,
+    gemini_request: &Value,
+) -> Result<(Value, String), AudioAttemptError> {
+    let (access_token, project_id, email, account_id, _wait_ms) = state
+        .token_manager
+        .get_token("text", force_rotate, None, model)
+        .await
+        .map_err(AudioAttemptError::Token)?;
+
+    // 包装请求为 v1internal 格式
     let wrapped_body = json!({
         "project": project_id,
         "requestId": format!("audio-{}", Uuid::new_v4()),
@@ -114,9 +615,8 @@ pub async fn handle_audio_transcription(
         "requestType": "text"
     });
 
-    // 8. 发送请求到 Gemini
-    let upstream = state.upstream.clone();
-    let response = upstream
+    let response = state
+        .upstream
         .call_v1_internal(
             "generateContent",
             &access_token,
@@ -125,46 +625,25 @@ pub async fn handle_audio_transcription(
             Some(account_id.as_str()),
         )
         .await
-        .map_err(|e| (StatusCode::BAD_GATEWAY, format!("上游请求失败: {}", e)))?
+        .map_err(AudioAttemptError::Network)?
         .response;
 
+    let status = response.status().as_u16();
     if !response.status().is_success() {
-        let error_text = response
+        let text = response
             .text()
             .await
             .unwrap_or_else(|_| "Unknown error".to_string());
-        return Err((
-            StatusCode::BAD_GATEWAY,
-            format!("Gemini API 错误: {}", error_text),
-        ));
+        return Err(AudioAttemptError::Upstream {
+            status,
+            email,
+            text,
+        });
     }
 
-    let result: Value = response
-        .json()
+    response
+        .json::<Value>()
         .await
-        .map_err(|e| (StatusCode::BAD_GATEWAY, format!("解析响应失败: {}", e)))?;
-
-    // 9. 提取文本响应（解包 v1internal 响应）
-    let inner_response = result.get("response").unwrap_or(&result);
-    let text = inner_response
-        .get("candidates")
-        .and_then(|c| c.get(0))
-        .and_then(|c| c.get("content"))
-        .and_then(|c| c.get("parts"))
-        .and_then(|p| p.get(0))
-        .and_then(|p| p.get("text"))
-        .and_then(|t| t.as_str())
-        .unwrap_or("");
-
-    info!("音频转录完成，返回 {} 字符", text.len());
-
-    // 10. 返回标准格式响应
-    Ok((
-        StatusCode::OK,
-        [("X-Account-Email", email.as_str())],
-        Json(json!({
-            "text": text
-        })),
-    )
-        .into_response())
+        .map(|result| (result, email))
+        .map_err(|e| AudioAttemptError::Network(format!("解析响应失败: {}", e)))
 }
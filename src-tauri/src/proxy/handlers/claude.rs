@@ -16,14 +16,17 @@ use crate::proxy::mappers::claude::{
     transform_claude_request_in, transform_response, create_claude_sse_stream, ClaudeRequest,
     filter_invalid_thinking_blocks_with_family, close_tool_loop_for_thinking,
     clean_cache_control_from_messages, merge_consecutive_messages,
-    models::{Message, MessageContent},
+    models::{ClaudeResponse, ContentBlock, Message, MessageContent},
 };
 use crate::proxy::server::AppState;
 use crate::proxy::mappers::context_manager::ContextManager;
+use crate::proxy::context_window::SummaryUpstream;
 use crate::proxy::mappers::estimation_calibrator::get_calibrator;
 use crate::proxy::debug_logger;
 use crate::proxy::upstream::client::mask_email;
-use crate::proxy::common::client_adapter::CLIENT_ADAPTERS; // [NEW] Import Adapter Registry
+use crate::proxy::common::client_adapter::find_client_adapter; // [NEW] Import Adapter Registry
+use crate::proxy::common::task_detection::{detect_task_type, is_detection_candidate, BackgroundTaskType};
+use crate::proxy::common::schema_validate::{self, ToolArgVerdict};
 use axum::http::HeaderMap;
 use std::sync::{atomic::Ordering, Arc};
 use crate::proxy::model_specs; // [NEW]
@@ -157,8 +160,6 @@ fn apply_thinking_hints(
     }
 }
 
-const MAX_RETRY_ATTEMPTS: usize = 3;
-
 // ===== Model Constants for Background Tasks =====
 // These can be adjusted for performance/cost optimization or overridden by custom_mapping
 const INTERNAL_BACKGROUND_TASK: &str = "internal-background-task";  // Unified virtual ID for all background tasks
@@ -235,7 +236,8 @@ The structure MUST be as follows:
 
 // ===== 统一退避策略模块 =====
 // 移除本地重复定义，使用 common 中的统一实现
-use super::common::{determine_retry_strategy, apply_retry_strategy, should_rotate_account, RetryStrategy};
+use super::common::RetryStrategy;
+use super::dispatch::Dispatcher;
 
 // ===== 退避策略模块结束 =====
 
@@ -254,15 +256,18 @@ pub async fn handle_messages(
     tracing::debug!("handle_messages called. Body JSON len: {}", body.to_string().len());
     
     // 生成随机 Trace ID 用户追踪
-    let trace_id: String = rand::Rng::sample_iter(rand::thread_rng(), &rand::distributions::Alphanumeric)
-        .take(6)
-        .map(char::from)
-        .collect::<String>().to_lowercase();
+    // 配置了脚本钩子时复用中间件生成的 trace id
+    let trace_id: String = crate::proxy::script_hooks::current_trace_id().unwrap_or_else(|| {
+        rand::Rng::sample_iter(rand::thread_rng(), &rand::distributions::Alphanumeric)
+            .take(6)
+            .map(char::from)
+            .collect::<String>().to_lowercase()
+    });
     let debug_cfg = state.debug_logging.read().await.clone();
     
     // [NEW] Detect Client Adapter
     // 检查是否有匹配的客户端适配器（如 opencode）
-    let client_adapter = CLIENT_ADAPTERS.iter().find(|a| a.matches(&headers)).cloned();
+    let client_adapter = find_client_adapter(&headers);
     if let Some(_adapter) = &client_adapter {
         tracing::debug!("[{}] Client Adapter detected: Applying custom strategies", trace_id);
     }
@@ -405,6 +410,25 @@ pub async fn handle_messages(
         )
         .await;
     }
+
+    // [NEW] 通用第三方提供商 (Anthropic 协议透传)
+    if let Some(route) = crate::proxy::providers::generic::resolve_route(
+        &state,
+        crate::proxy::ProviderProtocol::Anthropic,
+        &request.model,
+        &trace_id,
+    )
+    .await
+    {
+        let new_body = match serde_json::to_value(&request) {
+            Ok(v) => v,
+            Err(e) => {
+                tracing::error!("Failed to serialize fixed request for provider {}: {}", route.provider.name, e);
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+        };
+        return crate::proxy::providers::generic::forward(&state, &route, "/v1/messages", &headers, new_body).await;
+    }
     
     // Google Flow 继续使用 request 对象
     // (后续代码不需要再次 filter_invalid_thinking_blocks)
@@ -415,6 +439,7 @@ pub async fn handle_messages(
     let threshold_l1 = experimental.context_compression_threshold_l1;
     let threshold_l2 = experimental.context_compression_threshold_l2;
     let threshold_l3 = experimental.context_compression_threshold_l3;
+    let tool_arg_validation = experimental.tool_arg_validation.clone();
 
     // 获取最新一条“有意义”的消息内容（用于日志记录和后台任务检测）
     // 策略：反向遍历，首先筛选出所有角色为 "user" 的消息，然后从中找到第一条非 "Warmup" 且非空的文本消息
@@ -442,14 +467,7 @@ pub async fn handle_messages(
             // 1. 忽略空消息
             // 2. 忽略 "Warmup" 消息
             // 3. 忽略 <system-reminder> 标签的消息
-            if content.trim().is_empty() 
-                || content.starts_with("Warmup") 
-                || content.contains("<system-reminder>") 
-            {
-                None 
-            } else {
-                Some(content)
-            }
+            is_detection_candidate(&content).then_some(content)
         });
 
     // 如果经过过滤还是找不到（例如纯工具调用），则回退到最后一条消息的原始展示
@@ -521,15 +539,29 @@ pub async fn handle_messages(
     let pool_size = token_manager.len();
     // [FIX] Ensure max_attempts is at least 2 to allow for internal retries (e.g. stripping signatures)
     // even if the user has only 1 account.
-    let max_attempts = MAX_RETRY_ATTEMPTS.min(pool_size.saturating_add(1)).max(2);
+    let mut dispatcher = Dispatcher::new(pool_size.saturating_add(1), 2, &trace_id);
+    let max_attempts = dispatcher.max_attempts();
 
     let mut last_error = String::new();
     let retried_without_thinking = false;
     let mut last_email: Option<String> = None;
     let mut last_mapped_model: Option<String> = None;
     let mut last_status = StatusCode::SERVICE_UNAVAILABLE; // Default to 503 if no response reached
+
+    // 按客户端原始 Schema 校验返回的工具调用参数 (仅非流式响应)
+    let tool_schemas: std::collections::HashMap<String, Value> = request
+        .tools
+        .iter()
+        .flatten()
+        .filter_map(|tool| Some((tool.name.clone()?, tool.input_schema.clone()?)))
+        .collect();
+    let mut tool_arg_retries = 0u32;
     
     for attempt in 0..max_attempts {
+        if dispatcher.deadline_exceeded(attempt) {
+            break;
+        }
+
         // 2. 模型路由解析
         let mut mapped_model = crate::proxy::common::model_mapping::resolve_model_route(
             &request_for_body.model,
@@ -557,7 +589,7 @@ pub async fn handle_messages(
         let session_id_str = crate::proxy::session_manager::SessionManager::extract_session_id(&request_for_body);
         let session_id = Some(session_id_str.as_str());
 
-        let force_rotate_token = attempt > 0;
+        let force_rotate_token = dispatcher.force_rotate(attempt);
         let (access_token, project_id, email, account_id, _wait_ms) = match token_manager.get_token(&config.request_type, force_rotate_token, session_id, &config.final_model).await {
             Ok(t) => t,
             Err(e) => {
@@ -651,9 +683,9 @@ pub async fn handle_messages(
             };
 
             // 2. [ENHANCED] 使用校准器提高估算准确度 (PR #925)
-            let raw_estimated = ContextManager::estimate_token_usage(&request_with_mapped);
             let calibrator = get_calibrator();
-            let mut estimated_usage = calibrator.calibrate(raw_estimated);
+            let (raw_estimated, mut estimated_usage) =
+                ContextManager::estimate_calibrated_usage(&request_with_mapped, &mapped_model);
             let mut usage_ratio = estimated_usage as f32 / context_limit as f32;
             
             info!(
@@ -673,8 +705,8 @@ pub async fn handle_messages(
                     compression_applied = true;
                     
                     // Re-estimate after trimming (with calibration)
-                    let new_raw = ContextManager::estimate_token_usage(&request_with_mapped);
-                    let new_usage = calibrator.calibrate(new_raw);
+                    let (_, new_usage) =
+                        ContextManager::estimate_calibrated_usage(&request_with_mapped, &mapped_model);
                     let new_ratio = new_usage as f32 / context_limit as f32;
                     
                     info!(
@@ -712,8 +744,8 @@ pub async fn handle_messages(
                     is_purified = true; // Still breaks cache, but preserves signatures
                     compression_applied = true;
                     
-                    let new_raw = ContextManager::estimate_token_usage(&request_with_mapped);
-                    let new_usage = calibrator.calibrate(new_raw);
+                    let (_, new_usage) =
+                        ContextManager::estimate_calibrated_usage(&request_with_mapped, &mapped_model);
                     let new_ratio = new_usage as f32 / context_limit as f32;
                     
                     info!(
@@ -750,8 +782,8 @@ pub async fn handle_messages(
                         is_purified = false; // Fork doesn't break cache!
                         
                         // Re-estimate after fork (with calibration)
-                        let new_raw = ContextManager::estimate_token_usage(&request_with_mapped);
-                        let new_usage = calibrator.calibrate(new_raw);
+                        let (_, new_usage) =
+                            ContextManager::estimate_calibrated_usage(&request_with_mapped, &mapped_model);
                         let new_ratio = new_usage as f32 / context_limit as f32;
                         
                         info!(
@@ -784,10 +816,13 @@ pub async fn handle_messages(
 
         // [FIX] Estimate AFTER purification to get accurate token count for calibrator learning
         // Only estimate for calibrator when content was not purified, to avoid skewed learning
-        let raw_estimated = if !is_purified {
-            ContextManager::estimate_token_usage(&request_with_mapped)
+        let prompt_estimate = if !is_purified {
+            Some((
+                mapped_model.clone(),
+                ContextManager::estimate_breakdown(&request_with_mapped, &mapped_model),
+            ))
         } else {
-            0 // Don't record calibration data when content was purified
+            None // Don't record calibration data when content was purified
         };
 
         request_with_mapped.model = mapped_model.clone();
@@ -796,7 +831,7 @@ pub async fn handle_messages(
         // let _trace_id = format!("req_{}", chrono::Utc::now().timestamp_subsec_millis());
 
         let token_obj = token_manager.get_token_by_id(&account_id);
-        let gemini_body = match transform_claude_request_in(&request_with_mapped, &project_id, retried_without_thinking, Some(account_id.as_str()), &session_id_str, token_obj.as_ref()) {
+        let mut gemini_body = match transform_claude_request_in(&request_with_mapped, &project_id, retried_without_thinking, Some(account_id.as_str()), &session_id_str, token_obj.as_ref()) {
             Ok(b) => {
                 debug!("[{}] Transformed Gemini Body: {}", trace_id, serde_json::to_string_pretty(&b).unwrap_or_default());
                 b
@@ -820,6 +855,39 @@ pub async fn handle_messages(
             }
         };
 
+        // 上下文窗口策略 (按用户令牌 / 客户端选择)，裁剪后的估算不再用于校准
+        let context_trimmed = crate::proxy::context_window::apply_current(
+            &mut gemini_body,
+            &mapped_model,
+            SummaryUpstream {
+                upstream: &upstream,
+                access_token: &access_token,
+                project_id: &project_id,
+                account_id: &account_id,
+            },
+            &trace_id,
+        )
+        .await;
+        let prompt_estimate = if context_trimmed { None } else { prompt_estimate };
+
+        // 思考策略 (按模型家族 / 调用方选择)
+        crate::proxy::thinking_policy::apply_current(&mut gemini_body, &mapped_model, &trace_id);
+
+        // 脚本钩子 (upstream_request 阶段)
+        if let Err(e) = crate::proxy::script_hooks::apply_upstream(
+            crate::proxy::ProviderProtocol::Anthropic,
+            &mut gemini_body,
+            &mapped_model,
+            &trace_id,
+        )
+        .await
+        {
+            return crate::proxy::script_hooks::reject_response(
+                crate::proxy::ProviderProtocol::Anthropic,
+                &e,
+            );
+        }
+
         if debug_logger::is_enabled(&debug_cfg) {
             let payload = json!({
                 "kind": "v1internal_request",
@@ -869,8 +937,11 @@ pub async fn handle_messages(
 
         // Upstream call configuration continued...
 
-        let call_result = match upstream
-            .call_v1_internal_with_headers(method, &access_token, gemini_body, query, extra_headers.clone(), Some(account_id.as_str()))
+        let call_result = match dispatcher
+            .within_deadline(
+                upstream.call_v1_internal_with_headers(method, &access_token, gemini_body, query, extra_headers.clone(), Some(account_id.as_str())),
+                |e| e,
+            )
             .await {
             Ok(r) => r,
             Err(e) => {
@@ -954,7 +1025,7 @@ pub async fn handle_messages(
                     Some(session_id_str.clone()),
                     scaling_enabled,
                     context_limit,
-                    Some(raw_estimated), // [FIX] Pass estimated tokens for calibrator learning
+                    prompt_estimate.clone(), // [FIX] Pass estimated tokens for calibrator learning
                     current_message_count, // [NEW v4.0.0] Pass message count for rewind detection
                     client_adapter.clone(), // [NEW] Pass client adapter
                     registered_tool_names, // [FIX #MCP] Pass tool names for fuzzy matching
@@ -1040,6 +1111,18 @@ pub async fn handle_messages(
                             match collect_stream_to_json(combined_stream).await {
                                 Ok(full_response) => {
                                     info!("[{}] ✓ Stream collected and converted to JSON", trace_id);
+                                    let violations = tool_arg_violations(&full_response, &tool_schemas);
+                                    match schema_validate::decide(&tool_arg_validation, &violations, tool_arg_retries, &trace_id) {
+                                        ToolArgVerdict::Retry => {
+                                            tool_arg_retries += 1;
+                                            last_error = format!("Invalid tool call arguments: {}", violations.join("; "));
+                                            continue;
+                                        }
+                                        ToolArgVerdict::Reject(message) => {
+                                            return (StatusCode::BAD_GATEWAY, message).into_response();
+                                        }
+                                        ToolArgVerdict::Accept => {}
+                                    }
                                     return Response::builder()
                                         .status(StatusCode::OK)
                                         .header(header::CONTENT_TYPE, "application/json")
@@ -1087,6 +1170,17 @@ pub async fn handle_messages(
                     Ok(r) => r,
                     Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, format!("Convert error: {}", e)).into_response(),
                 };
+
+                // [FIX] Record actual token usage for calibrator learning (non-stream path)
+                if let (Some((model, breakdown)), Some(actual)) = (
+                    &prompt_estimate,
+                    gemini_response
+                        .usage_metadata
+                        .as_ref()
+                        .and_then(|u| u.prompt_token_count),
+                ) {
+                    get_calibrator().record_breakdown(model, breakdown, actual);
+                }
                 
                 // Determine context limit based on model
                 let context_limit = crate::proxy::mappers::claude::utils::get_context_limit_for_model(&request_with_mapped.model);
@@ -1107,6 +1201,19 @@ pub async fn handle_messages(
                     Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, format!("Transform error: {}", e)).into_response(),
                 };
 
+                let violations = tool_arg_violations(&claude_response, &tool_schemas);
+                match schema_validate::decide(&tool_arg_validation, &violations, tool_arg_retries, &trace_id) {
+                    ToolArgVerdict::Retry => {
+                        tool_arg_retries += 1;
+                        last_error = format!("Invalid tool call arguments: {}", violations.join("; "));
+                        continue;
+                    }
+                    ToolArgVerdict::Reject(message) => {
+                        return (StatusCode::BAD_GATEWAY, message).into_response();
+                    }
+                    ToolArgVerdict::Accept => {}
+                }
+
                 // [Optimization] 记录闭环日志：消耗情况
                 let cache_info = if let Some(cached) = claude_response.usage.cache_read_input_tokens {
                     format!(", Cached: {}", cached)
@@ -1258,13 +1365,10 @@ pub async fn handle_messages(
             
             // [FIX] 强制重试：因为我们已经清理了 thinking block，所以这是一个新的、可以重试的请求
             // 不要使用 determine_retry_strategy，因为它会因为 retried_without_thinking=true 而返回 NoRetry
-            if apply_retry_strategy(
-                RetryStrategy::FixedDelay(Duration::from_millis(200)), 
-                attempt, 
-                max_attempts,
-                status_code, 
-                &trace_id
-            ).await {
+            if dispatcher
+                .backoff(RetryStrategy::FixedDelay(Duration::from_millis(200)), attempt, status_code)
+                .await
+            {
                 continue;
             }
         }
@@ -1299,15 +1403,11 @@ pub async fn handle_messages(
             }
         }
 
-        // 确定重试策略
-        let strategy = determine_retry_strategy(status_code, &error_text, retried_without_thinking);
-        
-        // 执行退避
-        if apply_retry_strategy(strategy, attempt, max_attempts, status_code, &trace_id).await {
-            // 判断是否需要轮换账号
-            if !should_rotate_account(status_code) {
-                debug!("[{}] Keeping same account for status {} (server-side issue)", trace_id, status_code);
-            }
+        // 确定重试策略并执行退避 (是否轮换账号由 Dispatcher 在下一次取 token 时决定)
+        if dispatcher
+            .retry(attempt, status_code, &error_text, retried_without_thinking)
+            .await
+        {
             continue;
         } else {
             // 5. 增强的 400 错误处理: Prompt Too Long 友好提示
@@ -1409,21 +1509,10 @@ pub async fn handle_messages(
 
 /// 列出可用模型
 pub async fn handle_list_models(State(state): State<AppState>) -> impl IntoResponse {
-    use crate::proxy::common::model_mapping::get_all_dynamic_models;
-
-    let model_ids = get_all_dynamic_models(
-        &state.custom_mapping,
-        Some(&state.token_manager)
-    ).await;
-
-    let data: Vec<_> = model_ids.into_iter().map(|id| {
-        json!({
-            "id": id,
-            "object": "model",
-            "created": 1706745600,
-            "owned_by": "antigravity"
-        })
-    }).collect();
+    use crate::proxy::common::model_info::list_model_info;
+
+    let models = list_model_info(&state.custom_mapping, Some(&state.token_manager)).await;
+    let data: Vec<_> = models.iter().map(|m| m.to_openai_json()).collect();
 
     Json(json!({
         "object": "list",
@@ -1431,7 +1520,10 @@ pub async fn handle_list_models(State(state): State<AppState>) -> impl IntoRespo
     }))
 }
 
-/// 计算 tokens (占位符)
+/// 计算 tokens
+///
+/// 优先通过号池调用上游 countTokens；不可用时回退到本地估算 (经校准器修正，
+/// 包含图片、文档与工具定义)
 pub async fn handle_count_tokens(
     State(state): State<AppState>,
     headers: HeaderMap,
@@ -1452,11 +1544,70 @@ pub async fn handle_count_tokens(
         .await;
     }
 
-    Json(json!({
-        "input_tokens": 0,
-        "output_tokens": 0
-    }))
-    .into_response()
+    if let Some(model) = body.get("model").and_then(|v| v.as_str()) {
+        if let Some(route) = crate::proxy::providers::generic::resolve_route(
+            &state,
+            crate::proxy::ProviderProtocol::Anthropic,
+            model,
+            "count_tokens",
+        )
+        .await
+        {
+            return crate::proxy::providers::generic::forward(
+                &state,
+                &route,
+                "/v1/messages/count_tokens",
+                &headers,
+                body,
+            )
+            .await;
+        }
+    }
+
+    let mut request: ClaudeRequest = match serde_json::from_value(body) {
+        Ok(r) => r,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "type": "error",
+                    "error": {
+                        "type": "invalid_request_error",
+                        "message": format!("Invalid request body: {}", e)
+                    }
+                })),
+            )
+                .into_response();
+        }
+    };
+
+    request.model = crate::proxy::common::model_mapping::resolve_model_route(
+        &request.model,
+        &*state.custom_mapping.read().await,
+    );
+    let estimate = ContextManager::estimate_input_tokens(&request, &request.model);
+
+    let upstream_count = match transform_claude_request_in(&request, "", false, None, "count_tokens", None) {
+        Ok(gemini_body) => {
+            let contents = super::common::count_tokens_contents(&gemini_body["request"]);
+            super::common::count_tokens_upstream(&state, &request.model, contents).await
+        }
+        Err(e) => Err(e),
+    };
+
+    let input_tokens = match upstream_count {
+        Ok(count) => count,
+        Err(e) => {
+            let calibrated = estimate.calibrated(&request.model);
+            debug!(
+                "[CountTokens] Upstream count unavailable for {}, using estimate {} (text: {}, media: {}): {}",
+                request.model, calibrated, estimate.text.total(), estimate.media, e
+            );
+            calibrated
+        }
+    };
+
+    Json(json!({ "input_tokens": input_tokens })).into_response()
 }
 
 // 移除已失效的简单单元测试，后续将补全完整的集成测试
@@ -1474,109 +1625,10 @@ mod tests {
 
 // ===== 后台任务检测辅助函数 =====
 
-/// 后台任务类型
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum BackgroundTaskType {
-    TitleGeneration,      // 标题生成
-    SimpleSummary,        // 简单摘要
-    ContextCompression,   // 上下文压缩
-    PromptSuggestion,     // 提示建议
-    SystemMessage,        // 系统消息
-    EnvironmentProbe,     // 环境探测
-}
-
-/// 标题生成关键词
-const TITLE_KEYWORDS: &[&str] = &[
-    "write a 5-10 word title",
-    "Please write a 5-10 word title",
-    "Respond with the title",
-    "Generate a title for",
-    "Create a brief title",
-    "title for the conversation",
-    "conversation title",
-    "生成标题",
-    "为对话起个标题",
-];
-
-/// 摘要生成关键词
-const SUMMARY_KEYWORDS: &[&str] = &[
-    "Summarize this coding conversation",
-    "Summarize the conversation",
-    "Concise summary",
-    "in under 50 characters",
-    "compress the context",
-    "Provide a concise summary",
-    "condense the previous messages",
-    "shorten the conversation history",
-    "extract key points from",
-];
-
-/// 建议生成关键词
-const SUGGESTION_KEYWORDS: &[&str] = &[
-    "prompt suggestion generator",
-    "suggest next prompts",
-    "what should I ask next",
-    "generate follow-up questions",
-    "recommend next steps",
-    "possible next actions",
-];
-
-/// 系统消息关键词
-const SYSTEM_KEYWORDS: &[&str] = &[
-    "Warmup",
-    "<system-reminder>",
-    // Removed: "Caveat: The messages below were generated" - this is a normal Claude Desktop system prompt
-    "This is a system message",
-];
-
-/// 环境探测关键词
-const PROBE_KEYWORDS: &[&str] = &[
-    "check current directory",
-    "list available tools",
-    "verify environment",
-    "test connection",
-];
-
 /// 检测后台任务并返回任务类型
 fn detect_background_task_type(request: &ClaudeRequest) -> Option<BackgroundTaskType> {
     let last_user_msg = extract_last_user_message_for_detection(request)?;
-    let preview = last_user_msg.chars().take(500).collect::<String>();
-    
-    // 长度过滤：后台任务通常不超过 800 字符
-    if last_user_msg.len() > 800 {
-        return None;
-    }
-    
-    // 按优先级匹配
-    if matches_keywords(&preview, SYSTEM_KEYWORDS) {
-        return Some(BackgroundTaskType::SystemMessage);
-    }
-    
-    if matches_keywords(&preview, TITLE_KEYWORDS) {
-        return Some(BackgroundTaskType::TitleGeneration);
-    }
-    
-    if matches_keywords(&preview, SUMMARY_KEYWORDS) {
-        if preview.contains("in under 50 characters") {
-            return Some(BackgroundTaskType::SimpleSummary);
-        }
-        return Some(BackgroundTaskType::ContextCompression);
-    }
-    
-    if matches_keywords(&preview, SUGGESTION_KEYWORDS) {
-        return Some(BackgroundTaskType::PromptSuggestion);
-    }
-    
-    if matches_keywords(&preview, PROBE_KEYWORDS) {
-        return Some(BackgroundTaskType::EnvironmentProbe);
-    }
-    
-    None
-}
-
-/// 辅助函数：关键词匹配
-fn matches_keywords(text: &str, keywords: &[&str]) -> bool {
-    keywords.iter().any(|kw| text.contains(kw))
+    detect_task_type(&last_user_msg)
 }
 
 /// 辅助函数：提取最后一条用户消息（用于检测）
@@ -1597,14 +1649,7 @@ fn extract_last_user_message_for_detection(request: &ClaudeRequest) -> Option<St
                 }
             };
             
-            if content.trim().is_empty() 
-                || content.starts_with("Warmup") 
-                || content.contains("<system-reminder>") 
-            {
-                None 
-            } else {
-                Some(content)
-            }
+            is_detection_candidate(&content).then_some(content)
         })
 }
 
@@ -1677,6 +1722,23 @@ fn is_warmup_request(request: &ClaudeRequest) -> bool {
     false
 }
 
+/// 按客户端声明的原始 Schema 校验响应中的工具调用参数
+fn tool_arg_violations(
+    response: &ClaudeResponse,
+    schemas: &std::collections::HashMap<String, Value>,
+) -> Vec<String> {
+    if schemas.is_empty() {
+        return Vec::new();
+    }
+    schema_validate::validate_tool_calls(
+        response.content.iter().filter_map(|block| match block {
+            ContentBlock::ToolUse { name, input, .. } => Some((name.as_str(), input)),
+            _ => None,
+        }),
+        schemas,
+    )
+}
+
 /// 创建 Warmup 请求的模拟响应
 /// 
 /// 返回一个简单的响应，不消耗上游配额
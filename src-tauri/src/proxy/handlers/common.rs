@@ -1,4 +1,4 @@
-use tokio::time::{sleep, Duration};
+use tokio::time::{sleep, Duration, Instant};
 use tracing::{debug, info};
 use axum::{http::StatusCode, response::{IntoResponse, Response}, Json, extract::State};
 use serde_json::{json, Value};
@@ -75,62 +75,79 @@ pub fn determine_retry_strategy(
     }
 }
 
+/// 计算退避策略在第 attempt 次尝试后的基础延迟，None 表示不重试
+pub fn retry_delay(strategy: &RetryStrategy, attempt: usize) -> Option<Duration> {
+    match strategy {
+        RetryStrategy::NoRetry => None,
+        RetryStrategy::FixedDelay(duration) => Some(*duration),
+        RetryStrategy::LinearBackoff { base_ms } => {
+            Some(Duration::from_millis(base_ms.saturating_mul(attempt as u64 + 1)))
+        }
+        RetryStrategy::ExponentialBackoff { base_ms, max_ms } => {
+            let factor = 2_u64.saturating_pow(attempt.min(32) as u32);
+            Some(Duration::from_millis(base_ms.saturating_mul(factor).min(*max_ms)))
+        }
+    }
+}
+
+/// 在基础延迟上叠加 ±jitter_ratio 的随机抖动，避免多个请求同时重试
+pub fn jittered(delay: Duration, jitter_ratio: f64) -> Duration {
+    let ratio = jitter_ratio.clamp(0.0, 1.0);
+    if ratio == 0.0 || delay.is_zero() {
+        return delay;
+    }
+    let factor = 1.0 + rand::Rng::gen_range(&mut rand::thread_rng(), -ratio..=ratio);
+    delay.mul_f64(factor)
+}
+
 /// 执行退避策略并返回是否应该继续重试
+///
+/// 延迟会叠加随机抖动；若等待结束时已超过请求总时限 (deadline)，则直接放弃重试。
 pub async fn apply_retry_strategy(
     strategy: RetryStrategy,
     attempt: usize,
     max_attempts: usize,
     status_code: u16,
     trace_id: &str,
+    jitter_ratio: f64,
+    deadline: Option<Instant>,
 ) -> bool {
-    match strategy {
-        RetryStrategy::NoRetry => {
-            debug!("[{}] Non-retryable error {}, stopping", trace_id, status_code);
-            false
-        }
-
-        RetryStrategy::FixedDelay(duration) => {
-            let base_ms = duration.as_millis() as u64;
-            info!(
-                "[{}] ⏱️ Retry with fixed delay: status={}, attempt={}/{}, delay={}ms",
-                trace_id,
-                status_code,
-                attempt + 1,
-                max_attempts,
-                base_ms
-            );
-            sleep(duration).await;
-            true
-        }
+    let Some(base) = retry_delay(&strategy, attempt) else {
+        debug!("[{}] Non-retryable error {}, stopping", trace_id, status_code);
+        return false;
+    };
+    let delay = jittered(base, jitter_ratio);
 
-        RetryStrategy::LinearBackoff { base_ms } => {
-            let calculated_ms = base_ms * (attempt as u64 + 1);
+    if let Some(deadline) = deadline {
+        if Instant::now() + delay >= deadline {
             info!(
-                "[{}] ⏱️ Retry with linear backoff: status={}, attempt={}/{}, delay={}ms",
+                "[{}] Request deadline reached, not retrying: status={}, attempt={}/{}, delay={}ms",
                 trace_id,
                 status_code,
                 attempt + 1,
                 max_attempts,
-                calculated_ms
+                delay.as_millis()
             );
-            sleep(Duration::from_millis(calculated_ms)).await;
-            true
-        }
-
-        RetryStrategy::ExponentialBackoff { base_ms, max_ms } => {
-            let calculated_ms = (base_ms * 2_u64.pow(attempt as u32)).min(max_ms);
-            info!(
-                "[{}] ⏱️ Retry with exponential backoff: status={}, attempt={}/{}, delay={}ms",
-                trace_id,
-                status_code,
-                attempt + 1,
-                max_attempts,
-                calculated_ms
-            );
-            sleep(Duration::from_millis(calculated_ms)).await;
-            true
+            return false;
         }
     }
+
+    let kind = match strategy {
+        RetryStrategy::FixedDelay(_) => "fixed delay",
+        RetryStrategy::LinearBackoff { .. } => "linear backoff",
+        _ => "exponential backoff",
+    };
+    info!(
+        "[{}] ⏱️ Retry with {}: status={}, attempt={}/{}, delay={}ms",
+        trace_id,
+        kind,
+        status_code,
+        attempt + 1,
+        max_attempts,
+        delay.as_millis()
+    );
+    sleep(delay).await;
+    true
 }
 
 /// 判断是否应该轮换账号
@@ -145,6 +162,75 @@ pub fn should_rotate_account(status_code: u16) -> bool {
     }
 }
 
+// ===== Token 计数 =====
+
+/// 将 v1internal 风格的请求体转换为 countTokens 可接受的 contents
+///
+/// countTokens 只接受 contents，因此把 systemInstruction 作为首条 user 消息、
+/// 工具声明序列化为文本追加在末尾，使其同样计入总数
+pub fn count_tokens_contents(request: &Value) -> Value {
+    let mut contents = Vec::new();
+    if let Some(parts) = request.pointer("/systemInstruction/parts") {
+        contents.push(json!({ "role": "user", "parts": parts }));
+    }
+    if let Some(items) = request.get("contents").and_then(|c| c.as_array()) {
+        contents.extend(items.iter().cloned());
+    }
+    if let Some(tools) = request.get("tools").filter(|t| !t.is_null()) {
+        contents.push(json!({ "role": "user", "parts": [{ "text": tools.to_string() }] }));
+    }
+    Value::Array(contents)
+}
+
+/// 通过号池调用上游 countTokens，返回 totalTokens
+///
+/// 号池为空、取号失败或上游不支持该模型时返回 Err，由调用方回退到本地估算
+pub async fn count_tokens_upstream(
+    state: &AppState,
+    model: &str,
+    contents: Value,
+) -> Result<u32, String> {
+    if state.token_manager.len() == 0 {
+        return Err("no accounts available".to_string());
+    }
+    let (access_token, _project_id, email, account_id, _wait_ms) = state
+        .token_manager
+        .get_token("text", false, None, model)
+        .await?;
+
+    let body = json!({
+        "request": {
+            "model": format!("models/{}", model),
+            "contents": contents,
+        }
+    });
+    let response = state
+        .upstream
+        .call_v1_internal("countTokens", &access_token, body, None, Some(account_id.as_str()))
+        .await?
+        .response;
+
+    let status = response.status();
+    if !status.is_success() {
+        let text = response.text().await.unwrap_or_default();
+        return Err(format!(
+            "countTokens returned {} (account: {}): {}",
+            status.as_u16(),
+            crate::proxy::upstream::client::mask_email(&email),
+            text.chars().take(200).collect::<String>()
+        ));
+    }
+    let result: Value = response
+        .json()
+        .await
+        .map_err(|e| format!("解析 countTokens 响应失败: {}", e))?;
+    result
+        .get("totalTokens")
+        .and_then(|v| v.as_u64())
+        .map(|n| n.min(u32::MAX as u64) as u32)
+        .ok_or_else(|| "countTokens response missing totalTokens".to_string())
+}
+
 /// Detects model capabilities and configuration
 /// POST /v1/models/detect
 pub async fn handle_detect_model(
@@ -0,0 +1,354 @@
+// Dispatch 模块 - 统一的请求调度引擎
+//
+// 各协议 handler 共用的尝试循环控制：最大尝试次数、请求总时限、按状态码覆盖的重试行为、
+// 带抖动的退避 (基于 determine_retry_strategy / apply_retry_strategy) 以及非流式请求的对冲。
+
+use std::future::Future;
+use tokio::time::{Duration, Instant};
+use tracing::{debug, info};
+
+use super::common::{
+    apply_retry_strategy, determine_retry_strategy, should_rotate_account, RetryStrategy,
+};
+use crate::proxy::config::{DispatchConfig, StatusPolicy};
+
+/// 获取 token 失败/网络错误后重试前的基础延迟
+const TRANSIENT_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// 单个请求的调度状态
+pub struct Dispatcher {
+    config: DispatchConfig,
+    max_attempts: usize,
+    deadline: Option<Instant>,
+    /// 下一次尝试是否需要轮换账号
+    rotate_next: bool,
+    trace_id: String,
+}
+
+impl Dispatcher {
+    /// 使用当前全局调度配置创建
+    ///
+    /// - `pool_limit`: 账号池允许的尝试次数上限 (通常为账号数或账号数 + 1)
+    /// - `min_attempts`: 内部重试 (如移除 thinking 签名后重发) 所需的最少尝试次数
+    pub fn new(pool_limit: usize, min_attempts: usize, trace_id: &str) -> Self {
+        Self::with_config(
+            crate::proxy::config::get_dispatch_config(),
+            pool_limit,
+            min_attempts,
+            trace_id,
+        )
+    }
+
+    fn with_config(
+        config: DispatchConfig,
+        pool_limit: usize,
+        min_attempts: usize,
+        trace_id: &str,
+    ) -> Self {
+        let max_attempts = config
+            .max_attempts
+            .max(1)
+            .min(pool_limit)
+            .max(min_attempts)
+            .max(1);
+        let deadline = (config.request_deadline_secs > 0)
+            .then(|| Instant::now() + Duration::from_secs(config.request_deadline_secs));
+        Self {
+            config,
+            max_attempts,
+            deadline,
+            rotate_next: true,
+            trace_id: trace_id.to_string(),
+        }
+    }
+
+    pub fn max_attempts(&self) -> usize {
+        self.max_attempts
+    }
+
+    /// 是否已超过请求总时限 (首次尝试始终放行)
+    pub fn deadline_exceeded(&self, attempt: usize) -> bool {
+        let exceeded = attempt > 0 && self.deadline.is_some_and(|d| Instant::now() >= d);
+        if exceeded {
+            info!(
+                "[{}] Request deadline of {}s exceeded after {} attempts",
+                self.trace_id, self.config.request_deadline_secs, attempt
+            );
+        }
+        exceeded
+    }
+
+    /// 在剩余总时限内执行一次尝试，超时按失败处理 (未配置总时限时不限制)
+    ///
+    /// `timeout_error` 将超时说明转换为调用方的错误类型。
+    pub async fn within_deadline<T, E, Fut>(
+        &self,
+        attempt: Fut,
+        timeout_error: impl FnOnce(String) -> E,
+    ) -> Result<T, E>
+    where
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let Some(deadline) = self.deadline else {
+            return attempt.await;
+        };
+        match tokio::time::timeout_at(deadline, attempt).await {
+            Ok(result) => result,
+            Err(_) => {
+                info!(
+                    "[{}] Upstream attempt cancelled at the {}s request deadline",
+                    self.trace_id, self.config.request_deadline_secs
+                );
+                Err(timeout_error(format!(
+                    "Request deadline of {}s exceeded",
+                    self.config.request_deadline_secs
+                )))
+            }
+        }
+    }
+
+    /// 本次尝试获取 token 时是否强制轮换账号
+    ///
+    /// 首次尝试不轮换；之后除非上一次失败的状态码被判定为无需轮换，否则都换号。
+    pub fn force_rotate(&mut self, attempt: usize) -> bool {
+        let rotate = attempt > 0 && self.rotate_next;
+        self.rotate_next = true;
+        rotate
+    }
+
+    /// 结合状态码覆盖配置确定重试策略
+    pub fn strategy(
+        &self,
+        status_code: u16,
+        error_text: &str,
+        retried_without_thinking: bool,
+    ) -> RetryStrategy {
+        match self.config.status_overrides.get(&status_code) {
+            Some(policy) if !policy.retry => RetryStrategy::NoRetry,
+            Some(StatusPolicy {
+                delay_ms: Some(ms), ..
+            }) => RetryStrategy::FixedDelay(Duration::from_millis(*ms)),
+            _ => determine_retry_strategy(status_code, error_text, retried_without_thinking),
+        }
+    }
+
+    /// 结合状态码覆盖配置判断是否需要轮换账号
+    pub fn should_rotate(&self, status_code: u16) -> bool {
+        self.config
+            .status_overrides
+            .get(&status_code)
+            .and_then(|policy| policy.rotate)
+            .unwrap_or_else(|| should_rotate_account(status_code))
+    }
+
+    /// 执行指定策略的退避，返回是否继续下一次尝试
+    pub async fn backoff(
+        &mut self,
+        strategy: RetryStrategy,
+        attempt: usize,
+        status_code: u16,
+    ) -> bool {
+        self.rotate_next = self.should_rotate(status_code);
+        if !self.rotate_next {
+            debug!(
+                "[{}] Keeping same account for status {} (server-side issue)",
+                self.trace_id, status_code
+            );
+        }
+        apply_retry_strategy(
+            strategy,
+            attempt,
+            self.max_attempts,
+            status_code,
+            &self.trace_id,
+            self.config.jitter_ratio,
+            self.deadline,
+        )
+        .await
+    }
+
+    /// 按状态码与错误信息决定策略并退避，返回是否继续下一次尝试
+    pub async fn retry(
+        &mut self,
+        attempt: usize,
+        status_code: u16,
+        error_text: &str,
+        retried_without_thinking: bool,
+    ) -> bool {
+        let strategy = self.strategy(status_code, error_text, retried_without_thinking);
+        self.backoff(strategy, attempt, status_code).await
+    }
+
+    /// 没有上游状态码的失败 (获取 token 失败/网络错误) 的退避，返回是否继续下一次尝试
+    ///
+    /// 固定短延迟后轮换账号，同样受抖动与请求总时限约束；最后一次尝试后不再等待。
+    pub async fn retry_transient(&mut self, attempt: usize) -> bool {
+        if attempt + 1 >= self.max_attempts {
+            return false;
+        }
+        self.rotate_next = true;
+        apply_retry_strategy(
+            RetryStrategy::FixedDelay(TRANSIENT_RETRY_DELAY),
+            attempt,
+            self.max_attempts,
+            0,
+            &self.trace_id,
+            self.config.jitter_ratio,
+            self.deadline,
+        )
+        .await
+    }
+
+    /// 对冲请求：主请求在 hedge_delay_ms 内未完成时，强制轮换账号并发起第二个请求，
+    /// 采用先成功的结果。仅用于可安全重复的非流式请求 (音频转录/图像生成)。
+    ///
+    /// `make(force_rotate)` 负责获取 token 并发起一次完整请求。
+    pub async fn hedged<T, E, F, Fut>(&self, force_rotate: bool, make: F) -> Result<T, E>
+    where
+        F: Fn(bool) -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let primary = make(force_rotate);
+        if self.config.hedge_delay_ms == 0 {
+            return primary.await;
+        }
+        tokio::pin!(primary);
+        tokio::select! {
+            res = &mut primary => return res,
+            _ = tokio::time::sleep(Duration::from_millis(self.config.hedge_delay_ms)) => {}
+        }
+
+        info!(
+            "[{}] Primary request still pending after {}ms, sending hedged request",
+            self.trace_id, self.config.hedge_delay_ms
+        );
+        let secondary = make(true);
+        tokio::pin!(secondary);
+        tokio::select! {
+            res = &mut primary => match res {
+                Ok(v) => Ok(v),
+                Err(_) => secondary.await,
+            },
+            res = &mut secondary => match res {
+                Ok(v) => {
+                    info!("[{}] Hedged request won", self.trace_id);
+                    Ok(v)
+                }
+                Err(_) => primary.await,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::handlers::common::{jittered, retry_delay};
+
+    #[test]
+    fn test_retry_delay_and_jitter_bounds() {
+        let linear = RetryStrategy::LinearBackoff { base_ms: 1000 };
+        assert_eq!(retry_delay(&linear, 2), Some(Duration::from_millis(3000)));
+        let exp = RetryStrategy::ExponentialBackoff {
+            base_ms: 10_000,
+            max_ms: 60_000,
+        };
+        assert_eq!(retry_delay(&exp, 1), Some(Duration::from_millis(20_000)));
+        assert_eq!(retry_delay(&exp, 40), Some(Duration::from_millis(60_000)));
+        assert_eq!(retry_delay(&RetryStrategy::NoRetry, 0), None);
+
+        let base = Duration::from_millis(1000);
+        assert_eq!(jittered(base, 0.0), base);
+        for _ in 0..100 {
+            let d = jittered(base, 0.2);
+            assert!(d >= Duration::from_millis(800) && d <= Duration::from_millis(1200));
+        }
+    }
+
+    #[test]
+    fn test_attempts_and_status_overrides() {
+        let mut config = DispatchConfig {
+            max_attempts: 5,
+            ..Default::default()
+        };
+        assert_eq!(
+            Dispatcher::with_config(config.clone(), 2, 1, "t").max_attempts(),
+            2
+        );
+        assert_eq!(
+            Dispatcher::with_config(config.clone(), 1, 2, "t").max_attempts(),
+            2
+        );
+        assert_eq!(
+            Dispatcher::with_config(config.clone(), 0, 0, "t").max_attempts(),
+            1
+        );
+
+        config.status_overrides.insert(
+            503,
+            StatusPolicy {
+                retry: true,
+                rotate: Some(true),
+                delay_ms: Some(500),
+            },
+        );
+        config.status_overrides.insert(
+            429,
+            StatusPolicy {
+                retry: false,
+                rotate: None,
+                delay_ms: None,
+            },
+        );
+        let mut d = Dispatcher::with_config(config, 10, 1, "t");
+        assert!(matches!(
+            d.strategy(503, "", false),
+            RetryStrategy::FixedDelay(delay) if delay == Duration::from_millis(500)
+        ));
+        assert!(matches!(d.strategy(429, "", false), RetryStrategy::NoRetry));
+        assert!(d.should_rotate(503));
+        assert!(!d.should_rotate(529));
+
+        assert!(!d.force_rotate(0));
+        d.rotate_next = false;
+        assert!(!d.force_rotate(1));
+        assert!(d.force_rotate(2));
+    }
+
+    #[tokio::test]
+    async fn test_attempt_is_bounded_by_deadline() {
+        let mut d = Dispatcher::with_config(DispatchConfig::default(), 1, 1, "t");
+        let ok: Result<u8, String> = d.within_deadline(async { Ok(1) }, |e| e).await;
+        assert_eq!(ok, Ok(1));
+
+        d.deadline = Some(Instant::now() + Duration::from_millis(20));
+        let started = Instant::now();
+        let slow = d
+            .within_deadline(
+                async {
+                    tokio::time::sleep(Duration::from_secs(30)).await;
+                    Ok::<u8, String>(1)
+                },
+                |e| e,
+            )
+            .await;
+        assert!(slow.unwrap_err().contains("deadline"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_transient_retry_respects_attempts_and_deadline() {
+        let config = DispatchConfig {
+            max_attempts: 3,
+            ..Default::default()
+        };
+        let mut d = Dispatcher::with_config(config, 3, 1, "t");
+        d.rotate_next = false;
+        assert!(d.retry_transient(0).await);
+        assert!(d.force_rotate(1));
+        assert!(!d.retry_transient(2).await);
+
+        d.deadline = Some(Instant::now() + Duration::from_millis(100));
+        assert!(!d.retry_transient(0).await);
+    }
+}
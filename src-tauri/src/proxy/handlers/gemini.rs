@@ -1,53 +1,68 @@
 // Gemini Handler
 use axum::{
     extract::State,
-    extract::{Json, Path},
+    extract::{Extension, Json, Path, RawQuery},
     http::StatusCode,
     response::IntoResponse,
 };
 use serde_json::{json, Value};
 use tracing::{debug, error, info};
 
-use crate::proxy::common::client_adapter::CLIENT_ADAPTERS;
+use crate::proxy::common::client_adapter::find_client_adapter;
+use crate::proxy::common::schema_validate::{self, ToolArgVerdict};
+use crate::proxy::context_window::SummaryUpstream;
 use crate::proxy::debug_logger;
-use crate::proxy::handlers::common::{
-    apply_retry_strategy, determine_retry_strategy, should_rotate_account,
-};
+use crate::proxy::gemini_resources::{expand_request, owner_of};
+use crate::proxy::handlers::dispatch::Dispatcher;
+use crate::proxy::mappers::context_manager::ContextManager;
 use crate::proxy::mappers::gemini::{unwrap_response, wrap_request};
+use crate::proxy::middleware::auth::UserTokenIdentity;
+use crate::proxy::middleware::fallback::GeminiModelOverride;
 use crate::proxy::server::AppState;
 use crate::proxy::session_manager::SessionManager;
 use crate::proxy::upstream::client::mask_email;
 use axum::http::HeaderMap;
 
-const MAX_RETRY_ATTEMPTS: usize = 3;
+/// 解析路径参数 `model:method`；路由 / 降级中间件替换过模型时以替换结果为准
+pub(crate) fn resolve_model_action(
+    model_action: String,
+    model_override: Option<&GeminiModelOverride>,
+) -> (String, String) {
+    let (model_name, method) = match model_action.rsplit_once(':') {
+        Some((m, action)) => (m.to_string(), action.to_string()),
+        None => (model_action, "generateContent".to_string()),
+    };
+    match model_override {
+        Some(GeminiModelOverride(model)) => (model.clone(), method),
+        None => (model_name, method),
+    }
+}
 
 /// 处理 generateContent 和 streamGenerateContent
 /// 路径参数: model_name, method (e.g. "gemini-pro", "generateContent")
 pub async fn handle_generate(
     State(state): State<AppState>,
     Path(model_action): Path<String>,
+    RawQuery(query): RawQuery,
+    model_override: Option<Extension<GeminiModelOverride>>,
+    identity: Option<Extension<UserTokenIdentity>>,
     headers: HeaderMap,          // [NEW] Extract headers for adapter detection
     Json(mut body): Json<Value>, // 改为 mut 以支持修复提示词注入
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     // 解析 model:method
-    let (model_name, method) = if let Some((m, action)) = model_action.rsplit_once(':') {
-        (m.to_string(), action.to_string())
-    } else {
-        (model_action, "generateContent".to_string())
-    };
+    let (model_name, method) =
+        resolve_model_action(model_action, model_override.as_ref().map(|e| &e.0));
 
     crate::modules::logger::log_info(&format!(
         "Received Gemini request: {}/{}",
         model_name, method
     ));
-    let trace_id = format!("req_{}", chrono::Utc::now().timestamp_subsec_millis());
+    let trace_id = crate::proxy::script_hooks::current_trace_id()
+        .unwrap_or_else(|| format!("req_{}", chrono::Utc::now().timestamp_subsec_millis()));
     let debug_cfg = state.debug_logging.read().await.clone();
 
     // [NEW] Detect Client Adapter
-    let client_adapter = CLIENT_ADAPTERS
-        .iter()
-        .find(|a| a.matches(&headers))
-        .cloned();
+    let client_adapter = find_client_adapter(&headers);
     if client_adapter.is_some() {
         debug!("[{}] Client Adapter detected", trace_id);
     }
@@ -59,6 +74,14 @@ pub async fn handle_generate(
             format!("Unsupported method: {}", method),
         ));
     }
+    // 展开 cachedContent 与本地 Files 引用 (上游不支持这两类资源)
+    let owner = owner_of(identity.as_ref().map(|e| &e.0));
+    expand_request(&mut body, owner.as_deref()).await?;
+    // 未指定 alt=sse 的流式请求按 Gemini 约定返回 JSON 数组
+    let wants_sse = query
+        .as_deref()
+        .map(|q| q.split('&').any(|p| p == "alt=sse"))
+        .unwrap_or(false);
     if debug_logger::is_enabled(&debug_cfg) {
         let original_payload = json!({
             "kind": "original_request",
@@ -76,6 +99,28 @@ pub async fn handle_generate(
         )
         .await;
     }
+
+    // [NEW] 通用第三方提供商 (Gemini 协议透传)
+    if let Some(route) = crate::proxy::providers::generic::resolve_route(
+        &state,
+        crate::proxy::ProviderProtocol::Gemini,
+        &model_name,
+        &trace_id,
+    )
+    .await
+    {
+        // 仅透传 alt 参数，避免把本地 ?key= 泄露给上游
+        let path = format!(
+            "/v1beta/models/{}:{}{}",
+            route.upstream_model,
+            method,
+            if wants_sse { "?alt=sse" } else { "" }
+        );
+        return Ok(
+            crate::proxy::providers::generic::forward(&state, &route, &path, &headers, body).await,
+        );
+    }
+
     let client_wants_stream = method == "streamGenerateContent";
     // [AUTO-CONVERSION] 强制内部流式化
     let force_stream_internally = !client_wants_stream;
@@ -89,12 +134,22 @@ pub async fn handle_generate(
     let upstream = state.upstream.clone();
     let token_manager = state.token_manager;
     let pool_size = token_manager.len();
-    let max_attempts = MAX_RETRY_ATTEMPTS.min(pool_size).max(1);
+    let mut dispatcher = Dispatcher::new(pool_size, 1, &trace_id);
+    let max_attempts = dispatcher.max_attempts();
 
     let mut last_error = String::new();
     let mut last_email: Option<String> = None;
 
+    // 按客户端原始 Schema 校验返回的工具调用参数 (仅非流式响应)
+    let tool_arg_validation = state.experimental.read().await.tool_arg_validation.clone();
+    let tool_schemas = declared_tool_schemas(&body);
+    let mut tool_arg_retries = 0u32;
+
     for attempt in 0..max_attempts {
+        if dispatcher.deadline_exceeded(attempt) {
+            break;
+        }
+
         // 3. 模型路由解析
         let mapped_model = crate::proxy::common::model_mapping::resolve_model_route(
             &model_name,
@@ -131,11 +186,11 @@ pub async fn handle_generate(
         // 提取 SessionId (粘性指纹)
         let session_id = SessionManager::extract_gemini_session_id(&body, &model_name);
 
-        // 关键：在重试尝试 (attempt > 0) 时强制轮换账号
+        // 关键：在重试尝试时强制轮换账号 (上一次失败的状态码无需轮换时除外)
         let (access_token, project_id, email, account_id, _wait_ms) = match token_manager
             .get_token(
                 &config.request_type,
-                attempt > 0,
+                dispatcher.force_rotate(attempt),
                 Some(&session_id),
                 &config.final_model,
             )
@@ -161,7 +216,47 @@ pub async fn handle_generate(
         // [FIX #765] Pass session_id to wrap_request for signature injection
         // [NEW] 获取完整 Token 对象以注入动态规格 (dynamic > static default > 65535)
         let token_obj = token_manager.get_token_by_id(&account_id);
-        let wrapped_body = wrap_request(&body, &project_id, &mapped_model, Some(account_id.as_str()), Some(&session_id), token_obj.as_ref());
+        let mut wrapped_body = wrap_request(&body, &project_id, &mapped_model, Some(account_id.as_str()), Some(&session_id), token_obj.as_ref());
+
+        // 上下文窗口策略 (按用户令牌 / 客户端选择)
+        crate::proxy::context_window::apply_current(
+            &mut wrapped_body,
+            &mapped_model,
+            SummaryUpstream {
+                upstream: &upstream,
+                access_token: &access_token,
+                project_id: &project_id,
+                account_id: &account_id,
+            },
+            &trace_id,
+        )
+        .await;
+
+        // 思考策略 (按模型家族 / 调用方选择)
+        crate::proxy::thinking_policy::apply_current(&mut wrapped_body, &mapped_model, &trace_id);
+
+        // 脚本钩子 (upstream_request 阶段)
+        if let Err(e) = crate::proxy::script_hooks::apply_upstream(
+            crate::proxy::ProviderProtocol::Gemini,
+            &mut wrapped_body,
+            &mapped_model,
+            &trace_id,
+        )
+        .await
+        {
+            return Ok(crate::proxy::script_hooks::reject_response(
+                crate::proxy::ProviderProtocol::Gemini,
+                &e,
+            ));
+        }
+
+        // 按实际发往上游的请求估算，用于校准器学习
+        let prompt_estimate = wrapped_body.get("request").map(|request| {
+            (
+                mapped_model.clone(),
+                ContextManager::estimate_gemini_input_tokens(request, &mapped_model),
+            )
+        });
 
         if debug_logger::is_enabled(&debug_cfg) {
             let payload = json!({
@@ -201,14 +296,17 @@ pub async fn handle_generate(
             );
         }
 
-        let call_result = match upstream
-            .call_v1_internal_with_headers(
-                upstream_method,
-                &access_token,
-                wrapped_body,
-                query_string,
-                extra_headers.clone(),
-                Some(account_id.as_str()),
+        let call_result = match dispatcher
+            .within_deadline(
+                upstream.call_v1_internal_with_headers(
+                    upstream_method,
+                    &access_token,
+                    wrapped_body,
+                    query_string,
+                    extra_headers.clone(),
+                    Some(account_id.as_str()),
+                ),
+                |e| e,
             )
             .await
         {
@@ -330,6 +428,7 @@ pub async fn handle_generate(
 
                 let s_id_for_stream = s_id.clone();
                 let model_name_for_stream = mapped_model.clone();
+                let mut prompt_estimate_for_stream = prompt_estimate.clone();
                 let stream = async_stream::stream! {
                     let mut first_data = first_chunk;
                     loop {
@@ -402,6 +501,11 @@ pub async fn handle_generate(
                                                         }
                                                     }
                                                 }
+                                                if let Some(actual) = resp.pointer("/usageMetadata/promptTokenCount").and_then(|v| v.as_u64()) {
+                                                    if let Some((estimated_model, estimate)) = prompt_estimate_for_stream.take() {
+                                                        estimate.record(&estimated_model, actual as u32);
+                                                    }
+                                                }
                                             }
 
                                             // [FIX #1522] Inject Tool ID into Stream Response
@@ -433,7 +537,15 @@ pub async fn handle_generate(
                     }
                 };
 
-                if client_wants_stream {
+                if client_wants_stream && !wants_sse {
+                    return Ok(Response::builder()
+                        .header("Content-Type", "application/json")
+                        .header("X-Account-Email", &email)
+                        .header("X-Mapped-Model", &mapped_model)
+                        .body(Body::from_stream(sse_to_json_array(stream)))
+                        .unwrap()
+                        .into_response());
+                } else if client_wants_stream {
                     let body = Body::from_stream(stream);
                     return Ok(Response::builder()
                         .header("Content-Type", "text/event-stream")
@@ -455,6 +567,26 @@ pub async fn handle_generate(
                                 session_id
                             );
                             let unwrapped = unwrap_response(&gemini_resp);
+                            let violations = tool_arg_violations(&unwrapped, &tool_schemas);
+                            match schema_validate::decide(
+                                &tool_arg_validation,
+                                &violations,
+                                tool_arg_retries,
+                                &trace_id,
+                            ) {
+                                ToolArgVerdict::Retry => {
+                                    tool_arg_retries += 1;
+                                    last_error = format!(
+                                        "Invalid tool call arguments: {}",
+                                        violations.join("; ")
+                                    );
+                                    continue;
+                                }
+                                ToolArgVerdict::Reject(message) => {
+                                    return Ok((StatusCode::BAD_GATEWAY, message).into_response());
+                                }
+                                ToolArgVerdict::Accept => {}
+                            }
                             return Ok((
                                 StatusCode::OK,
                                 [
@@ -521,6 +653,31 @@ pub async fn handle_generate(
             }
 
             let unwrapped = unwrap_response(&gemini_resp);
+            if let (Some(actual), Some((estimated_model, estimate))) = (
+                unwrapped
+                    .pointer("/usageMetadata/promptTokenCount")
+                    .and_then(|v| v.as_u64()),
+                &prompt_estimate,
+            ) {
+                estimate.record(estimated_model, actual as u32);
+            }
+            let violations = tool_arg_violations(&unwrapped, &tool_schemas);
+            match schema_validate::decide(
+                &tool_arg_validation,
+                &violations,
+                tool_arg_retries,
+                &trace_id,
+            ) {
+                ToolArgVerdict::Retry => {
+                    tool_arg_retries += 1;
+                    last_error = format!("Invalid tool call arguments: {}", violations.join("; "));
+                    continue;
+                }
+                ToolArgVerdict::Reject(message) => {
+                    return Ok((StatusCode::BAD_GATEWAY, message).into_response());
+                }
+                ToolArgVerdict::Accept => {}
+            }
             return Ok((
                 StatusCode::OK,
                 [
@@ -562,12 +719,8 @@ pub async fn handle_generate(
             .await;
         }
 
-        // 确定重试策略
-        let strategy = determine_retry_strategy(status_code, &error_text, false);
-        let trace_id = format!("gemini_{}", session_id);
-
-        // 执行退避
-        if apply_retry_strategy(strategy, attempt, max_attempts, status_code, &trace_id).await {
+        // 确定重试策略并执行退避
+        if dispatcher.retry(attempt, status_code, &error_text, false).await {
             // [NEW] Apply Client Adapter "let_it_crash" strategy
             if let Some(adapter) = &client_adapter {
                 if adapter.let_it_crash() && attempt > 0 {
@@ -578,14 +731,6 @@ pub async fn handle_generate(
                     break;
                 }
             }
-
-            // 判断是否需要轮换账号
-            if !should_rotate_account(status_code) {
-                debug!(
-                    "[{}] Keeping same account for status {} (Gemini server-side issue)",
-                    trace_id, status_code
-                );
-            }
             continue;
         }
 
@@ -660,56 +805,143 @@ pub async fn handle_generate(
 pub async fn handle_list_models(
     State(state): State<AppState>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    use crate::proxy::common::model_mapping::get_all_dynamic_models;
-
-    // 获取所有动态模型列表（与 /v1/models 一致）
-    let model_ids = get_all_dynamic_models(&state.custom_mapping, Some(&state.token_manager)).await;
+    use crate::proxy::common::model_info::list_model_info;
 
-    // 转换为 Gemini API 格式
-    let models: Vec<_> = model_ids
-        .into_iter()
-        .map(|id| {
-            json!({
-                "name": format!("models/{}", id),
-                "version": "001",
-                "displayName": id.clone(),
-                "description": "",
-                "inputTokenLimit": 128000,
-                "outputTokenLimit": 8192,
-                "supportedGenerationMethods": ["generateContent", "countTokens"],
-                "temperature": 1.0,
-                "topP": 0.95,
-                "topK": 64
-            })
-        })
-        .collect();
+    // 获取所有动态模型列表（与 /v1/models 一致），附带真实限额与能力
+    let models = list_model_info(&state.custom_mapping, Some(&state.token_manager)).await;
+    let models: Vec<_> = models.iter().map(|m| m.to_gemini_json()).collect();
 
     Ok(Json(json!({ "models": models })))
 }
 
-pub async fn handle_get_model(Path(model_name): Path<String>) -> impl IntoResponse {
-    Json(json!({
-        "name": format!("models/{}", model_name),
-        "displayName": model_name
-    }))
+pub async fn handle_get_model(
+    State(state): State<AppState>,
+    Path(model_name): Path<String>,
+) -> impl IntoResponse {
+    use crate::proxy::common::model_info::resolve_model_info;
+
+    let model_name = model_name.trim_start_matches("models/");
+    let info = resolve_model_info(
+        model_name,
+        &*state.custom_mapping.read().await,
+        Some(&state.token_manager),
+    );
+    Json(info.to_gemini_json())
 }
 
+/// 计算 tokens: 优先调用上游 countTokens，不可用时回退到本地估算
 pub async fn handle_count_tokens(
     State(state): State<AppState>,
-    Path(_model_name): Path<String>,
-    Json(_body): Json<Value>,
+    Path(model_name): Path<String>,
+    identity: Option<Extension<UserTokenIdentity>>,
+    Json(mut body): Json<Value>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let model_group = "gemini";
-    let (_access_token, _project_id, _, _, _wait_ms) = state
-        .token_manager
-        .get_token(model_group, false, None, "gemini")
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::SERVICE_UNAVAILABLE,
-                format!("Token error: {}", e),
-            )
-        })?;
+    use crate::proxy::handlers::common::{count_tokens_contents, count_tokens_upstream};
+
+    let mapped_model = crate::proxy::common::model_mapping::resolve_model_route(
+        &model_name,
+        &*state.custom_mapping.read().await,
+    );
+
+    // 兼容 {contents} 与 {generateContentRequest: {...}} 两种请求体
+    let owner = owner_of(identity.as_ref().map(|e| &e.0));
+    match body.get_mut("generateContentRequest") {
+        Some(request) => expand_request(request, owner.as_deref()).await?,
+        None => expand_request(&mut body, owner.as_deref()).await?,
+    }
+    let request = body.get("generateContentRequest").unwrap_or(&body);
+    let total_tokens =
+        match count_tokens_upstream(&state, &mapped_model, count_tokens_contents(request)).await {
+            Ok(count) => count,
+            Err(e) => {
+                let estimate = ContextManager::estimate_gemini_input_tokens(request, &mapped_model);
+                let calibrated = estimate.calibrated(&mapped_model);
+                debug!(
+                    "[CountTokens] Upstream count unavailable for {}, using estimate {} (text: {}, media: {}): {}",
+                    mapped_model, calibrated, estimate.text.total(), estimate.media, e
+                );
+                calibrated
+            }
+        };
 
-    Ok(Json(json!({"totalTokens": 0})))
+    Ok(Json(json!({ "totalTokens": total_tokens })))
+}
+
+/// 提取请求中声明的函数原始 Schema (`parameters` 或 `parametersJsonSchema`)
+fn declared_tool_schemas(body: &Value) -> std::collections::HashMap<String, Value> {
+    body.get("tools")
+        .and_then(|t| t.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|tool| tool.get("functionDeclarations").and_then(|d| d.as_array()))
+        .flatten()
+        .filter_map(|decl| {
+            let name = decl.get("name")?.as_str()?;
+            let schema = decl
+                .get("parametersJsonSchema")
+                .or_else(|| decl.get("parameters"))?;
+            Some((name.to_string(), schema.clone()))
+        })
+        .collect()
+}
+
+/// 按客户端声明的原始 Schema 校验响应中的 functionCall 参数
+fn tool_arg_violations(
+    response: &Value,
+    schemas: &std::collections::HashMap<String, Value>,
+) -> Vec<String> {
+    if schemas.is_empty() {
+        return Vec::new();
+    }
+    let calls = response
+        .get("candidates")
+        .and_then(|c| c.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|cand| cand.pointer("/content/parts").and_then(|p| p.as_array()))
+        .flatten()
+        .filter_map(|part| {
+            let call = part.get("functionCall")?;
+            Some((call.get("name")?.as_str()?, call.get("args")?))
+        });
+    schema_validate::validate_tool_calls(calls, schemas)
+}
+
+/// 将 SSE 事件流转换为 Gemini 不带 `alt=sse` 时的流式 JSON 数组 (`[{...},\r\n{...}]`)
+fn sse_to_json_array<S>(stream: S) -> impl futures::Stream<Item = Result<bytes::Bytes, String>>
+where
+    S: futures::Stream<Item = Result<bytes::Bytes, String>> + Send + 'static,
+{
+    use bytes::{Bytes, BytesMut};
+    use futures::StreamExt;
+
+    async_stream::stream! {
+        let mut stream = Box::pin(stream);
+        let mut buffer = BytesMut::new();
+        let mut first = true;
+        yield Ok(Bytes::from_static(b"["));
+        while let Some(item) = stream.next().await {
+            match item {
+                Ok(bytes) => buffer.extend_from_slice(&bytes),
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            }
+            while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
+                let line = buffer.split_to(pos + 1);
+                let line = String::from_utf8_lossy(&line);
+                let Some(data) = line.trim().strip_prefix("data:").map(|d| d.trim()) else {
+                    continue;
+                };
+                if data.is_empty() || data == "[DONE]" {
+                    continue;
+                }
+                let separator = if first { "" } else { ",\r\n" };
+                first = false;
+                yield Ok(Bytes::from(format!("{}{}", separator, data)));
+            }
+        }
+        yield Ok(Bytes::from_static(b"]"));
+    }
 }
@@ -0,0 +1,1036 @@
+// Gemini 原生资源端点 - Files API、cachedContents 与 Batch API 的本地模拟
+//
+// 文件与缓存内容的存储见 `proxy::gemini_resources`；批量任务在后台把每条请求作为
+// generateContent 重新送入代理路由 (与普通请求共用鉴权、监控、用量统计、路由与各中间件)，
+// 结果保存在内存中，文件输入的批量任务结果写回 Files。
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{ConnectInfo, Extension, Json, Path, Query, RawQuery, State},
+    http::{header, HeaderMap, HeaderValue, Request, StatusCode},
+    response::{IntoResponse, Response},
+    Router,
+};
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tower::ServiceExt;
+use tracing::{info, warn};
+
+use crate::proxy::gemini_resources::{self as resources, owner_of, timestamp};
+use crate::proxy::middleware::auth::UserTokenIdentity;
+use crate::proxy::middleware::fallback::GeminiModelOverride;
+use crate::proxy::server::AppState;
+
+const DEFAULT_PAGE_SIZE: usize = 10;
+const MAX_PAGE_SIZE: usize = 100;
+/// 未完成的分片上传会话保留时长
+const UPLOAD_SESSION_TTL: Duration = Duration::from_secs(3600);
+/// 单个批量任务内并发执行的请求数
+const BATCH_CONCURRENCY: usize = 4;
+/// 保留的已结束批量任务数
+const MAX_FINISHED_BATCHES: usize = 100;
+
+/// Gemini 风格的错误响应
+fn error_response(status: StatusCode, message: impl Into<String>) -> Response {
+    let canonical = match status {
+        StatusCode::BAD_REQUEST => "INVALID_ARGUMENT",
+        StatusCode::NOT_FOUND => "NOT_FOUND",
+        StatusCode::PAYLOAD_TOO_LARGE => "OUT_OF_RANGE",
+        _ => "INTERNAL",
+    };
+    (
+        status,
+        Json(json!({
+            "error": {
+                "code": status.as_u16(),
+                "message": message.into(),
+                "status": canonical
+            }
+        })),
+    )
+        .into_response()
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> &'a str {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+}
+
+fn query_param(query: Option<&str>, key: &str) -> Option<String> {
+    query?.split('&').find_map(|pair| {
+        let (k, v) = pair.split_once('=')?;
+        (k == key).then(|| v.to_string())
+    })
+}
+
+/// 客户端访问本代理使用的地址 (用于生成文件 uri 与上传 URL)
+fn request_base_url(headers: &HeaderMap) -> String {
+    let host = match header_str(headers, "x-forwarded-host") {
+        "" => header_str(headers, "host"),
+        forwarded => forwarded,
+    };
+    let scheme = match header_str(headers, "x-forwarded-proto") {
+        "" => "http",
+        proto => proto,
+    };
+    if host.is_empty() {
+        "http://127.0.0.1".to_string()
+    } else {
+        format!("{}://{}", scheme, host)
+    }
+}
+
+/// 调用方的用户令牌身份 (资源归属)
+type Identity = Option<Extension<UserTokenIdentity>>;
+
+fn owner(identity: &Identity) -> Option<String> {
+    owner_of(identity.as_ref().map(|e| &e.0))
+}
+
+/// 分页参数 (pageToken 为偏移量)
+fn paginate<T>(items: Vec<T>, params: &HashMap<String, String>) -> (Vec<T>, Option<String>) {
+    let size = params
+        .get("pageSize")
+        .and_then(|s| s.parse::<usize>().ok())
+        .filter(|s| *s > 0)
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .min(MAX_PAGE_SIZE);
+    let offset = params
+        .get("pageToken")
+        .and_then(|s| s.parse::<usize>().ok())
+        .unwrap_or(0);
+    let total = items.len();
+    let page = items.into_iter().skip(offset).take(size).collect();
+    let next = (offset + size < total).then(|| (offset + size).to_string());
+    (page, next)
+}
+
+// ===== Files API =====
+
+struct PendingUpload {
+    display_name: String,
+    mime_type: String,
+    data: Vec<u8>,
+    created: Instant,
+    owner: Option<String>,
+}
+
+static UPLOAD_SESSIONS: Lazy<Mutex<HashMap<String, PendingUpload>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 解析 `multipart/related` 上传 (元数据 JSON + 文件内容)
+fn parse_multipart_related(content_type: &str, body: &[u8]) -> Option<(Value, String, Vec<u8>)> {
+    let boundary = content_type
+        .split(';')
+        .find_map(|p| p.trim().strip_prefix("boundary="))?
+        .trim_matches('"');
+    let delimiter = format!("--{}", boundary).into_bytes();
+
+    let mut sections = Vec::new();
+    let mut rest = body;
+    while let Some(pos) = find_bytes(rest, &delimiter) {
+        sections.push(&rest[..pos]);
+        rest = &rest[pos + delimiter.len()..];
+    }
+    // 第一个分隔符之前为空，最后一段为 `--` 结束标记
+    let parts: Vec<(String, &[u8])> = sections
+        .into_iter()
+        .skip(1)
+        .filter_map(|section| {
+            let section = section.strip_prefix(b"\r\n").unwrap_or(section);
+            let split = find_bytes(section, b"\r\n\r\n")?;
+            let headers = String::from_utf8_lossy(&section[..split]).to_string();
+            let content = &section[split + 4..];
+            let content = content.strip_suffix(b"\r\n").unwrap_or(content);
+            let mime = headers
+                .lines()
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.trim()
+                        .eq_ignore_ascii_case("content-type")
+                        .then(|| value.trim().to_string())
+                })
+                .unwrap_or_default();
+            Some((mime, content))
+        })
+        .collect();
+
+    let (_, metadata) = parts.first()?;
+    let metadata = serde_json::from_slice(metadata).unwrap_or(Value::Null);
+    let (mime, data) = parts.get(1)?;
+    Some((metadata, mime.clone(), data.to_vec()))
+}
+
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// 元数据中的 displayName / mimeType (兼容 snake_case)
+fn file_metadata(metadata: &Value) -> (String, String) {
+    let file = metadata.get("file").unwrap_or(metadata);
+    let field = |camel: &str, snake: &str| {
+        file.get(camel)
+            .or_else(|| file.get(snake))
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string()
+    };
+    (
+        field("displayName", "display_name"),
+        field("mimeType", "mime_type"),
+    )
+}
+
+async fn finish_upload(
+    display_name: &str,
+    mime_type: &str,
+    data: &[u8],
+    headers: &HeaderMap,
+    owner: Option<&str>,
+) -> Response {
+    match resources::save_file(
+        display_name,
+        mime_type,
+        data,
+        &request_base_url(headers),
+        "UPLOADED",
+        owner,
+    )
+    .await
+    {
+        Ok(meta) => (
+            [("X-Goog-Upload-Status", "final")],
+            Json(json!({ "file": meta })),
+        )
+            .into_response(),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
+
+/// POST /upload/v1beta/files
+///
+/// 支持 resumable (start + upload/finalize)、multipart/related 与直接上传三种方式。
+pub async fn handle_upload_file(
+    identity: Identity,
+    headers: HeaderMap,
+    RawQuery(query): RawQuery,
+    body: Bytes,
+) -> Response {
+    let owner = owner(&identity);
+    let protocol = header_str(&headers, "x-goog-upload-protocol").to_ascii_lowercase();
+    let command = header_str(&headers, "x-goog-upload-command").to_ascii_lowercase();
+
+    // resumable: 后续的 upload / finalize 请求
+    if let Some(upload_id) = query_param(query.as_deref(), "upload_id") {
+        let offset = header_str(&headers, "x-goog-upload-offset")
+            .parse::<usize>()
+            .unwrap_or(0);
+        let finished = {
+            let mut sessions = UPLOAD_SESSIONS.lock().unwrap_or_else(|e| e.into_inner());
+            let Some(session) = sessions
+                .get_mut(&upload_id)
+                .filter(|session| session.owner == owner)
+            else {
+                return error_response(StatusCode::NOT_FOUND, "Upload session not found");
+            };
+            if offset != session.data.len() {
+                return error_response(
+                    StatusCode::BAD_REQUEST,
+                    format!(
+                        "Invalid upload offset {} (received {} bytes)",
+                        offset,
+                        session.data.len()
+                    ),
+                );
+            }
+            session.data.extend_from_slice(&body);
+            if command.contains("finalize") {
+                sessions.remove(&upload_id)
+            } else {
+                let received = session.data.len().to_string();
+                return (
+                    [
+                        ("X-Goog-Upload-Status", "active".to_string()),
+                        ("X-Goog-Upload-Size-Received", received),
+                    ],
+                    "",
+                )
+                    .into_response();
+            }
+        };
+        return match finished {
+            Some(upload) => {
+                finish_upload(
+                    &upload.display_name,
+                    &upload.mime_type,
+                    &upload.data,
+                    &headers,
+                    owner.as_deref(),
+                )
+                .await
+            }
+            None => error_response(StatusCode::NOT_FOUND, "Upload session not found"),
+        };
+    }
+
+    // resumable: 创建上传会话
+    if protocol == "resumable" && command.contains("start") {
+        let metadata = serde_json::from_slice::<Value>(&body).unwrap_or(Value::Null);
+        let (display_name, mime_type) = file_metadata(&metadata);
+        let mime_type = match header_str(&headers, "x-goog-upload-header-content-type") {
+            "" => mime_type,
+            declared => declared.to_string(),
+        };
+        let upload_id = resources::new_resource_id();
+        {
+            let mut sessions = UPLOAD_SESSIONS.lock().unwrap_or_else(|e| e.into_inner());
+            sessions.retain(|_, s| s.created.elapsed() < UPLOAD_SESSION_TTL);
+            sessions.insert(
+                upload_id.clone(),
+                PendingUpload {
+                    display_name,
+                    mime_type,
+                    data: Vec::new(),
+                    created: Instant::now(),
+                    owner: owner.clone(),
+                },
+            );
+        }
+        let upload_url = format!(
+            "{}/upload/v1beta/files?upload_id={}",
+            request_base_url(&headers),
+            upload_id
+        );
+        return (
+            [
+                ("X-Goog-Upload-URL", upload_url),
+                ("X-Goog-Upload-Status", "active".to_string()),
+            ],
+            "",
+        )
+            .into_response();
+    }
+
+    let content_type = header_str(&headers, header::CONTENT_TYPE.as_str());
+    if protocol == "multipart" || content_type.starts_with("multipart/related") {
+        return match parse_multipart_related(content_type, &body) {
+            Some((metadata, part_mime, data)) => {
+                let (display_name, mime_type) = file_metadata(&metadata);
+                let mime_type = if mime_type.is_empty() {
+                    part_mime
+                } else {
+                    mime_type
+                };
+                finish_upload(&display_name, &mime_type, &data, &headers, owner.as_deref()).await
+            }
+            None => error_response(StatusCode::BAD_REQUEST, "Invalid multipart upload body"),
+        };
+    }
+
+    finish_upload("", content_type, &body, &headers, owner.as_deref()).await
+}
+
+/// GET /v1beta/files
+pub async fn handle_list_files(
+    identity: Identity,
+    Query(params): Query<HashMap<String, String>>,
+) -> Response {
+    match resources::list_files(owner(&identity).as_deref()).await {
+        Ok(files) => {
+            let (files, next) = paginate(files, &params);
+            let mut body = json!({ "files": files });
+            if let Some(next) = next {
+                body["nextPageToken"] = json!(next);
+            }
+            Json(body).into_response()
+        }
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
+
+/// GET /v1beta/files/:name
+pub async fn handle_get_file(identity: Identity, Path(name): Path<String>) -> Response {
+    let id = resources::resource_id(&name, "files").unwrap_or_default();
+    match resources::get_file(id, owner(&identity).as_deref()).await {
+        Ok(Some(meta)) => Json(meta).into_response(),
+        Ok(None) => error_response(
+            StatusCode::NOT_FOUND,
+            format!("File files/{} not found", name),
+        ),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
+
+/// DELETE /v1beta/files/:name
+pub async fn handle_delete_file(identity: Identity, Path(name): Path<String>) -> Response {
+    let id = resources::resource_id(&name, "files").unwrap_or_default();
+    match resources::delete_file(id, owner(&identity).as_deref()).await {
+        Ok(true) => Json(json!({})).into_response(),
+        Ok(false) => error_response(
+            StatusCode::NOT_FOUND,
+            format!("File files/{} not found", name),
+        ),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
+
+/// GET /download/v1beta/files/:name (`{id}:download`)
+pub async fn handle_download_file(identity: Identity, Path(name): Path<String>) -> Response {
+    let name = name.trim_end_matches(":download");
+    let id = resources::resource_id(name, "files").unwrap_or_default();
+    match resources::read_file(id, owner(&identity).as_deref()).await {
+        Ok(Some((meta, data))) => {
+            let mime = HeaderValue::from_str(&meta.mime_type)
+                .unwrap_or(HeaderValue::from_static("application/octet-stream"));
+            ([(header::CONTENT_TYPE, mime)], data).into_response()
+        }
+        Ok(None) => error_response(
+            StatusCode::NOT_FOUND,
+            format!("File files/{} not found", name),
+        ),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
+
+// ===== cachedContents =====
+
+/// POST /v1beta/cachedContents
+pub async fn handle_create_cached_content(identity: Identity, Json(body): Json<Value>) -> Response {
+    match resources::create_cached_content(&body, owner(&identity).as_deref()) {
+        Ok(value) => Json(value).into_response(),
+        Err((status, message)) => error_response(status, message),
+    }
+}
+
+/// GET /v1beta/cachedContents
+pub async fn handle_list_cached_contents(
+    identity: Identity,
+    Query(params): Query<HashMap<String, String>>,
+) -> Response {
+    let entries = resources::list_cached_contents(owner(&identity).as_deref());
+    let (entries, next) = paginate(entries, &params);
+    let mut body = json!({ "cachedContents": entries });
+    if let Some(next) = next {
+        body["nextPageToken"] = json!(next);
+    }
+    Json(body).into_response()
+}
+
+/// GET /v1beta/cachedContents/:name
+pub async fn handle_get_cached_content(identity: Identity, Path(name): Path<String>) -> Response {
+    match resources::get_cached_content(&name, owner(&identity).as_deref()) {
+        Some(entry) => Json(entry.to_json()).into_response(),
+        None => error_response(
+            StatusCode::NOT_FOUND,
+            format!("CachedContent not found (or expired): {}", name),
+        ),
+    }
+}
+
+/// PATCH /v1beta/cachedContents/:name (仅支持更新 ttl / expireTime)
+pub async fn handle_update_cached_content(
+    identity: Identity,
+    Path(name): Path<String>,
+    Json(body): Json<Value>,
+) -> Response {
+    match resources::update_cached_content(&name, &body, owner(&identity).as_deref()) {
+        Ok(value) => Json(value).into_response(),
+        Err((status, message)) => error_response(status, message),
+    }
+}
+
+/// DELETE /v1beta/cachedContents/:name
+pub async fn handle_delete_cached_content(
+    identity: Identity,
+    Path(name): Path<String>,
+) -> Response {
+    if resources::delete_cached_content(&name, owner(&identity).as_deref()) {
+        Json(json!({})).into_response()
+    } else {
+        error_response(
+            StatusCode::NOT_FOUND,
+            format!("CachedContent not found (or expired): {}", name),
+        )
+    }
+}
+
+// ===== Batch API =====
+
+const BATCH_TYPE: &str =
+    "type.googleapis.com/google.ai.generativelanguage.v1main.GenerateContentBatch";
+
+struct BatchJob {
+    name: String,
+    model: String,
+    display_name: String,
+    state: &'static str,
+    create_time: DateTime<Utc>,
+    update_time: DateTime<Utc>,
+    end_time: Option<DateTime<Utc>>,
+    request_count: usize,
+    succeeded: usize,
+    failed: usize,
+    output: Option<Value>,
+    error: Option<String>,
+    cancelled: Arc<AtomicBool>,
+    owner: Option<String>,
+}
+
+impl BatchJob {
+    fn is_done(&self) -> bool {
+        self.end_time.is_some()
+    }
+
+    /// 以 Operation 形式返回 (metadata 为 GenerateContentBatch)
+    fn to_operation(&self) -> Value {
+        let mut batch = json!({
+            "@type": BATCH_TYPE,
+            "name": self.name,
+            "model": self.model,
+            "state": self.state,
+            "createTime": timestamp(self.create_time),
+            "updateTime": timestamp(self.update_time),
+            "batchStats": {
+                "requestCount": self.request_count.to_string(),
+                "successfulRequestCount": self.succeeded.to_string(),
+                "failedRequestCount": self.failed.to_string(),
+                "pendingRequestCount": (self.request_count - self.succeeded - self.failed).to_string(),
+            },
+        });
+        if !self.display_name.is_empty() {
+            batch["displayName"] = json!(self.display_name);
+        }
+        if let Some(end) = self.end_time {
+            batch["endTime"] = json!(timestamp(end));
+        }
+        if let Some(output) = &self.output {
+            batch["output"] = output.clone();
+        }
+
+        let mut operation = json!({
+            "name": self.name,
+            "metadata": batch.clone(),
+            "done": self.is_done(),
+        });
+        match (&self.error, self.is_done()) {
+            (Some(message), _) => {
+                operation["error"] = json!({ "code": 13, "message": message });
+            }
+            (None, true) => operation["response"] = batch,
+            (None, false) => {}
+        }
+        operation
+    }
+}
+
+static BATCHES: Lazy<Mutex<HashMap<String, BatchJob>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 完整的代理路由 (含全部中间件)，批量任务的每条请求经由它执行
+static BATCH_ROUTER: Lazy<RwLock<Option<Router>>> = Lazy::new(|| RwLock::new(None));
+
+/// 服务启动时登记路由，之后创建的批量任务都通过它分发
+pub fn set_batch_router(router: Router) {
+    *BATCH_ROUTER.write().unwrap_or_else(|e| e.into_inner()) = Some(router);
+}
+
+fn batch_router() -> Option<Router> {
+    BATCH_ROUTER
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+/// 创建批量任务的调用方：逐条请求沿用其请求头 (鉴权凭据、客户端特征) 与连接地址
+#[derive(Clone)]
+struct BatchCaller {
+    headers: HeaderMap,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    owner: Option<String>,
+}
+
+/// 在调用方可见的批量任务上执行操作
+fn with_owned_batch<T>(
+    name: &str,
+    owner: Option<&str>,
+    f: impl FnOnce(&mut HashMap<String, BatchJob>, String) -> T,
+) -> Option<T> {
+    let name = batch_name(name);
+    let mut batches = BATCHES.lock().unwrap_or_else(|e| e.into_inner());
+    let owned = batches
+        .get(&name)
+        .is_some_and(|job| job.owner.as_deref() == owner);
+    owned.then(|| f(&mut batches, name))
+}
+
+fn update_batch(name: &str, f: impl FnOnce(&mut BatchJob)) {
+    let mut batches = BATCHES.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(job) = batches.get_mut(name) {
+        f(job);
+        job.update_time = Utc::now();
+    }
+}
+
+/// 批量请求中的一条: (key / metadata, GenerateContentRequest)
+type BatchItem = (Value, Value);
+
+/// 读取 inputConfig: 内联请求或已上传的 JSONL 文件
+async fn batch_items(input: &Value, owner: Option<&str>) -> Result<(Vec<BatchItem>, bool), String> {
+    if let Some(requests) = input
+        .pointer("/requests/requests")
+        .and_then(|r| r.as_array())
+    {
+        let items = requests
+            .iter()
+            .map(|r| {
+                (
+                    r.get("metadata").cloned().unwrap_or(Value::Null),
+                    r.get("request").cloned().unwrap_or(Value::Null),
+                )
+            })
+            .collect();
+        return Ok((items, false));
+    }
+    if let Some(file_name) = input.get("fileName").and_then(|f| f.as_str()) {
+        let id = resources::resource_id(file_name, "files").unwrap_or_default();
+        let (_, data) = resources::read_file(id, owner)
+            .await?
+            .ok_or_else(|| format!("File {} not found (or expired)", file_name))?;
+        let mut items = Vec::new();
+        for (i, line) in String::from_utf8_lossy(&data).lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: Value = serde_json::from_str(line)
+                .map_err(|e| format!("Invalid JSONL at line {}: {}", i + 1, e))?;
+            items.push((
+                entry.get("key").cloned().unwrap_or(Value::Null),
+                entry.get("request").cloned().unwrap_or(Value::Null),
+            ));
+        }
+        return Ok((items, true));
+    }
+    Err("batch.inputConfig must contain requests or fileName".to_string())
+}
+
+/// POST /v1beta/models/:model 的统一入口：批量请求交给 Batch API，其余交给 handle_generate
+pub async fn handle_model_post(
+    state: State<AppState>,
+    Path(model_action): Path<String>,
+    query: RawQuery,
+    model_override: Option<Extension<GeminiModelOverride>>,
+    identity: Identity,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    Json(body): Json<Value>,
+) -> Response {
+    // 批量任务使用客户端请求的模型：每条请求重新经过路由与降级中间件
+    if let Some(model) = model_action.strip_suffix(":batchGenerateContent") {
+        let caller = BatchCaller {
+            headers,
+            connect_info,
+            owner: owner(&identity),
+        };
+        return create_batch(model.to_string(), caller, body).await;
+    }
+    super::gemini::handle_generate(
+        state,
+        Path(model_action),
+        query,
+        model_override,
+        identity,
+        headers,
+        Json(body),
+    )
+    .await
+    .into_response()
+}
+
+async fn create_batch(model: String, caller: BatchCaller, body: Value) -> Response {
+    let batch = body.get("batch").unwrap_or(&body);
+    let input = batch.get("inputConfig").cloned().unwrap_or(Value::Null);
+    let (items, from_file) = match batch_items(&input, caller.owner.as_deref()).await {
+        Ok(result) => result,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, e),
+    };
+    if items.is_empty() {
+        return error_response(StatusCode::BAD_REQUEST, "Batch contains no requests");
+    }
+    let Some(router) = batch_router() else {
+        return error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Batch execution is unavailable",
+        );
+    };
+
+    let now = Utc::now();
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let job = BatchJob {
+        name: format!("batches/{}", resources::new_resource_id()),
+        model: format!("models/{}", model.trim_start_matches("models/")),
+        display_name: batch
+            .get("displayName")
+            .and_then(|d| d.as_str())
+            .unwrap_or_default()
+            .to_string(),
+        state: "BATCH_STATE_PENDING",
+        create_time: now,
+        update_time: now,
+        end_time: None,
+        request_count: items.len(),
+        succeeded: 0,
+        failed: 0,
+        output: None,
+        error: None,
+        cancelled: cancelled.clone(),
+        owner: caller.owner.clone(),
+    };
+    let operation = job.to_operation();
+    let name = job.name.clone();
+    {
+        let mut batches = BATCHES.lock().unwrap_or_else(|e| e.into_inner());
+        let mut finished: Vec<(DateTime<Utc>, String)> = batches
+            .values()
+            .filter(|j| j.is_done())
+            .map(|j| (j.create_time, j.name.clone()))
+            .collect();
+        if finished.len() >= MAX_FINISHED_BATCHES {
+            finished.sort();
+            for (_, old) in finished
+                .iter()
+                .take(finished.len() + 1 - MAX_FINISHED_BATCHES)
+            {
+                batches.remove(old);
+            }
+        }
+        batches.insert(name.clone(), job);
+    }
+    info!(
+        "[GeminiBatch] Created {} for {} ({} requests, file input: {})",
+        name,
+        model,
+        items.len(),
+        from_file
+    );
+
+    let base_url = request_base_url(&caller.headers);
+    tokio::spawn(run_batch(
+        router, name, model, caller, items, from_file, base_url, cancelled,
+    ));
+    Json(operation).into_response()
+}
+
+/// 构造单条 generateContent 请求 (沿用调用方请求头，重新计算 Content-Length)
+fn batch_item_request(model: &str, caller: &BatchCaller, request: &Value) -> Request<Body> {
+    let mut http_request = Request::new(Body::from(request.to_string()));
+    *http_request.method_mut() = axum::http::Method::POST;
+    *http_request.uri_mut() = format!("/v1beta/models/{}:generateContent", model)
+        .parse()
+        .unwrap_or_default();
+    let headers = http_request.headers_mut();
+    headers.extend(caller.headers.clone());
+    headers.remove(header::CONTENT_LENGTH);
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    if let Some(connect_info) = caller.connect_info {
+        http_request.extensions_mut().insert(connect_info);
+    }
+    http_request
+}
+
+/// 通过代理路由执行单条请求
+async fn run_batch_request(
+    router: Router,
+    model: &str,
+    caller: &BatchCaller,
+    request: Value,
+) -> Result<Value, Value> {
+    let response = match router
+        .oneshot(batch_item_request(model, caller, &request))
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => match e {},
+    };
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap_or_default();
+    let json = serde_json::from_slice::<Value>(&bytes).ok();
+    match (status.is_success(), json) {
+        (true, Some(json)) => Ok(json),
+        (_, Some(json)) if json.get("error").is_some() => Err(json["error"].clone()),
+        _ => Err(json!({
+            "code": status.as_u16(),
+            "message": String::from_utf8_lossy(&bytes).chars().take(500).collect::<String>(),
+        })),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn run_batch(
+    router: Router,
+    name: String,
+    model: String,
+    caller: BatchCaller,
+    items: Vec<BatchItem>,
+    from_file: bool,
+    base_url: String,
+    cancelled: Arc<AtomicBool>,
+) {
+    update_batch(&name, |job| job.state = "BATCH_STATE_RUNNING");
+
+    let mut results: Vec<(usize, Value, Option<Result<Value, Value>>)> =
+        futures::stream::iter(items.into_iter().enumerate())
+            .map(|(index, (key, request))| {
+                let (router, caller) = (router.clone(), caller.clone());
+                let (name, model, cancelled) = (name.clone(), model.clone(), cancelled.clone());
+                async move {
+                    if cancelled.load(Ordering::Relaxed) {
+                        return (index, key, None);
+                    }
+                    let result = run_batch_request(router, &model, &caller, request).await;
+                    update_batch(&name, |job| match &result {
+                        Ok(_) => job.succeeded += 1,
+                        Err(_) => job.failed += 1,
+                    });
+                    (index, key, Some(result))
+                }
+            })
+            .buffer_unordered(BATCH_CONCURRENCY)
+            .collect()
+            .await;
+    results.sort_by_key(|(index, _, _)| *index);
+
+    let entries: Vec<Value> = results
+        .into_iter()
+        .filter_map(|(_, key, result)| {
+            let (field, value) = match result? {
+                Ok(response) => ("response", response),
+                Err(error) => ("error", error),
+            };
+            let mut entry = serde_json::Map::new();
+            entry.insert(field.to_string(), value);
+            if from_file {
+                entry.insert("key".to_string(), key);
+            } else if !key.is_null() {
+                entry.insert("metadata".to_string(), key);
+            }
+            Some(Value::Object(entry))
+        })
+        .collect();
+
+    let output = if from_file {
+        let jsonl = entries
+            .iter()
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let display_name = format!("{}-results", name.replace('/', "-"));
+        resources::save_file(
+            &display_name,
+            "application/jsonl",
+            jsonl.as_bytes(),
+            &base_url,
+            "GENERATED",
+            caller.owner.as_deref(),
+        )
+        .await
+        .map(|meta| json!({ "responsesFile": meta.name }))
+    } else {
+        Ok(json!({ "inlinedResponses": { "inlinedResponses": entries } }))
+    };
+
+    let was_cancelled = cancelled.load(Ordering::Relaxed);
+    update_batch(&name, |job| {
+        job.end_time = Some(Utc::now());
+        match output {
+            Ok(output) => {
+                job.output = Some(output);
+                job.state = if was_cancelled {
+                    "BATCH_STATE_CANCELLED"
+                } else {
+                    "BATCH_STATE_SUCCEEDED"
+                };
+            }
+            Err(e) => {
+                warn!("[GeminiBatch] {} failed to store results: {}", name, e);
+                job.error = Some(e);
+                job.state = "BATCH_STATE_FAILED";
+            }
+        }
+        info!(
+            "[GeminiBatch] {} finished: {} ({} succeeded, {} failed)",
+            job.name, job.state, job.succeeded, job.failed
+        );
+    });
+}
+
+/// GET /v1beta/batches
+pub async fn handle_list_batches(
+    identity: Identity,
+    Query(params): Query<HashMap<String, String>>,
+) -> Response {
+    let owner = owner(&identity);
+    let mut operations: Vec<(DateTime<Utc>, Value)> = BATCHES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .values()
+        .filter(|job| job.owner == owner)
+        .map(|job| (job.create_time, job.to_operation()))
+        .collect();
+    operations.sort_by(|a, b| b.0.cmp(&a.0));
+    let operations: Vec<Value> = operations.into_iter().map(|(_, op)| op).collect();
+    let (operations, next) = paginate(operations, &params);
+    let mut body = json!({ "operations": operations });
+    if let Some(next) = next {
+        body["nextPageToken"] = json!(next);
+    }
+    Json(body).into_response()
+}
+
+fn batch_name(name: &str) -> String {
+    format!(
+        "batches/{}",
+        resources::resource_id(name, "batches").unwrap_or_default()
+    )
+}
+
+/// GET /v1beta/batches/:name
+pub async fn handle_get_batch(identity: Identity, Path(name): Path<String>) -> Response {
+    let operation = with_owned_batch(&name, owner(&identity).as_deref(), |batches, name| {
+        batches[&name].to_operation()
+    });
+    match operation {
+        Some(operation) => Json(operation).into_response(),
+        None => error_response(
+            StatusCode::NOT_FOUND,
+            format!("Batch batches/{} not found", name),
+        ),
+    }
+}
+
+/// POST /v1beta/batches/:name (`{id}:cancel`)
+pub async fn handle_batch_action(identity: Identity, Path(name): Path<String>) -> Response {
+    let Some(id) = name.strip_suffix(":cancel") else {
+        return error_response(
+            StatusCode::NOT_FOUND,
+            format!("Unsupported batch action: {}", name),
+        );
+    };
+    let cancelled = with_owned_batch(id, owner(&identity).as_deref(), |batches, name| {
+        batches[&name].cancelled.store(true, Ordering::Relaxed)
+    });
+    match cancelled {
+        Some(()) => Json(json!({})).into_response(),
+        None => error_response(
+            StatusCode::NOT_FOUND,
+            format!("Batch batches/{} not found", id),
+        ),
+    }
+}
+
+/// DELETE /v1beta/batches/:name (未结束的任务会先取消)
+pub async fn handle_delete_batch(identity: Identity, Path(name): Path<String>) -> Response {
+    let removed = with_owned_batch(&name, owner(&identity).as_deref(), |batches, name| {
+        batches.remove(&name)
+    });
+    match removed.flatten() {
+        Some(job) => {
+            job.cancelled.store(true, Ordering::Relaxed);
+            Json(json!({})).into_response()
+        }
+        None => error_response(
+            StatusCode::NOT_FOUND,
+            format!("Batch batches/{} not found", name),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_multipart_related() {
+        let body = b"--xyz\r\nContent-Type: application/json; charset=UTF-8\r\n\r\n{\"file\": {\"display_name\": \"notes\"}}\r\n--xyz\r\nContent-Type: text/plain\r\n\r\nhello\r\nworld\r\n--xyz--\r\n";
+        let (metadata, mime, data) =
+            parse_multipart_related("multipart/related; boundary=xyz", body).unwrap();
+        assert_eq!(file_metadata(&metadata).0, "notes");
+        assert_eq!(mime, "text/plain");
+        assert_eq!(data, b"hello\r\nworld");
+    }
+
+    #[tokio::test]
+    async fn test_batch_request_runs_through_router_layers() {
+        use axum::middleware::{self, Next};
+        use axum::routing::post;
+
+        // 模拟鉴权层：只有携带调用方凭据的请求才放行
+        async fn require_key(request: Request<Body>, next: Next) -> Response {
+            if request.headers().get("x-goog-api-key").is_none() {
+                return StatusCode::UNAUTHORIZED.into_response();
+            }
+            next.run(request).await
+        }
+        async fn echo(Path(model): Path<String>, Json(body): Json<Value>) -> Json<Value> {
+            Json(json!({ "model": model, "request": body }))
+        }
+        let router = Router::new()
+            .route("/v1beta/models/:model", post(echo))
+            .layer(middleware::from_fn(require_key));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-goog-api-key", HeaderValue::from_static("sk-user"));
+        headers.insert(header::CONTENT_LENGTH, HeaderValue::from_static("1"));
+        let caller = BatchCaller {
+            headers,
+            connect_info: None,
+            owner: None,
+        };
+        let request = json!({ "contents": [{ "parts": [{ "text": "hi" }] }] });
+        let response = run_batch_request(router.clone(), "gemini-2.5-flash", &caller, request)
+            .await
+            .unwrap();
+        assert_eq!(response["model"], "gemini-2.5-flash:generateContent");
+        assert_eq!(response["request"]["contents"][0]["parts"][0]["text"], "hi");
+
+        let anonymous = BatchCaller {
+            headers: HeaderMap::new(),
+            connect_info: None,
+            owner: None,
+        };
+        let error = run_batch_request(router, "gemini-2.5-flash", &anonymous, json!({}))
+            .await
+            .unwrap_err();
+        assert_eq!(error["code"], 401);
+    }
+
+    #[test]
+    fn test_paginate() {
+        let params = HashMap::from([("pageSize".to_string(), "2".to_string())]);
+        let (page, next) = paginate(vec![1, 2, 3], &params);
+        assert_eq!(page, vec![1, 2]);
+        assert_eq!(next.as_deref(), Some("2"));
+
+        let params = HashMap::from([
+            ("pageSize".to_string(), "2".to_string()),
+            ("pageToken".to_string(), "2".to_string()),
+        ]);
+        let (page, next) = paginate(vec![1, 2, 3], &params);
+        assert_eq!(page, vec![3]);
+        assert!(next.is_none());
+    }
+}
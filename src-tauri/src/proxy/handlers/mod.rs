@@ -4,8 +4,10 @@
 pub mod claude;
 pub mod openai;
 pub mod gemini;
+pub mod gemini_resources; // Gemini Files / cachedContents / Batch 本地模拟
 pub mod mcp;
 pub mod common;
-pub mod audio;  // 音频转录处理器
+pub mod dispatch; // 统一请求调度 (重试/时限/对冲)
+pub mod audio;  // 音频转录 / 翻译 / 语音合成处理器
 pub mod warmup; // 预热处理器
 
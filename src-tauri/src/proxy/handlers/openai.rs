@@ -1,6 +1,7 @@
 // OpenAI Handler
 use axum::{
-    extract::Json, extract::State, http::StatusCode, response::IntoResponse, response::Response,
+    extract::Json, extract::OriginalUri, extract::State, http::StatusCode,
+    response::IntoResponse, response::Response,
 };
 use base64::Engine as _;
 use bytes::Bytes;
@@ -8,18 +9,19 @@ use serde_json::{json, Value};
 use tracing::{debug, error, info}; // Import Engine trait for encode method
 
 use crate::proxy::mappers::openai::{
-    transform_openai_request, transform_openai_response, OpenAIRequest,
+    transform_openai_request, transform_openai_response, OpenAIRequest, OpenAIResponse,
 };
 // use crate::proxy::upstream::client::UpstreamClient; // 通过 state 获取
+use crate::proxy::context_window::SummaryUpstream;
 use crate::proxy::debug_logger;
+use crate::proxy::mappers::context_manager::ContextManager;
 use crate::proxy::server::AppState;
 use crate::proxy::upstream::client::mask_email;
 
-const MAX_RETRY_ATTEMPTS: usize = 3;
-use super::common::{
-    apply_retry_strategy, determine_retry_strategy, should_rotate_account, RetryStrategy,
-};
-use crate::proxy::common::client_adapter::CLIENT_ADAPTERS; // [NEW] Adapter Registry
+use super::common::RetryStrategy;
+use super::dispatch::Dispatcher;
+use crate::proxy::common::client_adapter::find_client_adapter; // [NEW] Adapter Registry
+use crate::proxy::common::schema_validate::{self, ToolArgVerdict};
 use crate::proxy::session_manager::SessionManager;
 use axum::http::HeaderMap;
 use tokio::time::Duration;
@@ -37,6 +39,27 @@ pub async fn handle_chat_completions(
         return intercept_chat_to_image(state, body, &model_name).await;
     }
 
+    // [NEW] 通用第三方提供商 (OpenAI 协议透传)
+    if let Some(model) = body.get("model").and_then(|v| v.as_str()) {
+        if let Some(route) = crate::proxy::providers::generic::resolve_route(
+            &state,
+            crate::proxy::ProviderProtocol::Openai,
+            model,
+            "openai",
+        )
+        .await
+        {
+            return Ok(crate::proxy::providers::generic::forward(
+                &state,
+                &route,
+                "/chat/completions",
+                &headers,
+                body,
+            )
+            .await);
+        }
+    }
+
     // [FIX] 保存原始请求体的完整副本，用于日志记录
     // 这确保了即使结构体定义遗漏字段，日志也能完整记录所有参数
     let original_body = body.clone();
@@ -110,7 +133,8 @@ pub async fn handle_chat_completions(
             });
     }
 
-    let trace_id = format!("req_{}", chrono::Utc::now().timestamp_subsec_millis());
+    let trace_id = crate::proxy::script_hooks::current_trace_id()
+        .unwrap_or_else(|| format!("req_{}", chrono::Utc::now().timestamp_subsec_millis()));
     info!(
         "[{}] OpenAI Chat Request: {} | {} messages | stream: {}",
         trace_id,
@@ -138,10 +162,7 @@ pub async fn handle_chat_completions(
     }
 
     // [NEW] Detect Client Adapter
-    let client_adapter = CLIENT_ADAPTERS
-        .iter()
-        .find(|a| a.matches(&headers))
-        .cloned();
+    let client_adapter = find_client_adapter(&headers);
     if client_adapter.is_some() {
         debug!("[{}] Client Adapter detected", trace_id);
     }
@@ -151,11 +172,17 @@ pub async fn handle_chat_completions(
     let token_manager = state.token_manager;
     let pool_size = token_manager.len();
     // [FIX] Ensure max_attempts is at least 2 to allow for internal retries
-    let max_attempts = MAX_RETRY_ATTEMPTS.min(pool_size.saturating_add(1)).max(2);
+    let mut dispatcher = Dispatcher::new(pool_size.saturating_add(1), 2, &trace_id);
+    let max_attempts = dispatcher.max_attempts();
 
     let mut last_error = String::new();
     let mut last_email: Option<String> = None;
 
+    // 按客户端原始 Schema 校验返回的工具调用参数 (仅非流式响应)
+    let tool_arg_validation = state.experimental.read().await.tool_arg_validation.clone();
+    let tool_schemas = declared_tool_schemas(openai_req.tools.as_deref());
+    let mut tool_arg_retries = 0u32;
+
     // 2. 模型路由解析 (移到循环外以支持在所有路径返回 X-Mapped-Model)
     let mapped_model = crate::proxy::common::model_mapping::resolve_model_route(
         &openai_req.model,
@@ -163,6 +190,10 @@ pub async fn handle_chat_completions(
     );
 
     for attempt in 0..max_attempts {
+        if dispatcher.deadline_exceeded(attempt) {
+            break;
+        }
+
         // 将 OpenAI 工具转为 Value 数组以便探测联网
         let tools_val: Option<Vec<Value>> = openai_req
             .tools
@@ -182,11 +213,11 @@ pub async fn handle_chat_completions(
         let session_id = SessionManager::extract_openai_session_id(&openai_req);
 
         // 4. 获取 Token (使用准确的 request_type)
-        // 关键：在重试尝试 (attempt > 0) 时强制轮换账号
+        // 关键：在重试尝试时强制轮换账号 (上一次失败的状态码无需轮换时除外)
         let (access_token, project_id, email, account_id, _wait_ms) = match token_manager
             .get_token(
                 &config.request_type,
-                attempt > 0,
+                dispatcher.force_rotate(attempt),
                 Some(&session_id),
                 &mapped_model,
             )
@@ -215,9 +246,49 @@ pub async fn handle_chat_completions(
         info!("✓ Using account: {} (type: {})", email, config.request_type);
 
         // 4. 转换请求 (返回内容包含 session_id 和 message_count)
-        let (gemini_body, session_id, message_count) =
+        let (mut gemini_body, session_id, message_count) =
             transform_openai_request(&openai_req, &project_id, &mapped_model, proxy_token.as_ref());
 
+        // 上下文窗口策略 (按用户令牌 / 客户端选择)
+        crate::proxy::context_window::apply_current(
+            &mut gemini_body,
+            &mapped_model,
+            SummaryUpstream {
+                upstream: &upstream,
+                access_token: &access_token,
+                project_id: &project_id,
+                account_id: &account_id,
+            },
+            &trace_id,
+        )
+        .await;
+
+        // 思考策略 (按模型家族 / 调用方选择)
+        crate::proxy::thinking_policy::apply_current(&mut gemini_body, &mapped_model, &trace_id);
+
+        // 脚本钩子 (upstream_request 阶段)
+        if let Err(e) = crate::proxy::script_hooks::apply_upstream(
+            crate::proxy::ProviderProtocol::Openai,
+            &mut gemini_body,
+            &mapped_model,
+            &trace_id,
+        )
+        .await
+        {
+            return Ok(crate::proxy::script_hooks::reject_response(
+                crate::proxy::ProviderProtocol::Openai,
+                &e,
+            ));
+        }
+
+        // 按实际发往上游的请求估算，用于校准器学习
+        let prompt_estimate = gemini_body.get("request").map(|request| {
+            (
+                mapped_model.clone(),
+                ContextManager::estimate_gemini_input_tokens(request, &mapped_model),
+            )
+        });
+
         if debug_logger::is_enabled(&debug_cfg) {
             let payload = json!({
                 "kind": "v1internal_request",
@@ -275,14 +346,17 @@ pub async fn handle_chat_completions(
             );
         }
 
-        let call_result = match upstream
-            .call_v1_internal_with_headers(
-                method,
-                &access_token,
-                gemini_body,
-                query_string,
-                extra_headers.clone(),
-                Some(account_id.as_str()),
+        let call_result = match dispatcher
+            .within_deadline(
+                upstream.call_v1_internal_with_headers(
+                    method,
+                    &access_token,
+                    gemini_body,
+                    query_string,
+                    extra_headers.clone(),
+                    Some(account_id.as_str()),
+                ),
+                |e| e,
             )
             .await
         {
@@ -368,6 +442,7 @@ pub async fn handle_chat_completions(
                     openai_req.model.clone(),
                     session_id,
                     message_count,
+                    prompt_estimate.clone(),
                 );
 
                 let mut first_data_chunk = None;
@@ -462,6 +537,26 @@ pub async fn handle_chat_completions(
                     match collect_stream_to_json(Box::pin(combined_stream)).await {
                         Ok(full_response) => {
                             info!("[{}] ✓ Stream collected and converted to JSON", trace_id);
+                            let violations = tool_arg_violations(&full_response, &tool_schemas);
+                            match schema_validate::decide(
+                                &tool_arg_validation,
+                                &violations,
+                                tool_arg_retries,
+                                &trace_id,
+                            ) {
+                                ToolArgVerdict::Retry => {
+                                    tool_arg_retries += 1;
+                                    last_error = format!(
+                                        "Invalid tool call arguments: {}",
+                                        violations.join("; ")
+                                    );
+                                    continue;
+                                }
+                                ToolArgVerdict::Reject(message) => {
+                                    return Ok((StatusCode::BAD_GATEWAY, message).into_response());
+                                }
+                                ToolArgVerdict::Accept => {}
+                            }
                             return Ok((
                                 StatusCode::OK,
                                 [
@@ -489,8 +584,29 @@ pub async fn handle_chat_completions(
                 .await
                 .map_err(|e| (StatusCode::BAD_GATEWAY, format!("Parse error: {}", e)))?;
 
-            let openai_response =
-                transform_openai_response(&gemini_resp, Some(&session_id), message_count);
+            let openai_response = transform_openai_response(
+                &gemini_resp,
+                Some(&session_id),
+                message_count,
+                prompt_estimate.as_ref(),
+            );
+            let violations = tool_arg_violations(&openai_response, &tool_schemas);
+            match schema_validate::decide(
+                &tool_arg_validation,
+                &violations,
+                tool_arg_retries,
+                &trace_id,
+            ) {
+                ToolArgVerdict::Retry => {
+                    tool_arg_retries += 1;
+                    last_error = format!("Invalid tool call arguments: {}", violations.join("; "));
+                    continue;
+                }
+                ToolArgVerdict::Reject(message) => {
+                    return Ok((StatusCode::BAD_GATEWAY, message).into_response());
+                }
+                ToolArgVerdict::Accept => {}
+            }
             return Ok((
                 StatusCode::OK,
                 [
@@ -545,7 +661,7 @@ pub async fn handle_chat_completions(
         }
 
         // 确定重试策略
-        let strategy = determine_retry_strategy(status_code, &error_text, false);
+        let strategy = dispatcher.strategy(status_code, &error_text, false);
 
         // 3. 标记限流状态(用于 UI 显示)
         if status_code == 429 || status_code == 529 || status_code == 503 || status_code == 500 {
@@ -562,7 +678,7 @@ pub async fn handle_chat_completions(
         }
 
         // 执行退避
-        if apply_retry_strategy(strategy, attempt, max_attempts, status_code, &trace_id).await {
+        if dispatcher.backoff(strategy, attempt, status_code).await {
             // [NEW] Apply Client Adapter "let_it_crash" strategy
             if let Some(adapter) = &client_adapter {
                 if adapter.let_it_crash() && attempt > 0 {
@@ -580,14 +696,6 @@ pub async fn handle_chat_completions(
                 }
             }
 
-            // 判断是否需要轮换账号
-            if !should_rotate_account(status_code) {
-                debug!(
-                    "[{}] Keeping same account for status {} (server-side issue)",
-                    trace_id, status_code
-                );
-            }
-
             // 2. [REMOVED] 不再特殊处理 QUOTA_EXHAUSTED，允许账号轮换
             // if error_text.contains("QUOTA_EXHAUSTED") { ... }
             /*
@@ -652,14 +760,13 @@ pub async fn handle_chat_completions(
 
         // 只有 403 (权限/地区限制) 和 401 (认证失效) 触发账号轮换
         if status_code == 403 || status_code == 401 {
-            if apply_retry_strategy(
-                RetryStrategy::FixedDelay(Duration::from_millis(200)),
-                attempt,
-                max_attempts,
-                status_code,
-                &trace_id,
-            )
-            .await
+            if dispatcher
+                .backoff(
+                    RetryStrategy::FixedDelay(Duration::from_millis(200)),
+                    attempt,
+                    status_code,
+                )
+                .await
             {
                 continue;
             }
@@ -698,14 +805,13 @@ pub async fn handle_chat_completions(
                 }
             }
 
-            if apply_retry_strategy(
-                RetryStrategy::FixedDelay(Duration::from_millis(200)),
-                attempt,
-                max_attempts,
-                status_code,
-                &trace_id,
-            )
-            .await
+            if dispatcher
+                .backoff(
+                    RetryStrategy::FixedDelay(Duration::from_millis(200)),
+                    attempt,
+                    status_code,
+                )
+                .await
             {
                 continue;
             }
@@ -752,10 +858,57 @@ pub async fn handle_chat_completions(
     }
 }
 
+/// 提取请求中声明的工具原始 Schema (兼容 `function` 包裹与精简格式)
+fn declared_tool_schemas(tools: Option<&[Value]>) -> std::collections::HashMap<String, Value> {
+    tools
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|tool| {
+            let function = tool.get("function").unwrap_or(tool);
+            let name = function.get("name")?.as_str()?;
+            let parameters = function.get("parameters")?;
+            Some((name.to_string(), parameters.clone()))
+        })
+        .collect()
+}
+
+/// 按客户端声明的原始 Schema 校验响应中的工具调用参数
+fn tool_arg_violations(
+    response: &OpenAIResponse,
+    schemas: &std::collections::HashMap<String, Value>,
+) -> Vec<String> {
+    if schemas.is_empty() {
+        return Vec::new();
+    }
+    let mut violations = Vec::new();
+    let mut calls = Vec::new();
+    for call in response
+        .choices
+        .iter()
+        .flat_map(|c| c.message.tool_calls.iter().flatten())
+    {
+        match serde_json::from_str::<Value>(&call.function.arguments) {
+            Ok(args) => calls.push((call.function.name.as_str(), args)),
+            Err(e) if schemas.contains_key(&call.function.name) => violations.push(format!(
+                "tool '{}' arguments are not valid JSON: {}",
+                call.function.name, e
+            )),
+            Err(_) => {}
+        }
+    }
+    violations.extend(schema_validate::validate_tool_calls(
+        calls.iter().map(|(name, args)| (*name, args)),
+        schemas,
+    ));
+    violations
+}
+
 /// 处理 Legacy Completions API (/v1/completions)
 /// 将 Prompt 转换为 Chat Message 格式，复用 handle_chat_completions
 pub async fn handle_completions(
     State(state): State<AppState>,
+    OriginalUri(uri): OriginalUri,
+    headers: HeaderMap,
     Json(mut body): Json<Value>,
 ) -> Response {
     debug!(
@@ -763,6 +916,22 @@ pub async fn handle_completions(
         body
     );
 
+    // [NEW] 通用第三方提供商 (OpenAI 协议透传，保留原始端点)
+    if let Some(model) = body.get("model").and_then(|v| v.as_str()) {
+        if let Some(route) = crate::proxy::providers::generic::resolve_route(
+            &state,
+            crate::proxy::ProviderProtocol::Openai,
+            model,
+            "openai",
+        )
+        .await
+        {
+            let path = uri.path().strip_prefix("/v1").unwrap_or(uri.path());
+            return crate::proxy::providers::generic::forward(&state, &route, path, &headers, body)
+                .await;
+        }
+    }
+
     let is_codex_style = body.get("input").is_some() || body.get("instructions").is_some();
 
     // 1. Convert Payload to Messages (Shared Chat Format)
@@ -1133,8 +1302,6 @@ pub async fn handle_completions(
     let upstream = state.upstream.clone();
     let token_manager = state.token_manager;
     let pool_size = token_manager.len();
-    // [FIX] Ensure max_attempts is at least 2 to allow for internal retries
-    let max_attempts = MAX_RETRY_ATTEMPTS.min(pool_size.saturating_add(1)).max(2);
 
     let mut last_error = String::new();
     let mut last_email: Option<String> = None;
@@ -1144,9 +1311,17 @@ pub async fn handle_completions(
         &openai_req.model,
         &*state.custom_mapping.read().await,
     );
-    let trace_id = format!("req_{}", chrono::Utc::now().timestamp_subsec_millis());
+    let trace_id = crate::proxy::script_hooks::current_trace_id()
+        .unwrap_or_else(|| format!("req_{}", chrono::Utc::now().timestamp_subsec_millis()));
+    // [FIX] Ensure max_attempts is at least 2 to allow for internal retries
+    let mut dispatcher = Dispatcher::new(pool_size.saturating_add(1), 2, &trace_id);
+    let max_attempts = dispatcher.max_attempts();
 
     for attempt in 0..max_attempts {
+        if dispatcher.deadline_exceeded(attempt) {
+            break;
+        }
+
         // 3. 模型配置解析
         // 将 OpenAI 工具转为 Value 数组以便探测联网
         let tools_val: Option<Vec<Value>> = openai_req
@@ -1168,8 +1343,8 @@ pub async fn handle_completions(
         let session_id_str = SessionManager::extract_openai_session_id(&openai_req);
         let session_id = Some(session_id_str.as_str());
 
-        // 重试时强制轮换，除非只是简单的网络抖动但 Claude 逻辑里 attempt > 0 总是 force_rotate
-        let force_rotate = attempt > 0;
+        // 重试时强制轮换，除非上一次失败的状态码被判定为无需轮换
+        let force_rotate = dispatcher.force_rotate(attempt);
 
         let (access_token, project_id, email, account_id, _wait_ms) = match token_manager
             .get_token(
@@ -1200,9 +1375,49 @@ pub async fn handle_completions(
         info!("✓ Using account: {} (type: {})", email, config.request_type);
 
         let proxy_token = token_manager.get_token_by_id(&account_id);
-        let (gemini_body, session_id, message_count) =
+        let (mut gemini_body, session_id, message_count) =
             transform_openai_request(&openai_req, &project_id, &mapped_model, proxy_token.as_ref());
 
+        // 上下文窗口策略 (按用户令牌 / 客户端选择)
+        crate::proxy::context_window::apply_current(
+            &mut gemini_body,
+            &mapped_model,
+            SummaryUpstream {
+                upstream: &upstream,
+                access_token: &access_token,
+                project_id: &project_id,
+                account_id: &account_id,
+            },
+            &trace_id,
+        )
+        .await;
+
+        // 思考策略 (按模型家族 / 调用方选择)
+        crate::proxy::thinking_policy::apply_current(&mut gemini_body, &mapped_model, &trace_id);
+
+        // 脚本钩子 (upstream_request 阶段)
+        if let Err(e) = crate::proxy::script_hooks::apply_upstream(
+            crate::proxy::ProviderProtocol::Openai,
+            &mut gemini_body,
+            &mapped_model,
+            &trace_id,
+        )
+        .await
+        {
+            return crate::proxy::script_hooks::reject_response(
+                crate::proxy::ProviderProtocol::Openai,
+                &e,
+            );
+        }
+
+        // 按实际发往上游的请求估算，用于校准器学习
+        let prompt_estimate = gemini_body.get("request").map(|request| {
+            (
+                mapped_model.clone(),
+                ContextManager::estimate_gemini_input_tokens(request, &mapped_model),
+            )
+        });
+
         // [New] 打印转换后的报文 (Gemini Body) 供调试 (Codex 路径) ———— 缩减为 simple debug
         debug!(
             "[Codex-Request] Transformed Gemini Body ({} parts)",
@@ -1224,13 +1439,16 @@ pub async fn handle_completions(
         };
         let query_string = if list_response { Some("alt=sse") } else { None };
 
-        let call_result = match upstream
-            .call_v1_internal(
-                method,
-                &access_token,
-                gemini_body,
-                query_string,
-                Some(account_id.as_str()),
+        let call_result = match dispatcher
+            .within_deadline(
+                upstream.call_v1_internal(
+                    method,
+                    &access_token,
+                    gemini_body,
+                    query_string,
+                    Some(account_id.as_str()),
+                ),
+                |e| e,
             )
             .await
         {
@@ -1281,6 +1499,7 @@ pub async fn handle_completions(
                             openai_req.model.clone(),
                             session_id,
                             message_count,
+                            prompt_estimate.clone(),
                         )
                     };
 
@@ -1360,6 +1579,7 @@ pub async fn handle_completions(
                         openai_req.model.clone(),
                         session_id,
                         message_count,
+                        prompt_estimate.clone(),
                     );
 
                     // Peek Logic (Repeated for safety/correctness on this stream type)
@@ -1475,7 +1695,12 @@ pub async fn handle_completions(
                 }
             };
 
-            let chat_resp = transform_openai_response(&gemini_resp, Some("session-123"), 1);
+            let chat_resp = transform_openai_response(
+                &gemini_resp,
+                Some("session-123"),
+                1,
+                prompt_estimate.as_ref(),
+            );
 
             // Map Chat Response -> Legacy Completions Response
             let choices = chat_resp.choices.iter().map(|c| {
@@ -1542,11 +1767,9 @@ pub async fn handle_completions(
                 .await;
         }
 
-        // 确定重试策略
-        let strategy = determine_retry_strategy(status_code, &error_text, false);
-
-        if apply_retry_strategy(strategy, attempt, max_attempts, status_code, &trace_id).await {
-            // 继续重试 (loop 会增加 attempt, 导致 force_rotate=true)
+        // 确定重试策略并执行退避
+        if dispatcher.retry(attempt, status_code, &error_text, false).await {
+            // 继续重试 (下一次尝试由 Dispatcher 决定是否轮换账号)
             continue;
         } else {
             // 不可重试
@@ -1581,21 +1804,10 @@ pub async fn handle_completions(
 }
 
 pub async fn handle_list_models(State(state): State<AppState>) -> impl IntoResponse {
-    use crate::proxy::common::model_mapping::get_all_dynamic_models;
-
-    let model_ids = get_all_dynamic_models(&state.custom_mapping, Some(&state.token_manager)).await;
-
-    let data: Vec<_> = model_ids
-        .into_iter()
-        .map(|id| {
-            json!({
-                "id": id,
-                "object": "model",
-                "created": 1706745600,
-                "owned_by": "antigravity"
-            })
-        })
-        .collect();
+    use crate::proxy::common::model_info::list_model_info;
+
+    let models = list_model_info(&state.custom_mapping, Some(&state.token_manager)).await;
+    let data: Vec<_> = models.iter().map(|m| m.to_openai_json()).collect();
 
     Json(json!({
         "object": "list",
@@ -1830,9 +2042,6 @@ pub async fn handle_images_generations_internal(
     let upstream = state.upstream.clone();
     let token_manager = state.token_manager.clone();
     let max_pool_size = token_manager.len();
-    let max_attempts = MAX_RETRY_ATTEMPTS
-        .min(max_pool_size.saturating_add(1))
-        .max(2);
 
     let mut tasks = Vec::new();
 
@@ -1845,106 +2054,35 @@ pub async fn handle_images_generations_internal(
 
         let model_to_use = clean_model_name.clone();
 
-        tasks.push(tokio::spawn(async move {
-            let mut last_error = String::new();
-
-            for attempt in 0..max_attempts {
-                // 4.1 获取 Token
-                let (access_token, project_id, email, account_id, _wait_ms) = match token_manager
-                    .get_token("image_gen", attempt > 0, None, &model_to_use)
-                    .await
-                {
-                    Ok(t) => t,
-                    Err(e) => {
-                        last_error = format!("Token error: {}", e);
-                        if attempt < max_attempts - 1 {
-                            tokio::time::sleep(Duration::from_millis(500)).await;
-                            continue;
-                        }
-                        break;
-                    }
-                };
-
-                let gemini_body = json!({
-                    "project": project_id,
-                    "requestId": format!("agent-{}", uuid::Uuid::new_v4()),
-                    "model": model_to_use,
-                    "userAgent": "antigravity",
-                    "requestType": "image_gen",
-                    "request": {
-                        "contents": [{
-                            "role": "user",
-                            "parts": [{"text": final_prompt}]
-                        }],
-                        "generationConfig": {
-                            "candidateCount": 1, // 强制单张
-                            "imageConfig": image_config // ✅ 使用完整配置（包含 aspectRatio 和 imageSize）
-                        },
-                        "safetySettings": [
-                            { "category": "HARM_CATEGORY_HARASSMENT", "threshold": "OFF" },
-                            { "category": "HARM_CATEGORY_HATE_SPEECH", "threshold": "OFF" },
-                            { "category": "HARM_CATEGORY_SEXUALLY_EXPLICIT", "threshold": "OFF" },
-                            { "category": "HARM_CATEGORY_DANGEROUS_CONTENT", "threshold": "OFF" },
-                            { "category": "HARM_CATEGORY_CIVIC_INTEGRITY", "threshold": "OFF" },
-                        ]
-                    }
-                });
-
-                match upstream
-                    .call_v1_internal(
-                        "generateContent",
-                        &access_token,
-                        gemini_body,
-                        None,
-                        Some(account_id.as_str()),
-                    )
-                    .await
-                {
-                    Ok(call_result) => {
-                        let response = call_result.response;
-                        let status = response.status();
-                        if !status.is_success() {
-                            let err_text = response.text().await.unwrap_or_default();
-                            let status_code = status.as_u16();
-                            last_error = format!("Upstream error {}: {}", status, err_text);
-
-                            // 429/500/503 等错误进行标记和重试
-                            if status_code == 429 || status_code == 503 || status_code == 500 {
-                                tracing::warn!(
-                                    "[Images] Account {} rate limited/error ({}), rotating...",
-                                    email,
-                                    status_code
-                                );
-                                token_manager
-                                    .mark_rate_limited_async(
-                                        &email,
-                                        status_code,
-                                        None,
-                                        &err_text,
-                                        Some("dall-e-3"),
-                                    )
-                                    .await;
-                                continue; // Retry loop
-                            }
-
-                            // 其他错误直接返回
-                            return Err(last_error);
-                        }
-                        match response.json::<Value>().await {
-                            Ok(json) => return Ok((json, email)),
-                            Err(e) => return Err(format!("Parse error: {}", e)),
-                        }
-                    }
-                    Err(e) => {
-                        last_error = format!("Network error: {}", e);
-                        continue;
-                    }
-                }
-            }
-
-            // All attempts failed
-            Err(format!("Max retries exhausted. Last error: {}", last_error))
-        }));
+        let overrides = crate::proxy::routing::current_overrides();
+        tasks.push(tokio::spawn(crate::proxy::routing::with_overrides(overrides, async move {
+            let request = json!({
+                "contents": [{
+                    "role": "user",
+                    "parts": [{"text": final_prompt}]
+                }],
+                "generationConfig": {
+                    "candidateCount": 1, // 强制单张
+                    "imageConfig": image_config // ✅ 使用完整配置（包含 aspectRatio 和 imageSize）
+                },
+                "safetySettings": [
+                    { "category": "HARM_CATEGORY_HARASSMENT", "threshold": "OFF" },
+                    { "category": "HARM_CATEGORY_HATE_SPEECH", "threshold": "OFF" },
+                    { "category": "HARM_CATEGORY_SEXUALLY_EXPLICIT", "threshold": "OFF" },
+                    { "category": "HARM_CATEGORY_DANGEROUS_CONTENT", "threshold": "OFF" },
+                    { "category": "HARM_CATEGORY_CIVIC_INTEGRITY", "threshold": "OFF" },
+                ]
+            });
+            let target = ImageGenTarget {
+                upstream: &upstream,
+                token_manager: &token_manager,
+                token_model: &model_to_use,
+                model: &model_to_use,
+                request_id_prefix: "agent",
+                request: &request,
+            };
+            dispatch_image_gen(target, max_pool_size).await
+        })));
     }
 
     // 5. 收集结果
@@ -2055,6 +2193,139 @@ pub async fn handle_images_generations_internal(
     Ok((email_header, openai_response))
 }
 
+/// 单次图像生成请求的目标与请求体
+#[derive(Clone, Copy)]
+struct ImageGenTarget<'a> {
+    upstream: &'a crate::proxy::upstream::client::UpstreamClient,
+    token_manager: &'a crate::proxy::TokenManager,
+    /// 选择账号时使用的模型 (配额维度)
+    token_model: &'a str,
+    /// 请求体中的上游模型
+    model: &'a str,
+    request_id_prefix: &'a str,
+    /// v1internal 请求中的 `request` 字段
+    request: &'a Value,
+}
+
+/// 单次图像生成尝试的失败原因
+enum ImageAttemptError {
+    Token(String),
+    Upstream { status: u16, message: String },
+    Network(String),
+    Parse(String),
+}
+
+/// 获取 token 并发起一次图像生成请求
+async fn image_gen_attempt(
+    target: ImageGenTarget<'_>,
+    force_rotate: bool,
+) -> Result<(Value, String), ImageAttemptError> {
+    let (access_token, project_id, email, account_id, _wait_ms) = target
+        .token_manager
+        .get_token("image_gen", force_rotate, None, target.token_model)
+        .await
+        .map_err(ImageAttemptError::Token)?;
+
+    let gemini_body = json!({
+        "project": project_id,
+        "requestId": format!("{}-{}", target.request_id_prefix, uuid::Uuid::new_v4()),
+        "model": target.model,
+        "userAgent": "antigravity",
+        "requestType": "image_gen",
+        "request": target.request,
+    });
+
+    let call_result = target
+        .upstream
+        .call_v1_internal(
+            "generateContent",
+            &access_token,
+            gemini_body,
+            None,
+            Some(account_id.as_str()),
+        )
+        .await
+        .map_err(ImageAttemptError::Network)?;
+
+    let response = call_result.response;
+    let status = response.status();
+    if !status.is_success() {
+        let err_text = response.text().await.unwrap_or_default();
+        let status_code = status.as_u16();
+
+        // 429/500/503 等错误进行标记 (用于 UI 显示与调度)
+        if status_code == 429 || status_code == 503 || status_code == 500 {
+            tracing::warn!(
+                "[Images] Account {} rate limited/error ({}), rotating...",
+                email,
+                status_code
+            );
+            target
+                .token_manager
+                .mark_rate_limited_async(&email, status_code, None, &err_text, Some("dall-e-3"))
+                .await;
+        }
+        return Err(ImageAttemptError::Upstream {
+            status: status_code,
+            message: format!("Upstream error {}: {}", status, err_text),
+        });
+    }
+
+    match response.json::<Value>().await {
+        Ok(json) => Ok((json, email)),
+        Err(e) => Err(ImageAttemptError::Parse(format!("Parse error: {}", e))),
+    }
+}
+
+/// 通过 Dispatcher 执行单张图像的生成 (重试/总时限/对冲)
+async fn dispatch_image_gen(
+    target: ImageGenTarget<'_>,
+    pool_size: usize,
+) -> Result<(Value, String), String> {
+    let mut dispatcher = Dispatcher::new(pool_size.saturating_add(1), 2, "Images");
+    let max_attempts = dispatcher.max_attempts();
+    let mut last_error = String::new();
+
+    for attempt in 0..max_attempts {
+        if dispatcher.deadline_exceeded(attempt) {
+            break;
+        }
+        let force_rotate = dispatcher.force_rotate(attempt);
+        let attempt_result =
+            dispatcher.hedged(force_rotate, |force| image_gen_attempt(target, force));
+        match dispatcher
+            .within_deadline(attempt_result, ImageAttemptError::Network)
+            .await
+        {
+            Ok(result) => return Ok(result),
+            Err(ImageAttemptError::Token(e)) => {
+                last_error = format!("Token error: {}", e);
+                if !dispatcher.retry_transient(attempt).await {
+                    break;
+                }
+            }
+            Err(ImageAttemptError::Upstream { status, message }) => {
+                let retry = dispatcher.retry(attempt, status, &message, false).await;
+                last_error = message;
+                if !retry {
+                    // 不可重试的错误直接返回
+                    return Err(last_error);
+                }
+            }
+            Err(ImageAttemptError::Network(e)) => {
+                last_error = format!("Network error: {}", e);
+                if !dispatcher.retry_transient(attempt).await {
+                    break;
+                }
+            }
+            Err(ImageAttemptError::Parse(e)) => return Err(e),
+        }
+    }
+
+    // All attempts failed
+    Err(format!("Max retries exhausted. Last error: {}", last_error))
+}
+
 pub async fn handle_images_edits(
     State(state): State<AppState>,
     mut multipart: axum::extract::Multipart,
@@ -2227,9 +2498,6 @@ pub async fn handle_images_edits(
     let upstream = state.upstream.clone();
     let token_manager = state.token_manager.clone();
     let max_pool_size = token_manager.len();
-    let max_attempts = MAX_RETRY_ATTEMPTS
-        .min(max_pool_size.saturating_add(1))
-        .max(2);
 
     let mut tasks = Vec::new();
     for _ in 0..n {
@@ -2240,108 +2508,42 @@ pub async fn handle_images_edits(
         let response_format = response_format.clone();
         let model = model.clone();
 
-        tasks.push(tokio::spawn(async move {
-            let mut last_error = String::new();
-
-            for attempt in 0..max_attempts {
-                // 4.1 获取 Token
-                let (access_token, project_id, email, account_id, _wait_ms) = match token_manager
-                    .get_token("image_gen", attempt > 0, None, "gemini-3-pro-image")
-                    .await
-                {
-                    Ok(t) => t,
-                    Err(e) => {
-                        last_error = format!("Token error: {}", e);
-                        if attempt < max_attempts - 1 {
-                            tokio::time::sleep(Duration::from_millis(500)).await;
-                            continue;
-                        }
-                        break;
-                    }
-                };
-
-                // 4.2 Construct Request Body (Need project_id)
-                let gemini_body = json!({
-                    "project": project_id,
-                    "requestId": format!("img-edit-{}", uuid::Uuid::new_v4()),
-                    "model": model,
-                    "userAgent": "antigravity",
-                    "requestType": "image_gen",
-                    "request": {
-                        "contents": [{
-                            "role": "user",
-                            "parts": contents_parts
-                        }],
-                        "generationConfig": {
-                            "candidateCount": 1,
-                            "imageConfig": image_config,
-                            "maxOutputTokens": 8192,
-                            "stopSequences": [],
-                            "temperature": 1.0,
-                            "topP": 0.95,
-                            "topK": 40
-                        },
-                        "safetySettings": [
-                            { "category": "HARM_CATEGORY_HARASSMENT", "threshold": "OFF" },
-                            { "category": "HARM_CATEGORY_HATE_SPEECH", "threshold": "OFF" },
-                            { "category": "HARM_CATEGORY_SEXUALLY_EXPLICIT", "threshold": "OFF" },
-                            { "category": "HARM_CATEGORY_DANGEROUS_CONTENT", "threshold": "OFF" },
-                            { "category": "HARM_CATEGORY_CIVIC_INTEGRITY", "threshold": "OFF" },
-                        ]
-                    }
-                });
-
-                match upstream
-                    .call_v1_internal(
-                        "generateContent",
-                        &access_token,
-                        gemini_body,
-                        None,
-                        Some(account_id.as_str()),
-                    )
-                    .await
-                {
-                    Ok(call_result) => {
-                        let response = call_result.response;
-                        let status = response.status();
-                        if !status.is_success() {
-                            let err_text = response.text().await.unwrap_or_default();
-                            let status_code = status.as_u16();
-                            last_error = format!("Upstream error {}: {}", status, err_text);
-
-                            // 429/500/503 等错误进行标记和重试
-                            if status_code == 429 || status_code == 503 || status_code == 500 {
-                                tracing::warn!(
-                                    "[Images] Account {} rate limited/error ({}), rotating...",
-                                    email,
-                                    status_code
-                                );
-                                token_manager
-                                    .mark_rate_limited_async(
-                                        &email,
-                                        status_code,
-                                        None,
-                                        &err_text,
-                                        Some("dall-e-3"),
-                                    )
-                                    .await;
-                                continue; // Retry loop
-                            }
-                            return Err(last_error);
-                        }
-                        match response.json::<Value>().await {
-                            Ok(json) => return Ok((json, response_format.clone(), email)),
-                            Err(e) => return Err(format!("Parse error: {}", e)),
-                        }
-                    }
-                    Err(e) => {
-                        last_error = format!("Network error: {}", e);
-                        continue;
-                    }
-                }
-            }
-            Err(format!("Max retries exhausted. Last error: {}", last_error))
-        }));
+        let overrides = crate::proxy::routing::current_overrides();
+        tasks.push(tokio::spawn(crate::proxy::routing::with_overrides(overrides, async move {
+            let request = json!({
+                "contents": [{
+                    "role": "user",
+                    "parts": contents_parts
+                }],
+                "generationConfig": {
+                    "candidateCount": 1,
+                    "imageConfig": image_config,
+                    "maxOutputTokens": 8192,
+                    "stopSequences": [],
+                    "temperature": 1.0,
+                    "topP": 0.95,
+                    "topK": 40
+                },
+                "safetySettings": [
+                    { "category": "HARM_CATEGORY_HARASSMENT", "threshold": "OFF" },
+                    { "category": "HARM_CATEGORY_HATE_SPEECH", "threshold": "OFF" },
+                    { "category": "HARM_CATEGORY_SEXUALLY_EXPLICIT", "threshold": "OFF" },
+                    { "category": "HARM_CATEGORY_DANGEROUS_CONTENT", "threshold": "OFF" },
+                    { "category": "HARM_CATEGORY_CIVIC_INTEGRITY", "threshold": "OFF" },
+                ]
+            });
+            let target = ImageGenTarget {
+                upstream: &upstream,
+                token_manager: &token_manager,
+                token_model: "gemini-3-pro-image",
+                model: &model,
+                request_id_prefix: "img-edit",
+                request: &request,
+            };
+            dispatch_image_gen(target, max_pool_size)
+                .await
+                .map(|(json, email)| (json, response_format, email))
+        })));
     }
 
     // 5. Collect Results
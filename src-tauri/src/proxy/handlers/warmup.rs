@@ -258,6 +258,7 @@ pub async fn handle_warmup(
                 output_tokens: Some(0),
                 protocol: Some("warmup".to_string()),
                 username: None,
+                reasoning_tokens: None,
             };
             state.monitor.log_request(log).await;
 
@@ -352,6 +353,7 @@ pub async fn handle_warmup(
                 output_tokens: None,
                 protocol: Some("warmup".to_string()),
                 username: None,
+                reasoning_tokens: None,
             };
             state.monitor.log_request(log).await;
 
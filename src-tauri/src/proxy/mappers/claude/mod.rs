@@ -16,6 +16,7 @@ pub use streaming::{PartProcessor, StreamingState};
 pub use thinking_utils::{close_tool_loop_for_thinking, filter_invalid_thinking_blocks_with_family};
 pub use collector::collect_stream_to_json;
 use crate::proxy::common::client_adapter::ClientAdapter; // [NEW]
+use crate::proxy::mappers::tokenizer::TokenBreakdown;
 
 use bytes::Bytes;
 use futures::Stream;
@@ -29,7 +30,7 @@ pub fn create_claude_sse_stream<S, E>(
     session_id: Option<String>, // [NEW v3.3.17] Session ID for signature caching
     scaling_enabled: bool, // [NEW] Flag for context usage scaling
     context_limit: u32,
-    estimated_prompt_tokens: Option<u32>, // [FIX] Estimated tokens for calibrator learning
+    prompt_estimate: Option<(String, TokenBreakdown)>, // [FIX] Mapped model + estimated tokens for calibrator learning
     message_count: usize, // [NEW v4.0.0] Message count for rewind detection
     client_adapter: Option<std::sync::Arc<dyn ClientAdapter>>, // [NEW] Adapter reference
     registered_tool_names: Vec<String>, // [FIX #MCP] Tool names for fuzzy matching
@@ -48,7 +49,7 @@ where
         state.message_count = message_count; // [NEW v4.0.0] Set message count
         state.scaling_enabled = scaling_enabled; // Set scaling enabled flag
         state.context_limit = context_limit;
-        state.estimated_prompt_tokens = estimated_prompt_tokens; // [FIX] Pass estimated tokens
+        state.prompt_estimate = prompt_estimate; // [FIX] Pass estimated tokens
         state.set_client_adapter(client_adapter); // [NEW] Set adapter
         state.set_registered_tool_names(registered_tool_names); // [FIX #MCP] Set tool names
         let mut buffer = BytesMut::new();
@@ -3,8 +3,10 @@
 
 use super::models::*;
 use crate::proxy::mappers::signature_store::get_thought_signature; // Deprecated, kept for fallback
+use crate::proxy::config::SystemPromptPosition;
 use crate::proxy::mappers::tool_result_compressor;
 use crate::proxy::session_manager::SessionManager;
+use crate::proxy::system_prompt::current_prompts;
 use serde_json::{json, Value};
 use std::collections::HashMap;
 
@@ -810,7 +812,7 @@ fn has_valid_signature_for_function_calls(
 /// 构建 System Instruction (支持动态身份映射与 Prompt 隔离)
 fn build_system_instruction(
     system: &Option<SystemPrompt>,
-    _model_name: &str,
+    model_name: &str,
     has_mcp_tools: bool,
 ) -> Option<Value> {
     let mut parts = Vec::new();
@@ -852,6 +854,11 @@ fn build_system_instruction(
         parts.push(json!({"text": global_prompt_config.content}));
     }
 
+    // 按条件注入的提示词片段 (客户端系统提示词之前)
+    for text in current_prompts("anthropic", model_name, SystemPromptPosition::Prepend) {
+        parts.push(json!({ "text": text }));
+    }
+
     // 添加用户的系统提示词
     if let Some(sys) = system {
         match sys {
@@ -871,6 +878,11 @@ fn build_system_instruction(
         }
     }
 
+    // 按条件注入的提示词片段 (客户端系统提示词之后)
+    for text in current_prompts("anthropic", model_name, SystemPromptPosition::Append) {
+        parts.push(json!({ "text": text }));
+    }
+
     // [NEW] MCP XML Bridge: 如果存在 mcp__ 开头的工具，注入专用的调用协议
     // 这能有效规避部分 MCP 链路在标准的 tool_use 协议下解析不稳的问题
     if has_mcp_tools {
@@ -1285,7 +1297,14 @@ fn build_contents(
                         // 使用智能压缩策略(浏览器快照、大文件提示等)
                         let mut compacted_content = content.clone();
                         if let Some(blocks) = compacted_content.as_array_mut() {
-                            tool_result_compressor::sanitize_tool_result_blocks(blocks);
+                            tool_result_compressor::sanitize_tool_result_blocks(blocks, &func_name);
+                        } else if let Some(text) = compacted_content.as_str() {
+                            // 字符串结果仅在命中配置的压缩策略时处理
+                            if let Some(compacted) =
+                                tool_result_compressor::compact_tool_result_string(text, &func_name)
+                            {
+                                compacted_content = serde_json::Value::String(compacted);
+                            }
                         }
 
                         // Smart Truncation: No longer stripping images from Tool Results
@@ -1706,7 +1725,10 @@ fn build_tools(
                     "type": "object",
                     "properties": {}
                 }));
-                crate::proxy::common::json_schema::clean_json_schema(&mut input_schema);
+                crate::proxy::common::json_schema::clean_json_schema_for_tool(
+                    &mut input_schema,
+                    name,
+                );
 
                 function_declarations.push(json!({
                     "name": name,
@@ -2895,6 +2917,7 @@ mod tests {
             mode: crate::proxy::config::ThinkingBudgetMode::Adaptive,
             custom_value: 0,
             effort: Some("high".to_string()),
+            ..Default::default()
         };
         crate::proxy::config::update_thinking_budget_config(config);
 
@@ -297,6 +297,7 @@ impl NonStreamingProcessor {
             // [FIX] Remap args for Gemini → Claude compatibility
             let mut args = fc.args.clone().unwrap_or(serde_json::json!({}));
             remap_function_call_args(&tool_name, &mut args);
+            crate::proxy::common::json_schema::rewrite_tool_call_args(&tool_name, &mut args);
 
             let mut tool_use = ContentBlock::ToolUse {
                 id: tool_id,
@@ -4,6 +4,7 @@
 use super::models::*;
 use super::utils::to_claude_usage;
 use crate::proxy::mappers::estimation_calibrator::get_calibrator;
+use crate::proxy::mappers::tokenizer::TokenBreakdown;
 // use crate::proxy::mappers::signature_store::store_thought_signature; // Deprecated
 use crate::proxy::SignatureCache;
 use crate::proxy::common::client_adapter::{ClientAdapter, SignatureBufferStrategy}; // [NEW]
@@ -225,8 +226,8 @@ pub struct StreamingState {
     // [NEW] MCP XML Bridge 缓冲区
     pub mcp_xml_buffer: String,
     pub in_mcp_xml: bool,
-    // [FIX] Estimated prompt tokens for calibrator learning
-    pub estimated_prompt_tokens: Option<u32>,
+    // [FIX] Mapped model + estimated prompt tokens for calibrator learning
+    pub prompt_estimate: Option<(String, TokenBreakdown)>,
     // [FIX #859] Post-thinking interruption tracking
     pub has_thinking: bool,
     pub has_content: bool,
@@ -257,7 +258,7 @@ impl StreamingState {
             context_limit: 1_048_576, // Default to 1M
             mcp_xml_buffer: String::new(),
             in_mcp_xml: false,
-            estimated_prompt_tokens: None,
+            prompt_estimate: None,
             has_thinking: false,
             has_content: false,
             message_count: 0,
@@ -494,11 +495,12 @@ impl StreamingState {
             .map(|u| {
                 // [FIX] Record actual token usage for calibrator learning
                 // Now properly pairs estimated tokens from request with actual tokens from response
-                if let (Some(estimated), Some(actual)) =
-                    (self.estimated_prompt_tokens, u.prompt_token_count)
+                if let (Some((model, breakdown)), Some(actual)) =
+                    (&self.prompt_estimate, u.prompt_token_count)
                 {
+                    let estimated = breakdown.total();
                     if estimated > 0 && actual > 0 {
-                        get_calibrator().record(estimated, actual);
+                        get_calibrator().record_breakdown(model, breakdown, actual);
                         tracing::debug!(
                             "[Calibrator] Recorded: estimated={}, actual={}, ratio={:.2}x",
                             estimated,
@@ -1054,6 +1056,10 @@ impl<'a> PartProcessor<'a> {
                 final_tool_name = "Grep".to_string();
             }
             remap_function_call_args(&final_tool_name, &mut remapped_args);
+            crate::proxy::common::json_schema::rewrite_tool_call_args(
+                &final_tool_name,
+                &mut remapped_args,
+            );
 
             let json_str =
                 serde_json::to_string(&remapped_args).unwrap_or_else(|_| "{}".to_string());
@@ -4,36 +4,136 @@
 //! to prevent "Prompt is too long" errors and avoid invalid signatures.
 
 use super::claude::models::{ClaudeRequest, ContentBlock, Message, MessageContent, SystemPrompt};
+use super::estimation_calibrator::get_calibrator;
+use super::tokenizer::{ContentKind, TokenBreakdown, TokenCounter};
+use base64::Engine as _;
+use serde_json::Value;
 use tracing::{debug, info};
 
-/// Helper to estimate tokens from text with multi-language awareness
-///
-/// Improved estimation algorithm:
-/// - ASCII/English: ~4 characters per token
-/// - Unicode/CJK: ~1.5 characters per token (Chinese, Japanese, Korean are tokenized differently)
-/// - Adds 15% safety margin to prevent underestimation
-fn estimate_tokens_from_str(s: &str) -> u32 {
-    if s.is_empty() {
-        return 0;
+/// Claude 单张图片的 token 上限 (约 1.15 MP / 750)
+const MAX_IMAGE_TOKENS: u32 = 1600;
+/// PDF 每页的估算 token (文本 + 页面图像)
+const PDF_TOKENS_PER_PAGE: u32 = 2500;
+/// 无法解析页数时按字节估算页数
+const PDF_BYTES_PER_PAGE: usize = 50_000;
+/// 解析图片尺寸时最多解码的字节数
+const IMAGE_HEADER_BYTES: usize = 64 * 1024;
+
+/// Input token estimate, split so that only the text part is calibrated
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InputTokenEstimate {
+    /// Text, tool schemas and tool calls by content type (needs calibration)
+    pub text: TokenBreakdown,
+    /// Images and documents (already in real token units)
+    pub media: u32,
+}
+
+impl InputTokenEstimate {
+    /// Apply the model's calibration factors to the text part
+    pub fn calibrated(&self, model: &str) -> u32 {
+        get_calibrator().calibrate_breakdown(model, &self.text) + self.media
     }
 
-    let mut ascii_chars = 0u32;
-    let mut unicode_chars = 0u32;
+    /// Feed the actual prompt tokens back to the calibrator (text part only)
+    pub fn record(&self, model: &str, actual: u32) {
+        get_calibrator().record_breakdown(model, &self.text, actual.saturating_sub(self.media));
+    }
+}
 
-    for c in s.chars() {
-        if c.is_ascii() {
-            ascii_chars += 1;
-        } else {
-            unicode_chars += 1;
-        }
+/// Count a document block: PDFs are media, text documents go through the counter
+fn count_document(counter: &mut TokenCounter, media_type: &str, data: &str) -> u32 {
+    if media_type == "application/pdf" {
+        return estimate_pdf_tokens(data);
     }
+    match base64::engine::general_purpose::STANDARD.decode(data) {
+        // 纯文本类文档按文本估算
+        Ok(bytes) => counter.add_text(&String::from_utf8_lossy(&bytes)),
+        Err(_) => counter.add_text(data),
+    };
+    0
+}
+
+/// Estimate image tokens with Claude's formula: `width * height / 750`
+///
+/// The image is scaled down to fit within 1568px on the long edge first.
+/// Falls back to the per-image maximum when dimensions can't be read.
+fn estimate_image_tokens(data: &str) -> u32 {
+    let Some((width, height)) = image_dimensions(data) else {
+        return MAX_IMAGE_TOKENS;
+    };
+    let (mut w, mut h) = (width as f64, height as f64);
+    let long_edge = w.max(h);
+    if long_edge > 1568.0 {
+        let scale = 1568.0 / long_edge;
+        w *= scale;
+        h *= scale;
+    }
+    ((w * h / 750.0).ceil() as u32).clamp(1, MAX_IMAGE_TOKENS)
+}
 
-    // ASCII: ~4 chars/token, Unicode/CJK: ~1.5 chars/token
-    let ascii_tokens = (ascii_chars as f32 / 4.0).ceil() as u32;
-    let unicode_tokens = (unicode_chars as f32 / 1.5).ceil() as u32;
+/// Read width/height from PNG, GIF or JPEG headers
+fn image_dimensions(data: &str) -> Option<(u32, u32)> {
+    // 只解码头部 (base64 按 4 字符对齐)
+    let prefix_len = (IMAGE_HEADER_BYTES / 3 * 4).min(data.len() / 4 * 4);
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(data.get(..prefix_len)?)
+        .ok()?;
+    let be16 = |i: usize| Some(u16::from_be_bytes([*bytes.get(i)?, *bytes.get(i + 1)?]) as u32);
+    let be32 = |i: usize| {
+        Some(u32::from_be_bytes([
+            *bytes.get(i)?,
+            *bytes.get(i + 1)?,
+            *bytes.get(i + 2)?,
+            *bytes.get(i + 3)?,
+        ]))
+    };
+
+    if bytes.starts_with(b"\x89PNG") {
+        return Some((be32(16)?, be32(20)?));
+    }
+    if bytes.starts_with(b"GIF8") {
+        let le16 = |i: usize| Some(u16::from_le_bytes([*bytes.get(i)?, *bytes.get(i + 1)?]) as u32);
+        return Some((le16(6)?, le16(8)?));
+    }
+    if bytes.starts_with(&[0xFF, 0xD8]) {
+        // 逐段扫描直到 SOF 段 (0xC0-0xCF，排除 DHT/JPG/DAC)
+        let mut i = 2;
+        while i + 9 < bytes.len() {
+            if bytes[i] != 0xFF {
+                return None;
+            }
+            let marker = bytes[i + 1];
+            if (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+                return Some((be16(i + 7)?, be16(i + 5)?));
+            }
+            i += 2 + be16(i + 2)? as usize;
+        }
+    }
+    None
+}
 
-    // Add 15% safety margin to account for tokenizer variations
-    ((ascii_tokens + unicode_tokens) as f32 * 1.15).ceil() as u32
+/// Estimate PDF tokens from its page count
+fn estimate_pdf_tokens(data: &str) -> u32 {
+    let Ok(bytes) = base64::engine::general_purpose::STANDARD.decode(data) else {
+        return (data.len() * 3 / 4 / PDF_BYTES_PER_PAGE).max(1) as u32 * PDF_TOKENS_PER_PAGE;
+    };
+    // 统计 `/Type /Page` (排除 `/Type /Pages`)
+    let pages = bytes
+        .windows(5)
+        .enumerate()
+        .filter(|(_, w)| *w == b"/Type")
+        .filter(|(i, _)| {
+            let rest = &bytes[i + 5..];
+            let skip = rest.iter().take_while(|b| b.is_ascii_whitespace()).count();
+            rest[skip..].starts_with(b"/Page") && rest.get(skip + 5) != Some(&b's')
+        })
+        .count();
+    let pages = if pages > 0 {
+        pages
+    } else {
+        (bytes.len() / PDF_BYTES_PER_PAGE).max(1)
+    };
+    pages as u32 * PDF_TOKENS_PER_PAGE
 }
 
 /// Strategy for context purification
@@ -107,15 +207,54 @@ impl ContextManager {
     /// This is a lightweight estimation, not a precise count.
     /// It iterates through all messages and blocks to sum up estimated tokens.
     pub fn estimate_token_usage(request: &ClaudeRequest) -> u32 {
-        let mut total = 0;
+        let mut total = Self::estimate_breakdown(request, &request.model).total();
+
+        // Thinking budget overhead if enabled
+        if let Some(thinking) = &request.thinking {
+            if let Some(budget) = thinking.budget_tokens {
+                // Reserve budget in estimation
+                total += budget;
+            }
+        }
+
+        total
+    }
+
+    /// Estimate token usage for `model`, returning `(raw, calibrated)`
+    ///
+    /// Both include the thinking budget; only the text part is calibrated.
+    pub fn estimate_calibrated_usage(request: &ClaudeRequest, model: &str) -> (u32, u32) {
+        let breakdown = Self::estimate_breakdown(request, model);
+        let budget = request
+            .thinking
+            .as_ref()
+            .and_then(|t| t.budget_tokens)
+            .unwrap_or(0);
+        (
+            breakdown.total() + budget,
+            get_calibrator().calibrate_breakdown(model, &breakdown) + budget,
+        )
+    }
 
+    /// Estimate text tokens by content type, using `model`'s tokenizer when loaded
+    ///
+    /// The thinking budget and media blocks are not included.
+    pub fn estimate_breakdown(request: &ClaudeRequest, model: &str) -> TokenBreakdown {
+        let mut counter = TokenCounter::for_model(model);
+        Self::count_request_text(request, &mut counter);
+        counter.breakdown
+    }
+
+    fn count_request_text(request: &ClaudeRequest, counter: &mut TokenCounter) {
         // System prompt
         if let Some(sys) = &request.system {
             match sys {
-                SystemPrompt::String(s) => total += estimate_tokens_from_str(s),
+                SystemPrompt::String(s) => {
+                    counter.add_text(s);
+                }
                 SystemPrompt::Array(blocks) => {
                     for block in blocks {
-                        total += estimate_tokens_from_str(&block.text);
+                        counter.add_text(&block.text);
                     }
                 }
             }
@@ -124,50 +263,50 @@ impl ContextManager {
         // Messages
         for msg in &request.messages {
             // Message overhead
-            total += 4;
+            counter.add_overhead(4);
 
             match &msg.content {
                 MessageContent::String(s) => {
-                    total += estimate_tokens_from_str(s);
+                    counter.add_text(s);
                 }
                 MessageContent::Array(blocks) => {
                     for block in blocks {
                         match block {
                             ContentBlock::Text { text } => {
-                                total += estimate_tokens_from_str(text);
+                                counter.add_text(text);
                             }
                             ContentBlock::Thinking { thinking, .. } => {
-                                total += estimate_tokens_from_str(thinking);
+                                counter.add_text(thinking);
                                 // Signature overhead
-                                total += 100;
+                                counter.add_overhead(100);
                             }
                             ContentBlock::RedactedThinking { data } => {
-                                total += estimate_tokens_from_str(data);
+                                counter.add(ContentKind::Structured, data);
                             }
                             ContentBlock::ToolUse { name, input, .. } => {
-                                total += 20; // Function call overhead
-                                total += estimate_tokens_from_str(name);
+                                counter.add_overhead(20); // Function call overhead
+                                counter.add(ContentKind::Structured, name);
                                 if let Ok(json_str) = serde_json::to_string(input) {
-                                    total += estimate_tokens_from_str(&json_str);
+                                    counter.add(ContentKind::Structured, &json_str);
                                 }
                             }
                             ContentBlock::ToolResult { content, .. } => {
-                                total += 10; // Result overhead
-                                             // content is serde_json::Value
+                                counter.add_overhead(10); // Result overhead
+                                                          // content is serde_json::Value
                                 if let Some(s) = content.as_str() {
-                                    total += estimate_tokens_from_str(s);
+                                    counter.add_text(s);
                                 } else if let Some(arr) = content.as_array() {
                                     for item in arr {
                                         if let Some(text) =
                                             item.get("text").and_then(|t| t.as_str())
                                         {
-                                            total += estimate_tokens_from_str(text);
+                                            counter.add_text(text);
                                         }
                                     }
                                 } else {
                                     // Fallback for objects or other types
                                     if let Ok(s) = serde_json::to_string(content) {
-                                        total += estimate_tokens_from_str(&s);
+                                        counter.add(ContentKind::Structured, &s);
                                     }
                                 }
                             }
@@ -182,20 +321,102 @@ impl ContextManager {
         if let Some(tools) = &request.tools {
             for tool in tools {
                 if let Ok(json_str) = serde_json::to_string(tool) {
-                    total += estimate_tokens_from_str(&json_str);
+                    counter.add(ContentKind::Structured, &json_str);
                 }
             }
         }
+    }
 
-        // Thinking budget overhead if enabled
-        if let Some(thinking) = &request.thinking {
-            if let Some(budget) = thinking.budget_tokens {
-                // Reserve budget in estimation
-                total += budget;
+    /// Estimate input tokens for `count_tokens`, including images and documents
+    ///
+    /// Unlike `estimate_token_usage`, the thinking budget is not included.
+    /// Text tokens are later scaled by the calibrator; media tokens are not.
+    pub fn estimate_input_tokens(request: &ClaudeRequest, model: &str) -> InputTokenEstimate {
+        let mut counter = TokenCounter::for_model(model);
+        Self::count_request_text(request, &mut counter);
+        let mut media = 0;
+
+        for msg in &request.messages {
+            let MessageContent::Array(blocks) = &msg.content else {
+                continue;
+            };
+            for block in blocks {
+                match block {
+                    ContentBlock::Image { source, .. } => {
+                        media += estimate_image_tokens(&source.data);
+                    }
+                    ContentBlock::Document { source, .. } => {
+                        media += count_document(&mut counter, &source.media_type, &source.data);
+                    }
+                    // 工具结果中的图片 (text 已在 count_request_text 中计入)
+                    ContentBlock::ToolResult { content, .. } => {
+                        for item in content.as_array().into_iter().flatten() {
+                            if item.get("type").and_then(|t| t.as_str()) == Some("image") {
+                                let data = item
+                                    .pointer("/source/data")
+                                    .and_then(|d| d.as_str())
+                                    .unwrap_or_default();
+                                media += estimate_image_tokens(data);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        InputTokenEstimate {
+            text: counter.breakdown,
+            media,
+        }
+    }
+
+    /// Estimate input tokens of a Gemini `generateContent` / `countTokens` body
+    pub fn estimate_gemini_input_tokens(body: &Value, model: &str) -> InputTokenEstimate {
+        let request = body.get("generateContentRequest").unwrap_or(body);
+        let mut counter = TokenCounter::for_model(model);
+        let mut media = 0;
+
+        let system_parts = request
+            .pointer("/systemInstruction/parts")
+            .and_then(|p| p.as_array());
+        let content_parts = request
+            .get("contents")
+            .and_then(|c| c.as_array())
+            .into_iter()
+            .flatten()
+            .flat_map(|content| {
+                counter.add_overhead(4); // Message overhead
+                content.get("parts").and_then(|p| p.as_array()).into_iter().flatten()
+            })
+            .collect::<Vec<_>>();
+
+        for part in system_parts.into_iter().flatten().chain(content_parts) {
+            if let Some(text) = part.get("text").and_then(|t| t.as_str()) {
+                counter.add_text(text);
+            } else if let Some(inline) = part.get("inlineData") {
+                let mime = inline.get("mimeType").and_then(|m| m.as_str()).unwrap_or_default();
+                let data = inline.get("data").and_then(|d| d.as_str()).unwrap_or_default();
+                if mime.starts_with("image/") {
+                    media += estimate_image_tokens(data);
+                } else {
+                    media += count_document(&mut counter, mime, data);
+                }
+            } else if part.get("fileData").is_some() {
+                media += MAX_IMAGE_TOKENS;
+            } else {
+                // functionCall / functionResponse 等按 JSON 文本估算
+                counter.add_overhead(10);
+                counter.add(ContentKind::Structured, &part.to_string());
             }
         }
 
-        total
+        if let Some(tools) = request.get("tools") {
+            counter.add(ContentKind::Structured, &tools.to_string());
+        }
+        InputTokenEstimate {
+            text: counter.breakdown,
+            media,
+        }
     }
 
     // ===== [Layer 2] Thinking Content Compression + Signature Preservation =====
@@ -574,4 +795,82 @@ mod tests {
             assert!(matches!(blocks[0], ContentBlock::Text { .. }));
         }
     }
+
+    fn b64(bytes: &[u8]) -> String {
+        use base64::Engine as _;
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    }
+
+    #[test]
+    fn test_estimate_input_tokens_with_media() {
+        use crate::proxy::mappers::claude::models::{DocumentSource, ImageSource};
+
+        // PNG 头: 1000 x 750 → 1000 tokens
+        let mut png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR".to_vec();
+        png.extend_from_slice(&1000u32.to_be_bytes());
+        png.extend_from_slice(&750u32.to_be_bytes());
+        png.extend_from_slice(&[8, 6, 0, 0, 0]);
+        let pdf = b"%PDF-1.4 /Type /Pages /Kids [] /Type /Page /Type/Page %%EOF";
+
+        let mut req = create_test_request();
+        req.thinking = Some(crate::proxy::mappers::claude::models::ThinkingConfig {
+            type_: "enabled".into(),
+            budget_tokens: Some(10_000),
+            effort: None,
+        });
+        req.messages = vec![Message {
+            role: "user".into(),
+            content: MessageContent::Array(vec![
+                ContentBlock::Text { text: "Describe".into() },
+                ContentBlock::Image {
+                    source: ImageSource {
+                        source_type: "base64".into(),
+                        media_type: "image/png".into(),
+                        data: b64(&png),
+                    },
+                    cache_control: None,
+                },
+                ContentBlock::Document {
+                    source: DocumentSource {
+                        source_type: "base64".into(),
+                        media_type: "application/pdf".into(),
+                        data: b64(pdf),
+                    },
+                    cache_control: None,
+                },
+            ]),
+        }];
+
+        let estimate = ContextManager::estimate_input_tokens(&req, &req.model);
+        assert_eq!(estimate.media, 1000 + 2 * PDF_TOKENS_PER_PAGE);
+        // 不包含 thinking 预算
+        assert!(estimate.text.total() < 20);
+        // 无法解析的图片按上限计
+        assert_eq!(estimate_image_tokens("not-an-image"), MAX_IMAGE_TOKENS);
+    }
+
+    #[test]
+    fn test_estimate_gemini_input_tokens() {
+        let body = serde_json::json!({
+            "generateContentRequest": {
+                "systemInstruction": {"parts": [{"text": "You are helpful"}]},
+                "contents": [
+                    {"role": "user", "parts": [
+                        {"text": "hello"},
+                        {"inlineData": {"mimeType": "image/jpeg", "data": "AAAA"}}
+                    ]},
+                    {"role": "model", "parts": [{"functionCall": {"name": "ls", "args": {}}}]}
+                ],
+                "tools": [{"functionDeclarations": [{"name": "ls", "parameters": {"type": "object"}}]}]
+            }
+        });
+        let estimate = ContextManager::estimate_gemini_input_tokens(&body, "gemini-3-flash");
+        assert_eq!(estimate.media, MAX_IMAGE_TOKENS);
+        assert!(estimate.text.total() > 8 + 10);
+        assert!(estimate.text.get(ContentKind::Structured) > 8 + 10);
+        assert_eq!(
+            ContextManager::estimate_gemini_input_tokens(&serde_json::json!({}), "gemini-3-flash"),
+            InputTokenEstimate::default()
+        );
+    }
 }
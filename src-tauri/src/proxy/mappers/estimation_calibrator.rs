@@ -2,10 +2,65 @@
 //!
 //! Learns from historical request/response pairs to improve token estimation accuracy.
 //! Uses actual token counts from Google API responses to calibrate future estimates.
+//!
+//! Besides the global factor (used for heuristic estimates), factors are also
+//! learned per model and per content type (prose / code / CJK / structured).
 
+use serde::Serialize;
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::RwLock;
-use tracing::info;
+use tracing::{debug, info};
+
+use super::tokenizer::{ContentKind, TokenBreakdown};
+
+/// Learning rate of the per-model factor update (normalized LMS)
+const MODEL_LEARNING_RATE: f32 = 0.3;
+/// Clamp range of per-model, per-content-type factors
+const MODEL_FACTOR_RANGE: (f32, f32) = (0.5, 4.0);
+
+/// Per-model calibration, one factor per content type
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelCalibration {
+    pub factors: HashMap<&'static str, f32>,
+    pub samples: u64,
+    /// Whether estimates for this model come from a real tokenizer
+    pub exact: bool,
+    #[serde(skip)]
+    raw: [f32; 4],
+}
+
+impl ModelCalibration {
+    fn new(initial: f32, exact: bool) -> Self {
+        let mut calibration = Self {
+            factors: HashMap::new(),
+            samples: 0,
+            exact,
+            raw: [initial; 4],
+        };
+        calibration.sync_factors();
+        calibration
+    }
+
+    fn sync_factors(&mut self) {
+        for kind in ContentKind::ALL {
+            let name = match kind {
+                ContentKind::Prose => "prose",
+                ContentKind::Code => "code",
+                ContentKind::Cjk => "cjk",
+                ContentKind::Structured => "structured",
+            };
+            self.factors.insert(name, self.raw[kind.index()]);
+        }
+    }
+
+    fn predict(&self, breakdown: &TokenBreakdown) -> f32 {
+        ContentKind::ALL
+            .iter()
+            .map(|kind| self.raw[kind.index()] * breakdown.get(*kind) as f32)
+            .sum()
+    }
+}
 
 /// Estimation Calibrator - learns estimation error from historical requests
 ///
@@ -20,11 +75,13 @@ pub struct EstimationCalibrator {
     sample_count: AtomicU64,
     /// Current calibration factor (estimated * factor ≈ actual)
     calibration_factor: RwLock<f32>,
+    /// Per-model, per-content-type factors
+    model_factors: RwLock<HashMap<String, ModelCalibration>>,
 }
 
 impl EstimationCalibrator {
     /// Create a new calibrator with default settings
-    pub const fn new() -> Self {
+    pub fn new() -> Self {
         Self {
             total_estimated: AtomicU64::new(0),
             total_actual: AtomicU64::new(0),
@@ -32,6 +89,7 @@ impl EstimationCalibrator {
             // Initial assumption: estimates are 2.0x lower than actual
             // This is conservative and will be adjusted based on real data
             calibration_factor: RwLock::new(2.0),
+            model_factors: RwLock::new(HashMap::new()),
         }
     }
 
@@ -97,6 +155,74 @@ impl EstimationCalibrator {
     pub fn get_factor(&self) -> f32 {
         self.calibration_factor.read().map(|f| *f).unwrap_or(2.0)
     }
+
+    /// Calibrate a per-content-type estimate for a specific model
+    ///
+    /// Uses the model's learned factors once available; otherwise tokenizer
+    /// counts are taken as-is and heuristic counts use the global factor.
+    pub fn calibrate_breakdown(&self, model: &str, breakdown: &TokenBreakdown) -> u32 {
+        let learned = self.model_factors.read().ok().and_then(|factors| {
+            factors
+                .get(model)
+                .filter(|c| c.samples > 0 && c.exact == breakdown.exact)
+                .map(|c| c.predict(breakdown))
+        });
+        match learned {
+            Some(predicted) => predicted.ceil() as u32,
+            None if breakdown.exact => breakdown.total(),
+            None => self.calibrate(breakdown.total()),
+        }
+    }
+
+    /// Record a per-content-type estimate against the actual prompt tokens of a model
+    pub fn record_breakdown(&self, model: &str, breakdown: &TokenBreakdown, actual: u32) {
+        let estimated = breakdown.total();
+        if estimated == 0 || actual == 0 {
+            return;
+        }
+        // 全局因子只用于启发式估算
+        if !breakdown.exact {
+            self.record(estimated, actual);
+        }
+
+        let initial = if breakdown.exact { 1.0 } else { self.get_factor() };
+        let Ok(mut factors) = self.model_factors.write() else {
+            return;
+        };
+        let calibration = factors
+            .entry(model.to_string())
+            .or_insert_with(|| ModelCalibration::new(initial, breakdown.exact));
+        // 估算方式变化 (加载/移除分词器) 时重新学习
+        if calibration.exact != breakdown.exact {
+            *calibration = ModelCalibration::new(initial, breakdown.exact);
+        }
+
+        // Normalized LMS: 按各内容类型的占比分摊误差
+        let error = actual as f32 - calibration.predict(breakdown);
+        let norm: f32 = breakdown.counts.iter().map(|c| (*c as f32).powi(2)).sum();
+        for kind in ContentKind::ALL {
+            let count = breakdown.get(kind) as f32;
+            if count > 0.0 {
+                let factor = &mut calibration.raw[kind.index()];
+                *factor = (*factor + MODEL_LEARNING_RATE * error * count / norm)
+                    .clamp(MODEL_FACTOR_RANGE.0, MODEL_FACTOR_RANGE.1);
+            }
+        }
+        calibration.samples += 1;
+        calibration.sync_factors();
+        debug!(
+            "[Calibrator] {} updated: estimated={}, actual={}, factors={:?}",
+            model, estimated, actual, calibration.raw
+        );
+    }
+
+    /// Snapshot of per-model calibration
+    pub fn model_factors(&self) -> HashMap<String, ModelCalibration> {
+        self.model_factors
+            .read()
+            .map(|factors| factors.clone())
+            .unwrap_or_default()
+    }
 }
 
 impl Default for EstimationCalibrator {
@@ -146,6 +272,34 @@ mod tests {
         assert_eq!(calibrated, 200);
     }
 
+    #[test]
+    fn test_breakdown_per_model_and_content_type() {
+        let calibrator = EstimationCalibrator::new();
+        let mut heuristic = TokenBreakdown::default();
+        heuristic.add(ContentKind::Prose, 100);
+        // 未学习前使用全局因子
+        assert_eq!(calibrator.calibrate_breakdown("gemini-3-flash", &heuristic), 200);
+
+        // 代码实际 token 明显偏多，散文接近估算
+        let mut code = TokenBreakdown::default();
+        code.add(ContentKind::Code, 100);
+        for _ in 0..30 {
+            calibrator.record_breakdown("gemini-3-flash", &heuristic, 110);
+            calibrator.record_breakdown("gemini-3-flash", &code, 250);
+        }
+        let prose_estimate = calibrator.calibrate_breakdown("gemini-3-flash", &heuristic);
+        let code_estimate = calibrator.calibrate_breakdown("gemini-3-flash", &code);
+        assert!((105..=120).contains(&prose_estimate), "{}", prose_estimate);
+        assert!((235..=260).contains(&code_estimate), "{}", code_estimate);
+        // 其他模型不受影响
+        assert_eq!(calibrator.calibrate_breakdown("claude-sonnet-4-6", &code), calibrator.calibrate(100));
+
+        // 分词器计数在未学习前原样返回
+        let exact = TokenBreakdown { counts: [40, 0, 0, 0], exact: true };
+        assert_eq!(calibrator.calibrate_breakdown("claude-sonnet-4-6", &exact), 40);
+        assert_eq!(calibrator.calibrate_breakdown("gemini-3-flash", &exact), 40);
+    }
+
     #[test]
     fn test_zero_handling() {
         let calibrator = EstimationCalibrator::new();
@@ -1,5 +1,28 @@
 // Gemini v1internal 包装/解包
 use serde_json::{json, Value};
+use crate::proxy::config::SystemPromptPosition;
+use crate::proxy::mappers::tool_result_compressor::compact_tool_result_string;
+use crate::proxy::system_prompt::current_prompts;
+
+/// 对 functionResponse.response 中的字符串字段应用工具结果压缩策略 (仅命中策略时生效)
+fn compact_function_response(fr: &mut Value) {
+    let name = fr
+        .get("name")
+        .and_then(|n| n.as_str())
+        .unwrap_or("unknown")
+        .to_string();
+    let Some(response) = fr.get_mut("response").and_then(|r| r.as_object_mut()) else {
+        return;
+    };
+    for value in response.values_mut() {
+        if let Some(compacted) = value
+            .as_str()
+            .and_then(|text| compact_tool_result_string(text, &name))
+        {
+            *value = Value::String(compacted);
+        }
+    }
+}
 
 /// 包装请求体为 v1internal 格式
 pub fn wrap_request(
@@ -84,6 +107,7 @@ pub fn wrap_request(
                                     .insert("id".to_string(), json!(call_id));
                                 tracing::debug!("[Gemini-Wrap] Request stage: Injected synced response_id '{}' for Claude model", call_id);
                             }
+                            compact_function_response(fr);
                         }
 
                         // 3. 处理 thoughtSignature
@@ -364,18 +388,26 @@ pub fn wrap_request(
                         for decl in decls_arr {
                             // 检测并转换字段名
                             if let Some(decl_obj) = decl.as_object_mut() {
+                                let name = decl_obj
+                                    .get("name")
+                                    .and_then(|v| v.as_str())
+                                    .unwrap_or_default()
+                                    .to_string();
                                 // 如果存在 parametersJsonSchema，将其重命名为 parameters
                                 if let Some(params_json_schema) =
                                     decl_obj.remove("parametersJsonSchema")
                                 {
                                     let mut params = params_json_schema;
-                                    crate::proxy::common::json_schema::clean_json_schema(
+                                    crate::proxy::common::json_schema::clean_json_schema_for_tool(
                                         &mut params,
+                                        &name,
                                     );
                                     decl_obj.insert("parameters".to_string(), params);
                                 } else if let Some(params) = decl_obj.get_mut("parameters") {
                                     // 标准 parameters 字段
-                                    crate::proxy::common::json_schema::clean_json_schema(params);
+                                    crate::proxy::common::json_schema::clean_json_schema_for_tool(
+                                        params, &name,
+                                    );
                                 }
                             }
                         }
@@ -484,6 +516,23 @@ pub fn wrap_request(
                             parts_array.push(json!({"text": global_prompt_config.content}));
                         }
                     }
+
+                    // 按条件注入的提示词片段：prepend 紧跟全局提示词，append 位于客户端指令之后
+                    let mut insert_pos = if global_prompt_config.enabled
+                        && !global_prompt_config.content.trim().is_empty()
+                    {
+                        2
+                    } else {
+                        1
+                    };
+                    for text in current_prompts("gemini", final_model_name, SystemPromptPosition::Prepend) {
+                        let pos = insert_pos.min(parts_array.len());
+                        parts_array.insert(pos, json!({ "text": text }));
+                        insert_pos = pos + 1;
+                    }
+                    for text in current_prompts("gemini", final_model_name, SystemPromptPosition::Append) {
+                        parts_array.push(json!({ "text": text }));
+                    }
                 }
             }
         } else {
@@ -494,6 +543,12 @@ pub fn wrap_request(
             if global_prompt_config.enabled && !global_prompt_config.content.trim().is_empty() {
                 parts.push(json!({"text": global_prompt_config.content}));
             }
+            // 按条件注入的提示词片段 (没有客户端指令，两种位置依次追加)
+            for position in [SystemPromptPosition::Prepend, SystemPromptPosition::Append] {
+                for text in current_prompts("gemini", final_model_name, position) {
+                    parts.push(json!({ "text": text }));
+                }
+            }
             inner_request["systemInstruction"] = json!({
                 "role": "user",
                 "parts": parts
@@ -823,6 +878,7 @@ mod tests {
             mode: ThinkingBudgetMode::Custom,
             custom_value: 1024, // Distinct value
             effort: None,
+            ..Default::default()
         });
 
         let body = json!({
@@ -871,6 +927,7 @@ mod tests {
                     mode: crate::proxy::config::ThinkingBudgetMode::Auto,
                     custom_value: 0,
                     effort: None,
+                    ..Default::default()
                 },
             );
 
@@ -920,6 +977,7 @@ mod tests {
                 mode: crate::proxy::config::ThinkingBudgetMode::Auto,
                 custom_value: 24576,
                 effort: None,
+                ..Default::default()
             },
         );
 
@@ -10,4 +10,5 @@ pub mod gemini;
 pub mod model_limits;
 pub mod openai;
 pub mod signature_store;
+pub mod tokenizer;
 pub mod tool_result_compressor;
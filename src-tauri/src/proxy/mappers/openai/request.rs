@@ -1,6 +1,9 @@
 // OpenAI → Gemini 请求转换
 use super::models::*;
+use crate::proxy::config::SystemPromptPosition;
+use crate::proxy::mappers::tool_result_compressor;
 use crate::proxy::model_specs;
+use crate::proxy::system_prompt::current_prompts;
 use crate::proxy::token_manager::ProxyToken;
 
 use serde_json::{json, Value};
@@ -363,13 +366,20 @@ pub fn transform_openai_request(
 
                 let mut extra_parts = Vec::new();
 
+                // 工具输出压缩: 与 Claude 映射共用按工具配置的压缩策略
                 let content_val = match &msg.content {
-                    Some(OpenAIContent::String(s)) => s.clone(),
+                    // 字符串结果仅在命中配置的压缩策略时处理
+                    Some(OpenAIContent::String(s)) => {
+                        tool_result_compressor::compact_tool_result_string(s, final_name)
+                            .unwrap_or_else(|| s.clone())
+                    }
                     Some(OpenAIContent::Array(blocks)) => {
                         let mut texts = Vec::new();
                         for block in blocks {
                             match block {
-                                OpenAIContentBlock::Text { text } => texts.push(text.clone()),
+                                OpenAIContentBlock::Text { text } => {
+                                    texts.push(json!({ "type": "text", "text": text }))
+                                }
                                 OpenAIContentBlock::ImageUrl { image_url } => {
                                     if image_url.url.starts_with("data:") {
                                         if let Some(pos) = image_url.url.find(',') {
@@ -382,13 +392,18 @@ pub fn transform_openai_request(
                                             }));
                                         }
                                     } else {
-                                        texts.push("[image link]".to_string());
+                                        texts.push(json!({ "type": "text", "text": "[image link]" }));
                                     }
                                 }
                                 _ => {}
                             }
                         }
-                        texts.join("\n")
+                        tool_result_compressor::sanitize_tool_result_blocks(&mut texts, final_name);
+                        texts
+                            .iter()
+                            .filter_map(|block| block.get("text").and_then(|t| t.as_str()))
+                            .collect::<Vec<_>>()
+                            .join("\n")
                     },
                     None => "".to_string()
                 };
@@ -620,7 +635,10 @@ pub fn transform_openai_request(
 
             if let Some(params) = gemini_func.get_mut("parameters") {
                 // [DEEP FIX] 统一调用公共库清洗：展开 $ref 并剔除所有层级的 format/definitions
-                crate::proxy::common::json_schema::clean_json_schema(params);
+                crate::proxy::common::json_schema::clean_json_schema_for_tool(
+                    params,
+                    name_opt.as_deref().unwrap_or_default(),
+                );
 
                 // Gemini v1internal 要求：
                 // 1. type 必须是大写 (OBJECT, STRING 等)
@@ -688,17 +706,25 @@ pub fn transform_openai_request(
         parts.push(json!({"text": antigravity_identity}));
     }
 
-    // 2. [NEW] 注入全局系统提示词 (紧跟 Antigravity 身份之后)
+    // 2. [NEW] 注入全局系统提示词与按条件注入的提示词片段 (紧跟 Antigravity 身份之后)
     let global_prompt_config = crate::proxy::config::get_global_system_prompt();
     if global_prompt_config.enabled && !global_prompt_config.content.trim().is_empty() {
         parts.push(json!({"text": global_prompt_config.content}));
     }
+    for text in current_prompts("openai", mapped_model, SystemPromptPosition::Prepend) {
+        parts.push(json!({ "text": text }));
+    }
 
     // 3. 追加用户指令 (作为独立 Parts)
     for inst in system_instructions {
         parts.push(json!({"text": inst}));
     }
 
+    // 4. 按条件注入的提示词片段 (客户端系统提示词之后)
+    for text in current_prompts("openai", mapped_model, SystemPromptPosition::Append) {
+        parts.push(json!({ "text": text }));
+    }
+
     inner_request["systemInstruction"] = json!({
         "role": "user",
         "parts": parts
@@ -806,6 +832,7 @@ mod tests {
             mode: ThinkingBudgetMode::Custom,
             custom_value: 32000,
             effort: None,
+            ..Default::default()
         });
 
         let req = OpenAIRequest {
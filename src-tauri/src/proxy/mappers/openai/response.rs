@@ -1,8 +1,14 @@
 // OpenAI 协议响应转换模块
 use super::models::*;
+use crate::proxy::mappers::context_manager::InputTokenEstimate;
 use serde_json::Value;
 
-pub fn transform_openai_response(gemini_response: &Value, session_id: Option<&str>, message_count: usize) -> OpenAIResponse {
+pub fn transform_openai_response(
+    gemini_response: &Value,
+    session_id: Option<&str>,
+    message_count: usize,
+    prompt_estimate: Option<&(String, InputTokenEstimate)>, // 映射模型 + 请求估算，用于校准器学习
+) -> OpenAIResponse {
     // 解包 response 字段
     let raw = gemini_response.get("response").unwrap_or(gemini_response);
 
@@ -53,10 +59,9 @@ pub fn transform_openai_response(gemini_response: &Value, session_id: Option<&st
                     // 工具调用部分
                     if let Some(fc) = part.get("functionCall") {
                         let name = fc.get("name").and_then(|v| v.as_str()).unwrap_or("unknown");
-                        let args = fc
-                            .get("args")
-                            .map(|v| v.to_string())
-                            .unwrap_or_else(|| "{}".to_string());
+                        let mut args = fc.get("args").cloned().unwrap_or_else(|| serde_json::json!({}));
+                        crate::proxy::common::json_schema::rewrite_tool_call_args(name, &mut args);
+                        let args = args.to_string();
                         let id = fc
                             .get("id")
                             .and_then(|v| v.as_str())
@@ -185,6 +190,11 @@ pub fn transform_openai_response(gemini_response: &Value, session_id: Option<&st
             .get("cachedContentTokenCount")
             .and_then(|v| v.as_u64())
             .map(|v| v as u32);
+        // Gemini 的思考 Token 单独计数 (不含在 candidatesTokenCount 中)
+        let reasoning_tokens = u
+            .get("thoughtsTokenCount")
+            .and_then(|v| v.as_u64())
+            .map(|v| v as u32);
 
         Some(super::models::OpenAIUsage {
             prompt_tokens,
@@ -193,10 +203,18 @@ pub fn transform_openai_response(gemini_response: &Value, session_id: Option<&st
             prompt_tokens_details: cached_tokens.map(|ct| super::models::PromptTokensDetails {
                 cached_tokens: Some(ct),
             }),
-            completion_tokens_details: None,
+            completion_tokens_details: reasoning_tokens.map(|rt| {
+                super::models::CompletionTokensDetails {
+                    reasoning_tokens: Some(rt),
+                }
+            }),
         })
     });
 
+    if let (Some(usage), Some((mapped_model, estimate))) = (&usage, prompt_estimate) {
+        estimate.record(mapped_model, usage.prompt_tokens);
+    }
+
     OpenAIResponse {
         id: raw
             .get("responseId")
@@ -233,7 +251,7 @@ mod tests {
             "responseId": "resp_123"
         });
 
-        let result = transform_openai_response(&gemini_resp, Some("session-123"), 1);
+        let result = transform_openai_response(&gemini_resp, Some("session-123"), 1, None);
         assert_eq!(result.object, "chat.completion");
         let content = match result.choices[0].message.content.as_ref().unwrap() {
             OpenAIContent::String(s) => s,
@@ -260,7 +278,7 @@ mod tests {
             "responseId": "resp_123"
         });
 
-        let result = transform_openai_response(&gemini_resp, Some("session-123"), 1);
+        let result = transform_openai_response(&gemini_resp, Some("session-123"), 1, None);
 
         assert!(result.usage.is_some());
         let usage = result.usage.unwrap();
@@ -282,7 +300,40 @@ mod tests {
             "responseId": "resp_123"
         });
 
-        let result = transform_openai_response(&gemini_resp, Some("session-123"), 1);
+        let result = transform_openai_response(&gemini_resp, Some("session-123"), 1, None);
         assert!(result.usage.is_none());
     }
+
+    #[test]
+    fn test_usage_metadata_feeds_calibrator() {
+        use crate::proxy::mappers::context_manager::ContextManager;
+        use crate::proxy::mappers::estimation_calibrator::get_calibrator;
+
+        let model = "calibrator-test-openai-response";
+        let request =
+            json!({ "contents": [{ "role": "user", "parts": [{ "text": "Hello there!" }] }] });
+        let estimate = (
+            model.to_string(),
+            ContextManager::estimate_gemini_input_tokens(&request, model),
+        );
+        let gemini_resp = json!({
+            "candidates": [{
+                "content": {"parts": [{"text": "Hi!"}]},
+                "finishReason": "STOP"
+            }],
+            "usageMetadata": {
+                "promptTokenCount": 12,
+                "candidatesTokenCount": 2,
+                "totalTokenCount": 14
+            }
+        });
+
+        transform_openai_response(&gemini_resp, None, 1, Some(&estimate));
+
+        let samples = get_calibrator()
+            .model_factors()
+            .get(model)
+            .map(|c| c.samples);
+        assert_eq!(samples, Some(1));
+    }
 }
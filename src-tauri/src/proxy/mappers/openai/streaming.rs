@@ -1,4 +1,5 @@
 // OpenAI 流式转换
+use crate::proxy::mappers::context_manager::InputTokenEstimate;
 use bytes::{Bytes, BytesMut};
 use chrono::Utc;
 use futures::{Stream, StreamExt};
@@ -33,7 +34,7 @@ pub fn store_thought_signature(sig: &str, session_id: &str, message_count: usize
 
 /// Extract and convert Gemini usageMetadata to OpenAI usage format
 fn extract_usage_metadata(u: &Value) -> Option<super::models::OpenAIUsage> {
-    use super::models::{OpenAIUsage, PromptTokensDetails};
+    use super::models::{CompletionTokensDetails, OpenAIUsage, PromptTokensDetails};
 
     let prompt_tokens = u
         .get("promptTokenCount")
@@ -51,6 +52,11 @@ fn extract_usage_metadata(u: &Value) -> Option<super::models::OpenAIUsage> {
         .get("cachedContentTokenCount")
         .and_then(|v| v.as_u64())
         .map(|v| v as u32);
+    // Gemini 的思考 Token 单独计数 (不含在 candidatesTokenCount 中)
+    let reasoning_tokens = u
+        .get("thoughtsTokenCount")
+        .and_then(|v| v.as_u64())
+        .map(|v| v as u32);
 
     Some(OpenAIUsage {
         prompt_tokens,
@@ -59,7 +65,9 @@ fn extract_usage_metadata(u: &Value) -> Option<super::models::OpenAIUsage> {
         prompt_tokens_details: cached_tokens.map(|ct| PromptTokensDetails {
             cached_tokens: Some(ct),
         }),
-        completion_tokens_details: None,
+        completion_tokens_details: reasoning_tokens.map(|rt| CompletionTokensDetails {
+            reasoning_tokens: Some(rt),
+        }),
     })
 }
 
@@ -68,6 +76,7 @@ pub fn create_openai_sse_stream<S, E>(
     model: String,
     session_id: String,
     message_count: usize,
+    mut prompt_estimate: Option<(String, InputTokenEstimate)>, // 映射模型 + 请求估算，用于校准器学习
 ) -> Pin<Box<dyn Stream<Item = Result<Bytes, String>> + Send>> 
 where
     S: Stream<Item = Result<Bytes, E>> + Send + ?Sized + 'static,
@@ -156,6 +165,7 @@ where
                                                                         }
                                                                     }
                                                                     
+                                                                    crate::proxy::common::json_schema::rewrite_tool_call_args(name, &mut args);
                                                                     let args_str = serde_json::to_string(&args).unwrap_or_default();
                                                                     let mut hasher = std::collections::hash_map::DefaultHasher::new();
                                                                     use std::hash::{Hash, Hasher};
@@ -262,6 +272,9 @@ where
                                                         if finish_reason.is_some() {
                                                             if let Some(ref usage) = final_usage {
                                                                 openai_chunk["usage"] = serde_json::to_value(usage).unwrap();
+                                                                if let Some((mapped_model, estimate)) = prompt_estimate.take() {
+                                                                    estimate.record(&mapped_model, usage.prompt_tokens);
+                                                                }
                                                             }
                                                         }
                                                         if finish_reason.is_some() { final_usage = None; }
@@ -325,6 +338,7 @@ pub fn create_legacy_sse_stream<S, E>(
     model: String,
     session_id: String,
     message_count: usize,
+    mut prompt_estimate: Option<(String, InputTokenEstimate)>, // 映射模型 + 请求估算，用于校准器学习
 ) -> Pin<Box<dyn Stream<Item = Result<Bytes, String>> + Send>> 
 where
     S: Stream<Item = Result<Bytes, E>> + Send + ?Sized + 'static,
@@ -389,7 +403,12 @@ where
                                                 "choices": [{ "text": content_out, "index": 0, "logprobs": null, "finish_reason": finish_reason }]
                                             });
                                             if let Some(ref usage) = final_usage { legacy_chunk["usage"] = serde_json::to_value(usage).unwrap(); }
-                                            if finish_reason.is_some() { final_usage = None; }
+                                            if finish_reason.is_some() {
+                                                if let (Some(usage), Some((mapped_model, estimate))) = (&final_usage, prompt_estimate.take()) {
+                                                    estimate.record(&mapped_model, usage.prompt_tokens);
+                                                }
+                                                final_usage = None;
+                                            }
                                             yield Ok::<Bytes, String>(Bytes::from(format!("data: {}\n\n", serde_json::to_string(&legacy_chunk).unwrap_or_default())));
                                         }
                                     }
@@ -699,7 +718,8 @@ mod tests {
             gemini_stream,
             "gemini-1.5-flash".to_string(),
             "test-session".to_string(),
-            0
+            0,
+            None
         );
 
         let mut chunks = Vec::new();
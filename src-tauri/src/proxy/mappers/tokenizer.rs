@@ -0,0 +1,656 @@
+//! Tokenizer Module
+//!
+//! Offline, tokenizer-backed token counting used by the context estimator.
+//! Vocab files are loaded from disk (tiktoken rank files or HuggingFace
+//! `tokenizer.json` BPE models) and selected per model or model family.
+//! Models without a tokenizer fall back to the character heuristic.
+
+use base64::Engine as _;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use crate::proxy::common::model_mapping::wildcard_match;
+use crate::proxy::config::{TokenizerConfig, TokenizerEntry, TokenizerFormat};
+
+/// GPT-2 / cl100k 风格的预分词 (regex crate 不支持 lookahead，`\s+(?!\S)` 简化为 `\s+`)
+static PRE_TOKENIZE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"'(?i:[sdmt]|ll|ve|re)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+")
+        .expect("valid pre-tokenize regex")
+});
+
+/// 单个预分词片段的最大字节数 (超长片段分块处理，避免 O(n²) 合并)
+const MAX_PIECE_BYTES: usize = 256;
+
+/// 自动发现时支持的模型家族
+const FAMILIES: [&str; 3] = ["gemini", "claude", "gpt"];
+
+pub trait Tokenizer: Send + Sync {
+    fn count(&self, text: &str) -> usize;
+    fn vocab_size(&self) -> usize;
+    fn format(&self) -> &'static str;
+}
+
+// ===== tiktoken =====
+
+/// Byte-level BPE over a tiktoken rank file
+pub struct TiktokenBpe {
+    ranks: HashMap<Vec<u8>, u32>,
+}
+
+impl TiktokenBpe {
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut ranks = HashMap::new();
+        for (line_no, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (token, rank) = line
+                .split_once(' ')
+                .ok_or_else(|| format!("line {}: expected '<token> <rank>'", line_no + 1))?;
+            let token = base64::engine::general_purpose::STANDARD
+                .decode(token)
+                .map_err(|e| format!("line {}: {}", line_no + 1, e))?;
+            let rank = rank
+                .trim()
+                .parse::<u32>()
+                .map_err(|e| format!("line {}: {}", line_no + 1, e))?;
+            ranks.insert(token, rank);
+        }
+        if ranks.is_empty() {
+            return Err("empty vocab".to_string());
+        }
+        Ok(Self { ranks })
+    }
+
+    fn count_piece(&self, piece: &[u8]) -> usize {
+        if self.ranks.contains_key(piece) {
+            return 1;
+        }
+        // 每次合并 rank 最小的相邻片段，直到无法继续合并
+        let mut bounds: Vec<usize> = (0..=piece.len()).collect();
+        loop {
+            let best = (0..bounds.len().saturating_sub(2))
+                .filter_map(|i| {
+                    self.ranks
+                        .get(&piece[bounds[i]..bounds[i + 2]])
+                        .map(|rank| (*rank, i))
+                })
+                .min();
+            match best {
+                Some((_, i)) => {
+                    bounds.remove(i + 1);
+                }
+                None => break,
+            }
+        }
+        bounds.len() - 1
+    }
+}
+
+impl Tokenizer for TiktokenBpe {
+    fn count(&self, text: &str) -> usize {
+        PRE_TOKENIZE
+            .find_iter(text)
+            .flat_map(|m| m.as_str().as_bytes().chunks(MAX_PIECE_BYTES))
+            .map(|piece| self.count_piece(piece))
+            .sum()
+    }
+
+    fn vocab_size(&self) -> usize {
+        self.ranks.len()
+    }
+
+    fn format(&self) -> &'static str {
+        "tiktoken"
+    }
+}
+
+// ===== HuggingFace tokenizer.json =====
+
+/// BPE model from a HuggingFace `tokenizer.json`
+///
+/// Supports byte-level models (GPT-2 style) and SentencePiece-style models
+/// (Metaspace `▁` with optional byte fallback).
+pub struct HfBpe {
+    vocab: HashMap<String, u32>,
+    merges: HashMap<(String, String), u32>,
+    byte_level: bool,
+    byte_fallback: bool,
+}
+
+impl HfBpe {
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let json: serde_json::Value =
+            serde_json::from_str(content).map_err(|e| format!("invalid tokenizer.json: {}", e))?;
+        let model = json.get("model").ok_or("missing 'model'")?;
+        let model_type = model.get("type").and_then(|t| t.as_str()).unwrap_or("BPE");
+        if model_type != "BPE" {
+            return Err(format!("unsupported model type '{}'", model_type));
+        }
+
+        let vocab: HashMap<String, u32> = model
+            .get("vocab")
+            .and_then(|v| v.as_object())
+            .ok_or("missing 'model.vocab'")?
+            .iter()
+            .filter_map(|(token, id)| id.as_u64().map(|id| (token.clone(), id as u32)))
+            .collect();
+
+        // merges 可能是 ["a b", ...] 或 [["a", "b"], ...]
+        let mut merges = HashMap::new();
+        for (rank, merge) in model
+            .get("merges")
+            .and_then(|m| m.as_array())
+            .into_iter()
+            .flatten()
+            .enumerate()
+        {
+            let pair = match merge {
+                serde_json::Value::String(s) => s
+                    .split_once(' ')
+                    .map(|(a, b)| (a.to_string(), b.to_string())),
+                serde_json::Value::Array(items) => match (items.first(), items.get(1)) {
+                    (Some(a), Some(b)) => a
+                        .as_str()
+                        .zip(b.as_str())
+                        .map(|(a, b)| (a.to_string(), b.to_string())),
+                    _ => None,
+                },
+                _ => None,
+            };
+            if let Some(pair) = pair {
+                merges.entry(pair).or_insert(rank as u32);
+            }
+        }
+        if vocab.is_empty() {
+            return Err("empty vocab".to_string());
+        }
+
+        let mentions_byte_level = |key: &str| {
+            json.get(key)
+                .map(|v| v.to_string().contains("\"ByteLevel\""))
+                .unwrap_or(false)
+        };
+        Ok(Self {
+            vocab,
+            merges,
+            byte_level: mentions_byte_level("pre_tokenizer") || mentions_byte_level("decoder"),
+            byte_fallback: model
+                .get("byte_fallback")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+        })
+    }
+
+    fn count_symbols(&self, symbols: Vec<String>) -> usize {
+        let mut symbols = symbols;
+        loop {
+            let best = symbols
+                .windows(2)
+                .enumerate()
+                .filter_map(|(i, pair)| {
+                    self.merges
+                        .get(&(pair[0].clone(), pair[1].clone()))
+                        .map(|rank| (*rank, i))
+                })
+                .min();
+            let Some((_, i)) = best else {
+                break;
+            };
+            let right = symbols.remove(i + 1);
+            symbols[i].push_str(&right);
+        }
+        symbols
+            .iter()
+            .map(|symbol| {
+                if self.byte_fallback && !self.vocab.contains_key(symbol) {
+                    symbol.len()
+                } else {
+                    1
+                }
+            })
+            .sum()
+    }
+}
+
+/// GPT-2 的字节到可见字符映射
+static BYTE_ALPHABET: Lazy<[char; 256]> = Lazy::new(|| {
+    let mut alphabet = ['\0'; 256];
+    let mut extra = 0u32;
+    for byte in 0..=255u32 {
+        let printable = (33..=126).contains(&byte)
+            || (161..=172).contains(&byte)
+            || (174..=255).contains(&byte);
+        alphabet[byte as usize] = if printable {
+            char::from_u32(byte).unwrap_or('?')
+        } else {
+            extra += 1;
+            char::from_u32(255 + extra).unwrap_or('?')
+        };
+    }
+    alphabet
+});
+
+impl Tokenizer for HfBpe {
+    fn count(&self, text: &str) -> usize {
+        if self.byte_level {
+            return PRE_TOKENIZE
+                .find_iter(text)
+                .flat_map(|m| m.as_str().as_bytes().chunks(MAX_PIECE_BYTES))
+                .map(|piece| {
+                    let mapped: String = piece.iter().map(|b| BYTE_ALPHABET[*b as usize]).collect();
+                    if self.vocab.contains_key(&mapped) {
+                        1
+                    } else {
+                        self.count_symbols(mapped.chars().map(String::from).collect())
+                    }
+                })
+                .sum();
+        }
+
+        // SentencePiece 风格: 空格替换为 `▁`，每个词以 `▁` 开头
+        let normalized = format!("▁{}", text.replace(' ', "▁"));
+        let mut count = 0;
+        let mut word = Vec::new();
+        for c in normalized.chars() {
+            if (c == '▁' && !word.is_empty()) || word.len() >= MAX_PIECE_BYTES {
+                count += self.count_symbols(std::mem::take(&mut word));
+            }
+            word.push(c.to_string());
+        }
+        if !word.is_empty() {
+            count += self.count_symbols(word);
+        }
+        count
+    }
+
+    fn vocab_size(&self) -> usize {
+        self.vocab.len()
+    }
+
+    fn format(&self) -> &'static str {
+        "hf_json"
+    }
+}
+
+// ===== Registry =====
+
+struct LoadedTokenizer {
+    name: String,
+    models: Vec<String>,
+    path: PathBuf,
+    tokenizer: Arc<dyn Tokenizer>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenizerInfo {
+    pub name: String,
+    pub models: Vec<String>,
+    pub path: String,
+    pub format: &'static str,
+    pub vocab_size: usize,
+}
+
+struct Registry {
+    /// 已应用配置的序列化结果，用于跳过未变化的重复加载
+    config_key: Option<String>,
+    tokenizers: Vec<LoadedTokenizer>,
+}
+
+static REGISTRY: Lazy<RwLock<Registry>> = Lazy::new(|| {
+    RwLock::new(Registry {
+        config_key: None,
+        tokenizers: Vec::new(),
+    })
+});
+
+pub fn load_tokenizer(path: &Path, format: TokenizerFormat) -> Result<Arc<dyn Tokenizer>, String> {
+    let content =
+        std::fs::read_to_string(path).map_err(|e| format!("读取词表失败 {:?}: {}", path, e))?;
+    let is_json = match format {
+        TokenizerFormat::Auto => path.extension().and_then(|e| e.to_str()) == Some("json"),
+        TokenizerFormat::HfJson => true,
+        TokenizerFormat::Tiktoken => false,
+    };
+    if is_json {
+        Ok(Arc::new(HfBpe::parse(&content)?))
+    } else {
+        Ok(Arc::new(TiktokenBpe::parse(&content)?))
+    }
+}
+
+fn tokenizer_dir(config: &TokenizerConfig) -> Option<PathBuf> {
+    match &config.dir {
+        Some(dir) => Some(PathBuf::from(dir)),
+        None => crate::modules::account::get_data_dir()
+            .ok()
+            .map(|dir| dir.join("tokenizers")),
+    }
+}
+
+/// 显式配置 + 按家族自动发现的分词器清单
+fn resolve_entries(config: &TokenizerConfig) -> Vec<TokenizerEntry> {
+    let dir = tokenizer_dir(config);
+    let mut entries: Vec<TokenizerEntry> = config
+        .entries
+        .iter()
+        .cloned()
+        .map(|mut entry| {
+            if let Some(dir) = &dir {
+                if Path::new(&entry.path).is_relative() {
+                    entry.path = dir.join(&entry.path).to_string_lossy().to_string();
+                }
+            }
+            entry
+        })
+        .collect();
+
+    if let Some(dir) = &dir {
+        for family in FAMILIES {
+            let discovered = ["tiktoken", "json"]
+                .iter()
+                .map(|ext| dir.join(format!("{}.{}", family, ext)))
+                .find(|path| path.is_file());
+            if let Some(path) = discovered {
+                entries.push(TokenizerEntry {
+                    name: family.to_string(),
+                    models: vec![format!("{}*", family)],
+                    path: path.to_string_lossy().to_string(),
+                    format: TokenizerFormat::Auto,
+                });
+            }
+        }
+    }
+    entries
+}
+
+/// 按配置 (重新) 加载分词器；配置未变化时跳过
+pub fn update_tokenizers(config: &TokenizerConfig) {
+    let key = serde_json::to_string(config).unwrap_or_default();
+    if REGISTRY
+        .read()
+        .map(|r| r.config_key.as_deref() == Some(key.as_str()))
+        .unwrap_or(false)
+    {
+        return;
+    }
+
+    let mut loaded = Vec::new();
+    for entry in resolve_entries(config) {
+        let path = PathBuf::from(&entry.path);
+        match load_tokenizer(&path, entry.format) {
+            Ok(tokenizer) => {
+                tracing::info!(
+                    "[Tokenizer] Loaded '{}' ({}, {} tokens) for {:?}",
+                    entry.name,
+                    tokenizer.format(),
+                    tokenizer.vocab_size(),
+                    entry.models
+                );
+                loaded.push(LoadedTokenizer {
+                    name: entry.name,
+                    models: entry.models,
+                    path,
+                    tokenizer,
+                });
+            }
+            Err(e) => tracing::warn!("[Tokenizer] Failed to load '{}': {}", entry.name, e),
+        }
+    }
+
+    if let Ok(mut registry) = REGISTRY.write() {
+        registry.config_key = Some(key);
+        registry.tokenizers = loaded;
+    }
+}
+
+/// 获取模型对应的分词器 (按配置顺序匹配第一个)
+pub fn tokenizer_for(model: &str) -> Option<Arc<dyn Tokenizer>> {
+    let registry = REGISTRY.read().ok()?;
+    registry
+        .tokenizers
+        .iter()
+        .find(|t| {
+            t.models
+                .iter()
+                .any(|pattern| wildcard_match(pattern, model))
+        })
+        .map(|t| t.tokenizer.clone())
+}
+
+pub fn list_tokenizers() -> Vec<TokenizerInfo> {
+    REGISTRY
+        .read()
+        .map(|registry| {
+            registry
+                .tokenizers
+                .iter()
+                .map(|t| TokenizerInfo {
+                    name: t.name.clone(),
+                    models: t.models.clone(),
+                    path: t.path.to_string_lossy().to_string(),
+                    format: t.tokenizer.format(),
+                    vocab_size: t.tokenizer.vocab_size(),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// ===== Content classification =====
+
+/// Content type used to bucket calibration factors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentKind {
+    Prose,
+    Code,
+    Cjk,
+    /// JSON: tool schemas, tool inputs and structured tool results
+    Structured,
+}
+
+impl ContentKind {
+    pub const ALL: [ContentKind; 4] = [
+        ContentKind::Prose,
+        ContentKind::Code,
+        ContentKind::Cjk,
+        ContentKind::Structured,
+    ];
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Classify free-form text by its character mix
+    pub fn classify(text: &str) -> Self {
+        let trimmed = text.trim();
+        if (trimmed.starts_with('{') && trimmed.ends_with('}'))
+            || (trimmed.starts_with('[') && trimmed.ends_with(']'))
+        {
+            return ContentKind::Structured;
+        }
+
+        let (mut total, mut cjk, mut symbols) = (0usize, 0usize, 0usize);
+        for c in trimmed.chars().filter(|c| !c.is_whitespace()) {
+            total += 1;
+            if matches!(c as u32, 0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xAC00..=0xD7AF | 0xF900..=0xFAFF)
+            {
+                cjk += 1;
+            } else if "{}[]();=<>/\\|&*_#$`:".contains(c) {
+                symbols += 1;
+            }
+        }
+        if total == 0 {
+            ContentKind::Prose
+        } else if cjk * 5 > total {
+            ContentKind::Cjk
+        } else if trimmed.contains("```") || symbols * 100 > total * 8 {
+            ContentKind::Code
+        } else {
+            ContentKind::Prose
+        }
+    }
+}
+
+/// Token counts split by content type
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct TokenBreakdown {
+    pub counts: [u32; 4],
+    /// Counted with a real tokenizer (no heuristic involved)
+    pub exact: bool,
+}
+
+impl TokenBreakdown {
+    pub fn total(&self) -> u32 {
+        self.counts.iter().sum()
+    }
+
+    pub fn get(&self, kind: ContentKind) -> u32 {
+        self.counts[kind.index()]
+    }
+
+    pub fn add(&mut self, kind: ContentKind, tokens: u32) {
+        self.counts[kind.index()] += tokens;
+    }
+}
+
+/// Counts text for one model, using its tokenizer if one is loaded
+pub struct TokenCounter {
+    tokenizer: Option<Arc<dyn Tokenizer>>,
+    pub breakdown: TokenBreakdown,
+}
+
+impl TokenCounter {
+    pub fn for_model(model: &str) -> Self {
+        let tokenizer = tokenizer_for(model);
+        Self {
+            breakdown: TokenBreakdown {
+                counts: [0; 4],
+                exact: tokenizer.is_some(),
+            },
+            tokenizer,
+        }
+    }
+
+    /// Count text and record it under the given content type; returns the token count
+    pub fn add(&mut self, kind: ContentKind, text: &str) -> u32 {
+        let tokens = match &self.tokenizer {
+            Some(tokenizer) => tokenizer.count(text) as u32,
+            None => heuristic_count(text),
+        };
+        self.breakdown.add(kind, tokens);
+        tokens
+    }
+
+    /// Count text, classifying its content type automatically
+    pub fn add_text(&mut self, text: &str) -> u32 {
+        self.add(ContentKind::classify(text), text)
+    }
+
+    /// Fixed overhead (message framing, call wrappers)
+    pub fn add_overhead(&mut self, tokens: u32) {
+        self.breakdown.add(ContentKind::Structured, tokens);
+    }
+}
+
+/// Character heuristic used when no tokenizer is loaded
+///
+/// - ASCII/English: ~4 characters per token
+/// - Unicode/CJK: ~1.5 characters per token
+/// - Adds 15% safety margin to prevent underestimation
+pub fn heuristic_count(s: &str) -> u32 {
+    if s.is_empty() {
+        return 0;
+    }
+
+    let mut ascii_chars = 0u32;
+    let mut unicode_chars = 0u32;
+    for c in s.chars() {
+        if c.is_ascii() {
+            ascii_chars += 1;
+        } else {
+            unicode_chars += 1;
+        }
+    }
+
+    let ascii_tokens = (ascii_chars as f32 / 4.0).ceil() as u32;
+    let unicode_tokens = (unicode_chars as f32 / 1.5).ceil() as u32;
+    ((ascii_tokens + unicode_tokens) as f32 * 1.15).ceil() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine as _;
+
+    fn b64(s: &str) -> String {
+        base64::engine::general_purpose::STANDARD.encode(s)
+    }
+
+    #[test]
+    fn test_tiktoken_merges_by_rank() {
+        let vocab: String = [
+            "h", "e", "l", "o", " ", "w", "r", "d", "he", "ll", "hell", "hello", " w", " wor",
+            "or", "ld",
+        ]
+        .iter()
+        .enumerate()
+        .map(|(rank, token)| format!("{} {}\n", b64(token), rank))
+        .collect();
+        let bpe = TiktokenBpe::parse(&vocab).unwrap();
+        assert_eq!(bpe.vocab_size(), 16);
+        // "hello" 整词命中；" world" → " wor" + "ld"
+        assert_eq!(bpe.count("hello"), 1);
+        assert_eq!(bpe.count("hello world"), 3);
+        // 未知字节逐字节计数
+        assert_eq!(bpe.count("zz"), 2);
+        assert!(TiktokenBpe::parse("not-base64!! x").is_err());
+    }
+
+    #[test]
+    fn test_hf_sentencepiece_bpe() {
+        let json = serde_json::json!({
+            "model": {
+                "type": "BPE",
+                "byte_fallback": true,
+                "vocab": {"▁": 0, "h": 1, "i": 2, "▁h": 3, "▁hi": 4, "t": 5, "▁t": 6},
+                "merges": ["▁ h", ["▁h", "i"], "▁ t"]
+            },
+            "pre_tokenizer": {"type": "Metaspace", "replacement": "▁"}
+        });
+        let bpe = HfBpe::parse(&json.to_string()).unwrap();
+        assert!(!bpe.byte_level);
+        // "hi hi" → ▁hi ▁hi
+        assert_eq!(bpe.count("hi hi"), 2);
+        // "ti" → ▁t i；"é" 不在词表，按 UTF-8 字节回退 (2 字节)
+        assert_eq!(bpe.count("ti"), 2);
+        assert_eq!(bpe.count("hié"), 3);
+    }
+
+    #[test]
+    fn test_classify_content() {
+        assert_eq!(
+            ContentKind::classify("The quick brown fox jumps."),
+            ContentKind::Prose
+        );
+        assert_eq!(
+            ContentKind::classify("fn main() { let x = vec![1]; }"),
+            ContentKind::Code
+        );
+        assert_eq!(
+            ContentKind::classify("你好，世界，这是一个测试"),
+            ContentKind::Cjk
+        );
+        assert_eq!(
+            ContentKind::classify(r#"{"a": 1}"#),
+            ContentKind::Structured
+        );
+        assert_eq!(heuristic_count("Hello World"), 4);
+    }
+}
@@ -3,14 +3,19 @@
 //! 提供智能压缩功能:
 //! - 浏览器快照压缩 (头+尾保留)
 //! - 大文件提示压缩 (提取关键信息)
-//! - 通用截断 (200,000 字符限制)
+//! - 通用截断 (默认 200,000 字符限制)
+//! - 按工具名称配置的压缩策略 (`experimental.tool_result_compression`)
 
+use once_cell::sync::Lazy;
 use regex::Regex;
-use serde_json::Value;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Mutex;
 use tracing::{debug, info};
 
-/// 最大工具结果字符数 (约 20 万,防止 prompt 超长)
-const MAX_TOOL_RESULT_CHARS: usize = 200_000;
+use crate::proxy::common::model_mapping::wildcard_match;
+use crate::proxy::config::{get_tool_result_compression_config, ToolResultPolicy};
 
 /// 浏览器快照检测阈值
 const SNAPSHOT_DETECTION_THRESHOLD: usize = 20_000;
@@ -244,36 +249,276 @@ fn deep_clean_html(html: &str) -> String {
     result
 }
 
+// ===== 可配置压缩策略 =====
+
+/// 默认错误行匹配
+static DEFAULT_ERROR_LINE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(error|errors|failed|failure|fail|panic|panicked|exception|traceback|fatal|assert)\b")
+        .expect("valid error line regex")
+});
+
+/// 日志行去重时忽略的数字 (时间戳、序号、耗时等)
+static DIGITS: Lazy<Regex> = Lazy::new(|| Regex::new(r"\d+").expect("valid digits regex"));
+
+/// 按策略统计的压缩效果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CompressionStats {
+    /// 处理过的工具结果数
+    pub results: u64,
+    /// 实际被压缩的工具结果数
+    pub compressed: u64,
+    pub original_chars: u64,
+    pub output_chars: u64,
+    pub saved_chars: u64,
+}
+
+static STATS: Lazy<Mutex<HashMap<String, CompressionStats>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn record_stats(policy: Option<&ToolResultPolicy>, original: usize, output: usize) {
+    let key = policy.map(|p| p.name.as_str()).unwrap_or("default");
+    if let Ok(mut stats) = STATS.lock() {
+        let entry = stats.entry(key.to_string()).or_default();
+        entry.results += 1;
+        if output < original {
+            entry.compressed += 1;
+        }
+        entry.original_chars += original as u64;
+        entry.output_chars += output as u64;
+        entry.saved_chars += original.saturating_sub(output) as u64;
+    }
+}
+
+/// 压缩统计 (按策略名称，未命中策略的计入 `default`)
+pub fn compression_stats() -> HashMap<String, CompressionStats> {
+    STATS.lock().map(|s| s.clone()).unwrap_or_default()
+}
+
+pub fn reset_compression_stats() {
+    if let Ok(mut stats) = STATS.lock() {
+        stats.clear();
+    }
+}
+
+fn find_policy<'a>(policies: &'a [ToolResultPolicy], tool_name: &str) -> Option<&'a ToolResultPolicy> {
+    policies
+        .iter()
+        .find(|p| p.tools.iter().any(|pattern| wildcard_match(pattern, tool_name)))
+}
+
+/// 向前取最近的 UTF-8 字符边界
+fn floor_boundary(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+/// 合并连续的相似行 (数字不同视为相同)，保留第一行并注明省略数量
+fn dedup_repeated_lines(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut lines = text.lines().peekable();
+    while let Some(line) = lines.next() {
+        out.push_str(line);
+        out.push('\n');
+        let key = DIGITS.replace_all(line, "#");
+        let mut repeated = 0;
+        while lines.peek().is_some_and(|next| DIGITS.replace_all(next, "#") == key) {
+            lines.next();
+            repeated += 1;
+        }
+        if repeated > 0 {
+            out.push_str(&format!("...[{} similar lines omitted]\n", repeated));
+        }
+    }
+    if !text.ends_with('\n') {
+        out.pop();
+    }
+    out
+}
+
+/// 裁剪 JSON: 数组保留前 `max_items` 项，字符串保留前 `max_string` 字节
+fn prune_json_value(value: &Value, max_items: usize, max_string: usize) -> Value {
+    match value {
+        Value::Array(items) => {
+            let mut pruned: Vec<Value> = items
+                .iter()
+                .take(max_items)
+                .map(|v| prune_json_value(v, max_items, max_string))
+                .collect();
+            if items.len() > max_items {
+                pruned.push(json!(format!("...[{} more items omitted]", items.len() - max_items)));
+            }
+            Value::Array(pruned)
+        }
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), prune_json_value(v, max_items, max_string)))
+                .collect(),
+        ),
+        Value::String(s) if s.len() > max_string => {
+            let cut = floor_boundary(s, max_string);
+            json!(format!("{}...[+{} chars]", &s[..cut], s.len() - cut))
+        }
+        other => other.clone(),
+    }
+}
+
+/// 逐步收紧裁剪力度直到结果不超过 `max_chars`；不是 JSON 或无法压到限制内时返回 None
+fn prune_json(text: &str, max_chars: usize) -> Option<String> {
+    let trimmed = text.trim_start();
+    if !trimmed.starts_with('{') && !trimmed.starts_with('[') {
+        return None;
+    }
+    let value: Value = serde_json::from_str(text).ok()?;
+    const LEVELS: [(usize, usize); 6] = [(50, 2000), (20, 1000), (10, 500), (5, 200), (2, 100), (1, 50)];
+    LEVELS.iter().find_map(|&(items, string)| {
+        let pruned = serde_json::to_string(&prune_json_value(&value, items, string)).ok()?;
+        (pruned.len() <= max_chars).then_some(pruned)
+    })
+}
+
+/// 头尾保留截断 (按行对齐)，可保留省略部分中的错误行
+fn head_tail_truncate(text: &str, max_chars: usize, head_ratio: f64, error_line: Option<&Regex>) -> String {
+    // 预留省略提示的长度
+    let budget = max_chars.saturating_sub(120);
+    if budget < 200 {
+        return truncate_text_safe(text, max_chars);
+    }
+
+    let mut head_budget = (budget as f64 * head_ratio.clamp(0.0, 1.0)) as usize;
+    let mut tail_budget = budget - head_budget;
+
+    // 1. 先从中间区域挑出错误行 (最多占预算的 30%)，再按比例压缩头尾
+    let mut errors = String::new();
+    if let Some(re) = error_line {
+        let middle_start = floor_boundary(text, head_budget);
+        let middle_end = floor_boundary(text, text.len().saturating_sub(tail_budget)).max(middle_start);
+        let error_budget = budget * 3 / 10;
+        for line in text[middle_start..middle_end].lines().filter(|l| re.is_match(l)) {
+            if errors.len() + line.len() + 1 > error_budget {
+                break;
+            }
+            errors.push_str(line);
+            errors.push('\n');
+        }
+        let used = errors.len();
+        let head_share = (used as f64 * head_ratio.clamp(0.0, 1.0)) as usize;
+        head_budget = head_budget.saturating_sub(head_share);
+        tail_budget = tail_budget.saturating_sub(used - head_share.min(used));
+    }
+
+    // 2. 头部截到最后一个完整行，尾部从第一个完整行开始
+    let head_cut = floor_boundary(text, head_budget);
+    let head_end = text[..head_cut].rfind('\n').map(|i| i + 1).filter(|i| *i > head_cut / 2).unwrap_or(head_cut);
+    let tail_cut = floor_boundary(text, text.len().saturating_sub(tail_budget)).max(head_end);
+    let tail_start = text[tail_cut..]
+        .find('\n')
+        .map(|i| tail_cut + i + 1)
+        .filter(|i| text.len() - *i > tail_budget / 2)
+        .unwrap_or(tail_cut);
+
+    let omitted = tail_start - head_end;
+    let mut out = String::with_capacity(max_chars);
+    out.push_str(&text[..head_end]);
+    if !out.ends_with('\n') && !out.is_empty() {
+        out.push('\n');
+    }
+    if errors.is_empty() {
+        out.push_str(&format!("...[omitted {} chars]...\n", omitted));
+    } else {
+        out.push_str(&format!("...[omitted {} chars; error lines kept below]...\n", omitted));
+        out.push_str(&errors);
+        out.push_str("...[end of kept error lines]...\n");
+    }
+    out.push_str(&text[tail_start..]);
+    out
+}
+
+/// 按策略压缩单段文本
+fn compact_with_policy(text: &str, max_chars: usize, policy: &ToolResultPolicy) -> String {
+    if text.len() <= max_chars {
+        return text.to_string();
+    }
+
+    let deduped;
+    let mut current = text;
+    if policy.dedup_lines {
+        deduped = dedup_repeated_lines(text);
+        debug!("[ToolCompressor] Dedup lines: {} -> {} chars", text.len(), deduped.len());
+        if deduped.len() <= max_chars {
+            return deduped;
+        }
+        current = &deduped;
+    }
+
+    if policy.json_prune {
+        if let Some(pruned) = prune_json(current, max_chars) {
+            debug!("[ToolCompressor] Pruned JSON: {} -> {} chars", current.len(), pruned.len());
+            return pruned;
+        }
+    }
+
+    let custom_error_line = policy
+        .error_pattern
+        .as_deref()
+        .and_then(|pattern| Regex::new(pattern).ok());
+    let error_line = policy
+        .keep_error_lines
+        .then(|| custom_error_line.as_ref().unwrap_or(&DEFAULT_ERROR_LINE));
+    head_tail_truncate(current, max_chars, policy.head_ratio, error_line)
+}
+
+/// 压缩字符串形式的工具结果；仅在命中策略时生效 (保持默认行为不变)
+pub fn compact_tool_result_string(text: &str, tool_name: &str) -> Option<String> {
+    let config = get_tool_result_compression_config();
+    let policy = find_policy(&config.policies, tool_name)?;
+    let max_chars = policy.max_chars.unwrap_or(config.max_chars);
+    let compacted = compact_with_policy(text, max_chars, policy);
+    record_stats(Some(policy), text.len(), compacted.len());
+    (compacted.len() < text.len()).then_some(compacted)
+}
+
 /// 清理工具结果 content blocks
 /// 
 /// 处理逻辑:
 /// 1. 移除 base64 图片 (避免体积过大)
-/// 2. 压缩文本内容 (使用智能压缩策略)
-/// 3. 限制总字符数 (默认 200,000)
+/// 2. 压缩文本内容 (命中工具策略时按策略压缩，否则使用智能压缩策略)
+/// 3. 限制总字符数 (默认 200,000，可按策略覆盖)
 /// 
 /// 清理并截断工具调用结果内容块
-pub fn sanitize_tool_result_blocks(blocks: &mut Vec<Value>) {
+pub fn sanitize_tool_result_blocks(blocks: &mut Vec<Value>, tool_name: &str) {
+    let config = get_tool_result_compression_config();
+    let policy = find_policy(&config.policies, tool_name);
+    let max_total = policy.and_then(|p| p.max_chars).unwrap_or(config.max_chars);
     let mut used_chars = 0;
+    let mut original_chars = 0;
     let mut cleaned_blocks = Vec::new();
 
     if !blocks.is_empty() {
         info!(
-            "[ToolCompressor] Processing {} blocks for truncation (MAX: {} chars)",
+            "[ToolCompressor] Processing {} blocks for truncation (MAX: {} chars, policy: {})",
             blocks.len(),
-            MAX_TOOL_RESULT_CHARS
+            max_total,
+            policy.map(|p| p.name.as_str()).unwrap_or("default")
         );
     }
     
     for block in blocks.iter() {
         // 压缩文本内容
         if let Some(text) = block.get("text").and_then(|v| v.as_str()) {
-            let remaining = MAX_TOOL_RESULT_CHARS.saturating_sub(used_chars);
+            let remaining = max_total.saturating_sub(used_chars);
             if remaining == 0 {
                 debug!("[ToolCompressor] Reached character limit, stopping");
                 break;
             }
+            original_chars += text.len();
             
-            let compacted = compact_tool_result_text(text, remaining);
+            let compacted = match policy {
+                Some(policy) => compact_with_policy(text, remaining, policy),
+                None => compact_tool_result_text(text, remaining),
+            };
             let mut new_block = block.clone();
             new_block["text"] = Value::String(compacted.clone());
             cleaned_blocks.push(new_block);
@@ -290,10 +535,24 @@ pub fn sanitize_tool_result_blocks(blocks: &mut Vec<Value>) {
             used_chars += 100; // 估算非文本块大小
         }
         
-        if used_chars >= MAX_TOOL_RESULT_CHARS {
+        if used_chars >= max_total {
             break;
         }
     }
+
+    // 被整体丢弃的文本块同样计入原始字符数
+    original_chars += blocks
+        .iter()
+        .skip(cleaned_blocks.len())
+        .filter_map(|b| b.get("text").and_then(|v| v.as_str()))
+        .map(str::len)
+        .sum::<usize>();
+    let output_chars = cleaned_blocks
+        .iter()
+        .filter_map(|b| b.get("text").and_then(|v| v.as_str()))
+        .map(str::len)
+        .sum::<usize>();
+    record_stats(policy, original_chars, output_chars);
     
     info!(
         "[ToolCompressor] Sanitization complete: {} → {} blocks, {} chars used",
@@ -374,7 +633,59 @@ Please read the file locally."#;
         ];
         
         // 确认工具结果不再剔除图片
-        sanitize_tool_result_blocks(&mut blocks);
+        sanitize_tool_result_blocks(&mut blocks, "unknown_tool");
         assert_eq!(blocks.len(), 4);
     }
+
+    fn policy(value: Value) -> ToolResultPolicy {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_policy_dedup_and_error_lines() {
+        let policy = policy(json!({
+            "name": "bash",
+            "tools": ["Bash"],
+            "dedup_lines": true,
+            "keep_error_lines": true
+        }));
+        let mut log = String::new();
+        for i in 0..2000 {
+            log.push_str(&format!("[{}] downloading chunk {}\n", i, i));
+        }
+        let deduped = compact_with_policy(&log, 1000, &policy);
+        assert!(deduped.starts_with("[0] downloading chunk 0\n...[1999 similar lines omitted]"));
+
+        let mut log = String::new();
+        for i in 0..3000 {
+            log.push_str(&format!("step {} ok {}\n", i, "x".repeat(i % 7)));
+            if i == 1500 {
+                log.push_str("error[E0308]: mismatched types\n");
+            }
+        }
+        let result = compact_with_policy(&log, 4000, &policy);
+        assert!(result.len() <= 4000);
+        assert!(result.starts_with("step 0 ok"));
+        assert!(result.contains("error[E0308]: mismatched types"));
+        assert!(result.ends_with("step 2999 ok xxx\n"));
+    }
+
+    #[test]
+    fn test_policy_json_prune() {
+        let policy = policy(json!({"name": "search", "tools": ["mcp__*"], "json_prune": true}));
+        let items: Vec<Value> = (0..500)
+            .map(|i| json!({"id": i, "body": "lorem ipsum ".repeat(50)}))
+            .collect();
+        let text = serde_json::to_string(&json!({"total": 500, "items": items})).unwrap();
+
+        let result = compact_with_policy(&text, 5000, &policy);
+        assert!(result.len() <= 5000);
+        let parsed: Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(parsed["total"], 500);
+        assert_eq!(parsed["items"][0]["id"], 0);
+        assert!(parsed["items"].as_array().unwrap().last().unwrap().as_str().unwrap().contains("more items omitted"));
+
+        assert!(find_policy(std::slice::from_ref(&policy), "mcp__github__search").is_some());
+        assert!(find_policy(std::slice::from_ref(&policy), "Read").is_none());
+    }
 }
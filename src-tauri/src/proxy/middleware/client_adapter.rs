@@ -0,0 +1,201 @@
+// Client Adapter 中间件 - 对匹配到适配器的对话请求应用请求改写与响应调整
+//
+// 请求: 适配器声明需要改写时，把 json_body 中间件解析的请求体交给 `adapt_request` 处理 (移除 thinking、调整工具定义)。
+// 响应: 追加配置的响应头；OpenAI 协议下可移除 `reasoning_content` (JSON 与 SSE 均支持)。
+
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, HeaderName, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use bytes::Bytes;
+use futures::StreamExt;
+use serde_json::Value;
+
+use super::fallback::protocol_for_path;
+use super::json_body::{error_response, ParsedBody};
+use crate::proxy::common::client_adapter::find_client_adapter;
+use crate::proxy::ProviderProtocol;
+
+const MAX_ADAPTER_BODY_SIZE: usize = 100 * 1024 * 1024; // 100MB
+
+pub async fn client_adapter_middleware(mut request: Request, next: Next) -> Response {
+    if request.method() != Method::POST {
+        return next.run(request).await;
+    }
+    let Some(protocol) = protocol_for_path(request.uri().path()) else {
+        return next.run(request).await;
+    };
+    let Some(adapter) = find_client_adapter(request.headers()) else {
+        return next.run(request).await;
+    };
+
+    // 请求体由 json_body 中间件解析，改写后由 commit 层统一写回
+    if adapter.rewrites_request() {
+        if let Some(parsed) = request.extensions_mut().get_mut::<ParsedBody>() {
+            if let Some(json) = parsed.json_mut() {
+                adapter.adapt_request(json);
+                parsed.mark_changed();
+                tracing::debug!("[ClientAdapter] Request adapted by '{}'", adapter.name());
+            }
+        }
+    }
+
+    let mut response = next.run(request).await;
+    let Some(quirks) = adapter.response_quirks() else {
+        return response;
+    };
+
+    for (name, value) in &quirks.headers {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(value),
+        ) {
+            response.headers_mut().insert(name, value);
+        }
+    }
+
+    if quirks.strip_reasoning_content && protocol == ProviderProtocol::Openai {
+        response = strip_reasoning_response(protocol, response).await;
+    }
+    response
+}
+
+/// 移除 choices[].message / choices[].delta 中的 reasoning_content，返回是否有改动
+fn strip_reasoning(json: &mut Value) -> bool {
+    let mut changed = false;
+    if let Some(choices) = json.get_mut("choices").and_then(|c| c.as_array_mut()) {
+        for choice in choices {
+            for key in ["message", "delta"] {
+                if let Some(obj) = choice.get_mut(key).and_then(|m| m.as_object_mut()) {
+                    changed |= obj.remove("reasoning_content").is_some();
+                }
+            }
+        }
+    }
+    changed
+}
+
+/// 处理单行 SSE 数据
+fn strip_reasoning_line(line: &[u8]) -> Option<Vec<u8>> {
+    let data = line.strip_prefix(b"data: ")?;
+    let mut json = serde_json::from_slice::<Value>(data).ok()?;
+    if !strip_reasoning(&mut json) {
+        return None;
+    }
+    let mut out = b"data: ".to_vec();
+    out.extend(serde_json::to_vec(&json).ok()?);
+    Some(out)
+}
+
+async fn strip_reasoning_response(protocol: ProviderProtocol, response: Response) -> Response {
+    let is_sse = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.contains("text/event-stream"));
+    let (mut parts, body) = response.into_parts();
+
+    if !is_sse {
+        let bytes = match axum::body::to_bytes(body, MAX_ADAPTER_BODY_SIZE).await {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                tracing::warn!("[ClientAdapter] Failed to read response body: {}", e);
+                return error_response(
+                    protocol,
+                    StatusCode::BAD_GATEWAY,
+                    "Failed to read upstream response body",
+                );
+            }
+        };
+        let bytes = match serde_json::from_slice::<Value>(&bytes) {
+            Ok(mut json) if strip_reasoning(&mut json) => {
+                parts.headers.remove(header::CONTENT_LENGTH);
+                serde_json::to_vec(&json).map(Bytes::from).unwrap_or(bytes)
+            }
+            _ => bytes,
+        };
+        return Response::from_parts(parts, Body::from(bytes));
+    }
+
+    // SSE: 按行缓冲，只改写包含 reasoning_content 的 data 行
+    let stream = futures::stream::unfold(
+        (body.into_data_stream(), Vec::<u8>::new(), false),
+        |(mut inner, mut pending, finished)| async move {
+            if finished {
+                return None;
+            }
+            match inner.next().await {
+                Some(Ok(chunk)) => {
+                    pending.extend_from_slice(&chunk);
+                    let out = match pending.iter().rposition(|b| *b == b'\n') {
+                        Some(last_newline) => {
+                            let complete: Vec<u8> = pending.drain(..=last_newline).collect();
+                            rewrite_sse_lines(&complete)
+                        }
+                        None => Vec::new(),
+                    };
+                    Some((Ok(Bytes::from(out)), (inner, pending, false)))
+                }
+                Some(Err(e)) => Some((Err(e), (inner, pending, true))),
+                // 流结束时输出残留的不完整行
+                None if !pending.is_empty() => {
+                    let out = rewrite_sse_lines(&pending);
+                    Some((Ok(Bytes::from(out)), (inner, Vec::new(), true)))
+                }
+                None => None,
+            }
+        },
+    );
+    Response::from_parts(parts, Body::from_stream(stream))
+}
+
+fn rewrite_sse_lines(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    for line in data.split_inclusive(|b| *b == b'\n') {
+        let content = line.strip_suffix(b"\n").unwrap_or(line);
+        let content = content.strip_suffix(b"\r").unwrap_or(content);
+        match strip_reasoning_line(content) {
+            Some(rewritten) => {
+                out.extend(rewritten);
+                out.extend_from_slice(&line[content.len()..]);
+            }
+            None => out.extend_from_slice(line),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_strip_reasoning() {
+        let mut body = json!({
+            "choices": [{"message": {"content": "hi", "reasoning_content": "thinking..."}}]
+        });
+        assert!(strip_reasoning(&mut body));
+        assert_eq!(body, json!({"choices": [{"message": {"content": "hi"}}]}));
+
+        let line = br#"data: {"choices":[{"delta":{"reasoning_content":"x"}}]}"#;
+        assert_eq!(
+            strip_reasoning_line(line).unwrap(),
+            br#"data: {"choices":[{"delta":{}}]}"#.to_vec()
+        );
+        assert!(strip_reasoning_line(b"data: [DONE]").is_none());
+        assert!(
+            strip_reasoning_line(br#"data: {"choices":[{"delta":{"content":"a"}}]}"#).is_none()
+        );
+
+        let sse =
+            b"data: {\"choices\":[{\"delta\":{\"reasoning_content\":\"x\"}}]}\r\n\r\ndata: [DONE]";
+        assert_eq!(
+            rewrite_sse_lines(sse),
+            b"data: {\"choices\":[{\"delta\":{}}]}\r\n\r\ndata: [DONE]".to_vec()
+        );
+    }
+}
@@ -0,0 +1,43 @@
+// Context Window 中间件 - 按用户令牌 / 客户端适配器选择上下文窗口策略
+//
+// 位于 routing 之内、client_adapter 之前：选出的策略通过 task-local 作用域传递给 handler，
+// 由 handler 在转换为上游请求后执行裁剪。响应头 `X-Context-Policy` 为生效的策略名称。
+
+use axum::{
+    extract::Request,
+    http::{HeaderValue, Method},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+
+use super::auth::UserTokenIdentity;
+use super::fallback::protocol_for_path;
+use crate::proxy::common::client_adapter::find_client_adapter;
+use crate::proxy::context_window::{select_policy, with_policy};
+
+pub async fn context_window_middleware(request: Request, next: Next) -> Response {
+    if request.method() != Method::POST || protocol_for_path(request.uri().path()).is_none() {
+        return next.run(request).await;
+    }
+    let config = crate::proxy::config::get_context_window_config();
+    if config.policies.is_empty() {
+        return next.run(request).await;
+    }
+
+    let user = request
+        .extensions()
+        .get::<UserTokenIdentity>()
+        .map(|identity| identity.username.clone());
+    let client = find_client_adapter(request.headers()).map(|adapter| adapter.name().to_string());
+    let Some(policy) = select_policy(&config, user.as_deref(), client.as_deref()) else {
+        return next.run(request).await;
+    };
+
+    let name = HeaderValue::from_str(&policy.name).ok();
+    let mut response = with_policy(Arc::new(policy), next.run(request)).await;
+    if let Some(name) = name {
+        response.headers_mut().insert("X-Context-Policy", name);
+    }
+    response
+}
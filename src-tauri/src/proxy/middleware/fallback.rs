@@ -0,0 +1,342 @@
+// Fallback 中间件 - Google 账号池耗尽时按模型降级链替换请求模型
+//
+// 位于 monitor 之内、handler 之前：替换请求体 (或 Gemini 路径) 中的模型，
+// 各协议 handler 无需感知降级逻辑；响应头携带替换信息，monitor 据此记录 mapped_model。
+
+use axum::{
+    extract::{Request, State},
+    http::{Extensions, HeaderValue, Method, Uri},
+    middleware::Next,
+    response::Response,
+};
+use serde_json::Value;
+use std::collections::HashMap;
+
+use super::json_body::ParsedBody;
+use crate::proxy::common::model_mapping::{normalize_to_standard_id, wildcard_match};
+use crate::proxy::server::AppState;
+use crate::proxy::{ProviderProtocol, ZaiDispatchMode};
+
+pub(crate) const GEMINI_MODELS_PREFIX: &str = "/v1beta/models/";
+
+/// 根据路径判断入口协议，仅对话类端点参与降级
+pub(crate) fn protocol_for_path(path: &str) -> Option<ProviderProtocol> {
+    match path {
+        "/v1/chat/completions" | "/v1/completions" | "/v1/responses" => {
+            Some(ProviderProtocol::Openai)
+        }
+        "/v1/messages" => Some(ProviderProtocol::Anthropic),
+        p if p.starts_with(GEMINI_MODELS_PREFIX) && p.contains(':') => {
+            Some(ProviderProtocol::Gemini)
+        }
+        _ => None,
+    }
+}
+
+/// 从 Gemini 路径 `/v1beta/models/{model}:{action}` 中提取模型
+pub(crate) fn gemini_path_model(uri: &Uri) -> Option<String> {
+    uri.path()
+        .strip_prefix(GEMINI_MODELS_PREFIX)
+        .and_then(|rest| rest.rsplit_once(':'))
+        .map(|(model, _)| model.to_string())
+}
+
+/// 中间件替换后的 Gemini 模型
+///
+/// 中间件通过 `Router::layer` 挂载，执行时路由已完成、`Path` 参数已固定，改写 URI 不会影响 handler；
+/// 因此替换结果放入请求扩展，Gemini handler 优先读取。
+#[derive(Debug, Clone)]
+pub(crate) struct GeminiModelOverride(pub String);
+
+/// Gemini 请求当前生效的模型：优先使用已替换的模型，其次是路径中的模型
+pub(crate) fn gemini_request_model(extensions: &Extensions, uri: &Uri) -> Option<String> {
+    extensions
+        .get::<GeminiModelOverride>()
+        .map(|o| o.0.clone())
+        .or_else(|| gemini_path_model(uri))
+}
+
+/// 替换 Gemini 路径中的模型，保留 action 与查询参数
+pub(crate) fn gemini_uri_with_model(uri: &Uri, model: &str) -> Option<Uri> {
+    let (_, action) = uri.path().rsplit_once(':')?;
+    let query = uri.query().map(|q| format!("?{}", q)).unwrap_or_default();
+    format!("{}{}:{}{}", GEMINI_MODELS_PREFIX, model, action, query)
+        .parse::<Uri>()
+        .ok()
+}
+
+/// 查找模型对应的降级链：精确匹配优先，其次是非通配字符最多的通配规则
+fn chain_for<'a>(chains: &'a HashMap<String, Vec<String>>, model: &str) -> Option<&'a [String]> {
+    if let Some(chain) = chains.get(model) {
+        return Some(chain);
+    }
+    chains
+        .iter()
+        .filter(|(pattern, _)| pattern.contains('*') && wildcard_match(pattern, model))
+        .max_by_key(|(pattern, _)| pattern.chars().count() - pattern.matches('*').count())
+        .map(|(_, chain)| chain.as_slice())
+}
+
+/// Google 账号池中是否还有可服务该模型的账号
+async fn pool_available(state: &AppState, model: &str) -> bool {
+    if state.token_manager.len() == 0 {
+        return false;
+    }
+    let normalized = normalize_to_standard_id(model).unwrap_or_else(|| model.to_string());
+    state
+        .token_manager
+        .has_available_account("", &normalized)
+        .await
+}
+
+/// 原模型不可用时，返回降级链中第一个可用的替代模型
+async fn pick_fallback(
+    state: &AppState,
+    protocol: ProviderProtocol,
+    model: &str,
+    chain: &[String],
+) -> Option<String> {
+    // 由提供商独占处理的请求不经过账号池
+    if crate::proxy::providers::generic::bypasses_pool(state, protocol, model).await {
+        return None;
+    }
+    if protocol == ProviderProtocol::Anthropic {
+        let zai = state.zai.read().await;
+        if zai.enabled && zai.dispatch_mode == ZaiDispatchMode::Exclusive {
+            return None;
+        }
+    }
+    if pool_available(state, model).await {
+        return None;
+    }
+
+    for candidate in chain {
+        if let Some((prefix, _)) = candidate.split_once(':') {
+            let provider_protocol = state
+                .providers
+                .read()
+                .await
+                .iter()
+                .find(|p| p.enabled && p.name.eq_ignore_ascii_case(prefix))
+                .map(|p| p.protocol);
+            match provider_protocol {
+                Some(p) if p == protocol => return Some(candidate.clone()),
+                Some(p) => {
+                    tracing::debug!(
+                        "[Fallback-Chain] Skip {}: provider protocol {:?} != {:?}",
+                        candidate,
+                        p,
+                        protocol
+                    );
+                    continue;
+                }
+                None => {}
+            }
+        }
+        if pool_available(state, candidate).await {
+            return Some(candidate.clone());
+        }
+    }
+
+    tracing::warn!(
+        "[Fallback-Chain] No available fallback for {} (chain: {})",
+        model,
+        chain.join(" -> ")
+    );
+    None
+}
+
+/// 查找降级链并挑选替代模型
+async fn resolve_substitute(
+    state: &AppState,
+    protocol: ProviderProtocol,
+    model: Option<&str>,
+    chains: &HashMap<String, Vec<String>>,
+) -> Option<String> {
+    let model = model?;
+    let chain = chain_for(chains, model)?;
+    let substitute = pick_fallback(state, protocol, model, chain).await?;
+    tracing::info!(
+        "[Fallback-Chain] {} -> {} (no available account in Google pool)",
+        model,
+        substitute
+    );
+    Some(substitute)
+}
+
+/// 在响应头中报告替换信息
+fn annotate(
+    mut response: Response,
+    original: Option<String>,
+    substitute: Option<String>,
+) -> Response {
+    if let (Some(original), Some(sub)) = (original, substitute) {
+        let headers = response.headers_mut();
+        if let Ok(v) = HeaderValue::from_str(&original) {
+            headers.insert("X-Fallback-From", v);
+        }
+        if let Ok(v) = HeaderValue::from_str(&sub) {
+            headers.insert("X-Fallback-Model", v.clone());
+            // handler 未设置时补上，保证 ProxyRequestLog::mapped_model 反映替换后的模型
+            headers.entry("X-Mapped-Model").or_insert(v);
+        }
+    }
+    response
+}
+
+pub async fn fallback_middleware(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if request.method() != Method::POST {
+        return next.run(request).await;
+    }
+    let Some(protocol) = protocol_for_path(request.uri().path()) else {
+        return next.run(request).await;
+    };
+    let chains = crate::proxy::config::get_fallback_chains();
+    if chains.is_empty() {
+        return next.run(request).await;
+    }
+
+    // Gemini 协议的模型位于路径中: /v1beta/models/{model}:{action}
+    if protocol == ProviderProtocol::Gemini {
+        let original = gemini_request_model(request.extensions(), request.uri());
+        let substitute = resolve_substitute(&state, protocol, original.as_deref(), &chains).await;
+        if let Some(sub) = &substitute {
+            request
+                .extensions_mut()
+                .insert(GeminiModelOverride(sub.clone()));
+        }
+        let response = next.run(request).await;
+        return annotate(response, original, substitute);
+    }
+
+    // 请求体由 json_body 中间件解析，改写后由 commit 层统一写回
+    let original = request
+        .extensions()
+        .get::<ParsedBody>()
+        .and_then(ParsedBody::json)
+        .and_then(|v| v.get("model"))
+        .and_then(|m| m.as_str())
+        .map(|s| s.to_string());
+    let substitute = resolve_substitute(&state, protocol, original.as_deref(), &chains).await;
+    if let (Some(sub), Some(parsed)) = (
+        &substitute,
+        request.extensions_mut().get_mut::<ParsedBody>(),
+    ) {
+        if let Some(v) = parsed.json_mut() {
+            v["model"] = Value::String(sub.clone());
+            parsed.mark_changed();
+        }
+    }
+    let response = next.run(request).await;
+    annotate(response, original, substitute)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chain_lookup_prefers_exact_then_specific_wildcard() {
+        let mut chains = HashMap::new();
+        chains.insert("claude-*".to_string(), vec!["gemini-3-flash".to_string()]);
+        chains.insert(
+            "claude-opus-*".to_string(),
+            vec!["gemini-3.1-pro-high".to_string(), "vllm:qwen".to_string()],
+        );
+        chains.insert("gpt-4o".to_string(), vec!["gemini-3-flash".to_string()]);
+
+        assert_eq!(chain_for(&chains, "gpt-4o").unwrap(), ["gemini-3-flash"]);
+        assert_eq!(
+            chain_for(&chains, "claude-opus-4-6-thinking").unwrap(),
+            ["gemini-3.1-pro-high", "vllm:qwen"]
+        );
+        assert_eq!(
+            chain_for(&chains, "claude-sonnet-4-5").unwrap(),
+            ["gemini-3-flash"]
+        );
+        assert!(chain_for(&chains, "gemini-3-flash").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_gemini_override_reaches_handler_through_router() {
+        use axum::body::Body;
+        use axum::extract::{Extension, Path};
+        use axum::http::header;
+        use axum::routing::post;
+        use tower::ServiceExt;
+
+        async fn echo_model(
+            Path(model_action): Path<String>,
+            model_override: Option<Extension<GeminiModelOverride>>,
+        ) -> String {
+            let (model, method) = crate::proxy::handlers::gemini::resolve_model_action(
+                model_action,
+                model_override.as_ref().map(|e| &e.0),
+            );
+            format!("{}:{}", model, method)
+        }
+        // 与 fallback_middleware 相同：路由完成后才执行，只能通过扩展传递替换结果
+        async fn substitute(mut request: Request, next: Next) -> Response {
+            let original = gemini_request_model(request.extensions(), request.uri());
+            if original.as_deref() == Some("gemini-3.1-pro-high") {
+                request
+                    .extensions_mut()
+                    .insert(GeminiModelOverride("gemini-3-flash".to_string()));
+            }
+            next.run(request).await
+        }
+
+        let app = axum::Router::new()
+            .route("/v1beta/models/:model", post(echo_model))
+            .layer(axum::middleware::from_fn(substitute));
+        let send = |uri: &'static str| {
+            let app = app.clone();
+            async move {
+                let response = app
+                    .oneshot(
+                        Request::post(uri)
+                            .header(header::CONTENT_TYPE, "application/json")
+                            .body(Body::from("{}"))
+                            .unwrap(),
+                    )
+                    .await
+                    .unwrap();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                String::from_utf8(bytes.to_vec()).unwrap()
+            }
+        };
+
+        assert_eq!(
+            send("/v1beta/models/gemini-3.1-pro-high:streamGenerateContent?alt=sse").await,
+            "gemini-3-flash:streamGenerateContent"
+        );
+        assert_eq!(
+            send("/v1beta/models/gemini-2.5-flash:generateContent").await,
+            "gemini-2.5-flash:generateContent"
+        );
+    }
+
+    #[test]
+    fn test_protocol_for_path() {
+        assert_eq!(
+            protocol_for_path("/v1/messages"),
+            Some(ProviderProtocol::Anthropic)
+        );
+        assert_eq!(
+            protocol_for_path("/v1/responses"),
+            Some(ProviderProtocol::Openai)
+        );
+        assert_eq!(
+            protocol_for_path("/v1beta/models/gemini-3-flash:streamGenerateContent"),
+            Some(ProviderProtocol::Gemini)
+        );
+        assert_eq!(protocol_for_path("/v1beta/models/gemini-3-flash"), None);
+        assert_eq!(protocol_for_path("/v1/messages/count_tokens"), None);
+    }
+}
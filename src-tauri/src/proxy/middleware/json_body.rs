@@ -0,0 +1,207 @@
+// JSON Body 中间件 - 对话请求体只读取、解析一次，供后续中间件共享
+//
+// 位于 monitor 之内、routing 之前：读取请求体 (遵循全局 DefaultBodyLimit) 并把解析结果作为
+// `ParsedBody` 放入请求扩展；routing / fallback / client_adapter / script_hooks 等中间件直接读取或修改它。
+// 位于 response_cache 之内的 commit 层在进入 handler 前把修改过的 JSON 统一序列化一次。
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{FromRequest, Request},
+    http::{header, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::{json, Value};
+
+use super::fallback::protocol_for_path;
+use crate::proxy::ProviderProtocol;
+
+/// 已解析的请求体
+#[derive(Debug, Clone)]
+pub(crate) struct ParsedBody {
+    bytes: Bytes,
+    json: Option<Value>,
+    changed: bool,
+}
+
+impl ParsedBody {
+    pub(crate) fn new(bytes: Bytes) -> Self {
+        let json = serde_json::from_slice(&bytes).ok();
+        Self {
+            bytes,
+            json,
+            changed: false,
+        }
+    }
+
+    /// 解析后的 JSON (请求体不是合法 JSON 时为 None，交由 handler 报错)
+    pub(crate) fn json(&self) -> Option<&Value> {
+        self.json.as_ref()
+    }
+
+    /// 可修改的 JSON；实际修改后需调用 [`ParsedBody::mark_changed`]
+    pub(crate) fn json_mut(&mut self) -> Option<&mut Value> {
+        self.json.as_mut()
+    }
+
+    pub(crate) fn mark_changed(&mut self) {
+        self.changed = true;
+    }
+
+    /// 客户端原始请求体的字节数
+    pub(crate) fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// 当前请求体 (修改过时重新序列化)
+    pub(crate) fn to_bytes(&self) -> Bytes {
+        match (&self.json, self.changed) {
+            (Some(json), true) => serde_json::to_vec(json)
+                .map(Bytes::from)
+                .unwrap_or_else(|_| self.bytes.clone()),
+            _ => self.bytes.clone(),
+        }
+    }
+}
+
+/// 按协议格式返回错误响应
+pub(crate) fn error_response(
+    protocol: ProviderProtocol,
+    status: StatusCode,
+    message: &str,
+) -> Response {
+    let error_type = match status {
+        StatusCode::PAYLOAD_TOO_LARGE => "request_too_large",
+        s if s.is_client_error() => "invalid_request_error",
+        _ => "api_error",
+    };
+    let body = match protocol {
+        ProviderProtocol::Anthropic => json!({
+            "type": "error",
+            "error": { "type": error_type, "message": message }
+        }),
+        ProviderProtocol::Openai => json!({
+            "error": { "message": message, "type": error_type }
+        }),
+        ProviderProtocol::Gemini => {
+            let canonical = match status {
+                StatusCode::BAD_REQUEST => "INVALID_ARGUMENT",
+                StatusCode::PAYLOAD_TOO_LARGE => "OUT_OF_RANGE",
+                StatusCode::BAD_GATEWAY => "UNAVAILABLE",
+                _ => "INTERNAL",
+            };
+            json!({
+                "error": { "code": status.as_u16(), "message": message, "status": canonical }
+            })
+        }
+    };
+    (status, Json(body)).into_response()
+}
+
+pub async fn json_body_middleware(request: Request, next: Next) -> Response {
+    if request.method() != Method::POST {
+        return next.run(request).await;
+    }
+    let Some(protocol) = protocol_for_path(request.uri().path()) else {
+        return next.run(request).await;
+    };
+
+    let (mut parts, body) = request.into_parts();
+    // 携带扩展 (DefaultBodyLimit) 读取，超限时返回 413，读取失败返回 400
+    let mut body_request = Request::new(body);
+    *body_request.extensions_mut() = parts.extensions.clone();
+    let bytes = match Bytes::from_request(body_request, &()).await {
+        Ok(bytes) => bytes,
+        Err(rejection) => {
+            tracing::warn!("[JsonBody] Failed to read request body: {}", rejection);
+            return error_response(protocol, rejection.status(), &rejection.body_text());
+        }
+    };
+    parts.extensions.insert(ParsedBody::new(bytes.clone()));
+    next.run(Request::from_parts(parts, Body::from(bytes)))
+        .await
+}
+
+/// 进入 handler 前写回被中间件修改过的请求体
+pub async fn json_body_commit_middleware(request: Request, next: Next) -> Response {
+    let changed = request
+        .extensions()
+        .get::<ParsedBody>()
+        .is_some_and(|parsed| parsed.changed);
+    if !changed {
+        return next.run(request).await;
+    }
+    let (mut parts, _) = request.into_parts();
+    let bytes = parts
+        .extensions
+        .get::<ParsedBody>()
+        .map(ParsedBody::to_bytes)
+        .unwrap_or_default();
+    parts.headers.remove(header::CONTENT_LENGTH);
+    next.run(Request::from_parts(parts, Body::from(bytes)))
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{middleware::from_fn, routing::post, Router};
+    use tower::ServiceExt;
+
+    async fn rewrite_model(mut request: Request, next: Next) -> Response {
+        if let Some(parsed) = request.extensions_mut().get_mut::<ParsedBody>() {
+            if let Some(json) = parsed.json_mut() {
+                json["model"] = json!("rewritten");
+                parsed.mark_changed();
+            }
+        }
+        next.run(request).await
+    }
+
+    fn router() -> Router {
+        Router::new()
+            .route(
+                "/v1/chat/completions",
+                post(|Json(body): Json<Value>| async move { Json(body) }),
+            )
+            .layer(from_fn(json_body_commit_middleware))
+            .layer(from_fn(rewrite_model))
+            .layer(from_fn(json_body_middleware))
+            .layer(axum::extract::DefaultBodyLimit::max(64))
+    }
+
+    async fn send(body: &'static str) -> (StatusCode, Value) {
+        let request = Request::post("/v1/chat/completions")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap();
+        let response = router().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (
+            status,
+            serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_changes_are_committed_once_before_handler() {
+        let (status, body) = send(r#"{"model":"original","messages":[]}"#).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["model"], "rewritten");
+        assert_eq!(body["messages"], json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_oversized_body_returns_413() {
+        let (status, body) = send(
+            r#"{"model":"original","messages":[{"role":"user","content":"0123456789abcdef"}]}"#,
+        )
+        .await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(body["error"]["type"], "request_too_large");
+    }
+}
@@ -1,15 +1,33 @@
 // Middleware 模块 - Axum 中间件
 
 pub mod auth;
+pub mod client_adapter;
+pub mod context_window;
 pub mod cors;
+pub mod fallback;
+pub mod json_body;
 pub mod logging;
 pub mod monitor;
+pub mod response_cache;
+pub mod routing;
+pub mod script_hooks;
+pub mod system_prompt;
+pub mod thinking_policy;
 pub mod ip_filter;
 
 pub mod service_status;
 
+pub use client_adapter::client_adapter_middleware;
+pub use context_window::context_window_middleware;
 pub use cors::cors_layer;
+pub use fallback::fallback_middleware;
+pub use json_body::{json_body_commit_middleware, json_body_middleware};
 pub use monitor::monitor_middleware;
+pub use response_cache::response_cache_middleware;
+pub use routing::routing_middleware;
+pub use script_hooks::script_hooks_middleware;
 pub use service_status::service_status_middleware;
+pub use system_prompt::system_prompt_middleware;
+pub use thinking_policy::thinking_policy_middleware;
 pub use auth::{auth_middleware, admin_auth_middleware};
 pub use ip_filter::ip_filter_middleware;
@@ -33,6 +33,16 @@ fn record_user_token_usage(
     }
 }
 
+/// 思考 Token：OpenAI `completion_tokens_details` / Responses `output_tokens_details` / Gemini `thoughtsTokenCount`
+fn reasoning_tokens(usage: &Value) -> Option<u32> {
+    usage
+        .pointer("/completion_tokens_details/reasoning_tokens")
+        .or(usage.pointer("/output_tokens_details/reasoning_tokens"))
+        .or(usage.get("thoughtsTokenCount"))
+        .and_then(|v| v.as_u64())
+        .map(|v| v as u32)
+}
+
 pub async fn monitor_middleware(
     State(state): State<AppState>,
     request: Request,
@@ -138,6 +148,17 @@ pub async fn monitor_middleware(
         .and_then(|v| v.to_str().ok())
         .map(|s| s.to_string());
 
+    // 非 JSON 响应 (字幕 / 音频) 通过头部携带用量
+    let header_tokens = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u32>().ok())
+    };
+    let header_input_tokens = header_tokens("X-Usage-Input-Tokens");
+    let header_output_tokens = header_tokens("X-Usage-Output-Tokens");
+
     // Determine protocol from URL path
     let protocol = if uri.contains("/v1/messages") {
         Some("anthropic".to_string())
@@ -169,10 +190,11 @@ pub async fn monitor_middleware(
         error: None,
         request_body: request_body_str,
         response_body: None,
-        input_tokens: None,
-        output_tokens: None,
+        input_tokens: header_input_tokens,
+        output_tokens: header_output_tokens,
         protocol,
         username,
+        reasoning_tokens: None,
     };
 
 
@@ -350,6 +372,7 @@ pub async fn monitor_middleware(
                                 .or(usage.get("candidatesTokenCount"))
                                 .and_then(|v| v.as_u64())
                                 .map(|v| v as u32);
+                            log.reasoning_tokens = reasoning_tokens(usage);
                             
                             if log.input_tokens.is_none() && log.output_tokens.is_none() {
                                 log.output_tokens = usage.get("total_tokens")
@@ -386,6 +409,9 @@ pub async fn monitor_middleware(
                 if let Some(output) = log.output_tokens {
                     consolidated.insert("output_tokens".to_string(), Value::Number(output.into()));
                 }
+                if let Some(reasoning) = log.reasoning_tokens {
+                    consolidated.insert("reasoning_tokens".to_string(), Value::Number(reasoning.into()));
+                }
                 
                 if consolidated.is_empty() {
                     // Fallback: store raw SSE data if parsing failed
@@ -418,6 +444,7 @@ pub async fn monitor_middleware(
                                         .or(usage.get("candidatesTokenCount"))
                                         .and_then(|v| v.as_u64())
                                         .map(|v| v as u32);
+                                    log.reasoning_tokens = reasoning_tokens(usage);
                                     break;
                                 }
                             }
@@ -455,6 +482,7 @@ pub async fn monitor_middleware(
                                 .or(usage.get("candidatesTokenCount"))
                                 .and_then(|v| v.as_u64())
                                 .map(|v| v as u32);
+                            log.reasoning_tokens = reasoning_tokens(usage);
                                 
                             if log.input_tokens.is_none() && log.output_tokens.is_none() {
                                 log.output_tokens = usage.get("total_tokens")
@@ -0,0 +1,229 @@
+// Response Cache 中间件 - 确定性请求命中缓存时直接返回响应，不进入 handler
+//
+// 位于 fallback 之内、handler 之前：缓存键基于路由、降级与脚本钩子改写后的最终请求体 (json_body 中间件解析)。
+// 响应头 `X-Cache` 为 HIT / MISS / BYPASS，命中时附带 `X-Cache-Age` (秒)。
+// 客户端可通过 `Cache-Control: no-cache` 跳过读取 (仍写入)，`no-store` 完全绕过。
+
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use bytes::{Bytes, BytesMut};
+use futures::StreamExt;
+
+use super::auth::UserTokenIdentity;
+use super::fallback::{gemini_uri_with_model, GeminiModelOverride};
+use super::json_body::{error_response, ParsedBody};
+use crate::proxy::common::model_mapping::wildcard_match;
+use crate::proxy::config::ResponseCacheConfig;
+use crate::proxy::response_cache::{self, CacheHit, CacheableRequest, CachedResponse};
+
+const MAX_CACHE_BODY_SIZE: usize = 100 * 1024 * 1024; // 100MB
+
+pub async fn response_cache_middleware(request: Request, next: Next) -> Response {
+    if request.method() != Method::POST {
+        return next.run(request).await;
+    }
+    let config = crate::proxy::config::get_response_cache_config();
+    if !config.enabled || !response_cache::is_cacheable_path(request.uri().path()) {
+        return next.run(request).await;
+    }
+
+    // 用户令牌需显式加入 users 才使用缓存
+    let identity = request.extensions().get::<UserTokenIdentity>().cloned();
+    let opted_in = match &identity {
+        Some(identity) => config
+            .users
+            .iter()
+            .any(|pattern| wildcard_match(pattern, &identity.username)),
+        None => config.include_api_key,
+    };
+    if !opted_in {
+        return next.run(request).await;
+    }
+
+    let directives = request
+        .headers()
+        .get(header::CACHE_CONTROL)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    if directives.contains("no-store") {
+        response_cache::record_bypass();
+        return with_cache_status(next.run(request).await, "BYPASS");
+    }
+
+    let extensions = request.extensions();
+    let scope = match (&identity, config.shared) {
+        (Some(identity), false) => identity.token_id.as_str(),
+        _ => "",
+    };
+    // Gemini 模型被路由 / 降级替换时，按替换后的模型计算缓存键
+    let path = extensions
+        .get::<GeminiModelOverride>()
+        .and_then(|o| gemini_uri_with_model(request.uri(), &o.0))
+        .map(|uri| uri.path().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+    // 缓存键基于 json_body 中间件解析、并经前序中间件改写后的最终请求体
+    let cacheable = extensions
+        .get::<ParsedBody>()
+        .and_then(ParsedBody::json)
+        .and_then(|json| {
+            response_cache::cacheable_request(&path, json, scope, config.deterministic_only)
+        });
+    let Some(cacheable) = cacheable else {
+        response_cache::record_bypass();
+        return with_cache_status(next.run(request).await, "BYPASS");
+    };
+
+    if !directives.contains("no-cache") {
+        if let Some(hit) = response_cache::lookup(&cacheable.key, &config) {
+            tracing::info!(
+                "[ResponseCache] Hit {:?} (stream: {}, age: {}s)",
+                cacheable.protocol,
+                cacheable.stream,
+                hit.age.as_secs()
+            );
+            return hit_response(&cacheable, hit);
+        }
+    }
+
+    let response = next.run(request).await;
+    if response.status() != StatusCode::OK {
+        return with_cache_status(response, "MISS");
+    }
+    store_response(response, cacheable, config).await
+}
+
+fn with_cache_status(mut response: Response, status: &'static str) -> Response {
+    response
+        .headers_mut()
+        .insert("X-Cache", HeaderValue::from_static(status));
+    response
+}
+
+/// 按客户端请求的流式模式返回缓存的响应
+fn hit_response(cacheable: &CacheableRequest, hit: CacheHit) -> Response {
+    let body = &hit.response.body;
+    let mut builder = Response::builder()
+        .status(StatusCode::OK)
+        .header("X-Cache", "HIT")
+        .header("X-Cache-Age", hit.age.as_secs().to_string());
+    if let Some(model) = hit
+        .response
+        .mapped_model
+        .as_deref()
+        .and_then(|m| HeaderValue::from_str(m).ok())
+    {
+        builder = builder.header("X-Mapped-Model", model);
+    }
+    let builder = if cacheable.stream {
+        builder
+            .header(header::CONTENT_TYPE, "text/event-stream")
+            .header(header::CACHE_CONTROL, "no-cache")
+    } else {
+        builder.header(header::CONTENT_TYPE, "application/json")
+    };
+    let payload = if cacheable.stream {
+        response_cache::replay_sse(cacheable.protocol, body)
+    } else {
+        body.to_string()
+    };
+    builder.body(Body::from(payload)).unwrap()
+}
+
+/// 未命中：透传响应，完整结束后写入缓存
+async fn store_response(
+    response: Response,
+    cacheable: CacheableRequest,
+    config: ResponseCacheConfig,
+) -> Response {
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let mapped_model = response
+        .headers()
+        .get("X-Mapped-Model")
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+    let max_bytes = (config.max_size_mb as usize).saturating_mul(1024 * 1024);
+
+    let (mut parts, body) = response.into_parts();
+    parts
+        .headers
+        .insert("X-Cache", HeaderValue::from_static("MISS"));
+
+    if cacheable.stream && content_type.contains("text/event-stream") {
+        let mut upstream = body.into_data_stream();
+        let stream = async_stream::stream! {
+            let mut captured = BytesMut::new();
+            let mut overflow = false;
+            let mut failed = false;
+            while let Some(chunk) = upstream.next().await {
+                match chunk {
+                    Ok(bytes) => {
+                        if !overflow {
+                            if captured.len() + bytes.len() > max_bytes {
+                                overflow = true;
+                                captured = BytesMut::new();
+                            } else {
+                                captured.extend_from_slice(&bytes);
+                            }
+                        }
+                        yield Ok::<Bytes, axum::Error>(bytes);
+                    }
+                    Err(e) => {
+                        failed = true;
+                        yield Err(e);
+                        break;
+                    }
+                }
+            }
+            if !failed && !overflow {
+                if let Some(body) = response_cache::collect_sse(cacheable.protocol, captured.freeze()).await {
+                    response_cache::store(
+                        cacheable.key,
+                        CachedResponse { protocol: cacheable.protocol, body, mapped_model },
+                        &config,
+                    );
+                }
+            }
+        };
+        return Response::from_parts(parts, Body::from_stream(stream));
+    }
+
+    if cacheable.stream || !content_type.contains("application/json") {
+        return Response::from_parts(parts, body);
+    }
+    let bytes = match axum::body::to_bytes(body, MAX_CACHE_BODY_SIZE).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::warn!("[ResponseCache] Failed to read response body: {}", e);
+            return error_response(
+                cacheable.protocol,
+                StatusCode::BAD_GATEWAY,
+                "Failed to read upstream response body",
+            );
+        }
+    };
+    if let Ok(json) = serde_json::from_slice(&bytes) {
+        if response_cache::is_complete(cacheable.protocol, &json) {
+            response_cache::store(
+                cacheable.key,
+                CachedResponse {
+                    protocol: cacheable.protocol,
+                    body: json,
+                    mapped_model,
+                },
+                &config,
+            );
+        }
+    }
+    Response::from_parts(parts, Body::from(bytes))
+}
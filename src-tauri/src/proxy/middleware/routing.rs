@@ -0,0 +1,233 @@
+// Routing 中间件 - 按路由规则改写模型并为请求设置账号分组 / Thinking Budget 覆盖
+//
+// 位于 monitor 之内、fallback 之前：先按规则确定目标模型，再由 fallback 判断该模型是否需要降级。
+// 响应头 `X-Route-Rule` 列出命中的规则。
+
+use axum::{
+    extract::Request,
+    http::{request::Parts, HeaderValue, Method},
+    middleware::Next,
+    response::Response,
+};
+use serde_json::Value;
+
+use super::auth::UserTokenIdentity;
+use super::fallback::{gemini_request_model, protocol_for_path, GeminiModelOverride};
+use super::json_body::ParsedBody;
+use crate::proxy::config::RoutingConfig;
+use crate::proxy::routing::{evaluate, with_overrides, RequestFacts, RouteDecision};
+use crate::proxy::ProviderProtocol;
+
+/// 路由评估结果
+pub(crate) struct RoutedRequest {
+    pub facts: RequestFacts,
+    pub decision: RouteDecision,
+    /// 改写后的模型 (规则未改写模型时为 None)
+    pub target: Option<String>,
+    /// 请求体中的 `model` 是否被改写
+    pub body_changed: bool,
+}
+
+/// 评估路由规则并把目标模型写入请求 (中间件与 dry-run 共用)
+///
+/// OpenAI / Claude 改写请求体中的 `model`；Gemini 的模型位于路径中，路由完成后路径参数无法改写，
+/// 改为写入 [`GeminiModelOverride`] 扩展，由 Gemini handler 优先读取。
+pub(crate) fn apply_routing(
+    config: &RoutingConfig,
+    protocol: Option<ProviderProtocol>,
+    parts: &mut Parts,
+    mut json: Option<&mut Value>,
+    body_bytes: usize,
+    user: Option<String>,
+) -> RoutedRequest {
+    let original = if protocol == Some(ProviderProtocol::Gemini) {
+        gemini_request_model(&parts.extensions, &parts.uri)
+    } else {
+        json.as_deref()
+            .and_then(|v| v.get("model"))
+            .and_then(|m| m.as_str())
+            .map(|s| s.to_string())
+    };
+    let facts = RequestFacts::new(
+        protocol,
+        original.clone(),
+        user,
+        &parts.headers,
+        json.as_deref(),
+        body_bytes,
+    );
+    let decision = evaluate(config, &facts);
+    let target = decision.target_model(original.as_deref());
+
+    let mut body_changed = false;
+    match (&target, protocol) {
+        (Some(model), Some(ProviderProtocol::Gemini)) => {
+            parts.extensions.insert(GeminiModelOverride(model.clone()));
+        }
+        (Some(model), _) => {
+            if let Some(v) = json.as_deref_mut() {
+                v["model"] = Value::String(model.clone());
+                body_changed = true;
+            }
+        }
+        (None, _) => {}
+    }
+    RoutedRequest {
+        facts,
+        decision,
+        target,
+        body_changed,
+    }
+}
+
+pub async fn routing_middleware(request: Request, next: Next) -> Response {
+    if request.method() != Method::POST {
+        return next.run(request).await;
+    }
+    let Some(protocol) = protocol_for_path(request.uri().path()) else {
+        return next.run(request).await;
+    };
+    let config = crate::proxy::config::get_routing_config();
+    if config.rules.is_empty() {
+        return next.run(request).await;
+    }
+
+    let user = request
+        .extensions()
+        .get::<UserTokenIdentity>()
+        .map(|identity| identity.username.clone());
+    let (mut parts, body) = request.into_parts();
+    // 请求体由 json_body 中间件解析，改写后由 commit 层统一写回
+    let mut parsed = parts.extensions.remove::<ParsedBody>();
+    let body_bytes = parsed.as_ref().map_or(0, ParsedBody::len);
+    let routed = apply_routing(
+        &config,
+        Some(protocol),
+        &mut parts,
+        parsed.as_mut().and_then(ParsedBody::json_mut),
+        body_bytes,
+        user,
+    );
+    if let Some(mut parsed) = parsed {
+        if routed.body_changed {
+            parsed.mark_changed();
+        }
+        parts.extensions.insert(parsed);
+    }
+    let request = Request::from_parts(parts, body);
+    let decision = routed.decision;
+    if decision.is_empty() {
+        return next.run(request).await;
+    }
+
+    tracing::info!(
+        "[Routing] Rules [{}] matched: model {} -> {}, account_group={:?}, thinking_budget={}",
+        decision.matched_rules.join(", "),
+        routed.facts.model.as_deref().unwrap_or("-"),
+        routed
+            .target
+            .as_deref()
+            .or(routed.facts.model.as_deref())
+            .unwrap_or("-"),
+        decision.account_group,
+        decision.thinking_budget.is_some()
+    );
+
+    let overrides = decision.overrides(&config);
+    let mut response = with_overrides(overrides, next.run(request)).await;
+    if let Ok(v) = HeaderValue::from_str(&decision.matched_rules.join(",")) {
+        response.headers_mut().insert("X-Route-Rule", v);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::extract::{Extension, Path};
+    use axum::http::header;
+    use axum::routing::post;
+    use tower::ServiceExt;
+
+    fn config() -> RoutingConfig {
+        serde_json::from_value(serde_json::json!({
+            "rules": [{
+                "name": "pro-to-flash",
+                "match": { "model": "route-test-pro" },
+                "action": { "model": "route-test-flash" }
+            }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_apply_routing_rewrites_body_or_extension() {
+        let config = config();
+
+        let (mut parts, _) = Request::post("/v1/messages").body(()).unwrap().into_parts();
+        let mut body = serde_json::json!({ "model": "route-test-pro" });
+        let routed = apply_routing(
+            &config,
+            Some(ProviderProtocol::Anthropic),
+            &mut parts,
+            Some(&mut body),
+            0,
+            None,
+        );
+        assert!(routed.body_changed);
+        assert_eq!(body["model"], "route-test-flash");
+
+        let (mut parts, _) = Request::post("/v1beta/models/route-test-pro:generateContent")
+            .body(())
+            .unwrap()
+            .into_parts();
+        let routed = apply_routing(
+            &config,
+            Some(ProviderProtocol::Gemini),
+            &mut parts,
+            None,
+            0,
+            None,
+        );
+        assert!(!routed.body_changed);
+        assert_eq!(routed.target.as_deref(), Some("route-test-flash"));
+        assert_eq!(
+            gemini_request_model(&parts.extensions, &parts.uri).as_deref(),
+            Some("route-test-flash")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_gemini_route_reaches_handler_through_router() {
+        async fn echo_model(
+            Path(model_action): Path<String>,
+            model_override: Option<Extension<GeminiModelOverride>>,
+        ) -> String {
+            crate::proxy::handlers::gemini::resolve_model_action(
+                model_action,
+                model_override.as_ref().map(|e| &e.0),
+            )
+            .0
+        }
+
+        crate::proxy::config::update_routing_config(config());
+        let app = axum::Router::new()
+            .route("/v1beta/models/:model", post(echo_model))
+            .layer(axum::middleware::from_fn(routing_middleware));
+        let response = app
+            .oneshot(
+                Request::post("/v1beta/models/route-test-pro:generateContent")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from("{}"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["X-Route-Rule"], "pro-to-flash");
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&bytes[..], b"route-test-flash");
+    }
+}
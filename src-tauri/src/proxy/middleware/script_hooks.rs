@@ -0,0 +1,140 @@
+// Script Hooks 中间件 - 对客户端请求执行 request 钩子，对 SSE 响应执行 sse_event 钩子
+//
+// 位于 fallback 之内、response_cache 之前：request 钩子看到的是客户端适配器改写后的请求，
+// 其结果参与缓存键计算。upstream_request 钩子由 handler 在协议转换后调用。
+
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, Method},
+    middleware::Next,
+    response::Response,
+};
+use bytes::Bytes;
+use futures::StreamExt;
+use std::sync::Arc;
+
+use super::auth::UserTokenIdentity;
+use super::fallback::{gemini_request_model, protocol_for_path};
+use super::json_body::ParsedBody;
+use crate::proxy::common::client_adapter::find_client_adapter;
+use crate::proxy::config::ScriptHookStage;
+use crate::proxy::script_hooks::{
+    has_hooks, protocol_name, reject_response, run_stage_blocking, with_caller, HookCaller,
+    HookContext, HookOutcome, SseHookProcessor,
+};
+
+pub async fn script_hooks_middleware(mut request: Request, next: Next) -> Response {
+    if request.method() != Method::POST {
+        return next.run(request).await;
+    }
+    let Some(protocol) = protocol_for_path(request.uri().path()) else {
+        return next.run(request).await;
+    };
+    let request_hooks = has_hooks(ScriptHookStage::Request, protocol);
+    let sse_hooks = has_hooks(ScriptHookStage::SseEvent, protocol);
+    if !request_hooks && !sse_hooks && !has_hooks(ScriptHookStage::UpstreamRequest, protocol) {
+        return next.run(request).await;
+    }
+
+    let caller = Arc::new(HookCaller {
+        user: request
+            .extensions()
+            .get::<UserTokenIdentity>()
+            .map(|identity| identity.username.clone()),
+        client: find_client_adapter(request.headers()).map(|adapter| adapter.name().to_string()),
+        // handler 通过 current_trace_id 复用同一 trace id，便于关联钩子日志
+        trace_id: format!("req_{}", uuid::Uuid::new_v4().simple()),
+    });
+    let trace_id = caller.trace_id.clone();
+    // 仅 upstream_request 钩子时无需读取请求体，handler 通过调用方作用域取得用户与客户端
+    if !request_hooks && !sse_hooks {
+        return with_caller(caller, next.run(request)).await;
+    }
+
+    // 请求体由 json_body 中间件解析，钩子的修改写回 ParsedBody，在进入 handler 前统一序列化
+    let mut parsed = request.extensions_mut().remove::<ParsedBody>();
+    let model = parsed
+        .as_ref()
+        .and_then(ParsedBody::json)
+        .and_then(|j| j.get("model"))
+        .and_then(|m| m.as_str())
+        .map(str::to_string)
+        .or_else(|| gemini_request_model(request.extensions(), request.uri()))
+        .unwrap_or_default();
+
+    if let Some(parsed) = parsed.as_mut().filter(|_| request_hooks) {
+        if let Some(json) = parsed.json_mut() {
+            let ctx = HookContext {
+                stage: ScriptHookStage::Request,
+                protocol: protocol_name(protocol).to_string(),
+                model: model.clone(),
+                user: caller.user.clone(),
+                client: caller.client.clone(),
+                event: None,
+                trace_id: trace_id.clone(),
+            };
+            let (rewritten, outcome) = run_stage_blocking(json.clone(), ctx).await;
+            if let HookOutcome::Reject(msg) = outcome {
+                return reject_response(protocol, &msg);
+            }
+            if rewritten != *json {
+                *json = rewritten;
+                parsed.mark_changed();
+            }
+        }
+    }
+    if let Some(parsed) = parsed {
+        request.extensions_mut().insert(parsed);
+    }
+
+    with_caller(caller, async move {
+        let response = next.run(request).await;
+        let is_sse = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|ct| ct.contains("text/event-stream"));
+        if !sse_hooks || !is_sse {
+            return response;
+        }
+        let processor = SseHookProcessor::new(protocol, &model, &trace_id);
+        rewrite_sse_response(response, processor)
+    })
+    .await
+}
+
+/// 按事件 (空行分隔) 缓冲 SSE 流，交给钩子逐个处理
+fn rewrite_sse_response(response: Response, processor: SseHookProcessor) -> Response {
+    let (mut parts, body) = response.into_parts();
+    parts.headers.remove(header::CONTENT_LENGTH);
+    let stream = futures::stream::unfold(
+        (body.into_data_stream(), Vec::<u8>::new(), processor, false),
+        |(mut inner, mut pending, processor, finished)| async move {
+            if finished {
+                return None;
+            }
+            match inner.next().await {
+                Some(Ok(chunk)) => {
+                    pending.extend_from_slice(&chunk);
+                    let out = match pending.windows(2).rposition(|w| w == b"\n\n") {
+                        Some(boundary) => {
+                            let complete: Vec<u8> = pending.drain(..boundary + 2).collect();
+                            processor.rewrite_events_blocking(complete).await
+                        }
+                        None => Vec::new(),
+                    };
+                    Some((Ok(Bytes::from(out)), (inner, pending, processor, false)))
+                }
+                Some(Err(e)) => Some((Err(e), (inner, pending, processor, true))),
+                // 流结束时处理残留的不完整事件
+                None if !pending.is_empty() => {
+                    let out = processor.rewrite_events_blocking(pending).await;
+                    Some((Ok(Bytes::from(out)), (inner, Vec::new(), processor, true)))
+                }
+                None => None,
+            }
+        },
+    );
+    Response::from_parts(parts, Body::from_stream(stream))
+}
@@ -0,0 +1,32 @@
+// System Prompt 中间件 - 收集调用方特征 (用户令牌 / 客户端适配器) 供提示词片段匹配
+//
+// 位于 thinking_policy 之内、client_adapter 之前：片段依赖协议与映射后的模型，由请求转换逻辑选择并注入。
+
+use axum::{extract::Request, http::Method, middleware::Next, response::Response};
+use std::sync::Arc;
+
+use super::auth::UserTokenIdentity;
+use super::fallback::protocol_for_path;
+use crate::proxy::common::client_adapter::find_client_adapter;
+use crate::proxy::system_prompt::{with_caller, PromptCaller};
+
+pub async fn system_prompt_middleware(request: Request, next: Next) -> Response {
+    if request.method() != Method::POST || protocol_for_path(request.uri().path()).is_none() {
+        return next.run(request).await;
+    }
+    if crate::proxy::config::get_global_system_prompt()
+        .prompts
+        .is_empty()
+    {
+        return next.run(request).await;
+    }
+
+    let caller = PromptCaller {
+        user: request
+            .extensions()
+            .get::<UserTokenIdentity>()
+            .map(|identity| identity.username.clone()),
+        client: find_client_adapter(request.headers()).map(|adapter| adapter.name().to_string()),
+    };
+    with_caller(Arc::new(caller), next.run(request)).await
+}
@@ -0,0 +1,55 @@
+// Thinking Policy 中间件 - 收集调用方特征 (用户令牌 / 客户端适配器 / 后台任务) 供思考策略匹配
+//
+// 位于 context_window 之内、client_adapter 之前：策略依赖映射后的模型，由 handler 在转换请求后选择并执行。
+// 响应头 `X-Thinking-Policy` 为生效的策略名称。
+
+use axum::{
+    extract::Request,
+    http::{HeaderValue, Method},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+
+use super::auth::UserTokenIdentity;
+use super::fallback::protocol_for_path;
+use super::json_body::ParsedBody;
+use crate::proxy::common::client_adapter::find_client_adapter;
+use crate::proxy::common::task_detection::{detect_task_type, last_user_text};
+use crate::proxy::thinking_policy::{with_caller, CallerFacts};
+
+pub async fn thinking_policy_middleware(request: Request, next: Next) -> Response {
+    if request.method() != Method::POST || protocol_for_path(request.uri().path()).is_none() {
+        return next.run(request).await;
+    }
+    let policies = crate::proxy::config::get_thinking_policies();
+    if policies.is_empty() {
+        return next.run(request).await;
+    }
+
+    let user = request
+        .extensions()
+        .get::<UserTokenIdentity>()
+        .map(|identity| identity.username.clone());
+    let client = find_client_adapter(request.headers()).map(|adapter| adapter.name().to_string());
+
+    // 仅在有策略按后台任务区分时才检查请求体 (由 json_body 中间件解析)
+    let background = policies.iter().any(|p| p.background.is_some())
+        && request
+            .extensions()
+            .get::<ParsedBody>()
+            .and_then(ParsedBody::json)
+            .and_then(last_user_text)
+            .and_then(|text| detect_task_type(&text))
+            .is_some();
+
+    let caller = Arc::new(CallerFacts::new(user, client, background));
+    let mut response = with_caller(caller.clone(), next.run(request)).await;
+    if let Some(name) = caller
+        .applied_policy()
+        .and_then(|name| HeaderValue::from_str(&name).ok())
+    {
+        response.headers_mut().insert("X-Thinking-Policy", name);
+    }
+    response
+}
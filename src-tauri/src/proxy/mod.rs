@@ -2,6 +2,7 @@
 
 // 现有模块 (保留)
 pub mod config;
+pub mod config_reload; // 配置文件热重载
 pub mod project_resolver;
 pub mod security;
 pub mod server;
@@ -12,7 +13,9 @@ pub mod audio; // 音频处理模块
 pub mod cli_sync; // CLI 配置同步 (v3.3.35)
 pub mod droid_sync; // Droid (Factory CLI) 配置同步
 pub mod common; // 公共工具
+pub mod context_window; // 上下文窗口策略 (滑动窗口 / 摘要)
 pub mod debug_logger;
+pub mod gemini_resources; // Gemini Files API / cachedContents 本地模拟
 pub mod handlers; // API 端点处理器
 pub mod mappers; // 协议转换器
 pub mod middleware; // Axum 中间件
@@ -21,10 +24,16 @@ pub mod opencode_sync; // OpenCode 配置同步
 pub mod providers; // Extra upstream providers (z.ai, etc.)
 pub mod proxy_pool; // 代理池管理器
 pub mod rate_limit; // 限流跟踪
+pub mod response_cache; // 确定性请求的响应缓存
+pub mod routing; // 路由规则引擎
+pub mod script_hooks; // Rhai 脚本钩子 (请求 / 上游请求 / SSE 事件)
 pub mod model_specs; // 模型规格管理 (v4.1.29)
 pub mod session_manager; // 会话指纹管理
 pub mod signature_cache; // Signature Cache (v3.3.16)
+pub mod signature_persistence; // 签名缓存落盘与恢复
 pub mod sticky_config; // 粘性调度配置
+pub mod system_prompt; // 按条件注入的系统提示词片段
+pub mod thinking_policy; // 按模型家族 / 调用方的思考策略
 pub mod upstream; // 上游客户端
 pub mod zai_vision_mcp; // Built-in Vision MCP server state
 pub mod zai_vision_tools; // Built-in Vision MCP tools (z.ai vision API) // 调试日志
@@ -32,9 +41,20 @@ pub mod zai_vision_tools; // Built-in Vision MCP tools (z.ai vision API) // 调
 pub use config::update_global_system_prompt_config;
 pub use config::update_thinking_budget_config;
 pub use config::update_image_thinking_mode;
+pub use config::update_fallback_chains;
+pub use config::update_dispatch_config;
+pub use config::update_routing_config;
+pub use config::update_signature_cache_config;
+pub use config::update_response_cache_config;
+pub use config::update_context_window_config;
+pub use common::client_adapter::update_client_adapters;
+pub use common::json_schema::update_tool_adapters;
+pub use script_hooks::update_script_hooks;
 pub use config::ProxyAuthMode;
 pub use config::ProxyConfig;
 pub use config::ProxyPoolConfig;
+pub use config::ProviderProtocol;
+pub use config::UpstreamProviderConfig;
 pub use config::ZaiConfig;
 pub use config::ZaiDispatchMode;
 pub use security::ProxySecurityConfig;
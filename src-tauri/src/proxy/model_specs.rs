@@ -5,6 +5,8 @@ use crate::proxy::token_manager::ProxyToken;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelSpec {
+    #[serde(default)]
+    pub max_input_tokens: Option<u64>,
     pub max_output_tokens: Option<u64>,
     pub thinking_budget: Option<u64>,
     pub is_thinking: Option<bool>,
@@ -26,6 +28,26 @@ pub fn resolve_alias(model_id: &str) -> String {
     SPECS.aliases.get(model_id).cloned().unwrap_or_else(|| model_id.to_string())
 }
 
+/// 获取模型的静态规格 (基于别名归一化)
+pub fn get_spec(model_id: &str) -> Option<ModelSpec> {
+    SPECS.models.get(&resolve_alias(model_id)).cloned()
+}
+
+/// 获取模型上下文 (输入) Token 限额：静态 JSON 优先，否则按模型家族兜底
+pub fn get_max_input_tokens(model_id: &str) -> u64 {
+    if let Some(limit) = get_spec(model_id).and_then(|spec| spec.max_input_tokens) {
+        return limit;
+    }
+    let lower = model_id.to_lowercase();
+    if lower.contains("claude") {
+        200_000
+    } else if lower.contains("gpt-oss") {
+        131_072
+    } else {
+        1_048_576
+    }
+}
+
 /// 获取模型输出 Token 限额 (动态优先)
 pub fn get_max_output_tokens(model_id: &str, token: Option<&ProxyToken>) -> u64 {
     let std_id = resolve_alias(model_id);
@@ -72,7 +94,6 @@ pub fn get_thinking_budget(model_id: &str, _token: Option<&ProxyToken>) -> u64 {
 }
 
 /// 判断是否为思维模型
-#[allow(dead_code)]
 pub fn is_thinking_model(model_id: &str) -> bool {
     let std_id = resolve_alias(model_id);
     if let Some(spec) = SPECS.models.get(&std_id) {
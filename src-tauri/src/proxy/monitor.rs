@@ -1,7 +1,7 @@
 use serde::{Serialize, Deserialize};
 use std::collections::VecDeque;
 use tokio::sync::RwLock;
-use tauri::Emitter;
+use crate::modules::integration::{emit_event, AppHandle};
 use std::sync::atomic::{AtomicBool, Ordering};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,6 +23,8 @@ pub struct ProxyRequestLog {
     pub output_tokens: Option<u32>,
     pub protocol: Option<String>,     // 协议类型: "openai", "anthropic", "gemini"
     pub username: Option<String>,     // User token username
+    #[serde(default)]
+    pub reasoning_tokens: Option<u32>, // 思考 Token (包含在 output_tokens 之外)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -37,11 +39,11 @@ pub struct ProxyMonitor {
     pub stats: RwLock<ProxyStats>,
     pub max_logs: usize,
     pub enabled: AtomicBool,
-    app_handle: Option<tauri::AppHandle>,
+    app_handle: Option<AppHandle>,
 }
 
 impl ProxyMonitor {
-    pub fn new(max_logs: usize, app_handle: Option<tauri::AppHandle>) -> Self {
+    pub fn new(max_logs: usize, app_handle: Option<AppHandle>) -> Self {
         // Initialize DB
         if let Err(e) = crate::modules::proxy_db::init_db() {
             tracing::error!("Failed to initialize proxy DB: {}", e);
@@ -86,8 +88,9 @@ impl ProxyMonitor {
         ) {
             let model = log.model.clone().unwrap_or_else(|| "unknown".to_string());
             let account = account.clone();
+            let reasoning = log.reasoning_tokens.unwrap_or(0);
             tokio::spawn(async move {
-                if let Err(e) = crate::modules::token_stats::record_usage(&account, &model, input, output) {
+                if let Err(e) = crate::modules::token_stats::record_usage(&account, &model, input, output, reasoning) {
                     tracing::debug!("Failed to record token stats: {}", e);
                 }
             });
@@ -153,7 +156,8 @@ impl ProxyMonitor {
                 log_to_save.output_tokens,
             ) {
                 let model = log_to_save.model.clone().unwrap_or_else(|| "unknown".to_string());
-                if let Err(e) = crate::modules::token_stats::record_usage(account, &model, input, output) {
+                let reasoning = log_to_save.reasoning_tokens.unwrap_or(0);
+                if let Err(e) = crate::modules::token_stats::record_usage(account, &model, input, output, reasoning) {
                     tracing::debug!("Failed to record token stats: {}", e);
                 }
             }
@@ -179,8 +183,9 @@ impl ProxyMonitor {
                 output_tokens: log.output_tokens,
                 protocol: log.protocol.clone(),
                 username: log.username.clone(),
+                reasoning_tokens: log.reasoning_tokens,
             };
-            let _ = app.emit("proxy://request", &log_summary);
+            emit_event(app, "proxy://request", &log_summary);
         }
     }
 
@@ -0,0 +1,321 @@
+//! 通用第三方上游提供商 (OpenAI / Anthropic / Gemini 协议透传)
+//!
+//! 调度语义沿用 z.ai 的 `ZaiDispatchMode`：
+//! - `exclusive`: 接管所有匹配的请求
+//! - `pooled`: 作为共享池中的额外槽位参与轮询
+//! - `fallback`: 仅当 Google 账号池不可用时使用
+//! - `off`: 仅能通过 `名称:模型` 前缀显式使用
+
+use axum::{
+    body::Body,
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode},
+    response::{IntoResponse, Response},
+};
+use bytes::Bytes;
+use futures::StreamExt;
+use serde_json::Value;
+use std::sync::atomic::Ordering;
+
+use super::zai_anthropic::{build_client, copy_passthrough_headers, join_base_url};
+use crate::proxy::common::model_mapping::{normalize_to_standard_id, wildcard_match};
+use crate::proxy::server::AppState;
+use crate::proxy::{ProviderProtocol, UpstreamProviderConfig, ZaiDispatchMode};
+
+/// 请求被路由到的提供商及其上游模型名
+#[derive(Debug, Clone)]
+pub struct ProviderRoute {
+    pub provider: UpstreamProviderConfig,
+    pub upstream_model: String,
+}
+
+/// 将客户端模型映射为上游模型，返回 None 表示该提供商不接管此模型
+fn map_model(provider: &UpstreamProviderConfig, model: &str) -> Option<String> {
+    if provider.model_mapping.is_empty() {
+        return Some(model.to_string());
+    }
+    if let Some(target) = provider.model_mapping.get(model) {
+        return Some(target.clone());
+    }
+    // 通配符: 非通配字符最多的规则优先 (与 resolve_model_route 一致)
+    provider
+        .model_mapping
+        .iter()
+        .filter(|(pattern, _)| pattern.contains('*') && wildcard_match(pattern, model))
+        .max_by_key(|(pattern, _)| pattern.chars().count() - pattern.matches('*').count())
+        .map(|(_, target)| target.clone())
+}
+
+/// `名称:模型` 形式的显式路由 (忽略 dispatch_mode)
+fn explicit_route(providers: &[&UpstreamProviderConfig], model: &str) -> Option<ProviderRoute> {
+    let (prefix, rest) = model.split_once(':')?;
+    let provider = providers
+        .iter()
+        .find(|p| p.name.eq_ignore_ascii_case(prefix))?;
+    Some(ProviderRoute {
+        provider: (*provider).clone(),
+        upstream_model: map_model(provider, rest).unwrap_or_else(|| rest.to_string()),
+    })
+}
+
+/// 按协议与模型挑选应接管请求的提供商
+///
+/// 优先级: 显式前缀 > exclusive > pooled (与 Google 账号数共同轮询) > fallback。
+pub async fn resolve_route(
+    state: &AppState,
+    protocol: ProviderProtocol,
+    model: &str,
+    trace_id: &str,
+) -> Option<ProviderRoute> {
+    let providers = state.providers.read().await;
+    let candidates: Vec<&UpstreamProviderConfig> = providers
+        .iter()
+        .filter(|p| p.enabled && p.protocol == protocol)
+        .collect();
+    if candidates.is_empty() {
+        return None;
+    }
+
+    if let Some(route) = explicit_route(&candidates, model) {
+        tracing::info!(
+            "[{}] Explicit provider route: {} -> {}:{}",
+            trace_id,
+            model,
+            route.provider.name,
+            route.upstream_model
+        );
+        return Some(route);
+    }
+
+    let serving: Vec<ProviderRoute> = candidates
+        .iter()
+        .filter(|p| p.dispatch_mode != ZaiDispatchMode::Off)
+        .filter_map(|p| {
+            map_model(p, model).map(|upstream_model| ProviderRoute {
+                provider: (*p).clone(),
+                upstream_model,
+            })
+        })
+        .collect();
+    drop(providers);
+
+    if let Some(route) = serving
+        .iter()
+        .find(|r| r.provider.dispatch_mode == ZaiDispatchMode::Exclusive)
+    {
+        return Some(route.clone());
+    }
+
+    let google_accounts = state.token_manager.len();
+    let pooled: Vec<&ProviderRoute> = serving
+        .iter()
+        .filter(|r| r.provider.dispatch_mode == ZaiDispatchMode::Pooled)
+        .collect();
+    if !pooled.is_empty() {
+        let total = google_accounts.saturating_add(pooled.len());
+        let slot = state.provider_rr.fetch_add(1, Ordering::Relaxed) % total;
+        if slot < pooled.len() {
+            return Some(pooled[slot].clone());
+        }
+    }
+
+    if let Some(route) = serving
+        .iter()
+        .find(|r| r.provider.dispatch_mode == ZaiDispatchMode::Fallback)
+    {
+        let normalized = normalize_to_standard_id(model).unwrap_or_else(|| model.to_string());
+        if google_accounts == 0
+            || !state
+                .token_manager
+                .has_available_account("", &normalized)
+                .await
+        {
+            tracing::info!(
+                "[{}] Google pool unavailable for {}, using fallback provider {}",
+                trace_id,
+                model,
+                route.provider.name
+            );
+            return Some(route.clone());
+        }
+    }
+
+    None
+}
+
+/// 请求是否无论账号池状态都会交给提供商处理 (显式前缀或 exclusive)
+pub async fn bypasses_pool(state: &AppState, protocol: ProviderProtocol, model: &str) -> bool {
+    let providers = state.providers.read().await;
+    let candidates: Vec<&UpstreamProviderConfig> = providers
+        .iter()
+        .filter(|p| p.enabled && p.protocol == protocol)
+        .collect();
+    explicit_route(&candidates, model).is_some()
+        || candidates
+            .iter()
+            .any(|p| p.dispatch_mode == ZaiDispatchMode::Exclusive && map_model(p, model).is_some())
+}
+
+fn set_provider_auth(headers: &mut HeaderMap, protocol: ProviderProtocol, api_key: &str) {
+    // 自建服务可能不需要鉴权
+    if api_key.trim().is_empty() {
+        return;
+    }
+    let (name, value) = match protocol {
+        ProviderProtocol::Openai => (header::AUTHORIZATION, format!("Bearer {}", api_key)),
+        ProviderProtocol::Anthropic => (HeaderName::from_static("x-api-key"), api_key.to_string()),
+        ProviderProtocol::Gemini => (
+            HeaderName::from_static("x-goog-api-key"),
+            api_key.to_string(),
+        ),
+    };
+    if let Ok(v) = HeaderValue::from_str(&value) {
+        headers.insert(name, v);
+    }
+}
+
+/// 将请求透传给提供商并流式返回上游响应
+///
+/// `path` 相对于提供商的 base_url；OpenAI/Anthropic 协议会把 body 中的 `model` 改写为上游模型名，
+/// Gemini 协议的模型位于路径中，由调用方拼接。
+pub async fn forward(
+    state: &AppState,
+    route: &ProviderRoute,
+    path: &str,
+    incoming_headers: &HeaderMap,
+    mut body: Value,
+) -> Response {
+    let provider = &route.provider;
+    if provider.protocol != ProviderProtocol::Gemini && body.get("model").is_some() {
+        body["model"] = Value::String(route.upstream_model.clone());
+    }
+
+    let url = match join_base_url(&provider.base_url, path) {
+        Ok(u) => u,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+
+    let upstream_proxy = state.upstream_proxy.read().await.clone();
+    let client = match build_client(Some(upstream_proxy), state.request_timeout) {
+        Ok(c) => c,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    };
+
+    let mut headers = copy_passthrough_headers(incoming_headers);
+    set_provider_auth(&mut headers, provider.protocol, &provider.api_key);
+    headers
+        .entry(header::CONTENT_TYPE)
+        .or_insert(HeaderValue::from_static("application/json"));
+    if provider.protocol == ProviderProtocol::Anthropic {
+        headers
+            .entry("anthropic-version")
+            .or_insert(HeaderValue::from_static("2023-06-01"));
+    }
+
+    let body_bytes = serde_json::to_vec(&body).unwrap_or_default();
+    tracing::debug!(
+        "Forwarding request to provider {} (len: {} bytes): {}",
+        provider.name,
+        body_bytes.len(),
+        url
+    );
+
+    let resp = match client
+        .request(Method::POST, &url)
+        .headers(headers)
+        .body(body_bytes)
+        .send()
+        .await
+    {
+        Ok(r) => r,
+        Err(e) => {
+            return (
+                StatusCode::BAD_GATEWAY,
+                [("X-Upstream-Provider", provider.name.as_str())],
+                format!("Upstream provider {} request failed: {}", provider.name, e),
+            )
+                .into_response();
+        }
+    };
+
+    let status = StatusCode::from_u16(resp.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+    let mut out = Response::builder()
+        .status(status)
+        .header("X-Upstream-Provider", provider.name.as_str())
+        .header("X-Mapped-Model", route.upstream_model.as_str());
+    if let Some(ct) = resp.headers().get(header::CONTENT_TYPE) {
+        out = out.header(header::CONTENT_TYPE, ct.clone());
+    }
+
+    let stream = resp.bytes_stream().map(|chunk| match chunk {
+        Ok(b) => Ok::<Bytes, std::io::Error>(b),
+        Err(e) => Ok(Bytes::from(format!("Upstream stream error: {}", e))),
+    });
+
+    out.body(Body::from_stream(stream)).unwrap_or_else(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to build response",
+        )
+            .into_response()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn provider(name: &str, mapping: &[(&str, &str)]) -> UpstreamProviderConfig {
+        UpstreamProviderConfig {
+            name: name.to_string(),
+            enabled: true,
+            base_url: "http://localhost:8000/v1".to_string(),
+            protocol: ProviderProtocol::Openai,
+            api_key: String::new(),
+            model_mapping: mapping
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<HashMap<_, _>>(),
+            dispatch_mode: ZaiDispatchMode::Exclusive,
+        }
+    }
+
+    #[test]
+    fn test_map_model_mapping_and_wildcards() {
+        let open = provider("vllm", &[]);
+        assert_eq!(map_model(&open, "llama-3"), Some("llama-3".to_string()));
+
+        let mapped = provider(
+            "vllm",
+            &[
+                ("gpt-4o", "qwen-72b"),
+                ("gpt-4*", "qwen-32b"),
+                ("gpt-*", "qwen-7b"),
+            ],
+        );
+        assert_eq!(map_model(&mapped, "gpt-4o"), Some("qwen-72b".to_string()));
+        assert_eq!(
+            map_model(&mapped, "gpt-4-turbo"),
+            Some("qwen-32b".to_string())
+        );
+        assert_eq!(map_model(&mapped, "gpt-3.5"), Some("qwen-7b".to_string()));
+        assert_eq!(map_model(&mapped, "claude-sonnet-4-5"), None);
+    }
+
+    #[test]
+    fn test_explicit_route_prefix() {
+        let a = provider("vllm", &[("fast", "llama-3-8b")]);
+        let b = provider("other", &[]);
+        let providers = vec![&a, &b];
+
+        let route = explicit_route(&providers, "VLLM:fast").unwrap();
+        assert_eq!(route.provider.name, "vllm");
+        assert_eq!(route.upstream_model, "llama-3-8b");
+
+        let route = explicit_route(&providers, "vllm:mistral-7b").unwrap();
+        assert_eq!(route.upstream_model, "mistral-7b");
+
+        assert!(explicit_route(&providers, "unknown:model").is_none());
+        assert!(explicit_route(&providers, "gpt-4o").is_none());
+    }
+}
@@ -1,2 +1,3 @@
+pub mod generic;
 pub mod zai_anthropic;
 
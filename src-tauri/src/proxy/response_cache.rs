@@ -0,0 +1,676 @@
+// 响应缓存 - 对确定性请求按规范化请求体缓存完整响应
+//
+// 缓存内容统一保存为非流式 JSON (流式响应经各协议 collector 收集)，
+// 命中时按客户端请求的流式模式返回 JSON 或回放等价的 SSE 事件。
+
+use bytes::Bytes;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::proxy::config::ResponseCacheConfig;
+use crate::proxy::mappers;
+use crate::proxy::ProviderProtocol;
+
+/// 不影响响应内容、不参与缓存键的顶层字段
+const IGNORED_FIELDS: &[&str] = &["stream", "stream_options", "metadata", "user"];
+const GEMINI_MODELS_PREFIX: &str = "/v1beta/models/";
+
+/// 可缓存的请求
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheableRequest {
+    pub key: String,
+    pub protocol: ProviderProtocol,
+    /// 客户端期望流式响应
+    pub stream: bool,
+}
+
+/// 缓存的响应 (非流式格式)
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    pub protocol: ProviderProtocol,
+    pub body: Value,
+    pub mapped_model: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CacheHit {
+    pub response: CachedResponse,
+    pub age: Duration,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ResponseCacheStats {
+    pub entries: usize,
+    pub size_bytes: usize,
+    pub hits: u64,
+    pub misses: u64,
+    /// 已启用缓存但请求不可缓存 (非确定性、多候选等)
+    pub bypassed: u64,
+    pub stored: u64,
+    pub evicted: u64,
+    /// 命中时未消耗的上游 token (按缓存响应中的用量统计)
+    pub saved_input_tokens: u64,
+    pub saved_output_tokens: u64,
+}
+
+/// 是否为参与缓存的对话端点
+pub fn is_cacheable_path(path: &str) -> bool {
+    matches!(path, "/v1/chat/completions" | "/v1/messages")
+        || path
+            .strip_prefix(GEMINI_MODELS_PREFIX)
+            .and_then(|rest| rest.rsplit_once(':'))
+            .is_some_and(|(_, action)| {
+                action == "generateContent" || action == "streamGenerateContent"
+            })
+}
+
+/// 判断请求是否可缓存并计算缓存键
+///
+/// `scope` 为缓存隔离范围 (用户令牌)，共享缓存时传空字符串。
+pub fn cacheable_request(
+    path: &str,
+    body: &Value,
+    scope: &str,
+    deterministic_only: bool,
+) -> Option<CacheableRequest> {
+    let (protocol, model, stream) = match path {
+        "/v1/chat/completions" | "/v1/messages" => {
+            let protocol = if path == "/v1/messages" {
+                ProviderProtocol::Anthropic
+            } else {
+                ProviderProtocol::Openai
+            };
+            let model = body.get("model").and_then(|m| m.as_str())?;
+            let stream = body
+                .get("stream")
+                .and_then(|s| s.as_bool())
+                .unwrap_or(false);
+            (protocol, model, stream)
+        }
+        _ => {
+            let (model, action) = path.strip_prefix(GEMINI_MODELS_PREFIX)?.rsplit_once(':')?;
+            let stream = match action {
+                "generateContent" => false,
+                "streamGenerateContent" => true,
+                _ => return None,
+            };
+            (ProviderProtocol::Gemini, model, stream)
+        }
+    };
+
+    let (temperature, candidates) = match protocol {
+        ProviderProtocol::Gemini => (
+            body.pointer("/generationConfig/temperature"),
+            body.pointer("/generationConfig/candidateCount"),
+        ),
+        _ => (body.get("temperature"), body.get("n")),
+    };
+    // collector 只保留第一个候选，多候选请求不缓存
+    if candidates.and_then(|n| n.as_u64()).unwrap_or(1) > 1 {
+        return None;
+    }
+    if deterministic_only && temperature.and_then(|t| t.as_f64()) != Some(0.0) {
+        return None;
+    }
+
+    let mut normalized = body.clone();
+    if let Some(obj) = normalized.as_object_mut() {
+        for field in IGNORED_FIELDS {
+            obj.remove(*field);
+        }
+    }
+    let mut hasher = Sha256::new();
+    hasher.update(format!("{:?}\n{}\n{}\n", protocol, model, scope));
+    hasher.update(canonicalize(&normalized).to_string());
+    Some(CacheableRequest {
+        key: format!("{:x}", hasher.finalize()),
+        protocol,
+        stream,
+    })
+}
+
+/// 按键名排序对象 (serde_json 开启了 preserve_order)，使字段顺序不影响缓存键
+fn canonicalize(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            Value::Object(
+                keys.into_iter()
+                    .map(|k| (k.clone(), canonicalize(&map[k])))
+                    .collect(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.iter().map(canonicalize).collect()),
+        other => other.clone(),
+    }
+}
+
+/// 非流式响应是否完整 (正常结束且不含错误)
+pub fn is_complete(protocol: ProviderProtocol, body: &Value) -> bool {
+    if body.get("error").is_some() {
+        return false;
+    }
+    match protocol {
+        ProviderProtocol::Anthropic => body.get("stop_reason").is_some_and(|r| r.is_string()),
+        ProviderProtocol::Openai => {
+            body.get("choices")
+                .and_then(|c| c.as_array())
+                .is_some_and(|choices| {
+                    !choices.is_empty()
+                        && choices
+                            .iter()
+                            .all(|c| c.get("finish_reason").is_some_and(|r| r.is_string()))
+                })
+        }
+        ProviderProtocol::Gemini => body.pointer("/candidates/0/finishReason").is_some(),
+    }
+}
+
+/// 将完整的 SSE 响应收集为非流式 JSON；流中出现错误或未正常结束时返回 None
+pub async fn collect_sse(protocol: ProviderProtocol, raw: Bytes) -> Option<Value> {
+    let text = String::from_utf8_lossy(&raw);
+    let mut finished = false;
+    for data in text.lines().filter_map(|l| l.strip_prefix("data:")) {
+        let Ok(event) = serde_json::from_str::<Value>(data.trim()) else {
+            continue;
+        };
+        if event.get("error").is_some()
+            || event.get("type").and_then(|t| t.as_str()) == Some("error")
+        {
+            return None;
+        }
+        let event = event.get("response").unwrap_or(&event);
+        finished |= match protocol {
+            ProviderProtocol::Anthropic => {
+                event.get("type").and_then(|t| t.as_str()) == Some("message_stop")
+            }
+            ProviderProtocol::Openai => event
+                .pointer("/choices/0/finish_reason")
+                .is_some_and(|r| r.is_string()),
+            ProviderProtocol::Gemini => event.pointer("/candidates/0/finishReason").is_some(),
+        };
+    }
+    if !finished {
+        return None;
+    }
+
+    let chunks = futures::stream::iter(vec![Ok::<_, std::io::Error>(raw.clone())]);
+    match protocol {
+        ProviderProtocol::Anthropic => {
+            let response = mappers::claude::collect_stream_to_json(chunks).await.ok()?;
+            serde_json::to_value(response).ok()
+        }
+        ProviderProtocol::Openai => {
+            let response = mappers::openai::collector::collect_stream_to_json(chunks)
+                .await
+                .ok()?;
+            serde_json::to_value(response).ok()
+        }
+        ProviderProtocol::Gemini => mappers::gemini::collector::collect_stream_to_json(chunks, "")
+            .await
+            .ok(),
+    }
+}
+
+fn push_event(out: &mut String, event: Option<&str>, data: &Value) {
+    if let Some(event) = event {
+        out.push_str("event: ");
+        out.push_str(event);
+        out.push('\n');
+    }
+    out.push_str("data: ");
+    out.push_str(&data.to_string());
+    out.push_str("\n\n");
+}
+
+/// 将缓存的非流式响应回放为对应协议的 SSE 事件
+pub fn replay_sse(protocol: ProviderProtocol, body: &Value) -> String {
+    let mut out = String::new();
+    match protocol {
+        ProviderProtocol::Anthropic => replay_claude(&mut out, body),
+        ProviderProtocol::Openai => replay_openai(&mut out, body),
+        ProviderProtocol::Gemini => push_event(&mut out, None, body),
+    }
+    out
+}
+
+fn replay_claude(out: &mut String, body: &Value) {
+    let mut message = body.clone();
+    message["content"] = json!([]);
+    message["stop_reason"] = Value::Null;
+    message["stop_sequence"] = Value::Null;
+    push_event(
+        out,
+        Some("message_start"),
+        &json!({ "type": "message_start", "message": message }),
+    );
+
+    let blocks = body.get("content").and_then(|c| c.as_array());
+    for (index, block) in blocks.into_iter().flatten().enumerate() {
+        let (start, deltas) = match block.get("type").and_then(|t| t.as_str()) {
+            Some("text") => (
+                json!({ "type": "text", "text": "" }),
+                vec![json!({ "type": "text_delta", "text": block["text"] })],
+            ),
+            Some("thinking") => {
+                let mut deltas =
+                    vec![json!({ "type": "thinking_delta", "thinking": block["thinking"] })];
+                if let Some(signature) = block.get("signature").filter(|s| s.is_string()) {
+                    deltas.push(json!({ "type": "signature_delta", "signature": signature }));
+                }
+                (json!({ "type": "thinking", "thinking": "" }), deltas)
+            }
+            Some("tool_use") => {
+                let mut start = block.clone();
+                start["input"] = json!({});
+                let input = block.get("input").cloned().unwrap_or_else(|| json!({}));
+                (
+                    start,
+                    vec![json!({ "type": "input_json_delta", "partial_json": input.to_string() })],
+                )
+            }
+            _ => (block.clone(), Vec::new()),
+        };
+        push_event(
+            out,
+            Some("content_block_start"),
+            &json!({ "type": "content_block_start", "index": index, "content_block": start }),
+        );
+        for delta in deltas {
+            push_event(
+                out,
+                Some("content_block_delta"),
+                &json!({ "type": "content_block_delta", "index": index, "delta": delta }),
+            );
+        }
+        push_event(
+            out,
+            Some("content_block_stop"),
+            &json!({ "type": "content_block_stop", "index": index }),
+        );
+    }
+
+    push_event(
+        out,
+        Some("message_delta"),
+        &json!({
+            "type": "message_delta",
+            "delta": {
+                "stop_reason": body.get("stop_reason"),
+                "stop_sequence": body.get("stop_sequence"),
+            },
+            "usage": body.get("usage"),
+        }),
+    );
+    push_event(
+        out,
+        Some("message_stop"),
+        &json!({ "type": "message_stop" }),
+    );
+}
+
+fn replay_openai(out: &mut String, body: &Value) {
+    let chunk = |choices: Value| {
+        json!({
+            "id": body.get("id"),
+            "object": "chat.completion.chunk",
+            "created": body.get("created"),
+            "model": body.get("model"),
+            "choices": choices,
+        })
+    };
+    let choices = body.get("choices").and_then(|c| c.as_array());
+
+    for choice in choices.into_iter().flatten() {
+        let message = choice.get("message").cloned().unwrap_or_else(|| json!({}));
+        let mut delta =
+            json!({ "role": message.get("role").and_then(|r| r.as_str()).unwrap_or("assistant") });
+        for field in ["content", "reasoning_content"] {
+            if let Some(value) = message.get(field).filter(|v| !v.is_null()) {
+                delta[field] = value.clone();
+            }
+        }
+        if let Some(calls) = message.get("tool_calls").and_then(|t| t.as_array()) {
+            let calls: Vec<Value> = calls
+                .iter()
+                .enumerate()
+                .map(|(i, call)| {
+                    let mut call = call.clone();
+                    call["index"] = json!(i);
+                    call
+                })
+                .collect();
+            delta["tool_calls"] = json!(calls);
+        }
+        push_event(
+            out,
+            None,
+            &chunk(
+                json!([{ "index": choice.get("index"), "delta": delta, "finish_reason": null }]),
+            ),
+        );
+    }
+
+    let finish: Vec<Value> = choices
+        .into_iter()
+        .flatten()
+        .map(|c| json!({ "index": c.get("index"), "delta": {}, "finish_reason": c.get("finish_reason") }))
+        .collect();
+    let mut last = chunk(json!(finish));
+    if let Some(usage) = body.get("usage") {
+        last["usage"] = usage.clone();
+    }
+    push_event(out, None, &last);
+    out.push_str("data: [DONE]\n\n");
+}
+
+/// 缓存响应中记录的上游用量 (输入, 输出)
+fn usage_tokens(protocol: ProviderProtocol, body: &Value) -> (u64, u64) {
+    let get = |pointer: &str| body.pointer(pointer).and_then(|v| v.as_u64()).unwrap_or(0);
+    match protocol {
+        ProviderProtocol::Anthropic => (get("/usage/input_tokens"), get("/usage/output_tokens")),
+        ProviderProtocol::Openai => (get("/usage/prompt_tokens"), get("/usage/completion_tokens")),
+        ProviderProtocol::Gemini => (
+            get("/usageMetadata/promptTokenCount"),
+            get("/usageMetadata/candidatesTokenCount"),
+        ),
+    }
+}
+
+struct CacheEntry {
+    response: CachedResponse,
+    size: usize,
+    stored_at: Instant,
+    last_used: Instant,
+}
+
+#[derive(Default)]
+struct ResponseCache {
+    entries: HashMap<String, CacheEntry>,
+    size: usize,
+    stats: ResponseCacheStats,
+}
+
+impl ResponseCache {
+    fn lookup(&mut self, key: &str, ttl: Duration) -> Option<CacheHit> {
+        let expired = match self.entries.get_mut(key) {
+            Some(entry) if entry.stored_at.elapsed() < ttl => {
+                entry.last_used = Instant::now();
+                let hit = CacheHit {
+                    response: entry.response.clone(),
+                    age: entry.stored_at.elapsed(),
+                };
+                let (input, output) = usage_tokens(hit.response.protocol, &hit.response.body);
+                self.stats.hits += 1;
+                self.stats.saved_input_tokens += input;
+                self.stats.saved_output_tokens += output;
+                return Some(hit);
+            }
+            Some(_) => true,
+            None => false,
+        };
+        if expired {
+            self.remove(key);
+        }
+        self.stats.misses += 1;
+        None
+    }
+
+    fn insert(&mut self, key: String, response: CachedResponse, config: &ResponseCacheConfig) {
+        let size = response.body.to_string().len();
+        let max_size = (config.max_size_mb as usize).saturating_mul(1024 * 1024);
+        if size > max_size || config.max_entries == 0 {
+            return;
+        }
+        self.remove(&key);
+
+        // 先清理过期条目，再按最近最少使用淘汰
+        let ttl = Duration::from_secs(config.ttl_secs);
+        let expired: Vec<String> = self
+            .entries
+            .iter()
+            .filter(|(_, e)| e.stored_at.elapsed() >= ttl)
+            .map(|(k, _)| k.clone())
+            .collect();
+        for k in expired {
+            self.remove(&k);
+            self.stats.evicted += 1;
+        }
+        while self.entries.len() >= config.max_entries || self.size + size > max_size {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, e)| e.last_used)
+                .map(|(k, _)| k.clone())
+            else {
+                break;
+            };
+            self.remove(&oldest);
+            self.stats.evicted += 1;
+        }
+
+        let now = Instant::now();
+        self.size += size;
+        self.stats.stored += 1;
+        self.entries.insert(
+            key,
+            CacheEntry {
+                response,
+                size,
+                stored_at: now,
+                last_used: now,
+            },
+        );
+    }
+
+    fn remove(&mut self, key: &str) -> bool {
+        match self.entries.remove(key) {
+            Some(entry) => {
+                self.size -= entry.size;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn stats(&self) -> ResponseCacheStats {
+        ResponseCacheStats {
+            entries: self.entries.len(),
+            size_bytes: self.size,
+            ..self.stats.clone()
+        }
+    }
+}
+
+static CACHE: Lazy<Mutex<ResponseCache>> = Lazy::new(|| Mutex::new(ResponseCache::default()));
+
+/// 查找未过期的缓存响应 (同时记录命中/未命中)
+pub fn lookup(key: &str, config: &ResponseCacheConfig) -> Option<CacheHit> {
+    let ttl = Duration::from_secs(config.ttl_secs);
+    CACHE.lock().ok()?.lookup(key, ttl)
+}
+
+pub fn store(key: String, response: CachedResponse, config: &ResponseCacheConfig) {
+    if let Ok(mut cache) = CACHE.lock() {
+        cache.insert(key, response, config);
+    }
+}
+
+pub fn record_bypass() {
+    if let Ok(mut cache) = CACHE.lock() {
+        cache.stats.bypassed += 1;
+    }
+}
+
+pub fn stats() -> ResponseCacheStats {
+    CACHE.lock().map(|c| c.stats()).unwrap_or_default()
+}
+
+/// 清空缓存，返回移除的条目数 (统计保留)
+pub fn clear() -> usize {
+    match CACHE.lock() {
+        Ok(mut cache) => {
+            let removed = cache.entries.len();
+            cache.entries.clear();
+            cache.size = 0;
+            removed
+        }
+        Err(_) => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cacheable_request_key() {
+        let a = json!({"model": "m", "temperature": 0, "stream": true, "messages": [{"role": "user", "content": "hi"}]});
+        let b = json!({"messages": [{"content": "hi", "role": "user"}], "temperature": 0.0, "model": "m", "metadata": {"user_id": "x"}});
+
+        let ka = cacheable_request("/v1/messages", &a, "", true).unwrap();
+        let kb = cacheable_request("/v1/messages", &b, "", true).unwrap();
+        assert_eq!(ka.key, kb.key);
+        assert!(ka.stream && !kb.stream);
+        assert_eq!(ka.protocol, ProviderProtocol::Anthropic);
+
+        // 协议、隔离范围不同时键不同
+        assert_ne!(
+            cacheable_request("/v1/chat/completions", &a, "", true)
+                .unwrap()
+                .key,
+            ka.key
+        );
+        assert_ne!(
+            cacheable_request("/v1/messages", &a, "user-1", true)
+                .unwrap()
+                .key,
+            ka.key
+        );
+
+        // 非确定性 / 多候选请求不缓存
+        let hot = json!({"model": "m", "temperature": 0.7, "messages": []});
+        assert!(cacheable_request("/v1/messages", &hot, "", true).is_none());
+        assert!(cacheable_request("/v1/messages", &hot, "", false).is_some());
+        let multi = json!({"model": "m", "temperature": 0, "n": 3, "messages": []});
+        assert!(cacheable_request("/v1/chat/completions", &multi, "", true).is_none());
+
+        let gemini = json!({"contents": [], "generationConfig": {"temperature": 0}});
+        let req = cacheable_request(
+            "/v1beta/models/gemini-3-flash:streamGenerateContent",
+            &gemini,
+            "",
+            true,
+        )
+        .unwrap();
+        assert!(req.stream);
+        assert!(is_cacheable_path(
+            "/v1beta/models/gemini-3-flash:generateContent"
+        ));
+        assert!(!is_cacheable_path(
+            "/v1beta/models/gemini-3-flash:countTokens"
+        ));
+        assert!(!is_cacheable_path("/v1/responses"));
+    }
+
+    #[tokio::test]
+    async fn test_replay_sse_round_trip() {
+        let claude = json!({
+            "id": "msg_1",
+            "type": "message",
+            "role": "assistant",
+            "model": "claude-sonnet-4-6",
+            "content": [
+                {"type": "thinking", "thinking": "Let me check", "signature": "sig123"},
+                {"type": "text", "text": "Running ls"},
+                {"type": "tool_use", "id": "toolu_1", "name": "Bash", "input": {"command": "ls"}}
+            ],
+            "stop_reason": "tool_use",
+            "usage": {"input_tokens": 12, "output_tokens": 34}
+        });
+        let sse = replay_sse(ProviderProtocol::Anthropic, &claude);
+        let collected = collect_sse(ProviderProtocol::Anthropic, Bytes::from(sse))
+            .await
+            .unwrap();
+        assert_eq!(collected["content"], claude["content"]);
+        assert_eq!(collected["stop_reason"], "tool_use");
+        assert_eq!(collected["usage"]["output_tokens"], 34);
+
+        let openai = json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 1700000000,
+            "model": "gemini-3-flash",
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": "done",
+                    "tool_calls": [{"id": "call_1", "type": "function", "function": {"name": "ls", "arguments": "{}"}}]
+                },
+                "finish_reason": "tool_calls"
+            }],
+            "usage": {"prompt_tokens": 5, "completion_tokens": 7, "total_tokens": 12}
+        });
+        let sse = replay_sse(ProviderProtocol::Openai, &openai);
+        assert!(sse.ends_with("data: [DONE]\n\n"));
+        let collected = collect_sse(ProviderProtocol::Openai, Bytes::from(sse))
+            .await
+            .unwrap();
+        assert_eq!(collected["choices"][0]["message"]["content"], "done");
+        assert_eq!(
+            collected["choices"][0]["message"]["tool_calls"][0]["function"]["name"],
+            "ls"
+        );
+        assert_eq!(collected["choices"][0]["finish_reason"], "tool_calls");
+
+        // 未正常结束的流不缓存
+        let truncated =
+            "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{}}\n\n";
+        assert!(
+            collect_sse(ProviderProtocol::Anthropic, Bytes::from(truncated))
+                .await
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_lru_eviction_and_ttl() {
+        let config = ResponseCacheConfig {
+            enabled: true,
+            max_entries: 2,
+            ..Default::default()
+        };
+        let response = |n: u64| CachedResponse {
+            protocol: ProviderProtocol::Openai,
+            body: json!({"usage": {"prompt_tokens": n, "completion_tokens": 1}}),
+            mapped_model: None,
+        };
+        let ttl = Duration::from_secs(config.ttl_secs);
+        let mut cache = ResponseCache::default();
+        cache.insert("a".into(), response(1), &config);
+        cache.insert("b".into(), response(2), &config);
+        assert!(cache.lookup("a", ttl).is_some());
+        cache.insert("c".into(), response(3), &config);
+
+        // b 最久未使用，被淘汰
+        assert!(cache.lookup("b", ttl).is_none());
+        assert!(cache.lookup("c", ttl).is_some());
+        let stats = cache.stats();
+        assert_eq!(
+            (stats.entries, stats.hits, stats.misses, stats.evicted),
+            (2, 2, 1, 1)
+        );
+        assert_eq!(stats.saved_input_tokens, 4);
+
+        // 过期条目视为未命中并被移除
+        assert!(cache.lookup("a", Duration::ZERO).is_none());
+        assert_eq!(cache.stats().entries, 1);
+    }
+}
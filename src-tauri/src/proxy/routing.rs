@@ -0,0 +1,356 @@
+//! 路由规则引擎
+//!
+//! 按 `proxy.routing.rules` 的顺序评估请求特征 (协议、模型、用户、请求头、客户端、请求大小、
+//! 工具、后台任务类型)，得出目标模型 / 提供商 / 账号分组 / Thinking Budget。
+//! 模型与提供商通过改写请求中的模型名生效 (提供商使用 `名称:模型` 前缀)；
+//! 账号分组与 Thinking Budget 通过 task-local 作用域传递给 TokenManager 与请求转换逻辑。
+
+use axum::http::HeaderMap;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+
+use crate::proxy::common::client_adapter::find_client_adapter;
+use crate::proxy::common::model_mapping::wildcard_match;
+use crate::proxy::common::task_detection::{detect_task_type, last_user_text, BackgroundTaskType};
+use crate::proxy::config::{RouteMatch, RoutingConfig, ThinkingBudgetConfig};
+use crate::proxy::ProviderProtocol;
+
+/// 参与规则匹配的请求特征
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RequestFacts {
+    pub protocol: Option<ProviderProtocol>,
+    pub model: Option<String>,
+    pub user: Option<String>,
+    /// 小写头名称 -> 取值
+    #[serde(skip)]
+    pub headers: HashMap<String, String>,
+    pub client: Option<String>,
+    pub body_bytes: usize,
+    pub has_tools: bool,
+    pub task_type: Option<BackgroundTaskType>,
+}
+
+impl RequestFacts {
+    pub fn new(
+        protocol: Option<ProviderProtocol>,
+        model: Option<String>,
+        user: Option<String>,
+        headers: &HeaderMap,
+        body: Option<&Value>,
+        body_bytes: usize,
+    ) -> Self {
+        let client = find_client_adapter(headers).map(|adapter| adapter.name().to_string());
+        let headers = headers
+            .iter()
+            .filter_map(|(name, value)| {
+                value
+                    .to_str()
+                    .ok()
+                    .map(|v| (name.as_str().to_lowercase(), v.to_string()))
+            })
+            .collect();
+        let has_tools = body
+            .and_then(|b| b.get("tools"))
+            .and_then(|t| t.as_array())
+            .is_some_and(|tools| !tools.is_empty());
+        let task_type = body
+            .and_then(last_user_text)
+            .and_then(|text| detect_task_type(&text));
+
+        Self {
+            protocol,
+            model,
+            user,
+            headers,
+            client,
+            body_bytes,
+            has_tools,
+            task_type,
+        }
+    }
+}
+
+/// 规则评估结果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RouteDecision {
+    /// 命中的规则名称 (按评估顺序)
+    pub matched_rules: Vec<String>,
+    pub model: Option<String>,
+    pub provider: Option<String>,
+    pub account_group: Option<String>,
+    pub thinking_budget: Option<ThinkingBudgetConfig>,
+}
+
+impl RouteDecision {
+    pub fn is_empty(&self) -> bool {
+        self.matched_rules.is_empty()
+    }
+
+    /// 改写后的模型名；提供商动作以 `名称:模型` 前缀表示，交由提供商显式路由处理
+    pub fn target_model(&self, original: Option<&str>) -> Option<String> {
+        let model = self.model.as_deref().or(original)?;
+        match &self.provider {
+            Some(provider) => {
+                // 已带前缀的模型不重复添加
+                let bare = model.split_once(':').map(|(_, m)| m).unwrap_or(model);
+                Some(format!("{}:{}", provider, bare))
+            }
+            None => self.model.clone(),
+        }
+    }
+
+    /// 构造需要在 handler 作用域内生效的覆盖项
+    pub fn overrides(&self, config: &RoutingConfig) -> RouteOverrides {
+        RouteOverrides {
+            account_group: self
+                .account_group
+                .as_ref()
+                .and_then(|name| config.account_groups.get(name))
+                .map(|members| Arc::new(members.clone())),
+            thinking_budget: self.thinking_budget.clone(),
+        }
+    }
+}
+
+fn glob_matches(pattern: &str, value: &str) -> bool {
+    if pattern.contains('*') {
+        wildcard_match(pattern, value)
+    } else {
+        pattern == value
+    }
+}
+
+fn optional_glob(pattern: &Option<String>, value: Option<&str>) -> bool {
+    match pattern {
+        None => true,
+        Some(pattern) => value.is_some_and(|v| glob_matches(pattern, v)),
+    }
+}
+
+/// 判断单条规则的条件是否全部满足
+pub fn rule_matches(conditions: &RouteMatch, facts: &RequestFacts) -> bool {
+    if conditions.protocol.is_some() && conditions.protocol != facts.protocol {
+        return false;
+    }
+    if !optional_glob(&conditions.model, facts.model.as_deref())
+        || !optional_glob(&conditions.user, facts.user.as_deref())
+    {
+        return false;
+    }
+    if let Some(client) = &conditions.client {
+        if !facts
+            .client
+            .as_deref()
+            .is_some_and(|c| c.eq_ignore_ascii_case(client))
+        {
+            return false;
+        }
+    }
+    let headers_match = conditions.headers.iter().all(|(name, pattern)| {
+        facts
+            .headers
+            .get(&name.to_lowercase())
+            .is_some_and(|value| glob_matches(pattern, value))
+    });
+    if !headers_match {
+        return false;
+    }
+    if conditions
+        .min_bytes
+        .is_some_and(|min| facts.body_bytes < min)
+        || conditions
+            .max_bytes
+            .is_some_and(|max| facts.body_bytes > max)
+    {
+        return false;
+    }
+    if conditions.has_tools.is_some_and(|v| v != facts.has_tools) {
+        return false;
+    }
+    if conditions.task_type.is_some() && conditions.task_type != facts.task_type {
+        return false;
+    }
+    true
+}
+
+/// 按顺序评估规则；先命中的规则优先，`stop = false` 的规则允许后续规则补充尚未设置的动作
+pub fn evaluate(config: &RoutingConfig, facts: &RequestFacts) -> RouteDecision {
+    let mut decision = RouteDecision::default();
+    for rule in config.rules.iter().filter(|r| r.enabled) {
+        if !rule_matches(&rule.conditions, facts) {
+            continue;
+        }
+        decision.matched_rules.push(rule.name.clone());
+        let action = &rule.action;
+        if decision.model.is_none() {
+            decision.model = action.model.clone();
+        }
+        if decision.provider.is_none() {
+            decision.provider = action.provider.clone();
+        }
+        if decision.account_group.is_none() {
+            decision.account_group = action.account_group.clone();
+        }
+        if decision.thinking_budget.is_none() {
+            decision.thinking_budget = action.thinking_budget.clone();
+        }
+        if rule.stop {
+            break;
+        }
+    }
+    decision
+}
+
+// ===== 请求作用域覆盖项 =====
+
+/// 仅对当前请求生效的覆盖项
+#[derive(Debug, Clone, Default)]
+pub struct RouteOverrides {
+    /// 允许使用的账号 (账号 ID 或邮箱)
+    pub account_group: Option<Arc<Vec<String>>>,
+    pub thinking_budget: Option<ThinkingBudgetConfig>,
+}
+
+tokio::task_local! {
+    static ROUTE_OVERRIDES: RouteOverrides;
+}
+
+/// 在覆盖项作用域内执行 future (handler 内 spawn 的任务需重新进入作用域)
+pub async fn with_overrides<F: Future>(overrides: RouteOverrides, fut: F) -> F::Output {
+    ROUTE_OVERRIDES.scope(overrides, fut).await
+}
+
+/// 当前作用域的覆盖项 (不在作用域内时返回空)
+pub fn current_overrides() -> RouteOverrides {
+    ROUTE_OVERRIDES.try_with(|o| o.clone()).unwrap_or_default()
+}
+
+/// 当前请求限定的账号分组
+pub fn current_account_group() -> Option<Arc<Vec<String>>> {
+    ROUTE_OVERRIDES
+        .try_with(|o| o.account_group.clone())
+        .ok()
+        .flatten()
+}
+
+/// 当前请求的 Thinking Budget 覆盖
+pub fn current_thinking_budget() -> Option<ThinkingBudgetConfig> {
+    ROUTE_OVERRIDES
+        .try_with(|o| o.thinking_budget.clone())
+        .ok()
+        .flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::config::{RouteAction, RoutingRule};
+    use serde_json::json;
+
+    fn rule(name: &str, conditions: RouteMatch, action: RouteAction, stop: bool) -> RoutingRule {
+        RoutingRule {
+            name: name.to_string(),
+            enabled: true,
+            conditions,
+            action,
+            stop,
+        }
+    }
+
+    #[test]
+    fn test_rule_conditions() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-team", "infra-east".parse().unwrap());
+        let body = json!({
+            "model": "claude-sonnet-4-5",
+            "tools": [{"name": "bash"}],
+            "messages": [{"role": "user", "content": "Please write a 5-10 word title"}]
+        });
+        let facts = RequestFacts::new(
+            Some(ProviderProtocol::Anthropic),
+            Some("claude-sonnet-4-5".to_string()),
+            Some("alice".to_string()),
+            &headers,
+            Some(&body),
+            2048,
+        );
+        assert!(facts.has_tools);
+        assert_eq!(facts.task_type, Some(BackgroundTaskType::TitleGeneration));
+
+        let mut conditions = RouteMatch {
+            protocol: Some(ProviderProtocol::Anthropic),
+            model: Some("claude-*".to_string()),
+            user: Some("ali*".to_string()),
+            max_bytes: Some(4096),
+            has_tools: Some(true),
+            task_type: Some(BackgroundTaskType::TitleGeneration),
+            ..Default::default()
+        };
+        conditions.headers.insert("x-team".into(), "infra-*".into());
+        assert!(rule_matches(&conditions, &facts));
+
+        conditions.min_bytes = Some(4096);
+        assert!(!rule_matches(&conditions, &facts));
+        conditions.min_bytes = None;
+        conditions.client = Some("opencode".to_string());
+        assert!(!rule_matches(&conditions, &facts));
+    }
+
+    #[test]
+    fn test_evaluate_order_and_stop() {
+        let facts = RequestFacts {
+            protocol: Some(ProviderProtocol::Openai),
+            model: Some("gpt-4o".to_string()),
+            ..Default::default()
+        };
+        let config = RoutingConfig {
+            rules: vec![
+                rule(
+                    "group",
+                    RouteMatch::default(),
+                    RouteAction {
+                        account_group: Some("team-a".into()),
+                        ..Default::default()
+                    },
+                    false,
+                ),
+                rule(
+                    "to-vllm",
+                    RouteMatch {
+                        model: Some("gpt-*".into()),
+                        ..Default::default()
+                    },
+                    RouteAction {
+                        provider: Some("vllm".into()),
+                        account_group: Some("ignored".into()),
+                        ..Default::default()
+                    },
+                    true,
+                ),
+                rule(
+                    "never",
+                    RouteMatch::default(),
+                    RouteAction {
+                        model: Some("gemini-3-flash".into()),
+                        ..Default::default()
+                    },
+                    true,
+                ),
+            ],
+            account_groups: HashMap::from([("team-a".to_string(), vec!["a@x.com".to_string()])]),
+        };
+
+        let decision = evaluate(&config, &facts);
+        assert_eq!(decision.matched_rules, vec!["group", "to-vllm"]);
+        assert_eq!(decision.account_group.as_deref(), Some("team-a"));
+        assert_eq!(
+            decision.target_model(Some("gpt-4o")).as_deref(),
+            Some("vllm:gpt-4o")
+        );
+        let overrides = decision.overrides(&config);
+        assert_eq!(overrides.account_group.unwrap().as_slice(), ["a@x.com"]);
+    }
+}
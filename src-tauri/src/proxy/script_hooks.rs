@@ -0,0 +1,705 @@
+//! 脚本钩子 - 在固定阶段调用用户编写的 Rhai 脚本读取 / 修改 JSON
+//!
+//! 三个调用点:
+//! - `request`: script_hooks 中间件解析客户端请求体之后 (客户端协议格式)
+//! - `upstream_request`: handler 完成协议转换、即将发送 v1internal 请求之前
+//! - `sse_event`: 返回给客户端的每个 SSE 事件 (`data:` 行的 JSON)
+//!
+//! 脚本通过变量 `body` 读写 JSON (SSE 阶段将 `body` 设为 `()` 表示丢弃该事件)，
+//! 只读常量 `ctx` 提供阶段、协议、模型、用户、客户端与 SSE 事件名。脚本运行在同步沙箱中：
+//! 无文件 / 网络访问，禁用 `eval`，并受执行时限、操作数、调用深度与字符串 / 数组 / 对象大小限制。
+//! 可调用 `reject(msg)` 拒绝请求，`log(msg)` 输出日志。
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use once_cell::sync::Lazy;
+use rhai::{Dynamic, Engine, EvalAltResult, Position, Scope, AST};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::proxy::common::model_mapping::wildcard_match;
+use crate::proxy::config::{
+    ProviderProtocol, ScriptHook, ScriptHookErrorMode, ScriptHookStage, ScriptHooksConfig,
+};
+
+/// `reject()` 抛出的错误前缀，用于区分脚本主动拒绝与脚本错误
+const REJECT_MARKER: &str = "__antigravity_reject__:";
+const TIMEOUT_TOKEN: &str = "timeout";
+const OPERATIONS_TOKEN: &str = "operations";
+
+thread_local! {
+    /// 当前线程正在执行的钩子的 (截止时间, 最大操作数)
+    static RUN_LIMITS: Cell<Option<(Instant, u64)>> = const { Cell::new(None) };
+}
+
+static ENGINE: Lazy<Engine> = Lazy::new(|| {
+    let mut engine = Engine::new();
+    engine.set_max_call_levels(32);
+    engine.set_max_expr_depths(64, 32);
+    engine.set_max_string_size(4 * 1024 * 1024);
+    engine.set_max_array_size(100_000);
+    engine.set_max_map_size(100_000);
+    engine.disable_symbol("eval");
+    engine.on_print(|text| tracing::info!("[Script-Hook] {}", text));
+    engine.on_debug(|text, _, pos| tracing::debug!("[Script-Hook] {} @ {}", text, pos));
+    // 每 1024 次操作检查一次时限，避免频繁读取时钟
+    engine.on_progress(|ops| {
+        let (deadline, max_ops) = RUN_LIMITS.with(|limits| limits.get())?;
+        if ops > max_ops {
+            return Some(Dynamic::from(OPERATIONS_TOKEN));
+        }
+        if ops % 1024 == 0 && Instant::now() > deadline {
+            return Some(Dynamic::from(TIMEOUT_TOKEN));
+        }
+        None
+    });
+    engine.register_fn("reject", |msg: &str| -> Result<(), Box<EvalAltResult>> {
+        Err(EvalAltResult::ErrorRuntime(
+            Dynamic::from(format!("{}{}", REJECT_MARKER, msg)),
+            Position::NONE,
+        )
+        .into())
+    });
+    engine.register_fn("log", |msg: &str| tracing::info!("[Script-Hook] {}", msg));
+    engine
+});
+
+struct CompiledHook {
+    config: ScriptHook,
+    source: String,
+    ast: AST,
+}
+
+#[derive(Default)]
+struct HookRegistry {
+    enabled: bool,
+    timeout_ms: u64,
+    max_operations: u64,
+    hooks: Vec<Arc<CompiledHook>>,
+    /// 钩子名称 -> 加载 / 编译错误
+    errors: Vec<(String, String)>,
+}
+
+static REGISTRY: Lazy<RwLock<Arc<HookRegistry>>> =
+    Lazy::new(|| RwLock::new(Arc::new(HookRegistry::default())));
+
+fn load_source(hook: &ScriptHook) -> Result<String, String> {
+    match (&hook.script, &hook.file) {
+        (Some(script), None) => Ok(script.clone()),
+        (None, Some(file)) => {
+            let path = std::path::Path::new(file);
+            let path = if path.is_absolute() {
+                path.to_path_buf()
+            } else {
+                crate::modules::account::get_data_dir()?.join(path)
+            };
+            std::fs::read_to_string(&path)
+                .map_err(|e| format!("failed to read {}: {}", path.display(), e))
+        }
+        _ => Err("exactly one of `script` or `file` must be set".to_string()),
+    }
+}
+
+/// 热更新钩子配置：加载并编译全部启用的脚本，失败的钩子被跳过并记录错误
+pub fn update_script_hooks(config: ScriptHooksConfig) {
+    let mut registry = HookRegistry {
+        enabled: config.enabled,
+        timeout_ms: config.timeout_ms,
+        max_operations: config.max_operations,
+        ..Default::default()
+    };
+    for hook in config.hooks.into_iter().filter(|h| h.enabled) {
+        let compiled = load_source(&hook).and_then(|source| {
+            ENGINE
+                .compile(&source)
+                .map(|ast| (source, ast))
+                .map_err(|e| e.to_string())
+        });
+        match compiled {
+            Ok((source, ast)) => registry.hooks.push(Arc::new(CompiledHook {
+                config: hook,
+                source,
+                ast,
+            })),
+            Err(e) => {
+                tracing::warn!("[Script-Hook] Hook '{}' skipped: {}", hook.name, e);
+                registry.errors.push((hook.name, e));
+            }
+        }
+    }
+    tracing::info!(
+        "[Script-Hook] Config updated: enabled={}, {} hook(s) loaded, {} failed",
+        registry.enabled,
+        registry.hooks.len(),
+        registry.errors.len()
+    );
+    if let Ok(mut current) = REGISTRY.write() {
+        *current = Arc::new(registry);
+    }
+}
+
+fn registry() -> Arc<HookRegistry> {
+    REGISTRY
+        .read()
+        .map(|r| r.clone())
+        .unwrap_or_else(|_| Arc::new(HookRegistry::default()))
+}
+
+/// 参与钩子匹配的调用方特征
+#[derive(Debug, Clone, Default)]
+pub struct HookCaller {
+    pub user: Option<String>,
+    pub client: Option<String>,
+    /// 请求级 trace id，handler 日志与钩子上下文共用
+    pub trace_id: String,
+}
+
+tokio::task_local! {
+    static CURRENT_CALLER: Arc<HookCaller>;
+}
+
+/// 在调用方作用域内执行 future
+pub async fn with_caller<F: Future>(caller: Arc<HookCaller>, fut: F) -> F::Output {
+    CURRENT_CALLER.scope(caller, fut).await
+}
+
+/// 当前请求的调用方特征
+pub fn current_caller() -> Option<Arc<HookCaller>> {
+    CURRENT_CALLER.try_with(|c| c.clone()).ok()
+}
+
+/// 当前请求的 trace id (仅在配置了钩子时由中间件生成)
+pub fn current_trace_id() -> Option<String> {
+    current_caller()
+        .map(|caller| caller.trace_id.clone())
+        .filter(|id| !id.is_empty())
+}
+
+pub fn protocol_name(protocol: ProviderProtocol) -> &'static str {
+    match protocol {
+        ProviderProtocol::Openai => "openai",
+        ProviderProtocol::Anthropic => "anthropic",
+        ProviderProtocol::Gemini => "gemini",
+    }
+}
+
+/// 传给脚本的只读上下文 (`ctx`)
+#[derive(Debug, Clone, Serialize)]
+pub struct HookContext {
+    pub stage: ScriptHookStage,
+    pub protocol: String,
+    pub model: String,
+    pub user: Option<String>,
+    pub client: Option<String>,
+    /// SSE 事件名 (`event:` 行)，仅 sse_event 阶段
+    pub event: Option<String>,
+    pub trace_id: String,
+}
+
+fn protocol_matches(protocols: &[String], protocol: &str) -> bool {
+    protocols.is_empty()
+        || protocols.iter().any(|p| {
+            let p = p.trim().to_lowercase();
+            p == protocol || (p == "claude" && protocol == "anthropic")
+        })
+}
+
+fn hook_matches(hook: &ScriptHook, ctx: &HookContext) -> bool {
+    hook.stage == ctx.stage
+        && protocol_matches(&hook.protocols, &ctx.protocol)
+        && (hook.models.is_empty() || hook.models.iter().any(|m| wildcard_match(m, &ctx.model)))
+        && (hook.users.is_empty()
+            || ctx
+                .user
+                .as_deref()
+                .is_some_and(|u| hook.users.iter().any(|p| wildcard_match(p, u))))
+        && (hook.clients.is_empty()
+            || ctx
+                .client
+                .as_deref()
+                .is_some_and(|c| hook.clients.iter().any(|n| n.eq_ignore_ascii_case(c))))
+}
+
+/// 当前配置下指定阶段是否可能有钩子执行 (仅按阶段与协议预判，用于跳过请求体 / 响应流的解析)
+pub fn has_hooks(stage: ScriptHookStage, protocol: ProviderProtocol) -> bool {
+    let registry = registry();
+    registry.enabled
+        && registry.hooks.iter().any(|hook| {
+            hook.config.stage == stage
+                && protocol_matches(&hook.config.protocols, protocol_name(protocol))
+        })
+}
+
+/// 单个脚本的执行结果
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptResult {
+    /// 返回 (可能修改过的) body
+    Body(Value),
+    /// 脚本将 body 设为 ()
+    Dropped,
+    /// 脚本调用了 reject(msg)
+    Rejected(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptError {
+    Timeout,
+    Failed(String),
+}
+
+impl std::fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScriptError::Timeout => write!(f, "script exceeded its time or operation limit"),
+            ScriptError::Failed(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+fn classify_error(err: &EvalAltResult) -> Result<ScriptResult, ScriptError> {
+    match err {
+        EvalAltResult::ErrorRuntime(value, _) => {
+            let text = value.to_string();
+            match text.strip_prefix(REJECT_MARKER) {
+                Some(msg) => Ok(ScriptResult::Rejected(msg.to_string())),
+                None => Err(ScriptError::Failed(err.to_string())),
+            }
+        }
+        EvalAltResult::ErrorInFunctionCall(_, _, inner, _) => classify_error(inner),
+        EvalAltResult::ErrorTerminated(_, _) | EvalAltResult::ErrorTooManyOperations(_) => {
+            Err(ScriptError::Timeout)
+        }
+        _ => Err(ScriptError::Failed(err.to_string())),
+    }
+}
+
+/// 在沙箱中执行已编译的脚本
+fn run_ast(
+    ast: &AST,
+    body: &Value,
+    ctx: &HookContext,
+    timeout: Duration,
+    max_operations: u64,
+) -> Result<ScriptResult, ScriptError> {
+    let to_dynamic =
+        |v: &Value| rhai::serde::to_dynamic(v).map_err(|e| ScriptError::Failed(e.to_string()));
+    let mut scope = Scope::new();
+    scope.push_dynamic("body", to_dynamic(body)?);
+    scope.push_constant_dynamic("ctx", to_dynamic(&json!(ctx))?);
+
+    RUN_LIMITS.with(|limits| limits.set(Some((Instant::now() + timeout, max_operations))));
+    let result = ENGINE.run_ast_with_scope(&mut scope, ast);
+    RUN_LIMITS.with(|limits| limits.set(None));
+    if let Err(e) = result {
+        return classify_error(&e);
+    }
+
+    let Some(output) = scope.get_value::<Dynamic>("body") else {
+        return Ok(ScriptResult::Dropped);
+    };
+    if output.is_unit() {
+        return Ok(ScriptResult::Dropped);
+    }
+    rhai::serde::from_dynamic(&output)
+        .map(ScriptResult::Body)
+        .map_err(|e| ScriptError::Failed(format!("invalid body after script: {}", e)))
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScriptHookStats {
+    pub runs: u64,
+    pub modified: u64,
+    pub dropped: u64,
+    pub rejected: u64,
+    pub errors: u64,
+    pub timeouts: u64,
+    /// 累计执行耗时 (微秒)
+    pub total_micros: u64,
+}
+
+static STATS: Lazy<Mutex<HashMap<String, ScriptHookStats>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub fn stats() -> HashMap<String, ScriptHookStats> {
+    STATS.lock().map(|s| s.clone()).unwrap_or_default()
+}
+
+/// 一个阶段内全部命中钩子的执行结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HookOutcome {
+    /// 继续处理 (body 可能已被修改)
+    Continue,
+    /// 丢弃 (仅 sse_event 阶段)
+    Drop,
+    /// 拒绝请求
+    Reject(String),
+}
+
+fn record(name: &str, update: impl FnOnce(&mut ScriptHookStats)) {
+    if let Ok(mut stats) = STATS.lock() {
+        update(stats.entry(name.to_string()).or_default());
+    }
+}
+
+/// 按配置顺序执行指定阶段的全部命中钩子
+pub fn run_stage(body: &mut Value, ctx: &HookContext) -> HookOutcome {
+    let registry = registry();
+    if !registry.enabled {
+        return HookOutcome::Continue;
+    }
+    for hook in registry
+        .hooks
+        .iter()
+        .filter(|h| hook_matches(&h.config, ctx))
+    {
+        let name = hook.config.name.as_str();
+        let timeout = Duration::from_millis(hook.config.timeout_ms.unwrap_or(registry.timeout_ms));
+        let started = Instant::now();
+        let result = run_ast(&hook.ast, body, ctx, timeout, registry.max_operations);
+        let elapsed = started.elapsed().as_micros() as u64;
+        record(name, |s| {
+            s.runs += 1;
+            s.total_micros += elapsed;
+        });
+
+        let failure = match result {
+            Ok(ScriptResult::Body(new_body)) => {
+                // 仅在内容变化时替换 (Rhai 的对象按键排序，会打乱原始字段顺序)
+                if &new_body != body {
+                    record(name, |s| s.modified += 1);
+                    *body = new_body;
+                }
+                continue;
+            }
+            Ok(ScriptResult::Dropped) if ctx.stage == ScriptHookStage::SseEvent => {
+                record(name, |s| s.dropped += 1);
+                return HookOutcome::Drop;
+            }
+            Ok(ScriptResult::Dropped) => {
+                ScriptError::Failed("body can only be removed in the sse_event stage".to_string())
+            }
+            Ok(ScriptResult::Rejected(msg)) => {
+                record(name, |s| s.rejected += 1);
+                tracing::info!(
+                    "[{}] Script hook '{}' rejected the request: {}",
+                    ctx.trace_id,
+                    name,
+                    msg
+                );
+                return HookOutcome::Reject(msg);
+            }
+            Err(e) => e,
+        };
+
+        record(name, |s| match &failure {
+            ScriptError::Timeout => s.timeouts += 1,
+            ScriptError::Failed(_) => s.errors += 1,
+        });
+        tracing::warn!(
+            "[{}] Script hook '{}' failed: {}",
+            ctx.trace_id,
+            name,
+            failure
+        );
+        if hook.config.on_error == ScriptHookErrorMode::Reject {
+            return match ctx.stage {
+                ScriptHookStage::SseEvent => HookOutcome::Drop,
+                _ => HookOutcome::Reject(format!("Script hook '{}' failed: {}", name, failure)),
+            };
+        }
+    }
+    HookOutcome::Continue
+}
+
+/// 在阻塞线程池中执行 [`run_stage`]，避免 Rhai 脚本 (每次最长 timeout_ms) 占用异步 worker
+pub async fn run_stage_blocking(mut body: Value, ctx: HookContext) -> (Value, HookOutcome) {
+    tokio::task::spawn_blocking(move || {
+        let outcome = run_stage(&mut body, &ctx);
+        (body, outcome)
+    })
+    .await
+    .unwrap_or_else(|e| {
+        (
+            Value::Null,
+            HookOutcome::Reject(format!("Script hook task failed: {}", e)),
+        )
+    })
+}
+
+fn context_for(
+    stage: ScriptHookStage,
+    protocol: ProviderProtocol,
+    model: &str,
+    trace_id: &str,
+) -> HookContext {
+    let caller = current_caller().unwrap_or_default();
+    HookContext {
+        stage,
+        protocol: protocol_name(protocol).to_string(),
+        model: model.to_string(),
+        user: caller.user.clone(),
+        client: caller.client.clone(),
+        event: None,
+        trace_id: trace_id.to_string(),
+    }
+}
+
+/// 对即将发送到上游的 v1internal 请求体执行 upstream_request 钩子，返回拒绝原因
+///
+/// handler 在每次尝试 (换号后重新构建请求体) 时都会调用，因此与 request / sse_event 阶段一样
+/// 在阻塞线程池中执行，避免脚本占用异步 worker。
+pub async fn apply_upstream(
+    protocol: ProviderProtocol,
+    body: &mut Value,
+    model: &str,
+    trace_id: &str,
+) -> Result<(), String> {
+    if !has_hooks(ScriptHookStage::UpstreamRequest, protocol) {
+        return Ok(());
+    }
+    let ctx = context_for(ScriptHookStage::UpstreamRequest, protocol, model, trace_id);
+    let (rewritten, outcome) = run_stage_blocking(std::mem::take(body), ctx).await;
+    match outcome {
+        HookOutcome::Reject(msg) => Err(msg),
+        HookOutcome::Continue | HookOutcome::Drop => {
+            *body = rewritten;
+            Ok(())
+        }
+    }
+}
+
+/// 按协议格式构造钩子拒绝请求时的 400 响应
+pub fn reject_response(protocol: ProviderProtocol, message: &str) -> Response {
+    let body = match protocol {
+        ProviderProtocol::Anthropic => json!({
+            "type": "error",
+            "error": { "type": "invalid_request_error", "message": message }
+        }),
+        ProviderProtocol::Openai => json!({
+            "error": { "message": message, "type": "invalid_request_error" }
+        }),
+        ProviderProtocol::Gemini => json!({
+            "error": { "code": 400, "message": message, "status": "INVALID_ARGUMENT" }
+        }),
+    };
+    (StatusCode::BAD_REQUEST, Json(body)).into_response()
+}
+
+/// 对响应流中的完整 SSE 事件执行 sse_event 钩子
+pub struct SseHookProcessor {
+    ctx: HookContext,
+}
+
+impl SseHookProcessor {
+    pub fn new(protocol: ProviderProtocol, model: &str, trace_id: &str) -> Self {
+        Self {
+            ctx: context_for(ScriptHookStage::SseEvent, protocol, model, trace_id),
+        }
+    }
+
+    /// 处理若干以空行结尾的完整事件；被丢弃的事件整体移除 (含 `event:` 行)
+    pub fn rewrite_events(&mut self, data: &[u8]) -> Vec<u8> {
+        String::from_utf8_lossy(data)
+            .split_inclusive("\n\n")
+            .filter_map(|event| self.rewrite_event(event))
+            .collect::<String>()
+            .into_bytes()
+    }
+
+    /// 在阻塞线程池中处理事件；钩子任务异常时丢弃这批事件
+    pub async fn rewrite_events_blocking(&self, data: Vec<u8>) -> Vec<u8> {
+        let mut processor = Self {
+            ctx: self.ctx.clone(),
+        };
+        tokio::task::spawn_blocking(move || processor.rewrite_events(&data))
+            .await
+            .unwrap_or_default()
+    }
+
+    fn rewrite_event(&mut self, event: &str) -> Option<String> {
+        self.ctx.event = event
+            .lines()
+            .find_map(|line| line.strip_prefix("event:"))
+            .map(|name| name.trim().to_string());
+        let mut out = String::with_capacity(event.len());
+        for line in event.split_inclusive('\n') {
+            let content = line.trim_end_matches(['\n', '\r']);
+            let parsed = content
+                .strip_prefix("data:")
+                .and_then(|data| serde_json::from_str::<Value>(data.trim_start()).ok());
+            let Some(original) = parsed else {
+                out.push_str(line);
+                continue;
+            };
+            let mut json = original.clone();
+            match run_stage(&mut json, &self.ctx) {
+                // 响应已开始输出，拒绝时只能丢弃该事件
+                HookOutcome::Drop | HookOutcome::Reject(_) => return None,
+                HookOutcome::Continue if json == original => out.push_str(line),
+                HookOutcome::Continue => {
+                    out.push_str("data: ");
+                    out.push_str(&json.to_string());
+                    out.push_str(&line[content.len()..]);
+                }
+            }
+        }
+        Some(out)
+    }
+}
+
+/// 已加载的钩子、编译错误与统计 (管理接口)
+pub fn list_hooks() -> Value {
+    let registry = registry();
+    let stats = stats();
+    json!({
+        "enabled": registry.enabled,
+        "timeout_ms": registry.timeout_ms,
+        "max_operations": registry.max_operations,
+        "hooks": registry.hooks.iter().map(|hook| json!({
+            "name": hook.config.name,
+            "stage": hook.config.stage,
+            "protocols": hook.config.protocols,
+            "models": hook.config.models,
+            "users": hook.config.users,
+            "clients": hook.config.clients,
+            "on_error": hook.config.on_error,
+            "stats": stats.get(&hook.config.name).cloned().unwrap_or_default(),
+        })).collect::<Vec<_>>(),
+        "errors": registry.errors.iter().map(|(name, error)| json!({
+            "name": name,
+            "error": error,
+        })).collect::<Vec<_>>(),
+    })
+}
+
+/// 试运行脚本：`name` 对应已加载的钩子，否则编译 `script`；不计入统计
+pub fn test_script(
+    name: Option<&str>,
+    script: Option<&str>,
+    body: &Value,
+    ctx: &HookContext,
+) -> Result<ScriptResult, String> {
+    let registry = registry();
+    let source = match (name, script) {
+        (_, Some(script)) => script.to_string(),
+        (Some(name), None) => registry
+            .hooks
+            .iter()
+            .find(|h| h.config.name == name)
+            .map(|h| h.source.clone())
+            .ok_or_else(|| format!("Script hook '{}' is not loaded", name))?,
+        (None, None) => return Err("Either `name` or `script` is required".to_string()),
+    };
+    let ast = ENGINE.compile(&source).map_err(|e| e.to_string())?;
+    let timeout = Duration::from_millis(registry.timeout_ms.max(1));
+    run_ast(&ast, body, ctx, timeout, registry.max_operations.max(1)).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx(stage: ScriptHookStage) -> HookContext {
+        HookContext {
+            stage,
+            protocol: "openai".to_string(),
+            model: "gemini-2.5-pro".to_string(),
+            user: Some("alice".to_string()),
+            client: None,
+            event: None,
+            trace_id: "test".to_string(),
+        }
+    }
+
+    fn run(script: &str, body: Value, stage: ScriptHookStage) -> Result<ScriptResult, ScriptError> {
+        let ast = ENGINE.compile(script).unwrap();
+        run_ast(
+            &ast,
+            &body,
+            &ctx(stage),
+            Duration::from_millis(200),
+            1_000_000,
+        )
+    }
+
+    #[test]
+    fn test_script_modifies_drops_and_rejects() {
+        let result = run(
+            r#"
+                body.temperature = 0.2;
+                if ctx.user == "alice" { body.metadata = #{ tagged: true }; }
+            "#,
+            json!({"model": "m", "temperature": 1.0}),
+            ScriptHookStage::Request,
+        );
+        assert_eq!(
+            result,
+            Ok(ScriptResult::Body(
+                json!({"model": "m", "temperature": 0.2, "metadata": {"tagged": true}})
+            ))
+        );
+
+        let result = run(
+            r#"if body.type == "ping" { body = (); }"#,
+            json!({"type": "ping"}),
+            ScriptHookStage::SseEvent,
+        );
+        assert_eq!(result, Ok(ScriptResult::Dropped));
+
+        let result = run(
+            r#"if body.model != "allowed" { reject("model not allowed"); }"#,
+            json!({"model": "other"}),
+            ScriptHookStage::Request,
+        );
+        assert_eq!(
+            result,
+            Ok(ScriptResult::Rejected("model not allowed".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_script_sandbox_limits() {
+        let result = run(
+            "let n = 0; loop { n += 1; }",
+            json!({}),
+            ScriptHookStage::Request,
+        );
+        assert_eq!(result, Err(ScriptError::Timeout));
+
+        assert!(ENGINE.compile(r#"eval("1 + 1")"#).is_err());
+
+        let result = run("ctx.model = \"x\";", json!({}), ScriptHookStage::Request);
+        assert!(matches!(result, Err(ScriptError::Failed(_))));
+    }
+
+    #[test]
+    fn test_hook_matching() {
+        let hook = ScriptHook {
+            name: "h".to_string(),
+            enabled: true,
+            stage: ScriptHookStage::SseEvent,
+            script: Some("".to_string()),
+            file: None,
+            protocols: vec!["openai".to_string()],
+            models: vec!["gemini-*".to_string()],
+            users: Vec::new(),
+            clients: vec!["opencode".to_string()],
+            timeout_ms: None,
+            on_error: ScriptHookErrorMode::Ignore,
+        };
+        let mut ctx = ctx(ScriptHookStage::SseEvent);
+        assert!(!hook_matches(&hook, &ctx));
+        ctx.client = Some("OpenCode".to_string());
+        assert!(hook_matches(&hook, &ctx));
+        ctx.stage = ScriptHookStage::Request;
+        assert!(!hook_matches(&hook, &ctx));
+    }
+}
@@ -101,6 +101,7 @@ pub struct AppState {
     pub upstream_proxy: Arc<tokio::sync::RwLock<crate::proxy::config::UpstreamProxyConfig>>,
     pub upstream: Arc<crate::proxy::upstream::client::UpstreamClient>,
     pub zai: Arc<RwLock<crate::proxy::ZaiConfig>>,
+    pub providers: Arc<RwLock<Vec<crate::proxy::UpstreamProviderConfig>>>, // [NEW] 通用第三方提供商
     pub provider_rr: Arc<AtomicUsize>,
     pub zai_vision_mcp: Arc<crate::proxy::zai_vision_mcp::ZaiVisionMcpState>,
     pub monitor: Arc<crate::proxy::monitor::ProxyMonitor>,
@@ -219,6 +220,7 @@ pub struct AxumServer {
     upstream: Arc<crate::proxy::upstream::client::UpstreamClient>,
     security_state: Arc<RwLock<crate::proxy::ProxySecurityConfig>>,
     zai_state: Arc<RwLock<crate::proxy::ZaiConfig>>,
+    providers_state: Arc<RwLock<Vec<crate::proxy::UpstreamProviderConfig>>>,
     experimental: Arc<RwLock<crate::proxy::config::ExperimentalConfig>>,
     debug_logging: Arc<RwLock<crate::proxy::config::DebugLoggingConfig>>,
     #[allow(dead_code)] // 预留给 cloudflared 运行状态查询与后续控制
@@ -264,7 +266,17 @@ impl AxumServer {
         tracing::info!("z.ai 配置已热更新");
     }
 
+    pub async fn update_providers(&self, config: &crate::proxy::config::ProxyConfig) {
+        let mut providers = self.providers_state.write().await;
+        *providers = config.providers.clone();
+        tracing::info!("第三方提供商配置已热更新 ({} 个)", providers.len());
+    }
+
     pub async fn update_experimental(&self, config: &crate::proxy::config::ProxyConfig) {
+        crate::proxy::config::update_tool_result_compression_config(
+            config.experimental.tool_result_compression.clone(),
+        );
+        crate::proxy::mappers::tokenizer::update_tokenizers(&config.experimental.tokenizers);
         let mut exp = self.experimental.write().await;
         *exp = config.experimental.clone();
         tracing::info!("实验性配置已热更新");
@@ -300,6 +312,7 @@ impl AxumServer {
         user_agent_override: Option<String>,
         security_config: crate::proxy::ProxySecurityConfig,
         zai_config: crate::proxy::ZaiConfig,
+        providers: Vec<crate::proxy::UpstreamProviderConfig>,
         monitor: Arc<crate::proxy::monitor::ProxyMonitor>,
         experimental_config: crate::proxy::config::ExperimentalConfig,
         debug_logging: crate::proxy::config::DebugLoggingConfig,
@@ -317,8 +330,13 @@ impl AxumServer {
     proxy_pool_manager.clone().start_health_check_loop();
         let security_state = Arc::new(RwLock::new(security_config));
         let zai_state = Arc::new(RwLock::new(zai_config));
+        let providers_state = Arc::new(RwLock::new(providers));
         let provider_rr = Arc::new(AtomicUsize::new(0));
         let zai_vision_mcp_state = Arc::new(crate::proxy::zai_vision_mcp::ZaiVisionMcpState::new());
+        crate::proxy::config::update_tool_result_compression_config(
+            experimental_config.tool_result_compression.clone(),
+        );
+        crate::proxy::mappers::tokenizer::update_tokenizers(&experimental_config.tokenizers);
         let experimental_state = Arc::new(RwLock::new(experimental_config));
         let debug_logging_state = Arc::new(RwLock::new(debug_logging));
         let is_running_state = Arc::new(RwLock::new(true));
@@ -343,6 +361,7 @@ impl AxumServer {
                 u
             },
             zai: zai_state.clone(),
+            providers: providers_state.clone(),
             provider_rr: provider_rr.clone(),
             zai_vision_mcp: zai_vision_mcp_state,
             monitor: monitor.clone(),
@@ -364,8 +383,11 @@ impl AxumServer {
         // 构建路由 - 使用新架构的 handlers！
         use crate::proxy::handlers;
         use crate::proxy::middleware::{
-            admin_auth_middleware, auth_middleware, cors_layer, ip_filter_middleware,
-            monitor_middleware, service_status_middleware,
+            admin_auth_middleware, auth_middleware, client_adapter_middleware,
+            context_window_middleware, cors_layer, fallback_middleware, ip_filter_middleware,
+            json_body_commit_middleware, json_body_middleware, monitor_middleware, response_cache_middleware,
+            routing_middleware, script_hooks_middleware, service_status_middleware,
+            system_prompt_middleware, thinking_policy_middleware,
         };
 
         // 1. 构建主 AI 代理路由 (遵循 auth_mode 配置)
@@ -395,6 +417,11 @@ impl AxumServer {
                 "/v1/audio/transcriptions",
                 post(handlers::audio::handle_audio_transcription),
             ) // 音频转录 API
+            .route(
+                "/v1/audio/translations",
+                post(handlers::audio::handle_audio_translation),
+            ) // 音频翻译 API
+            .route("/v1/audio/speech", post(handlers::audio::handle_audio_speech)) // 语音合成 API
             // Claude Protocol
             .route("/v1/messages", post(handlers::claude::handle_messages))
             .route(
@@ -420,12 +447,49 @@ impl AxumServer {
             // Handle both GET (get info) and POST (generateContent with colon) at the same route
             .route(
                 "/v1beta/models/:model",
-                get(handlers::gemini::handle_get_model).post(handlers::gemini::handle_generate),
+                get(handlers::gemini::handle_get_model)
+                    .post(handlers::gemini_resources::handle_model_post),
             )
             .route(
                 "/v1beta/models/:model/countTokens",
                 post(handlers::gemini::handle_count_tokens),
             ) // Specific route priority
+            // Gemini Files API / cachedContents / Batch API (本地模拟)
+            .route(
+                "/upload/v1beta/files",
+                post(handlers::gemini_resources::handle_upload_file),
+            )
+            .route("/v1beta/files", get(handlers::gemini_resources::handle_list_files))
+            .route(
+                "/v1beta/files/:name",
+                get(handlers::gemini_resources::handle_get_file)
+                    .delete(handlers::gemini_resources::handle_delete_file),
+            )
+            .route(
+                "/download/v1beta/files/:name",
+                get(handlers::gemini_resources::handle_download_file),
+            )
+            .route(
+                "/v1beta/cachedContents",
+                get(handlers::gemini_resources::handle_list_cached_contents)
+                    .post(handlers::gemini_resources::handle_create_cached_content),
+            )
+            .route(
+                "/v1beta/cachedContents/:name",
+                get(handlers::gemini_resources::handle_get_cached_content)
+                    .patch(handlers::gemini_resources::handle_update_cached_content)
+                    .delete(handlers::gemini_resources::handle_delete_cached_content),
+            )
+            .route(
+                "/v1beta/batches",
+                get(handlers::gemini_resources::handle_list_batches),
+            )
+            .route(
+                "/v1beta/batches/:name",
+                get(handlers::gemini_resources::handle_get_batch)
+                    .post(handlers::gemini_resources::handle_batch_action)
+                    .delete(handlers::gemini_resources::handle_delete_batch),
+            )
             .route(
                 "/v1/models/detect",
                 post(handlers::common::handle_detect_model),
@@ -435,9 +499,31 @@ impl AxumServer {
             .route("/v1/api/event_logging", post(silent_ok_handler))
             // 应用 AI 服务特定的层
             // 注意：Axum layer 执行顺序是从下往上（洋葱模型）
-            // 请求: ip_filter -> auth -> monitor -> handler
-            // 响应: handler -> monitor -> auth -> ip_filter
+            // 请求: ip_filter -> auth -> monitor -> json_body -> routing -> context_window -> thinking_policy -> system_prompt -> client_adapter -> fallback -> script_hooks -> response_cache -> json_body_commit -> handler
+            // 响应: handler -> json_body_commit -> response_cache -> script_hooks -> fallback -> client_adapter -> system_prompt -> thinking_policy -> context_window -> routing -> json_body -> monitor -> auth -> ip_filter
             // monitor 需要在 auth 之后执行才能获取 UserTokenIdentity
+            // json_body 只读取、解析一次请求体 (超出 DefaultBodyLimit 返回 413)，json_body_commit 在进入 handler 前写回修改
+            // fallback 位于 monitor 之内，monitor 记录的是客户端原始模型与替换后的 mapped_model
+            // routing 先于 fallback 改写模型，fallback 再对路由后的模型判断是否降级
+            // client_adapter 按匹配到的客户端适配器改写请求体并调整响应
+            // context_window 选出上下文窗口策略，由 handler 在转换请求后执行裁剪
+            // thinking_policy 收集调用方特征，由 handler 按映射后的模型选择思考策略
+            // system_prompt 收集调用方特征，由请求转换逻辑注入命中的提示词片段
+            // script_hooks 对请求体与 SSE 事件执行用户脚本 (upstream_request 阶段由 handler 执行)
+            // response_cache 以最终请求体为键，命中时不进入 handler
+            .layer(axum::middleware::from_fn(json_body_commit_middleware))
+            .layer(axum::middleware::from_fn(response_cache_middleware))
+            .layer(axum::middleware::from_fn(script_hooks_middleware))
+            .layer(axum::middleware::from_fn_with_state(
+                state.clone(),
+                fallback_middleware,
+            ))
+            .layer(axum::middleware::from_fn(client_adapter_middleware))
+            .layer(axum::middleware::from_fn(system_prompt_middleware))
+            .layer(axum::middleware::from_fn(thinking_policy_middleware))
+            .layer(axum::middleware::from_fn(context_window_middleware))
+            .layer(axum::middleware::from_fn(routing_middleware))
+            .layer(axum::middleware::from_fn(json_body_middleware))
             .layer(axum::middleware::from_fn_with_state(
                 state.clone(),
                 monitor_middleware,
@@ -525,6 +611,42 @@ impl AxumServer {
             .route("/proxy/start", post(admin_start_proxy_service))
             .route("/proxy/stop", post(admin_stop_proxy_service))
             .route("/proxy/mapping", post(admin_update_model_mapping))
+            .route("/proxy/routing/dry-run", post(admin_routing_dry_run))
+            .route("/proxy/client-adapters", get(admin_list_client_adapters))
+            .route("/proxy/tool-adapters", get(admin_list_tool_adapters))
+            .route(
+                "/proxy/signature-cache",
+                get(admin_get_signature_cache).delete(admin_clear_signature_cache),
+            )
+            .route(
+                "/proxy/signature-cache/sessions/:sessionId",
+                get(admin_get_session_signatures).delete(admin_purge_session_signatures),
+            )
+            .route(
+                "/proxy/tool-result-compression",
+                get(admin_get_tool_result_compression).delete(admin_reset_tool_result_compression),
+            )
+            .route("/proxy/token-estimation", get(admin_get_token_estimation))
+            .route(
+                "/proxy/response-cache",
+                get(admin_get_response_cache).delete(admin_clear_response_cache),
+            )
+            .route("/proxy/context-window", get(admin_get_context_window))
+            .route("/proxy/thinking-policies", get(admin_get_thinking_policies))
+            .route(
+                "/proxy/system-prompts/preview",
+                post(admin_preview_system_prompts),
+            )
+            .route("/proxy/script-hooks", get(admin_get_script_hooks))
+            .route("/proxy/script-hooks/test", post(admin_test_script_hook))
+            .route(
+                "/proxy/tool-adapters/schema-log",
+                get(admin_get_schema_log).delete(admin_clear_schema_log),
+            )
+            .route(
+                "/proxy/tool-adapters/schema-lint",
+                post(admin_lint_tool_schema),
+            )
             .route("/proxy/api-key/generate", post(admin_generate_api_key))
             .route(
                 "/proxy/session-bindings/clear",
@@ -604,6 +726,13 @@ impl AxumServer {
             )
             .route("/accounts/bulk-delete", post(admin_delete_accounts))
             .route("/accounts/export", post(admin_export_accounts))
+            .route("/accounts/backup", post(admin_create_backup))
+            .route("/accounts/backup/restore", post(admin_restore_backup))
+            .route("/accounts/encryption", get(admin_get_account_encryption_status))
+            .route("/accounts/encryption/enable", post(admin_enable_account_encryption))
+            .route("/accounts/encryption/unlock", post(admin_unlock_account_encryption))
+            .route("/accounts/encryption/rekey", post(admin_rekey_account_encryption))
+            .route("/accounts/encryption/disable", post(admin_disable_account_encryption))
             .route("/accounts/reorder", post(admin_reorder_accounts))
             .route("/accounts/:accountId/quota", get(admin_fetch_account_quota))
             .route(
@@ -684,6 +813,8 @@ impl AxumServer {
             .layer(cors_layer())
             .layer(DefaultBodyLimit::max(max_body_size)) // 放宽 body 大小限制
             .with_state(state.clone());
+        // Gemini 批量任务的逐条请求经由同一套路由与中间件执行
+        handlers::gemini_resources::set_batch_router(app.clone());
 
         // 静态文件托管 (用于 Headless/Docker 模式)
         let dist_path = std::env::var("ABV_DIST_PATH").unwrap_or_else(|_| "dist".to_string());
@@ -714,6 +845,7 @@ impl AxumServer {
             upstream: state.upstream.clone(),
             security_state,
             zai_state,
+            providers_state,
             experimental: experimental_state.clone(),
             debug_logging: debug_logging_state.clone(),
             cloudflared_state,
@@ -887,6 +1019,154 @@ async fn admin_export_accounts(
     Ok(Json(response))
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateBackupRequest {
+    passphrase: String,
+    #[serde(default)]
+    options: crate::modules::backup::BackupOptions,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RestoreBackupRequest {
+    bundle: crate::modules::backup::BackupBundle,
+    passphrase: String,
+    #[serde(default)]
+    options: crate::modules::backup::RestoreOptions,
+}
+
+fn backup_error(e: String) -> (StatusCode, Json<ErrorResponse>) {
+    let status = if e == "invalid_backup_passphrase" {
+        StatusCode::UNAUTHORIZED
+    } else if e.starts_with("backup_") {
+        StatusCode::BAD_REQUEST
+    } else {
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+    (status, Json(ErrorResponse { error: e }))
+}
+
+async fn admin_create_backup(
+    Json(payload): Json<CreateBackupRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    tokio::task::spawn_blocking(move || {
+        crate::modules::backup::create_backup(&payload.passphrase, &payload.options)
+    })
+    .await
+    .map_err(|e| backup_error(e.to_string()))?
+    .map(Json)
+    .map_err(backup_error)
+}
+
+async fn admin_restore_backup(
+    State(state): State<AppState>,
+    Json(payload): Json<RestoreBackupRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let report = tokio::task::spawn_blocking(move || {
+        crate::modules::backup::restore_backup(&payload.bundle, &payload.passphrase, &payload.options)
+    })
+    .await
+    .map_err(|e| backup_error(e.to_string()))?
+    .map_err(backup_error)?;
+
+    if let Err(e) = state.token_manager.load_accounts().await {
+        logger::log_error(&format!(
+            "[API] Failed to reload accounts after backup restore: {}",
+            e
+        ));
+    }
+    Ok(Json(report))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccountPassphraseRequest {
+    passphrase: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccountRekeyRequest {
+    current_passphrase: String,
+    new_passphrase: String,
+}
+
+fn account_crypto_error(e: String) -> (StatusCode, Json<ErrorResponse>) {
+    let status = match e.as_str() {
+        "invalid_account_passphrase" => StatusCode::UNAUTHORIZED,
+        "account_encryption_not_enabled"
+        | "account_encryption_already_enabled"
+        | "account_passphrase_empty" => StatusCode::BAD_REQUEST,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, Json(ErrorResponse { error: e }))
+}
+
+async fn run_account_crypto<F>(op: F) -> Result<usize, (StatusCode, Json<ErrorResponse>)>
+where
+    F: FnOnce() -> Result<usize, String> + Send + 'static,
+{
+    // PBKDF2 派生较慢，放到阻塞线程池执行
+    tokio::task::spawn_blocking(op)
+        .await
+        .map_err(|e| account_crypto_error(e.to_string()))?
+        .map_err(account_crypto_error)
+}
+
+async fn admin_get_account_encryption_status(
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    crate::modules::account_crypto::status()
+        .map(Json)
+        .map_err(account_crypto_error)
+}
+
+async fn admin_enable_account_encryption(
+    Json(payload): Json<AccountPassphraseRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let migrated =
+        run_account_crypto(move || crate::modules::account_crypto::enable(&payload.passphrase))
+            .await?;
+    Ok(Json(serde_json::json!({ "migrated": migrated })))
+}
+
+async fn admin_unlock_account_encryption(
+    State(state): State<AppState>,
+    Json(payload): Json<AccountPassphraseRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let migrated =
+        run_account_crypto(move || crate::modules::account_crypto::unlock(&payload.passphrase))
+            .await?;
+
+    // 解锁后重新加载账号池 (锁定期间加载的账号会被跳过)
+    if let Err(e) = state.token_manager.load_accounts().await {
+        logger::log_error(&format!(
+            "[API] Failed to reload accounts after unlock: {}",
+            e
+        ));
+    }
+    Ok(Json(serde_json::json!({ "migrated": migrated })))
+}
+
+async fn admin_rekey_account_encryption(
+    Json(payload): Json<AccountRekeyRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let rekeyed = run_account_crypto(move || {
+        crate::modules::account_crypto::rekey(&payload.current_passphrase, &payload.new_passphrase)
+    })
+    .await?;
+    Ok(Json(serde_json::json!({ "rekeyed": rekeyed })))
+}
+
+async fn admin_disable_account_encryption(
+    Json(payload): Json<AccountPassphraseRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let decrypted =
+        run_account_crypto(move || crate::modules::account_crypto::disable(&payload.passphrase))
+            .await?;
+    Ok(Json(serde_json::json!({ "decrypted": decrypted })))
+}
+
 async fn admin_get_current_account(
     State(state): State<AppState>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
@@ -1342,7 +1622,40 @@ async fn admin_save_config(
         *zai = new_config.clone().proxy.zai;
     }
 
+    // 更新第三方提供商配置
+    {
+        let mut providers = state.providers.write().await;
+        *providers = new_config.clone().proxy.providers;
+    }
+
+    // 更新模型降级链
+    crate::proxy::update_fallback_chains(new_config.proxy.fallback_chains.clone());
+
+    // 更新请求调度配置
+    crate::proxy::update_dispatch_config(new_config.proxy.dispatch.clone());
+
+    // 更新路由规则
+    crate::proxy::update_routing_config(new_config.proxy.routing.clone());
+
+    // 更新声明式客户端适配器
+    crate::proxy::update_client_adapters(new_config.proxy.client_adapters.clone());
+
+    // 更新声明式工具适配器
+    crate::proxy::update_tool_adapters(new_config.proxy.tool_adapters.clone());
+
+    // 更新签名缓存持久化配置
+    crate::proxy::update_signature_cache_config(new_config.proxy.signature_cache.clone());
+
+    // 更新响应缓存配置
+    crate::proxy::update_response_cache_config(new_config.proxy.response_cache.clone());
+    crate::proxy::update_context_window_config(new_config.proxy.context_window.clone());
+    crate::proxy::update_script_hooks(new_config.proxy.script_hooks.clone());
+
     // 更新实验性配置
+    crate::proxy::config::update_tool_result_compression_config(
+        new_config.proxy.experimental.tool_result_compression.clone(),
+    );
+    crate::proxy::mappers::tokenizer::update_tokenizers(&new_config.proxy.experimental.tokenizers);
     {
         let mut exp = state.experimental.write().await;
         *exp = new_config.clone().proxy.experimental;
@@ -1522,6 +1835,320 @@ async fn admin_update_model_mapping(
     Ok(StatusCode::OK)
 }
 
+#[derive(Deserialize)]
+struct RoutingDryRunRequest {
+    /// 请求路径，如 `/v1/messages` 或 `/v1beta/models/gemini-3-flash:generateContent`
+    path: String,
+    /// User Token 用户名 (可选)
+    #[serde(default)]
+    user: Option<String>,
+    #[serde(default)]
+    headers: std::collections::HashMap<String, String>,
+    #[serde(default)]
+    body: serde_json::Value,
+    /// 待测试的规则配置，缺省时使用当前生效的配置
+    #[serde(default)]
+    routing: Option<crate::proxy::config::RoutingConfig>,
+}
+
+/// 列出内置与配置声明的客户端适配器
+async fn admin_list_client_adapters() -> impl IntoResponse {
+    Json(crate::proxy::common::client_adapter::list_client_adapters())
+}
+
+/// 列出内置与配置声明的工具适配器
+async fn admin_list_tool_adapters() -> impl IntoResponse {
+    Json(crate::proxy::common::json_schema::list_tool_adapters())
+}
+
+/// 通用 Schema 清洗改动过的工具 Schema 记录
+async fn admin_get_schema_log() -> impl IntoResponse {
+    Json(crate::proxy::common::schema_log::list())
+}
+
+async fn admin_clear_schema_log() -> impl IntoResponse {
+    crate::proxy::common::schema_log::clear();
+    StatusCode::OK
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SchemaLintRequest {
+    /// 工具名称 (用于匹配工具适配器)
+    #[serde(default)]
+    tool: Option<String>,
+    schema: serde_json::Value,
+    /// 可选的工具调用参数，按原始 Schema 校验
+    #[serde(default)]
+    args: Option<serde_json::Value>,
+}
+
+/// Schema lint：返回清洗后的 Schema 与有损改动报告，可同时按原始 Schema 校验一组参数
+async fn admin_lint_tool_schema(Json(payload): Json<SchemaLintRequest>) -> impl IntoResponse {
+    let tool = payload.tool.as_deref().unwrap_or("");
+    let result = crate::proxy::common::json_schema::lint_schema_for_tool(&payload.schema, tool);
+    let violations = payload
+        .args
+        .as_ref()
+        .map(|args| crate::proxy::common::schema_validate::validate(args, &payload.schema));
+    Json(serde_json::json!({
+        "tool": result.tool,
+        "adapter": result.adapter,
+        "cleaned": result.cleaned,
+        "issues": result.issues,
+        "arg_violations": violations,
+    }))
+}
+
+/// 签名缓存概览：持久化配置、各层命中统计与最近的会话
+async fn admin_get_signature_cache() -> impl IntoResponse {
+    let cache = crate::proxy::SignatureCache::global();
+    Json(serde_json::json!({
+        "config": crate::proxy::config::get_signature_cache_config(),
+        "stats": cache.stats(),
+        "sessions": cache.list_sessions(100),
+    }))
+}
+
+async fn admin_clear_signature_cache() -> impl IntoResponse {
+    crate::proxy::SignatureCache::global().clear();
+    logger::log_info("[API] 已清空签名缓存");
+    StatusCode::OK
+}
+
+async fn admin_get_session_signatures(Path(session_id): Path<String>) -> Response {
+    match crate::proxy::SignatureCache::global().session_info(&session_id) {
+        Some(info) => Json(info).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+async fn admin_purge_session_signatures(Path(session_id): Path<String>) -> Response {
+    let removed = crate::proxy::SignatureCache::global().purge_session(&session_id);
+    if removed == 0 {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    logger::log_info(&format!("[API] 已清除会话 {} 的签名缓存", session_id));
+    Json(serde_json::json!({ "removed": removed })).into_response()
+}
+
+/// 工具结果压缩：当前策略与各策略节省的字符数
+async fn admin_get_tool_result_compression() -> impl IntoResponse {
+    use crate::proxy::mappers::tool_result_compressor::compression_stats;
+
+    let stats = compression_stats();
+    let (original, output) = stats.values().fold((0u64, 0u64), |(o, p), s| {
+        (o + s.original_chars, p + s.output_chars)
+    });
+    Json(serde_json::json!({
+        "config": crate::proxy::config::get_tool_result_compression_config(),
+        "stats": stats,
+        "total": {
+            "original_chars": original,
+            "output_chars": output,
+            "saved_chars": original.saturating_sub(output),
+        },
+    }))
+}
+
+async fn admin_reset_tool_result_compression() -> impl IntoResponse {
+    crate::proxy::mappers::tool_result_compressor::reset_compression_stats();
+    StatusCode::OK
+}
+
+/// 响应缓存：当前配置与命中统计
+async fn admin_get_response_cache() -> impl IntoResponse {
+    Json(serde_json::json!({
+        "config": crate::proxy::config::get_response_cache_config(),
+        "stats": crate::proxy::response_cache::stats(),
+    }))
+}
+
+async fn admin_clear_response_cache() -> impl IntoResponse {
+    let removed = crate::proxy::response_cache::clear();
+    logger::log_info(&format!("[API] 已清空响应缓存 ({} 条)", removed));
+    Json(serde_json::json!({ "removed": removed }))
+}
+
+/// 上下文窗口：策略配置与裁剪统计
+async fn admin_get_context_window() -> impl IntoResponse {
+    Json(serde_json::json!({
+        "config": crate::proxy::config::get_context_window_config(),
+        "stats": crate::proxy::context_window::stats(),
+    }))
+}
+
+/// 思考策略：策略配置与命中统计
+async fn admin_get_thinking_policies() -> impl IntoResponse {
+    Json(serde_json::json!({
+        "policies": crate::proxy::config::get_thinking_policies(),
+        "stats": crate::proxy::thinking_policy::stats(),
+    }))
+}
+
+#[derive(Deserialize)]
+struct SystemPromptPreviewRequest {
+    protocol: String,
+    model: String,
+    #[serde(default)]
+    user: Option<String>,
+    #[serde(default)]
+    client: Option<String>,
+}
+
+/// 提示词片段：按给定的协议 / 模型 / 用户 / 客户端预览命中并渲染后的片段
+async fn admin_preview_system_prompts(
+    Json(payload): Json<SystemPromptPreviewRequest>,
+) -> impl IntoResponse {
+    let config = crate::proxy::config::get_global_system_prompt();
+    let ctx = crate::proxy::system_prompt::PromptContext {
+        protocol: &payload.protocol,
+        model: &payload.model,
+        user: payload.user.as_deref(),
+        client: payload.client.as_deref(),
+    };
+    Json(serde_json::json!({
+        "prompts": crate::proxy::system_prompt::select_prompts(&config.prompts, &ctx),
+    }))
+}
+
+/// 脚本钩子：已加载的钩子、编译错误与执行统计
+async fn admin_get_script_hooks() -> impl IntoResponse {
+    Json(crate::proxy::script_hooks::list_hooks())
+}
+
+#[derive(Deserialize)]
+struct ScriptHookTestRequest {
+    /// 已加载钩子的名称，与 `script` 二选一
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    script: Option<String>,
+    #[serde(default = "default_test_stage")]
+    stage: crate::proxy::config::ScriptHookStage,
+    body: serde_json::Value,
+    #[serde(default)]
+    protocol: Option<String>,
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    user: Option<String>,
+    #[serde(default)]
+    client: Option<String>,
+    #[serde(default)]
+    event: Option<String>,
+}
+
+fn default_test_stage() -> crate::proxy::config::ScriptHookStage {
+    crate::proxy::config::ScriptHookStage::Request
+}
+
+/// 脚本钩子试运行：对给定的 body 执行脚本并返回结果，不影响实际请求与统计
+async fn admin_test_script_hook(Json(payload): Json<ScriptHookTestRequest>) -> impl IntoResponse {
+    use crate::proxy::script_hooks::{test_script, HookContext, ScriptResult};
+
+    let ctx = HookContext {
+        stage: payload.stage,
+        protocol: payload.protocol.unwrap_or_else(|| "openai".to_string()),
+        model: payload.model.unwrap_or_default(),
+        user: payload.user,
+        client: payload.client,
+        event: payload.event,
+        trace_id: "script_hook_test".to_string(),
+    };
+    let started = std::time::Instant::now();
+    let result = test_script(
+        payload.name.as_deref(),
+        payload.script.as_deref(),
+        &payload.body,
+        &ctx,
+    );
+    let duration_ms = started.elapsed().as_secs_f64() * 1000.0;
+    Json(match result {
+        Ok(ScriptResult::Body(body)) => {
+            serde_json::json!({ "outcome": "continue", "body": body, "duration_ms": duration_ms })
+        }
+        Ok(ScriptResult::Dropped) => {
+            serde_json::json!({ "outcome": "drop", "duration_ms": duration_ms })
+        }
+        Ok(ScriptResult::Rejected(message)) => {
+            serde_json::json!({ "outcome": "reject", "message": message, "duration_ms": duration_ms })
+        }
+        Err(error) => {
+            serde_json::json!({ "outcome": "error", "error": error, "duration_ms": duration_ms })
+        }
+    })
+}
+
+/// Token 估算：已加载的分词器与各模型的校准因子
+async fn admin_get_token_estimation() -> impl IntoResponse {
+    let calibrator = crate::proxy::mappers::estimation_calibrator::get_calibrator();
+    Json(serde_json::json!({
+        "tokenizers": crate::proxy::mappers::tokenizer::list_tokenizers(),
+        "global_factor": calibrator.get_factor(),
+        "models": calibrator.model_factors(),
+    }))
+}
+
+/// 路由规则试运行：与路由中间件相同的评估与改写流程，不发送任何上游请求
+async fn admin_routing_dry_run(
+    Json(payload): Json<RoutingDryRunRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    use crate::proxy::middleware::fallback::{gemini_request_model, protocol_for_path};
+    use crate::proxy::middleware::routing::apply_routing;
+
+    let bad_request = |error: String| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error }));
+    let (mut parts, _) = axum::http::Request::post(payload.path.as_str())
+        .body(())
+        .map_err(|e| bad_request(format!("Invalid path: {}", e)))?
+        .into_parts();
+    for (name, value) in &payload.headers {
+        if let (Ok(name), Ok(value)) = (
+            axum::http::HeaderName::from_bytes(name.as_bytes()),
+            axum::http::HeaderValue::from_str(value),
+        ) {
+            parts.headers.insert(name, value);
+        }
+    }
+    let protocol = protocol_for_path(parts.uri.path());
+
+    let config = payload
+        .routing
+        .unwrap_or_else(crate::proxy::config::get_routing_config);
+    let mut body = payload.body;
+    let body_bytes = serde_json::to_vec(&body).map(|b| b.len()).unwrap_or(0);
+    let routed = apply_routing(
+        &config,
+        protocol,
+        &mut parts,
+        Some(&mut body),
+        body_bytes,
+        payload.user,
+    );
+    // 按 handler 的读取方式取得最终模型
+    let target_model = if protocol == Some(crate::proxy::ProviderProtocol::Gemini) {
+        gemini_request_model(&parts.extensions, &parts.uri)
+    } else {
+        body.get("model")
+            .and_then(|m| m.as_str())
+            .map(|s| s.to_string())
+    };
+    let account_group_members = routed
+        .decision
+        .account_group
+        .as_ref()
+        .and_then(|name| config.account_groups.get(name));
+
+    Ok(Json(serde_json::json!({
+        // 非对话端点不经过路由中间件
+        "routed": protocol.is_some(),
+        "facts": routed.facts,
+        "decision": routed.decision,
+        "target_model": target_model,
+        "account_group_members": account_group_members,
+    })))
+}
+
 async fn admin_generate_api_key() -> impl IntoResponse {
     let new_key = format!("sk-{}", uuid::Uuid::new_v4().to_string().replace("-", ""));
     Json(new_key)
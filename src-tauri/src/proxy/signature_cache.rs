@@ -1,6 +1,8 @@
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Mutex, OnceLock};
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 // Node.js proxy uses 2 hours TTL
 const SIGNATURE_TTL: Duration = Duration::from_secs(2 * 60 * 60);
@@ -33,9 +35,134 @@ impl<T> CacheEntry<T> {
         }
     }
 
+    /// Rebuild an entry from a persisted unix timestamp (seconds)
+    fn restored(data: T, unix_secs: u64) -> Self {
+        Self {
+            data,
+            timestamp: UNIX_EPOCH + Duration::from_secs(unix_secs),
+        }
+    }
+
     fn is_expired(&self) -> bool {
         self.timestamp.elapsed().unwrap_or(Duration::ZERO) > SIGNATURE_TTL
     }
+
+    fn unix_secs(&self) -> u64 {
+        self.timestamp
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0)
+    }
+}
+
+/// Hit/miss counters for one cache layer
+#[derive(Default)]
+struct LayerCounters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl LayerCounters {
+    fn record(&self, hit: bool) {
+        let counter = if hit { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn stats(&self, entries: usize) -> LayerStats {
+        LayerStats {
+            entries,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LayerStats {
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SignatureCacheStats {
+    pub tool: LayerStats,
+    pub family: LayerStats,
+    pub session: LayerStats,
+}
+
+/// Inspection view of a session's cached signatures (admin API)
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionSignatureInfo {
+    pub session_id: String,
+    pub message_count: usize,
+    pub signature_len: usize,
+    /// First characters of the signature, never the full value
+    pub signature_preview: String,
+    pub family: Option<String>,
+    /// Tool calls whose cached signature is the session's latest signature
+    pub tool_use_ids: Vec<String>,
+    pub age_secs: u64,
+    pub expired: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotEntry {
+    pub key: String,
+    pub value: String,
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotSession {
+    pub session_id: String,
+    pub signature: String,
+    pub message_count: usize,
+    pub timestamp: u64,
+}
+
+/// Serializable copy of all three layers, used for on-disk persistence
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SignatureSnapshot {
+    #[serde(default)]
+    pub tool_signatures: Vec<SnapshotEntry>,
+    #[serde(default)]
+    pub thinking_families: Vec<SnapshotEntry>,
+    #[serde(default)]
+    pub sessions: Vec<SnapshotSession>,
+}
+
+/// Keep the `limit` most recent live entries of a layer
+fn newest_live<T: Clone>(
+    cache: &HashMap<String, CacheEntry<T>>,
+    limit: usize,
+) -> Vec<(String, CacheEntry<T>)> {
+    let mut entries: Vec<_> = cache
+        .iter()
+        .filter(|(_, v)| !v.is_expired())
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    entries.sort_by(|a, b| b.1.timestamp.cmp(&a.1.timestamp));
+    entries.truncate(limit);
+    entries
+}
+
+/// Insert a restored entry unless it expired or the cache already holds a newer one
+fn restore_entry<T>(
+    cache: &mut HashMap<String, CacheEntry<T>>,
+    key: String,
+    entry: CacheEntry<T>,
+) -> bool {
+    if entry.is_expired() {
+        return false;
+    }
+    let newer_exists = cache
+        .get(&key)
+        .is_some_and(|existing| existing.timestamp >= entry.timestamp);
+    if !newer_exists {
+        cache.insert(key, entry);
+    }
+    !newer_exists
 }
 
 /// Triple-layer signature cache to handle:
@@ -58,6 +185,13 @@ pub struct SignatureCache {
     /// Value: The most recent valid thought signature for this session
     /// This prevents signature pollution between different conversations
     session_signatures: Mutex<HashMap<String, CacheEntry<SessionSignatureEntry>>>,
+
+    tool_stats: LayerCounters,
+    family_stats: LayerCounters,
+    session_stats: LayerCounters,
+
+    /// Set whenever the content changes, cleared when persisted to disk
+    dirty: AtomicBool,
 }
 
 impl SignatureCache {
@@ -66,9 +200,22 @@ impl SignatureCache {
             tool_signatures: Mutex::new(HashMap::new()),
             thinking_families: Mutex::new(HashMap::new()),
             session_signatures: Mutex::new(HashMap::new()),
+            tool_stats: LayerCounters::default(),
+            family_stats: LayerCounters::default(),
+            session_stats: LayerCounters::default(),
+            dirty: AtomicBool::new(false),
         }
     }
 
+    fn mark_dirty(&self) {
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns whether the cache changed since the last call (used by the persistence flusher)
+    pub fn take_dirty(&self) -> bool {
+        self.dirty.swap(false, Ordering::Relaxed)
+    }
+
     /// Global singleton instance
     pub fn global() -> &'static SignatureCache {
         static INSTANCE: OnceLock<SignatureCache> = OnceLock::new();
@@ -84,7 +231,8 @@ impl SignatureCache {
         if let Ok(mut cache) = self.tool_signatures.lock() {
             tracing::debug!("[SignatureCache] Caching tool signature for id: {}", tool_use_id);
             cache.insert(tool_use_id.to_string(), CacheEntry::new(signature));
-            
+            self.mark_dirty();
+
             // Clean up expired entries when limit is reached
             if cache.len() > TOOL_CACHE_LIMIT {
                 let before = cache.len();
@@ -103,10 +251,12 @@ impl SignatureCache {
             if let Some(entry) = cache.get(tool_use_id) {
                 if !entry.is_expired() {
                     tracing::debug!("[SignatureCache] Hit tool signature for id: {}", tool_use_id);
+                    self.tool_stats.record(true);
                     return Some(entry.data.clone());
                 }
             }
         }
+        self.tool_stats.record(false);
         None
     }
 
@@ -119,7 +269,8 @@ impl SignatureCache {
         if let Ok(mut cache) = self.thinking_families.lock() {
             tracing::debug!("[SignatureCache] Caching thinking family for sig (len={}): {}", signature.len(), family);
             cache.insert(signature, CacheEntry::new(family));
-            
+            self.mark_dirty();
+
             if cache.len() > FAMILY_CACHE_LIMIT {
                 let before = cache.len();
                 cache.retain(|_, v| !v.is_expired());
@@ -136,12 +287,14 @@ impl SignatureCache {
         if let Ok(cache) = self.thinking_families.lock() {
             if let Some(entry) = cache.get(signature) {
                 if !entry.is_expired() {
+                    self.family_stats.record(true);
                     return Some(entry.data.clone());
                 } else {
                     tracing::debug!("[SignatureCache] Signature family entry expired");
                 }
             }
         }
+        self.family_stats.record(false);
         None
     }
 
@@ -200,6 +353,7 @@ impl SignatureCache {
                         message_count 
                     })
                 );
+                self.mark_dirty();
             }
 
             // Cleanup when limit is reached (Session cache has largest limit)
@@ -230,12 +384,14 @@ impl SignatureCache {
                         session_id,
                         entry.data.signature.len()
                     );
+                    self.session_stats.record(true);
                     return Some(entry.data.signature.clone());
                 } else {
                     tracing::debug!("[SignatureCache] Session {} -> EXPIRED", session_id);
                 }
             }
         }
+        self.session_stats.record(false);
         None
     }
 
@@ -245,13 +401,188 @@ impl SignatureCache {
         if let Ok(mut cache) = self.session_signatures.lock() {
             if cache.remove(session_id).is_some() {
                 tracing::debug!("[SignatureCache] Deleted session signature for: {}", session_id);
+                self.mark_dirty();
             }
         }
     }
 
+    /// Inspect a session's signature and the tool/family entries sharing it
+    pub fn session_info(&self, session_id: &str) -> Option<SessionSignatureInfo> {
+        let (signature, message_count, age_secs, expired) = {
+            let cache = self.session_signatures.lock().ok()?;
+            let entry = cache.get(session_id)?;
+            (
+                entry.data.signature.clone(),
+                entry.data.message_count,
+                entry.timestamp.elapsed().unwrap_or(Duration::ZERO).as_secs(),
+                entry.is_expired(),
+            )
+        };
+        let family = self
+            .thinking_families
+            .lock()
+            .ok()
+            .and_then(|cache| cache.get(&signature).map(|e| e.data.clone()));
+        let mut tool_use_ids: Vec<String> = self
+            .tool_signatures
+            .lock()
+            .map(|cache| {
+                cache
+                    .iter()
+                    .filter(|(_, v)| v.data == signature)
+                    .map(|(k, _)| k.clone())
+                    .collect()
+            })
+            .unwrap_or_default();
+        tool_use_ids.sort();
+
+        Some(SessionSignatureInfo {
+            session_id: session_id.to_string(),
+            message_count,
+            signature_len: signature.len(),
+            signature_preview: format!("{}...", signature.chars().take(16).collect::<String>()),
+            family,
+            tool_use_ids,
+            age_secs,
+            expired,
+        })
+    }
+
+    /// Most recently updated sessions first
+    pub fn list_sessions(&self, limit: usize) -> Vec<SessionSignatureInfo> {
+        let mut ids: Vec<(String, SystemTime)> = self
+            .session_signatures
+            .lock()
+            .map(|cache| cache.iter().map(|(k, v)| (k.clone(), v.timestamp)).collect())
+            .unwrap_or_default();
+        ids.sort_by(|a, b| b.1.cmp(&a.1));
+        ids.into_iter()
+            .take(limit)
+            .filter_map(|(id, _)| self.session_info(&id))
+            .collect()
+    }
+
+    /// Remove a session's signature together with the tool and family entries carrying it.
+    /// Returns the number of removed entries.
+    pub fn purge_session(&self, session_id: &str) -> usize {
+        let Some(entry) = self
+            .session_signatures
+            .lock()
+            .ok()
+            .and_then(|mut cache| cache.remove(session_id))
+        else {
+            return 0;
+        };
+        let signature = entry.data.signature;
+        let mut removed = 1;
+
+        if let Ok(mut cache) = self.tool_signatures.lock() {
+            let before = cache.len();
+            cache.retain(|_, v| v.data != signature);
+            removed += before - cache.len();
+        }
+        if let Ok(mut cache) = self.thinking_families.lock() {
+            removed += usize::from(cache.remove(&signature).is_some());
+        }
+        self.mark_dirty();
+        tracing::info!(
+            "[SignatureCache] Purged session {} ({} entries)",
+            session_id,
+            removed
+        );
+        removed
+    }
+
+    pub fn stats(&self) -> SignatureCacheStats {
+        let len = |m: &Mutex<HashMap<String, CacheEntry<String>>>| {
+            m.lock().map(|c| c.len()).unwrap_or(0)
+        };
+        SignatureCacheStats {
+            tool: self.tool_stats.stats(len(&self.tool_signatures)),
+            family: self.family_stats.stats(len(&self.thinking_families)),
+            session: self
+                .session_stats
+                .stats(self.session_signatures.lock().map(|c| c.len()).unwrap_or(0)),
+        }
+    }
+
+    /// Copy of the live entries, bounded by the per-layer limits (newest first)
+    pub fn snapshot(&self) -> SignatureSnapshot {
+        let entries = |m: &Mutex<HashMap<String, CacheEntry<String>>>, limit: usize| {
+            m.lock()
+                .map(|cache| {
+                    newest_live(&cache, limit)
+                        .into_iter()
+                        .map(|(key, e)| SnapshotEntry {
+                            key,
+                            timestamp: e.unix_secs(),
+                            value: e.data,
+                        })
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        let sessions = self
+            .session_signatures
+            .lock()
+            .map(|cache| {
+                newest_live(&cache, SESSION_CACHE_LIMIT)
+                    .into_iter()
+                    .map(|(session_id, e)| SnapshotSession {
+                        session_id,
+                        timestamp: e.unix_secs(),
+                        signature: e.data.signature,
+                        message_count: e.data.message_count,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        SignatureSnapshot {
+            tool_signatures: entries(&self.tool_signatures, TOOL_CACHE_LIMIT),
+            thinking_families: entries(&self.thinking_families, FAMILY_CACHE_LIMIT),
+            sessions,
+        }
+    }
+
+    /// Merge a persisted snapshot; expired or too-short entries are skipped and
+    /// entries already updated in this process are kept. Returns the number restored.
+    pub fn restore(&self, snapshot: SignatureSnapshot) -> usize {
+        let mut restored = 0;
+        if let Ok(mut cache) = self.tool_signatures.lock() {
+            for e in snapshot.tool_signatures.into_iter().take(TOOL_CACHE_LIMIT) {
+                if e.value.len() >= MIN_SIGNATURE_LENGTH {
+                    let entry = CacheEntry::restored(e.value, e.timestamp);
+                    restored += usize::from(restore_entry(&mut cache, e.key, entry));
+                }
+            }
+        }
+        if let Ok(mut cache) = self.thinking_families.lock() {
+            for e in snapshot.thinking_families.into_iter().take(FAMILY_CACHE_LIMIT) {
+                if e.key.len() >= MIN_SIGNATURE_LENGTH {
+                    let entry = CacheEntry::restored(e.value, e.timestamp);
+                    restored += usize::from(restore_entry(&mut cache, e.key, entry));
+                }
+            }
+        }
+        if let Ok(mut cache) = self.session_signatures.lock() {
+            for e in snapshot.sessions.into_iter().take(SESSION_CACHE_LIMIT) {
+                if e.signature.len() >= MIN_SIGNATURE_LENGTH {
+                    let entry = SessionSignatureEntry {
+                        signature: e.signature,
+                        message_count: e.message_count,
+                    };
+                    let entry = CacheEntry::restored(entry, e.timestamp);
+                    restored += usize::from(restore_entry(&mut cache, e.session_id, entry));
+                }
+            }
+        }
+        restored
+    }
+
     /// Clear all caches (for testing or manual reset)
-    #[allow(dead_code)] // Used in tests
     pub fn clear(&self) {
+        self.mark_dirty();
         if let Ok(mut cache) = self.tool_signatures.lock() {
             cache.clear();
         }
@@ -329,6 +660,44 @@ mod tests {
         assert!(cache.get_session_signature("sid-other").is_none());
     }
 
+    #[test]
+    fn test_snapshot_restore_and_purge() {
+        let cache = SignatureCache::new();
+        let sig = "s".repeat(60);
+        cache.cache_tool_signature("toolu_1", sig.clone());
+        cache.cache_thinking_family(sig.clone(), "claude".to_string());
+        cache.cache_session_signature("sid-1", sig.clone(), 4);
+        assert!(cache.take_dirty());
+        assert!(!cache.take_dirty());
+
+        let mut snapshot = cache.snapshot();
+        // Expired entries are dropped on restore
+        snapshot.tool_signatures.push(SnapshotEntry {
+            key: "toolu_old".to_string(),
+            value: "o".repeat(60),
+            timestamp: 0,
+        });
+
+        let restored = SignatureCache::new();
+        assert_eq!(restored.restore(snapshot), 3);
+        assert_eq!(restored.get_tool_signature("toolu_1"), Some(sig.clone()));
+        assert_eq!(restored.get_tool_signature("toolu_old"), None);
+
+        let info = restored.session_info("sid-1").unwrap();
+        assert_eq!(info.message_count, 4);
+        assert_eq!(info.family.as_deref(), Some("claude"));
+        assert_eq!(info.tool_use_ids, vec!["toolu_1".to_string()]);
+        assert!(!info.signature_preview.contains(&sig));
+
+        let stats = restored.stats();
+        assert_eq!((stats.tool.hits, stats.tool.misses), (1, 1));
+
+        assert_eq!(restored.purge_session("sid-1"), 3);
+        assert!(restored.get_tool_signature("toolu_1").is_none());
+        assert!(restored.get_signature_family(&sig).is_none());
+        assert_eq!(restored.purge_session("sid-1"), 0);
+    }
+
     #[test]
     fn test_clear_all_caches() {
         let cache = SignatureCache::new();
@@ -0,0 +1,186 @@
+// 思维签名缓存持久化
+//
+// 将 `SignatureCache` 的三层缓存定期写入数据目录，启动时恢复，
+// 使长会话在重启后仍能复用原有签名，而不是降级为移除 thinking。
+// 文件内容可选使用本机设备密钥加密；读取时根据前缀自动识别是否加密。
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use crate::modules::account::{atomic_replace_file, get_data_dir};
+use crate::proxy::config::get_signature_cache_config;
+use crate::proxy::signature_cache::{SignatureCache, SignatureSnapshot};
+use crate::utils::crypto;
+
+const CACHE_FILE: &str = "signature_cache.json";
+const FILE_VERSION: u32 = 1;
+const MIN_FLUSH_INTERVAL_SECS: u64 = 5;
+
+static STARTED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    saved_at: i64,
+    #[serde(flatten)]
+    snapshot: SignatureSnapshot,
+}
+
+pub fn cache_file_path() -> Result<PathBuf, String> {
+    Ok(get_data_dir()?.join(CACHE_FILE))
+}
+
+fn encode(snapshot: SignatureSnapshot, encrypt: bool) -> Result<String, String> {
+    let file = CacheFile {
+        version: FILE_VERSION,
+        saved_at: chrono::Utc::now().timestamp(),
+        snapshot,
+    };
+    let json = serde_json::to_string(&file).map_err(|e| format!("序列化签名缓存失败: {}", e))?;
+    if encrypt {
+        crypto::encrypt_with_key(&crypto::device_key(), &json)
+    } else {
+        Ok(json)
+    }
+}
+
+fn decode(content: &str) -> Result<SignatureSnapshot, String> {
+    let json = if crypto::is_keyed_ciphertext(content) {
+        crypto::decrypt_with_key(&crypto::device_key(), content)?
+    } else {
+        content.to_string()
+    };
+    let file: CacheFile =
+        serde_json::from_str(&json).map_err(|e| format!("解析签名缓存失败: {}", e))?;
+    if file.version > FILE_VERSION {
+        return Err(format!("不支持的签名缓存版本: {}", file.version));
+    }
+    Ok(file.snapshot)
+}
+
+/// 从磁盘恢复签名缓存，返回恢复的条目数
+pub fn load_from_disk() -> Result<usize, String> {
+    let path = cache_file_path()?;
+    if !path.exists() {
+        return Ok(0);
+    }
+    let content = std::fs::read_to_string(&path).map_err(|e| format!("读取签名缓存失败: {}", e))?;
+    let restored = SignatureCache::global().restore(decode(content.trim())?);
+    tracing::info!(
+        "[SignatureCache] Restored {} entries from {:?}",
+        restored,
+        path
+    );
+    Ok(restored)
+}
+
+/// 将当前签名缓存写入磁盘 (原子替换)，返回写入的条目数
+pub fn save_to_disk() -> Result<usize, String> {
+    let config = get_signature_cache_config();
+    if !config.persist {
+        return Ok(0);
+    }
+    let snapshot = SignatureCache::global().snapshot();
+    let count =
+        snapshot.tool_signatures.len() + snapshot.thinking_families.len() + snapshot.sessions.len();
+    let content = encode(snapshot, config.encrypt)?;
+
+    let path = cache_file_path()?;
+    let temp_path = path.with_extension(format!("json.tmp.{}", uuid::Uuid::new_v4()));
+    std::fs::write(&temp_path, content).map_err(|e| format!("写入签名缓存失败: {}", e))?;
+    atomic_replace_file(&temp_path, &path)?;
+    tracing::debug!("[SignatureCache] Persisted {} entries", count);
+    Ok(count)
+}
+
+/// 启动时恢复缓存并启动后台落盘任务 (进程内只执行一次)
+pub fn start() {
+    if STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    if get_signature_cache_config().persist {
+        if let Err(e) = load_from_disk() {
+            tracing::warn!("[SignatureCache] Failed to restore cache: {}", e);
+        }
+    }
+    // 恢复过程不算作变化
+    SignatureCache::global().take_dirty();
+
+    tokio::spawn(async move {
+        loop {
+            let interval = get_signature_cache_config()
+                .flush_interval_secs
+                .max(MIN_FLUSH_INTERVAL_SECS);
+            tokio::time::sleep(Duration::from_secs(interval)).await;
+
+            if !get_signature_cache_config().persist || !SignatureCache::global().take_dirty() {
+                continue;
+            }
+            match tokio::task::spawn_blocking(save_to_disk).await {
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => tracing::warn!("[SignatureCache] Failed to persist cache: {}", e),
+                Err(e) => tracing::warn!("[SignatureCache] Persist task panicked: {}", e),
+            }
+        }
+    });
+}
+
+/// 退出前落盘
+pub fn flush_on_shutdown() {
+    if !STARTED.load(Ordering::SeqCst) {
+        return;
+    }
+    match save_to_disk() {
+        Ok(count) if count > 0 => {
+            tracing::info!("[SignatureCache] Persisted {} entries before exit", count)
+        }
+        Ok(_) => {}
+        Err(e) => tracing::warn!("[SignatureCache] Failed to persist cache on exit: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::signature_cache::{SnapshotEntry, SnapshotSession};
+
+    fn sample() -> SignatureSnapshot {
+        let now = chrono::Utc::now().timestamp() as u64;
+        SignatureSnapshot {
+            tool_signatures: vec![SnapshotEntry {
+                key: "toolu_01".to_string(),
+                value: "s".repeat(64),
+                timestamp: now,
+            }],
+            thinking_families: Vec::new(),
+            sessions: vec![SnapshotSession {
+                session_id: "sid-1".to_string(),
+                signature: "s".repeat(64),
+                message_count: 3,
+                timestamp: now,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_encode_decode_plain_and_encrypted() {
+        let plain = encode(sample(), false).unwrap();
+        assert!(plain.starts_with('{'));
+        assert_eq!(decode(&plain).unwrap().sessions[0].message_count, 3);
+
+        let encrypted = encode(sample(), true).unwrap();
+        assert!(crypto::is_keyed_ciphertext(&encrypted));
+        assert!(!encrypted.contains("sid-1"));
+        let snapshot = decode(&encrypted).unwrap();
+        assert_eq!(snapshot.tool_signatures[0].key, "toolu_01");
+    }
+
+    #[test]
+    fn test_decode_rejects_newer_version() {
+        let content = r#"{"version": 99, "saved_at": 0}"#;
+        assert!(decode(content).is_err());
+        assert!(decode("not json").is_err());
+    }
+}
@@ -0,0 +1,245 @@
+//! 按条件注入的系统提示词片段
+//!
+//! `proxy.global_system_prompt.prompts` 中的片段按协议、映射后的模型、用户令牌与客户端适配器匹配，
+//! 全部命中的片段按配置顺序注入：`prepend` 位于全局提示词之后、客户端系统提示词之前，
+//! `append` 位于客户端系统提示词之后。三种协议的请求转换逻辑在构建 systemInstruction 时调用
+//! [`current_prompts`]；调用方特征由 system_prompt 中间件通过 task-local 作用域传递。
+
+use serde::Serialize;
+use std::future::Future;
+use std::sync::Arc;
+
+use crate::proxy::common::model_mapping::wildcard_match;
+use crate::proxy::config::{SystemPromptPosition, SystemPromptRule};
+
+/// 参与片段匹配的调用方特征
+#[derive(Debug, Clone, Default)]
+pub struct PromptCaller {
+    pub user: Option<String>,
+    pub client: Option<String>,
+}
+
+tokio::task_local! {
+    static CURRENT_CALLER: Arc<PromptCaller>;
+}
+
+/// 在调用方作用域内执行 future
+pub async fn with_caller<F: Future>(caller: Arc<PromptCaller>, fut: F) -> F::Output {
+    CURRENT_CALLER.scope(caller, fut).await
+}
+
+/// 当前请求的调用方特征
+pub fn current_caller() -> Option<Arc<PromptCaller>> {
+    CURRENT_CALLER.try_with(|c| c.clone()).ok()
+}
+
+/// 匹配与模板渲染所需的请求信息
+#[derive(Debug, Clone, Copy)]
+pub struct PromptContext<'a> {
+    pub protocol: &'a str,
+    pub model: &'a str,
+    pub user: Option<&'a str>,
+    pub client: Option<&'a str>,
+}
+
+/// 渲染后的片段
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct RenderedPrompt {
+    pub name: String,
+    pub position: SystemPromptPosition,
+    pub text: String,
+}
+
+fn normalize_protocol(protocol: &str) -> String {
+    let lower = protocol.trim().to_lowercase();
+    if lower == "claude" {
+        "anthropic".to_string()
+    } else {
+        lower
+    }
+}
+
+fn any_glob(patterns: &[String], value: Option<&str>) -> bool {
+    value.is_some_and(|value| patterns.iter().any(|p| wildcard_match(p, value)))
+}
+
+fn any_name(names: &[String], value: Option<&str>) -> bool {
+    value.is_some_and(|value| names.iter().any(|n| n.eq_ignore_ascii_case(value)))
+}
+
+/// 判断片段是否适用于当前请求
+pub fn rule_matches(rule: &SystemPromptRule, ctx: &PromptContext) -> bool {
+    if !rule.enabled || rule.content.trim().is_empty() {
+        return false;
+    }
+    let protocol = normalize_protocol(ctx.protocol);
+    (rule.protocols.is_empty()
+        || rule
+            .protocols
+            .iter()
+            .any(|p| normalize_protocol(p) == protocol))
+        && (rule.models.is_empty() || any_glob(&rule.models, Some(ctx.model)))
+        && (rule.users.is_empty() || any_glob(&rule.users, ctx.user))
+        && (rule.clients.is_empty() || any_name(&rule.clients, ctx.client))
+        && !any_glob(&rule.exclude_users, ctx.user)
+        && !any_name(&rule.exclude_clients, ctx.client)
+}
+
+/// 替换模板变量 (未知变量原样保留)
+pub fn render(template: &str, ctx: &PromptContext, now: chrono::DateTime<chrono::Local>) -> String {
+    let date = now.format("%Y-%m-%d").to_string();
+    let datetime = now.format("%Y-%m-%d %H:%M %:z").to_string();
+    [
+        ("{{date}}", date.as_str()),
+        ("{{datetime}}", datetime.as_str()),
+        ("{{username}}", ctx.user.unwrap_or("")),
+        ("{{model}}", ctx.model),
+        ("{{client}}", ctx.client.unwrap_or("")),
+        ("{{protocol}}", ctx.protocol),
+    ]
+    .iter()
+    .fold(template.to_string(), |text, (key, value)| {
+        text.replace(key, value)
+    })
+}
+
+/// 按配置顺序选出全部命中的片段并渲染
+pub fn select_prompts(rules: &[SystemPromptRule], ctx: &PromptContext) -> Vec<RenderedPrompt> {
+    let now = chrono::Local::now();
+    rules
+        .iter()
+        .filter(|rule| rule_matches(rule, ctx))
+        .map(|rule| RenderedPrompt {
+            name: rule.name.clone(),
+            position: rule.position,
+            text: render(&rule.content, ctx, now),
+        })
+        .collect()
+}
+
+/// 当前请求在指定位置需要注入的片段文本
+pub fn current_prompts(protocol: &str, model: &str, position: SystemPromptPosition) -> Vec<String> {
+    let config = crate::proxy::config::get_global_system_prompt();
+    if config.prompts.is_empty() {
+        return Vec::new();
+    }
+    let caller = current_caller().unwrap_or_default();
+    let ctx = PromptContext {
+        protocol,
+        model,
+        user: caller.user.as_deref(),
+        client: caller.client.as_deref(),
+    };
+    select_prompts(&config.prompts, &ctx)
+        .into_iter()
+        .filter(|prompt| prompt.position == position)
+        .map(|prompt| {
+            tracing::debug!(
+                "[System-Prompt] Injecting '{}' ({:?}) for {} / {}",
+                prompt.name,
+                position,
+                protocol,
+                model
+            );
+            prompt.text
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(name: &str, content: &str) -> SystemPromptRule {
+        SystemPromptRule {
+            name: name.to_string(),
+            enabled: true,
+            content: content.to_string(),
+            position: SystemPromptPosition::Prepend,
+            protocols: Vec::new(),
+            models: Vec::new(),
+            users: Vec::new(),
+            clients: Vec::new(),
+            exclude_users: Vec::new(),
+            exclude_clients: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_rule_matching() {
+        let mut policy = rule("policy", "Follow company policy.");
+        policy.protocols = vec!["openai".to_string(), "claude".to_string()];
+        policy.users = vec!["ext-*".to_string()];
+        policy.exclude_clients = vec!["opencode".to_string()];
+        let mut gemini_only = rule("gemini", "Be brief.");
+        gemini_only.models = vec!["gemini-*".to_string()];
+        gemini_only.position = SystemPromptPosition::Append;
+        let rules = vec![policy, gemini_only];
+
+        let ctx = |protocol, model, user, client| PromptContext {
+            protocol,
+            model,
+            user,
+            client,
+        };
+        let names = |ctx: PromptContext| {
+            select_prompts(&rules, &ctx)
+                .into_iter()
+                .map(|p| p.name)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            names(ctx(
+                "anthropic",
+                "claude-sonnet-4-5",
+                Some("ext-acme"),
+                None
+            )),
+            vec!["policy"]
+        );
+        assert!(names(ctx(
+            "anthropic",
+            "claude-sonnet-4-5",
+            Some("ext-acme"),
+            Some("OpenCode")
+        ))
+        .is_empty());
+        assert_eq!(
+            names(ctx("openai", "gemini-2.5-pro", Some("alice"), None)),
+            vec!["gemini"]
+        );
+        assert_eq!(
+            names(ctx("openai", "gemini-2.5-pro", Some("ext-acme"), None)),
+            vec!["policy", "gemini"]
+        );
+        // 匿名请求不匹配带用户条件的片段
+        assert_eq!(
+            names(ctx("openai", "gemini-2.5-pro", None, None)),
+            vec!["gemini"]
+        );
+    }
+
+    #[test]
+    fn test_render_template_variables() {
+        let ctx = PromptContext {
+            protocol: "openai",
+            model: "gemini-2.5-pro",
+            user: Some("alice"),
+            client: None,
+        };
+        let now = chrono::Local::now();
+        let text = render(
+            "Today is {{date}}. User {{username}} on {{model}} via {{protocol}}{{client}}. {{unknown}}",
+            &ctx,
+            now,
+        );
+        assert_eq!(
+            text,
+            format!(
+                "Today is {}. User alice on gemini-2.5-pro via openai. {{{{unknown}}}}",
+                now.format("%Y-%m-%d")
+            )
+        );
+    }
+}
@@ -0,0 +1,401 @@
+//! 思考策略 (按模型家族 / 调用方)
+//!
+//! `proxy.thinking_budget.policies` 按顺序匹配映射后的模型、用户令牌、客户端适配器与后台任务类型，
+//! 首个命中的策略作用于转换后的 v1internal 请求 (`request.generationConfig.thinkingConfig`)：
+//! - `cap`: 思考预算不超过 `budget` (thinkingLevel / 动态预算 -1 也改为固定预算)
+//! - `max`: 使用模型允许的最大思考预算，并保证 maxOutputTokens 大于预算
+//! - `off`: 关闭思考；Gemini 模型无法完全关闭时使用最小预算并隐藏思考内容
+//!
+//! 调用方特征由 thinking_policy 中间件收集并通过 task-local 作用域传递给 handler。
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use crate::proxy::common::model_mapping::wildcard_match;
+use crate::proxy::config::{ThinkingPolicy, ThinkingPolicyAction};
+use crate::proxy::mappers::claude::request::clean_thinking_fields_recursive;
+
+/// Gemini Pro 系列允许的最小思考预算
+const GEMINI_MIN_THINKING_BUDGET: u64 = 128;
+/// 提高思考预算时为正文预留的输出 Token
+const ANSWER_HEADROOM: u64 = 8192;
+
+/// 参与策略匹配的调用方特征
+#[derive(Debug, Default)]
+pub struct CallerFacts {
+    pub user: Option<String>,
+    pub client: Option<String>,
+    /// 是否为后台任务 (标题生成、摘要等)
+    pub background: bool,
+    /// 本次请求实际生效的策略名称 (供中间件写入响应头)
+    applied: Mutex<Option<String>>,
+}
+
+impl CallerFacts {
+    pub fn new(user: Option<String>, client: Option<String>, background: bool) -> Self {
+        Self {
+            user,
+            client,
+            background,
+            applied: Mutex::new(None),
+        }
+    }
+
+    pub fn applied_policy(&self) -> Option<String> {
+        self.applied.lock().ok().and_then(|name| name.clone())
+    }
+}
+
+tokio::task_local! {
+    static CURRENT_CALLER: Arc<CallerFacts>;
+}
+
+/// 在调用方作用域内执行 future
+pub async fn with_caller<F: Future>(caller: Arc<CallerFacts>, fut: F) -> F::Output {
+    CURRENT_CALLER.scope(caller, fut).await
+}
+
+/// 当前请求的调用方特征
+pub fn current_caller() -> Option<Arc<CallerFacts>> {
+    CURRENT_CALLER.try_with(|c| c.clone()).ok()
+}
+
+/// 选择首个命中的策略 (条件之间为 AND，未填写的条件匹配任意值)
+pub fn select_policy<'a>(
+    policies: &'a [ThinkingPolicy],
+    model: &str,
+    caller: &CallerFacts,
+) -> Option<&'a ThinkingPolicy> {
+    let matches_any = |patterns: &[String], value: Option<&str>, glob: bool| {
+        patterns.is_empty()
+            || value.is_some_and(|value| {
+                patterns.iter().any(|pattern| {
+                    if glob {
+                        wildcard_match(pattern, value)
+                    } else {
+                        pattern.eq_ignore_ascii_case(value)
+                    }
+                })
+            })
+    };
+    policies.iter().find(|policy| {
+        matches_any(&policy.models, Some(model), true)
+            && matches_any(&policy.users, caller.user.as_deref(), true)
+            && matches_any(&policy.clients, caller.client.as_deref(), false)
+            && policy
+                .background
+                .map_or(true, |background| background == caller.background)
+    })
+}
+
+/// 策略对请求的修改结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ThinkingChange {
+    Unchanged,
+    Capped { from: Option<i64>, to: u64 },
+    Raised { to: u64 },
+    Disabled,
+}
+
+fn is_gemini(model: &str) -> bool {
+    model.to_lowercase().starts_with("gemini")
+}
+
+/// 保证 maxOutputTokens 大于思考预算
+fn ensure_output_headroom(gen_config: &mut Value, budget: u64, model: &str) {
+    let limit = crate::proxy::model_specs::get_max_output_tokens(model, None);
+    let current = gen_config.get("maxOutputTokens").and_then(|v| v.as_u64());
+    if current.map_or(true, |current| current <= budget) {
+        gen_config["maxOutputTokens"] = json!((budget + ANSWER_HEADROOM).min(limit));
+    }
+}
+
+/// 对 v1internal 请求体应用策略
+pub fn apply_policy(policy: &ThinkingPolicy, body: &mut Value, model: &str) -> ThinkingChange {
+    let Some(request) = body.get_mut("request").and_then(|r| r.as_object_mut()) else {
+        return ThinkingChange::Unchanged;
+    };
+
+    match policy.action {
+        ThinkingPolicyAction::Cap => {
+            let cap = policy.budget.unwrap_or(0) as u64;
+            let Some(thinking) = request
+                .get_mut("generationConfig")
+                .and_then(|g| g.get_mut("thinkingConfig"))
+                .and_then(|t| t.as_object_mut())
+            else {
+                return ThinkingChange::Unchanged;
+            };
+            let current = thinking.get("thinkingBudget").and_then(|v| v.as_i64());
+            // -1 (动态) 与 thinkingLevel 均无法保证上限，统一改为固定预算
+            let exceeds = thinking.contains_key("thinkingLevel")
+                || current.is_some_and(|b| b < 0 || b as u64 > cap);
+            if !exceeds {
+                return ThinkingChange::Unchanged;
+            }
+            thinking.remove("thinkingLevel");
+            thinking.insert("thinkingBudget".to_string(), json!(cap));
+            ThinkingChange::Capped {
+                from: current,
+                to: cap,
+            }
+        }
+        ThinkingPolicyAction::Max => {
+            if !crate::proxy::model_specs::is_thinking_model(model) && !is_gemini(model) {
+                return ThinkingChange::Unchanged;
+            }
+            let budget = crate::proxy::model_specs::get_thinking_budget(model, None);
+            let gen_config = request
+                .entry("generationConfig")
+                .or_insert_with(|| json!({}));
+            if !gen_config.is_object() {
+                return ThinkingChange::Unchanged;
+            }
+            let thinking = &mut gen_config["thinkingConfig"];
+            if !thinking.is_object() {
+                *thinking = json!({ "includeThoughts": true });
+            }
+            if let Some(level) = thinking.get_mut("thinkingLevel") {
+                // Claude 分级参数：直接使用最高等级
+                if level.as_str() == Some("high") {
+                    return ThinkingChange::Unchanged;
+                }
+                *level = json!("high");
+                return ThinkingChange::Raised { to: budget };
+            }
+            if thinking.get("thinkingBudget").and_then(|v| v.as_u64()) == Some(budget) {
+                return ThinkingChange::Unchanged;
+            }
+            thinking["thinkingBudget"] = json!(budget);
+            ensure_output_headroom(gen_config, budget, model);
+            ThinkingChange::Raised { to: budget }
+        }
+        ThinkingPolicyAction::Off => {
+            let Some(gen_config) = request
+                .get_mut("generationConfig")
+                .and_then(|g| g.as_object_mut())
+            else {
+                return ThinkingChange::Unchanged;
+            };
+            if !gen_config.contains_key("thinkingConfig") {
+                return ThinkingChange::Unchanged;
+            }
+            if is_gemini(model) {
+                // Flash 系列可以关闭思考；Pro 系列只能降到最小预算
+                let budget = if model.to_lowercase().contains("flash") {
+                    0
+                } else {
+                    GEMINI_MIN_THINKING_BUDGET
+                };
+                gen_config.insert(
+                    "thinkingConfig".to_string(),
+                    json!({ "includeThoughts": false, "thinkingBudget": budget }),
+                );
+            } else {
+                gen_config.remove("thinkingConfig");
+                // 与 Claude 映射中关闭思考的处理一致：历史中的思考内容降级为普通文本
+                if let Some(contents) = request.get_mut("contents") {
+                    clean_thinking_fields_recursive(contents);
+                }
+            }
+            ThinkingChange::Disabled
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ThinkingPolicyStats {
+    /// 命中策略的请求数
+    pub matched_requests: u64,
+    pub capped: u64,
+    pub raised: u64,
+    pub disabled: u64,
+    /// 策略名称 -> 命中次数
+    pub by_policy: HashMap<String, u64>,
+}
+
+static STATS: Lazy<Mutex<ThinkingPolicyStats>> =
+    Lazy::new(|| Mutex::new(ThinkingPolicyStats::default()));
+
+pub fn stats() -> ThinkingPolicyStats {
+    STATS.lock().map(|s| s.clone()).unwrap_or_default()
+}
+
+fn record(policy: &str, change: &ThinkingChange) {
+    if let Ok(mut stats) = STATS.lock() {
+        stats.matched_requests += 1;
+        *stats.by_policy.entry(policy.to_string()).or_insert(0) += 1;
+        match change {
+            ThinkingChange::Capped { .. } => stats.capped += 1,
+            ThinkingChange::Raised { .. } => stats.raised += 1,
+            ThinkingChange::Disabled => stats.disabled += 1,
+            ThinkingChange::Unchanged => {}
+        }
+    }
+}
+
+/// 对 v1internal 请求体应用当前调用方命中的策略，返回策略名称
+pub fn apply_current(body: &mut Value, model: &str, trace_id: &str) -> Option<String> {
+    let policies = crate::proxy::config::get_thinking_policies();
+    if policies.is_empty() {
+        return None;
+    }
+    let caller = current_caller().unwrap_or_default();
+    let policy = select_policy(&policies, model, &caller)?;
+    let change = apply_policy(policy, body, model);
+    if change != ThinkingChange::Unchanged {
+        tracing::info!(
+            "[{}] Thinking policy '{}' applied to {}: {:?}",
+            trace_id,
+            policy.name,
+            model,
+            change
+        );
+    }
+    record(&policy.name, &change);
+    if let Ok(mut applied) = caller.applied.lock() {
+        *applied = Some(policy.name.clone());
+    }
+    Some(policy.name.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(name: &str, action: ThinkingPolicyAction, budget: Option<u32>) -> ThinkingPolicy {
+        ThinkingPolicy {
+            name: name.to_string(),
+            models: Vec::new(),
+            users: Vec::new(),
+            clients: Vec::new(),
+            background: None,
+            action,
+            budget,
+        }
+    }
+
+    fn body_with(thinking: Value) -> Value {
+        json!({
+            "request": {
+                "contents": [
+                    { "role": "user", "parts": [{ "text": "hi" }] },
+                    { "role": "model", "parts": [{ "text": "plan", "thought": true, "thoughtSignature": "sig" }, { "text": "ok" }] }
+                ],
+                "generationConfig": { "maxOutputTokens": 16000, "thinkingConfig": thinking }
+            }
+        })
+    }
+
+    #[test]
+    fn test_select_policy_by_model_and_caller() {
+        let mut bulk = policy("opus-bulk", ThinkingPolicyAction::Cap, Some(8192));
+        bulk.models = vec!["claude-opus-*".to_string()];
+        bulk.users = vec!["batch-*".to_string()];
+        let mut background = policy("background", ThinkingPolicyAction::Off, None);
+        background.background = Some(true);
+        let mut interactive = policy("interactive", ThinkingPolicyAction::Max, None);
+        interactive.clients = vec!["opencode".to_string()];
+        let policies = vec![bulk, background, interactive];
+
+        let batch = CallerFacts::new(Some("batch-etl".to_string()), None, false);
+        let pick = |model: &str, caller: &CallerFacts| {
+            select_policy(&policies, model, caller).map(|p| p.name.clone())
+        };
+        assert_eq!(
+            pick("claude-opus-4-6-thinking", &batch).as_deref(),
+            Some("opus-bulk")
+        );
+        assert_eq!(pick("gemini-2.5-pro", &batch), None);
+
+        let title = CallerFacts::new(
+            Some("alice".to_string()),
+            Some("OpenCode".to_string()),
+            true,
+        );
+        assert_eq!(
+            pick("gemini-2.5-pro", &title).as_deref(),
+            Some("background")
+        );
+        let chat = CallerFacts::new(
+            Some("alice".to_string()),
+            Some("OpenCode".to_string()),
+            false,
+        );
+        assert_eq!(
+            pick("gemini-2.5-pro", &chat).as_deref(),
+            Some("interactive")
+        );
+    }
+
+    #[test]
+    fn test_cap_and_max() {
+        let cap = policy("cap", ThinkingPolicyAction::Cap, Some(8192));
+        let mut body = body_with(json!({ "includeThoughts": true, "thinkingBudget": 32000 }));
+        assert_eq!(
+            apply_policy(&cap, &mut body, "claude-opus-4-6-thinking"),
+            ThinkingChange::Capped {
+                from: Some(32000),
+                to: 8192
+            }
+        );
+        assert_eq!(
+            body["request"]["generationConfig"]["thinkingConfig"]["thinkingBudget"],
+            8192
+        );
+        assert_eq!(
+            apply_policy(&cap, &mut body, "claude-opus-4-6-thinking"),
+            ThinkingChange::Unchanged
+        );
+
+        let mut body = body_with(json!({ "includeThoughts": true, "thinkingLevel": "high" }));
+        apply_policy(&cap, &mut body, "claude-opus-4-6-thinking");
+        let thinking = &body["request"]["generationConfig"]["thinkingConfig"];
+        assert!(thinking.get("thinkingLevel").is_none());
+        assert_eq!(thinking["thinkingBudget"], 8192);
+
+        let max = policy("max", ThinkingPolicyAction::Max, None);
+        let mut body = body_with(json!({ "includeThoughts": true, "thinkingBudget": 1024 }));
+        let expected = crate::proxy::model_specs::get_thinking_budget("gemini-2.5-pro", None);
+        assert_eq!(
+            apply_policy(&max, &mut body, "gemini-2.5-pro"),
+            ThinkingChange::Raised { to: expected }
+        );
+        let gen_config = &body["request"]["generationConfig"];
+        assert_eq!(gen_config["thinkingConfig"]["thinkingBudget"], expected);
+        assert!(gen_config["maxOutputTokens"].as_u64().unwrap() > expected);
+    }
+
+    #[test]
+    fn test_off_by_model_family() {
+        let off = policy("off", ThinkingPolicyAction::Off, None);
+
+        let mut body = body_with(json!({ "includeThoughts": true, "thinkingBudget": 24576 }));
+        assert_eq!(
+            apply_policy(&off, &mut body, "claude-opus-4-6-thinking"),
+            ThinkingChange::Disabled
+        );
+        let request = &body["request"];
+        assert!(request["generationConfig"].get("thinkingConfig").is_none());
+        let part = &request["contents"][1]["parts"][0];
+        assert!(part.get("thought").is_none() && part.get("thoughtSignature").is_none());
+
+        let mut body = body_with(json!({ "includeThoughts": true, "thinkingBudget": 24576 }));
+        apply_policy(&off, &mut body, "gemini-2.5-flash");
+        assert_eq!(
+            body["request"]["generationConfig"]["thinkingConfig"],
+            json!({ "includeThoughts": false, "thinkingBudget": 0 })
+        );
+
+        let mut body = body_with(json!({ "includeThoughts": true, "thinkingBudget": 24576 }));
+        apply_policy(&off, &mut body, "gemini-2.5-pro");
+        assert_eq!(
+            body["request"]["generationConfig"]["thinkingConfig"]["thinkingBudget"],
+            GEMINI_MIN_THINKING_BUDGET
+        );
+    }
+}
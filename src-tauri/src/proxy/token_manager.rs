@@ -52,6 +52,8 @@ pub struct TokenManager {
     /// 支持优雅关闭时主动 abort 后台任务
     auto_cleanup_handle: Arc<tokio::sync::Mutex<Option<tokio::task::JoinHandle<()>>>>,
     cancel_token: CancellationToken,
+    /// 从各账号配额数据中汇总的模型元数据 (按原始模型名索引)
+    model_metadata: Arc<DashMap<String, crate::models::quota::ModelQuota>>,
 }
 
 impl TokenManager {
@@ -72,6 +74,7 @@ impl TokenManager {
             )),
             auto_cleanup_handle: Arc::new(tokio::sync::Mutex::new(None)),
             cancel_token: CancellationToken::new(),
+            model_metadata: Arc::new(DashMap::new()),
         }
     }
 
@@ -434,13 +437,14 @@ impl TokenManager {
         let token_obj = account["token"].as_object()
             .ok_or("缺少 token 字段")?;
 
-        let access_token = token_obj["access_token"].as_str()
-            .ok_or("缺少 access_token")?
-            .to_string();
+        // 账号文件可能启用了静态加密，读取时透明解密
+        let access_token = crate::modules::account_crypto::open_field(
+            token_obj["access_token"].as_str().ok_or("缺少 access_token")?,
+        )?;
 
-        let refresh_token = token_obj["refresh_token"].as_str()
-            .ok_or("缺少 refresh_token")?
-            .to_string();
+        let refresh_token = crate::modules::account_crypto::open_field(
+            token_obj["refresh_token"].as_str().ok_or("缺少 refresh_token")?,
+        )?;
 
         let expires_in = token_obj["expires_in"].as_i64()
             .ok_or("缺少 expires_in")?;
@@ -504,6 +508,10 @@ impl TokenManager {
                 ) {
                     model_limits.insert(name.to_string(), limit);
                 }
+                // [NEW] 汇总模型元数据 (上下文/输出限额、能力)，供模型列表接口使用
+                if let Ok(info) = serde_json::from_value::<crate::models::quota::ModelQuota>(model.clone()) {
+                    self.merge_model_metadata(info);
+                }
             }
         }
 
@@ -1121,6 +1129,19 @@ impl TokenManager {
             return Err("Token pool is empty".to_string());
         }
 
+        // 路由规则限定的账号分组 (成员为账号 ID 或邮箱)
+        if let Some(members) = crate::proxy::routing::current_account_group() {
+            tokens_snapshot.retain(|t| {
+                members
+                    .iter()
+                    .any(|m| m == &t.account_id || m.eq_ignore_ascii_case(&t.email))
+            });
+            total = tokens_snapshot.len();
+            if total == 0 {
+                return Err("No accounts available in the routed account group".to_string());
+            }
+        }
+
         // [NEW] 1. 动态能力过滤 (Capability Filter)
         
         // 定义常量
@@ -1798,7 +1819,9 @@ impl TokenManager {
 
         let now = chrono::Utc::now().timestamp();
 
-        content["token"]["access_token"] = serde_json::Value::String(token_response.access_token.clone());
+        content["token"]["access_token"] = serde_json::Value::String(
+            crate::modules::account_crypto::seal_field(&token_response.access_token)?,
+        );
         content["token"]["expires_in"] = serde_json::Value::Number(token_response.expires_in.into());
         content["token"]["expiry_timestamp"] = serde_json::Value::Number((now + token_response.expires_in).into());
 
@@ -2500,6 +2523,30 @@ impl TokenManager {
         all_models
     }
 
+    /// 合并一条模型元数据；已有字段保持不变，仅补全缺失字段
+    fn merge_model_metadata(&self, info: crate::models::quota::ModelQuota) {
+        let mut entry = self
+            .model_metadata
+            .entry(info.name.clone())
+            .or_insert_with(|| info.clone());
+        let existing = entry.value_mut();
+        existing.display_name = existing.display_name.take().or(info.display_name);
+        existing.supports_images = existing.supports_images.or(info.supports_images);
+        existing.supports_thinking = existing.supports_thinking.or(info.supports_thinking);
+        existing.thinking_budget = existing.thinking_budget.or(info.thinking_budget);
+        existing.recommended = existing.recommended.or(info.recommended);
+        existing.max_tokens = existing.max_tokens.or(info.max_tokens);
+        existing.max_output_tokens = existing.max_output_tokens.or(info.max_output_tokens);
+        if existing.supported_mime_types.is_none() {
+            existing.supported_mime_types = info.supported_mime_types;
+        }
+    }
+
+    /// 获取账号配额数据中汇总的模型元数据
+    pub fn get_model_metadata(&self, model: &str) -> Option<crate::models::quota::ModelQuota> {
+        self.model_metadata.get(model).map(|m| m.value().clone())
+    }
+
     /// [NEW] 从指定账号的动态额度数据中获取特定模型的 max_output_tokens
     ///
     /// # 返回
@@ -8,6 +8,9 @@ use sha2::Digest;
 
 const FIXED_NONCE: &[u8; 12] = b"antigravsalt";
 const ENCRYPTED_PREFIX: &str = "ag_enc_";
+/// 口令派生密钥加密的前缀 (随机 nonce 与密文一同编码)
+pub const KEYED_PREFIX: &str = "ag_key_v1:";
+const NONCE_LEN: usize = 12;
 
 /// 生成加密密钥 (基于设备 ID)
 fn get_encryption_key() -> [u8; 32] {
@@ -111,6 +114,69 @@ pub fn decrypt_string(encrypted: &str) -> Result<String, String> {
     }
 }
 
+/// 基于设备 ID 的本机密钥 (用于加密仅在本机使用的缓存文件)
+pub fn device_key() -> [u8; 32] {
+    get_encryption_key()
+}
+
+/// 使用 PBKDF2-HMAC-SHA256 从口令派生 256 位密钥
+pub fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<sha2::Sha256>(passphrase.as_bytes(), salt, iterations, &mut key);
+    key
+}
+
+/// 生成随机盐值
+pub fn random_salt() -> [u8; 16] {
+    use rand::RngCore;
+    let mut salt = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut salt);
+    salt
+}
+
+/// 使用指定密钥加密 (每次随机 nonce)，输出 `ag_key_v1:` + Base64(nonce || ciphertext)
+pub fn encrypt_with_key(key: &[u8; 32], plaintext: &str) -> Result<String, String> {
+    use rand::RngCore;
+    let cipher = Aes256Gcm::new(&(*key).into());
+    let mut nonce_bytes = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce_bytes);
+
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce_bytes), plaintext.as_bytes())
+        .map_err(|e| format!("Encryption failed: {}", e))?;
+
+    let mut payload = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+    payload.extend_from_slice(&nonce_bytes);
+    payload.extend_from_slice(&ciphertext);
+    Ok(format!("{}{}", KEYED_PREFIX, general_purpose::STANDARD.encode(payload)))
+}
+
+/// 解密 `encrypt_with_key` 的输出
+pub fn decrypt_with_key(key: &[u8; 32], encrypted: &str) -> Result<String, String> {
+    let encoded = encrypted
+        .strip_prefix(KEYED_PREFIX)
+        .ok_or_else(|| "Missing keyed ciphertext prefix".to_string())?;
+    let payload = general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|e| format!("Base64 decode failed: {}", e))?;
+    if payload.len() <= NONCE_LEN {
+        return Err("Ciphertext too short".to_string());
+    }
+
+    let (nonce_bytes, ciphertext) = payload.split_at(NONCE_LEN);
+    let cipher = Aes256Gcm::new(&(*key).into());
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(nonce_bytes), ciphertext)
+        .map_err(|e| format!("Decryption failed: {}", e))?;
+
+    String::from_utf8(plaintext).map_err(|e| format!("UTF-8 conversion failed: {}", e))
+}
+
+/// 判断字符串是否为口令派生密钥加密的密文
+pub fn is_keyed_ciphertext(value: &str) -> bool {
+    value.starts_with(KEYED_PREFIX)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -143,4 +209,19 @@ mod tests {
         let decrypted = decrypt_string(&legacy_encrypted).unwrap();
         assert_eq!(password, decrypted);
     }
+
+    #[test]
+    fn test_keyed_encrypt_decrypt_cycle() {
+        let salt = random_salt();
+        let key = derive_key("correct horse", &salt, 1_000);
+        let encrypted = encrypt_with_key(&key, "1//refresh-token").unwrap();
+
+        assert!(is_keyed_ciphertext(&encrypted));
+        // 随机 nonce：同一明文两次加密结果不同
+        assert_ne!(encrypted, encrypt_with_key(&key, "1//refresh-token").unwrap());
+        assert_eq!(decrypt_with_key(&key, &encrypted).unwrap(), "1//refresh-token");
+
+        let wrong_key = derive_key("wrong horse", &salt, 1_000);
+        assert!(decrypt_with_key(&wrong_key, &encrypted).is_err());
+    }
 }
@@ -25,6 +25,7 @@ export interface ProxyConfig {
     global_system_prompt?: GlobalSystemPromptConfig;
     image_thinking_mode?: 'enabled' | 'disabled'; // [NEW] 图像思维模式开关
     proxy_pool?: ProxyPoolConfig;
+    script_hooks?: ScriptHooksConfig;
 }
 
 // ============================================================================
@@ -45,6 +46,27 @@ export interface ThinkingBudgetConfig {
     custom_value: number;
     /** 思考强度 (仅在 mode=adaptive 时生效) */
     effort?: ThinkingEffort;
+    /** 按模型家族 / 调用方的思考策略 (仅配置文件维护，界面保存时原样保留) */
+    policies?: ThinkingPolicy[];
+}
+
+/** 思考策略动作 */
+export type ThinkingPolicyAction = 'cap' | 'max' | 'off';
+
+/** 单个思考策略 */
+export interface ThinkingPolicy {
+    name: string;
+    /** 映射后的模型 (支持 * 通配符) */
+    models?: string[];
+    /** 用户令牌用户名 (支持 * 通配符) */
+    users?: string[];
+    /** 客户端适配器名称 */
+    clients?: string[];
+    /** true 仅匹配后台任务，false 排除后台任务 */
+    background?: boolean;
+    action: ThinkingPolicyAction;
+    /** 预算上限 (仅 action=cap) */
+    budget?: number;
 }
 
 // ============================================================================
@@ -57,6 +79,56 @@ export interface GlobalSystemPromptConfig {
     enabled: boolean;
     /** 提示词内容 */
     content: string;
+    /** 按条件注入的提示词片段 (仅配置文件维护，界面保存时原样保留) */
+    prompts?: SystemPromptRule[];
+}
+
+/** 单个提示词片段 */
+export interface SystemPromptRule {
+    name: string;
+    enabled?: boolean;
+    /** 支持 {{date}} {{datetime}} {{username}} {{model}} {{client}} {{protocol}} */
+    content: string;
+    position?: 'prepend' | 'append';
+    protocols?: string[];
+    models?: string[];
+    users?: string[];
+    clients?: string[];
+    exclude_users?: string[];
+    exclude_clients?: string[];
+}
+
+// ============================================================================
+// 脚本钩子配置 (Rhai)
+// ============================================================================
+
+/** 钩子调用阶段 */
+export type ScriptHookStage = 'request' | 'upstream_request' | 'sse_event';
+
+/** 脚本钩子配置 (仅配置文件维护，界面保存时原样保留) */
+export interface ScriptHooksConfig {
+    enabled: boolean;
+    /** 单个钩子的默认执行时限 (毫秒) */
+    timeout_ms?: number;
+    max_operations?: number;
+    hooks?: ScriptHook[];
+}
+
+/** 单个脚本钩子 */
+export interface ScriptHook {
+    name: string;
+    enabled?: boolean;
+    stage: ScriptHookStage;
+    /** 内联脚本，与 file 二选一 */
+    script?: string;
+    /** 脚本文件 (相对路径基于数据目录) */
+    file?: string;
+    protocols?: string[];
+    models?: string[];
+    users?: string[];
+    clients?: string[];
+    timeout_ms?: number;
+    on_error?: 'ignore' | 'reject';
 }
 
 export interface DebugLoggingConfig {